@@ -71,6 +71,21 @@ impl AIProcessor {
         }
     }
 
+    /// Use a different Ollama embedding model, e.g. a multilingual one such as "bge-m3"
+    pub fn with_embedding_model(mut self, embedding_model: String) -> Self {
+        self.embedding_model = embedding_model;
+        self
+    }
+
+    /// The Ollama model content is analyzed with
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
     pub async fn analyze_content(&self, content: &ExtractedContent) -> Result<AIAnalysis> {
         // Create analysis prompt based on content type
         let prompt = self.create_analysis_prompt(content);
@@ -94,6 +109,9 @@ impl AIProcessor {
             content.text.clone()
         };
 
+        let language = content.metadata.language.as_deref().unwrap_or("unknown");
+        let language_hint = Self::language_hint(content);
+
         match content.file_type.as_str() {
             "pdf" | "document" => {
                 format!(
@@ -104,7 +122,7 @@ impl AIProcessor {
 4. Key entities mentioned (people, organizations, locations)
 5. Main topics discussed
 6. Sentiment (positive/negative/neutral as a number from -1 to 1)
-
+{}
 Content:
 {}
 
@@ -116,10 +134,10 @@ Respond in JSON format:
   "sentiment": 0.0,
   "key_entities": ["entity1", "entity2", ...],
   "topics": ["topic1", "topic2", ...],
-  "language": "english",
+  "language": "{}",
   "confidence": 0.85
 }}"#,
-                    content_preview
+                    language_hint, content_preview, language
                 )
             }
             "code" => {
@@ -181,7 +199,7 @@ Respond in JSON format:
 3. Content category
 4. Key elements or entities
 5. Main topics
-
+{}
 Content:
 {}
 
@@ -193,15 +211,29 @@ Respond in JSON format:
   "sentiment": 0.0,
   "key_entities": ["entity1", "entity2", ...],
   "topics": ["topic1", "topic2", ...],
-  "language": "unknown",
+  "language": "{}",
   "confidence": 0.75
 }}"#,
-                    content_preview
+                    language_hint, content_preview, language
                 )
             }
         }
     }
 
+    /// Ask for the summary in the document's own language, with English tags alongside so
+    /// searches in either language find it. Empty for English or undetected text.
+    fn language_hint(content: &ExtractedContent) -> String {
+        let Some(name) = content.metadata.language.as_deref()
+            .filter(|code| *code != "en")
+            .and_then(crate::language::language_name)
+        else {
+            return String::new();
+        };
+        format!(
+            "\nThe content is written in {name}. Write the summary in {name}, and give the tags in {name} followed by their English translations.\n"
+        )
+    }
+
     async fn query_ollama(&self, prompt: &str) -> Result<String> {
         let request = OllamaRequest {
             model: self.model.clone(),
@@ -230,6 +262,11 @@ Respond in JSON format:
         Ok(ollama_response.response)
     }
 
+    /// Free-form completion from the configured model
+    pub async fn generate(&self, prompt: &str) -> Result<String> {
+        self.query_ollama(prompt).await
+    }
+
     pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
         // Truncate text if too long for embedding
         let embedding_text = if text.len() > 8000 {
@@ -311,9 +348,12 @@ Respond in JSON format:
                 })
                 .unwrap_or_default();
 
-            let language = parsed.get("language")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
+            // The detected ISO code wins over whatever name the model gives the language
+            let language = content.metadata.language.clone().or_else(|| {
+                parsed.get("language")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+            });
 
             let confidence = parsed.get("confidence")
                 .and_then(|v| v.as_f64())
@@ -386,7 +426,7 @@ Respond in JSON format:
             sentiment: Some(0.0),
             key_entities: Vec::new(),
             topics: Vec::new(),
-            language: Some("unknown".to_string()),
+            language: content.metadata.language.clone().or_else(|| Some("unknown".to_string())),
             confidence: 0.3,
             embedding,
             metadata,
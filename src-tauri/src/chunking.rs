@@ -0,0 +1,140 @@
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::ai_processor::AIProcessor;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkingConfig {
+    /// Preferred chunk length in characters
+    pub chunk_chars: usize,
+    /// Characters shared by neighbouring chunks so sentences on a boundary stay intact
+    pub overlap_chars: usize,
+    /// Embedding calls per file are bounded; longer documents get longer chunks instead
+    pub max_chunks: usize,
+    /// Longest chunk the embedding model is given
+    pub max_chunk_chars: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_chars: 1000,
+            overlap_chars: 200,
+            max_chunks: 128,
+            max_chunk_chars: 6000,
+        }
+    }
+}
+
+/// A slice of a document's extracted text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextChunk {
+    pub index: usize,
+    /// Byte offsets into the text the chunk was cut from
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Split `text` into overlapping chunks, preferring to end chunks on whitespace
+pub fn chunk_text(text: &str, config: &ChunkingConfig) -> Vec<TextChunk> {
+    let chars: Vec<char> = text.chars().collect();
+    let boundaries: Vec<usize> = text.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let char_count = chars.len();
+    if text.trim().is_empty() || config.max_chunks == 0 {
+        return Vec::new();
+    }
+
+    // Headroom for overlap and whitespace snapping so max_chunks still reaches the end
+    let needed = char_count.div_ceil(config.max_chunks) * 3 / 2 + 2 * config.overlap_chars;
+    let size = config.chunk_chars.max(needed).min(config.max_chunk_chars).max(1);
+    let overlap = config.overlap_chars.min(size / 2);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < char_count && chunks.len() < config.max_chunks {
+        let mut end = (start + size).min(char_count);
+        if end < char_count {
+            // Only look back over the last fifth so chunks keep most of their length
+            let floor = end - size / 5;
+            if let Some(space) = (floor.max(start + 1)..end).rev().find(|&i| chars[i].is_whitespace()) {
+                end = space;
+            }
+        }
+
+        let slice = &text[boundaries[start]..boundaries[end]];
+        if !slice.trim().is_empty() {
+            chunks.push(TextChunk {
+                index: chunks.len(),
+                start: boundaries[start],
+                end: boundaries[end],
+                text: slice.to_string(),
+            });
+        }
+
+        if end >= char_count {
+            break;
+        }
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+
+    chunks
+}
+
+/// Chunk `text` and embed every chunk with the configured embedding model
+pub async fn embed_chunks(ai_processor: &AIProcessor, text: &str, config: &ChunkingConfig) -> Result<Vec<(TextChunk, Vec<f32>)>> {
+    let chunks = chunk_text(text, config);
+    let mut embedded = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let vector = ai_processor.generate_embedding(&chunk.text).await?;
+        embedded.push((chunk, vector));
+    }
+    Ok(embedded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_overlap_and_cover_text() {
+        let text = "word ".repeat(600);
+        let config = ChunkingConfig { chunk_chars: 500, overlap_chars: 100, ..Default::default() };
+        let chunks = chunk_text(&text, &config);
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].start, 0);
+        assert_eq!(chunks.last().unwrap().end, text.len());
+        for pair in chunks.windows(2) {
+            assert!(pair[1].start < pair[0].end, "chunks should overlap");
+        }
+        for chunk in &chunks {
+            assert_eq!(&text[chunk.start..chunk.end], chunk.text);
+            assert!(chunk.text.chars().count() <= 500);
+        }
+    }
+
+    #[test]
+    fn test_long_documents_grow_chunks_instead_of_count() {
+        let text = "abcdefghij ".repeat(2000);
+        let config = ChunkingConfig { chunk_chars: 100, overlap_chars: 10, max_chunks: 8, max_chunk_chars: 10_000 };
+        let chunks = chunk_text(&text, &config);
+
+        assert!(chunks.len() <= 8);
+        assert_eq!(chunks.last().unwrap().end, text.len());
+    }
+
+    #[test]
+    fn test_offsets_are_byte_offsets() {
+        let text = "äöü ".repeat(100);
+        let config = ChunkingConfig { chunk_chars: 50, overlap_chars: 10, ..Default::default() };
+        for chunk in chunk_text(&text, &config) {
+            assert!(text.is_char_boundary(chunk.start) && text.is_char_boundary(chunk.end));
+            assert_eq!(&text[chunk.start..chunk.end], chunk.text);
+        }
+        assert!(chunk_text("   ", &config).is_empty());
+    }
+}
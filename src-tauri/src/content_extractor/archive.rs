@@ -0,0 +1,109 @@
+use std::io::{Cursor, Read, Seek};
+
+use anyhow::Result;
+use serde::Serialize;
+use zip::ZipArchive;
+
+/// Entries listed per archive, nested ones included, so an archive of a whole disk stays readable
+const MAX_ENTRIES: usize = 1_000;
+
+/// A zip inside a zip is read into memory to be opened, so larger ones are only listed
+const MAX_NESTED_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Extensions of the archives inside an archive that are opened in turn
+const NESTED_EXTENSIONS: &[&str] = &[".zip", ".jar", ".epub"];
+
+/// The files in a zip archive, with those of the zips inside it as `outer.zip/inner.txt`
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ArchiveListing {
+    pub entries: Vec<String>,
+    /// Files past `MAX_ENTRIES`, left out
+    pub omitted: usize,
+    /// Uncompressed size of the listed files
+    pub total_bytes: u64,
+}
+
+/// List the archive, opening nested archives until `max_depth` archives deep; a depth of 1
+/// lists the archive's own files only
+pub fn list_zip(reader: impl Read + Seek, max_depth: u32) -> Result<ArchiveListing> {
+    let mut listing = ArchiveListing::default();
+    list_into(&mut ZipArchive::new(reader)?, "", max_depth, &mut listing)?;
+    Ok(listing)
+}
+
+fn list_into<R: Read + Seek>(archive: &mut ZipArchive<R>, prefix: &str, depth: u32, listing: &mut ArchiveListing) -> Result<()> {
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if entry.is_dir() {
+            continue;
+        }
+        if listing.entries.len() >= MAX_ENTRIES {
+            listing.omitted += 1;
+            continue;
+        }
+        let name = format!("{}{}", prefix, entry.name());
+        listing.total_bytes += entry.size();
+        listing.entries.push(name.clone());
+
+        let lowercase = name.to_lowercase();
+        let nested = NESTED_EXTENSIONS.iter().any(|extension| lowercase.ends_with(extension));
+        if nested && depth > 1 && entry.size() <= MAX_NESTED_BYTES {
+            // The size is the archive's own claim, so neither the buffer nor the read trusts it
+            let mut bytes = Vec::with_capacity(entry.size().min(MAX_NESTED_BYTES) as usize);
+            (&mut entry).take(MAX_NESTED_BYTES).read_to_end(&mut bytes)?;
+            // A damaged inner archive is still listed by name
+            if let Ok(mut inner) = ZipArchive::new(Cursor::new(bytes)) {
+                list_into(&mut inner, &format!("{}/", name), depth - 1, listing)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl ArchiveListing {
+    /// A line per file, so an archive is found by the names of what it holds
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Archive: {} files, {} bytes uncompressed\n", self.entries.len() + self.omitted, self.total_bytes);
+        for entry in &self.entries {
+            text.push_str(&format!("  {}\n", entry));
+        }
+        if self.omitted > 0 {
+            text.push_str(&format!("... and {} more\n", self.omitted));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_list_nested_zip() {
+        let inner = zip(&[("invoice.pdf", b"%PDF"), ("notes.txt", b"hello")]);
+        let outer = zip(&[("readme.md", b"# Backup"), ("2023/archive.zip", &inner)]);
+
+        let listing = list_zip(Cursor::new(&outer), 2).unwrap();
+        assert_eq!(listing.entries, vec!["readme.md", "2023/archive.zip", "2023/archive.zip/invoice.pdf", "2023/archive.zip/notes.txt"]);
+        assert!(listing.to_text().starts_with("Archive: 4 files"));
+
+        let shallow = list_zip(Cursor::new(&outer), 1).unwrap();
+        assert_eq!(shallow.entries, vec!["readme.md", "2023/archive.zip"]);
+    }
+
+    #[test]
+    fn test_not_a_zip() {
+        assert!(list_zip(Cursor::new(b"plain text".to_vec()), 1).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::File;
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Tag};
+use symphonia::core::probe::Hint;
+
+/// Tags and length of a music file, from ID3, Vorbis comments, MP4 atoms or RIFF INFO chunks
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub composer: Option<String>,
+    pub genre: Option<String>,
+    /// The release date or year, as tagged
+    pub date: Option<String>,
+    pub track: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<usize>,
+}
+
+impl AudioTags {
+    /// Probe the container for its tags and the default track's length, without decoding any audio
+    pub fn read(file: File, extension: &str) -> Result<Self> {
+        let source = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension(extension);
+        let mut probed = symphonia::default::get_probe()
+            .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| anyhow!("Unrecognized audio file: {}", e))?;
+
+        let mut tags = Self::default();
+        // ID3v2 tags ahead of an MP3 stream are found while probing, the container's own afterwards
+        if let Some(revision) = probed.metadata.get().as_ref().and_then(|metadata| metadata.current()) {
+            tags.apply(revision);
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            tags.apply(revision);
+        }
+
+        if let Some(track) = probed.format.default_track() {
+            let parameters = &track.codec_params;
+            tags.sample_rate = parameters.sample_rate;
+            tags.channels = parameters.channels.map(|channels| channels.count());
+            tags.duration_seconds = match (parameters.time_base, parameters.n_frames, parameters.sample_rate) {
+                (Some(time_base), Some(frames), _) => {
+                    let time = time_base.calc_time(frames);
+                    Some(time.seconds as f64 + time.frac)
+                }
+                (None, Some(frames), Some(rate)) if rate > 0 => Some(frames as f64 / rate as f64),
+                _ => None,
+            };
+        }
+        Ok(tags)
+    }
+
+    /// Take the revision's values, the first tag of each kind winning
+    fn apply(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let slot = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::Album) => &mut self.album,
+                Some(StandardTagKey::AlbumArtist) => &mut self.album_artist,
+                Some(StandardTagKey::Composer) => &mut self.composer,
+                Some(StandardTagKey::Genre) => &mut self.genre,
+                Some(StandardTagKey::Date | StandardTagKey::ReleaseDate | StandardTagKey::OriginalDate) => &mut self.date,
+                Some(StandardTagKey::TrackNumber) => &mut self.track,
+                _ => continue,
+            };
+            if slot.is_none() {
+                *slot = value(tag);
+            }
+        }
+    }
+
+    /// Lines for the extracted text, so music is found by its tags rather than its file name
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let lines = [
+            ("Title", &self.title),
+            ("Artist", &self.artist),
+            ("Album", &self.album),
+            ("Album artist", &self.album_artist),
+            ("Composer", &self.composer),
+            ("Genre", &self.genre),
+            ("Date", &self.date),
+            ("Track", &self.track),
+        ];
+        for (label, value) in lines {
+            if let Some(value) = value {
+                text.push_str(&format!("{}: {}\n", label, value));
+            }
+        }
+        if let Some(seconds) = self.duration_seconds {
+            let seconds = seconds.round() as u64;
+            text.push_str(&format!("Duration: {}:{:02}\n", seconds / 60, seconds % 60));
+        }
+        text
+    }
+}
+
+fn value(tag: &Tag) -> Option<String> {
+    let value = tag.value.to_string().trim_matches(char::from(0)).trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Seek, Write};
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_read_wav_tags() {
+        // Two and a half seconds of 8 kHz mono 16-bit silence, tagged in a RIFF INFO list
+        let format = [&1u16.to_le_bytes()[..], &1u16.to_le_bytes(), &8000u32.to_le_bytes(), &16000u32.to_le_bytes(), &2u16.to_le_bytes(), &16u16.to_le_bytes()].concat();
+        let info = [&b"INFO"[..], &chunk(b"INAM", b"Test tone\0"), &chunk(b"IART", b"Ada\0"), &chunk(b"IGNR", b"Ambient\0")].concat();
+        let body = [&b"WAVE"[..], &chunk(b"fmt ", &format), &chunk(b"LIST", &info), &chunk(b"data", &[0; 40_000])].concat();
+
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(&chunk(b"RIFF", &body)).unwrap();
+        file.rewind().unwrap();
+
+        let tags = AudioTags::read(file, "wav").unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Test tone"));
+        assert_eq!(tags.artist.as_deref(), Some("Ada"));
+        assert_eq!(tags.sample_rate, Some(8000));
+        assert_eq!(tags.channels, Some(1));
+        assert_eq!(tags.to_text(), "Title: Test tone\nArtist: Ada\nGenre: Ambient\nDuration: 0:03\n");
+    }
+
+    #[test]
+    fn test_unrecognized_audio() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"not audio at all").unwrap();
+        file.rewind().unwrap();
+        assert!(AudioTags::read(file, "mp3").is_err());
+    }
+}
@@ -0,0 +1,412 @@
+use serde::Serialize;
+use tree_sitter::{Language, Node, Parser};
+
+/// Sources larger than this are indexed as plain text without an outline
+pub const MAX_OUTLINE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Symbols kept per file; generated code can define thousands
+const MAX_SYMBOLS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    /// Classes, structs, enums, traits, interfaces and type aliases
+    Type,
+    Module,
+}
+
+impl SymbolKind {
+    fn label(self) -> &'static str {
+        match self {
+            SymbolKind::Function => "fn",
+            SymbolKind::Type => "type",
+            SymbolKind::Module => "mod",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-based, as editors show it
+    pub line: usize,
+    /// The first paragraph of the doc comment or docstring
+    pub doc: Option<String>,
+}
+
+/// The definitions and imports of a source file
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeOutline {
+    pub language: &'static str,
+    pub symbols: Vec<Symbol>,
+    pub imports: Vec<String>,
+}
+
+/// How symbols are found in one language's syntax tree
+struct Grammar {
+    name: &'static str,
+    language: fn() -> Language,
+    definitions: &'static [(&'static str, SymbolKind)],
+    imports: &'static [&'static str],
+    /// Nodes a definition sits in, such as `export` statements and decorators, whose comments
+    /// are the definition's own
+    wrappers: &'static [&'static str],
+}
+
+const COMMENTS: &[&str] = &["comment", "line_comment", "block_comment"];
+
+const RUST: Grammar = Grammar {
+    name: "rust",
+    language: || tree_sitter_rust::LANGUAGE.into(),
+    definitions: &[
+        ("function_item", SymbolKind::Function),
+        ("function_signature_item", SymbolKind::Function),
+        ("struct_item", SymbolKind::Type),
+        ("enum_item", SymbolKind::Type),
+        ("union_item", SymbolKind::Type),
+        ("trait_item", SymbolKind::Type),
+        ("type_item", SymbolKind::Type),
+        ("mod_item", SymbolKind::Module),
+        ("macro_definition", SymbolKind::Function),
+    ],
+    imports: &["use_declaration", "extern_crate_declaration"],
+    wrappers: &[],
+};
+
+const PYTHON: Grammar = Grammar {
+    name: "python",
+    language: || tree_sitter_python::LANGUAGE.into(),
+    definitions: &[
+        ("function_definition", SymbolKind::Function),
+        ("class_definition", SymbolKind::Type),
+    ],
+    imports: &["import_statement", "import_from_statement"],
+    wrappers: &["decorated_definition"],
+};
+
+const JAVASCRIPT_DEFINITIONS: &[(&str, SymbolKind)] = &[
+    ("function_declaration", SymbolKind::Function),
+    ("generator_function_declaration", SymbolKind::Function),
+    ("method_definition", SymbolKind::Function),
+    ("class_declaration", SymbolKind::Type),
+];
+
+const TYPESCRIPT_DEFINITIONS: &[(&str, SymbolKind)] = &[
+    ("function_declaration", SymbolKind::Function),
+    ("generator_function_declaration", SymbolKind::Function),
+    ("function_signature", SymbolKind::Function),
+    ("method_definition", SymbolKind::Function),
+    ("method_signature", SymbolKind::Function),
+    ("class_declaration", SymbolKind::Type),
+    ("abstract_class_declaration", SymbolKind::Type),
+    ("interface_declaration", SymbolKind::Type),
+    ("type_alias_declaration", SymbolKind::Type),
+    ("enum_declaration", SymbolKind::Type),
+    ("internal_module", SymbolKind::Module),
+];
+
+const JAVASCRIPT: Grammar = Grammar {
+    name: "javascript",
+    language: || tree_sitter_javascript::LANGUAGE.into(),
+    definitions: JAVASCRIPT_DEFINITIONS,
+    imports: &["import_statement"],
+    wrappers: &["export_statement"],
+};
+
+const TYPESCRIPT: Grammar = Grammar {
+    name: "typescript",
+    language: || tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+    definitions: TYPESCRIPT_DEFINITIONS,
+    imports: &["import_statement"],
+    wrappers: &["export_statement"],
+};
+
+const TSX: Grammar = Grammar {
+    language: || tree_sitter_typescript::LANGUAGE_TSX.into(),
+    ..TYPESCRIPT
+};
+
+const GO: Grammar = Grammar {
+    name: "go",
+    language: || tree_sitter_go::LANGUAGE.into(),
+    definitions: &[
+        ("function_declaration", SymbolKind::Function),
+        ("method_declaration", SymbolKind::Function),
+        ("type_spec", SymbolKind::Type),
+    ],
+    imports: &["import_spec"],
+    wrappers: &["type_declaration"],
+};
+
+const JAVA: Grammar = Grammar {
+    name: "java",
+    language: || tree_sitter_java::LANGUAGE.into(),
+    definitions: &[
+        ("method_declaration", SymbolKind::Function),
+        ("constructor_declaration", SymbolKind::Function),
+        ("class_declaration", SymbolKind::Type),
+        ("interface_declaration", SymbolKind::Type),
+        ("enum_declaration", SymbolKind::Type),
+        ("record_declaration", SymbolKind::Type),
+    ],
+    imports: &["import_declaration"],
+    wrappers: &[],
+};
+
+const C: Grammar = Grammar {
+    name: "c",
+    language: || tree_sitter_c::LANGUAGE.into(),
+    definitions: &[
+        ("function_definition", SymbolKind::Function),
+        ("struct_specifier", SymbolKind::Type),
+        ("union_specifier", SymbolKind::Type),
+        ("enum_specifier", SymbolKind::Type),
+    ],
+    imports: &["preproc_include"],
+    wrappers: &[],
+};
+
+const CPP: Grammar = Grammar {
+    name: "cpp",
+    language: || tree_sitter_cpp::LANGUAGE.into(),
+    definitions: &[
+        ("function_definition", SymbolKind::Function),
+        ("class_specifier", SymbolKind::Type),
+        ("struct_specifier", SymbolKind::Type),
+        ("union_specifier", SymbolKind::Type),
+        ("enum_specifier", SymbolKind::Type),
+        ("namespace_definition", SymbolKind::Module),
+    ],
+    imports: &["preproc_include"],
+    wrappers: &["template_declaration"],
+};
+
+fn grammar(extension: &str) -> Option<&'static Grammar> {
+    match extension {
+        "rs" => Some(&RUST),
+        "py" => Some(&PYTHON),
+        "js" | "jsx" | "mjs" | "cjs" => Some(&JAVASCRIPT),
+        "ts" | "mts" | "cts" => Some(&TYPESCRIPT),
+        "tsx" => Some(&TSX),
+        "go" => Some(&GO),
+        "java" => Some(&JAVA),
+        "c" | "h" => Some(&C),
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => Some(&CPP),
+        _ => None,
+    }
+}
+
+/// The outline of a source file, for the languages with a grammar here
+pub fn outline(extension: &str, source: &str) -> Option<CodeOutline> {
+    let grammar = grammar(extension)?;
+    if source.len() > MAX_OUTLINE_BYTES {
+        return None;
+    }
+    let mut parser = Parser::new();
+    parser.set_language(&(grammar.language)()).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut outline = CodeOutline { language: grammar.name, symbols: Vec::new(), imports: Vec::new() };
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if grammar.imports.contains(&node.kind()) {
+            let import = text(node, source).split_whitespace().collect::<Vec<_>>().join(" ");
+            outline.imports.push(import);
+            continue;
+        }
+        if let Some(&(_, kind)) = grammar.definitions.iter().find(|(definition, _)| *definition == node.kind()) {
+            if let Some(name) = symbol_name(node, source) {
+                if outline.symbols.len() < MAX_SYMBOLS {
+                    outline.symbols.push(Symbol {
+                        name,
+                        kind,
+                        line: node.start_position().row + 1,
+                        doc: docstring(node, source).or_else(|| doc_comment(node, grammar, source)),
+                    });
+                }
+            }
+        }
+        // Children in reverse, so symbols come out in source order
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    Some(outline)
+}
+
+impl CodeOutline {
+    /// Imports and symbols as lines for the extracted text, ahead of the source
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Language: {}\n", self.language);
+        if !self.imports.is_empty() {
+            summary.push_str("Imports:\n");
+            for import in &self.imports {
+                summary.push_str(&format!("  {}\n", import));
+            }
+        }
+        if !self.symbols.is_empty() {
+            summary.push_str("Symbols:\n");
+            for symbol in &self.symbols {
+                summary.push_str(&format!("  {} {} (line {})", symbol.kind.label(), symbol.name, symbol.line));
+                if let Some(doc) = &symbol.doc {
+                    summary.push_str(&format!(": {}", doc));
+                }
+                summary.push('\n');
+            }
+        }
+        summary
+    }
+}
+
+fn text<'a>(node: Node, source: &'a str) -> &'a str {
+    node.utf8_text(source.as_bytes()).unwrap_or_default()
+}
+
+/// The `name` field, or for C and C++ the name inside the nested declarators
+fn symbol_name(node: Node, source: &str) -> Option<String> {
+    if let Some(name) = node.child_by_field_name("name") {
+        // A C struct named only where it is used, as in `struct point p;`, defines nothing
+        if node.kind().ends_with("_specifier") && node.child_by_field_name("body").is_none() {
+            return None;
+        }
+        return Some(text(name, source).to_string());
+    }
+    let mut declarator = node.child_by_field_name("declarator")?;
+    while let Some(inner) = declarator.child_by_field_name("declarator") {
+        declarator = inner;
+    }
+    Some(text(declarator, source).to_string())
+}
+
+/// A Python docstring, the string the body starts with
+fn docstring(node: Node, source: &str) -> Option<String> {
+    let first = node.child_by_field_name("body")?.named_child(0)?;
+    let string = match first.kind() {
+        "expression_statement" => first.named_child(0).filter(|string| string.kind() == "string")?,
+        _ => return None,
+    };
+    let quoted = text(string, source);
+    let unquoted = quoted.trim_start_matches(['r', 'u', 'R', 'U'])
+        .trim_start_matches("\"\"\"").trim_start_matches("'''")
+        .trim_end_matches("\"\"\"").trim_end_matches("'''")
+        .trim_matches(['"', '\'']);
+    first_paragraph(unquoted.lines())
+}
+
+/// The comments right above a definition, skipping attributes such as `#[derive(...)]`
+fn doc_comment(node: Node, grammar: &Grammar, source: &str) -> Option<String> {
+    let mut anchor = node;
+    while let Some(parent) = anchor.parent().filter(|parent| grammar.wrappers.contains(&parent.kind())) {
+        anchor = parent;
+    }
+    let mut comments = Vec::new();
+    let mut next_row = anchor.start_position().row;
+    let mut sibling = anchor.prev_named_sibling();
+    while let Some(node) = sibling {
+        let is_comment = COMMENTS.contains(&node.kind());
+        if !(is_comment || node.kind() == "attribute_item") || node.end_position().row + 1 < next_row {
+            break;
+        }
+        if is_comment {
+            comments.push(text(node, source));
+        }
+        next_row = node.start_position().row;
+        sibling = node.prev_named_sibling();
+    }
+    comments.reverse();
+    let lines = comments.iter().flat_map(|comment| comment.lines()).map(|line| {
+        line.trim()
+            .trim_start_matches("/**").trim_start_matches("/*!").trim_start_matches("/*")
+            .trim_end_matches("*/")
+            .trim_start_matches('/').trim_start_matches('!').trim_start_matches('*').trim_start_matches('#')
+    });
+    first_paragraph(lines)
+}
+
+/// Lines up to the first blank one, joined
+fn first_paragraph<'a>(lines: impl Iterator<Item = &'a str>) -> Option<String> {
+    let paragraph: Vec<&str> = lines
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect();
+    (!paragraph.is_empty()).then(|| paragraph.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(outline: &CodeOutline) -> Vec<(&str, SymbolKind)> {
+        outline.symbols.iter().map(|symbol| (symbol.name.as_str(), symbol.kind)).collect()
+    }
+
+    #[test]
+    fn test_rust_outline() {
+        let source = r#"
+use std::collections::HashMap;
+
+/// Counts words.
+///
+/// Details that are left out.
+#[derive(Debug)]
+pub struct Counter {
+    words: HashMap<String, usize>,
+}
+
+impl Counter {
+    // Adds a word
+    pub fn add(&mut self, word: &str) {}
+}
+"#;
+        let outline = outline("rs", source).unwrap();
+        assert_eq!(outline.imports, vec!["use std::collections::HashMap;"]);
+        assert_eq!(names(&outline), vec![("Counter", SymbolKind::Type), ("add", SymbolKind::Function)]);
+        assert_eq!(outline.symbols[0].doc.as_deref(), Some("Counts words."));
+        assert_eq!(outline.symbols[0].line, 8);
+        assert_eq!(outline.symbols[1].doc.as_deref(), Some("Adds a word"));
+    }
+
+    #[test]
+    fn test_python_outline() {
+        let source = r#"
+from os import path
+
+@dataclass
+class Report:
+    """A quarterly report."""
+
+    def render(self):
+        return path.join("a", "b")
+"#;
+        let outline = outline("py", source).unwrap();
+        assert_eq!(outline.imports, vec!["from os import path"]);
+        assert_eq!(names(&outline), vec![("Report", SymbolKind::Type), ("render", SymbolKind::Function)]);
+        assert_eq!(outline.symbols[0].doc.as_deref(), Some("A quarterly report."));
+        assert_eq!(outline.symbols[1].doc, None);
+    }
+
+    #[test]
+    fn test_typescript_and_c_outlines() {
+        let source = "import { x } from './x';\n/** Greets. */\nexport function greet(): void {}\ninterface Shape { area(): number }\n";
+        let typescript = outline("ts", source).unwrap();
+        assert_eq!(names(&typescript), vec![
+            ("greet", SymbolKind::Function),
+            ("Shape", SymbolKind::Type),
+            ("area", SymbolKind::Function),
+        ]);
+        assert_eq!(typescript.symbols[0].doc.as_deref(), Some("Greets."));
+
+        let source = "#include <stdio.h>\nstruct point { int x; };\nstatic int *scale(struct point p) { return 0; }\n";
+        let c = outline("c", source).unwrap();
+        assert_eq!(c.imports, vec!["#include <stdio.h>"]);
+        assert_eq!(names(&c), vec![("point", SymbolKind::Type), ("scale", SymbolKind::Function)]);
+    }
+
+    #[test]
+    fn test_unknown_language() {
+        assert!(outline("cobol", "IDENTIFICATION DIVISION.").is_none());
+    }
+}
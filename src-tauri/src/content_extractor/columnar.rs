@@ -0,0 +1,249 @@
+use std::fs::File;
+
+use anyhow::Result;
+use parquet::basic::{ConvertedType, LogicalType};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::file::statistics::Statistics;
+use serde::Serialize;
+
+/// What a column of a dataset holds
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnStats {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub nulls: Option<u64>,
+    /// Smallest and largest values, where the file keeps statistics for the column
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+/// The schema and size of a Parquet or Arrow file
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetSummary {
+    /// `parquet` or `arrow`
+    pub format: &'static str,
+    pub rows: u64,
+    /// Row groups of a Parquet file, record batches of an Arrow one
+    pub chunks: usize,
+    pub columns: Vec<ColumnStats>,
+    /// The library that wrote the file, when it says
+    pub created_by: Option<String>,
+}
+
+/// A bound of a column chunk's statistics, compared with those of other chunks of its column
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum Bound {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl Bound {
+    fn pair(statistics: &Statistics) -> (Option<Bound>, Option<Bound>) {
+        fn map<T>(min: Option<&T>, max: Option<&T>, bound: impl Fn(&T) -> Option<Bound>) -> (Option<Bound>, Option<Bound>) {
+            (min.and_then(&bound), max.and_then(&bound))
+        }
+        match statistics {
+            Statistics::Boolean(s) => map(s.min_opt(), s.max_opt(), |v| Some(Bound::Bool(*v))),
+            Statistics::Int32(s) => map(s.min_opt(), s.max_opt(), |v| Some(Bound::Int(*v as i64))),
+            Statistics::Int64(s) => map(s.min_opt(), s.max_opt(), |v| Some(Bound::Int(*v))),
+            Statistics::Float(s) => map(s.min_opt(), s.max_opt(), |v| Some(Bound::Float(*v as f64))),
+            Statistics::Double(s) => map(s.min_opt(), s.max_opt(), |v| Some(Bound::Float(*v))),
+            Statistics::ByteArray(s) => map(s.min_opt(), s.max_opt(), |v| v.as_utf8().ok().map(|v| Bound::Text(v.to_string()))),
+            // Legacy timestamps and fixed-width binaries have no readable order
+            Statistics::Int96(_) | Statistics::FixedLenByteArray(_) => (None, None),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Bound::Bool(value) => value.to_string(),
+            Bound::Int(value) => value.to_string(),
+            Bound::Float(value) => value.to_string(),
+            Bound::Text(value) => value.clone(),
+        }
+    }
+}
+
+/// Keep the smaller or larger of two bounds of the same kind
+fn fold(current: Option<Bound>, next: Option<Bound>, smaller: bool) -> Option<Bound> {
+    match (current, next) {
+        (Some(current), Some(next)) => match current.partial_cmp(&next) {
+            Some(std::cmp::Ordering::Greater) if smaller => Some(next),
+            Some(std::cmp::Ordering::Less) if !smaller => Some(next),
+            _ => Some(current),
+        },
+        (current, next) => current.or(next),
+    }
+}
+
+/// Read the footer of a Parquet file, whose column statistics spare reading the data
+pub fn read_parquet(file: File) -> Result<DatasetSummary> {
+    let reader = SerializedFileReader::new(file)?;
+    let metadata = reader.metadata();
+    let schema = metadata.file_metadata().schema_descr();
+
+    let mut columns: Vec<ColumnStats> = schema.columns().iter()
+        .map(|column| ColumnStats {
+            name: column.path().string(),
+            // Older writers only set the converted type
+            data_type: match (column.logical_type(), column.converted_type()) {
+                (Some(LogicalType::String), _) | (None, ConvertedType::UTF8) => "string".to_string(),
+                (Some(LogicalType::Date), _) | (None, ConvertedType::DATE) => "date".to_string(),
+                (Some(LogicalType::Timestamp { .. }), _) => "timestamp".to_string(),
+                (Some(LogicalType::Decimal { .. }), _) | (None, ConvertedType::DECIMAL) => "decimal".to_string(),
+                (Some(LogicalType::Json), _) | (None, ConvertedType::JSON) => "json".to_string(),
+                _ => column.physical_type().to_string().to_lowercase(),
+            },
+            nulls: Some(0),
+            min: None,
+            max: None,
+        })
+        .collect();
+    let mut bounds: Vec<(Option<Bound>, Option<Bound>)> = vec![(None, None); columns.len()];
+
+    for row_group in metadata.row_groups() {
+        for (index, chunk) in row_group.columns().iter().enumerate().take(columns.len()) {
+            let Some(statistics) = chunk.statistics() else {
+                columns[index].nulls = None;
+                continue;
+            };
+            columns[index].nulls = columns[index].nulls.zip(statistics.null_count_opt()).map(|(a, b)| a + b);
+            let (min, max) = Bound::pair(statistics);
+            let (current_min, current_max) = std::mem::take(&mut bounds[index]);
+            bounds[index] = (fold(current_min, min, true), fold(current_max, max, false));
+        }
+    }
+    for (column, (min, max)) in columns.iter_mut().zip(bounds) {
+        column.min = min.map(|bound| bound.to_text());
+        column.max = max.map(|bound| bound.to_text());
+    }
+
+    Ok(DatasetSummary {
+        format: "parquet",
+        rows: metadata.file_metadata().num_rows().max(0) as u64,
+        chunks: metadata.num_row_groups(),
+        columns,
+        created_by: metadata.file_metadata().created_by().map(str::to_string),
+    })
+}
+
+/// Read an Arrow IPC file (`.arrow`, `.feather`) batch by batch, counting rows and nulls
+pub fn read_arrow(file: File) -> Result<DatasetSummary> {
+    let reader = arrow_ipc::reader::FileReader::try_new(std::io::BufReader::new(file), None)?;
+    let schema = reader.schema();
+    let mut columns: Vec<ColumnStats> = schema.fields().iter()
+        .map(|field| ColumnStats {
+            name: field.name().clone(),
+            data_type: field.data_type().to_string().to_lowercase(),
+            nulls: Some(0),
+            min: None,
+            max: None,
+        })
+        .collect();
+
+    let mut rows = 0;
+    let mut chunks = 0;
+    for batch in reader {
+        let batch = batch?;
+        rows += batch.num_rows() as u64;
+        chunks += 1;
+        for (column, array) in columns.iter_mut().zip(batch.columns()) {
+            column.nulls = column.nulls.map(|nulls| nulls + array.null_count() as u64);
+        }
+    }
+
+    Ok(DatasetSummary { format: "arrow", rows, chunks, columns, created_by: None })
+}
+
+impl DatasetSummary {
+    /// The schema as lines, so datasets are found by their column names
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Dataset: {} rows, {} columns ({})\n", self.rows, self.columns.len(), self.format);
+        if let Some(created_by) = &self.created_by {
+            text.push_str(&format!("Written by: {}\n", created_by));
+        }
+        text.push_str("Columns:\n");
+        for column in &self.columns {
+            text.push_str(&format!("  {} ({}", column.name, column.data_type));
+            if let Some(nulls) = column.nulls.filter(|nulls| *nulls > 0) {
+                text.push_str(&format!(", {} null", nulls));
+            }
+            text.push(')');
+            if let (Some(min), Some(max)) = (&column.min, &column.max) {
+                text.push_str(&format!(": {} to {}", min, max));
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    #[test]
+    fn test_read_parquet() {
+        let schema = Arc::new(parse_message_type(
+            "message trips { required int64 distance; optional binary city (UTF8); }"
+        ).unwrap());
+        let file = tempfile::tempfile().unwrap();
+        let mut writer = SerializedFileWriter::new(file.try_clone().unwrap(), schema, Arc::new(WriterProperties::default())).unwrap();
+        for (distances, cities) in [(vec![12, 3], vec!["Oslo"]), (vec![40], vec!["Bergen"])] {
+            let mut row_group = writer.next_row_group().unwrap();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column.typed::<Int64Type>().write_batch(&distances, None, None).unwrap();
+            column.close().unwrap();
+            // One city is null in the first group
+            let definition_levels: Vec<i16> = match distances.len() { 2 => vec![1, 0], _ => vec![1] };
+            let cities: Vec<ByteArray> = cities.into_iter().map(ByteArray::from).collect();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column.typed::<ByteArrayType>().write_batch(&cities, Some(&definition_levels), None).unwrap();
+            column.close().unwrap();
+            row_group.close().unwrap();
+        }
+        writer.close().unwrap();
+
+        let summary = read_parquet(file).unwrap();
+        assert_eq!(summary.rows, 3);
+        assert_eq!(summary.chunks, 2);
+        assert_eq!(summary.columns[0].data_type, "int64");
+        assert_eq!(summary.columns[0].min.as_deref(), Some("3"));
+        assert_eq!(summary.columns[0].max.as_deref(), Some("40"));
+        assert_eq!(summary.columns[1].data_type, "string");
+        assert_eq!(summary.columns[1].nulls, Some(1));
+        assert_eq!(summary.columns[1].min.as_deref(), Some("Bergen"));
+        assert!(summary.to_text().contains("  distance (int64): 3 to 40\n  city (string, 1 null): Bergen to Oslo\n"));
+    }
+
+    #[test]
+    fn test_read_arrow() {
+        use arrow_array::{ArrayRef, Int32Array, RecordBatch, StringArray};
+
+        let batch = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int32Array::from(vec![1, 2, 3])) as ArrayRef),
+            ("label", Arc::new(StringArray::from(vec![Some("a"), None, Some("c")])) as ArrayRef),
+        ]).unwrap();
+        let file = tempfile::tempfile().unwrap();
+        let mut writer = arrow_ipc::writer::FileWriter::try_new(file.try_clone().unwrap(), &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+
+        let mut file = file;
+        std::io::Seek::rewind(&mut file).unwrap();
+        let summary = read_arrow(file).unwrap();
+        assert_eq!(summary.rows, 6);
+        assert_eq!(summary.chunks, 2);
+        assert_eq!(summary.columns[0].data_type, "int32");
+        assert_eq!(summary.columns[1].nulls, Some(2));
+    }
+}
@@ -0,0 +1,258 @@
+use std::io::Read;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use mail_parser::{Address, MessageParser, MimeHeaders};
+use mail_parser::mailbox::mbox::MessageIterator;
+use serde::Serialize;
+
+/// Messages of a mailbox read at most; the rest are only counted
+pub const MAX_MBOX_MESSAGES: usize = 1_000;
+
+/// An email read from a `.eml`, `.msg` or mbox file
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Email {
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub subject: Option<String>,
+    /// When it was sent, as RFC 3339
+    pub date: Option<String>,
+    pub attachments: Vec<String>,
+    #[serde(skip)]
+    pub body: String,
+}
+
+impl Email {
+    /// A message in the Internet Message Format, as in `.eml` files
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let message = MessageParser::default().parse(bytes)?;
+        let addresses = |address: Option<&Address>| -> Vec<String> {
+            address.map(|address| address.iter().filter_map(format_address).collect()).unwrap_or_default()
+        };
+        // Text parts, with those only sent as HTML converted to text
+        let body = (0..message.text_body_count())
+            .filter_map(|part| message.body_text(part))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        Some(Self {
+            from: addresses(message.from()).into_iter().next(),
+            to: addresses(message.to()),
+            cc: addresses(message.cc()),
+            subject: message.subject().map(str::to_string),
+            date: message.date().map(|date| date.to_rfc3339()),
+            attachments: message.attachments()
+                .filter_map(|attachment| attachment.attachment_name().map(str::to_string))
+                .collect(),
+            body: body.trim().to_string(),
+        })
+    }
+
+    /// The headers, a blank line and the body, as the extracted text
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(from) = &self.from {
+            text.push_str(&format!("From: {}\n", from));
+        }
+        if !self.to.is_empty() {
+            text.push_str(&format!("To: {}\n", self.to.join(", ")));
+        }
+        if !self.cc.is_empty() {
+            text.push_str(&format!("Cc: {}\n", self.cc.join(", ")));
+        }
+        if let Some(date) = &self.date {
+            text.push_str(&format!("Date: {}\n", date));
+        }
+        if let Some(subject) = &self.subject {
+            text.push_str(&format!("Subject: {}\n", subject));
+        }
+        if !self.attachments.is_empty() {
+            text.push_str(&format!("Attachments: {}\n", self.attachments.join(", ")));
+        }
+        text.push('\n');
+        text.push_str(&self.body);
+        text
+    }
+}
+
+/// `Name <address>`, or whichever of the two is there
+fn format_address(address: &mail_parser::Addr) -> Option<String> {
+    match (address.name.as_deref(), address.address.as_deref()) {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+        (name, email) => name.or(email).map(str::to_string),
+    }
+}
+
+/// The messages of an mbox file, up to `MAX_MBOX_MESSAGES`, and how many it holds in all.
+/// Mailboxes run to gigabytes, so they are read a message at a time.
+pub fn parse_mbox(mbox: impl Read) -> (Vec<Email>, usize) {
+    let mut emails = Vec::new();
+    let mut count = 0;
+    for message in MessageIterator::new(mbox).flatten() {
+        count += 1;
+        if emails.len() < MAX_MBOX_MESSAGES {
+            emails.extend(Email::parse(message.contents()));
+        }
+    }
+    (emails, count)
+}
+
+// MAPI properties of an Outlook message, stored as `__substg1.0_<id><type>` streams
+const PR_SUBJECT: u16 = 0x0037;
+const PR_CLIENT_SUBMIT_TIME: u16 = 0x0039;
+const PR_SENDER_NAME: u16 = 0x0C1A;
+const PR_SENDER_EMAIL_ADDRESS: u16 = 0x0C1F;
+const PR_DISPLAY_CC: u16 = 0x0E03;
+const PR_DISPLAY_TO: u16 = 0x0E04;
+const PR_MESSAGE_DELIVERY_TIME: u16 = 0x0E06;
+const PR_BODY: u16 = 0x1000;
+const PR_ATTACH_FILENAME: u16 = 0x3704;
+const PR_ATTACH_LONG_FILENAME: u16 = 0x3707;
+const PR_SENDER_SMTP_ADDRESS: u16 = 0x5D01;
+
+const PT_STRING8: u16 = 0x001E;
+const PT_UNICODE: u16 = 0x001F;
+const PT_SYSTIME: u16 = 0x0040;
+
+/// An Outlook `.msg` file, an OLE compound file of MAPI property streams
+pub fn parse_msg(reader: impl Read + std::io::Seek) -> Result<Email> {
+    let mut msg = cfb::CompoundFile::open(reader)
+        .map_err(|e| anyhow!("Not an Outlook message: {}", e))?;
+    let mut string = |storage: &str, property: u16| read_string(&mut msg, storage, property);
+
+    let sender_address = string("", PR_SENDER_SMTP_ADDRESS).or_else(|| string("", PR_SENDER_EMAIL_ADDRESS));
+    let from = match (string("", PR_SENDER_NAME), sender_address) {
+        (Some(name), Some(address)) if name != address => Some(format!("{} <{}>", name, address)),
+        (name, address) => address.or(name),
+    };
+    // Display lists are separated by semicolons
+    let list = |value: Option<String>| -> Vec<String> {
+        value.map(|value| value.split(';').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    let to = list(string("", PR_DISPLAY_TO));
+    let cc = list(string("", PR_DISPLAY_CC));
+    let subject = string("", PR_SUBJECT);
+    let body = string("", PR_BODY).unwrap_or_default();
+
+    let attachment_storages: Vec<String> = msg.read_root_storage()
+        .filter(|entry| entry.is_storage() && entry.name().starts_with("__attach_version1.0_"))
+        .map(|entry| entry.name().to_string())
+        .collect();
+    let attachments = attachment_storages.iter()
+        .filter_map(|storage| {
+            read_string(&mut msg, storage, PR_ATTACH_LONG_FILENAME)
+                .or_else(|| read_string(&mut msg, storage, PR_ATTACH_FILENAME))
+        })
+        .collect();
+
+    let mut properties = Vec::new();
+    if let Ok(mut stream) = msg.open_stream("/__properties_version1.0") {
+        stream.read_to_end(&mut properties)?;
+    }
+    let date = [PR_CLIENT_SUBMIT_TIME, PR_MESSAGE_DELIVERY_TIME].into_iter()
+        .find_map(|property| read_time(&properties, property))
+        .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+    Ok(Email { from, to, cc, subject, date, attachments, body: body.trim().to_string() })
+}
+
+/// A string property of the message, or of one of its attachment or recipient storages
+fn read_string<F: Read + std::io::Seek>(msg: &mut cfb::CompoundFile<F>, storage: &str, property: u16) -> Option<String> {
+    [PT_UNICODE, PT_STRING8].into_iter().find_map(|kind| {
+        let mut stream = msg.open_stream(format!("{}/__substg1.0_{:04X}{:04X}", storage, property, kind)).ok()?;
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).ok()?;
+        let value = match kind {
+            PT_UNICODE => {
+                let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+                String::from_utf16_lossy(&units)
+            }
+            _ => String::from_utf8_lossy(&bytes).into_owned(),
+        };
+        let value = value.trim_end_matches('\0').trim().to_string();
+        (!value.is_empty()).then_some(value)
+    })
+}
+
+/// A time property from the top-level properties stream: a 32-byte header, then 16-byte entries
+/// of tag, flags and an 8-byte value, here a FILETIME
+fn read_time(properties: &[u8], property: u16) -> Option<DateTime<Utc>> {
+    let tag = ((property as u32) << 16) | PT_SYSTIME as u32;
+    let entry = properties.get(32..)?
+        .chunks_exact(16)
+        .find(|entry| u32::from_le_bytes(entry[0..4].try_into().unwrap()) == tag)?;
+    let filetime = i64::from_le_bytes(entry[8..16].try_into().unwrap());
+    // 100-nanosecond intervals since 1601-01-01
+    const UNIX_EPOCH_FILETIME: i64 = 116_444_736_000_000_000;
+    let nanos = filetime.checked_sub(UNIX_EPOCH_FILETIME)?.checked_mul(100)?;
+    Some(DateTime::from_timestamp_nanos(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    const EML: &str = "From: Ada Lovelace <ada@example.com>\r\n\
+        To: Charles Babbage <charles@example.com>, team@example.com\r\n\
+        Subject: Notes on the engine\r\n\
+        Date: Mon, 1 Apr 2024 09:30:00 +0000\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        The analytical engine weaves algebraic patterns.\r\n";
+
+    #[test]
+    fn test_parse_eml() {
+        let email = Email::parse(EML.as_bytes()).unwrap();
+        assert_eq!(email.from.as_deref(), Some("Ada Lovelace <ada@example.com>"));
+        assert_eq!(email.to, vec!["Charles Babbage <charles@example.com>", "team@example.com"]);
+        assert_eq!(email.subject.as_deref(), Some("Notes on the engine"));
+        assert_eq!(email.date.as_deref(), Some("2024-04-01T09:30:00Z"));
+        assert_eq!(email.body, "The analytical engine weaves algebraic patterns.");
+        assert!(email.to_text().starts_with("From: Ada Lovelace <ada@example.com>\nTo: "));
+    }
+
+    #[test]
+    fn test_parse_mbox() {
+        let mbox = format!("From ada@example.com Mon Apr  1 09:30:00 2024\n{}\nFrom ada@example.com Tue Apr  2 09:30:00 2024\n{}", EML, EML);
+        let (emails, count) = parse_mbox(mbox.as_bytes());
+        assert_eq!(count, 2);
+        assert_eq!(emails.len(), 2);
+        assert_eq!(emails[1].subject.as_deref(), Some("Notes on the engine"));
+    }
+
+    fn write_unicode(msg: &mut cfb::CompoundFile<Cursor<Vec<u8>>>, path: &str, value: &str) {
+        let bytes: Vec<u8> = value.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        msg.create_stream(path).unwrap().write_all(&bytes).unwrap();
+    }
+
+    #[test]
+    fn test_parse_msg() {
+        let mut msg = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        write_unicode(&mut msg, "/__substg1.0_0037001F", "Quarterly report");
+        write_unicode(&mut msg, "/__substg1.0_0C1A001F", "Grace Hopper");
+        write_unicode(&mut msg, "/__substg1.0_5D01001F", "grace@example.com");
+        write_unicode(&mut msg, "/__substg1.0_0E04001F", "Ada Lovelace; Alan Turing");
+        write_unicode(&mut msg, "/__substg1.0_1000001F", "Figures attached.\r\n");
+        msg.create_storage("/__attach_version1.0_#00000000").unwrap();
+        write_unicode(&mut msg, "/__attach_version1.0_#00000000/__substg1.0_3707001F", "q1.xlsx");
+
+        let mut properties = vec![0u8; 32];
+        properties.extend_from_slice(&0x0039_0040u32.to_le_bytes());
+        properties.extend_from_slice(&0u32.to_le_bytes());
+        // 2024-04-01T09:30:00Z
+        properties.extend_from_slice(&(133_564_374_000_000_000i64).to_le_bytes());
+        msg.create_stream("/__properties_version1.0").unwrap().write_all(&properties).unwrap();
+        let bytes = msg.into_inner().into_inner();
+
+        let email = parse_msg(Cursor::new(bytes)).unwrap();
+        assert_eq!(email.from.as_deref(), Some("Grace Hopper <grace@example.com>"));
+        assert_eq!(email.to, vec!["Ada Lovelace", "Alan Turing"]);
+        assert_eq!(email.subject.as_deref(), Some("Quarterly report"));
+        assert_eq!(email.date.as_deref(), Some("2024-04-01T09:30:00Z"));
+        assert_eq!(email.attachments, vec!["q1.xlsx"]);
+        assert_eq!(email.body, "Figures attached.");
+        assert!(parse_msg(Cursor::new(EML.as_bytes())).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Serialize;
+
+/// Elements whose content is never shown as text
+const HIDDEN: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "canvas", "iframe"];
+
+/// Elements that start a new line of text
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption", "figure",
+    "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav", "ol", "p",
+    "pre", "section", "table", "td", "th", "tr", "ul",
+];
+
+/// What a web page says about itself, and the text it shows
+#[derive(Debug, Default, Serialize)]
+pub struct HtmlPage {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub keywords: Vec<String>,
+    pub author: Option<String>,
+    /// The `lang` attribute of the page
+    pub language: Option<String>,
+    /// `og:` properties without the prefix, such as `title`, `type` and `image`
+    pub open_graph: BTreeMap<String, String>,
+    /// Headings in page order, prefixed with their level, e.g. `h2 Pricing`
+    pub headings: Vec<String>,
+    /// The visible text, a line per block
+    #[serde(skip)]
+    pub text: String,
+}
+
+impl HtmlPage {
+    pub fn parse(html: &str) -> Self {
+        let document = Html::parse_document(html);
+        let selector = |selector: &str| Selector::parse(selector).expect("valid selector");
+        let content = |element: ElementRef| element.value().attr("content").map(clean).filter(|value| !value.is_empty());
+        let meta = |name: &str| {
+            document.select(&selector(&format!("meta[name='{}' i]", name))).find_map(content)
+        };
+
+        let mut open_graph = BTreeMap::new();
+        for element in document.select(&selector("meta[property^='og:']")) {
+            if let (Some(property), Some(value)) = (element.value().attr("property"), content(element)) {
+                open_graph.entry(property.trim_start_matches("og:").to_string()).or_insert(value);
+            }
+        }
+
+        let title = document.select(&selector("title")).next()
+            .map(|title| clean(&title.text().collect::<String>()))
+            .filter(|title| !title.is_empty())
+            .or_else(|| open_graph.get("title").cloned());
+        let description = meta("description").or_else(|| open_graph.get("description").cloned());
+        let keywords = meta("keywords")
+            .map(|keywords| keywords.split(',').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default();
+        let headings = document.select(&selector("h1, h2, h3, h4, h5, h6"))
+            .map(|heading| format!("{} {}", heading.value().name(), clean(&heading.text().collect::<String>())))
+            .filter(|heading| heading.len() > 3)
+            .collect();
+        let language = document.root_element().value().attr("lang").map(str::to_string);
+
+        let mut text = String::new();
+        collect_text(document.root_element(), &mut text, false);
+        let text = text.lines().map(clean).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n");
+
+        Self {
+            title,
+            description,
+            keywords,
+            author: meta("author"),
+            language,
+            open_graph,
+            headings,
+            text,
+        }
+    }
+
+    /// The page's metadata as lines ahead of its text
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(title) = &self.title {
+            text.push_str(&format!("Title: {}\n", title));
+        }
+        if let Some(description) = &self.description {
+            text.push_str(&format!("Description: {}\n", description));
+        }
+        if !self.keywords.is_empty() {
+            text.push_str(&format!("Keywords: {}\n", self.keywords.join(", ")));
+        }
+        if !self.headings.is_empty() {
+            text.push_str("Headings:\n");
+            for heading in &self.headings {
+                text.push_str(&format!("  {}\n", heading));
+            }
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&self.text);
+        text
+    }
+}
+
+/// The text of an XML document, a line per element
+pub fn xml_text(xml: &str) -> String {
+    let document = Html::parse_document(xml);
+    let mut text = String::new();
+    collect_text(document.root_element(), &mut text, true);
+    text.lines().map(clean).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n")
+}
+
+/// Text in document order, with HTML's hidden elements left out unless this is XML
+fn collect_text(element: ElementRef, text: &mut String, xml: bool) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(fragment) => text.push_str(fragment),
+            Node::Element(child_element) => {
+                let name = child_element.name();
+                if !xml && HIDDEN.contains(&name) {
+                    continue;
+                }
+                let block = xml || BLOCKS.contains(&name);
+                if block {
+                    text.push('\n');
+                }
+                if let Some(child) = ElementRef::wrap(child) {
+                    collect_text(child, text, xml);
+                }
+                if block {
+                    text.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whitespace collapsed to single spaces
+fn clean(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <title> Pricing | Acme </title>
+  <meta name="Description" content="Plans for   every team.">
+  <meta name="keywords" content="pricing, plans, ">
+  <meta property="og:title" content="Acme pricing">
+  <meta property="og:image" content="https://acme.test/card.png">
+  <style>body { color: red; }</style>
+</head>
+<body>
+  <script>var tracking = "hidden";</script>
+  <h1>Pricing</h1>
+  <p>Start <b>free</b>, upgrade 1&lt;2 later.</p>
+  <noscript>Enable JavaScript</noscript>
+  <ul><li>Basic</li><li>Pro</li></ul>
+</body>
+</html>"#;
+
+    #[test]
+    fn test_parse_page() {
+        let page = HtmlPage::parse(PAGE);
+        assert_eq!(page.title.as_deref(), Some("Pricing | Acme"));
+        assert_eq!(page.description.as_deref(), Some("Plans for every team."));
+        assert_eq!(page.keywords, vec!["pricing", "plans"]);
+        assert_eq!(page.language.as_deref(), Some("en"));
+        assert_eq!(page.open_graph.get("image").map(String::as_str), Some("https://acme.test/card.png"));
+        assert_eq!(page.headings, vec!["h1 Pricing"]);
+        assert_eq!(page.text, "Pricing\nStart free, upgrade 1<2 later.\nBasic\nPro");
+    }
+
+    #[test]
+    fn test_xml_text() {
+        let xml = r#"<?xml version="1.0"?><notes><note><to>Ada</to><body>Check the engine</body></note></notes>"#;
+        assert_eq!(xml_text(xml), "Ada\nCheck the engine");
+    }
+
+    #[test]
+    fn test_title_falls_back_to_open_graph() {
+        let page = HtmlPage::parse(r#"<meta property="og:title" content="Shared card"><p>Body</p>"#);
+        assert_eq!(page.title.as_deref(), Some("Shared card"));
+        assert_eq!(page.text, "Body");
+        assert!(page.to_text().starts_with("Title: Shared card\n\nBody"));
+    }
+}
@@ -0,0 +1,80 @@
+/// Formats stored inside a zip, which sniff as the zip itself or as one another
+const ZIP_FORMATS: &[&str] = &[
+    "zip", "docx", "xlsx", "pptx", "odt", "ods", "odp", "pages", "numbers", "key", "epub", "jar", "apk",
+];
+
+/// Formats stored inside an OLE compound file, which sniff as whichever OLE format matches first
+const OLE_FORMATS: &[&str] = &["doc", "xls", "ppt", "msg", "msi"];
+
+/// What a file's leading bytes say it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sniffed {
+    /// The extension to dispatch on: the file's own unless its bytes say it is something else
+    pub extension: String,
+    /// The type identified by the bytes, when they identify one
+    pub mime_type: Option<String>,
+}
+
+/// Compare the file's leading bytes with its extension. Markup and scripts are left to the
+/// extension, since an SVG is XML and a `.sh` file is text whatever its bytes look like.
+pub fn sniff(extension: &str, header: &[u8]) -> Sniffed {
+    let detected = infer::get(header).filter(|kind| kind.matcher_type() != infer::MatcherType::Text);
+    let Some(kind) = detected else {
+        return Sniffed { extension: extension.to_string(), mime_type: None };
+    };
+
+    let claimed = family(extension, None);
+    let actual = family(kind.extension(), Some(kind.mime_type()));
+    let extension = match claimed == actual {
+        true => extension.to_string(),
+        false => kind.extension().to_lowercase(),
+    };
+    Sniffed { extension, mime_type: Some(kind.mime_type().to_string()) }
+}
+
+/// Formats close enough to share an extractor, so a PNG saved as `.jpg` keeps its extension
+fn family(extension: &str, mime_type: Option<&str>) -> String {
+    let extension = extension.to_lowercase();
+    if ZIP_FORMATS.contains(&extension.as_str()) {
+        return "zip".to_string();
+    }
+    if OLE_FORMATS.contains(&extension.as_str()) {
+        return "ole".to_string();
+    }
+    let mime_type = mime_type
+        .map(str::to_string)
+        .or_else(|| mime_guess::from_ext(&extension).first().map(|mime| mime.essence_str().to_string()));
+    match mime_type {
+        Some(mime) if mime.starts_with("image/") => "image".to_string(),
+        Some(mime) if mime.starts_with("audio/") || mime.starts_with("video/") => "media".to_string(),
+        Some(mime) => mime,
+        None => extension,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PDF: &[u8] = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n1 0 obj";
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const ZIP: &[u8] = b"PK\x03\x04\x14\0\0\0\x08\0";
+
+    #[test]
+    fn test_renamed_files_are_dispatched_by_their_bytes() {
+        let sniffed = sniff("txt", PDF);
+        assert_eq!(sniffed.extension, "pdf");
+        assert_eq!(sniffed.mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(sniff("", PNG).extension, "png");
+        assert_eq!(sniff("pdf", ZIP).extension, "zip");
+    }
+
+    #[test]
+    fn test_matching_families_keep_their_extension() {
+        assert_eq!(sniff("jpg", PNG).extension, "jpg");
+        assert_eq!(sniff("pages", ZIP).extension, "pages");
+        assert_eq!(sniff("key", ZIP).extension, "key");
+        assert_eq!(sniff("svg", b"<?xml version=\"1.0\"?><svg/>"), Sniffed { extension: "svg".to_string(), mime_type: None });
+        assert_eq!(sniff("md", b"# Notes"), Sniffed { extension: "md".to_string(), mime_type: None });
+    }
+}
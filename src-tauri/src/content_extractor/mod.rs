@@ -1,8 +1,81 @@
 use std::path::Path;
 use anyhow::{Result, anyhow};
 use tokio::fs;
+use tokio::io::AsyncReadExt;
 use serde::{Serialize, Deserialize};
-// extern crate kamadak_exif as exif; // Temporarily disabled
+
+mod archive;
+mod audio;
+mod code;
+mod columnar;
+mod email;
+mod html;
+mod magic;
+mod ocr;
+mod office;
+mod photo;
+mod sqlite;
+mod stream;
+mod subtitles;
+mod table;
+
+pub use magic::Sniffed;
+pub use ocr::OcrSettings;
+pub use photo::ExifSettings;
+
+/// Names of the extractors files are dispatched to, which can be turned off one by one
+pub const EXTRACTORS: &[&str] = &[
+    "pdf", "text", "image", "office", "document", "spreadsheet", "presentation", "json", "csv", "dataset",
+    "database", "markup", "code", "archive", "audio", "video", "email", "subtitles",
+];
+
+/// How files are extracted, from the `extraction` section of the configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractionSettings {
+    /// Recognizing text in images, so screenshots and scans are searchable
+    pub ocr: OcrSettings,
+    /// Camera, capture date and location of photos
+    pub exif: ExifSettings,
+    /// Extractors turned off, named as in `EXTRACTORS`; their files are indexed by name and size only
+    pub disabled_extractors: Vec<String>,
+    /// Pages of a PDF read for text at most, from the first
+    pub pdf_max_pages: u32,
+    /// Rows of a CSV or TSV file quoted in its text after the columns
+    pub csv_sample_rows: usize,
+    /// How many archives deep zip files are listed: 0 leaves archives unopened, 1 lists their
+    /// files, 2 the files of the zips inside them too, and so on
+    pub archive_max_depth: u32,
+}
+
+impl Default for ExtractionSettings {
+    fn default() -> Self {
+        Self {
+            ocr: OcrSettings::default(),
+            exif: ExifSettings::default(),
+            disabled_extractors: Vec::new(),
+            pdf_max_pages: 500,
+            csv_sample_rows: table::SAMPLE_ROWS,
+            archive_max_depth: 2,
+        }
+    }
+}
+
+impl ExtractionSettings {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(name) = self.disabled_extractors.iter().find(|name| !EXTRACTORS.contains(&name.as_str())) {
+            return Err(anyhow!("Unknown extractor '{}'", name));
+        }
+        if self.pdf_max_pages == 0 {
+            return Err(anyhow!("PDFs need at least one page read"));
+        }
+        self.ocr.validate()
+    }
+
+    fn is_enabled(&self, extractor: &str) -> bool {
+        !self.disabled_extractors.iter().any(|name| name == extractor)
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExtractedContent {
@@ -25,6 +98,37 @@ pub struct ContentMetadata {
     pub image_count: Option<u32>,
     pub dimensions: Option<(u32, u32)>,
     pub exif_data: Option<serde_json::Value>,
+    /// Some or all of the text was recognized by OCR rather than read from the file
+    #[serde(default)]
+    pub ocr_used: bool,
+    /// Sender, recipients, subject, date and attachments of an email, or for a mailbox its
+    /// message count and the headers of each message
+    #[serde(default)]
+    pub email: Option<serde_json::Value>,
+    /// Language, imports and defined symbols of a source file
+    #[serde(default)]
+    pub code: Option<serde_json::Value>,
+    /// Description, OpenGraph properties and headings of a web page
+    #[serde(default)]
+    pub html: Option<serde_json::Value>,
+    /// Row count and column names, types and sample values of a CSV or TSV file
+    #[serde(default)]
+    pub table: Option<serde_json::Value>,
+    /// Row count and column types and statistics of a Parquet or Arrow file
+    #[serde(default)]
+    pub dataset: Option<serde_json::Value>,
+    /// Tables, columns and row counts of a SQLite database
+    #[serde(default)]
+    pub database: Option<serde_json::Value>,
+    /// Title, artist, album, genre and duration of a music file
+    #[serde(default)]
+    pub audio: Option<serde_json::Value>,
+    /// For subtitles the video they go with, for a video its subtitle files
+    #[serde(default)]
+    pub subtitles: Option<serde_json::Value>,
+    /// The type the file's leading bytes identify it as, whatever its extension says
+    #[serde(default)]
+    pub mime_type: Option<String>,
 }
 
 impl Default for ContentMetadata {
@@ -42,6 +146,16 @@ impl Default for ContentMetadata {
             image_count: None,
             dimensions: None,
             exif_data: None,
+            ocr_used: false,
+            email: None,
+            code: None,
+            html: None,
+            table: None,
+            dataset: None,
+            database: None,
+            audio: None,
+            subtitles: None,
+            mime_type: None,
         }
     }
 }
@@ -49,50 +163,177 @@ impl Default for ContentMetadata {
 pub struct ContentExtractor;
 
 impl ContentExtractor {
-    pub async fn extract_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+    /// The video next to a subtitle file that the subtitle was made for, none for other files
+    pub fn subtitled_video(path: &Path) -> Option<std::path::PathBuf> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match subtitles::SUBTITLE_EXTENSIONS.contains(&extension.as_str()) {
+            true => subtitles::video_for(path),
+            false => None,
+        }
+    }
+
+    /// Hex SHA-256 of the raw file bytes, read in chunks so large files stay out of memory
+    pub async fn compute_file_hash<P: AsRef<Path>>(path: P) -> Result<String> {
+        use sha2::{Sha256, Digest};
+        use tokio::io::AsyncReadExt;
+
+        let mut file = fs::File::open(path.as_ref()).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    pub async fn extract_content<P: AsRef<Path>>(path: P, settings: &ExtractionSettings) -> Result<ExtractedContent> {
         let path = path.as_ref();
-        let extension = path.extension()
+        let sniffed = Self::sniff(path).await;
+        Self::extract_sniffed(path, sniffed, settings).await
+    }
+
+    /// What the file's leading bytes say it is. A renamed file goes to the extractor for what
+    /// it really is, and gets that file type's extraction time limit.
+    pub async fn sniff(path: &Path) -> Sniffed {
+        let claimed = path.extension()
             .and_then(|e| e.to_str())
             .map(|e| e.to_lowercase())
             .unwrap_or_default();
 
-        match extension.as_str() {
-            "pdf" => Self::extract_pdf_content(path).await,
-            "txt" | "md" | "readme" | "log" | "yaml" | "yml" | "toml" | "ini" | "cfg" => Self::extract_text_content(path).await,
-            "jpg" | "jpeg" | "png" | "tiff" | "tif" | "bmp" | "gif" | "webp" | "svg" | "ico" => Self::extract_image_content(path).await,
-            "doc" | "docx" | "odt" | "rtf" => Self::extract_document_content(path).await,
-            "xls" | "xlsx" | "ods" => Self::extract_spreadsheet_content(path).await,
-            "ppt" | "pptx" | "odp" => Self::extract_presentation_content(path).await,
-            "json" | "geojson" => Self::extract_json_content(path).await,
-            "csv" | "tsv" => Self::extract_csv_content(path).await,
-            "xml" | "html" | "htm" | "xhtml" => Self::extract_markup_content(path).await,
-            "js" | "ts" | "jsx" | "tsx" | "py" | "rs" | "java" | "cpp" | "c" | "h" | "css" | "scss" | "sass" | "go" | "php" | "rb" | "swift" | "kt" | "dart" | "vue" | "sql" | "sh" | "bash" | "zsh" | "fish" => {
-                Self::extract_code_content(path).await
-            }
-            "zip" | "tar" | "gz" | "rar" | "7z" => Self::extract_archive_content(path).await,
-            "mp3" | "wav" | "flac" | "m4a" | "ogg" => Self::extract_audio_content(path).await,
-            "mp4" | "avi" | "mkv" | "mov" | "wmv" | "webm" => Self::extract_video_content(path).await,
+        let sniffed = match stream::read_prefix(path, stream::SNIFF_LIMIT).await {
+            Ok(header) => magic::sniff(&claimed, &header.bytes),
+            Err(_) => Sniffed { extension: claimed.clone(), mime_type: None },
+        };
+        if sniffed.extension != claimed {
+            tracing::debug!("{} is {} despite its extension", path.display(), sniffed.mime_type.as_deref().unwrap_or_default());
+        }
+        sniffed
+    }
+
+    /// Extract the file with the extractor for the type `sniff` found
+    pub async fn extract_sniffed(path: &Path, sniffed: Sniffed, settings: &ExtractionSettings) -> Result<ExtractedContent> {
+        let extension = sniffed.extension;
+
+        let extractor = Self::extractor_for(&extension);
+        if !settings.is_enabled(extractor) {
+            return Self::extract_file_info(path).await;
+        }
+        let mut content = match extractor {
+            "pdf" => Self::extract_pdf_content(path, settings).await,
+            "text" => Self::extract_text_content(path).await,
+            "image" => Self::extract_image_content(path, settings).await,
+            "office" => Self::extract_office_content(path, &extension).await,
+            "document" => Self::extract_document_content(path).await,
+            "spreadsheet" => Self::extract_spreadsheet_content(path).await,
+            "presentation" => Self::extract_presentation_content(path).await,
+            "json" => Self::extract_json_content(path).await,
+            "csv" => Self::extract_csv_content(path, settings).await,
+            "dataset" => Self::extract_dataset_content(path, &extension).await,
+            "database" => Self::extract_database_content(path).await,
+            "markup" => Self::extract_markup_content(path).await,
+            "code" => Self::extract_code_content(path).await,
+            "archive" => Self::extract_archive_content(path, &extension, settings).await,
+            "audio" => Self::extract_audio_content(path, &extension).await,
+            "video" => Self::extract_video_content(path).await,
+            "email" => Self::extract_email_content(path, &extension).await,
+            "subtitles" => Self::extract_subtitle_content(path).await,
             _ => Self::extract_generic_content(path).await,
+        }?;
+        content.metadata.mime_type = sniffed.mime_type;
+        Ok(content)
+    }
+
+    /// The extractor for files with `extension`, one of `EXTRACTORS` or `generic`
+    fn extractor_for(extension: &str) -> &'static str {
+        match extension {
+            "pdf" => "pdf",
+            "txt" | "md" | "readme" | "log" | "yaml" | "yml" | "toml" | "ini" | "cfg" => "text",
+            "jpg" | "jpeg" | "png" | "tiff" | "tif" | "bmp" | "gif" | "webp" | "svg" | "ico"
+            | "heic" | "heif" | "avif" | "cr2" | "nef" | "arw" | "dng" => "image",
+            "odt" | "ods" | "odp" | "pages" | "numbers" | "key" => "office",
+            "doc" | "docx" | "rtf" => "document",
+            "xls" | "xlsx" => "spreadsheet",
+            "ppt" | "pptx" => "presentation",
+            "json" | "geojson" => "json",
+            "csv" | "tsv" => "csv",
+            "parquet" | "arrow" | "feather" | "ipc" => "dataset",
+            "sqlite" | "sqlite3" | "db" | "db3" => "database",
+            "xml" | "html" | "htm" | "xhtml" => "markup",
+            "js" | "ts" | "jsx" | "tsx" | "mjs" | "cjs" | "py" | "rs" | "java" | "cpp" | "cc" | "hpp" | "c" | "h" | "css" | "scss" | "sass" | "go" | "php" | "rb" | "swift" | "kt" | "dart" | "vue" | "sql" | "sh" | "bash" | "zsh" | "fish" => "code",
+            "zip" | "tar" | "gz" | "rar" | "7z" => "archive",
+            "mp3" | "wav" | "flac" | "m4a" | "ogg" | "opus" => "audio",
+            "mp4" | "avi" | "mkv" | "mov" | "wmv" | "webm" => "video",
+            "eml" | "msg" | "mbox" => "email",
+            "srt" | "vtt" => "subtitles",
+            _ => "generic",
         }
     }
 
-    async fn extract_pdf_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+    /// Name and size only, for files whose extractor is turned off
+    async fn extract_file_info(path: &Path) -> Result<ExtractedContent> {
+        let metadata_std = fs::metadata(path).await?;
+        let text = format!(
+            "File: {}\nSize: {} bytes\nExtension: {}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            metadata_std.len(),
+            path.extension().unwrap_or_default().to_string_lossy()
+        );
+
+        Ok(ExtractedContent {
+            text,
+            metadata: ContentMetadata::default(),
+            file_type: "unextracted".to_string(),
+        })
+    }
+
+    async fn extract_pdf_content<P: AsRef<Path>>(path: P, settings: &ExtractionSettings) -> Result<ExtractedContent> {
         let path = path.as_ref();
+        let size = fs::metadata(path).await?.len();
+        if size > stream::PDF_LIMIT {
+            return Ok(ExtractedContent {
+                text: format!(
+                    "PDF file: {}\nSize: {}\nToo large to read for text",
+                    path.file_name().unwrap_or_default().to_string_lossy(),
+                    stream::megabytes(size),
+                ),
+                metadata: ContentMetadata::default(),
+                file_type: "pdf".to_string(),
+            });
+        }
         let bytes = fs::read(path).await?;
         
         // Try pdf-extract first, fallback to basic file info if it fails
-        match pdf_extract::extract_text_from_mem(&bytes) {
-            Ok(text) => {
-                let mut metadata = ContentMetadata::default();
+        match Self::pdf_text(&bytes, settings.pdf_max_pages) {
+            Ok((mut text, pages)) => {
+                let mut metadata = ContentMetadata {
+                    page_count: Some(pages),
+                    ..Default::default()
+                };
                 
-                // Try to extract PDF metadata using lopdf (temporarily simplified)
-                if let Ok(doc) = lopdf::Document::load_mem(&bytes) {
-                    // Get page count
-                    metadata.page_count = Some(doc.get_pages().len() as u32);
+                // Scans are images of pages, so their text has to be recognized
+                let ocr = &settings.ocr;
+                if ocr.enabled && ocr.scanned_pdfs && ocr::is_scanned(&text, metadata.page_count.unwrap_or(1)) {
+                    match ocr::recognize_pdf(path, ocr).await {
+                        Ok(recognized) if !recognized.is_empty() => {
+                            text = format!("{}\n\n{}", text.trim(), recognized);
+                            metadata.ocr_used = true;
+                        }
+                        Ok(_) => {}
+                        Err(e) => tracing::debug!("No OCR for scanned PDF {}: {}", path.display(), e),
+                    }
                 }
                 
                 // Count words
                 metadata.word_count = Some(text.split_whitespace().count() as u32);
+                if pages > settings.pdf_max_pages {
+                    text.push_str(&format!("\n\n[Only the first {} of {} pages indexed]", settings.pdf_max_pages, pages));
+                }
                 
                 Ok(ExtractedContent {
                     text: text.trim().to_string(),
@@ -127,12 +368,31 @@ impl ContentExtractor {
         }
     }
 
+    /// The text of the first `max_pages` pages of a PDF, and how many pages it has
+    fn pdf_text(bytes: &[u8], max_pages: u32) -> Result<(String, u32), pdf_extract::OutputError> {
+        let mut doc = pdf_extract::Document::load_mem(bytes)?;
+        // Many PDFs are encrypted with an empty password just to set permissions
+        if doc.is_encrypted() {
+            doc.decrypt("")?;
+        }
+        let pages = doc.get_pages().len() as u32;
+        let mut text = String::new();
+        for page in 1..=pages.min(max_pages) {
+            let mut output = pdf_extract::PlainTextOutput::new(&mut text);
+            pdf_extract::output_doc_page(&doc, &mut output, page)?;
+        }
+        Ok((text, pages))
+    }
+
     async fn extract_text_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
         let path = path.as_ref();
-        let text = fs::read_to_string(path).await?;
+        let prefix = stream::read_prefix(path, stream::TEXT_LIMIT).await?;
+        let mut text = prefix.to_utf8()?;
         
-        let mut metadata = ContentMetadata::default();
-        metadata.word_count = Some(text.split_whitespace().count() as u32);
+        let mut metadata = ContentMetadata {
+            word_count: Some(Self::word_count(path, &prefix, &text).await?),
+            ..Default::default()
+        };
         
         // Try to detect language (simple heuristic)
         metadata.language = Self::detect_language(&text);
@@ -143,6 +403,7 @@ impl ContentExtractor {
                 metadata.title = Some(first_line.trim_start_matches('#').trim().to_string());
             }
         }
+        text.extend(prefix.truncation_note());
 
         Ok(ExtractedContent {
             text,
@@ -151,44 +412,50 @@ impl ContentExtractor {
         })
     }
 
-    async fn extract_image_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+    /// The language packs the OCR engine has installed
+    pub async fn installed_ocr_languages(settings: &ExtractionSettings) -> Result<Vec<String>> {
+        ocr::installed_languages(&settings.ocr).await
+    }
+
+    async fn extract_image_content<P: AsRef<Path>>(path: P, settings: &ExtractionSettings) -> Result<ExtractedContent> {
         let path = path.as_ref();
-        let bytes = fs::read(path).await?;
+        let bytes = stream::read_prefix(path, stream::IMAGE_HEADER_LIMIT).await?.bytes;
         
         let mut metadata = ContentMetadata::default();
         let mut text = String::new();
         
-        // Try to open image and get dimensions
-        if let Ok(img) = image::load_from_memory(&bytes) {
-            metadata.dimensions = Some((img.width(), img.height()));
-            text.push_str(&format!("Image dimensions: {}x{}\n", img.width(), img.height()));
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let is_raw = photo::RAW_EXTENSIONS.contains(&extension.as_str());
+        
+        // Read from the header, so phone photos in HEIC and camera RAW files get a size too
+        if let Some((width, height)) = photo::dimensions(&bytes, is_raw) {
+            metadata.dimensions = Some((width, height));
+            text.push_str(&format!("Image dimensions: {}x{}\n", width, height));
         }
         
-        // Try to extract EXIF data (temporarily disabled for compilation)
-        // TODO: Re-enable EXIF extraction once compatibility issues are resolved
-        /*
-        if let Ok(exif_reader) = exif::Reader::new() {
-            if let Ok(exif_data) = exif_reader.read_from_container(&mut std::io::Cursor::new(&bytes)) {
-                let mut exif_json = serde_json::Map::new();
-                
-                for field in exif_data.fields() {
-                    let tag_name = format!("{}", field.tag);
-                    let value_str = field.display_value().to_string();
-                    exif_json.insert(tag_name, serde_json::Value::String(value_str.clone()));
-                    
-                    // Add important EXIF data to text for searching
-                    if field.tag == exif::Tag::DateTime || 
-                       field.tag == exif::Tag::Make || 
-                       field.tag == exif::Tag::Model ||
-                       field.tag == exif::Tag::Software {
-                        text.push_str(&format!("{}: {}\n", field.tag, value_str));
-                    }
+        // Photos are searched for by camera and date, and by place when that is allowed
+        if settings.exif.enabled {
+            if let Some(photo) = photo::PhotoInfo::read(&bytes, &settings.exif) {
+                text.push_str(&photo.summary());
+                metadata.exif_data = Some(photo.to_json());
+            }
+        }
+        
+        // Screenshots, scans and photos of documents become searchable by the text in them
+        let large_enough = metadata.dimensions.is_none_or(|(width, height)| width >= ocr::MIN_OCR_SIDE && height >= ocr::MIN_OCR_SIDE);
+        let tesseract_reads = !is_raw && !matches!(extension.as_str(), "svg" | "heic" | "heif" | "avif");
+        if settings.ocr.enabled && large_enough && tesseract_reads {
+            match ocr::recognize(path, &settings.ocr).await {
+                Ok(recognized) if !recognized.is_empty() => {
+                    metadata.word_count = Some(recognized.split_whitespace().count() as u32);
+                    metadata.language = Self::detect_language(&recognized);
+                    metadata.ocr_used = true;
+                    text.push_str(&format!("Text in image:\n{}\n", recognized));
                 }
-                
-                metadata.exif_data = Some(serde_json::Value::Object(exif_json));
+                Ok(_) => {}
+                Err(e) => tracing::debug!("No OCR for {}: {}", path.display(), e),
             }
         }
-        */
         
         // Generate descriptive text for the image
         if text.is_empty() {
@@ -202,6 +469,58 @@ impl ContentExtractor {
         })
     }
 
+    async fn extract_email_content(path: &Path, extension: &str) -> Result<ExtractedContent> {
+        let mut metadata = ContentMetadata::default();
+        let text = match extension {
+            "mbox" => {
+                let mbox = path.to_path_buf();
+                let (emails, count) = tokio::task::spawn_blocking(move || -> Result<_> {
+                    Ok(email::parse_mbox(std::io::BufReader::new(std::fs::File::open(mbox)?)))
+                }).await??;
+                metadata.email = Some(serde_json::json!({ "message_count": count, "messages": emails }));
+                let mut text = emails.iter().map(|email| email.to_text()).collect::<Vec<_>>().join("\n\n");
+                if count > emails.len() {
+                    text.push_str(&format!("\n\n[{} more messages not indexed]", count - emails.len()));
+                }
+                text
+            }
+            "msg" => {
+                // Outlook messages are compound files read in place, attachments left on disk
+                let file = std::fs::File::open(path)?;
+                let Ok(email) = tokio::task::spawn_blocking(move || email::parse_msg(file)).await? else {
+                    return Self::extract_generic_content(path).await;
+                };
+                Self::email_metadata(&mut metadata, &email)?;
+                email.to_text()
+            }
+            _ => {
+                // Headers and body come before any large attachments
+                let bytes = stream::read_prefix(path, stream::EMAIL_LIMIT).await?.bytes;
+                let Some(email) = email::Email::parse(&bytes) else {
+                    return Self::extract_generic_content(path).await;
+                };
+                Self::email_metadata(&mut metadata, &email)?;
+                email.to_text()
+            }
+        };
+        metadata.word_count = Some(text.split_whitespace().count() as u32);
+        metadata.language = Self::detect_language(&text);
+
+        Ok(ExtractedContent {
+            text,
+            metadata,
+            file_type: "email".to_string(),
+        })
+    }
+
+    fn email_metadata(metadata: &mut ContentMetadata, email: &email::Email) -> Result<()> {
+        metadata.title = email.subject.clone();
+        metadata.author = email.from.clone();
+        metadata.created_date = email.date.clone();
+        metadata.email = Some(serde_json::to_value(email)?);
+        Ok(())
+    }
+
     async fn extract_document_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
         // For now, treat as binary and extract basic info
         // In a full implementation, you'd use libraries like docx-rs or similar
@@ -223,8 +542,54 @@ impl ContentExtractor {
         })
     }
 
+    async fn extract_office_content<P: AsRef<Path>>(path: P, extension: &str) -> Result<ExtractedContent> {
+        let path = path.as_ref();
+        let header = stream::read_prefix(path, 4).await?;
+        
+        // `.key` is also a private key, and iWork documents may be saved as folders instead of zips
+        if !office::is_zip(&header.bytes) {
+            return Self::extract_generic_content(path).await;
+        }
+        
+        // The archive is read in place, one entry at a time
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let is_opendocument = extension.starts_with("od");
+        let document = tokio::task::spawn_blocking(move || match is_opendocument {
+            true => office::read_opendocument(file),
+            false => office::read_iwork(file),
+        }).await??;
+        
+        let metadata = ContentMetadata {
+            title: document.title,
+            author: document.author,
+            created_date: document.created,
+            modified_date: document.modified,
+            page_count: document.pages,
+            language: document.language,
+            subject: document.subject,
+            keywords: document.keywords,
+            word_count: Some(document.text.split_whitespace().count() as u32),
+            ..Default::default()
+        };
+        
+        let file_type = match extension {
+            "ods" | "numbers" => "spreadsheet",
+            "odp" | "key" => "presentation",
+            _ => "document",
+        };
+
+        Ok(ExtractedContent {
+            text: document.text,
+            metadata,
+            file_type: file_type.to_string(),
+        })
+    }
+
     async fn extract_json_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
         let path = path.as_ref();
+        if fs::metadata(path).await?.len() > stream::JSON_LIMIT {
+            return Self::extract_text_content(path).await;
+        }
         let text = fs::read_to_string(path).await?;
         
         let mut metadata = ContentMetadata::default();
@@ -247,53 +612,114 @@ impl ContentExtractor {
         }
     }
 
-    async fn extract_csv_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+    async fn extract_csv_content<P: AsRef<Path>>(path: P, settings: &ExtractionSettings) -> Result<ExtractedContent> {
         let path = path.as_ref();
-        let text = fs::read_to_string(path).await?;
+        let is_tsv = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("tsv"));
         
         let mut metadata = ContentMetadata::default();
-        let lines: Vec<&str> = text.lines().collect();
         
-        // Extract header and some sample data for searching
-        let mut searchable_text = String::new();
+        // Tables run to millions of rows, so they are read a record at a time
+        let file_path = path.to_path_buf();
+        let sample_rows = settings.csv_sample_rows;
+        let summary = tokio::task::spawn_blocking(move || -> Result<_> {
+            let mut reader = std::io::BufReader::new(std::fs::File::open(file_path)?);
+            let delimiter = match is_tsv {
+                true => b'\t',
+                false => {
+                    let start = String::from_utf8_lossy(std::io::BufRead::fill_buf(&mut reader)?);
+                    table::sniff_delimiter(start.lines().next().unwrap_or_default())
+                }
+            };
+            table::summarize(reader, delimiter, sample_rows)
+        }).await??;
         
-        if !lines.is_empty() {
-            searchable_text.push_str("CSV Headers: ");
-            searchable_text.push_str(lines[0]);
-            searchable_text.push('\n');
-            
-            // Add a few sample rows
-            for (i, line) in lines.iter().skip(1).take(5).enumerate() {
-                searchable_text.push_str(&format!("Row {}: {}\n", i + 1, line));
-            }
+        let searchable_text = summary.to_text();
+        metadata.table = Some(serde_json::to_value(&summary)?);
+        metadata.word_count = Some(searchable_text.split_whitespace().count() as u32);
+
+        Ok(ExtractedContent {
+            text: searchable_text,
+            metadata,
+            file_type: "csv".to_string(),
+        })
+    }
+
+    async fn extract_dataset_content<P: AsRef<Path>>(path: P, extension: &str) -> Result<ExtractedContent> {
+        let file = std::fs::File::open(path.as_ref())?;
+        let is_parquet = extension == "parquet";
+        
+        let mut metadata = ContentMetadata::default();
+        
+        // Only the Parquet footer is read; Arrow files are walked a batch at a time
+        let summary = tokio::task::spawn_blocking(move || match is_parquet {
+            true => columnar::read_parquet(file),
+            false => columnar::read_arrow(file),
+        }).await??;
+        
+        let searchable_text = summary.to_text();
+        metadata.dataset = Some(serde_json::to_value(&summary)?);
+        metadata.word_count = Some(searchable_text.split_whitespace().count() as u32);
+
+        Ok(ExtractedContent {
+            text: searchable_text,
+            metadata,
+            file_type: "dataset".to_string(),
+        })
+    }
+
+    async fn extract_database_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+        let path = path.as_ref();
+        
+        // Other formats use `.db` too, so only a SQLite header gets the database opened
+        let mut header = [0u8; 16];
+        let is_sqlite = match fs::File::open(path).await {
+            Ok(mut file) => file.read_exact(&mut header).await.is_ok() && sqlite::is_sqlite(&header),
+            Err(_) => false,
+        };
+        if !is_sqlite {
+            return Self::extract_generic_content(path).await;
         }
         
+        let mut metadata = ContentMetadata::default();
+        
+        let schema = sqlite::inspect(path).await?;
+        let searchable_text = schema.to_text();
+        metadata.database = Some(serde_json::to_value(&schema)?);
         metadata.word_count = Some(searchable_text.split_whitespace().count() as u32);
 
         Ok(ExtractedContent {
             text: searchable_text,
             metadata,
-            file_type: "csv".to_string(),
+            file_type: "database".to_string(),
         })
     }
 
     async fn extract_markup_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
         let path = path.as_ref();
-        let text = fs::read_to_string(path).await?;
+        let prefix = stream::read_prefix(path, stream::MARKUP_LIMIT).await?;
+        let text = prefix.to_utf8()?;
         
         let mut metadata = ContentMetadata::default();
         
-        // Basic HTML/XML content extraction (remove tags)
-        let content = Self::strip_html_tags(&text);
-        metadata.word_count = Some(content.split_whitespace().count() as u32);
-        
-        // Try to extract title from HTML
-        if let Some(title_start) = text.find("<title>") {
-            if let Some(title_end) = text[title_start..].find("</title>") {
-                let title = &text[title_start + 7..title_start + title_end];
-                metadata.title = Some(title.trim().to_string());
+        let is_xml = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("xml"));
+        let mut content = match is_xml {
+            true => html::xml_text(&text),
+            false => {
+                let page = html::HtmlPage::parse(&text);
+                metadata.title = page.title.clone();
+                metadata.subject = page.description.clone();
+                metadata.author = page.author.clone();
+                metadata.keywords = page.keywords.clone();
+                metadata.language = page.language.clone();
+                metadata.html = Some(serde_json::to_value(&page)?);
+                page.to_text()
             }
+        };
+        metadata.word_count = Some(content.split_whitespace().count() as u32);
+        if metadata.language.is_none() {
+            metadata.language = Self::detect_language(&content);
         }
+        content.extend(prefix.truncation_note());
 
         Ok(ExtractedContent {
             text: content,
@@ -304,13 +730,27 @@ impl ContentExtractor {
 
     async fn extract_code_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
         let path = path.as_ref();
-        let text = fs::read_to_string(path).await?;
-        
-        let mut metadata = ContentMetadata::default();
-        metadata.word_count = Some(text.split_whitespace().count() as u32);
+        let prefix = stream::read_prefix(path, stream::CODE_LIMIT).await?;
+        let text = prefix.to_utf8()?;
+        
+        let mut metadata = ContentMetadata {
+            word_count: Some(Self::word_count(path, &prefix, &text).await?),
+            ..Default::default()
+        };
+        
+        // The outline goes first, so symbol names and docs are searchable and lead the AI prompt
+        let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+        let source = text.clone();
+        let outline = tokio::task::spawn_blocking(move || code::outline(&extension, &source)).await?;
+        let mut searchable_text = match outline {
+            Some(outline) => {
+                metadata.code = Some(serde_json::to_value(&outline)?);
+                format!("{}\n{}", outline.summary(), text)
+            }
+            None => text,
+        };
         
-        // Extract comments and function names for better searchability
-        let mut searchable_text = text.clone();
+        searchable_text.extend(prefix.truncation_note());
         
         // Add file extension as context
         if let Some(ext) = path.extension() {
@@ -327,10 +767,13 @@ impl ContentExtractor {
     async fn extract_generic_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
         let path = path.as_ref();
         
-        // Try to read as text first
-        if let Ok(text) = fs::read_to_string(path).await {
+        // Try to read as text first, judging by the start of the file. Bytes further in may
+        // still not be UTF-8, and then the file is recorded as binary after all.
+        if let Ok(text) = stream::read_prefix(path, stream::SNIFF_LIMIT).await?.to_utf8() {
             if text.is_ascii() || text.chars().all(|c| !c.is_control() || c.is_whitespace()) {
-                return Self::extract_text_content(path).await;
+                if let Ok(content) = Self::extract_text_content(path).await {
+                    return Ok(content);
+                }
             }
         }
         
@@ -384,22 +827,6 @@ impl ContentExtractor {
         }
     }
 
-    fn strip_html_tags(html: &str) -> String {
-        let mut result = String::new();
-        let mut in_tag = false;
-        
-        for ch in html.chars() {
-            match ch {
-                '<' => in_tag = true,
-                '>' => in_tag = false,
-                _ if !in_tag => result.push(ch),
-                _ => {}
-            }
-        }
-        
-        result.trim().to_string()
-    }
-
     async fn extract_spreadsheet_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
         let path = path.as_ref();
         let metadata_std = fs::metadata(path).await?;
@@ -438,17 +865,30 @@ impl ContentExtractor {
         })
     }
 
-    async fn extract_archive_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+    async fn extract_archive_content<P: AsRef<Path>>(path: P, extension: &str, settings: &ExtractionSettings) -> Result<ExtractedContent> {
         let path = path.as_ref();
         let metadata_std = fs::metadata(path).await?;
         
         let metadata = ContentMetadata::default();
-        let text = format!(
+        let mut text = format!(
             "Archive file: {}\nSize: {} bytes\nExtension: {}\nCompressed archive containing multiple files",
             path.file_name().unwrap_or_default().to_string_lossy(),
             metadata_std.len(),
             path.extension().unwrap_or_default().to_string_lossy()
         );
+        
+        // Zips are listed by the names of the files in them, read from the central directory
+        if extension == "zip" && settings.archive_max_depth > 0 {
+            let zip_path = path.to_path_buf();
+            let depth = settings.archive_max_depth;
+            let listing = tokio::task::spawn_blocking(move || {
+                archive::list_zip(std::io::BufReader::new(std::fs::File::open(zip_path)?), depth)
+            });
+            match listing.await? {
+                Ok(listing) => text = format!("{}\n\n{}", text, listing.to_text()),
+                Err(e) => tracing::debug!("Could not list {}: {}", path.display(), e),
+            }
+        }
 
         Ok(ExtractedContent {
             text,
@@ -457,17 +897,30 @@ impl ContentExtractor {
         })
     }
 
-    async fn extract_audio_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+    async fn extract_audio_content<P: AsRef<Path>>(path: P, extension: &str) -> Result<ExtractedContent> {
         let path = path.as_ref();
         let metadata_std = fs::metadata(path).await?;
         
-        let metadata = ContentMetadata::default();
-        let text = format!(
-            "Audio file: {}\nSize: {} bytes\nExtension: {}\nAudio content - music, speech, or sound recording",
+        let mut metadata = ContentMetadata::default();
+        let mut text = format!(
+            "Audio file: {}\nSize: {} bytes\nExtension: {}\nAudio content - music, speech, or sound recording\n",
             path.file_name().unwrap_or_default().to_string_lossy(),
             metadata_std.len(),
             path.extension().unwrap_or_default().to_string_lossy()
         );
+        
+        // Music libraries are searched by tags, which file names often leave out
+        let file = std::fs::File::open(path)?;
+        let extension = extension.to_string();
+        match tokio::task::spawn_blocking(move || audio::AudioTags::read(file, &extension)).await? {
+            Ok(tags) => {
+                text.push_str(&tags.to_text());
+                metadata.title = tags.title.clone();
+                metadata.author = tags.artist.clone();
+                metadata.audio = Some(serde_json::to_value(&tags)?);
+            }
+            Err(e) => tracing::debug!("No audio tags for {}: {}", path.display(), e),
+        }
 
         Ok(ExtractedContent {
             text,
@@ -480,13 +933,28 @@ impl ContentExtractor {
         let path = path.as_ref();
         let metadata_std = fs::metadata(path).await?;
         
-        let metadata = ContentMetadata::default();
-        let text = format!(
+        let mut metadata = ContentMetadata::default();
+        let mut text = format!(
             "Video file: {}\nSize: {} bytes\nExtension: {}\nVideo content with visual and audio elements",
             path.file_name().unwrap_or_default().to_string_lossy(),
             metadata_std.len(),
             path.extension().unwrap_or_default().to_string_lossy()
         );
+        
+        // The dialogue of subtitles next to the video, so searching for a quote finds the video too
+        let video = path.to_path_buf();
+        let subtitle_files = tokio::task::spawn_blocking(move || subtitles::subtitles_for(&video)).await?;
+        for subtitle in &subtitle_files {
+            let cues = subtitles::parse(&stream::read_prefix(subtitle, stream::SUBTITLE_LIMIT).await?.to_string_lossy());
+            text.push_str(&format!(
+                "\n\nDialogue ({}):\n{}",
+                subtitle.file_name().unwrap_or_default().to_string_lossy(),
+                subtitles::to_text(&cues),
+            ));
+        }
+        if !subtitle_files.is_empty() {
+            metadata.subtitles = Some(serde_json::json!({ "files": subtitle_files }));
+        }
 
         Ok(ExtractedContent {
             text,
@@ -495,15 +963,48 @@ impl ContentExtractor {
         })
     }
 
-    fn detect_language(text: &str) -> Option<String> {
-        // Very basic language detection - in production use a proper library
-        let sample = text.chars().take(1000).collect::<String>();
+    async fn extract_subtitle_content<P: AsRef<Path>>(path: P) -> Result<ExtractedContent> {
+        let path = path.as_ref();
+        // Older subtitles are rarely UTF-8, and a wrong accent should not lose the whole file
+        let content = stream::read_prefix(path, stream::SUBTITLE_LIMIT).await?.to_string_lossy();
+        let cues = subtitles::parse(&content);
         
-        if sample.chars().any(|c| c as u32 > 127) {
-            Some("non-english".to_string())
-        } else {
-            Some("english".to_string())
+        let mut metadata = ContentMetadata::default();
+        let mut text = String::new();
+        
+        let subtitle = path.to_path_buf();
+        let video = tokio::task::spawn_blocking(move || subtitles::video_for(&subtitle)).await?;
+        if let Some(video) = &video {
+            text.push_str(&format!("Subtitles for: {}\n\n", video.file_name().unwrap_or_default().to_string_lossy()));
+            metadata.language = subtitles::language(path, video);
         }
+        text.push_str(&subtitles::to_text(&cues));
+        
+        metadata.word_count = Some(cues.iter().map(|cue| cue.text.split_whitespace().count() as u32).sum());
+        metadata.subtitles = Some(serde_json::json!({
+            "video": video,
+            "cue_count": cues.len(),
+            "last_cue": cues.last().map(|cue| &cue.start),
+        }));
+
+        Ok(ExtractedContent {
+            text,
+            metadata,
+            file_type: "subtitles".to_string(),
+        })
+    }
+
+    /// Words in the text, or in the whole file when only its start was read
+    async fn word_count(path: &Path, prefix: &stream::Prefix, text: &str) -> Result<u32> {
+        let words = match prefix.is_truncated() {
+            true => stream::count_words(path).await?,
+            false => text.split_whitespace().count() as u64,
+        };
+        Ok(words.min(u32::MAX as u64) as u32)
+    }
+
+    fn detect_language(text: &str) -> Option<String> {
+        crate::language::detect_language(text)
     }
 }
 
@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// Images smaller than this on either side, such as icons, are not worth recognizing
+pub const MIN_OCR_SIDE: u32 = 32;
+
+/// A PDF with less text than this per page is taken for a scan
+const MIN_TEXT_CHARS_PER_PAGE: usize = 50;
+
+/// Resolution PDF pages are rendered at for recognition; tesseract works best from 300 DPI
+const PDF_RENDER_DPI: u32 = 300;
+
+/// Text recognition in images with the `tesseract` command, which has to be installed along
+/// with the language packs used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OcrSettings {
+    pub enabled: bool,
+    /// Tesseract language packs to recognize with, e.g. `eng` or `deu`; several are tried at once
+    pub languages: Vec<String>,
+    /// The tesseract executable, when it is not on the PATH
+    pub tesseract_path: Option<String>,
+    /// Recognize the pages of PDFs that have next to no text, such as scans. Needs poppler's
+    /// `pdftoppm` to render the pages. Off by default, since every page takes seconds.
+    pub scanned_pdfs: bool,
+    /// Pages of a scanned PDF recognized at most, from the first
+    pub max_pdf_pages: u32,
+    /// The pdftoppm executable, when it is not on the PATH
+    pub pdftoppm_path: Option<String>,
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            languages: vec!["eng".to_string()],
+            tesseract_path: None,
+            scanned_pdfs: false,
+            max_pdf_pages: 5,
+            pdftoppm_path: None,
+        }
+    }
+}
+
+impl OcrSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.languages.is_empty() {
+            return Err(anyhow!("At least one OCR language is needed"));
+        }
+        // Tesseract names packs like `eng`, `chi_sim` or `script/Latin`
+        let valid = |language: &String| !language.is_empty()
+            && language.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '/');
+        if let Some(language) = self.languages.iter().find(|language| !valid(language)) {
+            return Err(anyhow!("Invalid OCR language '{}'", language));
+        }
+        if self.max_pdf_pages == 0 {
+            return Err(anyhow!("OCR of scanned PDFs needs at least one page"));
+        }
+        Ok(())
+    }
+
+    fn command(&self) -> Command {
+        Command::new(self.tesseract_path.as_deref().unwrap_or("tesseract"))
+    }
+}
+
+/// The text tesseract recognizes in the image at `path`, trimmed
+pub async fn recognize(path: &Path, settings: &OcrSettings) -> Result<String> {
+    let output = settings.command()
+        .arg(path)
+        .arg("stdout")
+        .args(["-l", &settings.languages.join("+")])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run tesseract: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether a PDF's extracted text is too little for its pages to be anything but images
+pub fn is_scanned(text: &str, pages: u32) -> bool {
+    let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    chars < MIN_TEXT_CHARS_PER_PAGE * pages.max(1) as usize
+}
+
+/// The text recognized on the first pages of the PDF at `path`, up to the settings' page cap,
+/// with a blank line between pages
+pub async fn recognize_pdf(path: &Path, settings: &OcrSettings) -> Result<String> {
+    let pages_dir = std::env::temp_dir().join(format!("metamind-ocr-{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&pages_dir).await?;
+    let recognized = recognize_pdf_pages(path, &pages_dir, settings).await;
+    if let Err(e) = tokio::fs::remove_dir_all(&pages_dir).await {
+        tracing::warn!("Failed to remove rendered pages in {}: {}", pages_dir.display(), e);
+    }
+    recognized
+}
+
+async fn recognize_pdf_pages(path: &Path, pages_dir: &Path, settings: &OcrSettings) -> Result<String> {
+    let output = Command::new(settings.pdftoppm_path.as_deref().unwrap_or("pdftoppm"))
+        .args(["-r", &PDF_RENDER_DPI.to_string(), "-png", "-f", "1", "-l", &settings.max_pdf_pages.to_string()])
+        .arg(path)
+        .arg(pages_dir.join("page"))
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run pdftoppm: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Pdftoppm failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    // Named page-1.png or page-01.png and so on, padded to the same width
+    let mut pages = Vec::new();
+    let mut entries = tokio::fs::read_dir(pages_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        pages.push(entry.path());
+    }
+    pages.sort();
+
+    let mut text = Vec::with_capacity(pages.len());
+    for page in pages {
+        let recognized = recognize(&page, settings).await?;
+        if !recognized.is_empty() {
+            text.push(recognized);
+        }
+    }
+    Ok(text.join("\n\n"))
+}
+
+/// The language packs tesseract has installed
+pub async fn installed_languages(settings: &OcrSettings) -> Result<Vec<String>> {
+    let output = settings.command()
+        .arg("--list-langs")
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run tesseract: {}", e))?;
+    // Older versions print the list to stderr
+    let listing = match output.stdout.is_empty() {
+        true => output.stderr,
+        false => output.stdout,
+    };
+    Ok(parse_language_list(&String::from_utf8_lossy(&listing)))
+}
+
+/// Skip the `List of available languages in "..." (3):` header line
+fn parse_language_list(listing: &str) -> Vec<String> {
+    listing.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("List of available languages"))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_language_list() {
+        let listing = "List of available languages in \"/usr/share/tesseract-ocr/5/tessdata/\" (3):\neng\nosd\nchi_sim\n";
+        assert_eq!(parse_language_list(listing), vec!["eng", "osd", "chi_sim"]);
+    }
+
+    #[test]
+    fn test_is_scanned() {
+        assert!(is_scanned("", 3));
+        assert!(is_scanned("  \n\x0c 12 \n", 1));
+        assert!(!is_scanned(&"word ".repeat(40), 3));
+        assert!(is_scanned(&"word ".repeat(40), 10));
+    }
+
+    #[test]
+    fn test_validate_languages() {
+        let settings = |languages: &[&str]| OcrSettings {
+            languages: languages.iter().map(|language| language.to_string()).collect(),
+            ..Default::default()
+        };
+        assert!(settings(&["eng", "chi_sim", "script/Latin"]).validate().is_ok());
+        assert!(settings(&[]).validate().is_err());
+        assert!(settings(&["eng+deu"]).validate().is_err());
+        assert!(settings(&["-l"]).validate().is_err());
+    }
+}
@@ -0,0 +1,463 @@
+use std::io::{BufReader, Read, Seek};
+
+use anyhow::{Result, anyhow, bail};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use zip::ZipArchive;
+
+/// The first bytes of a zip file; iWork documents saved as folders or by iWork '09 are not zips
+const ZIP_HEADER: &[u8] = b"PK\x03\x04";
+
+/// Elements of an OpenDocument body holding its text, a line each; text outside them is only indentation
+const ODF_PARAGRAPHS: &[&[u8]] = &[b"text:p", b"text:h"];
+
+/// Elements whose text is not part of the document as it reads, such as deleted tracked changes
+const ODF_HIDDEN: &[&[u8]] = &[b"text:tracked-changes", b"office:forms", b"text:sequence-decls"];
+
+/// Message types of iWork archives holding text: paragraphs of `TSWP.StorageArchive` and the
+/// cell strings of a `TST.TableDataList`, the same in Pages, Numbers and Keynote
+const IWA_STORAGE: u32 = 2001;
+const IWA_TABLE_DATA_LIST: u32 = 6005;
+
+/// Most bytes an iWork archive is read or decompressed to, so a small package can't expand without bound
+const MAX_IWA_BYTES: usize = 64 * 1024 * 1024;
+
+/// Most spaces one `text:s` stands for; the count comes straight from the document
+const MAX_ODF_SPACES: usize = 1024;
+
+/// What an OpenDocument or iWork file says about itself, and its text
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OfficeDocument {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    pub author: Option<String>,
+    pub keywords: Vec<String>,
+    pub language: Option<String>,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    /// Pages of a text document, sheets of a spreadsheet or slides of a presentation
+    pub pages: Option<u32>,
+    pub text: String,
+}
+
+/// Whether the bytes start like a zip file
+pub fn is_zip(header: &[u8]) -> bool {
+    header.starts_with(ZIP_HEADER)
+}
+
+/// Read an OpenDocument file (`.odt`, `.ods`, `.odp`): its text from `content.xml`, the rest from `meta.xml`
+pub fn read_opendocument(reader: impl Read + Seek) -> Result<OfficeDocument> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut document = OfficeDocument::default();
+
+    if let Ok(meta) = archive.by_name("meta.xml") {
+        read_odf_meta(BufReader::new(meta), &mut document)?;
+    }
+    let content = archive.by_name("content.xml").map_err(|_| anyhow!("No content.xml in the document"))?;
+    let (text, sections) = read_odf_content(BufReader::new(content))?;
+    document.text = text;
+    // Text documents count pages in their metadata; sheets and slides are counted here
+    if document.pages.is_none() && sections > 0 {
+        document.pages = Some(sections);
+    }
+    Ok(document)
+}
+
+fn read_odf_meta(reader: impl std::io::BufRead, document: &mut OfficeDocument) -> Result<()> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(element) => current = Some(element.name().as_ref().to_vec()),
+            Event::Empty(element) if element.name().as_ref() == b"meta:document-statistic" => {
+                document.pages = attribute(&element, "meta:page-count")?.and_then(|pages| pages.parse().ok());
+            }
+            Event::Text(text) => {
+                let value = text.unescape()?.trim().to_string();
+                if value.is_empty() {
+                    continue;
+                }
+                match current.as_deref() {
+                    Some(b"dc:title") => document.title = Some(value),
+                    Some(b"dc:subject") | Some(b"dc:description") => {
+                        document.subject.get_or_insert(value);
+                    }
+                    Some(b"meta:keyword") => document.keywords.push(value),
+                    // The last editor is only the author when nobody is named as the creator
+                    Some(b"meta:initial-creator") => document.author = Some(value),
+                    Some(b"dc:creator") => {
+                        document.author.get_or_insert(value);
+                    }
+                    Some(b"dc:language") => document.language = Some(value),
+                    Some(b"meta:creation-date") => document.created = Some(value),
+                    Some(b"dc:date") => document.modified = Some(value),
+                    _ => {}
+                }
+            }
+            Event::End(_) => current = None,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+/// The body's text, a line per paragraph, with sheet and slide names; and how many sheets or slides there are
+fn read_odf_content(reader: impl std::io::BufRead) -> Result<(String, u32)> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let mut sections = 0;
+    let mut hidden = 0usize;
+    let mut paragraphs = 0usize;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(element) => {
+                let name = element.name();
+                if hidden > 0 || ODF_HIDDEN.contains(&name.as_ref()) {
+                    hidden += 1;
+                    continue;
+                }
+                let section = match name.as_ref() {
+                    b"table:table" => attribute(&element, "table:name")?.map(|name| format!("Sheet: {}", name)),
+                    b"draw:page" => attribute(&element, "draw:name")?.map(|name| format!("Slide: {}", name)),
+                    _ => None,
+                };
+                if ODF_PARAGRAPHS.contains(&name.as_ref()) {
+                    paragraphs += 1;
+                }
+                if let Some(section) = section {
+                    sections += 1;
+                    text.push_str(&section);
+                    text.push('\n');
+                }
+            }
+            Event::Empty(element) if hidden == 0 => match element.name().as_ref() {
+                b"text:s" => {
+                    let spaces = attribute(&element, "text:c")?.and_then(|count| count.parse().ok()).unwrap_or(1);
+                    text.push_str(&" ".repeat(spaces.min(MAX_ODF_SPACES)));
+                }
+                b"text:tab" => text.push('\t'),
+                b"text:line-break" => text.push('\n'),
+                _ => {}
+            },
+            // Runs of whitespace in a paragraph count as one space; `text:s` stands for more
+            Event::Text(fragment) if hidden == 0 && paragraphs > 0 => {
+                let fragment = fragment.unescape()?;
+                let mut words = fragment.split(|c: char| c.is_ascii_whitespace());
+                text.push_str(words.next().unwrap_or_default());
+                for word in words {
+                    if !text.ends_with(' ') {
+                        text.push(' ');
+                    }
+                    text.push_str(word);
+                }
+            }
+            Event::End(element) => {
+                if hidden > 0 {
+                    hidden -= 1;
+                } else if ODF_PARAGRAPHS.contains(&element.name().as_ref()) {
+                    paragraphs = paragraphs.saturating_sub(1);
+                    text.push('\n');
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    let text = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n");
+    Ok((text, sections))
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>> {
+    Ok(match element.try_get_attribute(name)? {
+        Some(attribute) => Some(attribute.unescape_value()?.into_owned()),
+        None => None,
+    })
+}
+
+/// Read a Pages, Numbers or Keynote package. Its text lives in Snappy-compressed protobuf
+/// archives (`Index/*.iwa`), read without the schema by picking out the message types holding text.
+/// Only the text is read: the title and authors are in a binary plist under `Metadata/` and
+/// are left out, so the document's metadata stays empty.
+pub fn read_iwork(reader: impl Read + Seek) -> Result<OfficeDocument> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut names: Vec<String> = archive.file_names()
+        .filter(|name| name.starts_with("Index/") && name.ends_with(".iwa"))
+        .map(str::to_string)
+        .collect();
+    if names.is_empty() {
+        bail!("Not an iWork 2013 or later document");
+    }
+    // The document body comes before slides, stylesheets and the rest
+    names.sort_by_key(|name| (name != "Index/Document.iwa", name.clone()));
+
+    let mut text = String::new();
+    for name in names {
+        let mut data = Vec::new();
+        archive.by_name(&name)?.take(MAX_IWA_BYTES as u64 + 1).read_to_end(&mut data)?;
+        if data.len() > MAX_IWA_BYTES {
+            bail!("{} is larger than {} bytes", name, MAX_IWA_BYTES);
+        }
+        for fragment in iwa_text(&decompress_iwa(&data)?) {
+            text.push_str(&fragment);
+            text.push('\n');
+        }
+    }
+    let text = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n");
+    Ok(OfficeDocument { text, ..Default::default() })
+}
+
+/// Chunks of an IWA file: a zero byte, a 24-bit little-endian length, then Snappy data without framing
+fn decompress_iwa(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = snap::raw::Decoder::new();
+    let mut output = Vec::new();
+    let mut position = 0;
+    while position + 4 <= data.len() {
+        if data[position] != 0 {
+            bail!("Unexpected IWA chunk type {}", data[position]);
+        }
+        let length = u32::from_le_bytes([data[position + 1], data[position + 2], data[position + 3], 0]) as usize;
+        let chunk = data.get(position + 4..position + 4 + length).ok_or_else(|| anyhow!("Truncated IWA chunk"))?;
+        // Checked before decompressing, as the decoder allocates the length the chunk claims
+        if output.len() + snap::raw::decompress_len(chunk)? > MAX_IWA_BYTES {
+            bail!("IWA data decompresses to more than {} bytes", MAX_IWA_BYTES);
+        }
+        output.extend(decoder.decompress_vec(chunk)?);
+        position += 4 + length;
+    }
+    Ok(output)
+}
+
+/// Text of the messages in decompressed IWA data. Each archive is a length-prefixed
+/// `ArchiveInfo` listing the type and length of the messages that follow it.
+fn iwa_text(data: &[u8]) -> Vec<String> {
+    let mut fragments = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let Some(info_length) = varint(data, &mut position) else { break };
+        let Some(end) = position.checked_add(info_length as usize) else { break };
+        let Some(info) = data.get(position..end) else { break };
+        position = end;
+
+        let mut messages = Vec::new();
+        for_each_field(info, |field, value| {
+            if let (2, Wire::Bytes(message_info)) = (field, value) {
+                let (mut kind, mut length) = (0, 0);
+                for_each_field(message_info, |field, value| match (field, value) {
+                    (1, Wire::Varint(value)) => kind = value as u32,
+                    (3, Wire::Varint(value)) => length = value as usize,
+                    _ => {}
+                });
+                messages.push((kind, length));
+            }
+        });
+
+        for (kind, length) in messages {
+            let Some(end) = position.checked_add(length) else { return fragments };
+            let Some(message) = data.get(position..end) else { return fragments };
+            position = end;
+            match kind {
+                IWA_STORAGE => for_each_field(message, |field, value| {
+                    if let (3, Wire::Bytes(text)) = (field, value) {
+                        fragments.push(clean_iwa_text(text));
+                    }
+                }),
+                IWA_TABLE_DATA_LIST => for_each_field(message, |field, value| {
+                    if let (3, Wire::Bytes(entry)) = (field, value) {
+                        for_each_field(entry, |field, value| {
+                            if let (3, Wire::Bytes(text)) = (field, value) {
+                                fragments.push(clean_iwa_text(text));
+                            }
+                        });
+                    }
+                }),
+                _ => {}
+            }
+        }
+    }
+    fragments
+}
+
+/// Paragraph text with the placeholders iWork puts in for attachments, footnotes and the like removed
+fn clean_iwa_text(text: &[u8]) -> String {
+    String::from_utf8_lossy(text)
+        .chars()
+        .filter_map(|c| match c {
+            '\u{2028}' | '\u{2029}' => Some('\n'),
+            '\u{fffc}' => None,
+            c if c.is_control() && c != '\n' && c != '\t' => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Call `f` with the number and value of each field of a protobuf message, stopping at anything malformed
+fn for_each_field<'a>(message: &'a [u8], mut f: impl FnMut(u64, Wire<'a>)) {
+    let mut position = 0;
+    while position < message.len() {
+        let Some(key) = varint(message, &mut position) else { return };
+        let value = match key & 7 {
+            0 => match varint(message, &mut position) {
+                Some(value) => Wire::Varint(value),
+                None => return,
+            },
+            1 | 5 => {
+                position = position.saturating_add(if key & 7 == 1 { 8 } else { 4 });
+                Wire::Fixed
+            }
+            2 => {
+                let Some(length) = varint(message, &mut position) else { return };
+                let Some(end) = position.checked_add(length as usize) else { return };
+                let Some(bytes) = message.get(position..end) else { return };
+                position = end;
+                Wire::Bytes(bytes)
+            }
+            _ => return,
+        };
+        f(key >> 3, value);
+    }
+}
+
+fn varint(data: &[u8], position: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*position)?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+
+    fn zip(files: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    #[test]
+    fn test_read_opendocument() {
+        let meta = br#"<office:document-meta><office:meta>
+            <dc:title>Field notes</dc:title>
+            <meta:initial-creator>Ada</meta:initial-creator>
+            <dc:creator>Grace</dc:creator>
+            <meta:keyword>birds</meta:keyword><meta:keyword>spring</meta:keyword>
+            <dc:language>en-GB</dc:language>
+            <meta:document-statistic meta:page-count="3" meta:word-count="120"/>
+        </office:meta></office:document-meta>"#;
+        let content = br#"<office:document-content><office:body><office:text>
+            <text:sequence-decls><text:sequence-decl text:name="Figure"/></text:sequence-decls>
+            <text:h>Day&#160;one</text:h>
+            <text:p>Saw a <text:span>heron</text:span>,<text:s text:c="2"/>two ducks.</text:p>
+            <text:tracked-changes><text:p>deleted words</text:p></text:tracked-changes>
+            <text:list><text:list-item><text:p>Binoculars</text:p></text:list-item></text:list>
+        </office:text></office:body></office:document-content>"#;
+        let document = read_opendocument(zip(&[("meta.xml", meta), ("content.xml", content)])).unwrap();
+        assert_eq!(document.title.as_deref(), Some("Field notes"));
+        assert_eq!(document.author.as_deref(), Some("Ada"));
+        assert_eq!(document.keywords, vec!["birds", "spring"]);
+        assert_eq!(document.language.as_deref(), Some("en-GB"));
+        assert_eq!(document.pages, Some(3));
+        assert_eq!(document.text, "Day\u{a0}one\nSaw a heron,  two ducks.\nBinoculars");
+    }
+
+    #[test]
+    fn test_spreadsheet_sheets() {
+        let content = br#"<office:document-content><office:body><office:spreadsheet>
+            <table:table table:name="Budget"><table:table-row>
+                <table:table-cell><text:p>Rent</text:p></table:table-cell>
+                <table:table-cell><text:p>900</text:p></table:table-cell>
+            </table:table-row></table:table>
+            <table:table table:name="Notes"/>
+        </office:spreadsheet></office:body></office:document-content>"#;
+        let document = read_opendocument(zip(&[("content.xml", content)])).unwrap();
+        assert_eq!(document.pages, Some(1));
+        assert_eq!(document.text, "Sheet: Budget\nRent\n900");
+    }
+
+    fn encode_varint(mut value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while value >= 0x80 {
+            bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+        bytes
+    }
+
+    /// A protobuf length-delimited field
+    fn field(number: u64, bytes: &[u8]) -> Vec<u8> {
+        [encode_varint(number << 3 | 2), encode_varint(bytes.len() as u64), bytes.to_vec()].concat()
+    }
+
+    /// An archive of one message, as it appears in an IWA file
+    fn archive(kind: u32, message: &[u8]) -> Vec<u8> {
+        let message_info = [vec![0x08], encode_varint(kind as u64), vec![0x18], encode_varint(message.len() as u64)].concat();
+        let info = field(2, &message_info);
+        [encode_varint(info.len() as u64), info, message.to_vec()].concat()
+    }
+
+    #[test]
+    fn test_oversized_lengths_are_malformed() {
+        let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert!(iwa_text(&huge).is_empty());
+        let mut field = vec![0x0a];
+        field.extend(huge);
+        let mut fields = 0;
+        for_each_field(&field, |_, _| fields += 1);
+        assert_eq!(fields, 0);
+    }
+
+    #[test]
+    fn test_read_iwork() {
+        let data = [
+            archive(IWA_STORAGE, &[field(3, "Quarterly plan\u{fffc}".as_bytes()), vec![0x20, 0x01]].concat()),
+            archive(200, &field(3, b"not text")),
+            archive(IWA_TABLE_DATA_LIST, &field(3, &field(3, b"Revenue"))),
+        ].concat();
+        let compressed = snap::raw::Encoder::new().compress_vec(&data).unwrap();
+        let mut iwa = vec![0, compressed.len() as u8, (compressed.len() >> 8) as u8, 0];
+        iwa.extend(compressed);
+
+        let document = read_iwork(zip(&[("Index/Document.iwa", &iwa), ("preview.jpg", b"")])).unwrap();
+        assert_eq!(document.text, "Quarterly plan\nRevenue");
+        assert!(read_iwork(zip(&[("index.xml.gz", b"")])).is_err());
+    }
+
+    #[test]
+    fn test_expansion_is_bounded() {
+        let content = br#"<office:document-content><office:body><office:text>
+            <text:p>a<text:s text:c="4000000000"/>b</text:p>
+        </office:text></office:body></office:document-content>"#;
+        let document = read_opendocument(zip(&[("content.xml", content)])).unwrap();
+        assert_eq!(document.text.len(), MAX_ODF_SPACES + 2);
+
+        // A chunk claiming to decompress to 4 GiB is refused before it is decoded
+        let chunk = encode_varint(u32::MAX as u64);
+        let iwa = [vec![0, chunk.len() as u8, 0, 0], chunk].concat();
+        assert!(decompress_iwa(&iwa).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use std::io::Cursor;
+
+use exif::{Context, DateTime, Exif, In, Rational, Reader, Tag, Value};
+use serde::{Deserialize, Serialize};
+
+/// Camera RAW formats, TIFF-based, whose first image is often only a thumbnail
+pub const RAW_EXTENSIONS: &[&str] = &["cr2", "nef", "arw", "dng"];
+
+/// Reading camera details from the EXIF data of photos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExifSettings {
+    pub enabled: bool,
+    /// Keep where photos were taken. Off by default, since GPS coordinates give away homes and
+    /// the places people go.
+    pub include_location: bool,
+}
+
+impl Default for ExifSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            include_location: false,
+        }
+    }
+}
+
+/// What the EXIF data of a photo says about it
+#[derive(Debug, Default)]
+pub struct PhotoInfo {
+    /// Make and model, e.g. `Canon EOS R6`
+    pub camera: Option<String>,
+    /// When the photo was taken, in the camera's local time as `2024-05-01T14:30:00`
+    pub captured_at: Option<String>,
+    /// Latitude and longitude in decimal degrees, only with location allowed
+    pub location: Option<(f64, f64)>,
+    /// Every field of the main image by tag name; GPS fields only with location allowed
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PhotoInfo {
+    /// The photo's EXIF data, if the format carries any
+    pub fn read(bytes: &[u8], settings: &ExifSettings) -> Option<Self> {
+        let exif = Reader::new().read_from_container(&mut Cursor::new(bytes)).ok()?;
+        Some(Self::from_exif(&exif, settings))
+    }
+
+    fn from_exif(exif: &Exif, settings: &ExifSettings) -> Self {
+        let fields = exif.fields()
+            .filter(|field| field.ifd_num == In::PRIMARY)
+            .filter(|field| settings.include_location || field.tag.context() != Context::Gps)
+            .map(|field| {
+                let value = field.display_value().with_unit(exif).to_string();
+                (field.tag.to_string(), serde_json::Value::String(value))
+            })
+            .collect();
+
+        let text = |tag| exif.get_field(tag, In::PRIMARY).and_then(|field| ascii(&field.value));
+        let camera = match (text(Tag::Make), text(Tag::Model)) {
+            // Many cameras repeat the make in the model, e.g. `Canon` and `Canon EOS R6`
+            (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.or(model),
+        };
+        let captured_at = [Tag::DateTimeOriginal, Tag::DateTime].into_iter()
+            .filter_map(|tag| exif.get_field(tag, In::PRIMARY))
+            .find_map(|field| match &field.value {
+                Value::Ascii(values) => values.first().and_then(|value| DateTime::from_ascii(value).ok()),
+                _ => None,
+            })
+            .map(|taken| format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                taken.year, taken.month, taken.day, taken.hour, taken.minute, taken.second,
+            ));
+        let location = match settings.include_location {
+            true => coordinates(exif),
+            false => None,
+        };
+
+        Self { camera, captured_at, location, fields }
+    }
+
+    /// Lines for the extracted text, so photos are found by camera, date and place
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        if let Some(camera) = &self.camera {
+            summary.push_str(&format!("Camera: {}\n", camera));
+        }
+        if let Some(captured_at) = &self.captured_at {
+            summary.push_str(&format!("Taken: {}\n", captured_at.replace('T', " ")));
+        }
+        if let Some((latitude, longitude)) = self.location {
+            summary.push_str(&format!("Location: {:.5}, {:.5}\n", latitude, longitude));
+        }
+        summary
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "camera": self.camera,
+            "captured_at": self.captured_at,
+            "latitude": self.location.map(|(latitude, _)| latitude),
+            "longitude": self.location.map(|(_, longitude)| longitude),
+            "fields": self.fields,
+        })
+    }
+}
+
+/// Width and height from the file's header, without decoding it. The `image` crate cannot
+/// decode HEIC or, by default, AVIF; RAW files give their size in the EXIF data instead.
+pub fn dimensions(bytes: &[u8], raw: bool) -> Option<(u32, u32)> {
+    let from_exif = || {
+        let exif = Reader::new().read_from_container(&mut Cursor::new(bytes)).ok()?;
+        let side = |tag| exif.get_field(tag, In::PRIMARY)?.value.get_uint(0);
+        Some((side(Tag::PixelXDimension)?, side(Tag::PixelYDimension)?))
+    };
+    match raw {
+        true => from_exif(),
+        false => imagesize::blob_size(bytes).ok()
+            .and_then(|size| Some((u32::try_from(size.width).ok()?, u32::try_from(size.height).ok()?)))
+            .or_else(from_exif),
+    }
+}
+
+/// The first string of an ASCII value, without the padding some cameras leave
+fn ascii(value: &Value) -> Option<String> {
+    match value {
+        Value::Ascii(values) => values.first()
+            .map(|value| String::from_utf8_lossy(value).trim_matches(char::from(0)).trim().to_string())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+fn coordinates(exif: &Exif) -> Option<(f64, f64)> {
+    let coordinate = |tag, reference, negative: &str| {
+        let degrees = match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Rational(parts) => to_degrees(parts)?,
+            _ => return None,
+        };
+        let reference = exif.get_field(reference, In::PRIMARY).and_then(|field| ascii(&field.value));
+        match reference {
+            Some(reference) if reference.eq_ignore_ascii_case(negative) => Some(-degrees),
+            _ => Some(degrees),
+        }
+    };
+    Some((
+        coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?,
+        coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?,
+    ))
+}
+
+/// Degrees, minutes and seconds as decimal degrees
+fn to_degrees(parts: &[Rational]) -> Option<f64> {
+    let [degrees, minutes, seconds] = parts else {
+        return None;
+    };
+    let decimal = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+    decimal.is_finite().then_some(decimal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::Field;
+    use exif::experimental::Writer;
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field { tag, ifd_num: In::PRIMARY, value }
+    }
+
+    fn ascii_value(value: &str) -> Value {
+        Value::Ascii(vec![value.as_bytes().to_vec()])
+    }
+
+    fn rationals(parts: &[(u32, u32)]) -> Value {
+        Value::Rational(parts.iter().map(|&(num, denom)| Rational { num, denom }).collect())
+    }
+
+    fn photo(settings: &ExifSettings) -> PhotoInfo {
+        let fields = [
+            field(Tag::Make, ascii_value("Canon")),
+            field(Tag::Model, ascii_value("Canon EOS R6")),
+            field(Tag::DateTimeOriginal, ascii_value("2024:05:01 14:30:00")),
+            field(Tag::GPSLatitudeRef, ascii_value("N")),
+            field(Tag::GPSLatitude, rationals(&[(48, 1), (51, 1), (2964, 100)])),
+            field(Tag::GPSLongitudeRef, ascii_value("W")),
+            field(Tag::GPSLongitude, rationals(&[(2, 1), (17, 1), (4020, 100)])),
+        ];
+        let mut writer = Writer::new();
+        fields.iter().for_each(|field| writer.push_field(field));
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let exif = Reader::new().read_raw(tiff.into_inner()).unwrap();
+        PhotoInfo::from_exif(&exif, settings)
+    }
+
+    #[test]
+    fn test_photo_info() {
+        let info = photo(&ExifSettings { include_location: true, ..Default::default() });
+        assert_eq!(info.camera.as_deref(), Some("Canon EOS R6"));
+        assert_eq!(info.captured_at.as_deref(), Some("2024-05-01T14:30:00"));
+        let (latitude, longitude) = info.location.unwrap();
+        assert!((latitude - 48.85823).abs() < 1e-5);
+        assert!((longitude + 2.29450).abs() < 1e-5);
+        assert!(info.summary().contains("Location: 48.85823, -2.29450"));
+        assert!(info.fields.contains_key("GPSLatitude"));
+    }
+
+    #[test]
+    fn test_raw_dimensions_come_from_exif() {
+        let mut writer = Writer::new();
+        let thumbnail = [
+            field(Tag::ImageWidth, Value::Long(vec![160])),
+            field(Tag::ImageLength, Value::Long(vec![120])),
+            field(Tag::PixelXDimension, Value::Long(vec![6000])),
+            field(Tag::PixelYDimension, Value::Long(vec![4000])),
+        ];
+        thumbnail.iter().for_each(|field| writer.push_field(field));
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+        assert_eq!(dimensions(&tiff, true), Some((6000, 4000)));
+        assert_eq!(dimensions(&tiff, false), Some((160, 120)));
+    }
+
+    #[test]
+    fn test_webp_dimensions() {
+        // A lossless WebP header: 14 bits each of width and height, less one
+        let mut webp = b"RIFF\x1a\0\0\0WEBPVP8L\x0d\0\0\0\x2f".to_vec();
+        let bits: u32 = (640 - 1) | ((480 - 1) << 14);
+        webp.extend(bits.to_le_bytes());
+        webp.extend([0; 9]);
+        assert_eq!(dimensions(&webp, false), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_location_is_private_by_default() {
+        let info = photo(&ExifSettings::default());
+        assert_eq!(info.location, None);
+        assert!(!info.fields.keys().any(|tag| tag.starts_with("GPS")));
+        assert!(info.fields.contains_key("Model"));
+        assert_eq!(info.summary(), "Camera: Canon EOS R6\nTaken: 2024-05-01 14:30:00\n");
+    }
+
+    #[test]
+    fn test_to_degrees() {
+        let parts = |value: Value| match value {
+            Value::Rational(parts) => parts,
+            _ => unreachable!(),
+        };
+        assert_eq!(to_degrees(&parts(rationals(&[(10, 1), (30, 1), (0, 1)]))), Some(10.5));
+        assert_eq!(to_degrees(&parts(rationals(&[(10, 0), (30, 1), (0, 1)]))), None);
+        assert_eq!(to_degrees(&parts(rationals(&[(10, 1)]))), None);
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Connection, Row, SqliteConnection};
+
+/// The first bytes of every SQLite database file
+const HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Tables and views described, so a database with thousands of tables stays readable
+const MAX_TABLES: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DbColumn {
+    pub name: String,
+    /// The declared type, empty when the column has none
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub primary_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DbTable {
+    pub name: String,
+    /// `table` or `view`
+    pub kind: String,
+    pub columns: Vec<DbColumn>,
+    /// Counted for tables only, since a view may be costly to run
+    pub rows: Option<u64>,
+}
+
+/// The schema of a SQLite database, with the row count of each table
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseSchema {
+    pub tables: Vec<DbTable>,
+    /// Tables and views past `MAX_TABLES`, left out
+    pub omitted: usize,
+}
+
+/// Whether the bytes start like a SQLite database, since `.db` is used by other formats too
+pub fn is_sqlite(header: &[u8]) -> bool {
+    header.starts_with(HEADER)
+}
+
+/// Open the database read-only and describe its tables and views
+pub async fn inspect(path: &Path) -> Result<DatabaseSchema> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .foreign_keys(false)
+        .busy_timeout(Duration::from_secs(1));
+    let mut connection = SqliteConnection::connect_with(&options).await?;
+
+    let entries = sqlx::query(
+        "SELECT name, type FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY type, name"
+    )
+    .fetch_all(&mut connection)
+    .await?;
+    let omitted = entries.len().saturating_sub(MAX_TABLES);
+
+    let mut tables = Vec::new();
+    for entry in entries.into_iter().take(MAX_TABLES) {
+        let name: String = entry.get("name");
+        let kind: String = entry.get("type");
+        let columns = sqlx::query("SELECT name, type, pk FROM pragma_table_info(?1) ORDER BY cid")
+            .bind(&name)
+            .fetch_all(&mut connection)
+            .await?
+            .into_iter()
+            .map(|column| DbColumn {
+                name: column.get("name"),
+                data_type: column.get::<String, _>("type").to_lowercase(),
+                primary_key: column.get::<i64, _>("pk") > 0,
+            })
+            .collect();
+        // A virtual table whose module is not loaded cannot be counted, which is no reason to give up on the rest
+        let rows = match kind.as_str() {
+            "table" => sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")))
+                .fetch_one(&mut connection)
+                .await
+                .ok()
+                .map(|rows| rows.max(0) as u64),
+            _ => None,
+        };
+        tables.push(DbTable { name, kind, columns, rows });
+    }
+
+    connection.close().await?;
+    Ok(DatabaseSchema { tables, omitted })
+}
+
+impl DatabaseSchema {
+    /// A line per table with its columns, so a database is found by the names in its schema
+    pub fn to_text(&self) -> String {
+        let count = |kind: &str| self.tables.iter().filter(|table| table.kind == kind).count();
+        let mut text = format!("SQLite database: {} tables, {} views\n", count("table"), count("view"));
+        for table in &self.tables {
+            text.push_str(&format!("{} {}", table.kind, table.name));
+            if let Some(rows) = table.rows {
+                text.push_str(&format!(" ({} rows)", rows));
+            }
+            let columns: Vec<String> = table.columns.iter()
+                .map(|column| {
+                    let mut text = column.name.clone();
+                    if !column.data_type.is_empty() {
+                        text.push_str(&format!(" {}", column.data_type));
+                    }
+                    if column.primary_key {
+                        text.push_str(" primary key");
+                    }
+                    text
+                })
+                .collect();
+            text.push_str(&format!(": {}\n", columns.join(", ")));
+        }
+        if self.omitted > 0 {
+            text.push_str(&format!("... and {} more\n", self.omitted));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_inspect() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shop.db");
+        let options = SqliteConnectOptions::new().filename(&path).create_if_missing(true);
+        let mut connection = SqliteConnection::connect_with(&options).await.unwrap();
+        for statement in [
+            "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT NOT NULL, joined DATE)",
+            "CREATE TABLE \"order items\" (sku, quantity INTEGER)",
+            "CREATE VIEW regulars AS SELECT name FROM customers",
+            "INSERT INTO customers (name) VALUES ('Ada'), ('Grace')",
+        ] {
+            sqlx::query(statement).execute(&mut connection).await.unwrap();
+        }
+        connection.close().await.unwrap();
+
+        assert!(is_sqlite(&std::fs::read(&path).unwrap()));
+        let schema = inspect(&path).await.unwrap();
+        assert_eq!(schema.tables.len(), 3);
+        assert_eq!(schema.tables[0].rows, Some(2));
+        assert_eq!(schema.tables[2].kind, "view");
+        assert_eq!(schema.to_text(),
+            "SQLite database: 2 tables, 1 views\n\
+             table customers (2 rows): id integer primary key, name text, joined date\n\
+             table order items (0 rows): sku, quantity integer\n\
+             view regulars: name text\n");
+    }
+
+    #[test]
+    fn test_is_sqlite() {
+        assert!(!is_sqlite(b"PK\x03\x04 not a database"));
+        assert!(!is_sqlite(b""));
+    }
+}
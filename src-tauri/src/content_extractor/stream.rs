@@ -0,0 +1,156 @@
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use tokio::io::AsyncReadExt;
+
+const MB: u64 = 1024 * 1024;
+
+// Bytes read from a file for its text, by kind of file, so a multi-gigabyte log or dump is
+// indexed from its start within a fixed memory budget instead of being read whole
+pub const TEXT_LIMIT: u64 = 16 * MB;
+pub const CODE_LIMIT: u64 = 4 * MB;
+pub const MARKUP_LIMIT: u64 = 16 * MB;
+pub const SUBTITLE_LIMIT: u64 = 4 * MB;
+pub const EMAIL_LIMIT: u64 = 32 * MB;
+/// JSON has to be parsed whole, so larger files are indexed as text instead
+pub const JSON_LIMIT: u64 = 32 * MB;
+/// Dimensions and EXIF sit in the first blocks of an image
+pub const IMAGE_HEADER_LIMIT: u64 = 16 * MB;
+/// PDFs are parsed in memory, so larger ones are indexed by name and size only
+pub const PDF_LIMIT: u64 = 256 * MB;
+/// Enough of an unknown file to tell text from binary
+pub const SNIFF_LIMIT: u64 = 8 * 1024;
+
+/// Size of the chunks a file is walked in when it is too large to read for its text
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Bytes of a file whose words are counted; the count for the rest is estimated from them
+const WORD_COUNT_LIMIT: u64 = 256 * MB;
+
+/// The start of a file, up to a limit, and the size of the whole file
+#[derive(Debug)]
+pub struct Prefix {
+    pub bytes: Vec<u8>,
+    pub total: u64,
+}
+
+pub async fn read_prefix(path: &Path, limit: u64) -> Result<Prefix> {
+    let file = tokio::fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+    let mut bytes = Vec::with_capacity(total.min(limit) as usize);
+    file.take(limit).read_to_end(&mut bytes).await?;
+    Ok(Prefix { bytes, total })
+}
+
+impl Prefix {
+    pub fn is_truncated(&self) -> bool {
+        (self.bytes.len() as u64) < self.total
+    }
+
+    /// The bytes as UTF-8, a character cut in half by the limit left out. Fails like
+    /// `read_to_string` when the file is not UTF-8.
+    pub fn to_utf8(&self) -> Result<String> {
+        match std::str::from_utf8(&self.bytes) {
+            Ok(text) => Ok(text.to_string()),
+            // An incomplete sequence at the very end is the limit's doing, not the file's
+            Err(e) if e.error_len().is_none() && self.is_truncated() => {
+                Ok(String::from_utf8_lossy(&self.bytes[..e.valid_up_to()]).into_owned())
+            }
+            Err(_) => Err(anyhow!("stream did not contain valid UTF-8")),
+        }
+    }
+
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+
+    /// A line for the end of the extracted text saying how much of the file it covers
+    pub fn truncation_note(&self) -> Option<String> {
+        self.is_truncated().then(|| format!(
+            "\n\n[Only the first {} of {} indexed]",
+            megabytes(self.bytes.len() as u64),
+            megabytes(self.total),
+        ))
+    }
+}
+
+pub fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / MB as f64)
+}
+
+/// Words in the whole file, counted a chunk at a time so the count stays right for files
+/// indexed from their start only. Past `WORD_COUNT_LIMIT` the count is scaled up from the
+/// words before it rather than reading a multi-gigabyte log to the end.
+pub async fn count_words(path: &Path) -> Result<u64> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&path)?;
+        let total = file.metadata()?.len();
+        let words = count_words_in(file.take(WORD_COUNT_LIMIT), CHUNK_BYTES)?;
+        Ok(match total > WORD_COUNT_LIMIT {
+            true => (words as f64 * total as f64 / WORD_COUNT_LIMIT as f64) as u64,
+            false => words,
+        })
+    }).await?
+}
+
+fn count_words_in(mut reader: impl Read, chunk_bytes: usize) -> Result<u64> {
+    let mut buffer = vec![0u8; chunk_bytes];
+    let mut words = 0;
+    // Carried between chunks, so a word split across two is counted once
+    let mut in_word = false;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(words);
+        }
+        for &byte in &buffer[..read] {
+            let is_space = byte.is_ascii_whitespace();
+            if !is_space && !in_word {
+                words += 1;
+            }
+            in_word = !is_space;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        std::fs::write(&path, "Grüße aus Köln").unwrap();
+
+        // The limit falls inside the two bytes of `ü`
+        let prefix = read_prefix(&path, 3).await.unwrap();
+        assert!(prefix.is_truncated());
+        assert_eq!(prefix.total, 17);
+        assert_eq!(prefix.to_utf8().unwrap(), "Gr");
+        assert!(prefix.truncation_note().unwrap().contains("of 0.0 MB indexed"));
+
+        let whole = read_prefix(&path, TEXT_LIMIT).await.unwrap();
+        assert!(!whole.is_truncated());
+        assert_eq!(whole.to_utf8().unwrap(), "Grüße aus Köln");
+        assert_eq!(whole.truncation_note(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"ok \xff\xfe then more").unwrap();
+        assert!(read_prefix(&path, 5).await.unwrap().to_utf8().is_err());
+    }
+
+    #[test]
+    fn test_count_words_across_chunks() {
+        let text = "one two  three\nfour\tfive ";
+        for chunk_bytes in [1, 3, 4, 64] {
+            assert_eq!(count_words_in(text.as_bytes(), chunk_bytes).unwrap(), 5);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+pub const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "vtt"];
+
+/// Extensions of the videos subtitles are matched with
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "wmv", "webm", "m4v"];
+
+/// Name parts short enough to pass for a language code that mark a kind of subtitle instead
+const NOT_LANGUAGES: &[&str] = &["sdh", "cc"];
+
+/// A line or two of dialogue and when it is shown
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cue {
+    /// Start time as `hh:mm:ss`
+    pub start: String,
+    pub text: String,
+}
+
+/// Cues of an SRT or WebVTT file, with styling tags and positioning left out
+pub fn parse(content: &str) -> Vec<Cue> {
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n").replace('\r', "\n");
+    let mut cues = Vec::new();
+    for block in content.split("\n\n") {
+        let mut lines = block.lines().map(str::trim).filter(|line| !line.is_empty());
+        // SRT numbers its cues and WebVTT may name them; either way the timing line comes next
+        let Some(timing) = lines.by_ref().take(2).find(|line| line.contains("-->")) else {
+            continue;
+        };
+        let Some(start) = timing.split("-->").next().and_then(timestamp) else {
+            continue;
+        };
+        let text = lines.map(strip_tags).filter(|line| !line.is_empty()).collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            cues.push(Cue { start, text });
+        }
+    }
+    cues
+}
+
+/// `00:01:02,500` (SRT), `00:01:02.500` or `01:02.500` (WebVTT) as `00:01:02`
+fn timestamp(value: &str) -> Option<String> {
+    let value = value.trim();
+    let whole = value.split([',', '.']).next()?;
+    let parts: Vec<u32> = whole.split(':').map(|part| part.trim().parse().ok()).collect::<Option<_>>()?;
+    let (hours, minutes, seconds) = match parts[..] {
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        [minutes, seconds] => (0, minutes, seconds),
+        _ => return None,
+    };
+    Some(format!("{:02}:{:02}:{:02}", hours, minutes, seconds))
+}
+
+/// A cue line without `<i>`, `<v Speaker>` and similar tags, or `{\an8}` positioning
+fn strip_tags(line: &str) -> String {
+    let mut text = String::new();
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (Some(end), c) if c == end => closing = None,
+            (Some(_), _) => {}
+            (None, c) => text.push(c),
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The dialogue a line per cue, each with its start time, so a quote is found with where it is said
+pub fn to_text(cues: &[Cue]) -> String {
+    cues.iter().map(|cue| format!("[{}] {}", cue.start, cue.text)).collect::<Vec<_>>().join("\n")
+}
+
+/// Whether the subtitle's name is the video's, with extra parts such as a language or `forced`
+/// allowed before the extension: `Movie.en.forced.srt` goes with `Movie.mkv`
+fn belongs_to(subtitle_stem: &str, video_stem: &str) -> bool {
+    let subtitle_stem = subtitle_stem.to_lowercase();
+    let video_stem = video_stem.to_lowercase();
+    subtitle_stem == video_stem
+        || subtitle_stem.strip_prefix(&video_stem).is_some_and(|rest| rest.starts_with('.'))
+}
+
+fn stem(path: &Path) -> Option<String> {
+    path.file_stem().map(|stem| stem.to_string_lossy().to_string())
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().is_some_and(|extension| extensions.contains(&extension.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// The files in the folder, with their stems
+fn folder_files(folder: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter_map(|path| Some((stem(&path)?, path)))
+        .collect()
+}
+
+/// Of the videos, the one the subtitle was made for, the longest matching name winning so
+/// `Movie.Part2.en.srt` goes with `Movie.Part2.mkv` rather than `Movie.mkv`
+fn closest_video<'a>(subtitle_stem: &str, files: &'a [(String, PathBuf)]) -> Option<&'a PathBuf> {
+    files.iter()
+        .filter(|(video_stem, path)| has_extension(path, VIDEO_EXTENSIONS) && belongs_to(subtitle_stem, video_stem))
+        .max_by_key(|(video_stem, _)| video_stem.len())
+        .map(|(_, path)| path)
+}
+
+/// The video next to a subtitle file that it was made for. The subtitle itself need not exist
+/// any more, so a removed subtitle still leads to its video.
+pub fn video_for(subtitle: &Path) -> Option<PathBuf> {
+    closest_video(&stem(subtitle)?, &folder_files(subtitle.parent()?)).cloned()
+}
+
+/// Subtitle files next to a video that were made for it, by name, from one read of the folder
+pub fn subtitles_for(video: &Path) -> Vec<PathBuf> {
+    let Some(folder) = video.parent() else {
+        return Vec::new();
+    };
+    let files = folder_files(folder);
+    let mut subtitles: Vec<PathBuf> = files.iter()
+        .filter(|(_, path)| has_extension(path, SUBTITLE_EXTENSIONS))
+        // A subtitle belongs to the closest video, which need not be this one
+        .filter(|(subtitle_stem, _)| closest_video(subtitle_stem, &files).map(PathBuf::as_path) == Some(video))
+        .map(|(_, path)| path.clone())
+        .collect();
+    subtitles.sort();
+    subtitles
+}
+
+/// The language part of a subtitle's name, as in `Movie.en.srt` or `Movie.pt-BR.forced.srt`
+pub fn language(subtitle: &Path, video: &Path) -> Option<String> {
+    let rest = stem(subtitle)?.get(stem(video)?.len()..)?.to_string();
+    rest.split('.')
+        .find(|part| {
+            let code = part.split(['-', '_']).next().unwrap_or_default();
+            (2..=3).contains(&code.len())
+                && code.chars().all(|c| c.is_ascii_alphabetic())
+                && !NOT_LANGUAGES.contains(&code.to_lowercase().as_str())
+        })
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srt() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:03,500\r\n<i>Where are</i> we?\r\nNowhere.\r\n\r\n\
+                   2\r\n01:02:03,004 --> 01:02:05,000\r\n{\\an8}On the roof\r\n\r\n";
+        assert_eq!(parse(srt), vec![
+            Cue { start: "00:00:01".to_string(), text: "Where are we? Nowhere.".to_string() },
+            Cue { start: "01:02:03".to_string(), text: "On the roof".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_vtt() {
+        let vtt = "WEBVTT - Episode 1\n\nNOTE written by hand\n\nSTYLE\n::cue { color: yellow }\n\n\
+                   intro\n00:05.250 --> 00:07.000 align:start\n<v Ada>Run the engine.</v>\n";
+        let cues = parse(vtt);
+        assert_eq!(cues, vec![Cue { start: "00:00:05".to_string(), text: "Run the engine.".to_string() }]);
+        assert_eq!(to_text(&cues), "[00:00:05] Run the engine.");
+    }
+
+    #[test]
+    fn test_video_and_subtitles_are_matched_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["Movie.mkv", "Movie.Part2.mp4", "Movie.en.srt", "Movie.Part2.pt-BR.forced.vtt", "Other.srt"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let movie = dir.path().join("Movie.mkv");
+        let part2 = dir.path().join("Movie.Part2.mp4");
+        assert_eq!(video_for(&dir.path().join("Movie.en.srt")), Some(movie.clone()));
+        assert_eq!(video_for(&dir.path().join("Movie.Part2.pt-BR.forced.vtt")), Some(part2.clone()));
+        assert_eq!(video_for(&dir.path().join("Other.srt")), None);
+        assert_eq!(subtitles_for(&movie), vec![dir.path().join("Movie.en.srt")]);
+        assert_eq!(language(&dir.path().join("Movie.Part2.pt-BR.forced.vtt"), &part2).as_deref(), Some("pt-BR"));
+
+        // A removed subtitle still leads to the video whose dialogue it was
+        std::fs::remove_file(dir.path().join("Movie.en.srt")).unwrap();
+        assert_eq!(video_for(&dir.path().join("Movie.en.srt")), Some(movie.clone()));
+        assert!(subtitles_for(&movie).is_empty());
+    }
+}
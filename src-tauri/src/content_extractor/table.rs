@@ -0,0 +1,266 @@
+use std::io::Read;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+/// Rows quoted in the extracted text after the schema, unless configured otherwise
+pub const SAMPLE_ROWS: usize = 5;
+
+/// Distinct values shown per column
+const SAMPLE_VALUES: usize = 3;
+
+/// Rows whose values are parsed for the column types; the rest of a large export is only counted
+const TYPED_ROWS: usize = 10_000;
+
+/// Delimiters tried on the header line of a `.csv` file, which is not always comma-separated
+const DELIMITERS: &[u8] = b",;\t|";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    /// No values at all
+    Empty,
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    Text,
+}
+
+impl ColumnType {
+    fn name(self) -> &'static str {
+        match self {
+            ColumnType::Empty => "empty",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Date => "date",
+            ColumnType::Text => "text",
+        }
+    }
+
+    fn of(value: &str) -> Self {
+        let lower = value.to_ascii_lowercase();
+        if value.parse::<i64>().is_ok() {
+            ColumnType::Integer
+        } else if value.parse::<f64>().is_ok() && value.chars().any(|c| c.is_ascii_digit()) {
+            ColumnType::Float
+        } else if matches!(lower.as_str(), "true" | "false" | "yes" | "no") {
+            ColumnType::Boolean
+        } else if is_date(value) {
+            ColumnType::Date
+        } else {
+            ColumnType::Text
+        }
+    }
+
+    /// The type fitting values of both types
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (ColumnType::Empty, other) | (other, ColumnType::Empty) => other,
+            (a, b) if a == b => a,
+            (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => ColumnType::Float,
+            _ => ColumnType::Text,
+        }
+    }
+}
+
+fn is_date(value: &str) -> bool {
+    const DATES: &[&str] = &["%Y-%m-%d", "%d/%m/%Y", "%m/%d/%Y", "%d.%m.%Y"];
+    const DATE_TIMES: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"];
+    DATES.iter().any(|format| NaiveDate::parse_from_str(value, format).is_ok())
+        || DATE_TIMES.iter().any(|format| NaiveDateTime::parse_from_str(value, format).is_ok())
+        || DateTime::parse_from_rfc3339(value).is_ok()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Column {
+    pub name: String,
+    /// The type of the values in the first `TYPED_ROWS` rows
+    #[serde(rename = "type")]
+    pub kind: ColumnType,
+    /// Rows where the column is blank or missing
+    pub empty: usize,
+    /// The first few distinct values
+    pub samples: Vec<String>,
+}
+
+/// The shape of a delimited table, read in full
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSummary {
+    pub delimiter: char,
+    /// Data rows, not counting the header
+    pub rows: usize,
+    pub columns: Vec<Column>,
+    #[serde(skip)]
+    pub sample_rows: Vec<Vec<String>>,
+}
+
+/// The delimiter the line is split by most often, outside quotes; a comma when there is none
+pub fn sniff_delimiter(line: &str) -> u8 {
+    let mut counts = [0usize; DELIMITERS.len()];
+    let mut quoted = false;
+    for byte in line.bytes() {
+        match byte {
+            b'"' => quoted = !quoted,
+            _ if quoted => {}
+            _ => {
+                if let Some(index) = DELIMITERS.iter().position(|&delimiter| delimiter == byte) {
+                    counts[index] += 1;
+                }
+            }
+        }
+    }
+    // The first of the most frequent, so a tie goes to the comma
+    let mut best = 0;
+    for (index, &count) in counts.iter().enumerate() {
+        if count > counts[best] {
+            best = index;
+        }
+    }
+    DELIMITERS[best]
+}
+
+/// Read the whole table a record at a time, taking the first row for the header and keeping
+/// up to `max_sample_rows` rows to quote. Values are parsed for their type in the first
+/// `TYPED_ROWS` rows only, and no longer once a column is text; later rows are only counted.
+pub fn summarize(reader: impl Read, delimiter: u8, max_sample_rows: usize) -> Result<TableSummary> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(reader);
+    let mut columns: Vec<Column> = reader.byte_headers()?
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let name = String::from_utf8_lossy(name).trim().to_string();
+            Column {
+                name: if name.is_empty() { format!("column {}", index + 1) } else { name },
+                kind: ColumnType::Empty,
+                empty: 0,
+                samples: Vec::new(),
+            }
+        })
+        .collect();
+
+    let mut rows = 0;
+    let mut sample_rows = Vec::new();
+    let text = |value: &[u8]| String::from_utf8_lossy(value).trim().to_string();
+    for record in reader.byte_records() {
+        let record = record?;
+        rows += 1;
+        let typed = rows <= TYPED_ROWS;
+        for (index, column) in columns.iter_mut().enumerate() {
+            let Some(value) = record.get(index).filter(|value| !value.trim_ascii().is_empty()) else {
+                column.empty += 1;
+                continue;
+            };
+            let settled = column.kind == ColumnType::Text && column.samples.len() >= SAMPLE_VALUES;
+            if !typed || settled {
+                continue;
+            }
+            let value = text(value);
+            if column.kind != ColumnType::Text {
+                column.kind = column.kind.merge(ColumnType::of(&value));
+            }
+            if column.samples.len() < SAMPLE_VALUES && !column.samples.contains(&value) {
+                column.samples.push(value);
+            }
+        }
+        if sample_rows.len() < max_sample_rows {
+            sample_rows.push(record.iter().map(text).collect());
+        }
+    }
+
+    Ok(TableSummary { delimiter: delimiter as char, rows, columns, sample_rows })
+}
+
+impl TableSummary {
+    /// The schema and sample rows, for the extracted text and the AI prompt
+    pub fn to_text(&self) -> String {
+        let delimiter = match self.delimiter {
+            ',' => "comma".to_string(),
+            ';' => "semicolon".to_string(),
+            '\t' => "tab".to_string(),
+            other => format!("'{}'", other),
+        };
+        let mut text = format!(
+            "Table: {} rows, {} columns, {}-separated\nColumns:\n",
+            self.rows, self.columns.len(), delimiter,
+        );
+        for column in &self.columns {
+            text.push_str(&format!("  {} ({}", column.name, column.kind.name()));
+            if column.empty > 0 {
+                text.push_str(&format!(", {} empty", column.empty));
+            }
+            text.push(')');
+            if !column.samples.is_empty() {
+                text.push_str(&format!(": {}", column.samples.join(", ")));
+            }
+            text.push('\n');
+        }
+        if !self.sample_rows.is_empty() {
+            text.push_str("Sample rows:\n");
+            for row in &self.sample_rows {
+                text.push_str(&format!("  {}\n", row.join(" | ")));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let csv = "id,name,score,joined,active\n\
+            1,\"Lovelace, Ada\",9.5,2024-01-31,yes\n\
+            2,Grace,10,2024-02-01,no\n\
+            3,,7,,true\n";
+        let summary = summarize(csv.as_bytes(), b',', SAMPLE_ROWS).unwrap();
+        assert_eq!(summary.rows, 3);
+        let kinds: Vec<ColumnType> = summary.columns.iter().map(|column| column.kind).collect();
+        assert_eq!(kinds, vec![
+            ColumnType::Integer,
+            ColumnType::Text,
+            ColumnType::Float,
+            ColumnType::Date,
+            ColumnType::Boolean,
+        ]);
+        assert_eq!(summary.columns[1].samples, vec!["Lovelace, Ada", "Grace"]);
+        assert_eq!(summary.columns[1].empty, 1);
+        assert!(summary.to_text().starts_with("Table: 3 rows, 5 columns, comma-separated\nColumns:\n  id (integer): 1, 2, 3\n"));
+    }
+
+    #[test]
+    fn test_ragged_rows() {
+        let summary = summarize("a\tb\n1\n2\t3\t4\n".as_bytes(), b'\t', SAMPLE_ROWS).unwrap();
+        assert_eq!(summary.rows, 2);
+        assert_eq!(summary.columns[1].empty, 1);
+        assert_eq!(summary.columns[1].kind, ColumnType::Integer);
+    }
+
+    #[test]
+    fn test_large_table_is_typed_from_its_start() {
+        let mut csv = String::from("id,code\n");
+        for row in 0..TYPED_ROWS + 5 {
+            csv.push_str(&format!("{},{}\n", row, if row < TYPED_ROWS { "7" } else { "x7" }));
+        }
+        csv.push_str(",\n");
+        let summary = summarize(csv.as_bytes(), b',', SAMPLE_ROWS).unwrap();
+        assert_eq!(summary.rows, TYPED_ROWS + 6);
+        assert_eq!(summary.columns[1].kind, ColumnType::Integer);
+        assert_eq!(summary.columns[1].empty, 1);
+    }
+
+    #[test]
+    fn test_sniff_delimiter() {
+        assert_eq!(sniff_delimiter("a,b,c"), b',');
+        assert_eq!(sniff_delimiter("name;\"city, country\";zip"), b';');
+        assert_eq!(sniff_delimiter("a\tb"), b'\t');
+        assert_eq!(sniff_delimiter("single"), b',');
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use std::path::{Path, PathBuf};
+
+use super::Database;
+
+const BACKUP_PREFIX: &str = "metamind-";
+const BACKUP_EXTENSION: &str = "db";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Database {
+    /// Write a consistent snapshot of the live database into `backup_dir`.
+    /// `VACUUM INTO` reads through SQLite itself, so pages still in the WAL are included.
+    pub async fn backup_to(&self, backup_dir: &Path) -> Result<BackupInfo> {
+        tokio::fs::create_dir_all(backup_dir).await?;
+
+        let created_at = Utc::now();
+        let name = format!("{}{}.{}", BACKUP_PREFIX, created_at.format("%Y%m%d-%H%M%S%3f"), BACKUP_EXTENSION);
+        let path = backup_dir.join(&name);
+
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy().to_string())
+            .execute(&self.pool)
+            .await?;
+
+        let size = tokio::fs::metadata(&path).await?.len();
+        Ok(BackupInfo {
+            name,
+            path: path.to_string_lossy().to_string(),
+            size,
+            created_at,
+        })
+    }
+}
+
+/// Backups in `backup_dir`, newest first
+pub async fn list_backups(backup_dir: &Path) -> Result<Vec<BackupInfo>> {
+    let mut backups = Vec::new();
+    let mut entries = match tokio::fs::read_dir(backup_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(backups),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(created_at) = parse_backup_time(&name) else {
+            continue;
+        };
+        backups.push(BackupInfo {
+            path: entry.path().to_string_lossy().to_string(),
+            size: entry.metadata().await?.len(),
+            name,
+            created_at,
+        });
+    }
+
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Delete all but the newest `keep` backups, returning how many were removed
+pub async fn prune_backups(backup_dir: &Path, keep: usize) -> Result<usize> {
+    let backups = list_backups(backup_dir).await?;
+    let mut removed = 0;
+    for backup in backups.iter().skip(keep) {
+        tokio::fs::remove_file(&backup.path).await?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Check a backup and put it next to `database_path` to replace it on the next start.
+/// The live database stays untouched while connections are open. Snapshots of an
+/// encrypted database are encrypted with the same key, which is needed to check them.
+pub async fn stage_restore(database_path: &Path, backup_dir: &Path, backup_name: &str, key: Option<&str>) -> Result<()> {
+    // Only names from list_backups are accepted, never arbitrary paths
+    if parse_backup_time(backup_name).is_none() || backup_name.contains(['/', '\\']) {
+        return Err(anyhow!("Not a backup name: {}", backup_name));
+    }
+    let backup_path = backup_dir.join(backup_name);
+    if !backup_path.exists() {
+        return Err(anyhow!("Backup not found: {}", backup_name));
+    }
+
+    // Snapshots taken before encryption was enabled are still plain
+    let key = if super::encryption::is_plaintext(&backup_path).await? { None } else { key };
+    verify_backup(&backup_path, key).await?;
+    tokio::fs::copy(&backup_path, restore_path(database_path)).await?;
+    Ok(())
+}
+
+/// Replace the database with a staged restore, if any. Must run before the pool is opened.
+pub async fn apply_staged_restore(database_path: &Path) -> Result<bool> {
+    let staged = restore_path(database_path);
+    if !staged.exists() {
+        return Ok(false);
+    }
+
+    // Stale WAL pages belong to the old database and would corrupt the restored one
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = PathBuf::from(format!("{}{}", database_path.display(), suffix));
+        if sidecar.exists() {
+            tokio::fs::remove_file(&sidecar).await?;
+        }
+    }
+    tokio::fs::rename(&staged, database_path).await?;
+    Ok(true)
+}
+
+async fn verify_backup(backup_path: &Path, key: Option<&str>) -> Result<()> {
+    let mut options = SqliteConnectOptions::new()
+        .filename(backup_path)
+        .read_only(true);
+    if let Some(key) = key {
+        options = options.pragma("key", super::encryption::key_pragma(key));
+    }
+    let mut connection = options.connect().await?;
+    let result: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&mut connection)
+        .await?;
+    connection.close().await?;
+
+    if result != "ok" {
+        return Err(anyhow!("Backup failed the integrity check: {}", result));
+    }
+    Ok(())
+}
+
+fn restore_path(database_path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.restore", database_path.display()))
+}
+
+fn parse_backup_time(name: &str) -> Option<DateTime<Utc>> {
+    let stamp = name.strip_prefix(BACKUP_PREFIX)?
+        .strip_suffix(BACKUP_EXTENSION)?
+        .strip_suffix('.')?;
+    chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S%3f")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_backup_time() {
+        let time = parse_backup_time("metamind-20240315-093000123.db").unwrap();
+        assert_eq!(time.to_rfc3339(), "2024-03-15T09:30:00.123+00:00");
+        assert!(parse_backup_time("metamind.db").is_none());
+        assert!(parse_backup_time("metamind-20240315-093000123.db-wal").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_backup_prune_and_restore() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("metamind.db");
+        let backup_dir = temp_dir.path().join("backups");
+        let database = Database::new(&db_path).await.expect("Failed to create database");
+
+        let first = database.backup_to(&backup_dir).await.expect("Failed to back up");
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let second = database.backup_to(&backup_dir).await.expect("Failed to back up");
+
+        let backups = list_backups(&backup_dir).await.unwrap();
+        assert_eq!(backups.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(), vec![second.name.as_str(), first.name.as_str()]);
+
+        assert_eq!(prune_backups(&backup_dir, 1).await.unwrap(), 1);
+        assert_eq!(list_backups(&backup_dir).await.unwrap().len(), 1);
+
+        assert!(stage_restore(&db_path, &backup_dir, "../metamind.db", None).await.is_err());
+        stage_restore(&db_path, &backup_dir, &second.name, None).await.expect("Failed to stage restore");
+        database.pool.close().await;
+
+        assert!(apply_staged_restore(&db_path).await.unwrap());
+        assert!(!apply_staged_restore(&db_path).await.unwrap());
+        Database::new(&db_path).await.expect("Restored database should open");
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+use std::collections::HashMap;
+
+use super::Database;
+
+/// Longest history returned in one request
+pub const MAX_DAILY_STATS_DAYS: u32 = 366;
+
+/// Indexing activity during one UTC day
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyStats {
+    /// `YYYY-MM-DD`
+    pub day: String,
+    pub files_added: i64,
+    pub files_processed: i64,
+    pub files_errored: i64,
+    pub bytes_indexed: i64,
+}
+
+impl Database {
+    /// Counters for the last `days` days up to today, oldest first. Days without activity are
+    /// included with zeros so the series can be charted as is.
+    pub async fn get_daily_stats(&self, days: u32) -> Result<Vec<DailyStats>> {
+        let days = days.clamp(1, MAX_DAILY_STATS_DAYS);
+        let today = Utc::now().date_naive();
+        let first_day = today - Duration::days(i64::from(days) - 1);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT day, files_added, files_processed, files_errored, bytes_indexed
+            FROM daily_stats
+            WHERE day >= ?
+            "#
+        )
+        .bind(first_day.format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut recorded: HashMap<String, DailyStats> = rows.iter()
+            .map(|row| {
+                let stats = DailyStats {
+                    day: row.get("day"),
+                    files_added: row.get("files_added"),
+                    files_processed: row.get("files_processed"),
+                    files_errored: row.get("files_errored"),
+                    bytes_indexed: row.get("bytes_indexed"),
+                };
+                (stats.day.clone(), stats)
+            })
+            .collect();
+
+        Ok(first_day.iter_days()
+            .take_while(|day| *day <= today)
+            .map(|day| {
+                let key = day.format("%Y-%m-%d").to_string();
+                recorded.remove(&key).unwrap_or(DailyStats { day: key, ..Default::default() })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::{Result, anyhow};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqlitePool};
+use std::path::{Path, PathBuf};
+
+const KEYCHAIN_SERVICE: &str = "com.metamind.keychain";
+const KEYCHAIN_ACCOUNT: &str = "database-key";
+const KEY_DERIVATION_ROUNDS: u32 = 100_000;
+const SALT_LENGTH: usize = 16;
+
+/// First bytes of every unencrypted SQLite file; SQLCipher files start with random salt instead
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Attempts at reading the keychain before giving up; it may not be unlocked yet right after login
+const KEYCHAIN_READ_ATTEMPTS: u32 = 5;
+const KEYCHAIN_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 256-bit database key as hex, derived from a passphrase and the database's salt
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<String> {
+    use pbkdf2::pbkdf2_hmac;
+    use sha2::Sha256;
+
+    if passphrase.chars().count() < 8 {
+        return Err(anyhow!("The passphrase must be at least 8 characters"));
+    }
+
+    let mut key = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, KEY_DERIVATION_ROUNDS, &mut key);
+    Ok(to_hex(&key))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// The salt is kept next to the database, so the passphrase derives the same key again
+/// when the keychain entry is lost
+fn salt_path(database_path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.salt", database_path.display()))
+}
+
+/// The database's salt, if encryption has ever been enabled for it
+pub async fn load_salt(database_path: &Path) -> Result<Option<Vec<u8>>> {
+    let hex = match tokio::fs::read_to_string(salt_path(database_path)).await {
+        Ok(hex) => hex,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let hex = hex.trim();
+    if hex.len() != SALT_LENGTH * 2 {
+        return Err(anyhow!("The database salt file is damaged"));
+    }
+    let salt = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| anyhow!("The database salt file is damaged"))?;
+    Ok(Some(salt))
+}
+
+/// The database's salt, created with random bytes the first time encryption is enabled
+pub async fn load_or_create_salt(database_path: &Path) -> Result<Vec<u8>> {
+    use rand::RngCore;
+
+    if let Some(salt) = load_salt(database_path).await? {
+        return Ok(salt);
+    }
+    let mut salt = vec![0u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+    tokio::fs::write(salt_path(database_path), to_hex(&salt)).await?;
+    Ok(salt)
+}
+
+/// Derive the key again from the passphrase and the stored salt, check that it opens the
+/// database and put it back in the keychain
+pub async fn recover_key(database_path: &Path, passphrase: &str) -> Result<String> {
+    let salt = load_salt(database_path).await?
+        .ok_or_else(|| anyhow!("Database encryption has not been enabled"))?;
+    let key = derive_key(passphrase, &salt)?;
+
+    if !is_plaintext(database_path).await? {
+        let mut connection = SqliteConnectOptions::new()
+            .filename(database_path)
+            .pragma("key", key_pragma(&key))
+            .connect()
+            .await?;
+        let opened = sqlx::query("SELECT COUNT(*) FROM sqlite_master").fetch_one(&mut connection).await;
+        connection.close().await?;
+        opened.map_err(|_| anyhow!("The passphrase does not match the database"))?;
+    }
+
+    store_key(&key)?;
+    Ok(key)
+}
+
+pub fn store_key(key: &str) -> Result<()> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)?.set_password(key)?;
+    Ok(())
+}
+
+/// The database key, present once encryption has been enabled
+pub fn load_key() -> Result<Option<String>> {
+    match keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!("Failed to read the database key from the keychain: {}", e)),
+    }
+}
+
+/// `load_key`, tried again a few times when the keychain cannot be read
+pub async fn load_key_retrying() -> Result<Option<String>> {
+    let mut attempt = 1;
+    loop {
+        match load_key() {
+            Err(e) if attempt < KEYCHAIN_READ_ATTEMPTS => {
+                tracing::warn!("{} (attempt {}/{})", e, attempt, KEYCHAIN_READ_ATTEMPTS);
+                attempt += 1;
+                tokio::time::sleep(KEYCHAIN_RETRY_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Value for `PRAGMA key`; a raw hex key skips SQLCipher's own passphrase derivation
+pub(crate) fn key_pragma(key: &str) -> String {
+    format!("\"x'{}'\"", key)
+}
+
+/// Whether SQLite was built with SQLCipher, i.e. the `sqlcipher` feature is enabled
+pub async fn sqlcipher_available(pool: &SqlitePool) -> Result<bool> {
+    let version: Option<String> = sqlx::query_scalar("PRAGMA cipher_version")
+        .fetch_optional(pool)
+        .await?;
+    Ok(version.is_some())
+}
+
+/// Whether the file is a plain SQLite database that still has to be encrypted
+pub async fn is_plaintext(database_path: &Path) -> Result<bool> {
+    use tokio::io::AsyncReadExt;
+
+    let mut header = [0u8; 16];
+    let mut file = tokio::fs::File::open(database_path).await?;
+    match file.read_exact(&mut header).await {
+        Ok(_) => Ok(header == SQLITE_HEADER),
+        // Empty or truncated files have no content to migrate
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Rewrite an unencrypted database with `key`. The encrypted copy is built next to the
+/// original and only swapped in once complete, so a failure leaves the database as it was.
+pub async fn encrypt_in_place(database_path: &Path, key: &str) -> Result<()> {
+    let encrypted_path = PathBuf::from(format!("{}.encrypting", database_path.display()));
+    if encrypted_path.exists() {
+        tokio::fs::remove_file(&encrypted_path).await?;
+    }
+
+    let mut connection = SqliteConnectOptions::new()
+        .filename(database_path)
+        .connect()
+        .await?;
+
+    let version: Option<String> = sqlx::query_scalar("PRAGMA cipher_version")
+        .fetch_optional(&mut connection)
+        .await?;
+    if version.is_none() {
+        connection.close().await?;
+        return Err(anyhow!("This build does not include SQLCipher; rebuild with the sqlcipher feature"));
+    }
+
+    // Flush the WAL into the main file so the export sees every committed page
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&mut connection).await?;
+    sqlx::query("ATTACH DATABASE ? AS encrypted KEY ?")
+        .bind(encrypted_path.to_string_lossy().to_string())
+        .bind(format!("x'{}'", key))
+        .execute(&mut connection)
+        .await?;
+    sqlx::query("SELECT sqlcipher_export('encrypted')").execute(&mut connection).await?;
+    sqlx::query("DETACH DATABASE encrypted").execute(&mut connection).await?;
+    connection.close().await?;
+
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = PathBuf::from(format!("{}{}", database_path.display(), suffix));
+        if sidecar.exists() {
+            tokio::fs::remove_file(&sidecar).await?;
+        }
+    }
+    tokio::fs::rename(&encrypted_path, database_path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_key_is_derived_again_from_stored_salt() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("metamind.db");
+        assert_eq!(load_salt(&db_path).await.unwrap(), None);
+
+        let salt = load_or_create_salt(&db_path).await.unwrap();
+        assert_eq!(salt.len(), SALT_LENGTH);
+        assert_eq!(load_or_create_salt(&db_path).await.unwrap(), salt);
+
+        let first = derive_key("correct horse battery", &salt).unwrap();
+        let again = derive_key("correct horse battery", &load_salt(&db_path).await.unwrap().unwrap()).unwrap();
+        assert_eq!(first.len(), 64);
+        assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(first, again);
+        assert_ne!(first, derive_key("correct horse battery", &[0u8; SALT_LENGTH]).unwrap());
+        assert!(derive_key("short", &salt).is_err());
+
+        tokio::fs::write(salt_path(&db_path), "not hex").await.unwrap();
+        assert!(load_salt(&db_path).await.is_err());
+    }
+
+    /// Encrypts a database, then opens it with the key derived again from the passphrase
+    #[cfg(feature = "sqlcipher")]
+    #[tokio::test]
+    async fn test_derived_key_reopens_encrypted_database() {
+        use crate::database::ConnectionSettings;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("metamind.db");
+        let database = Database::new(&db_path).await.expect("Failed to create database");
+        database.pool.close().await;
+
+        let salt = load_or_create_salt(&db_path).await.unwrap();
+        let key = derive_key("correct horse battery", &salt).unwrap();
+        let database = Database::open(&db_path, Some(&key), &ConnectionSettings::default()).await
+            .expect("Failed to encrypt database");
+        database.pool.close().await;
+        assert!(!is_plaintext(&db_path).await.unwrap());
+
+        let salt = load_salt(&db_path).await.unwrap().unwrap();
+        let rederived = derive_key("correct horse battery", &salt).unwrap();
+        let database = Database::open(&db_path, Some(&rederived), &ConnectionSettings::default()).await
+            .expect("Failed to reopen database with the derived key");
+        assert!(database.schema_version().await.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_detects_plaintext_database() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("metamind.db");
+        let database = Database::new(&db_path).await.expect("Failed to create database");
+        database.pool.close().await;
+        assert!(is_plaintext(&db_path).await.unwrap());
+
+        let empty_path = temp_dir.path().join("empty.db");
+        tokio::fs::File::create(&empty_path).await.unwrap();
+        assert!(!is_plaintext(&empty_path).await.unwrap());
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+
+use super::{paths_below, Database};
+
+/// A path that leads to a file indexed under another path, or, for hard links, the path a
+/// multiply linked file is indexed under itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileLink {
+    pub path: String,
+    pub target_path: String,
+    /// `symlink` or `hardlink`
+    pub kind: String,
+    /// Identify the file behind a hard link; unset for symlinks
+    pub device: Option<i64>,
+    pub inode: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FileLink {
+    pub fn symlink(path: String, target_path: String) -> Self {
+        Self { path, target_path, kind: "symlink".to_string(), device: None, inode: None, created_at: Utc::now() }
+    }
+
+    pub fn hardlink(path: String, target_path: String, device: i64, inode: i64) -> Self {
+        Self { path, target_path, kind: "hardlink".to_string(), device: Some(device), inode: Some(inode), created_at: Utc::now() }
+    }
+}
+
+impl Database {
+    /// Insert the link or point it at its current target
+    pub async fn save_file_link(&self, link: &FileLink) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO file_links (path, target_path, kind, device, inode, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(path) DO UPDATE SET
+                target_path = excluded.target_path,
+                kind = excluded.kind,
+                device = excluded.device,
+                inode = excluded.inode
+            "#
+        )
+        .bind(&link.path)
+        .bind(&link.target_path)
+        .bind(&link.kind)
+        .bind(link.device)
+        .bind(link.inode)
+        .bind(link.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Paths a hard linked file was indexed under, first recorded first. Soft deleted files are
+    /// left out; a target may still be gone from disk or only be waiting in a scan batch.
+    pub async fn get_hardlink_targets(&self, device: i64, inode: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT file_links.target_path
+            FROM file_links
+            LEFT JOIN files ON files.path = file_links.target_path
+            WHERE file_links.kind = 'hardlink' AND file_links.device = ? AND file_links.inode = ?
+              AND files.deleted_at IS NULL
+            GROUP BY file_links.target_path
+            ORDER BY MIN(file_links.created_at)
+            "#
+        )
+        .bind(device)
+        .bind(inode)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("target_path")).collect())
+    }
+
+    /// Other paths leading to the file indexed at `target_path`
+    pub async fn get_file_links(&self, target_path: &str) -> Result<Vec<FileLink>> {
+        let rows = sqlx::query("SELECT * FROM file_links WHERE target_path = ? AND path != target_path ORDER BY path")
+            .bind(target_path)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(FileLink {
+                    path: row.get("path"),
+                    target_path: row.get("target_path"),
+                    kind: row.get("kind"),
+                    device: row.get("device"),
+                    inode: row.get("inode"),
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns whether a link was stored at `path`
+    pub async fn remove_file_link(&self, path: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM file_links WHERE path = ?")
+            .bind(path)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Forget the links found inside a removed folder, returning how many there were
+    pub async fn remove_file_links_below(&self, folder: &str) -> Result<u64> {
+        let result = sqlx::query(r"DELETE FROM file_links WHERE path LIKE ? ESCAPE '\'")
+            .bind(paths_below(folder))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
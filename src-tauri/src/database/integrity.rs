@@ -0,0 +1,246 @@
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+
+use super::{migrations, Database};
+
+/// `integrity_check` stops after this many problems
+const MAX_INTEGRITY_MESSAGES: i64 = 100;
+
+/// Triggers on `files` created by the database itself; vector triggers are checked by vector storage
+const FILE_TRIGGERS: &[&str] = &[
+    "files_fts_insert",
+    "files_fts_update",
+    "files_fts_delete",
+    "files_tags_delete",
+    "files_search_tokens_delete",
+    "files_versions_delete",
+    "files_code_symbols_delete",
+    "files_links_delete",
+    "files_links_soft_delete",
+    "files_dead_jobs_delete",
+    "files_dead_jobs_soft_delete",
+    "files_deleted_cleanup",
+];
+
+/// Tables keyed by file id that lose their rows with the file
+const FILE_CHILD_TABLES: &[&str] = &["file_tags", "file_search_tokens", "file_collections", "file_versions", "code_symbols", "dead_jobs"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub check: String,
+    pub detail: String,
+    pub repaired: bool,
+}
+
+impl IntegrityIssue {
+    pub fn repaired(check: &str, detail: String) -> Self {
+        Self { check: check.to_string(), detail, repaired: true }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub issues: Vec<IntegrityIssue>,
+    /// What `PRAGMA integrity_check` still reports after repair
+    pub corruption: Vec<String>,
+    /// Repair could not fix the file; only `reset_database` is left
+    pub reset_recommended: bool,
+}
+
+impl Database {
+    /// Check the file for corruption and the derived tables for drift, repairing what can be
+    /// rebuilt from `files`. Corruption that survives a reindex is left for `reset_database`.
+    pub async fn check_integrity(&self) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+
+        let messages = self.integrity_messages().await?;
+        if !messages.is_empty() {
+            // Damaged indexes are the one kind of corruption that can be rebuilt in place
+            sqlx::query("REINDEX").execute(&self.pool).await?;
+            let remaining = self.integrity_messages().await?;
+            report.issues.push(IntegrityIssue {
+                check: "integrity_check".to_string(),
+                detail: messages.join("; "),
+                repaired: remaining.is_empty(),
+            });
+            if !remaining.is_empty() {
+                report.corruption = remaining;
+                report.reset_recommended = true;
+                return Ok(report);
+            }
+        }
+
+        if let Some(issue) = self.reattach_triggers().await? {
+            report.issues.push(issue);
+        }
+        if let Some(issue) = self.resync_search_table().await? {
+            report.issues.push(issue);
+        }
+        report.issues.extend(self.remove_orphaned_rows().await?);
+
+        Ok(report)
+    }
+
+    async fn integrity_messages(&self) -> Result<Vec<String>> {
+        let messages: Vec<String> = sqlx::query_scalar(&format!("PRAGMA integrity_check({})", MAX_INTEGRITY_MESSAGES))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(messages.into_iter().filter(|message| message != "ok").collect())
+    }
+
+    async fn reattach_triggers(&self) -> Result<Option<IntegrityIssue>> {
+        let existing: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'trigger' AND tbl_name = 'files'"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let missing: Vec<&str> = FILE_TRIGGERS.iter()
+            .copied()
+            .filter(|name| !existing.iter().any(|e| e == name))
+            .collect();
+        if missing.is_empty() {
+            return Ok(None);
+        }
+
+        // Every trigger is created with IF NOT EXISTS, so the ones still in place are untouched.
+        // The migrated search trigger goes first so the table setup does not bring back the old one.
+        sqlx::query(migrations::FILES_FTS_UPDATE_TRIGGER).execute(&self.pool).await?;
+        sqlx::query(migrations::FILES_DELETED_CLEANUP_TRIGGER).execute(&self.pool).await?;
+        self.create_fts_table().await?;
+        self.create_tags_tables().await?;
+        self.create_search_tokens_table().await?;
+        sqlx::query(migrations::FILE_VERSIONS_DELETE_TRIGGER).execute(&self.pool).await?;
+        sqlx::query(migrations::FILE_CODE_SYMBOLS_DELETE_TRIGGER).execute(&self.pool).await?;
+        sqlx::query(migrations::FILE_LINKS_DELETE_TRIGGER).execute(&self.pool).await?;
+        sqlx::query(migrations::FILE_LINKS_SOFT_DELETE_TRIGGER).execute(&self.pool).await?;
+        sqlx::query(migrations::FILE_DEAD_JOBS_DELETE_TRIGGER).execute(&self.pool).await?;
+        sqlx::query(migrations::FILE_DEAD_JOBS_SOFT_DELETE_TRIGGER).execute(&self.pool).await?;
+
+        Ok(Some(IntegrityIssue::repaired("triggers", format!("Reattached missing triggers: {}", missing.join(", ")))))
+    }
+
+    /// Rebuild the search table when it has drifted from `files`, e.g. while a trigger was missing.
+    /// Deleted files have no search row.
+    async fn resync_search_table(&self) -> Result<Option<IntegrityIssue>> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM files f
+                 WHERE f.deleted_at IS NULL AND NOT EXISTS (SELECT 1 FROM files_fts s WHERE s.id = f.id)) as missing,
+                (SELECT COUNT(*) FROM files_fts s
+                 WHERE NOT EXISTS (SELECT 1 FROM files f WHERE f.id = s.id AND f.deleted_at IS NULL)) as stale,
+                (SELECT COUNT(*) FROM files f INNER JOIN files_fts s ON s.id = f.id
+                 WHERE s.name IS NOT f.name
+                    OR s.content IS NOT COALESCE(f.content, '')
+                    OR s.tags IS NOT COALESCE(f.tags, '')
+                    OR s.ai_analysis IS NOT COALESCE(f.ai_analysis, '')) as outdated
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let missing: i64 = row.get("missing");
+        let stale: i64 = row.get("stale");
+        let outdated: i64 = row.get("outdated");
+        if missing + stale + outdated == 0 {
+            return Ok(None);
+        }
+
+        let mut tx = self.begin_immediate().await?;
+        sqlx::query("DELETE FROM files_fts").execute(&mut *tx).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO files_fts (id, name, content, tags, ai_analysis)
+            SELECT id, name, COALESCE(content, ''), COALESCE(tags, ''), COALESCE(ai_analysis, '')
+            FROM files
+            WHERE deleted_at IS NULL
+            "#
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(IntegrityIssue::repaired(
+            "search_index",
+            format!("Rebuilt the search table ({} missing, {} stale, {} outdated rows)", missing, stale, outdated),
+        )))
+    }
+
+    async fn remove_orphaned_rows(&self) -> Result<Vec<IntegrityIssue>> {
+        let mut issues = Vec::new();
+        for table in FILE_CHILD_TABLES {
+            let removed = sqlx::query(&format!(
+                "DELETE FROM {} WHERE NOT EXISTS (SELECT 1 FROM files WHERE files.id = {}.file_id)",
+                table, table
+            ))
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+            if removed > 0 {
+                issues.push(IntegrityIssue::repaired(table, format!("Removed {} rows of deleted files", removed)));
+            }
+        }
+
+        if issues.iter().any(|issue| issue.check == "file_collections") {
+            sqlx::query(
+                r#"
+                UPDATE collections
+                SET file_count = (
+                    SELECT COUNT(*) FROM file_collections WHERE collection_id = collections.id
+                )
+                "#
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_repairs_dropped_trigger_and_search_drift() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await
+            .expect("Failed to create test database");
+
+        let report = database.check_integrity().await.unwrap();
+        assert!(report.issues.is_empty());
+
+        sqlx::query("DROP TRIGGER files_fts_insert").execute(&database.pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO files (id, path, name, size, created_at, modified_at) VALUES ('a', '/a.txt', 'a.txt', 1, '', '')"
+        )
+        .execute(&database.pool)
+        .await
+        .unwrap();
+        // Left behind by a connection without foreign key enforcement; detached so the pragma
+        // does not go back into the pool with it
+        let mut connection = database.pool.acquire().await.unwrap().detach();
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut connection).await.unwrap();
+        sqlx::query("INSERT INTO file_tags (file_id, tag_id) VALUES ('gone', 1)")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        drop(connection);
+
+        let report = database.check_integrity().await.unwrap();
+        let checks: Vec<&str> = report.issues.iter().map(|issue| issue.check.as_str()).collect();
+        assert_eq!(checks, vec!["triggers", "search_index", "file_tags"]);
+        assert!(report.issues.iter().all(|issue| issue.repaired));
+        assert!(!report.reset_recommended);
+
+        let indexed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files_fts WHERE id = 'a'")
+            .fetch_one(&database.pool)
+            .await
+            .unwrap();
+        assert_eq!(indexed, 1);
+        assert!(database.check_integrity().await.unwrap().issues.is_empty());
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use sqlx::sqlite::SqliteRow;
+
+use super::{paths_below, Database};
+
+/// File ids bound per statement, well below SQLite's limit on parameters
+const BIND_CHUNK: usize = 500;
+
+/// Columns read into a `StoredJob`
+const STORED_JOB_COLUMNS: &str = "id, file_id, file_path, priority, state, retry_count, created_at, file_size";
+
+/// Jobs a processing time average is taken over. Past this, each new job takes the share of
+/// an average one, so timings from before an upgrade or a model change fade out.
+const TIMING_WINDOW: i64 = 100;
+
+/// A processing job as stored, so the queue can be rebuilt after a restart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredJob {
+    pub id: String,
+    pub file_id: String,
+    pub file_path: String,
+    /// `low`, `normal`, `high` or `critical`
+    pub priority: String,
+    /// `queued`, `running` while a worker has it, or `spilled` while it is kept here only
+    /// because the queue in memory is full
+    pub state: String,
+    pub retry_count: i64,
+    pub created_at: DateTime<Utc>,
+    /// Size of the file when the job was queued
+    pub file_size: i64,
+}
+
+/// A job that failed on its last retry, kept with the reason until it is retried or dismissed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadJob {
+    pub id: String,
+    pub file_id: String,
+    pub file_path: String,
+    pub priority: String,
+    /// The processing stage the job failed in, e.g. `extracting`
+    pub stage: String,
+    /// `timeout`, `panic` or `failed`
+    pub kind: String,
+    /// The error with all its causes
+    pub error: String,
+    pub retry_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// One run of a job over a file, kept so the file's analysis can be explained later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessingAttempt {
+    pub job_id: String,
+    pub file_id: String,
+    pub file_path: String,
+    /// 0 for the first run, counting up with each retry
+    pub attempt: i64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Milliseconds in each stage, for the stages the attempt got through
+    pub extraction_ms: Option<i64>,
+    pub analysis_ms: Option<i64>,
+    pub embedding_ms: Option<i64>,
+    /// The AI model that analyzed the content, or none for the basic analysis
+    pub model: Option<String>,
+    /// `completed`, `failed` or `timeout`
+    pub result: String,
+    pub error: Option<String>,
+}
+
+/// Processing time of the jobs of one extension and size bucket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessingTiming {
+    /// Lowercased, empty for files without one
+    pub extension: String,
+    pub size_bucket: i64,
+    pub jobs: i64,
+    pub total_ms: i64,
+}
+
+/// A queued file to estimate, with what its processing time depends on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedFile {
+    pub file_path: String,
+    pub extension: String,
+    pub size: i64,
+}
+
+/// Files under 16 KiB fall in bucket 0, and each bucket after holds files up to four times larger
+pub fn size_bucket(size: i64) -> i64 {
+    const FIRST_BUCKET_BYTES: i64 = 16 * 1024;
+    const MAX_BUCKET: i64 = 10;
+    let mut bucket = 0;
+    let mut limit = FIRST_BUCKET_BYTES;
+    while size >= limit && bucket < MAX_BUCKET {
+        bucket += 1;
+        limit = limit.saturating_mul(4);
+    }
+    bucket
+}
+
+impl Database {
+    /// Insert the jobs or update them where they are stored already, in one transaction
+    pub async fn save_jobs(&self, jobs: &[StoredJob]) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+        for job in jobs {
+            sqlx::query(
+                r#"
+                INSERT INTO jobs (id, file_id, file_path, priority, state, retry_count, created_at, updated_at, file_size)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(id) DO UPDATE SET
+                    priority = excluded.priority,
+                    state = excluded.state,
+                    retry_count = excluded.retry_count,
+                    created_at = excluded.created_at,
+                    updated_at = excluded.updated_at,
+                    file_size = excluded.file_size
+                "#
+            )
+            .bind(&job.id)
+            .bind(&job.file_id)
+            .bind(&job.file_path)
+            .bind(&job.priority)
+            .bind(&job.state)
+            .bind(job.retry_count)
+            .bind(job.created_at.to_rfc3339())
+            .bind(&now)
+            .bind(job.file_size)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_job_state(&self, id: &str, state: &str) -> Result<()> {
+        sqlx::query("UPDATE jobs SET state = ?, updated_at = ? WHERE id = ?")
+            .bind(state)
+            .bind(Utc::now().to_rfc3339())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_jobs_state(&self, ids: &[String], state: &str) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+        for id in ids {
+            sqlx::query("UPDATE jobs SET state = ?, updated_at = ? WHERE id = ?")
+                .bind(state)
+                .bind(&now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_jobs_priority(&self, ids: &[String], priority: &str) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+        for id in ids {
+            sqlx::query("UPDATE jobs SET priority = ?, updated_at = ? WHERE id = ?")
+                .bind(priority)
+                .bind(&now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Forget jobs that finished, for good or after their last retry
+    pub async fn remove_jobs(&self, ids: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for id in ids {
+            sqlx::query("DELETE FROM jobs WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn clear_jobs(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM jobs").execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Every stored job that is not spilled, oldest first. Jobs left running by the last session
+    /// lost their work with it, so they are queued again.
+    pub async fn load_jobs(&self) -> Result<Vec<StoredJob>> {
+        sqlx::query("UPDATE jobs SET state = 'queued', updated_at = ? WHERE state = 'running'")
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        let rows = sqlx::query(&format!("SELECT {} FROM jobs WHERE state != 'spilled' ORDER BY created_at, rowid", STORED_JOB_COLUMNS))
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(stored_job).collect()
+    }
+
+    /// Up to `limit` spilled jobs, highest priority and then oldest first
+    pub async fn load_spilled_jobs(&self, limit: i64) -> Result<Vec<StoredJob>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM jobs WHERE state = 'spilled'
+            ORDER BY CASE priority WHEN 'critical' THEN 0 WHEN 'high' THEN 1 WHEN 'normal' THEN 2 ELSE 3 END,
+                created_at, rowid
+            LIMIT ?
+            "#,
+            STORED_JOB_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(stored_job).collect()
+    }
+
+    pub async fn count_spilled_jobs(&self) -> Result<i64> {
+        let row = sqlx::query("SELECT COUNT(*) AS count FROM jobs WHERE state = 'spilled'")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+
+    /// Which of `file_ids` have a spilled job, looked up through the file id index
+    pub async fn spilled_file_ids_among(&self, file_ids: &[String]) -> Result<HashSet<String>> {
+        let mut spilled = HashSet::new();
+        for chunk in file_ids.chunks(BIND_CHUNK) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT DISTINCT file_id FROM jobs WHERE state = 'spilled' AND file_id IN (");
+            let mut separated = builder.separated(", ");
+            for file_id in chunk {
+                separated.push_bind(file_id);
+            }
+            builder.push(")");
+            let rows = builder.build().fetch_all(&self.pool).await?;
+            spilled.extend(rows.iter().map(|row| row.get::<String, _>("file_id")));
+        }
+
+        Ok(spilled)
+    }
+
+    /// Give spilled jobs of `file_ids` a new priority, returning how many changed
+    pub async fn set_spilled_priority_for_files(&self, file_ids: &[String], priority: &str) -> Result<u64> {
+        let now = Utc::now().to_rfc3339();
+        let mut changed = 0;
+        for chunk in file_ids.chunks(BIND_CHUNK) {
+            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new("UPDATE jobs SET priority = ");
+            builder.push_bind(priority).push(", updated_at = ").push_bind(&now);
+            builder.push(" WHERE state = 'spilled' AND priority != ").push_bind(priority).push(" AND file_id IN (");
+            let mut separated = builder.separated(", ");
+            for file_id in chunk {
+                separated.push_bind(file_id);
+            }
+            builder.push(")");
+            changed += builder.build().execute(&self.pool).await?.rows_affected();
+        }
+
+        Ok(changed)
+    }
+
+    /// Give spilled jobs of files below `folder` a new priority, returning how many changed.
+    /// Critical jobs keep their priority.
+    pub async fn set_spilled_priority_below(&self, folder: &str, priority: &str) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs SET priority = ?, updated_at = ?
+            WHERE state = 'spilled' AND priority NOT IN (?, 'critical') AND (file_path = ? OR file_path LIKE ? ESCAPE '\')
+            "#
+        )
+        .bind(priority)
+        .bind(Utc::now().to_rfc3339())
+        .bind(priority)
+        .bind(folder)
+        .bind(paths_below(folder))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Files with a stored job, queued, spilled or running
+    pub async fn get_job_file_ids(&self) -> Result<HashSet<String>> {
+        let rows = sqlx::query("SELECT DISTINCT file_id FROM jobs")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get("file_id")).collect())
+    }
+
+    /// Store a failed job, replacing the one stored earlier for the same file
+    pub async fn add_dead_job(&self, job: &DeadJob) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO dead_jobs (id, file_id, file_path, priority, stage, kind, error, retry_count, created_at, failed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&job.id)
+        .bind(&job.file_id)
+        .bind(&job.file_path)
+        .bind(&job.priority)
+        .bind(&job.stage)
+        .bind(&job.kind)
+        .bind(&job.error)
+        .bind(job.retry_count)
+        .bind(job.created_at.to_rfc3339())
+        .bind(job.failed_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Jobs that failed for good on files still indexed, most recent first
+    pub async fn get_dead_jobs(&self) -> Result<Vec<DeadJob>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT dead_jobs.id, dead_jobs.file_id, dead_jobs.file_path, dead_jobs.priority, dead_jobs.stage,
+                   dead_jobs.kind, dead_jobs.error, dead_jobs.retry_count, dead_jobs.created_at, dead_jobs.failed_at
+            FROM dead_jobs
+            JOIN files ON files.id = dead_jobs.file_id AND files.deleted_at IS NULL
+            ORDER BY dead_jobs.failed_at DESC, dead_jobs.rowid DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(DeadJob {
+                    id: row.get("id"),
+                    file_id: row.get("file_id"),
+                    file_path: row.get("file_path"),
+                    priority: row.get("priority"),
+                    stage: row.get("stage"),
+                    kind: row.get("kind"),
+                    error: row.get("error"),
+                    retry_count: row.get("retry_count"),
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+                    failed_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("failed_at"))?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    /// Forget failed jobs, returning how many were stored
+    pub async fn remove_dead_jobs(&self, ids: &[String]) -> Result<u64> {
+        let mut removed = 0;
+        let mut tx = self.pool.begin().await?;
+        for id in ids {
+            removed += sqlx::query("DELETE FROM dead_jobs WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        Ok(removed)
+    }
+
+    pub async fn record_processing_attempt(&self, attempt: &ProcessingAttempt) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO processing_history (
+                job_id, file_id, file_path, attempt, started_at, finished_at,
+                extraction_ms, analysis_ms, embedding_ms, model, result, error
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&attempt.job_id)
+        .bind(&attempt.file_id)
+        .bind(&attempt.file_path)
+        .bind(attempt.attempt)
+        .bind(attempt.started_at.to_rfc3339())
+        .bind(attempt.finished_at.to_rfc3339())
+        .bind(attempt.extraction_ms)
+        .bind(attempt.analysis_ms)
+        .bind(attempt.embedding_ms)
+        .bind(&attempt.model)
+        .bind(&attempt.result)
+        .bind(&attempt.error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forget processing attempts started before `cutoff`, returning how many there were
+    pub async fn prune_processing_history(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM processing_history WHERE started_at < ?")
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Every processing attempt for a file, most recent first
+    pub async fn get_processing_history(&self, file_id: &str) -> Result<Vec<ProcessingAttempt>> {
+        let rows = sqlx::query("SELECT * FROM processing_history WHERE file_id = ? ORDER BY started_at DESC, id DESC")
+            .bind(file_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(ProcessingAttempt {
+                    job_id: row.get("job_id"),
+                    file_id: row.get("file_id"),
+                    file_path: row.get("file_path"),
+                    attempt: row.get("attempt"),
+                    started_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("started_at"))?.with_timezone(&Utc),
+                    finished_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("finished_at"))?.with_timezone(&Utc),
+                    extraction_ms: row.get("extraction_ms"),
+                    analysis_ms: row.get("analysis_ms"),
+                    embedding_ms: row.get("embedding_ms"),
+                    model: row.get("model"),
+                    result: row.get("result"),
+                    error: row.get("error"),
+                })
+            })
+            .collect()
+    }
+
+    /// Add how long a finished job for the file took to its extension and size bucket
+    pub async fn record_processing_time(&self, file_id: &str, duration_ms: u64) -> Result<()> {
+        let Some(row) = sqlx::query("SELECT extension, size FROM files WHERE id = ?")
+            .bind(file_id)
+            .fetch_optional(&self.pool)
+            .await?
+        else {
+            return Ok(());
+        };
+        let extension = row.get::<Option<String>, _>("extension").unwrap_or_default().to_lowercase();
+        let size: i64 = row.get("size");
+
+        sqlx::query(
+            r#"
+            INSERT INTO processing_timings (extension, size_bucket, jobs, total_ms)
+            VALUES (?1, ?2, 1, ?3)
+            ON CONFLICT(extension, size_bucket) DO UPDATE SET
+                jobs = MIN(jobs + 1, ?4),
+                total_ms = CASE WHEN jobs >= ?4 THEN total_ms - total_ms / jobs ELSE total_ms END + excluded.total_ms
+            "#
+        )
+        .bind(extension)
+        .bind(size_bucket(size))
+        .bind(duration_ms as i64)
+        .bind(TIMING_WINDOW)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_processing_timings(&self) -> Result<Vec<ProcessingTiming>> {
+        let rows = sqlx::query("SELECT * FROM processing_timings")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter()
+            .map(|row| ProcessingTiming {
+                extension: row.get("extension"),
+                size_bucket: row.get("size_bucket"),
+                jobs: row.get("jobs"),
+                total_ms: row.get("total_ms"),
+            })
+            .collect())
+    }
+
+    /// The files of stored jobs, with their extension and size where the file is indexed
+    pub async fn get_queued_files(&self) -> Result<Vec<QueuedFile>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT jobs.file_path, COALESCE(LOWER(files.extension), '') AS extension, COALESCE(files.size, 0) AS size
+            FROM jobs LEFT JOIN files ON files.id = jobs.file_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter()
+            .map(|row| QueuedFile {
+                file_path: row.get("file_path"),
+                extension: row.get("extension"),
+                size: row.get("size"),
+            })
+            .collect())
+    }
+}
+
+fn stored_job(row: &SqliteRow) -> Result<StoredJob> {
+    Ok(StoredJob {
+        id: row.get("id"),
+        file_id: row.get("file_id"),
+        file_path: row.get("file_path"),
+        priority: row.get("priority"),
+        state: row.get("state"),
+        retry_count: row.get("retry_count"),
+        created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+        file_size: row.get("file_size"),
+    })
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use sqlx::pool::PoolConnection;
+use sqlx::{Sqlite, SqliteConnection, SqlitePool};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+use super::Database;
+
+/// How long a connection waits for another writer before SQLite reports the database as locked
+pub(crate) const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const BUSY_RETRIES: u32 = 3;
+const BUSY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// SQLite primary result codes for SQLITE_BUSY and SQLITE_LOCKED
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+
+/// Whether the error is SQLite giving up on a lock held by another connection
+pub fn is_busy(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| match cause.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Database(e)) => e.code()
+            .and_then(|code| code.parse::<i32>().ok())
+            // Extended codes such as SQLITE_BUSY_SNAPSHOT keep the primary code in the low byte
+            .map(|code| matches!(code & 0xff, SQLITE_BUSY | SQLITE_LOCKED))
+            .unwrap_or(false),
+        _ => false,
+    })
+}
+
+/// A transaction that takes the write lock when it starts. Deferred transactions that read
+/// before writing fail outright when another writer commits in between; these wait instead.
+pub struct ImmediateTransaction {
+    connection: Option<PoolConnection<Sqlite>>,
+}
+
+impl ImmediateTransaction {
+    pub async fn begin(pool: &SqlitePool) -> Result<Self> {
+        Database::retry_on_busy(|| async {
+            let mut connection = pool.acquire().await?;
+            sqlx::query("BEGIN IMMEDIATE").execute(&mut *connection).await?;
+            Ok(Self { connection: Some(connection) })
+        }).await
+    }
+
+    pub async fn commit(self) -> Result<()> {
+        self.finish("COMMIT").await
+    }
+
+    pub async fn rollback(self) -> Result<()> {
+        self.finish("ROLLBACK").await
+    }
+
+    async fn finish(mut self, statement: &str) -> Result<()> {
+        if let Some(mut connection) = self.connection.take() {
+            if let Err(e) = sqlx::query(statement).execute(&mut *connection).await {
+                // The transaction may still be open; never hand that connection back to the pool
+                drop(connection.detach());
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deref for ImmediateTransaction {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection.as_ref().expect("transaction already finished")
+    }
+}
+
+impl DerefMut for ImmediateTransaction {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection.as_mut().expect("transaction already finished")
+    }
+}
+
+impl Drop for ImmediateTransaction {
+    fn drop(&mut self) {
+        // sqlx does not know about this transaction, so the connection cannot go back to the
+        // pool mid-transaction. Closing it makes SQLite discard the uncommitted changes.
+        if let Some(connection) = self.connection.take() {
+            drop(connection.detach());
+        }
+    }
+}
+
+impl Database {
+    pub async fn begin_immediate(&self) -> Result<ImmediateTransaction> {
+        ImmediateTransaction::begin(&self.pool).await
+    }
+
+    /// Run `operation` again after a short pause when it fails because the database is locked.
+    /// The busy timeout already covers most contention; this catches what it cannot wait out.
+    pub async fn retry_on_busy<T, F, Fut>(mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Err(e) if attempt < BUSY_RETRIES && is_busy(&e) => {
+                    attempt += 1;
+                    tracing::debug!("Database busy, retrying ({}/{})", attempt, BUSY_RETRIES);
+                    tokio::time::sleep(BUSY_BACKOFF * attempt).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::ConnectOptions;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_dropped_transaction_is_rolled_back() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await
+            .expect("Failed to create test database");
+        sqlx::query("CREATE TABLE counter (value INTEGER)").execute(&database.pool).await.unwrap();
+
+        {
+            let mut tx = database.begin_immediate().await.unwrap();
+            sqlx::query("INSERT INTO counter (value) VALUES (1)").execute(&mut *tx).await.unwrap();
+        }
+        let mut tx = database.begin_immediate().await.unwrap();
+        sqlx::query("INSERT INTO counter (value) VALUES (2)").execute(&mut *tx).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let values: Vec<i64> = sqlx::query_scalar("SELECT value FROM counter")
+            .fetch_all(&database.pool)
+            .await
+            .unwrap();
+        assert_eq!(values, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_busy_errors_are_detected() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await
+            .expect("Failed to create test database");
+
+        let holder = database.begin_immediate().await.unwrap();
+        let mut other = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(temp_dir.path().join("test.db"))
+            .busy_timeout(Duration::from_millis(0))
+            .connect()
+            .await
+            .unwrap();
+        let error: anyhow::Error = sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut other)
+            .await
+            .unwrap_err()
+            .into();
+        assert!(is_busy(&error));
+        assert!(!is_busy(&anyhow::anyhow!("unrelated")));
+        holder.rollback().await.unwrap();
+    }
+}
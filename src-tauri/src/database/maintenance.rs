@@ -0,0 +1,207 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+use std::time::Instant;
+
+use super::Database;
+
+/// Settings row holding when `optimize` last finished
+const LAST_OPTIMIZED_KEY: &str = "maintenance.last_optimized_at";
+
+/// `PRAGMA auto_vacuum` value that lets freed pages be returned with `incremental_vacuum`
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub size_before: i64,
+    pub size_after: i64,
+    pub bytes_reclaimed: i64,
+    /// WAL frames copied back into the database file
+    pub checkpointed_frames: i64,
+    /// Set when the database was switched to incremental vacuuming, which needs one full VACUUM
+    pub full_vacuum: bool,
+    /// Deleted files whose content or search rows were still stored
+    pub deleted_files_cleared: u64,
+    pub duration_ms: u64,
+}
+
+impl Database {
+    /// Clear what deleted files left behind, checkpoint the WAL, refresh query planner statistics,
+    /// rebuild indexes and return free pages to the file system. Runs on one connection since the
+    /// vacuum mode applies per connection.
+    pub async fn optimize(&self) -> Result<MaintenanceReport> {
+        let started = Instant::now();
+        let deleted_files_cleared = self.clear_deleted_files_data().await?;
+        let mut connection = self.pool.acquire().await?;
+
+        let checkpoint = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .fetch_one(&mut *connection)
+            .await?;
+        let checkpointed_frames: i64 = checkpoint.get(2);
+        let size_before = database_size(&mut connection).await?;
+
+        sqlx::query("PRAGMA optimize").execute(&mut *connection).await?;
+        sqlx::query("REINDEX").execute(&mut *connection).await?;
+
+        let auto_vacuum: i64 = sqlx::query_scalar("PRAGMA auto_vacuum")
+            .fetch_one(&mut *connection)
+            .await?;
+        let full_vacuum = auto_vacuum != AUTO_VACUUM_INCREMENTAL;
+        if full_vacuum {
+            // Databases created before this mode existed only switch after a full rebuild
+            sqlx::query("PRAGMA auto_vacuum = INCREMENTAL").execute(&mut *connection).await?;
+            sqlx::query("VACUUM").execute(&mut *connection).await?;
+        } else {
+            sqlx::query("PRAGMA incremental_vacuum").execute(&mut *connection).await?;
+        }
+
+        // Vacuuming goes through the WAL too
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&mut *connection).await?;
+        let size_after = database_size(&mut connection).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?2)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#
+        )
+        .bind(LAST_OPTIMIZED_KEY)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *connection)
+        .await?;
+
+        Ok(MaintenanceReport {
+            size_before,
+            size_after,
+            bytes_reclaimed: (size_before - size_after).max(0),
+            checkpointed_frames,
+            full_vacuum,
+            deleted_files_cleared,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Drop the content, analysis, search rows and collection links still held by deleted files,
+    /// returning how many files had any. Files deleted before the deletion triggers existed kept
+    /// all of it; a restored file is processed again either way.
+    pub async fn clear_deleted_files_data(&self) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let cleared = sqlx::query(
+            r#"
+            UPDATE files SET content = NULL, ai_analysis = NULL, embedding = NULL, indexed_at = NULL
+            WHERE deleted_at IS NOT NULL
+              AND (content IS NOT NULL OR ai_analysis IS NOT NULL OR embedding IS NOT NULL OR indexed_at IS NOT NULL
+                   OR id IN (SELECT id FROM files_fts)
+                   OR id IN (SELECT file_id FROM file_search_tokens)
+                   OR id IN (SELECT file_id FROM file_collections))
+            "#
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if cleared > 0 {
+            sqlx::query("DELETE FROM files_fts WHERE id IN (SELECT id FROM files WHERE deleted_at IS NOT NULL)")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM file_search_tokens WHERE file_id IN (SELECT id FROM files WHERE deleted_at IS NOT NULL)")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM file_collections WHERE file_id IN (SELECT id FROM files WHERE deleted_at IS NOT NULL)")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE collections SET file_count = (SELECT COUNT(*) FROM file_collections WHERE collection_id = collections.id)")
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(cleared)
+    }
+
+    /// When `optimize` last finished, kept across restarts so the weekly run keeps its schedule
+    pub async fn last_optimized_at(&self) -> Result<Option<DateTime<Utc>>> {
+        let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(LAST_OPTIMIZED_KEY)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(value.and_then(|value| DateTime::parse_from_rfc3339(&value).ok()).map(|at| at.with_timezone(&Utc)))
+    }
+}
+
+async fn database_size(connection: &mut sqlx::SqliteConnection) -> Result<i64> {
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(&mut *connection).await?;
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(&mut *connection).await?;
+    Ok(page_count * page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_optimize_reclaims_deleted_pages() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await
+            .expect("Failed to create test database");
+
+        sqlx::query("CREATE TABLE filler (body TEXT)").execute(&database.pool).await.unwrap();
+        for _ in 0..200 {
+            sqlx::query("INSERT INTO filler (body) VALUES (?)")
+                .bind("x".repeat(4096))
+                .execute(&database.pool)
+                .await
+                .unwrap();
+        }
+        database.optimize().await.expect("Failed to optimize");
+
+        sqlx::query("DELETE FROM filler").execute(&database.pool).await.unwrap();
+        let first_run = database.last_optimized_at().await.unwrap().expect("Run not recorded");
+        let report = database.optimize().await.expect("Failed to optimize");
+        assert!(database.last_optimized_at().await.unwrap() >= Some(first_run));
+        assert!(!report.full_vacuum);
+        assert!(report.bytes_reclaimed > 0);
+        assert_eq!(report.size_before - report.size_after, report.bytes_reclaimed);
+    }
+
+    #[tokio::test]
+    async fn test_optimize_clears_data_left_by_deleted_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await
+            .expect("Failed to create test database");
+
+        // Deleted before the deletion triggers existed, with its content still stored
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            INSERT INTO files (id, path, name, size, created_at, modified_at, content, indexed_at, processing_status, deleted_at)
+            VALUES ('old', '/test/old.txt', 'old.txt', 10, ?1, ?1, 'stale words', ?1, 'deleted', ?1)
+            "#
+        )
+        .bind(&now)
+        .execute(&database.pool)
+        .await
+        .unwrap();
+
+        let report = database.optimize().await.expect("Failed to optimize");
+        assert_eq!(report.deleted_files_cleared, 1);
+
+        let content: Option<String> = sqlx::query_scalar("SELECT content FROM files WHERE id = 'old'")
+            .fetch_one(&database.pool)
+            .await
+            .unwrap();
+        assert_eq!(content, None);
+        let search_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files_fts WHERE id = 'old'")
+            .fetch_one(&database.pool)
+            .await
+            .unwrap();
+        assert_eq!(search_rows, 0);
+
+        // The record itself stays until it is purged
+        assert_eq!(database.clear_deleted_files_data().await.unwrap(), 0);
+        assert!(database.get_file_by_id("old").await.unwrap().is_some());
+    }
+}
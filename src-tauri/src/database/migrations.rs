@@ -0,0 +1,974 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::Database;
+use super::locking::ImmediateTransaction;
+use crate::paths;
+
+/// A numbered schema change with the statements that apply and revert it.
+/// Tables created by `run_migrations` before versioning existed form the baseline, version 0.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static [&'static str],
+    pub down: &'static [&'static str],
+}
+
+/// Also reattached by the integrity check
+pub(crate) const FILE_VERSIONS_DELETE_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_versions_delete AFTER DELETE ON files BEGIN
+                DELETE FROM file_versions WHERE file_id = old.id;
+            END
+            "#;
+
+/// Also reattached by the integrity check. Links lose their rows with the file they lead to,
+/// whether it is deleted or only soft deleted.
+pub(crate) const FILE_LINKS_DELETE_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_links_delete AFTER DELETE ON files BEGIN
+                DELETE FROM file_links WHERE target_path = old.path;
+            END
+            "#;
+
+/// Also reattached by the integrity check
+pub(crate) const FILE_LINKS_SOFT_DELETE_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_links_soft_delete AFTER UPDATE OF deleted_at ON files
+            WHEN old.deleted_at IS NULL AND new.deleted_at IS NOT NULL
+            BEGIN
+                DELETE FROM file_links WHERE target_path = old.path;
+            END
+            "#;
+
+/// Also reattached by the integrity check. A failed job goes with its file, whether the file
+/// is deleted or only soft deleted.
+pub(crate) const FILE_DEAD_JOBS_DELETE_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_dead_jobs_delete AFTER DELETE ON files BEGIN
+                DELETE FROM dead_jobs WHERE file_id = old.id;
+            END
+            "#;
+
+/// Also reattached by the integrity check
+pub(crate) const FILE_DEAD_JOBS_SOFT_DELETE_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_dead_jobs_soft_delete AFTER UPDATE OF deleted_at ON files
+            WHEN old.deleted_at IS NULL AND new.deleted_at IS NOT NULL
+            BEGIN
+                DELETE FROM dead_jobs WHERE file_id = old.id;
+            END
+            "#;
+
+/// Also reattached by the integrity check
+pub(crate) const FILE_CODE_SYMBOLS_DELETE_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_code_symbols_delete AFTER DELETE ON files BEGIN
+                DELETE FROM code_symbols WHERE file_id = old.id;
+            END
+            "#;
+
+/// Also reattached by the integrity check
+pub(crate) const FILES_FTS_UPDATE_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_fts_update AFTER UPDATE ON files BEGIN
+                DELETE FROM files_fts WHERE id = old.id;
+                INSERT INTO files_fts(id, name, content, tags, ai_analysis)
+                SELECT new.id, new.name, COALESCE(new.content, ''), COALESCE(new.tags, ''), COALESCE(new.ai_analysis, '')
+                WHERE new.deleted_at IS NULL;
+            END
+            "#;
+
+/// Also reattached by the integrity check
+pub(crate) const FILES_DELETED_CLEANUP_TRIGGER: &str = r#"
+            CREATE TRIGGER IF NOT EXISTS files_deleted_cleanup AFTER UPDATE OF deleted_at ON files
+            WHEN old.deleted_at IS NULL AND new.deleted_at IS NOT NULL
+            BEGIN
+                DELETE FROM file_search_tokens WHERE file_id = old.id;
+                UPDATE collections
+                SET file_count = (
+                    SELECT COUNT(*) FROM file_collections
+                    WHERE collection_id = collections.id AND file_id != old.id
+                )
+                WHERE id IN (SELECT collection_id FROM file_collections WHERE file_id = old.id);
+                DELETE FROM file_collections WHERE file_id = old.id;
+            END
+            "#;
+
+const CANONICAL_PATHS_VERSION: i64 = 4;
+
+/// Every schema change in order; append new entries, never edit applied ones
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "files_extension_index",
+        up: &["CREATE INDEX IF NOT EXISTS idx_files_extension ON files(extension)"],
+        down: &["DROP INDEX IF EXISTS idx_files_extension"],
+    },
+    Migration {
+        version: 2,
+        name: "files_deleted_at",
+        up: &[
+            "ALTER TABLE files ADD COLUMN deleted_at TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_files_deleted_at ON files(deleted_at)",
+            // Files already marked deleted start their grace period now
+            "UPDATE files SET deleted_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now') WHERE processing_status = 'deleted'",
+        ],
+        down: &[
+            "DROP INDEX IF EXISTS idx_files_deleted_at",
+            "ALTER TABLE files DROP COLUMN deleted_at",
+        ],
+    },
+    Migration {
+        version: 3,
+        name: "file_versions",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS file_versions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                hash TEXT,
+                size INTEGER NOT NULL,
+                modified_at TEXT NOT NULL,
+                summary TEXT,
+                tags TEXT,
+                indexed_at TEXT,
+                recorded_at TEXT NOT NULL,
+                UNIQUE(file_id, version)
+            )
+            "#,
+            FILE_VERSIONS_DELETE_TRIGGER,
+        ],
+        down: &[
+            "DROP TRIGGER IF EXISTS files_versions_delete",
+            "DROP TABLE IF EXISTS file_versions",
+        ],
+    },
+    // Marks when `merge_duplicate_paths` has run; the merge needs the file system, not SQL
+    Migration {
+        version: CANONICAL_PATHS_VERSION,
+        name: "canonical_paths",
+        up: &[],
+        down: &[],
+    },
+    // `directory` is the parent folder of a file, `parent` that of a folder: everything before the
+    // last separator. Each row holds the counts of its whole subtree, kept current by triggers,
+    // which rely on `recursive_triggers` so a change rolls up through every ancestor. Rows are
+    // added with NOT EXISTS because a trigger's INSERT OR IGNORE takes on the OR REPLACE of
+    // `insert_file` and would reset the counts.
+    Migration {
+        version: 5,
+        name: "directory_stats",
+        up: &[
+            r#"
+            ALTER TABLE files ADD COLUMN directory TEXT GENERATED ALWAYS AS (substr(rtrim(path, replace(replace(path, '/', ''), '\', '')), 1, length(rtrim(path, replace(replace(path, '/', ''), '\', ''))) - 1)) VIRTUAL
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS directory_stats (
+                directory TEXT PRIMARY KEY,
+                parent TEXT GENERATED ALWAYS AS (substr(rtrim(directory, replace(replace(directory, '/', ''), '\', '')), 1, length(rtrim(directory, replace(replace(directory, '/', ''), '\', ''))) - 1)) VIRTUAL,
+                total INTEGER NOT NULL DEFAULT 0,
+                completed INTEGER NOT NULL DEFAULT 0,
+                pending INTEGER NOT NULL DEFAULT 0,
+                processing INTEGER NOT NULL DEFAULT 0,
+                errors INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+            r#"
+            WITH RECURSIVE ancestors(directory, processing_status) AS (
+                SELECT directory, processing_status FROM files WHERE deleted_at IS NULL AND directory != ''
+                UNION ALL
+                SELECT substr(rtrim(directory, replace(replace(directory, '/', ''), '\', '')), 1, length(rtrim(directory, replace(replace(directory, '/', ''), '\', ''))) - 1), processing_status
+                FROM ancestors WHERE directory != ''
+            )
+            INSERT INTO directory_stats (directory, total, completed, pending, processing, errors)
+            SELECT directory,
+                   COUNT(*),
+                   SUM(processing_status = 'completed'),
+                   SUM(processing_status = 'pending'),
+                   SUM(processing_status = 'processing'),
+                   SUM(processing_status = 'error')
+            FROM ancestors
+            WHERE directory != ''
+            GROUP BY directory
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS directory_stats_rollup AFTER UPDATE ON directory_stats
+            WHEN new.parent != ''
+            BEGIN
+                INSERT INTO directory_stats (directory)
+                SELECT new.parent WHERE NOT EXISTS (SELECT 1 FROM directory_stats WHERE directory = new.parent);
+                UPDATE directory_stats SET
+                    total = total + new.total - old.total,
+                    completed = completed + new.completed - old.completed,
+                    pending = pending + new.pending - old.pending,
+                    processing = processing + new.processing - old.processing,
+                    errors = errors + new.errors - old.errors
+                WHERE directory = new.parent;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_directory_stats_insert AFTER INSERT ON files
+            WHEN new.deleted_at IS NULL
+            BEGIN
+                INSERT INTO directory_stats (directory)
+                SELECT new.directory WHERE NOT EXISTS (SELECT 1 FROM directory_stats WHERE directory = new.directory);
+                UPDATE directory_stats SET
+                    total = total + 1,
+                    completed = completed + (new.processing_status = 'completed'),
+                    pending = pending + (new.processing_status = 'pending'),
+                    processing = processing + (new.processing_status = 'processing'),
+                    errors = errors + (new.processing_status = 'error')
+                WHERE directory = new.directory;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_directory_stats_delete AFTER DELETE ON files
+            WHEN old.deleted_at IS NULL
+            BEGIN
+                UPDATE directory_stats SET
+                    total = total - 1,
+                    completed = completed - (old.processing_status = 'completed'),
+                    pending = pending - (old.processing_status = 'pending'),
+                    processing = processing - (old.processing_status = 'processing'),
+                    errors = errors - (old.processing_status = 'error')
+                WHERE directory = old.directory;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_directory_stats_update_old
+            AFTER UPDATE OF path, processing_status, deleted_at ON files
+            WHEN old.deleted_at IS NULL
+            BEGIN
+                UPDATE directory_stats SET
+                    total = total - 1,
+                    completed = completed - (old.processing_status = 'completed'),
+                    pending = pending - (old.processing_status = 'pending'),
+                    processing = processing - (old.processing_status = 'processing'),
+                    errors = errors - (old.processing_status = 'error')
+                WHERE directory = old.directory;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_directory_stats_update_new
+            AFTER UPDATE OF path, processing_status, deleted_at ON files
+            WHEN new.deleted_at IS NULL
+            BEGIN
+                INSERT INTO directory_stats (directory)
+                SELECT new.directory WHERE NOT EXISTS (SELECT 1 FROM directory_stats WHERE directory = new.directory);
+                UPDATE directory_stats SET
+                    total = total + 1,
+                    completed = completed + (new.processing_status = 'completed'),
+                    pending = pending + (new.processing_status = 'pending'),
+                    processing = processing + (new.processing_status = 'processing'),
+                    errors = errors + (new.processing_status = 'error')
+                WHERE directory = new.directory;
+            END
+            "#,
+        ],
+        down: &[
+            "DROP TRIGGER IF EXISTS files_directory_stats_update_new",
+            "DROP TRIGGER IF EXISTS files_directory_stats_update_old",
+            "DROP TRIGGER IF EXISTS files_directory_stats_delete",
+            "DROP TRIGGER IF EXISTS files_directory_stats_insert",
+            "DROP TRIGGER IF EXISTS directory_stats_rollup",
+            "DROP TABLE IF EXISTS directory_stats",
+            "ALTER TABLE files DROP COLUMN directory",
+        ],
+    },
+    // Activity per UTC day. Upserts keep their own conflict handling when `insert_file`'s
+    // OR REPLACE fires the trigger.
+    Migration {
+        version: 6,
+        name: "daily_stats",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS daily_stats (
+                day TEXT PRIMARY KEY,
+                files_added INTEGER NOT NULL DEFAULT 0,
+                files_processed INTEGER NOT NULL DEFAULT 0,
+                files_errored INTEGER NOT NULL DEFAULT 0,
+                bytes_indexed INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_daily_stats_insert AFTER INSERT ON files
+            BEGIN
+                INSERT INTO daily_stats (day, files_added) VALUES (date('now'), 1)
+                ON CONFLICT(day) DO UPDATE SET files_added = files_added + 1;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_daily_stats_status AFTER UPDATE OF processing_status ON files
+            WHEN new.processing_status IS NOT old.processing_status
+                AND new.processing_status IN ('completed', 'error')
+            BEGIN
+                INSERT INTO daily_stats (day, files_processed, files_errored, bytes_indexed)
+                VALUES (
+                    date('now'),
+                    new.processing_status = 'completed',
+                    new.processing_status = 'error',
+                    CASE WHEN new.processing_status = 'completed' THEN new.size ELSE 0 END
+                )
+                ON CONFLICT(day) DO UPDATE SET
+                    files_processed = files_processed + excluded.files_processed,
+                    files_errored = files_errored + excluded.files_errored,
+                    bytes_indexed = bytes_indexed + excluded.bytes_indexed;
+            END
+            "#,
+        ],
+        down: &[
+            "DROP TRIGGER IF EXISTS files_daily_stats_status",
+            "DROP TRIGGER IF EXISTS files_daily_stats_insert",
+            "DROP TABLE IF EXISTS daily_stats",
+        ],
+    },
+    // Watched folders and their indexing policy; the pattern columns hold JSON arrays
+    Migration {
+        version: 7,
+        name: "watched_paths",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS watched_paths (
+                path TEXT PRIMARY KEY,
+                respect_ignore_files INTEGER NOT NULL DEFAULT 1,
+                include_patterns TEXT NOT NULL DEFAULT '[]',
+                exclude_patterns TEXT NOT NULL DEFAULT '[]',
+                max_file_size INTEGER,
+                priority TEXT NOT NULL DEFAULT 'normal',
+                added_at TEXT NOT NULL
+            )
+            "#,
+        ],
+        down: &["DROP TABLE IF EXISTS watched_paths"],
+    },
+    // A file deleted from disk drops out of search right away: its search row is not rewritten
+    // while it is deleted, and its search tokens and collection links go with the deletion.
+    // `mark_file_deleted` clears the stored content itself; files deleted before this version
+    // are cleared by `optimize`.
+    Migration {
+        version: 8,
+        name: "deleted_files_cleanup",
+        up: &[
+            "DROP TRIGGER IF EXISTS files_fts_update",
+            FILES_FTS_UPDATE_TRIGGER,
+            FILES_DELETED_CLEANUP_TRIGGER,
+        ],
+        down: &[
+            "DROP TRIGGER IF EXISTS files_deleted_cleanup",
+            "DROP TRIGGER IF EXISTS files_fts_update",
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_fts_update AFTER UPDATE ON files BEGIN
+                DELETE FROM files_fts WHERE id = old.id;
+                INSERT INTO files_fts(id, name, content, tags, ai_analysis)
+                VALUES (new.id, new.name, COALESCE(new.content, ''), COALESCE(new.tags, ''), COALESCE(new.ai_analysis, ''));
+            END
+            "#,
+        ],
+    },
+    // Symbolic and hard links met while indexing, so a file reached by several paths is
+    // analyzed once. Hard links carry their device and inode to find the path indexed already.
+    Migration {
+        version: 9,
+        name: "file_links",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS file_links (
+                path TEXT PRIMARY KEY,
+                target_path TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                device INTEGER,
+                inode INTEGER,
+                created_at TEXT NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_file_links_target ON file_links(target_path)",
+            "CREATE INDEX IF NOT EXISTS idx_file_links_inode ON file_links(device, inode)",
+        ],
+        down: &["DROP TABLE IF EXISTS file_links"],
+    },
+    // Whether a watched folder is on a local disk, a network share or a removable drive
+    Migration {
+        version: 10,
+        name: "watched_paths_volume_kind",
+        up: &["ALTER TABLE watched_paths ADD COLUMN volume_kind TEXT NOT NULL DEFAULT 'local'"],
+        down: &["ALTER TABLE watched_paths DROP COLUMN volume_kind"],
+    },
+    // Processing jobs, so the queue survives a crash or quit. Jobs that were running when the
+    // app stopped are queued again on the next start.
+    Migration {
+        version: 11,
+        name: "processing_jobs",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS jobs (
+                id TEXT PRIMARY KEY,
+                file_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                priority TEXT NOT NULL,
+                state TEXT NOT NULL DEFAULT 'queued',
+                retry_count INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_jobs_file_id ON jobs(file_id)",
+        ],
+        down: &["DROP TABLE IF EXISTS jobs"],
+    },
+    // Jobs that failed their last retry, with the stage they failed in and the error, until the
+    // user retries or dismisses them
+    Migration {
+        version: 12,
+        name: "dead_jobs",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS dead_jobs (
+                id TEXT PRIMARY KEY,
+                file_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                priority TEXT NOT NULL,
+                stage TEXT NOT NULL,
+                error TEXT NOT NULL,
+                retry_count INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                failed_at TEXT NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_dead_jobs_file_id ON dead_jobs(file_id)",
+        ],
+        down: &["DROP TABLE IF EXISTS dead_jobs"],
+    },
+    // How long processing took, summed per extension and size bucket, to estimate what is queued
+    Migration {
+        version: 13,
+        name: "processing_timings",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS processing_timings (
+                extension TEXT NOT NULL,
+                size_bucket INTEGER NOT NULL,
+                jobs INTEGER NOT NULL DEFAULT 0,
+                total_ms INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (extension, size_bucket)
+            )
+            "#,
+        ],
+        down: &["DROP TABLE IF EXISTS processing_timings"],
+    },
+    // When processing of a file last started, so files can be requeued by how long ago it failed
+    Migration {
+        version: 14,
+        name: "files_last_attempt",
+        up: &["ALTER TABLE files ADD COLUMN last_attempt_at TEXT"],
+        down: &["ALTER TABLE files DROP COLUMN last_attempt_at"],
+    },
+    // Every processing attempt with its stage durations, model and result, to explain a file's
+    // analysis. Kept when the file is removed, like other history.
+    Migration {
+        version: 15,
+        name: "processing_history",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS processing_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id TEXT NOT NULL,
+                file_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                attempt INTEGER NOT NULL DEFAULT 0,
+                started_at TEXT NOT NULL,
+                finished_at TEXT NOT NULL,
+                extraction_ms INTEGER,
+                analysis_ms INTEGER,
+                embedding_ms INTEGER,
+                model TEXT,
+                result TEXT NOT NULL,
+                error TEXT
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_processing_history_file ON processing_history(file_id, started_at)",
+        ],
+        down: &["DROP TABLE IF EXISTS processing_history"],
+    },
+    // Whether a failed job timed out, panicked or failed with an error, so hung and crashing
+    // files can be told from unreadable ones
+    Migration {
+        version: 16,
+        name: "dead_jobs_kind",
+        up: &["ALTER TABLE dead_jobs ADD COLUMN kind TEXT NOT NULL DEFAULT 'failed'"],
+        down: &["ALTER TABLE dead_jobs DROP COLUMN kind"],
+    },
+    // The symbols of source file outlines in a table of their own, so symbol search reads an
+    // index on names instead of the metadata of every file. Filled from the outlines stored so far.
+    Migration {
+        version: 17,
+        name: "code_symbols",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS code_symbols (
+                file_id TEXT NOT NULL,
+                name TEXT NOT NULL COLLATE NOCASE,
+                kind TEXT NOT NULL,
+                line INTEGER NOT NULL,
+                doc TEXT
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_code_symbols_name ON code_symbols(name)",
+            "CREATE INDEX IF NOT EXISTS idx_code_symbols_file_id ON code_symbols(file_id)",
+            r#"
+            INSERT INTO code_symbols (file_id, name, kind, line, doc)
+            SELECT files.id, json_extract(symbol.value, '$.name'), json_extract(symbol.value, '$.kind'),
+                   json_extract(symbol.value, '$.line'), json_extract(symbol.value, '$.doc')
+            FROM files, json_each(
+                CASE WHEN json_valid(files.metadata) THEN files.metadata ELSE '{}' END,
+                '$.code.symbols'
+            ) AS symbol
+            WHERE json_extract(symbol.value, '$.name') IS NOT NULL
+            "#,
+            FILE_CODE_SYMBOLS_DELETE_TRIGGER,
+        ],
+        down: &[
+            "DROP TRIGGER IF EXISTS files_code_symbols_delete",
+            "DROP TABLE IF EXISTS code_symbols",
+        ],
+    },
+    // Small values the backend keeps between runs, such as when maintenance last ran
+    Migration {
+        version: 18,
+        name: "settings",
+        up: &[r#"
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#],
+        down: &["DROP TABLE IF EXISTS settings"],
+    },
+    // Links to a removed file go with it, so a hard link skipped for it is indexed on the next scan
+    Migration {
+        version: 19,
+        name: "file_links_cleanup",
+        up: &[
+            FILE_LINKS_DELETE_TRIGGER,
+            FILE_LINKS_SOFT_DELETE_TRIGGER,
+            "DELETE FROM file_links WHERE target_path IN (SELECT path FROM files WHERE deleted_at IS NOT NULL)",
+        ],
+        down: &[
+            "DROP TRIGGER IF EXISTS files_links_soft_delete",
+            "DROP TRIGGER IF EXISTS files_links_delete",
+        ],
+    },
+    // One failed job per file, the latest, and none for files that are gone
+    Migration {
+        version: 20,
+        name: "dead_jobs_per_file",
+        up: &[
+            r#"
+            DELETE FROM dead_jobs WHERE EXISTS (
+                SELECT 1 FROM dead_jobs AS newer
+                WHERE newer.file_id = dead_jobs.file_id
+                  AND (newer.failed_at > dead_jobs.failed_at
+                       OR (newer.failed_at = dead_jobs.failed_at AND newer.rowid > dead_jobs.rowid))
+            )
+            "#,
+            "DELETE FROM dead_jobs WHERE file_id NOT IN (SELECT id FROM files WHERE deleted_at IS NULL)",
+            "DROP INDEX IF EXISTS idx_dead_jobs_file_id",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_dead_jobs_file_id ON dead_jobs(file_id)",
+            FILE_DEAD_JOBS_DELETE_TRIGGER,
+            FILE_DEAD_JOBS_SOFT_DELETE_TRIGGER,
+        ],
+        down: &[
+            "DROP TRIGGER IF EXISTS files_dead_jobs_soft_delete",
+            "DROP TRIGGER IF EXISTS files_dead_jobs_delete",
+            "DROP INDEX IF EXISTS idx_dead_jobs_file_id",
+            "CREATE INDEX IF NOT EXISTS idx_dead_jobs_file_id ON dead_jobs(file_id)",
+        ],
+    },
+    // The size of a job's file, so jobs are scheduled by kind of file after a restart without
+    // reading each file again
+    Migration {
+        version: 21,
+        name: "jobs_file_size",
+        up: &[
+            "ALTER TABLE jobs ADD COLUMN file_size INTEGER NOT NULL DEFAULT 0",
+            "UPDATE jobs SET file_size = COALESCE((SELECT size FROM files WHERE files.id = jobs.file_id), 0)",
+        ],
+        down: &["ALTER TABLE jobs DROP COLUMN file_size"],
+    },
+    // When each watched folder was last rescanned, so schedules carry over restarts
+    Migration {
+        version: 22,
+        name: "watched_paths_last_rescan",
+        up: &["ALTER TABLE watched_paths ADD COLUMN last_rescan_at TEXT"],
+        down: &["ALTER TABLE watched_paths DROP COLUMN last_rescan_at"],
+    },
+];
+
+/// A row of `files` with the path it should be stored under
+struct StoredPath {
+    id: String,
+    path: String,
+    canonical: String,
+    active: bool,
+    indexed_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: String,
+}
+
+impl Database {
+    async fn create_migrations_table(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TEXT NOT NULL
+            )
+            "#
+        ).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Highest applied migration, 0 for the baseline schema
+    pub async fn schema_version(&self) -> Result<i64> {
+        self.create_migrations_table().await?;
+        let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_migrations")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(version.unwrap_or(0))
+    }
+
+    pub async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>> {
+        self.create_migrations_table().await?;
+        let rows = sqlx::query("SELECT version, name, applied_at FROM schema_migrations ORDER BY version")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter()
+            .map(|row| AppliedMigration {
+                version: row.get("version"),
+                name: row.get("name"),
+                applied_at: row.get("applied_at"),
+            })
+            .collect())
+    }
+
+    /// Apply pending migrations in order, each in its own transaction, returning how many ran
+    pub async fn apply_migrations(&self) -> Result<usize> {
+        let previous = self.schema_version().await?;
+        let applied = apply_pending(self, MIGRATIONS).await?;
+
+        if previous < CANONICAL_PATHS_VERSION && self.schema_version().await? >= CANONICAL_PATHS_VERSION {
+            match self.merge_duplicate_paths().await {
+                Ok(0) => {}
+                Ok(merged) => tracing::info!("Merged {} files indexed under more than one path", merged),
+                Err(e) => tracing::error!("Failed to merge duplicate paths: {}", e),
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// Rewrite stored paths to their canonical form, folding rows that name the same file into
+    /// one. The kept row is the one still on disk with the most recent analysis; collection
+    /// links move over to it. Returns how many duplicate rows were removed.
+    pub async fn merge_duplicate_paths(&self) -> Result<usize> {
+        let rows: Vec<(String, String, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT id, path, deleted_at, indexed_at FROM files"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Resolving symlinks touches the disk for every file
+        let resolved = tokio::task::spawn_blocking(move || {
+            rows.into_iter()
+                .map(|(id, path, deleted_at, indexed_at)| StoredPath {
+                    canonical: paths::canonical_string(Path::new(&path)),
+                    id,
+                    path,
+                    active: deleted_at.is_none(),
+                    indexed_at,
+                })
+                .collect::<Vec<_>>()
+        }).await?;
+
+        let mut groups: HashMap<String, Vec<StoredPath>> = HashMap::new();
+        for row in resolved {
+            groups.entry(paths::path_key(&row.canonical)).or_default().push(row);
+        }
+
+        let mut merged = 0;
+        let mut tx = self.begin_immediate().await?;
+        let mut renames = Vec::new();
+        for (_, mut rows) in groups {
+            if rows.len() == 1 && rows[0].path == rows[0].canonical {
+                continue;
+            }
+
+            // Active rows first, then the most recently indexed; RFC 3339 strings sort by time
+            rows.sort_by(|a, b| b.active.cmp(&a.active).then_with(|| b.indexed_at.cmp(&a.indexed_at)));
+            let keeper = rows.remove(0);
+
+            for duplicate in &rows {
+                sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO file_collections (file_id, collection_id, added_at)
+                    SELECT ?, collection_id, added_at FROM file_collections WHERE file_id = ?
+                    "#
+                )
+                .bind(&keeper.id)
+                .bind(&duplicate.id)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query("DELETE FROM file_collections WHERE file_id = ?")
+                    .bind(&duplicate.id)
+                    .execute(&mut *tx)
+                    .await?;
+                // Triggers remove the search rows, tags, versions and vectors
+                sqlx::query("DELETE FROM files WHERE id = ?")
+                    .bind(&duplicate.id)
+                    .execute(&mut *tx)
+                    .await?;
+                merged += 1;
+            }
+
+            if keeper.path != keeper.canonical {
+                renames.push((keeper.id, keeper.canonical));
+            }
+        }
+
+        // Only after every duplicate is gone, so no rename collides with a path still in use
+        for (file_id, canonical) in renames {
+            let name = Path::new(&canonical).file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| canonical.clone());
+            sqlx::query("UPDATE files SET path = ?, name = ? WHERE id = ?")
+                .bind(&canonical)
+                .bind(name)
+                .bind(&file_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        if merged > 0 {
+            sqlx::query(
+                r#"
+                UPDATE collections
+                SET file_count = (
+                    SELECT COUNT(*) FROM file_collections WHERE collection_id = collections.id
+                )
+                "#
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(merged)
+    }
+
+    /// Revert applied migrations newer than `target_version`, newest first
+    pub async fn rollback_migrations(&self, target_version: i64) -> Result<usize> {
+        rollback_to(self, MIGRATIONS, target_version).await
+    }
+}
+
+async fn apply_pending(database: &Database, migrations: &[Migration]) -> Result<usize> {
+    validate_order(migrations)?;
+    let current = database.schema_version().await?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    if current > latest {
+        // Written by a newer build; its extra objects are left alone
+        tracing::warn!("Database schema version {} is newer than this build supports ({})", current, latest);
+        return Ok(0);
+    }
+
+    let mut applied = 0;
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        tracing::info!("Applying database migration {} ({})", migration.version, migration.name);
+        let mut tx = begin_migration(database).await?;
+        for statement in migration.up {
+            sqlx::query(statement).execute(&mut *tx).await
+                .map_err(|e| anyhow!("Migration {} ({}) failed: {}", migration.version, migration.name, e))?;
+        }
+        sqlx::query("INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        applied += 1;
+    }
+
+    Ok(applied)
+}
+
+async fn rollback_to(database: &Database, migrations: &[Migration], target_version: i64) -> Result<usize> {
+    validate_order(migrations)?;
+    let applied_versions: Vec<i64> = database.applied_migrations().await?
+        .into_iter()
+        .map(|m| m.version)
+        .filter(|version| *version > target_version)
+        .collect();
+
+    let mut reverted = 0;
+    for version in applied_versions.into_iter().rev() {
+        let migration = migrations.iter()
+            .find(|m| m.version == version)
+            .ok_or_else(|| anyhow!("Migration {} is not known to this build and cannot be rolled back", version))?;
+
+        tracing::info!("Rolling back database migration {} ({})", migration.version, migration.name);
+        let mut tx = begin_migration(database).await?;
+        for statement in migration.down {
+            sqlx::query(statement).execute(&mut *tx).await
+                .map_err(|e| anyhow!("Rollback of migration {} ({}) failed: {}", migration.version, migration.name, e))?;
+        }
+        sqlx::query("DELETE FROM schema_migrations WHERE version = ?")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        reverted += 1;
+    }
+
+    Ok(reverted)
+}
+
+/// Begin a migration's transaction with the connection's view of the schema up to date.
+/// SQLite resolves an ALTER TABLE against the schema the connection cached, which is stale
+/// after another pooled connection changed it, and only a read notices the change.
+async fn begin_migration(database: &Database) -> Result<ImmediateTransaction> {
+    let mut tx = database.begin_immediate().await?;
+    sqlx::query("SELECT COUNT(*) FROM sqlite_master").fetch_one(&mut *tx).await?;
+    Ok(tx)
+}
+
+fn validate_order(migrations: &[Migration]) -> Result<()> {
+    let mut previous = 0;
+    for migration in migrations {
+        if migration.version <= previous {
+            return Err(anyhow!("Migration {} ({}) is out of order", migration.version, migration.name));
+        }
+        previous = migration.version;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            name: "notes_table",
+            up: &["CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)"],
+            down: &["DROP TABLE notes"],
+        },
+        Migration {
+            version: 2,
+            name: "notes_pinned",
+            up: &["ALTER TABLE notes ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0"],
+            down: &["ALTER TABLE notes DROP COLUMN pinned"],
+        },
+        Migration {
+            version: 3,
+            name: "broken",
+            up: &["CREATE INDEX idx_notes_missing ON notes(missing_column)"],
+            down: &[],
+        },
+    ];
+
+    async fn create_test_database() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await
+            .expect("Failed to create test database");
+        (database, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_builtin_migrations_are_applied_once() {
+        let (database, _temp_dir) = create_test_database().await;
+
+        let latest = MIGRATIONS.last().map(|m| m.version).unwrap_or(0);
+        assert_eq!(database.schema_version().await.unwrap(), latest);
+        assert_eq!(database.apply_migrations().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_is_not_recorded() {
+        let (database, _temp_dir) = create_test_database().await;
+        database.rollback_migrations(0).await.expect("Failed to roll back builtin migrations");
+
+        assert!(apply_pending(&database, TEST_MIGRATIONS).await.is_err());
+        assert_eq!(database.schema_version().await.unwrap(), 2);
+
+        sqlx::query("INSERT INTO notes (body, pinned) VALUES ('x', 1)")
+            .execute(&database.pool)
+            .await
+            .expect("Applied migrations should be usable");
+    }
+
+    #[tokio::test]
+    async fn test_rollback_reverts_newest_first() {
+        let (database, _temp_dir) = create_test_database().await;
+        database.rollback_migrations(0).await.expect("Failed to roll back builtin migrations");
+
+        apply_pending(&database, &TEST_MIGRATIONS[..2]).await.expect("Failed to apply migrations");
+        assert_eq!(rollback_to(&database, TEST_MIGRATIONS, 1).await.unwrap(), 1);
+        assert_eq!(database.schema_version().await.unwrap(), 1);
+
+        assert!(sqlx::query("SELECT pinned FROM notes").fetch_all(&database.pool).await.is_err());
+        assert_eq!(rollback_to(&database, TEST_MIGRATIONS, 0).await.unwrap(), 1);
+        assert!(sqlx::query("SELECT * FROM notes").fetch_all(&database.pool).await.is_err());
+    }
+
+    #[test]
+    fn test_versions_must_increase() {
+        assert!(validate_order(MIGRATIONS).is_ok());
+        assert!(validate_order(TEST_MIGRATIONS).is_ok());
+
+        let unordered = [
+            Migration { version: 2, name: "b", up: &[], down: &[] },
+            Migration { version: 1, name: "a", up: &[], down: &[] },
+        ];
+        assert!(validate_order(&unordered).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_paths_are_merged() {
+        let (database, temp_dir) = create_test_database().await;
+        let root = std::fs::canonicalize(temp_dir.path()).unwrap();
+        std::fs::write(root.join("a.txt"), "a").unwrap();
+
+        let spellings = [
+            ("plain", root.join("a.txt"), Some("2024-01-01T00:00:00+00:00")),
+            ("dotted", root.join(".").join("a.txt"), Some("2024-06-01T00:00:00+00:00")),
+            ("unindexed", root.join("sub").join("..").join("a.txt"), None),
+        ];
+        for (id, path, indexed_at) in &spellings {
+            sqlx::query(
+                "INSERT INTO files (id, path, name, size, created_at, modified_at, indexed_at) VALUES (?, ?, 'a.txt', 1, '', '', ?)"
+            )
+            .bind(id)
+            .bind(path.to_string_lossy().to_string())
+            .bind(indexed_at)
+            .execute(&database.pool)
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(database.merge_duplicate_paths().await.unwrap(), 2);
+        let remaining: Vec<(String, String)> = sqlx::query_as("SELECT id, path FROM files")
+            .fetch_all(&database.pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![("dotted".to_string(), root.join("a.txt").to_string_lossy().to_string())]);
+        assert_eq!(database.merge_duplicate_paths().await.unwrap(), 0);
+    }
+}
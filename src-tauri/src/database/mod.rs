@@ -1,14 +1,73 @@
-use sqlx::{SqlitePool, Row};
+use sqlx::{SqlitePool, Row, QueryBuilder, Sqlite};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
 use anyhow::Result;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 
+use crate::language;
+use crate::paths;
+use crate::search_query::QueryNode;
+
+pub mod backup;
+pub mod daily_stats;
+pub mod encryption;
+pub mod file_links;
+pub mod integrity;
+pub mod jobs;
+pub mod locking;
+pub mod maintenance;
+pub mod migrations;
+pub mod symbols;
+pub mod versions;
+pub mod watched_paths;
+
+/// Maps a file extension onto the coarse categories shown in insights and facets
+const CATEGORY_CASE_SQL: &str = r#"
+                CASE 
+                    WHEN extension IN ('pdf', 'doc', 'docx', 'txt', 'md', 'rtf') THEN 'documents'
+                    WHEN extension IN ('jpg', 'jpeg', 'png', 'gif', 'bmp', 'svg', 'tiff', 'webp') THEN 'images'
+                    WHEN extension IN ('js', 'ts', 'py', 'rs', 'java', 'cpp', 'c', 'h', 'css', 'html', 'xml', 'json') THEN 'code'
+                    ELSE 'other'
+                END"#;
+
 #[derive(Debug, Clone)]
 pub struct Database {
     pub pool: SqlitePool,
 }
 
+/// Pool size and SQLite tuning applied to every connection when the database is opened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionSettings {
+    pub max_connections: u32,
+    /// `PRAGMA cache_size`: pages when positive, KiB when negative
+    pub cache_size: i64,
+    /// Bytes of the file mapped into memory; 0 disables memory-mapped I/O
+    pub mmap_size: u64,
+    /// `PRAGMA synchronous`: off, normal, full or extra
+    pub synchronous: String,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            cache_size: 1000,
+            mmap_size: 0,
+            synchronous: "normal".to_string(),
+        }
+    }
+}
+
+impl ConnectionSettings {
+    /// The `synchronous` mode as SQLite takes it, or why it is not one
+    pub fn synchronous_mode(&self) -> Result<SqliteSynchronous> {
+        Ok(self.synchronous.parse()?)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileRecord {
     pub id: String,
@@ -43,28 +102,312 @@ pub struct Collection {
     pub insights: Option<String>,
 }
 
+/// Structured filters accepted by the search commands
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    /// File extensions without the leading dot, e.g. "pdf"
+    #[serde(alias = "file_types")]
+    pub extensions: Vec<String>,
+    /// Top-level MIME types, e.g. "image" matches "image/png"
+    pub mime_categories: Vec<String>,
+    pub size_range: Option<SizeRange>,
+    pub date_range: Option<DateRange>,
+    pub processing_status: Vec<String>,
+    /// Only match files below this watched root
+    pub root_path: Option<String>,
+    /// Only match files that belong to this collection
+    pub collection_id: Option<String>,
+    /// Only match files carrying every one of these tags
+    pub tags: Vec<String>,
+}
+
+impl SearchFilters {
+    /// Whether nothing but the collection scope is set, which vector searches can honour too
+    pub fn is_scope_only(&self) -> bool {
+        self.extensions.is_empty()
+            && self.mime_categories.is_empty()
+            && self.size_range.is_none()
+            && self.date_range.is_none()
+            && self.processing_status.is_empty()
+            && self.root_path.is_none()
+            && self.tags.is_empty()
+    }
+
+    /// Whether the filters match every file
+    pub fn is_empty(&self) -> bool {
+        self.is_scope_only() && self.collection_id.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SizeRange {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+/// Facet buckets returned alongside search results for drill-down filtering
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchFacets {
+    pub extensions: Vec<FacetBucket>,
+    pub categories: Vec<FacetBucket>,
+    pub years: Vec<FacetBucket>,
+    pub collections: Vec<FacetBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FacetBucket {
+    pub value: String,
+    pub label: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageCount {
+    /// ISO 639-1 code, or "unknown" when no language could be detected
+    pub language: String,
+    pub count: i64,
+}
+
+/// What a rescan compares to tell whether an indexed file changed on disk
+#[derive(Debug, Clone)]
+pub struct FileFingerprint {
+    pub id: String,
+    pub size: i64,
+    pub modified_at: DateTime<Utc>,
+    pub hash: Option<String>,
+    pub processing_status: String,
+}
+
+/// A file removed from disk that can still be restored until it is purged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedFile {
+    pub id: String,
+    pub path: String,
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// How often a query was run, from the search statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryCount {
+    pub query: String,
+    pub count: i64,
+    /// Day of the most recent search, YYYY-MM-DD
+    pub last_searched: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchTypeLatency {
+    pub search_type: String,
+    pub searches: i64,
+    pub avg_latency_ms: f64,
+    pub zero_result_rate: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchAnalytics {
+    pub total_searches: i64,
+    pub top_queries: Vec<QueryCount>,
+    pub latency_by_type: Vec<SearchTypeLatency>,
+    /// Queries that found nothing, pointing at gaps in the index
+    pub zero_result_queries: Vec<QueryCount>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    Tag,
+    Entity,
+    FileName,
+    Folder,
+}
+
+impl SuggestionKind {
+    /// How strongly a suggestion of this kind is preferred at equal frequency
+    fn weight(self) -> f64 {
+        match self {
+            SuggestionKind::Tag => 1.0,
+            SuggestionKind::Entity => 0.9,
+            SuggestionKind::Folder => 0.8,
+            SuggestionKind::FileName => 0.6,
+        }
+    }
+}
+
+/// A completion for a partial query drawn from the index itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSuggestion {
+    pub text: String,
+    pub kind: SuggestionKind,
+    /// Number of indexed files the suggestion refers to
+    pub count: i64,
+}
+
+/// Files sharing identical content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: i64,
+    /// Bytes that could be reclaimed by keeping a single copy
+    pub wasted_bytes: i64,
+    pub files: Vec<DuplicateFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    pub id: String,
+    pub path: String,
+    pub name: String,
+    pub modified_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub group_count: i64,
+    pub duplicate_files: i64,
+    pub total_wasted_bytes: i64,
+    pub groups: Vec<DuplicateGroup>,
+}
+
+/// One page of keyword search results
+#[derive(Debug)]
+pub struct SearchPage {
+    pub files: Vec<FileRecord>,
+    /// Opaque cursor for the following page, `None` on the last page
+    pub next_cursor: Option<String>,
+    /// Number of files matching the query and filters across all pages
+    pub total: i64,
+}
+
+/// Position of the last row of a page in the search ordering
+#[derive(Debug, Clone, PartialEq)]
+struct SearchCursor {
+    analysis_rank: i64,
+    status_rank: i64,
+    modified_at: String,
+    id: String,
+}
+
+impl SearchCursor {
+    fn encode(&self) -> String {
+        format!("{}|{}|{}|{}", self.analysis_rank, self.status_rank, self.modified_at, self.id)
+    }
+
+    fn decode(cursor: &str) -> Result<Self> {
+        let parts: Vec<&str> = cursor.splitn(4, '|').collect();
+        if parts.len() != 4 {
+            return Err(anyhow::anyhow!("Malformed search cursor"));
+        }
+
+        Ok(Self {
+            analysis_rank: parts[0].parse().map_err(|_| anyhow::anyhow!("Malformed search cursor"))?,
+            status_rank: parts[1].parse().map_err(|_| anyhow::anyhow!("Malformed search cursor"))?,
+            modified_at: parts[2].to_string(),
+            id: parts[3].to_string(),
+        })
+    }
+}
+
+const ANALYSIS_RANK_SQL: &str = "CASE WHEN f.ai_analysis IS NOT NULL THEN 1 ELSE 2 END";
+const STATUS_RANK_SQL: &str = "CASE WHEN f.processing_status = 'completed' THEN 1 ELSE 2 END";
+
+/// Rows written per transaction by `insert_files`
+pub const INSERT_BATCH_SIZE: usize = 500;
+
+/// The set of files a facet query aggregates over
+enum FacetScope<'a> {
+    Query { query: &'a QueryNode, filters: &'a SearchFilters },
+    FileIds(&'a [String]),
+}
+
+/// Date range on the modified (default), created or last processing attempt timestamp, both
+/// bounds inclusive
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DateRange {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub field: DateField,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateField {
+    #[default]
+    Modified,
+    Created,
+    /// When processing last started; files never processed have none and match no range
+    #[serde(rename = "last_attempt")]
+    LastAttempt,
+}
+
+impl DateField {
+    fn column(&self) -> &'static str {
+        match self {
+            DateField::Modified => "f.modified_at",
+            DateField::Created => "f.created_at",
+            DateField::LastAttempt => "f.last_attempt_at",
+        }
+    }
+}
+
 impl Database {
     pub async fn new<P: AsRef<Path>>(database_path: P) -> Result<Self> {
+        Self::open(database_path, None, &ConnectionSettings::default()).await
+    }
+
+    /// Open the database, encrypted with `key` through SQLCipher when one is given.
+    /// An existing unencrypted database is encrypted on the first open with a key.
+    pub async fn open<P: AsRef<Path>>(database_path: P, key: Option<&str>, settings: &ConnectionSettings) -> Result<Self> {
         let database_path = database_path.as_ref();
+        let synchronous = settings.synchronous_mode()?;
         
         // Create the database directory if it doesn't exist
         if let Some(parent) = database_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
 
+        // A restore requested in the previous session replaces the file before it is opened
+        if backup::apply_staged_restore(database_path).await? {
+            tracing::info!("Restored database from backup");
+        }
+
         // Create the database file if it doesn't exist
         if !database_path.exists() {
             tokio::fs::File::create(database_path).await?;
         }
 
-        let database_url = format!("sqlite:{}?mode=rwc", database_path.display());
-        let pool = SqlitePool::connect(&database_url).await?;
-        
-        // Set pragmas for better reliability
-        sqlx::query("PRAGMA journal_mode = WAL").execute(&pool).await?;
-        sqlx::query("PRAGMA synchronous = NORMAL").execute(&pool).await?;
-        sqlx::query("PRAGMA cache_size = 1000").execute(&pool).await?;
-        sqlx::query("PRAGMA temp_store = memory").execute(&pool).await?;
+        // Connection options apply to every pooled connection, unlike PRAGMA statements run on the pool
+        let mut options = SqliteConnectOptions::new()
+            .filename(database_path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(synchronous)
+            .busy_timeout(locking::BUSY_TIMEOUT)
+            .pragma("cache_size", settings.cache_size.to_string())
+            .pragma("mmap_size", settings.mmap_size.to_string())
+            .pragma("temp_store", "memory")
+            // Directory stats roll up through a trigger that fires itself
+            .pragma("recursive_triggers", "ON");
+        if let Some(key) = key {
+            if encryption::is_plaintext(database_path).await? {
+                tracing::info!("Encrypting existing database");
+                encryption::encrypt_in_place(database_path, key).await?;
+            }
+            // sqlx sends the key before any other pragma, as SQLCipher requires
+            options = options.pragma("key", encryption::key_pragma(key));
+        }
+        let pool = SqlitePoolOptions::new()
+            .max_connections(settings.max_connections)
+            .connect_with(options)
+            .await?;
         
         let db = Database { pool };
         
@@ -83,9 +426,17 @@ impl Database {
         self.create_collections_table().await?;
         self.create_file_collections_table().await?;
         self.create_fts_table().await?;
+        self.create_search_history_table().await?;
+        self.create_search_stats_table().await?;
+        self.create_tags_tables().await?;
+        self.create_search_tokens_table().await?;
         
-        // Run schema migrations
+        // Fixes for databases created before versioned migrations
         self.migrate_schema().await?;
+        self.migrate_json_tags().await?;
+        
+        // Versioned schema changes on top of the tables above
+        self.apply_migrations().await?;
         
         Ok(())
     }
@@ -123,6 +474,8 @@ impl Database {
             .execute(&self.pool).await?;
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_files_modified ON files(modified_at)")
             .execute(&self.pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_files_hash ON files(hash)")
+            .execute(&self.pool).await?;
 
         Ok(())
     }
@@ -208,6 +561,219 @@ impl Database {
         Ok(())
     }
 
+    async fn create_search_history_table(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS search_history (
+                query TEXT PRIMARY KEY,
+                hit_count INTEGER NOT NULL DEFAULT 1,
+                last_searched_at TEXT NOT NULL
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_search_history_last ON search_history(last_searched_at)")
+            .execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// One row per search; only the day is kept and the query text may be withheld
+    async fn create_search_stats_table(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS search_stats (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                query TEXT,
+                search_type TEXT NOT NULL,
+                result_count INTEGER NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                searched_on TEXT NOT NULL
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_search_stats_day ON search_stats(searched_on)")
+            .execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    async fn create_tags_tables(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS file_tags (
+                file_id TEXT NOT NULL,
+                tag_id INTEGER NOT NULL,
+                PRIMARY KEY (file_id, tag_id),
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE,
+                FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_file_tags_tag ON file_tags(tag_id)")
+            .execute(&self.pool).await?;
+
+        // The cascade covers connections enforcing foreign keys, as ours do; this also covers
+        // deletes made by tools that leave enforcement off
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_tags_delete AFTER DELETE ON files BEGIN
+                DELETE FROM file_tags WHERE file_id = old.id;
+            END
+            "#
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Folded, language-aware tokens of each file, matched alongside the raw text so
+    /// accents, case and unspaced scripts do not defeat keyword search
+    async fn create_search_tokens_table(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS file_search_tokens (
+                file_id TEXT PRIMARY KEY,
+                language TEXT,
+                tokens TEXT NOT NULL,
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_file_search_tokens_language ON file_search_tokens(language)")
+            .execute(&self.pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_search_tokens_delete AFTER DELETE ON files BEGIN
+                DELETE FROM file_search_tokens WHERE file_id = old.id;
+            END
+            "#
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Detect the language of a file and store its search tokens
+    pub async fn index_search_tokens(&self, file_id: &str, name: &str, content: Option<&str>, analysis: Option<&str>) -> Result<()> {
+        let (detected, tokens) = Self::search_tokens(name, content, analysis);
+
+        sqlx::query("INSERT OR REPLACE INTO file_search_tokens (file_id, language, tokens) VALUES (?, ?, ?)")
+            .bind(file_id)
+            .bind(detected)
+            .bind(tokens)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Detected language and folded search tokens of a file, tokenized for that language
+    fn search_tokens(name: &str, content: Option<&str>, analysis: Option<&str>) -> (Option<String>, String) {
+        let body = content.filter(|c| !c.trim().is_empty()).or(analysis).unwrap_or("");
+        let detected = language::detect_language(body);
+        let tokens = [Some(name), content, analysis]
+            .into_iter()
+            .flatten()
+            .map(|text| language::search_text_for(text, detected.as_deref()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        (detected, tokens)
+    }
+
+    /// Tokenize files indexed before search tokens existed. Returns the number of files indexed.
+    pub async fn backfill_search_tokens(&self, batch_size: i64) -> Result<usize> {
+        let mut indexed = 0;
+        loop {
+            let rows = sqlx::query(
+                r#"
+                SELECT id, name, content, ai_analysis FROM files
+                WHERE id NOT IN (SELECT file_id FROM file_search_tokens)
+                LIMIT ?
+                "#
+            )
+            .bind(batch_size)
+            .fetch_all(&self.pool)
+            .await?;
+            if rows.is_empty() {
+                return Ok(indexed);
+            }
+
+            for row in &rows {
+                let content: Option<String> = row.get("content");
+                let analysis: Option<String> = row.get("ai_analysis");
+                self.index_search_tokens(row.get("id"), row.get("name"), content.as_deref(), analysis.as_deref()).await?;
+            }
+            indexed += rows.len();
+        }
+    }
+
+    pub async fn get_language_distribution(&self) -> Result<Vec<LanguageCount>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT COALESCE(language, 'unknown') as language, COUNT(*) as count
+            FROM file_search_tokens
+            GROUP BY COALESCE(language, 'unknown')
+            ORDER BY count DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter()
+            .map(|row| LanguageCount { language: row.get("language"), count: row.get("count") })
+            .collect())
+    }
+
+    /// Populate `file_tags` from the legacy JSON `files.tags` column once
+    async fn migrate_json_tags(&self) -> Result<()> {
+        let (linked,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM file_tags")
+            .fetch_one(&self.pool)
+            .await?;
+        if linked > 0 {
+            return Ok(());
+        }
+
+        let mut tx = self.begin_immediate().await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO tags (name)
+            SELECT DISTINCT TRIM(j.value)
+            FROM files f, json_each(f.tags) j
+            WHERE json_valid(f.tags) AND json_type(f.tags) = 'array' AND TRIM(j.value) != ''
+            "#
+        ).execute(&mut *tx).await?;
+
+        let migrated = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO file_tags (file_id, tag_id)
+            SELECT f.id, t.id
+            FROM files f, json_each(f.tags) j
+            INNER JOIN tags t ON t.name = TRIM(j.value)
+            WHERE json_valid(f.tags) AND json_type(f.tags) = 'array'
+            "#
+        ).execute(&mut *tx).await?.rows_affected();
+
+        tx.commit().await?;
+
+        if migrated > 0 {
+            tracing::info!("Migrated {} file tags into file_tags", migrated);
+        }
+
+        Ok(())
+    }
+
     async fn migrate_schema(&self) -> Result<()> {
         // Check if content column exists in files table
         let columns: Vec<(String,)> = sqlx::query_as("PRAGMA table_info(files)")
@@ -264,6 +830,75 @@ impl Database {
         Ok(count.0 > 0)
     }
 
+    /// Insert many new files, committing every `INSERT_BATCH_SIZE` rows. Files whose path is
+    /// already indexed are left untouched; the ids of the records actually inserted are returned.
+    pub async fn insert_files(&self, files: &[FileRecord]) -> Result<Vec<String>> {
+        let mut inserted = Vec::new();
+
+        for batch in files.chunks(INSERT_BATCH_SIZE) {
+            let mut tx = self.begin_immediate().await?;
+            for file in batch {
+                let embedding_blob = file.embedding.as_ref().map(|e| {
+                    e.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>()
+                });
+
+                let result = sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO files 
+                    (id, path, name, extension, size, created_at, modified_at, last_accessed, 
+                     mime_type, hash, content, tags, metadata, ai_analysis, embedding, indexed_at, 
+                     processing_status, error_message)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#
+                )
+                .bind(&file.id)
+                .bind(&file.path)
+                .bind(&file.name)
+                .bind(&file.extension)
+                .bind(file.size)
+                .bind(file.created_at.to_rfc3339())
+                .bind(file.modified_at.to_rfc3339())
+                .bind(file.last_accessed.map(|dt| dt.to_rfc3339()))
+                .bind(&file.mime_type)
+                .bind(&file.hash)
+                .bind(&file.content)
+                .bind(&file.tags)
+                .bind(&file.metadata)
+                .bind(&file.ai_analysis)
+                .bind(embedding_blob)
+                .bind(file.indexed_at.map(|dt| dt.to_rfc3339()))
+                .bind(&file.processing_status)
+                .bind(&file.error_message)
+                .execute(&mut *tx)
+                .await?;
+
+                if result.rows_affected() == 0 {
+                    continue;
+                }
+
+                let (detected, tokens) = Self::search_tokens(&file.name, file.content.as_deref(), file.ai_analysis.as_deref());
+                sqlx::query("INSERT OR REPLACE INTO file_search_tokens (file_id, language, tokens) VALUES (?, ?, ?)")
+                    .bind(&file.id)
+                    .bind(detected)
+                    .bind(tokens)
+                    .execute(&mut *tx)
+                    .await?;
+                inserted.push(file.id.clone());
+            }
+            tx.commit().await?;
+        }
+
+        // Tag rows are rare for new files and go through the usual path
+        let inserted_ids: HashSet<&str> = inserted.iter().map(String::as_str).collect();
+        for file in files.iter().filter(|f| inserted_ids.contains(f.id.as_str())) {
+            if let Some(tags) = file.tags.as_deref().and_then(|t| serde_json::from_str::<Vec<String>>(t).ok()) {
+                self.set_file_tags(&file.id, &tags).await?;
+            }
+        }
+
+        Ok(inserted)
+    }
+
     pub async fn insert_file(&self, file: &FileRecord) -> Result<()> {
         let embedding_blob = file.embedding.as_ref().map(|e| {
             e.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>()
@@ -299,6 +934,11 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        if let Some(tags) = file.tags.as_deref().and_then(|t| serde_json::from_str::<Vec<String>>(t).ok()) {
+            self.set_file_tags(&file.id, &tags).await?;
+        }
+        self.index_search_tokens(&file.id, &file.name, file.content.as_deref(), file.ai_analysis.as_deref()).await?;
+
         Ok(())
     }
 
@@ -315,13 +955,28 @@ impl Database {
         }
     }
 
-    pub async fn get_files_by_status(&self, status: &str) -> Result<Vec<FileRecord>> {
-        let rows = sqlx::query("SELECT * FROM files WHERE processing_status = ? ORDER BY modified_at DESC")
-            .bind(status)
+    pub async fn get_file_by_id(&self, id: &str) -> Result<Option<FileRecord>> {
+        let row = sqlx::query("SELECT * FROM files WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if let Some(row) = row {
+            Ok(Some(self.row_to_file_record(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A page of files that are not deleted, ordered by path
+    pub async fn get_active_files(&self, limit: i64, offset: i64) -> Result<Vec<FileRecord>> {
+        let rows = sqlx::query("SELECT * FROM files WHERE deleted_at IS NULL ORDER BY path LIMIT ? OFFSET ?")
+            .bind(limit)
+            .bind(offset)
             .fetch_all(&self.pool)
             .await?;
 
-        let mut files = Vec::new();
+        let mut files = Vec::with_capacity(rows.len());
         for row in rows {
             files.push(self.row_to_file_record(row)?);
         }
@@ -329,25 +984,53 @@ impl Database {
         Ok(files)
     }
 
-    pub async fn get_error_files_in_location(&self, location_path: &str) -> Result<Vec<FileRecord>> {
-        let query = if std::path::Path::new(location_path).is_file() {
-            // For individual files, match exact path
-            r#"
-            SELECT * FROM files 
-            WHERE path = ? AND processing_status = 'error'
-            ORDER BY modified_at DESC
-            "#
-        } else {
-            // For directories, match files within that directory  
-            r#"
-            SELECT * FROM files 
-            WHERE path LIKE ? || '%' AND processing_status = 'error'
+    pub async fn get_files_by_hash(&self, hash: &str) -> Result<Vec<FileRecord>> {
+        let rows = sqlx::query("SELECT * FROM files WHERE hash = ? AND deleted_at IS NULL")
+            .bind(hash)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut files = Vec::with_capacity(rows.len());
+        for row in rows {
+            files.push(self.row_to_file_record(row)?);
+        }
+
+        Ok(files)
+    }
+
+    pub async fn get_files_by_status(&self, status: &str) -> Result<Vec<FileRecord>> {
+        let rows = sqlx::query("SELECT * FROM files WHERE processing_status = ? ORDER BY modified_at DESC")
+            .bind(status)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(self.row_to_file_record(row)?);
+        }
+
+        Ok(files)
+    }
+
+    pub async fn get_error_files_in_location(&self, location_path: &str) -> Result<Vec<FileRecord>> {
+        let (query, pattern) = if std::path::Path::new(location_path).is_file() {
+            // For individual files, match exact path
+            (r#"
+            SELECT * FROM files 
+            WHERE path = ? AND processing_status = 'error'
             ORDER BY modified_at DESC
-            "#
+            "#, location_path.to_string())
+        } else {
+            // For directories, match files within that directory  
+            (r#"
+            SELECT * FROM files 
+            WHERE path LIKE ? ESCAPE '\' AND processing_status = 'error'
+            ORDER BY modified_at DESC
+            "#, paths_below(location_path))
         };
         
         let rows = sqlx::query(query)
-            .bind(location_path)
+            .bind(pattern)
             .fetch_all(&self.pool)
             .await?;
 
@@ -360,67 +1043,917 @@ impl Database {
     }
 
     pub async fn update_file_status(&self, file_id: &str, status: &str, error_message: Option<&str>) -> Result<()> {
-        sqlx::query("UPDATE files SET processing_status = ?, error_message = ? WHERE id = ?")
-            .bind(status)
-            .bind(error_message)
+        // Starting to process a file is an attempt
+        let attempted_at = (status == "processing").then(|| Utc::now().to_rfc3339());
+        // Called from every queue worker, so the most likely write to meet contention
+        Self::retry_on_busy(|| async {
+            sqlx::query("UPDATE files SET processing_status = ?, error_message = ?, last_attempt_at = COALESCE(?, last_attempt_at) WHERE id = ?")
+                .bind(status)
+                .bind(error_message)
+                .bind(&attempted_at)
+                .bind(file_id)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        }).await
+    }
+
+    /// Put files left `processing` by a worker that crashed or a session that ended mid-job
+    /// back to `pending`. Returns how many there were.
+    pub async fn reset_processing_files(&self) -> Result<u64> {
+        let result = sqlx::query("UPDATE files SET processing_status = 'pending' WHERE processing_status = 'processing'")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Set the status of every file matching `filters` in a single statement, leaving the rest
+    /// of each record as it was. Soft-deleted files are never touched, so they cannot be brought
+    /// back for processing. Returns the updated files.
+    pub async fn bulk_update_status(&self, filters: &SearchFilters, status: &str, error_message: Option<&str>) -> Result<Vec<FileRecord>> {
+        if status == "deleted" {
+            return Err(anyhow::anyhow!("Files are deleted with mark_file_deleted, not a status update"));
+        }
+
+        let rows = Self::retry_on_busy(|| async {
+            let mut builder = QueryBuilder::<Sqlite>::new("UPDATE files AS f SET processing_status = ");
+            builder.push_bind(status)
+                .push(", error_message = ")
+                .push_bind(error_message)
+                .push(" WHERE f.deleted_at IS NULL");
+            Self::push_filter_predicates(&mut builder, filters);
+            builder.push(" RETURNING *");
+            Ok(builder.build().fetch_all(&self.pool).await?)
+        }).await?;
+
+        rows.into_iter().map(|row| self.row_to_file_record(row)).collect()
+    }
+
+    /// Mark a file as removed from disk. Its content, analysis and embedding are dropped, and
+    /// triggers clear its search rows, collection links and vectors; the record itself, with its
+    /// tags and history, stays until `purge_deleted_files`.
+    pub async fn mark_file_deleted(&self, file_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE files
+            SET processing_status = 'deleted', deleted_at = ?,
+                content = NULL, ai_analysis = NULL, embedding = NULL, indexed_at = NULL
+            WHERE id = ? AND deleted_at IS NULL
+            "#
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark every file below `directory` as removed from disk, as `mark_file_deleted` does,
+    /// after the folder was deleted or moved to the trash. Returns how many were marked.
+    pub async fn mark_directory_deleted(&self, directory: &str) -> Result<u64> {
+        let prefix = format!("{}{}", directory.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+        let result = sqlx::query(
+            r#"
+            UPDATE files
+            SET processing_status = 'deleted', deleted_at = ?,
+                content = NULL, ai_analysis = NULL, embedding = NULL, indexed_at = NULL
+            WHERE substr(path, 1, length(?)) = ? AND deleted_at IS NULL
+            "#
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(&prefix)
+        .bind(&prefix)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Undo a deletion, returning false if the file is not deleted or was already purged.
+    /// The file is pending again since its index went with the deletion.
+    pub async fn restore_deleted_file(&self, file_id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE files
+            SET deleted_at = NULL, processing_status = 'pending'
+            WHERE id = ? AND deleted_at IS NOT NULL
+            "#
+        )
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        // Findable by name until it is processed again
+        if let Some(file) = self.get_file_by_id(file_id).await? {
+            self.index_search_tokens(&file.id, &file.name, None, None).await?;
+        }
+        Ok(true)
+    }
+
+    /// Point a record at the path its file was renamed to, keeping its analysis and history
+    pub async fn rename_file(&self, file_id: &str, new_path: &str) -> Result<()> {
+        let name = Path::new(new_path).file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        sqlx::query("UPDATE files SET path = ?, name = ? WHERE id = ?")
+            .bind(new_path)
+            .bind(&name)
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+
+        // The name is part of the search tokens
+        if let Some(file) = self.get_file_by_id(file_id).await? {
+            self.index_search_tokens(&file.id, &file.name, file.content.as_deref(), file.ai_analysis.as_deref()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Move the records of every file below `from` to the same place below `to` after a folder
+    /// was renamed. Files whose new path is already taken are left for reconciliation.
+    pub async fn rename_directory(&self, from: &str, to: &str) -> Result<u64> {
+        let from_prefix = format!("{}{}", from.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+        let to_prefix = format!("{}{}", to.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+
+        let result = sqlx::query(
+            r#"
+            UPDATE files
+            SET path = ? || substr(path, length(?) + 1)
+            WHERE substr(path, 1, length(?)) = ?
+              AND NOT EXISTS (
+                  SELECT 1 FROM files AS taken WHERE taken.path = ? || substr(files.path, length(?) + 1)
+              )
+            "#
+        )
+        .bind(&to_prefix)
+        .bind(&from_prefix)
+        .bind(&from_prefix)
+        .bind(&from_prefix)
+        .bind(&to_prefix)
+        .bind(&from_prefix)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deleted files awaiting purge, most recently deleted first
+    pub async fn get_deleted_files(&self) -> Result<Vec<DeletedFile>> {
+        let rows = sqlx::query(
+            "SELECT id, path, name, deleted_at FROM files WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut files = Vec::with_capacity(rows.len());
+        for row in rows {
+            let deleted_at: String = row.get("deleted_at");
+            files.push(DeletedFile {
+                id: row.get("id"),
+                path: row.get("path"),
+                name: row.get("name"),
+                deleted_at: DateTime::parse_from_rfc3339(&deleted_at)?.with_timezone(&Utc),
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Permanently remove files deleted before `cutoff`, returning how many were purged.
+    /// Triggers on `files` clear their search rows, tags and vectors; collection links are removed here.
+    pub async fn purge_deleted_files(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let cutoff = cutoff.to_rfc3339();
+        let mut tx = self.begin_immediate().await?;
+
+        let links = sqlx::query(
+            "DELETE FROM file_collections WHERE file_id IN (SELECT id FROM files WHERE deleted_at < ?)"
+        )
+        .bind(&cutoff)
+        .execute(&mut *tx)
+        .await?;
+
+        let purged = sqlx::query("DELETE FROM files WHERE deleted_at < ?")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?;
+
+        if links.rows_affected() > 0 {
+            sqlx::query(
+                r#"
+                UPDATE collections
+                SET file_count = (
+                    SELECT COUNT(*) FROM file_collections WHERE collection_id = collections.id
+                )
+                "#
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(purged.rows_affected())
+    }
+
+    /// Files indexed before `cutoff` that still hold extracted content, analysis or an embedding
+    pub async fn get_files_past_retention(&self, cutoff: DateTime<Utc>, limit: i64) -> Result<Vec<String>> {
+        let ids = sqlx::query_scalar(
+            r#"
+            SELECT id FROM files
+            WHERE indexed_at < ?
+              AND (content IS NOT NULL OR ai_analysis IS NOT NULL OR embedding IS NOT NULL)
+            ORDER BY indexed_at
+            LIMIT ?
+            "#
+        )
+        .bind(cutoff.to_rfc3339())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Drop the stored content, analysis and embedding of files, keeping path, name, size,
+    /// dates, tags and metadata. Search tokens are rebuilt from the name alone.
+    pub async fn strip_file_content(&self, file_ids: &[String]) -> Result<u64> {
+        if file_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "UPDATE files SET content = NULL, ai_analysis = NULL, embedding = NULL WHERE id IN ("
+        );
+        let mut separated = builder.separated(", ");
+        for id in file_ids {
+            separated.push_bind(id);
+        }
+        separated.push_unseparated(")");
+        let stripped = builder.build().execute(&self.pool).await?.rows_affected();
+
+        // Earlier summaries are derived from the content too
+        let mut builder = QueryBuilder::<Sqlite>::new("UPDATE file_versions SET summary = NULL WHERE file_id IN (");
+        let mut separated = builder.separated(", ");
+        for id in file_ids {
+            separated.push_bind(id);
+        }
+        separated.push_unseparated(")");
+        builder.build().execute(&self.pool).await?;
+
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT id, name FROM files WHERE id IN (");
+        let mut separated = builder.separated(", ");
+        for id in file_ids {
+            separated.push_bind(id);
+        }
+        separated.push_unseparated(")");
+        for row in builder.build().fetch_all(&self.pool).await? {
+            let id: String = row.get("id");
+            let name: String = row.get("name");
+            self.index_search_tokens(&id, &name, None, None).await?;
+        }
+
+        Ok(stripped)
+    }
+
+    pub async fn update_file_analysis(&self, file_id: &str, content: &str, analysis: &str, tags: Option<&str>, embedding: Option<&[f32]>) -> Result<()> {
+        let embedding_blob = embedding.map(|e| {
+            e.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>()
+        });
+
+        Self::retry_on_busy(|| async {
+            sqlx::query(
+                "UPDATE files SET content = ?, ai_analysis = ?, tags = ?, embedding = ?, processing_status = 'completed', indexed_at = ? WHERE id = ?"
+            )
+            .bind(content)
+            .bind(analysis)
+            .bind(tags)
+            .bind(&embedding_blob)
+            .bind(Utc::now().to_rfc3339())
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }).await?;
+
+        if let Some(tags) = tags.and_then(|t| serde_json::from_str::<Vec<String>>(t).ok()) {
+            self.set_file_tags(file_id, &tags).await?;
+        }
+
+        let name: Option<String> = sqlx::query_scalar("SELECT name FROM files WHERE id = ?")
+            .bind(file_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if let Some(name) = name {
+            self.index_search_tokens(file_id, &name, Some(content), Some(analysis)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Take over the analysis of a file from another index, keeping its original `indexed_at`
+    pub async fn apply_imported_analysis(&self, file_id: &str, imported: &FileRecord) -> Result<()> {
+        let embedding_blob = imported.embedding.as_ref().map(|e| {
+            e.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>()
+        });
+
+        sqlx::query(
+            r#"
+            UPDATE files
+            SET content = ?, ai_analysis = ?, tags = ?, metadata = ?, embedding = ?, hash = COALESCE(?, hash),
+                indexed_at = ?, processing_status = 'completed', error_message = NULL
+            WHERE id = ?
+            "#
+        )
+        .bind(&imported.content)
+        .bind(&imported.ai_analysis)
+        .bind(&imported.tags)
+        .bind(&imported.metadata)
+        .bind(embedding_blob)
+        .bind(&imported.hash)
+        .bind(imported.indexed_at.unwrap_or_else(Utc::now).to_rfc3339())
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+
+        let tags = imported.tags.as_deref()
+            .and_then(|t| serde_json::from_str::<Vec<String>>(t).ok())
+            .unwrap_or_default();
+        self.set_file_tags(file_id, &tags).await?;
+
+        let name: Option<String> = sqlx::query_scalar("SELECT name FROM files WHERE id = ?")
+            .bind(file_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if let Some(name) = name {
+            self.index_search_tokens(file_id, &name, imported.content.as_deref(), imported.ai_analysis.as_deref()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the normalized tag links of a file
+    pub async fn set_file_tags(&self, file_id: &str, tags: &[String]) -> Result<()> {
+        let mut tx = self.begin_immediate().await?;
+
+        sqlx::query("DELETE FROM file_tags WHERE file_id = ?")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("INSERT OR IGNORE INTO file_tags (file_id, tag_id) SELECT ?, id FROM tags WHERE name = ?")
+                .bind(file_id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Tags in use, most frequent first
+    pub async fn list_tags(&self) -> Result<Vec<TagCount>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT t.name as name, COUNT(ft.file_id) as count
+            FROM tags t
+            INNER JOIN file_tags ft ON ft.tag_id = t.id
+            INNER JOIN files f ON f.id = ft.file_id
+            WHERE f.deleted_at IS NULL
+            GROUP BY t.id
+            ORDER BY count DESC, t.name ASC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter()
+            .map(|row| TagCount { name: row.get("name"), count: row.get("count") })
+            .collect())
+    }
+
+    /// Rename a tag, merging it into `new_name` if that tag already exists.
+    /// Returns the number of files whose tags changed.
+    pub async fn rename_tag(&self, old_name: &str, new_name: &str) -> Result<u64> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(anyhow::anyhow!("Tag name cannot be empty"));
+        }
+
+        let mut tx = self.begin_immediate().await?;
+
+        let old_id: i64 = sqlx::query_scalar("SELECT id FROM tags WHERE name = ?")
+            .bind(old_name.trim())
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Tag not found: {}", old_name))?;
+
+        let affected: Vec<String> = sqlx::query_scalar("SELECT file_id FROM file_tags WHERE tag_id = ?")
+            .bind(old_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM tags WHERE name = ?")
+            .bind(new_name)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match existing {
+            Some(target_id) if target_id != old_id => {
+                sqlx::query("INSERT OR IGNORE INTO file_tags (file_id, tag_id) SELECT file_id, ? FROM file_tags WHERE tag_id = ?")
+                    .bind(target_id)
+                    .bind(old_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM file_tags WHERE tag_id = ?")
+                    .bind(old_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM tags WHERE id = ?")
+                    .bind(old_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            _ => {
+                // Same tag, possibly with a different capitalisation
+                sqlx::query("UPDATE tags SET name = ? WHERE id = ?")
+                    .bind(new_name)
+                    .bind(old_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        // Keep the JSON column, which text search still reads, in step with file_tags
+        for file_id in &affected {
+            sqlx::query(
+                r#"
+                UPDATE files SET tags = (
+                    SELECT json_group_array(t.name)
+                    FROM file_tags ft INNER JOIN tags t ON t.id = ft.tag_id
+                    WHERE ft.file_id = files.id
+                )
+                WHERE id = ?
+                "#
+            )
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(affected.len() as u64)
+    }
+
+    pub async fn update_file_hash(&self, file_id: &str, hash: &str) -> Result<()> {
+        sqlx::query("UPDATE files SET hash = ? WHERE id = ?")
+            .bind(hash)
             .bind(file_id)
             .execute(&self.pool)
             .await?;
 
-        Ok(())
-    }
+        Ok(())
+    }
+
+    /// Replace the type guessed from the file's extension with the one its bytes identify
+    pub async fn update_file_mime_type(&self, file_id: &str, mime_type: &str) -> Result<()> {
+        sqlx::query("UPDATE files SET mime_type = ? WHERE id = ?")
+            .bind(mime_type)
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Size, mtime and hash of the indexed files among `paths`, keyed by path
+    pub async fn get_file_fingerprints(&self, paths: &[String]) -> Result<HashMap<String, FileFingerprint>> {
+        let mut fingerprints = HashMap::new();
+        if paths.is_empty() {
+            return Ok(fingerprints);
+        }
+
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "SELECT id, path, size, modified_at, hash, processing_status FROM files WHERE path IN ("
+        );
+        let mut separated = builder.separated(", ");
+        for path in paths {
+            separated.push_bind(path);
+        }
+        separated.push_unseparated(")");
+
+        for row in builder.build().fetch_all(&self.pool).await? {
+            let modified_at: String = row.get("modified_at");
+            fingerprints.insert(row.get("path"), FileFingerprint {
+                id: row.get("id"),
+                size: row.get("size"),
+                modified_at: DateTime::parse_from_rfc3339(&modified_at)?.with_timezone(&Utc),
+                hash: row.get("hash"),
+                processing_status: row.get("processing_status"),
+            });
+        }
+
+        Ok(fingerprints)
+    }
+
+    /// Record a new size and mtime. A changed file is also set back to pending for reprocessing.
+    pub async fn update_file_stat(&self, file_id: &str, size: i64, modified_at: DateTime<Utc>, changed: bool) -> Result<()> {
+        let query = if changed {
+            "UPDATE files SET size = ?, modified_at = ?, processing_status = 'pending', error_message = NULL WHERE id = ?"
+        } else {
+            "UPDATE files SET size = ?, modified_at = ? WHERE id = ?"
+        };
+
+        let mut tx = self.begin_immediate().await?;
+        // Reprocessing overwrites the summary and tags, so keep the previous ones
+        if changed {
+            versions::record_version(&mut tx, file_id).await?;
+        }
+        sqlx::query(query)
+            .bind(size)
+            .bind(modified_at.to_rfc3339())
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Groups of files with identical content hashes, largest waste first
+    pub async fn find_duplicates(&self, limit: i64) -> Result<DuplicateReport> {
+        let summary = sqlx::query(
+            r#"
+            SELECT 
+                COUNT(*) as group_count,
+                COALESCE(SUM(copies), 0) as duplicate_files,
+                COALESCE(SUM((copies - 1) * size), 0) as wasted_bytes
+            FROM (
+                SELECT COUNT(*) as copies, MAX(size) as size
+                FROM files
+                WHERE hash IS NOT NULL AND deleted_at IS NULL
+                GROUP BY hash
+                HAVING COUNT(*) > 1
+            )
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let group_rows = sqlx::query(
+            r#"
+            SELECT hash, MAX(size) as size, COUNT(*) as copies
+            FROM files
+            WHERE hash IS NOT NULL AND deleted_at IS NULL
+            GROUP BY hash
+            HAVING COUNT(*) > 1
+            ORDER BY (COUNT(*) - 1) * MAX(size) DESC, hash ASC
+            LIMIT ?
+            "#
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut groups = Vec::with_capacity(group_rows.len());
+        for row in group_rows {
+            let hash: String = row.get("hash");
+            let size: i64 = row.get("size");
+            let copies: i64 = row.get("copies");
+
+            let files = sqlx::query("SELECT id, path, name, modified_at FROM files WHERE hash = ? AND deleted_at IS NULL ORDER BY modified_at ASC")
+                .bind(&hash)
+                .fetch_all(&self.pool)
+                .await?
+                .iter()
+                .map(|file| DuplicateFile {
+                    id: file.get("id"),
+                    path: file.get("path"),
+                    name: file.get("name"),
+                    modified_at: file.get("modified_at"),
+                })
+                .collect();
+
+            groups.push(DuplicateGroup {
+                hash,
+                size,
+                wasted_bytes: (copies - 1) * size,
+                files,
+            });
+        }
+
+        Ok(DuplicateReport {
+            group_count: summary.get("group_count"),
+            duplicate_files: summary.get("duplicate_files"),
+            total_wasted_bytes: summary.get("wasted_bytes"),
+            groups,
+        })
+    }
+
+    // Search operations
+    pub async fn search_files(&self, query: &str, limit: i64, offset: i64) -> Result<Vec<FileRecord>> {
+        let query = QueryNode::parse(query)?;
+        self.search_files_filtered(&query, &SearchFilters::default(), limit, offset).await
+    }
+
+    pub async fn search_files_filtered(&self, query: &QueryNode, filters: &SearchFilters, limit: i64, offset: i64) -> Result<Vec<FileRecord>> {
+        // Enhanced search with AI analysis prioritization
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT f.* FROM files f WHERE ");
+        Self::push_query_predicate(&mut builder, query);
+        Self::push_filter_predicates(&mut builder, filters);
+        Self::push_search_order(&mut builder);
+        builder.push(" LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(self.row_to_file_record(row)?);
+        }
+
+        Ok(files)
+    }
+
+    /// Keyset-paginated search; pass the previous page's `next_cursor` to continue
+    pub async fn search_files_page(&self, query: &QueryNode, filters: &SearchFilters, limit: i64, cursor: Option<&str>) -> Result<SearchPage> {
+        let cursor = cursor.map(SearchCursor::decode).transpose()?;
+
+        let mut builder = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT f.*, {} AS cursor_analysis_rank, {} AS cursor_status_rank FROM files f WHERE ",
+            ANALYSIS_RANK_SQL, STATUS_RANK_SQL
+        ));
+        Self::push_query_predicate(&mut builder, query);
+        Self::push_filter_predicates(&mut builder, filters);
+
+        if let Some(cursor) = &cursor {
+            // Rows strictly after the cursor: ranks ascending, then newest first, then id
+            builder.push(format!(" AND ({} > ", ANALYSIS_RANK_SQL)).push_bind(cursor.analysis_rank);
+            builder.push(format!(" OR ({} = ", ANALYSIS_RANK_SQL)).push_bind(cursor.analysis_rank);
+            builder.push(format!(" AND ({} > ", STATUS_RANK_SQL)).push_bind(cursor.status_rank);
+            builder.push(format!(" OR ({} = ", STATUS_RANK_SQL)).push_bind(cursor.status_rank);
+            builder.push(" AND (f.modified_at < ").push_bind(cursor.modified_at.clone());
+            builder.push(" OR (f.modified_at = ").push_bind(cursor.modified_at.clone());
+            builder.push(" AND f.id > ").push_bind(cursor.id.clone());
+            builder.push("))))))");
+        }
+
+        Self::push_search_order(&mut builder);
+        // One extra row tells us whether another page exists
+        builder.push(" LIMIT ").push_bind(limit + 1);
+
+        let mut rows = builder.build().fetch_all(&self.pool).await?;
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit.max(0) as usize);
+
+        let next_cursor = match rows.last() {
+            Some(row) if has_more => Some(SearchCursor {
+                analysis_rank: row.try_get("cursor_analysis_rank")?,
+                status_rank: row.try_get("cursor_status_rank")?,
+                modified_at: row.try_get("modified_at")?,
+                id: row.try_get("id")?,
+            }.encode()),
+            _ => None,
+        };
+
+        let mut files = Vec::with_capacity(rows.len());
+        for row in rows {
+            files.push(self.row_to_file_record(row)?);
+        }
+
+        let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM files f WHERE ");
+        Self::push_query_predicate(&mut count, query);
+        Self::push_filter_predicates(&mut count, filters);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        Ok(SearchPage { files, next_cursor, total })
+    }
+
+    fn push_search_order(builder: &mut QueryBuilder<'_, Sqlite>) {
+        builder.push(format!(
+            " ORDER BY {}, {}, f.modified_at DESC, f.id ASC",
+            ANALYSIS_RANK_SQL, STATUS_RANK_SQL
+        ));
+    }
+
+    /// Aggregate facet counts for the files matched by a text search
+    pub async fn get_search_facets(&self, query: &QueryNode, filters: &SearchFilters) -> Result<SearchFacets> {
+        self.collect_facets(FacetScope::Query { query, filters }).await
+    }
+
+    /// Aggregate facet counts for an explicit result set, e.g. semantic search hits
+    pub async fn get_facets_for_files(&self, file_ids: &[String]) -> Result<SearchFacets> {
+        if file_ids.is_empty() {
+            return Ok(SearchFacets::default());
+        }
+        self.collect_facets(FacetScope::FileIds(file_ids)).await
+    }
+
+    async fn collect_facets(&self, scope: FacetScope<'_>) -> Result<SearchFacets> {
+        let category_select = format!("{} as value, NULL as label", CATEGORY_CASE_SQL);
+
+        Ok(SearchFacets {
+            extensions: self.facet_counts("COALESCE(f.extension, '') as value, NULL as label", "", "value", &scope).await?,
+            categories: self.facet_counts(&category_select, "", "value", &scope).await?,
+            years: self.facet_counts("substr(f.modified_at, 1, 4) as value, NULL as label", "", "value", &scope).await?,
+            collections: self.facet_counts(
+                "c.id as value, c.name as label",
+                " INNER JOIN file_collections fc ON f.id = fc.file_id INNER JOIN collections c ON c.id = fc.collection_id",
+                "c.id",
+                &scope,
+            ).await?,
+        })
+    }
+
+    async fn facet_counts(&self, select: &str, joins: &str, group_by: &str, scope: &FacetScope<'_>) -> Result<Vec<FacetBucket>> {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {}, COUNT(*) as count FROM files f{} WHERE ", select, joins));
+
+        match scope {
+            FacetScope::Query { query, filters } => {
+                Self::push_query_predicate(&mut builder, query);
+                Self::push_filter_predicates(&mut builder, filters);
+            }
+            FacetScope::FileIds(ids) => {
+                builder.push("f.id IN (");
+                let mut separated = builder.separated(", ");
+                for id in ids.iter() {
+                    separated.push_bind(id.clone());
+                }
+                separated.push_unseparated(")");
+            }
+        }
+
+        builder.push(format!(" GROUP BY {} ORDER BY count DESC, value ASC", group_by));
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(|row| {
+            let value: String = row.get("value");
+            let label: Option<String> = row.get("label");
+            FacetBucket {
+                label: label.unwrap_or_else(|| value.clone()),
+                value,
+                count: row.get("count"),
+            }
+        }).collect())
+    }
+
+    /// Ids from `file_ids` whose indexed text satisfies `query` and `filters`
+    pub async fn file_ids_matching(&self, file_ids: &[String], query: &QueryNode, filters: &SearchFilters) -> Result<Vec<String>> {
+        if file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT f.id FROM files f WHERE f.id IN (");
+        let mut separated = builder.separated(", ");
+        for id in file_ids {
+            separated.push_bind(id.clone());
+        }
+        separated.push_unseparated(") AND ");
+        Self::push_query_predicate(&mut builder, query);
+        Self::push_filter_predicates(&mut builder, filters);
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(|row| row.get("id")).collect())
+    }
+
+    /// Translate a parsed boolean query into a parenthesised SQL predicate
+    fn push_query_predicate(builder: &mut QueryBuilder<'_, Sqlite>, query: &QueryNode) {
+        match query {
+            QueryNode::Term(text) | QueryNode::Phrase(text) => Self::push_text_predicate(builder, text),
+            QueryNode::Path(path) => {
+                // Absolute paths scope to a subtree, anything else matches a path fragment
+                if Path::new(path).is_absolute() {
+                    builder.push("(f.path = ").push_bind(path.clone());
+                    builder.push(" OR f.path LIKE ").push_bind(paths_below(path)).push(r" ESCAPE '\')");
+                } else {
+                    builder.push("f.path LIKE ").push_bind(format!("%{}%", escape_like(path))).push(r" ESCAPE '\'");
+                }
+            }
+            QueryNode::And(children) if children.is_empty() => {
+                builder.push("1 = 1");
+            }
+            QueryNode::And(children) | QueryNode::Or(children) => {
+                let operator = if matches!(query, QueryNode::And(_)) { " AND " } else { " OR " };
+                builder.push("(");
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        builder.push(operator);
+                    }
+                    Self::push_query_predicate(builder, child);
+                }
+                builder.push(")");
+            }
+            QueryNode::Not(inner) => {
+                builder.push("NOT ");
+                Self::push_query_predicate(builder, inner);
+            }
+        }
+    }
+
+    /// Push the substring match over name, content, analysis and tags
+    fn push_text_predicate(builder: &mut QueryBuilder<'_, Sqlite>, text: &str) {
+        let search_pattern = format!("%{}%", escape_like(text));
+
+        // COALESCE keeps the predicate two-valued so NOT behaves on files without content
+        builder.push("(f.name LIKE ").push_bind(search_pattern.clone()).push(r" ESCAPE '\'");
+        builder.push(" OR COALESCE(f.content, '') LIKE ").push_bind(search_pattern.clone()).push(r" ESCAPE '\'");
+        builder.push(" OR COALESCE(f.ai_analysis, '') LIKE ").push_bind(search_pattern.clone()).push(r" ESCAPE '\'");
+        builder.push(" OR COALESCE(f.tags, '') LIKE ").push_bind(search_pattern).push(r" ESCAPE '\'");
+
+        // Folded tokens catch accent, case and script differences the raw LIKE misses
+        let tokens = language::search_text(text);
+        if !tokens.is_empty() {
+            builder.push(" OR EXISTS (SELECT 1 FROM file_search_tokens st WHERE st.file_id = f.id AND st.tokens LIKE ")
+                .push_bind(format!("%{}%", escape_like(&tokens)))
+                .push(r" ESCAPE '\')");
+        }
+        builder.push(")");
+    }
+
+    /// Append one `AND` predicate per populated filter field (files are aliased as `f`)
+    fn push_filter_predicates(builder: &mut QueryBuilder<'_, Sqlite>, filters: &SearchFilters) {
+        // Deleted files wait for purge out of sight unless asked for by status
+        if !filters.processing_status.iter().any(|status| status == "deleted") {
+            builder.push(" AND f.deleted_at IS NULL");
+        }
+
+        if !filters.extensions.is_empty() {
+            builder.push(" AND LOWER(f.extension) IN (");
+            let mut separated = builder.separated(", ");
+            for extension in &filters.extensions {
+                separated.push_bind(extension.trim_start_matches('.').to_lowercase());
+            }
+            separated.push_unseparated(")");
+        }
 
-    pub async fn update_file_analysis(&self, file_id: &str, content: &str, analysis: &str, tags: Option<&str>, embedding: Option<&[f32]>) -> Result<()> {
-        let embedding_blob = embedding.map(|e| {
-            e.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>()
-        });
+        if !filters.mime_categories.is_empty() {
+            builder.push(" AND (");
+            for (i, category) in filters.mime_categories.iter().enumerate() {
+                if i > 0 {
+                    builder.push(" OR ");
+                }
+                builder.push("f.mime_type LIKE ")
+                    .push_bind(format!("{}/%", escape_like(&category.to_lowercase())))
+                    .push(r" ESCAPE '\'");
+            }
+            builder.push(")");
+        }
 
-        sqlx::query(
-            "UPDATE files SET content = ?, ai_analysis = ?, tags = ?, embedding = ?, processing_status = 'completed', indexed_at = ? WHERE id = ?"
-        )
-        .bind(content)
-        .bind(analysis)
-        .bind(tags)
-        .bind(embedding_blob)
-        .bind(Utc::now().to_rfc3339())
-        .bind(file_id)
-        .execute(&self.pool)
-        .await?;
+        if let Some(size_range) = &filters.size_range {
+            if let Some(min) = size_range.min {
+                builder.push(" AND f.size >= ").push_bind(min);
+            }
+            if let Some(max) = size_range.max {
+                builder.push(" AND f.size <= ").push_bind(max);
+            }
+        }
 
-        Ok(())
-    }
+        // Timestamps are stored as RFC 3339 in UTC, so string comparison preserves ordering
+        if let Some(date_range) = &filters.date_range {
+            let column = date_range.field.column();
+            if let Some(start) = date_range.start {
+                builder.push(format!(" AND {} >= ", column)).push_bind(start.to_rfc3339());
+            }
+            if let Some(end) = date_range.end {
+                builder.push(format!(" AND {} <= ", column)).push_bind(end.to_rfc3339());
+            }
+        }
 
-    // Search operations
-    pub async fn search_files(&self, query: &str, limit: i64, offset: i64) -> Result<Vec<FileRecord>> {
-        // Enhanced search with AI analysis prioritization
-        let search_pattern = format!("%{}%", query);
-        
-        let rows = sqlx::query(
-            r#"
-            SELECT f.* FROM files f
-            WHERE f.name LIKE ? OR f.content LIKE ? OR f.ai_analysis LIKE ? OR f.tags LIKE ?
-            ORDER BY 
-                CASE WHEN f.ai_analysis IS NOT NULL THEN 1 ELSE 2 END,
-                CASE WHEN f.processing_status = 'completed' THEN 1 ELSE 2 END,
-                f.modified_at DESC
-            LIMIT ? OFFSET ?
-            "#
-        )
-        .bind(&search_pattern)
-        .bind(&search_pattern)
-        .bind(&search_pattern)
-        .bind(&search_pattern)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&self.pool)
-        .await?;
+        if !filters.processing_status.is_empty() {
+            builder.push(" AND f.processing_status IN (");
+            let mut separated = builder.separated(", ");
+            for status in &filters.processing_status {
+                separated.push_bind(status.clone());
+            }
+            separated.push_unseparated(")");
+        }
 
-        let mut files = Vec::new();
-        for row in rows {
-            files.push(self.row_to_file_record(row)?);
+        if let Some(root_path) = &filters.root_path {
+            builder.push(" AND (f.path = ").push_bind(root_path.clone());
+            builder.push(" OR f.path LIKE ").push_bind(paths_below(root_path)).push(r" ESCAPE '\')");
         }
 
-        Ok(files)
+        for tag in &filters.tags {
+            builder.push(" AND EXISTS (SELECT 1 FROM file_tags ft INNER JOIN tags t ON t.id = ft.tag_id WHERE ft.file_id = f.id AND t.name = ")
+                .push_bind(tag.trim().to_string())
+                .push(")");
+        }
+
+        if let Some(collection_id) = &filters.collection_id {
+            builder.push(" AND EXISTS (SELECT 1 FROM file_collections scope WHERE scope.file_id = f.id AND scope.collection_id = ")
+                .push_bind(collection_id.clone())
+                .push(")");
+        }
     }
 
     pub async fn search_files_with_embeddings(&self, query: &str, limit: i64) -> Result<Vec<FileRecord>> {
@@ -499,21 +2032,17 @@ impl Database {
             };
         
         // Get file type statistics
-        let file_types = match sqlx::query(
+        let file_types_sql = format!(
             r#"
-            SELECT 
-                CASE 
-                    WHEN extension IN ('pdf', 'doc', 'docx', 'txt', 'md', 'rtf') THEN 'documents'
-                    WHEN extension IN ('jpg', 'jpeg', 'png', 'gif', 'bmp', 'svg', 'tiff', 'webp') THEN 'images'
-                    WHEN extension IN ('js', 'ts', 'py', 'rs', 'java', 'cpp', 'c', 'h', 'css', 'html', 'xml', 'json') THEN 'code'
-                    ELSE 'other'
-                END as category,
+            SELECT {} as category,
                 COUNT(*) as count
             FROM files 
             WHERE processing_status = 'completed'
             GROUP BY category
-            "#
-        )
+            "#,
+            CATEGORY_CASE_SQL
+        );
+        let file_types = match sqlx::query(&file_types_sql)
         .fetch_all(&self.pool)
         .await {
             Ok(result) => {
@@ -636,6 +2165,16 @@ impl Database {
             0.0
         };
 
+        let duplicates = self.find_duplicates(10).await.unwrap_or_else(|e| {
+            tracing::warn!("Duplicate detection failed: {}", e);
+            DuplicateReport::default()
+        });
+
+        let languages = self.get_language_distribution().await.unwrap_or_else(|e| {
+            tracing::warn!("Language distribution query failed: {}", e);
+            Vec::new()
+        });
+
         tracing::debug!("Insights data collection completed successfully");
 
         Ok(serde_json::json!({
@@ -673,6 +2212,8 @@ impl Database {
                 }
             ],
             "recent_activity": activity_items,
+            "duplicates": duplicates,
+            "languages": languages,
             "processing_summary": {
                 "total_files": total_db_files,
                 "completed_files": completed_files,
@@ -683,37 +2224,44 @@ impl Database {
     }
 
     pub async fn get_location_stats(&self, location_path: &str) -> Result<serde_json::Value> {
+        let location = paths::canonical_string(Path::new(location_path));
+
         // Handle both individual files and directories
-        let query = if std::path::Path::new(location_path).is_file() {
+        let stats = if Path::new(location_path).is_file() {
             // For individual files, match exact path
-            r#"
-            SELECT 
-                COUNT(CASE WHEN processing_status = 'completed' THEN 1 END) as completed,
-                COUNT(CASE WHEN processing_status = 'pending' THEN 1 END) as pending,
-                COUNT(CASE WHEN processing_status = 'processing' THEN 1 END) as processing,
-                COUNT(CASE WHEN processing_status = 'error' THEN 1 END) as errors,
-                COUNT(*) as total
-            FROM files
-            WHERE path = ?
-            "#
-        } else {
-            // For directories, match files within that directory (path starts with the directory path)
-            r#"
-            SELECT 
-                COUNT(CASE WHEN processing_status = 'completed' THEN 1 END) as completed,
-                COUNT(CASE WHEN processing_status = 'pending' THEN 1 END) as pending,
-                COUNT(CASE WHEN processing_status = 'processing' THEN 1 END) as processing,
-                COUNT(CASE WHEN processing_status = 'error' THEN 1 END) as errors,
-                COUNT(*) as total
-            FROM files
-            WHERE path LIKE ? || '%'
-            "#
-        };
-        
-        let stats = sqlx::query(query)
-            .bind(location_path)
+            sqlx::query(
+                r#"
+                SELECT 
+                    COUNT(CASE WHEN processing_status = 'completed' THEN 1 END) as completed,
+                    COUNT(CASE WHEN processing_status = 'pending' THEN 1 END) as pending,
+                    COUNT(CASE WHEN processing_status = 'processing' THEN 1 END) as processing,
+                    COUNT(CASE WHEN processing_status = 'error' THEN 1 END) as errors,
+                    COUNT(*) as total
+                FROM files
+                WHERE path = ?
+                "#
+            )
+            .bind(&location)
             .fetch_one(&self.pool)
+            .await?
+        } else {
+            // Directories are kept up to date in directory_stats; folders without indexed files have no row
+            let row = sqlx::query(
+                "SELECT completed, pending, processing, errors, total FROM directory_stats WHERE directory = ?"
+            )
+            .bind(&location)
+            .fetch_optional(&self.pool)
             .await?;
+            match row {
+                Some(row) => row,
+                None => return Ok(serde_json::json!({
+                    "total_files": 0,
+                    "processed_files": 0,
+                    "pending_files": 0,
+                    "error_files": 0
+                })),
+            }
+        };
 
         Ok(serde_json::json!({
             "total_files": stats.get::<i64, _>("total"),
@@ -723,6 +2271,374 @@ impl Database {
         }))
     }
 
+    // Search history operations
+    pub async fn record_search_query(&self, query: &str) -> Result<()> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO search_history (query, hit_count, last_searched_at)
+            VALUES (?, 1, ?)
+            ON CONFLICT(query) DO UPDATE SET
+                hit_count = hit_count + 1,
+                last_searched_at = excluded.last_searched_at
+            "#
+        )
+        .bind(query)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Previously executed queries starting with `prefix`, ranked by frequency decayed by age in days
+    pub async fn get_search_history_suggestions(&self, prefix: &str, limit: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT query FROM search_history
+            WHERE query LIKE ? || '%' ESCAPE '\' AND query != ? COLLATE NOCASE
+            ORDER BY hit_count / (1.0 + julianday('now') - julianday(last_searched_at)) DESC,
+                last_searched_at DESC
+            LIMIT ?
+            "#
+        )
+        .bind(escape_like(prefix.trim()))
+        .bind(prefix.trim())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("query")).collect())
+    }
+
+    /// Suggest tags, entity names, file names and folder names starting with `prefix`,
+    /// ranked by how many files they cover and deduplicated case-insensitively
+    pub async fn get_index_suggestions(&self, prefix: &str, limit: usize) -> Result<Vec<SearchSuggestion>> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let per_source = (limit * 2) as i64;
+        let mut candidates = Vec::new();
+
+        let tag_rows = sqlx::query(
+            r#"
+            SELECT t.name as text, COUNT(ft.file_id) as count
+            FROM tags t
+            INNER JOIN file_tags ft ON ft.tag_id = t.id
+            INNER JOIN files f ON f.id = ft.file_id
+            WHERE t.name LIKE ? || '%' ESCAPE '\' AND f.deleted_at IS NULL
+            GROUP BY t.id
+            ORDER BY count DESC
+            LIMIT ?
+            "#
+        )
+        .bind(escape_like(prefix))
+        .bind(per_source)
+        .fetch_all(&self.pool)
+        .await?;
+        candidates.extend(tag_rows.iter().map(|row| SearchSuggestion {
+            text: row.get("text"),
+            kind: SuggestionKind::Tag,
+            count: row.get("count"),
+        }));
+
+        // Invalid metadata is treated as having no entities instead of failing the query
+        let entity_rows = sqlx::query(
+            r#"
+            SELECT entity.value as text, COUNT(DISTINCT files.id) as count
+            FROM files, json_each(
+                CASE WHEN json_valid(files.metadata) THEN files.metadata ELSE '{}' END,
+                '$.entities'
+            ) entity
+            WHERE entity.type = 'text' AND entity.value LIKE ? || '%' ESCAPE '\'
+                AND files.deleted_at IS NULL
+            GROUP BY entity.value COLLATE NOCASE
+            ORDER BY count DESC
+            LIMIT ?
+            "#
+        )
+        .bind(escape_like(prefix))
+        .bind(per_source)
+        .fetch_all(&self.pool)
+        .await?;
+        candidates.extend(entity_rows.iter().map(|row| SearchSuggestion {
+            text: row.get("text"),
+            kind: SuggestionKind::Entity,
+            count: row.get("count"),
+        }));
+
+        let name_rows = sqlx::query(
+            r#"
+            SELECT name as text, COUNT(*) as count
+            FROM files
+            WHERE name LIKE ? || '%' ESCAPE '\' AND deleted_at IS NULL
+            GROUP BY name COLLATE NOCASE
+            ORDER BY count DESC, name ASC
+            LIMIT ?
+            "#
+        )
+        .bind(escape_like(prefix))
+        .bind(per_source)
+        .fetch_all(&self.pool)
+        .await?;
+        candidates.extend(name_rows.iter().map(|row| SearchSuggestion {
+            text: row.get("text"),
+            kind: SuggestionKind::FileName,
+            count: row.get("count"),
+        }));
+
+        // Folder names are path components, so match in SQL loosely and filter here
+        let path_rows = sqlx::query(r"SELECT path FROM files WHERE path LIKE '%' || ? || '%' ESCAPE '\' AND deleted_at IS NULL LIMIT 2000")
+            .bind(escape_like(prefix))
+            .fetch_all(&self.pool)
+            .await?;
+        let lowered_prefix = prefix.to_lowercase();
+        let mut folders: HashMap<String, SearchSuggestion> = HashMap::new();
+        for row in &path_rows {
+            let path: String = row.get("path");
+            let mut seen_in_path = HashSet::new();
+            for folder in Path::new(&path).ancestors().skip(1).filter_map(|p| p.file_name()?.to_str()) {
+                let key = folder.to_lowercase();
+                if !key.starts_with(&lowered_prefix) || !seen_in_path.insert(key.clone()) {
+                    continue;
+                }
+                folders.entry(key)
+                    .or_insert_with(|| SearchSuggestion {
+                        text: folder.to_string(),
+                        kind: SuggestionKind::Folder,
+                        count: 0,
+                    })
+                    .count += 1;
+            }
+        }
+        candidates.extend(folders.into_values());
+
+        Ok(rank_suggestions(candidates, limit))
+    }
+
+    pub async fn clear_search_history(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM search_history")
+            .execute(&self.pool)
+            .await?;
+        // Statistics keep their counts and timings but no longer say what was searched
+        sqlx::query("UPDATE search_stats SET query = NULL WHERE query IS NOT NULL")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Record one search for the analytics report. `query` is None when search history is off;
+    /// it is stored lowercased with collapsed whitespace so equivalent queries count together.
+    /// Rows older than `retention_days` are dropped.
+    pub async fn record_search_stat(
+        &self,
+        query: Option<&str>,
+        search_type: &str,
+        result_count: i64,
+        latency_ms: i64,
+        retention_days: u32,
+    ) -> Result<()> {
+        let query = query
+            .map(|query| query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+            .filter(|query| !query.is_empty());
+        let today = Utc::now().date_naive();
+
+        sqlx::query(
+            "INSERT INTO search_stats (query, search_type, result_count, latency_ms, searched_on) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(query)
+        .bind(search_type)
+        .bind(result_count)
+        .bind(latency_ms)
+        .bind(today.format("%Y-%m-%d").to_string())
+        .execute(&self.pool)
+        .await?;
+
+        let cutoff = today - chrono::Duration::days(retention_days as i64);
+        sqlx::query("DELETE FROM search_stats WHERE searched_on < ?")
+            .bind(cutoff.format("%Y-%m-%d").to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Most frequent queries, latency per search type and queries that returned nothing
+    pub async fn get_search_analytics(&self, limit: i64) -> Result<SearchAnalytics> {
+        let total_searches: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM search_stats")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let query_counts = |rows: Vec<sqlx::sqlite::SqliteRow>| -> Vec<QueryCount> {
+            rows.iter()
+                .map(|row| QueryCount {
+                    query: row.get("query"),
+                    count: row.get("count"),
+                    last_searched: row.get("last_searched"),
+                })
+                .collect()
+        };
+
+        let top_rows = sqlx::query(
+            r#"
+            SELECT query, COUNT(*) as count, MAX(searched_on) as last_searched
+            FROM search_stats
+            WHERE query IS NOT NULL
+            GROUP BY query
+            ORDER BY count DESC, last_searched DESC
+            LIMIT ?
+            "#
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // A query counts as a gap only if it never found anything in the retained period
+        let zero_rows = sqlx::query(
+            r#"
+            SELECT query, COUNT(*) as count, MAX(searched_on) as last_searched
+            FROM search_stats
+            WHERE query IS NOT NULL
+            GROUP BY query
+            HAVING MAX(result_count) = 0
+            ORDER BY count DESC, last_searched DESC
+            LIMIT ?
+            "#
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let latency_rows = sqlx::query(
+            r#"
+            SELECT search_type,
+                   COUNT(*) as searches,
+                   AVG(latency_ms) as avg_latency_ms,
+                   AVG(CASE WHEN result_count = 0 THEN 1.0 ELSE 0.0 END) as zero_result_rate
+            FROM search_stats
+            GROUP BY search_type
+            ORDER BY searches DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(SearchAnalytics {
+            total_searches,
+            top_queries: query_counts(top_rows),
+            latency_by_type: latency_rows.iter()
+                .map(|row| SearchTypeLatency {
+                    search_type: row.get("search_type"),
+                    searches: row.get("searches"),
+                    avg_latency_ms: row.get("avg_latency_ms"),
+                    zero_result_rate: row.get("zero_result_rate"),
+                })
+                .collect(),
+            zero_result_queries: query_counts(zero_rows),
+        })
+    }
+
+    /// Store the named entities found by AI analysis in the file's metadata
+    pub async fn update_file_entities(&self, file_id: &str, entities: &[String]) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE files SET metadata = json_set(
+                CASE WHEN json_valid(metadata) THEN metadata ELSE '{}' END,
+                '$.entities', json(?)
+            )
+            WHERE id = ?
+            "#
+        )
+        .bind(serde_json::to_string(entities)?)
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Store a photo's EXIF data in the file's metadata, or drop what is stored when there is none
+    pub async fn update_file_exif(&self, file_id: &str, exif: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.exif", exif).await
+    }
+
+    /// Store the headers of an email or mailbox in the file's metadata
+    pub async fn update_file_email(&self, file_id: &str, email: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.email", email).await
+    }
+
+    /// Store the outline of a source file in the file's metadata, and its symbols where symbol
+    /// search finds them
+    pub async fn update_file_code(&self, file_id: &str, code: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.code", code).await?;
+        self.replace_code_symbols(file_id, code).await
+    }
+
+    /// Store a web page's description, OpenGraph properties and headings in the file's metadata
+    pub async fn update_file_html(&self, file_id: &str, html: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.html", html).await
+    }
+
+    /// Store the schema of a CSV or TSV file in the file's metadata
+    pub async fn update_file_table(&self, file_id: &str, table: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.table", table).await
+    }
+
+    /// Store the schema and column statistics of a Parquet or Arrow file in the file's metadata
+    pub async fn update_file_dataset(&self, file_id: &str, dataset: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.dataset", dataset).await
+    }
+
+    /// Store the tables of an indexed SQLite database in the file's metadata
+    pub async fn update_file_database(&self, file_id: &str, database: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.database", database).await
+    }
+
+    /// Store the tags and duration of a music file in the file's metadata
+    pub async fn update_file_audio(&self, file_id: &str, audio: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.audio", audio).await
+    }
+
+    /// Store which video a subtitle file goes with, or which subtitles a video has, in the file's metadata
+    pub async fn update_file_subtitles(&self, file_id: &str, subtitles: Option<&serde_json::Value>) -> Result<()> {
+        self.update_metadata_field(file_id, "$.subtitles", subtitles).await
+    }
+
+    /// Set one field of the file's JSON metadata, or remove it when there is no value
+    async fn update_metadata_field(&self, file_id: &str, field: &str, value: Option<&serde_json::Value>) -> Result<()> {
+        match value {
+            Some(value) => sqlx::query(
+                r#"
+                UPDATE files SET metadata = json_set(
+                    CASE WHEN json_valid(metadata) THEN metadata ELSE '{}' END,
+                    ?, json(?)
+                )
+                WHERE id = ?
+                "#
+            )
+            .bind(field)
+            .bind(value.to_string()),
+            None => sqlx::query(
+                r#"
+                UPDATE files SET metadata = json_remove(
+                    CASE WHEN json_valid(metadata) THEN metadata ELSE '{}' END,
+                    ?
+                )
+                WHERE id = ?
+                "#
+            )
+            .bind(field),
+        }
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     fn row_to_file_record(&self, row: sqlx::sqlite::SqliteRow) -> Result<FileRecord> {
         let embedding_blob: Option<Vec<u8>> = row.try_get("embedding")?;
         let embedding = embedding_blob.map(|blob| {
@@ -950,5 +2866,50 @@ impl Database {
     }
 }
 
+/// A pattern matching the paths below `folder`, for `LIKE ? ESCAPE '\'`. The separator keeps
+/// `/docs` from matching `/docs-old`, and `%` or `_` in folder names are taken literally.
+pub(crate) fn paths_below(folder: &str) -> String {
+    let separator = std::path::MAIN_SEPARATOR;
+    let prefix = format!("{}{}", folder.trim_end_matches(separator), separator);
+    format!("{}%", escape_like(&prefix))
+}
+
+/// The text with the wildcards of `LIKE` taken literally, for `ESCAPE '\'`
+pub(crate) fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Order suggestions by weighted frequency, keeping the best entry per distinct text
+fn rank_suggestions(candidates: Vec<SearchSuggestion>, limit: usize) -> Vec<SearchSuggestion> {
+    let mut best: HashMap<String, SearchSuggestion> = HashMap::new();
+    for candidate in candidates {
+        let score = candidate.count as f64 * candidate.kind.weight();
+        match best.get(&candidate.text.to_lowercase()) {
+            Some(existing) if existing.count as f64 * existing.kind.weight() >= score => {}
+            _ => {
+                best.insert(candidate.text.to_lowercase(), candidate);
+            }
+        }
+    }
+
+    let mut ranked: Vec<SearchSuggestion> = best.into_values().collect();
+    ranked.sort_by(|a, b| {
+        let score_a = a.count as f64 * a.kind.weight();
+        let score_b = b.count as f64 * b.kind.weight();
+        score_b.partial_cmp(&score_a).unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.text.len().cmp(&b.text.len()))
+            .then_with(|| a.text.cmp(&b.text))
+    });
+    ranked.truncate(limit);
+    ranked
+}
+
 #[cfg(test)]
 mod tests;
\ No newline at end of file
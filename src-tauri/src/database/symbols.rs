@@ -0,0 +1,85 @@
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+
+use super::{escape_like, Database};
+
+/// A function, type or module defined in an indexed source file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeSymbol {
+    pub file_id: String,
+    pub path: String,
+    pub name: String,
+    /// `function`, `type` or `module`
+    pub kind: String,
+    pub line: i64,
+    pub doc: Option<String>,
+}
+
+impl Database {
+    /// Symbols whose name contains `query`, ignoring case. Exact names come first, then names
+    /// starting with the query, then shorter names.
+    pub async fn search_code_symbols(&self, query: &str, limit: i64) -> Result<Vec<CodeSymbol>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        // `name` compares without case, so the exact-name rank ignores case like LIKE does. A
+        // substring match can't use an index, so this scans the symbols of live files.
+        let rows = sqlx::query(
+            r#"
+            SELECT files.id AS file_id, files.path AS path, symbol.name, symbol.kind, symbol.line, symbol.doc
+            FROM code_symbols AS symbol
+            INNER JOIN files ON files.id = symbol.file_id
+            WHERE files.deleted_at IS NULL
+              AND symbol.name LIKE '%' || ?1 || '%' ESCAPE '\'
+            ORDER BY symbol.name = ?2 DESC,
+                     symbol.name LIKE ?1 || '%' ESCAPE '\' DESC,
+                     length(symbol.name), path, symbol.line
+            LIMIT ?3
+            "#
+        )
+        .bind(escape_like(query))
+        .bind(query)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|row| CodeSymbol {
+                file_id: row.get("file_id"),
+                path: row.get("path"),
+                name: row.get("name"),
+                kind: row.get("kind"),
+                line: row.get("line"),
+                doc: row.get("doc"),
+            })
+            .collect())
+    }
+
+    /// Replace the file's symbols with those of its outline, or remove them without one
+    pub(super) async fn replace_code_symbols(&self, file_id: &str, code: Option<&serde_json::Value>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM code_symbols WHERE file_id = ?")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        let symbols = code.and_then(|code| code["symbols"].as_array()).map(Vec::as_slice).unwrap_or_default();
+        for symbol in symbols {
+            let Some(name) = symbol["name"].as_str() else {
+                continue;
+            };
+            sqlx::query("INSERT INTO code_symbols (file_id, name, kind, line, doc) VALUES (?, ?, ?, ?, ?)")
+                .bind(file_id)
+                .bind(name)
+                .bind(symbol["kind"].as_str().unwrap_or_default())
+                .bind(symbol["line"].as_i64().unwrap_or_default())
+                .bind(symbol["doc"].as_str())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -1,417 +1,1622 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
-    use tokio;
-    use chrono::Utc;
-    use uuid::Uuid;
-
-    async fn create_test_database() -> (Database, TempDir) {
-        let temp_dir = TempDir::new().expect("Failed to create temp directory");
-        let db_path = temp_dir.path().join("test.db");
-        let database = Database::new(db_path).await.expect("Failed to create test database");
-        (database, temp_dir)
+use super::*;
+use tempfile::TempDir;
+use tokio;
+use chrono::Utc;
+use uuid::Uuid;
+use crate::search_query::QueryNode;
+
+async fn create_test_database() -> (Database, TempDir) {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+    let database = Database::new(db_path).await.expect("Failed to create test database");
+    (database, temp_dir)
+}
+
+fn create_test_file_record() -> FileRecord {
+    let now = Utc::now();
+    FileRecord {
+        id: Uuid::new_v4().to_string(),
+        path: "/test/path/file.txt".to_string(),
+        name: "file.txt".to_string(),
+        extension: Some("txt".to_string()),
+        size: 1024,
+        created_at: now,
+        modified_at: now,
+        last_accessed: Some(now),
+        mime_type: Some("text/plain".to_string()),
+        hash: Some("test-hash".to_string()),
+        content: Some("Test file content".to_string()),
+        tags: Some(r#"["test", "document"]"#.to_string()),
+        metadata: Some(r#"{"author": "Test Author"}"#.to_string()),
+        ai_analysis: Some("This is a test document.".to_string()),
+        embedding: Some(vec![0.1, 0.2, 0.3, 0.4]),
+        indexed_at: Some(now),
+        processing_status: "completed".to_string(),
+        error_message: None,
     }
-
-    fn create_test_file_record() -> FileRecord {
-        let now = Utc::now();
-        FileRecord {
-            id: Uuid::new_v4().to_string(),
-            path: "/test/path/file.txt".to_string(),
-            name: "file.txt".to_string(),
-            extension: Some("txt".to_string()),
-            size: 1024,
-            created_at: now,
-            modified_at: now,
-            last_accessed: Some(now),
-            mime_type: Some("text/plain".to_string()),
-            hash: Some("test-hash".to_string()),
-            content: Some("Test file content".to_string()),
-            tags: Some(r#"["test", "document"]"#.to_string()),
-            metadata: Some(r#"{"author": "Test Author"}"#.to_string()),
-            ai_analysis: Some("This is a test document.".to_string()),
-            embedding: Some(vec![0.1, 0.2, 0.3, 0.4]),
-            indexed_at: Some(now),
-            processing_status: "completed".to_string(),
-            error_message: None,
+}
+
+#[tokio::test]
+async fn test_database_creation() {
+    let (_database, _temp_dir) = create_test_database().await;
+    // If we get here without panicking, the database was created successfully
+}
+
+#[tokio::test]
+async fn test_file_insertion_and_retrieval() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file_record = create_test_file_record();
+
+    // Insert file
+    database.insert_file(&file_record).await.expect("Failed to insert file");
+
+    // Retrieve file by path
+    let retrieved = database.get_file_by_path(&file_record.path).await
+        .expect("Failed to retrieve file")
+        .expect("File not found");
+
+    assert_eq!(retrieved.id, file_record.id);
+    assert_eq!(retrieved.path, file_record.path);
+    assert_eq!(retrieved.name, file_record.name);
+    assert_eq!(retrieved.content, file_record.content);
+    assert_eq!(retrieved.processing_status, file_record.processing_status);
+}
+
+#[tokio::test]
+async fn test_file_exists() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file_record = create_test_file_record();
+
+    // File should not exist initially
+    let exists_before = database.file_exists(&file_record.path).await
+        .expect("Failed to check file existence");
+    assert!(!exists_before);
+
+    // Insert file
+    database.insert_file(&file_record).await.expect("Failed to insert file");
+
+    // File should exist now
+    let exists_after = database.file_exists(&file_record.path).await
+        .expect("Failed to check file existence");
+    assert!(exists_after);
+}
+
+#[tokio::test]
+async fn test_file_status_update() {
+    let (database, _temp_dir) = create_test_database().await;
+    let mut file_record = create_test_file_record();
+    file_record.processing_status = "pending".to_string();
+
+    database.insert_file(&file_record).await.expect("Failed to insert file");
+
+    // Update status to processing
+    database.update_file_status(&file_record.id, "processing", None).await
+        .expect("Failed to update file status");
+
+    let updated = database.get_file_by_path(&file_record.path).await
+        .expect("Failed to retrieve file")
+        .expect("File not found");
+
+    assert_eq!(updated.processing_status, "processing");
+    assert_eq!(updated.error_message, None);
+
+    // Update status to error with message
+    let error_msg = "Test error message";
+    database.update_file_status(&file_record.id, "error", Some(error_msg)).await
+        .expect("Failed to update file status");
+
+    let updated_with_error = database.get_file_by_path(&file_record.path).await
+        .expect("Failed to retrieve file")
+        .expect("File not found");
+
+    assert_eq!(updated_with_error.processing_status, "error");
+    assert_eq!(updated_with_error.error_message, Some(error_msg.to_string()));
+
+    // Only files stuck in processing are reset
+    database.update_file_status(&file_record.id, "processing", None).await.unwrap();
+    assert_eq!(database.reset_processing_files().await.expect("Failed to reset files"), 1);
+    let reset = database.get_file_by_path(&file_record.path).await.unwrap().expect("File not found");
+    assert_eq!(reset.processing_status, "pending");
+    assert_eq!(database.reset_processing_files().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_file_metadata_fields() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file_record = create_test_file_record();
+    database.insert_file(&file_record).await.expect("Failed to insert file");
+    database.update_file_entities(&file_record.id, &["Paris".to_string()]).await.unwrap();
+
+    let exif = serde_json::json!({ "camera": "Canon EOS R6", "captured_at": "2024-05-01T14:30:00" });
+    database.update_file_exif(&file_record.id, Some(&exif)).await
+        .expect("Failed to store EXIF data");
+    let stored = database.get_file_by_path(&file_record.path).await.unwrap().expect("File not found");
+    let metadata: serde_json::Value = serde_json::from_str(&stored.metadata.unwrap()).unwrap();
+    assert_eq!(metadata["exif"], exif);
+    assert_eq!(metadata["entities"][0], "Paris");
+
+    database.update_file_exif(&file_record.id, None).await.expect("Failed to clear EXIF data");
+    let cleared = database.get_file_by_path(&file_record.path).await.unwrap().expect("File not found");
+    let metadata: serde_json::Value = serde_json::from_str(&cleared.metadata.unwrap()).unwrap();
+    assert!(metadata.get("exif").is_none());
+    assert_eq!(metadata["entities"][0], "Paris");
+
+    let email = serde_json::json!({ "from": "ada@example.com", "subject": "Notes" });
+    database.update_file_email(&file_record.id, Some(&email)).await.expect("Failed to store email");
+    let stored = database.get_file_by_path(&file_record.path).await.unwrap().expect("File not found");
+    let metadata: serde_json::Value = serde_json::from_str(&stored.metadata.unwrap()).unwrap();
+    assert_eq!(metadata["email"]["subject"], "Notes");
+}
+
+#[tokio::test]
+async fn test_search_code_symbols() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file_record = create_test_file_record();
+    database.insert_file(&file_record).await.expect("Failed to insert file");
+    let code = serde_json::json!({
+        "language": "rust",
+        "imports": [],
+        "symbols": [
+            { "name": "parse_config", "kind": "function", "line": 12, "doc": "Reads the config file." },
+            { "name": "Config", "kind": "type", "line": 3, "doc": null },
+            { "name": "ConfigError", "kind": "type", "line": 8, "doc": null },
+        ],
+    });
+    database.update_file_code(&file_record.id, Some(&code)).await.expect("Failed to store code outline");
+
+    let symbols = database.search_code_symbols("config", 10).await.expect("Failed to search symbols");
+    let names: Vec<&str> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, vec!["Config", "ConfigError", "parse_config"]);
+    assert_eq!(symbols[2].line, 12);
+    assert_eq!(symbols[2].doc.as_deref(), Some("Reads the config file."));
+    assert_eq!(symbols[0].path, file_record.path);
+
+    assert!(database.search_code_symbols("render", 10).await.unwrap().is_empty());
+    assert!(database.search_code_symbols(" ", 10).await.unwrap().is_empty());
+    assert!(database.search_code_symbols("_", 10).await.unwrap().iter().all(|symbol| symbol.name.contains('_')));
+
+    // Storing the outline again replaces the symbols of the file
+    let code = serde_json::json!({ "symbols": [{ "name": "render_config", "kind": "function", "line": 1, "doc": null }] });
+    database.update_file_code(&file_record.id, Some(&code)).await.unwrap();
+    let names: Vec<String> = database.search_code_symbols("config", 10).await.unwrap().into_iter().map(|symbol| symbol.name).collect();
+    assert_eq!(names, vec!["render_config"]);
+    database.update_file_code(&file_record.id, None).await.unwrap();
+    assert!(database.search_code_symbols("config", 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_file_analysis_update() {
+    let (database, _temp_dir) = create_test_database().await;
+    let mut file_record = create_test_file_record();
+    file_record.content = None;
+    file_record.ai_analysis = None;
+    file_record.tags = None;
+    file_record.embedding = None;
+
+    database.insert_file(&file_record).await.expect("Failed to insert file");
+
+    let content = "Updated content";
+    let analysis = "Updated AI analysis";
+    let tags = r#"["updated", "tags"]"#;
+    let embedding = vec![0.5, 0.6, 0.7, 0.8];
+
+    database.update_file_analysis(&file_record.id, content, analysis, Some(tags), Some(&embedding)).await
+        .expect("Failed to update file analysis");
+
+    let updated = database.get_file_by_path(&file_record.path).await
+        .expect("Failed to retrieve file")
+        .expect("File not found");
+
+    assert_eq!(updated.content, Some(content.to_string()));
+    assert_eq!(updated.ai_analysis, Some(analysis.to_string()));
+    assert_eq!(updated.tags, Some(tags.to_string()));
+    assert_eq!(updated.embedding, Some(embedding));
+    assert_eq!(updated.processing_status, "completed");
+    assert!(updated.indexed_at.is_some());
+}
+
+#[tokio::test]
+async fn test_get_files_by_status() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    // Create files with different statuses
+    let mut file1 = create_test_file_record();
+    file1.path = "/test/file1.txt".to_string();
+    file1.processing_status = "pending".to_string();
+
+    let mut file2 = create_test_file_record();
+    file2.path = "/test/file2.txt".to_string();
+    file2.processing_status = "completed".to_string();
+
+    let mut file3 = create_test_file_record();
+    file3.path = "/test/file3.txt".to_string();
+    file3.processing_status = "pending".to_string();
+
+    database.insert_file(&file1).await.expect("Failed to insert file1");
+    database.insert_file(&file2).await.expect("Failed to insert file2");
+    database.insert_file(&file3).await.expect("Failed to insert file3");
+
+    // Get pending files
+    let pending_files = database.get_files_by_status("pending").await
+        .expect("Failed to get pending files");
+    assert_eq!(pending_files.len(), 2);
+
+    // Get completed files
+    let completed_files = database.get_files_by_status("completed").await
+        .expect("Failed to get completed files");
+    assert_eq!(completed_files.len(), 1);
+    assert_eq!(completed_files[0].id, file2.id);
+}
+
+#[tokio::test]
+async fn test_search_files() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    let mut file1 = create_test_file_record();
+    file1.path = "/test/document.pdf".to_string();
+    file1.name = "document.pdf".to_string();
+    file1.content = Some("This is a PDF document about machine learning".to_string());
+
+    let mut file2 = create_test_file_record();
+    file2.path = "/test/image.jpg".to_string();
+    file2.name = "image.jpg".to_string();
+    file2.content = Some("Image file description".to_string());
+
+    let mut file3 = create_test_file_record();
+    file3.path = "/test/report.txt".to_string();
+    file3.name = "report.txt".to_string();
+    file3.content = Some("Annual report with machine learning insights".to_string());
+
+    database.insert_file(&file1).await.expect("Failed to insert file1");
+    database.insert_file(&file2).await.expect("Failed to insert file2");
+    database.insert_file(&file3).await.expect("Failed to insert file3");
+
+    // Search for "machine learning"
+    let results = database.search_files("machine learning", 10, 0).await
+        .expect("Failed to search files");
+    
+    assert_eq!(results.len(), 2);
+    let result_paths: Vec<&String> = results.iter().map(|f| &f.path).collect();
+    assert!(result_paths.contains(&&file1.path));
+    assert!(result_paths.contains(&&file3.path));
+
+    // Search for "image"
+    let image_results = database.search_files("image", 10, 0).await
+        .expect("Failed to search files");
+    
+    assert_eq!(image_results.len(), 1);
+    assert_eq!(image_results[0].path, file2.path);
+}
+
+#[tokio::test]
+async fn test_search_files_with_filters() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut small_pdf = create_test_file_record();
+    small_pdf.path = "/work/notes.pdf".to_string();
+    small_pdf.name = "notes.pdf".to_string();
+    small_pdf.extension = Some("pdf".to_string());
+    small_pdf.mime_type = Some("application/pdf".to_string());
+    small_pdf.size = 500;
+
+    let mut large_txt = create_test_file_record();
+    large_txt.path = "/work/notes.txt".to_string();
+    large_txt.name = "notes.txt".to_string();
+    large_txt.size = 50_000;
+
+    let mut other_root = create_test_file_record();
+    other_root.path = "/home/notes.txt".to_string();
+    other_root.name = "notes.txt".to_string();
+    other_root.processing_status = "pending".to_string();
+
+    database.insert_file(&small_pdf).await.expect("Failed to insert pdf");
+    database.insert_file(&large_txt).await.expect("Failed to insert txt");
+    database.insert_file(&other_root).await.expect("Failed to insert other root");
+
+    // Shares the root's name as a prefix without being below it
+    let mut sibling = create_test_file_record();
+    sibling.path = "/workshop/notes.txt".to_string();
+    sibling.name = "notes.txt".to_string();
+    sibling.size = 50_000;
+    database.insert_file(&sibling).await.expect("Failed to insert sibling");
+
+    let notes = QueryNode::parse("notes").unwrap();
+
+    let by_extension = SearchFilters {
+        extensions: vec![".PDF".to_string()],
+        ..Default::default()
+    };
+    let results = database.search_files_filtered(&notes, &by_extension, 10, 0).await
+        .expect("Failed to search with extension filter");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, small_pdf.path);
+
+    let by_size_and_root = SearchFilters {
+        size_range: Some(SizeRange { min: Some(1000), max: None }),
+        root_path: Some("/work".to_string()),
+        ..Default::default()
+    };
+    let results = database.search_files_filtered(&notes, &by_size_and_root, 10, 0).await
+        .expect("Failed to search with size and root filters");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, large_txt.path);
+
+    // Wildcards in the root are taken literally
+    let by_wildcard_root = SearchFilters {
+        root_path: Some("/wor_".to_string()),
+        ..Default::default()
+    };
+    let results = database.search_files_filtered(&notes, &by_wildcard_root, 10, 0).await
+        .expect("Failed to search with wildcard root");
+    assert!(results.is_empty());
+
+    // So are wildcards in a MIME category
+    let by_wildcard_mime = SearchFilters {
+        mime_categories: vec!["%".to_string()],
+        ..Default::default()
+    };
+    let results = database.search_files_filtered(&notes, &by_wildcard_mime, 10, 0).await
+        .expect("Failed to search with wildcard MIME category");
+    assert!(results.is_empty());
+
+    let by_status_and_mime = SearchFilters {
+        mime_categories: vec!["text".to_string()],
+        processing_status: vec!["pending".to_string()],
+        ..Default::default()
+    };
+    let results = database.search_files_filtered(&notes, &by_status_and_mime, 10, 0).await
+        .expect("Failed to search with status filter");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, other_root.path);
+
+    let collection = database.create_collection("Workspace", None).await
+        .expect("Failed to create collection");
+    database.add_file_to_collection(&large_txt.id, &collection.id).await
+        .expect("Failed to add file to collection");
+    let by_collection = SearchFilters {
+        collection_id: Some(collection.id.clone()),
+        ..Default::default()
+    };
+    let results = database.search_files_filtered(&notes, &by_collection, 10, 0).await
+        .expect("Failed to search within collection");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, large_txt.path);
+}
+
+#[tokio::test]
+async fn test_search_files_boolean_query() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut report = create_test_file_record();
+    report.path = "/docs/annual-report.txt".to_string();
+    report.name = "annual-report.txt".to_string();
+    report.content = Some("the annual report for 2023".to_string());
+
+    let mut draft = create_test_file_record();
+    draft.path = "/docs/annual-draft.txt".to_string();
+    draft.name = "annual-draft.txt".to_string();
+    draft.content = Some("annual figures, still a draft".to_string());
+
+    let mut budget = create_test_file_record();
+    budget.path = "/docs/budget.txt".to_string();
+    budget.name = "budget.txt".to_string();
+    budget.content = None;
+
+    database.insert_file(&report).await.expect("Failed to insert report");
+    database.insert_file(&draft).await.expect("Failed to insert draft");
+    database.insert_file(&budget).await.expect("Failed to insert budget");
+
+    let search = |query: &str| QueryNode::parse(query).unwrap();
+    let filters = SearchFilters::default();
+
+    let results = database.search_files_filtered(&search("annual -draft"), &filters, 10, 0).await
+        .expect("Failed to search with negation");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, report.path);
+
+    let results = database.search_files_filtered(&search("\"annual report\" OR budget"), &filters, 10, 0).await
+        .expect("Failed to search with phrase");
+    assert_eq!(results.len(), 2);
+
+    // Files without content must still satisfy a negated term
+    let results = database.search_files_filtered(&search("budget NOT annual"), &filters, 10, 0).await
+        .expect("Failed to search without content");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, budget.path);
+
+    let results = database.search_files_filtered(&search("path:/docs -path:draft"), &filters, 10, 0).await
+        .expect("Failed to search with path constraints");
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|f| f.path != draft.path));
+
+    let ids = vec![report.id.clone(), draft.id.clone(), budget.id.clone()];
+    let matching = database.file_ids_matching(&ids, &search("draft"), &filters).await
+        .expect("Failed to match file ids");
+    assert_eq!(matching, vec![draft.id.clone()]);
+}
+
+#[tokio::test]
+async fn test_search_files_cursor_pagination() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let now = Utc::now();
+    let mut expected = Vec::new();
+    for i in 0..5 {
+        let mut file = create_test_file_record();
+        file.path = format!("/pages/report-{}.txt", i);
+        file.name = format!("report-{}.txt", i);
+        // Two files share a timestamp so the id tiebreak is exercised
+        file.modified_at = now - chrono::Duration::hours((i / 2) as i64);
+        if i == 4 {
+            file.processing_status = "pending".to_string();
         }
+        database.insert_file(&file).await.expect("Failed to insert file");
+        expected.push(file.path);
     }
 
-    #[tokio::test]
-    async fn test_database_creation() {
-        let (_database, _temp_dir) = create_test_database().await;
-        // If we get here without panicking, the database was created successfully
+    let query = QueryNode::parse("report").unwrap();
+    let filters = SearchFilters::default();
+
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page = database.search_files_page(&query, &filters, 2, cursor.as_deref()).await
+            .expect("Failed to fetch search page");
+        assert_eq!(page.total, 5);
+        assert!(page.files.len() <= 2);
+        seen.extend(page.files.into_iter().map(|f| f.path));
+
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
     }
 
-    #[tokio::test]
-    async fn test_file_insertion_and_retrieval() {
-        let (database, _temp_dir) = create_test_database().await;
-        let file_record = create_test_file_record();
-
-        // Insert file
-        database.insert_file(&file_record).await.expect("Failed to insert file");
-
-        // Retrieve file by path
-        let retrieved = database.get_file_by_path(&file_record.path).await
-            .expect("Failed to retrieve file")
-            .expect("File not found");
-
-        assert_eq!(retrieved.id, file_record.id);
-        assert_eq!(retrieved.path, file_record.path);
-        assert_eq!(retrieved.name, file_record.name);
-        assert_eq!(retrieved.content, file_record.content);
-        assert_eq!(retrieved.processing_status, file_record.processing_status);
+    assert_eq!(seen.len(), 5);
+    // The pending file sorts after every completed one
+    assert_eq!(seen.last(), Some(&"/pages/report-4.txt".to_string()));
+    seen.sort();
+    expected.sort();
+    assert_eq!(seen, expected);
+
+    assert!(database.search_files_page(&query, &filters, 2, Some("garbage")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_find_duplicates() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    for (name, hash, size) in [("a.bin", "aaa", 100), ("a-copy.bin", "aaa", 100), ("a-copy2.bin", "aaa", 100), ("b.bin", "bbb", 10), ("unique.bin", "ccc", 1)] {
+        let mut file = create_test_file_record();
+        file.path = format!("/dupes/{}", name);
+        file.name = name.to_string();
+        file.size = size;
+        database.insert_file(&file).await.expect("Failed to insert file");
+        database.update_file_hash(&file.id, hash).await.expect("Failed to update hash");
     }
-
-    #[tokio::test]
-    async fn test_file_exists() {
-        let (database, _temp_dir) = create_test_database().await;
-        let file_record = create_test_file_record();
-
-        // File should not exist initially
-        let exists_before = database.file_exists(&file_record.path).await
-            .expect("Failed to check file existence");
-        assert!(!exists_before);
-
-        // Insert file
-        database.insert_file(&file_record).await.expect("Failed to insert file");
-
-        // File should exist now
-        let exists_after = database.file_exists(&file_record.path).await
-            .expect("Failed to check file existence");
-        assert!(exists_after);
+    let mut b_copy = create_test_file_record();
+    b_copy.path = "/dupes/b-copy.bin".to_string();
+    b_copy.size = 10;
+    database.insert_file(&b_copy).await.expect("Failed to insert file");
+    database.update_file_hash(&b_copy.id, "bbb").await.expect("Failed to update hash");
+
+    let report = database.find_duplicates(10).await.expect("Failed to find duplicates");
+    assert_eq!(report.group_count, 2);
+    assert_eq!(report.duplicate_files, 5);
+    assert_eq!(report.total_wasted_bytes, 210);
+    assert_eq!(report.groups[0].hash, "aaa");
+    assert_eq!(report.groups[0].files.len(), 3);
+    assert_eq!(report.groups[0].wasted_bytes, 200);
+
+    // A copy deleted from disk is no longer a duplicate, so its pair is unique again
+    database.mark_file_deleted(&b_copy.id).await.expect("Failed to mark deleted");
+    let report = database.find_duplicates(10).await.unwrap();
+    assert_eq!(report.group_count, 1);
+    assert_eq!(report.duplicate_files, 3);
+    assert_eq!(report.total_wasted_bytes, 200);
+    assert!(report.groups.iter().all(|group| group.files.iter().all(|file| file.id != b_copy.id)));
+}
+
+#[tokio::test]
+async fn test_normalized_tags() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut invoice = create_test_file_record();
+    invoice.path = "/tags/invoice.pdf".to_string();
+    invoice.tags = Some(r#"["finance", "Invoice"]"#.to_string());
+    let mut receipt = create_test_file_record();
+    receipt.path = "/tags/receipt.pdf".to_string();
+    receipt.tags = Some(r#"["FINANCE", " receipt "]"#.to_string());
+
+    database.insert_file(&invoice).await.expect("Failed to insert invoice");
+    database.insert_file(&receipt).await.expect("Failed to insert receipt");
+
+    let tags = database.list_tags().await.expect("Failed to list tags");
+    assert_eq!(tags.len(), 3);
+    assert_eq!(tags[0].name, "finance");
+    assert_eq!(tags[0].count, 2);
+
+    let renamed = database.rename_tag("receipt", "invoice").await.expect("Failed to rename tag");
+    assert_eq!(renamed, 1);
+    let tags = database.list_tags().await.expect("Failed to list tags");
+    assert_eq!(tags.len(), 2);
+    assert!(tags.iter().all(|t| t.count == 2));
+
+    let by_tag = SearchFilters { tags: vec!["INVOICE".to_string()], ..Default::default() };
+    let results = database.search_files_filtered(&QueryNode::And(Vec::new()), &by_tag, 10, 0).await
+        .expect("Failed to search by tag");
+    assert_eq!(results.len(), 2);
+
+    let updated = database.get_file_by_path(&receipt.path).await.unwrap().unwrap();
+    assert!(updated.tags.unwrap().contains("Invoice"));
+    assert!(database.rename_tag("missing", "anything").await.is_err());
+}
+
+#[tokio::test]
+async fn test_search_facets() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let names = vec!["budget.pdf", "summary.pdf", "budget.rs"];
+    let mut ids = Vec::new();
+    for name in names {
+        let mut file = create_test_file_record();
+        file.path = format!("/test/{}", name);
+        file.name = name.to_string();
+        file.extension = Some(name.rsplit('.').next().unwrap().to_string());
+        file.content = Some("quarterly budget".to_string());
+        database.insert_file(&file).await.expect("Failed to insert file");
+        ids.push(file.id);
     }
 
-    #[tokio::test]
-    async fn test_file_status_update() {
-        let (database, _temp_dir) = create_test_database().await;
-        let mut file_record = create_test_file_record();
-        file_record.processing_status = "pending".to_string();
-
-        database.insert_file(&file_record).await.expect("Failed to insert file");
-
-        // Update status to processing
-        database.update_file_status(&file_record.id, "processing", None).await
-            .expect("Failed to update file status");
-
-        let updated = database.get_file_by_path(&file_record.path).await
-            .expect("Failed to retrieve file")
-            .expect("File not found");
-
-        assert_eq!(updated.processing_status, "processing");
-        assert_eq!(updated.error_message, None);
-
-        // Update status to error with message
-        let error_msg = "Test error message";
-        database.update_file_status(&file_record.id, "error", Some(error_msg)).await
-            .expect("Failed to update file status");
-
-        let updated_with_error = database.get_file_by_path(&file_record.path).await
-            .expect("Failed to retrieve file")
-            .expect("File not found");
-
-        assert_eq!(updated_with_error.processing_status, "error");
-        assert_eq!(updated_with_error.error_message, Some(error_msg.to_string()));
+    let collection = database.create_collection("Finance", None).await
+        .expect("Failed to create collection");
+    database.add_file_to_collection(&ids[0], &collection.id).await
+        .expect("Failed to add file to collection");
+
+    let facets = database.get_search_facets(&QueryNode::parse("budget").unwrap(), &SearchFilters::default()).await
+        .expect("Failed to get search facets");
+
+    assert_eq!(facets.extensions[0].value, "pdf");
+    assert_eq!(facets.extensions[0].count, 2);
+    assert_eq!(facets.categories.iter().find(|b| b.value == "code").unwrap().count, 1);
+    assert_eq!(facets.years.iter().map(|b| b.count).sum::<i64>(), 3);
+    assert_eq!(facets.collections.len(), 1);
+    assert_eq!(facets.collections[0].label, "Finance");
+
+    let subset = database.get_facets_for_files(&ids[2..]).await
+        .expect("Failed to get facets for ids");
+    assert_eq!(subset.extensions.len(), 1);
+    assert_eq!(subset.extensions[0].value, "rs");
+}
+
+#[tokio::test]
+async fn test_search_files_with_embeddings() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    let mut file_with_embedding = create_test_file_record();
+    file_with_embedding.path = "/test/with_embedding.txt".to_string();
+    file_with_embedding.content = Some("Content with embedding".to_string());
+    file_with_embedding.embedding = Some(vec![0.1, 0.2, 0.3]);
+
+    let mut file_without_embedding = create_test_file_record();
+    file_without_embedding.path = "/test/without_embedding.txt".to_string();
+    file_without_embedding.content = Some("Content without embedding".to_string());
+    file_without_embedding.embedding = None;
+
+    database.insert_file(&file_with_embedding).await.expect("Failed to insert file with embedding");
+    database.insert_file(&file_without_embedding).await.expect("Failed to insert file without embedding");
+
+    // Search for files with embeddings
+    let results = database.search_files_with_embeddings("content", 10).await
+        .expect("Failed to search files with embeddings");
+    
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, file_with_embedding.path);
+    assert!(results[0].embedding.is_some());
+}
+
+#[tokio::test]
+async fn test_search_history_suggestions() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    database.record_search_query("budget 2024").await.expect("Failed to record query");
+    database.record_search_query("budget report").await.expect("Failed to record query");
+    database.record_search_query("budget report").await.expect("Failed to record query");
+    database.record_search_query("holiday photos").await.expect("Failed to record query");
+    database.record_search_query("   ").await.expect("Blank queries should be ignored");
+
+    let suggestions = database.get_search_history_suggestions("Bud", 10).await
+        .expect("Failed to get suggestions");
+    assert_eq!(suggestions, vec!["budget report".to_string(), "budget 2024".to_string()]);
+
+    let removed = database.clear_search_history().await.expect("Failed to clear history");
+    assert_eq!(removed, 3);
+
+    let suggestions = database.get_search_history_suggestions("bud", 10).await
+        .expect("Failed to get suggestions");
+    assert!(suggestions.is_empty());
+}
+
+#[tokio::test]
+async fn test_search_analytics() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    database.record_search_stat(Some("Budget  Report"), "keyword", 4, 10, 365).await.expect("Failed to record search");
+    database.record_search_stat(Some("budget report"), "keyword", 0, 30, 365).await.expect("Failed to record search");
+    database.record_search_stat(Some("lost contract"), "semantic", 0, 200, 365).await.expect("Failed to record search");
+    database.record_search_stat(None, "semantic", 0, 100, 365).await.expect("Failed to record search");
+
+    let analytics = database.get_search_analytics(10).await.expect("Failed to get analytics");
+    assert_eq!(analytics.total_searches, 4);
+    assert_eq!(analytics.top_queries[0].query, "budget report");
+    assert_eq!(analytics.top_queries[0].count, 2);
+
+    // Queries that found something at least once are not gaps
+    let gaps: Vec<&str> = analytics.zero_result_queries.iter().map(|q| q.query.as_str()).collect();
+    assert_eq!(gaps, vec!["lost contract"]);
+
+    let semantic = analytics.latency_by_type.iter().find(|t| t.search_type == "semantic").unwrap();
+    assert_eq!(semantic.searches, 2);
+    assert_eq!(semantic.avg_latency_ms, 150.0);
+    assert_eq!(semantic.zero_result_rate, 1.0);
+
+    database.clear_search_history().await.expect("Failed to clear history");
+    let analytics = database.get_search_analytics(10).await.expect("Failed to get analytics");
+    assert_eq!(analytics.total_searches, 4);
+    assert!(analytics.top_queries.is_empty());
+}
+
+#[tokio::test]
+async fn test_index_suggestions() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let paths = ["/work/Projects/project-plan.md", "/work/Projects/notes.txt", "/home/proposal.pdf"];
+    for (i, path) in paths.iter().enumerate() {
+        let mut file = create_test_file_record();
+        file.path = path.to_string();
+        file.name = path.rsplit('/').next().unwrap().to_string();
+        file.tags = Some(r#"["project", "planning"]"#.to_string());
+        database.insert_file(&file).await.expect("Failed to insert file");
+        if i == 0 {
+            database.update_file_entities(&file.id, &["Prosper Ltd".to_string()]).await
+                .expect("Failed to store entities");
+        }
     }
 
-    #[tokio::test]
-    async fn test_file_analysis_update() {
-        let (database, _temp_dir) = create_test_database().await;
-        let mut file_record = create_test_file_record();
-        file_record.content = None;
-        file_record.ai_analysis = None;
-        file_record.tags = None;
-        file_record.embedding = None;
-
-        database.insert_file(&file_record).await.expect("Failed to insert file");
-
-        let content = "Updated content";
-        let analysis = "Updated AI analysis";
-        let tags = r#"["updated", "tags"]"#;
-        let embedding = vec![0.5, 0.6, 0.7, 0.8];
-
-        database.update_file_analysis(&file_record.id, content, analysis, Some(tags), Some(&embedding)).await
-            .expect("Failed to update file analysis");
-
-        let updated = database.get_file_by_path(&file_record.path).await
-            .expect("Failed to retrieve file")
-            .expect("File not found");
-
-        assert_eq!(updated.content, Some(content.to_string()));
-        assert_eq!(updated.ai_analysis, Some(analysis.to_string()));
-        assert_eq!(updated.tags, Some(tags.to_string()));
-        assert_eq!(updated.embedding, Some(embedding));
-        assert_eq!(updated.processing_status, "completed");
-        assert!(updated.indexed_at.is_some());
+    let suggestions = database.get_index_suggestions("pro", 10).await
+        .expect("Failed to get suggestions");
+    let texts: Vec<&str> = suggestions.iter().map(|s| s.text.as_str()).collect();
+
+    // The tag covers every file and outranks the folder of the same name
+    assert_eq!(suggestions[0].text, "project");
+    assert_eq!(suggestions[0].kind, SuggestionKind::Tag);
+    assert_eq!(suggestions[0].count, 3);
+    assert!(texts.contains(&"Projects"));
+    assert!(texts.contains(&"Prosper Ltd"));
+    assert!(texts.contains(&"proposal.pdf"));
+    assert!(!texts.contains(&"planning"));
+
+    assert!(database.get_index_suggestions("  ", 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_deleted_files_leave_tags_and_suggestions() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut kept = create_test_file_record();
+    kept.path = "/tags/kept.txt".to_string();
+    kept.name = "kept.txt".to_string();
+    kept.tags = Some(r#"["shared"]"#.to_string());
+    let mut gone = create_test_file_record();
+    gone.path = "/trashed/gone-report.txt".to_string();
+    gone.name = "gone-report.txt".to_string();
+    gone.tags = Some(r#"["shared", "gonetag"]"#.to_string());
+    database.insert_file(&kept).await.expect("Failed to insert file");
+    database.insert_file(&gone).await.expect("Failed to insert file");
+    database.mark_file_deleted(&gone.id).await.expect("Failed to mark deleted");
+
+    let tags = database.list_tags().await.expect("Failed to list tags");
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].name, "shared");
+    assert_eq!(tags[0].count, 1);
+
+    for prefix in ["gone", "trash"] {
+        let suggestions = database.get_index_suggestions(prefix, 10).await
+            .expect("Failed to get suggestions");
+        assert!(suggestions.is_empty(), "{:?}", suggestions);
     }
-
-    #[tokio::test]
-    async fn test_get_files_by_status() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        // Create files with different statuses
-        let mut file1 = create_test_file_record();
-        file1.path = "/test/file1.txt".to_string();
-        file1.processing_status = "pending".to_string();
-
-        let mut file2 = create_test_file_record();
-        file2.path = "/test/file2.txt".to_string();
-        file2.processing_status = "completed".to_string();
-
-        let mut file3 = create_test_file_record();
-        file3.path = "/test/file3.txt".to_string();
-        file3.processing_status = "pending".to_string();
-
-        database.insert_file(&file1).await.expect("Failed to insert file1");
-        database.insert_file(&file2).await.expect("Failed to insert file2");
-        database.insert_file(&file3).await.expect("Failed to insert file3");
-
-        // Get pending files
-        let pending_files = database.get_files_by_status("pending").await
-            .expect("Failed to get pending files");
-        assert_eq!(pending_files.len(), 2);
-
-        // Get completed files
-        let completed_files = database.get_files_by_status("completed").await
-            .expect("Failed to get completed files");
-        assert_eq!(completed_files.len(), 1);
-        assert_eq!(completed_files[0].id, file2.id);
+}
+
+#[tokio::test]
+async fn test_multilingual_keyword_search() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut german = create_test_file_record();
+    german.path = "/docs/brief.txt".to_string();
+    german.name = "brief.txt".to_string();
+    german.content = Some("Die Wohnung in der Hauptstraße in München ist nicht mehr frei".to_string());
+    let mut japanese = create_test_file_record();
+    japanese.path = "/docs/memo.txt".to_string();
+    japanese.name = "memo.txt".to_string();
+    japanese.content = Some("東京都の会議は来週です".to_string());
+
+    database.insert_file(&german).await.expect("Failed to insert file");
+    database.insert_file(&japanese).await.expect("Failed to insert file");
+
+    let no_filters = SearchFilters::default();
+    for (query, expected) in [("HAUPTSTRASSE", "brief.txt"), ("munchen", "brief.txt"), ("東京都", "memo.txt")] {
+        let results = database.search_files_filtered(&QueryNode::parse(query).unwrap(), &no_filters, 10, 0).await
+            .expect("Failed to search");
+        assert_eq!(results.len(), 1, "query {}", query);
+        assert_eq!(results[0].name, expected);
     }
 
-    #[tokio::test]
-    async fn test_search_files() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        let mut file1 = create_test_file_record();
-        file1.path = "/test/document.pdf".to_string();
-        file1.name = "document.pdf".to_string();
-        file1.content = Some("This is a PDF document about machine learning".to_string());
-
-        let mut file2 = create_test_file_record();
-        file2.path = "/test/image.jpg".to_string();
-        file2.name = "image.jpg".to_string();
-        file2.content = Some("Image file description".to_string());
-
-        let mut file3 = create_test_file_record();
-        file3.path = "/test/report.txt".to_string();
-        file3.name = "report.txt".to_string();
-        file3.content = Some("Annual report with machine learning insights".to_string());
-
-        database.insert_file(&file1).await.expect("Failed to insert file1");
-        database.insert_file(&file2).await.expect("Failed to insert file2");
-        database.insert_file(&file3).await.expect("Failed to insert file3");
-
-        // Search for "machine learning"
-        let results = database.search_files("machine learning", 10, 0).await
-            .expect("Failed to search files");
-        
-        assert_eq!(results.len(), 2);
-        let result_paths: Vec<&String> = results.iter().map(|f| &f.path).collect();
-        assert!(result_paths.contains(&&file1.path));
-        assert!(result_paths.contains(&&file3.path));
-
-        // Search for "image"
-        let image_results = database.search_files("image", 10, 0).await
-            .expect("Failed to search files");
-        
-        assert_eq!(image_results.len(), 1);
-        assert_eq!(image_results[0].path, file2.path);
+    let languages = database.get_language_distribution().await.expect("Failed to get languages");
+    let codes: Vec<&str> = languages.iter().map(|l| l.language.as_str()).collect();
+    assert!(codes.contains(&"de"));
+    assert!(codes.contains(&"ja"));
+}
+
+#[tokio::test]
+async fn test_processing_stats() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    // Create files with different statuses
+    let statuses = ["completed", "pending", "processing", "error", "completed"];
+    for (i, status) in statuses.iter().enumerate() {
+        let mut file = create_test_file_record();
+        file.path = format!("/test/file{}.txt", i);
+        file.processing_status = status.to_string();
+        database.insert_file(&file).await.expect("Failed to insert file");
     }
 
-    #[tokio::test]
-    async fn test_search_files_with_embeddings() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        let mut file_with_embedding = create_test_file_record();
-        file_with_embedding.path = "/test/with_embedding.txt".to_string();
-        file_with_embedding.content = Some("Content with embedding".to_string());
-        file_with_embedding.embedding = Some(vec![0.1, 0.2, 0.3]);
-
-        let mut file_without_embedding = create_test_file_record();
-        file_without_embedding.path = "/test/without_embedding.txt".to_string();
-        file_without_embedding.content = Some("Content without embedding".to_string());
-        file_without_embedding.embedding = None;
-
-        database.insert_file(&file_with_embedding).await.expect("Failed to insert file with embedding");
-        database.insert_file(&file_without_embedding).await.expect("Failed to insert file without embedding");
-
-        // Search for files with embeddings
-        let results = database.search_files_with_embeddings("content", 10).await
-            .expect("Failed to search files with embeddings");
-        
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].path, file_with_embedding.path);
-        assert!(results[0].embedding.is_some());
+    let stats = database.get_processing_stats().await
+        .expect("Failed to get processing stats");
+
+    let stats_obj = stats.as_object().expect("Stats should be an object");
+    assert_eq!(stats_obj["total_processed"].as_i64().unwrap(), 2); // 2 completed
+    assert_eq!(stats_obj["queue_size"].as_i64().unwrap(), 1); // 1 pending
+    assert_eq!(stats_obj["current_processing"].as_i64().unwrap(), 1); // 1 processing
+    assert_eq!(stats_obj["errors"].as_i64().unwrap(), 1); // 1 error
+}
+
+#[tokio::test]
+async fn test_collection_operations() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    // Create collection
+    let collection = database.create_collection("Test Collection", Some("Test description")).await
+        .expect("Failed to create collection");
+
+    assert_eq!(collection.name, "Test Collection");
+    assert_eq!(collection.description, Some("Test description".to_string()));
+    assert_eq!(collection.file_count, 0);
+
+    // Get collections
+    let collections = database.get_collections().await
+        .expect("Failed to get collections");
+    assert_eq!(collections.len(), 1);
+    assert_eq!(collections[0].id, collection.id);
+
+    // Update collection
+    database.update_collection(&collection.id, Some("Updated Name"), None).await
+        .expect("Failed to update collection");
+
+    let updated_collection = database.get_collection_by_id(&collection.id).await
+        .expect("Failed to get collection by id")
+        .expect("Collection not found");
+    assert_eq!(updated_collection.name, "Updated Name");
+
+    // Delete collection
+    database.delete_collection(&collection.id).await
+        .expect("Failed to delete collection");
+
+    let deleted_collection = database.get_collection_by_id(&collection.id).await
+        .expect("Failed to check for deleted collection");
+    assert!(deleted_collection.is_none());
+}
+
+#[tokio::test]
+async fn test_file_collection_operations() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    // Create file and collection
+    let file_record = create_test_file_record();
+    database.insert_file(&file_record).await.expect("Failed to insert file");
+
+    let collection = database.create_collection("Test Collection", None).await
+        .expect("Failed to create collection");
+
+    // Add file to collection
+    database.add_file_to_collection(&file_record.id, &collection.id).await
+        .expect("Failed to add file to collection");
+
+    // Check collection file count updated
+    let updated_collection = database.get_collection_by_id(&collection.id).await
+        .expect("Failed to get collection")
+        .expect("Collection not found");
+    assert_eq!(updated_collection.file_count, 1);
+
+    // Get files in collection
+    let files_in_collection = database.get_files_in_collection(&collection.id).await
+        .expect("Failed to get files in collection");
+    assert_eq!(files_in_collection.len(), 1);
+    assert_eq!(files_in_collection[0].id, file_record.id);
+
+    // Remove file from collection
+    database.remove_file_from_collection(&file_record.id, &collection.id).await
+        .expect("Failed to remove file from collection");
+
+    let final_collection = database.get_collection_by_id(&collection.id).await
+        .expect("Failed to get collection")
+        .expect("Collection not found");
+    assert_eq!(final_collection.file_count, 0);
+
+    let empty_files = database.get_files_in_collection(&collection.id).await
+        .expect("Failed to get files in collection");
+    assert_eq!(empty_files.len(), 0);
+}
+
+#[tokio::test]
+async fn test_location_stats() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    // Create files in different locations with different statuses
+    let locations_and_statuses = vec![
+        ("/test/dir/file1.txt", "completed"),
+        ("/test/dir/file2.txt", "pending"),
+        ("/test/dir/subdir/file3.txt", "error"),
+        ("/other/file4.txt", "completed"),
+    ];
+
+    for (path, status) in locations_and_statuses {
+        let mut file = create_test_file_record();
+        file.path = path.to_string();
+        file.processing_status = status.to_string();
+        database.insert_file(&file).await.expect("Failed to insert file");
     }
 
-    #[tokio::test]
-    async fn test_processing_stats() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        // Create files with different statuses
-        let statuses = vec!["completed", "pending", "processing", "error", "completed"];
-        for (i, status) in statuses.iter().enumerate() {
-            let mut file = create_test_file_record();
-            file.path = format!("/test/file{}.txt", i);
-            file.processing_status = status.to_string();
-            database.insert_file(&file).await.expect("Failed to insert file");
-        }
-
-        let stats = database.get_processing_stats().await
-            .expect("Failed to get processing stats");
-
-        let stats_obj = stats.as_object().expect("Stats should be an object");
-        assert_eq!(stats_obj["total_processed"].as_i64().unwrap(), 2); // 2 completed
-        assert_eq!(stats_obj["queue_size"].as_i64().unwrap(), 1); // 1 pending
-        assert_eq!(stats_obj["current_processing"].as_i64().unwrap(), 1); // 1 processing
-        assert_eq!(stats_obj["errors"].as_i64().unwrap(), 1); // 1 error
+    // Get stats for /test/dir (should include subdirectories)
+    let stats = database.get_location_stats("/test/dir").await
+        .expect("Failed to get location stats");
+
+    let stats_obj = stats.as_object().expect("Stats should be an object");
+    assert_eq!(stats_obj["total_files"].as_i64().unwrap(), 3);
+    assert_eq!(stats_obj["processed_files"].as_i64().unwrap(), 1);
+    assert_eq!(stats_obj["pending_files"].as_i64().unwrap(), 1); // pending + processing
+    assert_eq!(stats_obj["error_files"].as_i64().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_location_stats_follow_file_changes() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut ids = Vec::new();
+    for path in ["/indexed/a/one.txt", "/indexed/a/b/two.txt", "/indexed/c/three.txt"] {
+        let mut file = create_test_file_record();
+        file.path = path.to_string();
+        file.processing_status = "pending".to_string();
+        database.insert_file(&file).await.expect("Failed to insert file");
+        ids.push(file.id);
     }
 
-    #[tokio::test]
-    async fn test_collection_operations() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        // Create collection
-        let collection = database.create_collection("Test Collection", Some("Test description")).await
-            .expect("Failed to create collection");
-
-        assert_eq!(collection.name, "Test Collection");
-        assert_eq!(collection.description, Some("Test description".to_string()));
-        assert_eq!(collection.file_count, 0);
-
-        // Get collections
-        let collections = database.get_collections().await
-            .expect("Failed to get collections");
-        assert_eq!(collections.len(), 1);
-        assert_eq!(collections[0].id, collection.id);
-
-        // Update collection
-        database.update_collection(&collection.id, Some("Updated Name"), None).await
-            .expect("Failed to update collection");
-
-        let updated_collection = database.get_collection_by_id(&collection.id).await
-            .expect("Failed to get collection by id")
-            .expect("Collection not found");
-        assert_eq!(updated_collection.name, "Updated Name");
-
-        // Delete collection
-        database.delete_collection(&collection.id).await
-            .expect("Failed to delete collection");
-
-        let deleted_collection = database.get_collection_by_id(&collection.id).await
-            .expect("Failed to check for deleted collection");
-        assert!(deleted_collection.is_none());
+    database.update_file_status(&ids[1], "error", Some("failed")).await.unwrap();
+    database.update_file_status(&ids[0], "completed", None).await.unwrap();
+    database.mark_file_deleted(&ids[2]).await.unwrap();
+
+    let counts = |stats: serde_json::Value| -> Vec<i64> {
+        ["total_files", "processed_files", "pending_files", "error_files"]
+            .iter()
+            .map(|key| stats[key].as_i64().unwrap())
+            .collect()
+    };
+    assert_eq!(counts(database.get_location_stats("/indexed").await.unwrap()), vec![2, 1, 0, 1]);
+    assert_eq!(counts(database.get_location_stats("/indexed/a/b").await.unwrap()), vec![1, 0, 0, 1]);
+    assert_eq!(counts(database.get_location_stats("/indexed/c").await.unwrap()), vec![0, 0, 0, 0]);
+    assert_eq!(counts(database.get_location_stats("/nowhere").await.unwrap()), vec![0, 0, 0, 0]);
+
+    // Replacing a row must not count its file twice
+    let mut replacement = create_test_file_record();
+    replacement.id = ids[0].clone();
+    replacement.path = "/indexed/a/one.txt".to_string();
+    replacement.processing_status = "pending".to_string();
+    database.insert_file(&replacement).await.unwrap();
+    assert_eq!(counts(database.get_location_stats("/indexed/a").await.unwrap()), vec![2, 0, 1, 1]);
+}
+
+#[tokio::test]
+async fn test_daily_stats() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut ids = Vec::new();
+    for path in ["/daily/a.txt", "/daily/b.txt"] {
+        let mut file = create_test_file_record();
+        file.path = path.to_string();
+        file.processing_status = "pending".to_string();
+        database.insert_file(&file).await.expect("Failed to insert file");
+        ids.push(file.id);
     }
-
-    #[tokio::test]
-    async fn test_file_collection_operations() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        // Create file and collection
-        let file_record = create_test_file_record();
-        database.insert_file(&file_record).await.expect("Failed to insert file");
-
-        let collection = database.create_collection("Test Collection", None).await
-            .expect("Failed to create collection");
-
-        // Add file to collection
-        database.add_file_to_collection(&file_record.id, &collection.id).await
-            .expect("Failed to add file to collection");
-
-        // Check collection file count updated
-        let updated_collection = database.get_collection_by_id(&collection.id).await
-            .expect("Failed to get collection")
-            .expect("Collection not found");
-        assert_eq!(updated_collection.file_count, 1);
-
-        // Get files in collection
-        let files_in_collection = database.get_files_in_collection(&collection.id).await
-            .expect("Failed to get files in collection");
-        assert_eq!(files_in_collection.len(), 1);
-        assert_eq!(files_in_collection[0].id, file_record.id);
-
-        // Remove file from collection
-        database.remove_file_from_collection(&file_record.id, &collection.id).await
-            .expect("Failed to remove file from collection");
-
-        let final_collection = database.get_collection_by_id(&collection.id).await
-            .expect("Failed to get collection")
-            .expect("Collection not found");
-        assert_eq!(final_collection.file_count, 0);
-
-        let empty_files = database.get_files_in_collection(&collection.id).await
-            .expect("Failed to get files in collection");
-        assert_eq!(empty_files.len(), 0);
+    database.update_file_status(&ids[0], "completed", None).await.unwrap();
+    database.update_file_status(&ids[1], "error", Some("failed")).await.unwrap();
+
+    let stats = database.get_daily_stats(7).await.expect("Failed to get daily stats");
+    assert_eq!(stats.len(), 7);
+    assert!(stats[..6].iter().all(|day| day.files_added == 0 && day.bytes_indexed == 0));
+
+    let today = stats.last().unwrap();
+    assert_eq!(today.day, Utc::now().format("%Y-%m-%d").to_string());
+    assert_eq!((today.files_added, today.files_processed, today.files_errored), (2, 1, 1));
+    assert_eq!(today.bytes_indexed, 1024);
+}
+
+#[tokio::test]
+async fn test_watched_paths() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut record = watched_paths::WatchedPathRecord {
+        path: "/watched/photos".to_string(),
+        respect_ignore_files: true,
+        include_patterns: vec!["*.jpg".to_string()],
+        exclude_patterns: Vec::new(),
+        max_file_size: Some(50 * 1024 * 1024),
+        priority: "low".to_string(),
+        volume_kind: "removable".to_string(),
+        added_at: Utc::now(),
+        last_rescan_at: None,
+    };
+    database.save_watched_path(&record).await.expect("Failed to save watched path");
+
+    let added_at = record.added_at;
+    record.priority = "high".to_string();
+    record.added_at = Utc::now() + chrono::Duration::days(1);
+    database.save_watched_path(&record).await.expect("Failed to update watched path");
+
+    let stored = database.get_watched_paths().await.expect("Failed to get watched paths");
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].priority, "high");
+    assert_eq!(stored[0].include_patterns, vec!["*.jpg".to_string()]);
+    assert_eq!(stored[0].volume_kind, "removable");
+    assert_eq!(stored[0].added_at.timestamp(), added_at.timestamp());
+    assert_eq!(stored[0].last_rescan_at, None);
+
+    // Saving the folder again keeps when it was last rescanned
+    let rescanned_at = Utc::now();
+    database.set_last_rescan("/watched/photos", rescanned_at).await.expect("Failed to set last rescan");
+    database.save_watched_path(&record).await.unwrap();
+    let stored = database.get_watched_paths().await.unwrap();
+    assert_eq!(stored[0].last_rescan_at.map(|at| at.timestamp()), Some(rescanned_at.timestamp()));
+
+    assert!(database.remove_watched_path("/watched/photos").await.unwrap());
+    assert!(database.get_watched_paths().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_file_links() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file = create_test_file_record();
+    database.insert_file(&file).await.expect("Failed to insert file");
+
+    assert!(database.get_hardlink_targets(1, 42).await.unwrap().is_empty());
+    database.save_file_link(&file_links::FileLink::hardlink(file.path.clone(), file.path.clone(), 1, 42)).await.unwrap();
+    database.save_file_link(&file_links::FileLink::hardlink("/test/other.txt".to_string(), file.path.clone(), 1, 42)).await.unwrap();
+    database.save_file_link(&file_links::FileLink::symlink("/test/link.txt".to_string(), file.path.clone())).await.unwrap();
+    assert_eq!(database.get_hardlink_targets(1, 42).await.unwrap(), vec![file.path.clone()]);
+
+    let links = database.get_file_links(&file.path).await.expect("Failed to get file links");
+    let paths: Vec<&str> = links.iter().map(|link| link.path.as_str()).collect();
+    assert_eq!(paths, vec!["/test/link.txt", "/test/other.txt"]);
+
+    assert!(database.remove_file_link("/test/link.txt").await.unwrap());
+    assert!(!database.remove_file_link("/test/link.txt").await.unwrap());
+
+    // A deleted target takes its links along and no longer stands in for its other paths
+    database.mark_file_deleted(&file.id).await.unwrap();
+    assert!(database.get_hardlink_targets(1, 42).await.unwrap().is_empty());
+    assert!(database.get_file_links(&file.path).await.unwrap().is_empty());
+
+    database.save_file_link(&file_links::FileLink::symlink("/test/dir/a.txt".to_string(), "/a.txt".to_string())).await.unwrap();
+    database.save_file_link(&file_links::FileLink::symlink("/test/dir_b.txt".to_string(), "/b.txt".to_string())).await.unwrap();
+    assert_eq!(database.remove_file_links_below("/test/dir").await.unwrap(), 1);
+    assert_eq!(database.get_file_links("/b.txt").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_stored_jobs() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let job = |id: &str, file_id: &str, age_seconds: i64| jobs::StoredJob {
+        id: id.to_string(),
+        file_id: file_id.to_string(),
+        file_path: format!("/jobs/{}.txt", file_id),
+        priority: "normal".to_string(),
+        state: "queued".to_string(),
+        retry_count: 0,
+        created_at: Utc::now() - chrono::Duration::seconds(age_seconds),
+        file_size: 1024,
+    };
+    database.save_jobs(&[job("newer", "b", 10), job("older", "a", 20)]).await.expect("Failed to save jobs");
+    database.set_job_state("older", "running").await.unwrap();
+    database.set_jobs_priority(&["newer".to_string()], "high").await.unwrap();
+
+    // A job running when the app stopped is queued again
+    let stored = database.load_jobs().await.expect("Failed to load jobs");
+    let ids: Vec<&str> = stored.iter().map(|job| job.id.as_str()).collect();
+    assert_eq!(ids, vec!["older", "newer"]);
+    assert!(stored.iter().all(|job| job.state == "queued"));
+    assert_eq!(stored[1].priority, "high");
+
+    let mut retried = stored[0].clone();
+    retried.retry_count = 2;
+    database.save_jobs(&[retried]).await.unwrap();
+    assert_eq!(database.load_jobs().await.unwrap()[0].retry_count, 2);
+
+    database.remove_jobs(&["older".to_string()]).await.unwrap();
+    assert_eq!(database.get_job_file_ids().await.unwrap(), ["b".to_string()].into_iter().collect());
+    assert_eq!(database.clear_jobs().await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_spilled_jobs() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let job = |id: &str, priority: &str, age_seconds: i64| jobs::StoredJob {
+        id: id.to_string(),
+        file_id: format!("file-{}", id),
+        file_path: format!("/spill/{}.txt", id),
+        priority: priority.to_string(),
+        state: "queued".to_string(),
+        retry_count: 0,
+        created_at: Utc::now() - chrono::Duration::seconds(age_seconds),
+        file_size: 1024,
+    };
+    database.save_jobs(&[job("kept", "low", 40), job("old", "low", 30), job("urgent", "high", 10), job("new", "low", 20)])
+        .await
+        .expect("Failed to save jobs");
+    let spilled: Vec<String> = ["old", "urgent", "new"].iter().map(|id| id.to_string()).collect();
+    database.set_jobs_state(&spilled, "spilled").await.expect("Failed to spill jobs");
+
+    // Spilled jobs stay in the table and are not restored into memory
+    let restored = database.load_jobs().await.unwrap();
+    assert_eq!(restored.iter().map(|job| job.id.as_str()).collect::<Vec<_>>(), vec!["kept"]);
+    assert_eq!(database.count_spilled_jobs().await.unwrap(), 3);
+    let among = database.spilled_file_ids_among(&["file-urgent".to_string(), "file-kept".to_string()]).await.unwrap();
+    assert_eq!(among, HashSet::from(["file-urgent".to_string()]));
+
+    let loaded = database.load_spilled_jobs(2).await.expect("Failed to load spilled jobs");
+    assert_eq!(loaded.iter().map(|job| job.id.as_str()).collect::<Vec<_>>(), vec!["urgent", "old"]);
+    assert_eq!(database.get_job_file_ids().await.unwrap().len(), 4);
+
+    // Reprioritizing reaches spilled jobs, and only those below the folder itself
+    database.save_jobs(&[jobs::StoredJob { file_path: "/spill-old/new.txt".to_string(), ..job("other", "low", 5) }]).await.unwrap();
+    database.set_jobs_state(&["other".to_string()], "spilled").await.unwrap();
+    assert_eq!(database.set_spilled_priority_below("/spill", "high").await.unwrap(), 2);
+    assert_eq!(database.set_spilled_priority_below("/sp_ll", "high").await.unwrap(), 0);
+    assert_eq!(database.set_spilled_priority_for_files(&["file-new".to_string(), "file-kept".to_string()], "critical").await.unwrap(), 1);
+    let loaded = database.load_spilled_jobs(10).await.unwrap();
+    assert_eq!(loaded.iter().map(|job| (job.id.as_str(), job.priority.as_str())).collect::<Vec<_>>(),
+        vec![("new", "critical"), ("old", "high"), ("urgent", "high"), ("other", "low")]);
+    assert_eq!(database.set_spilled_priority_below("/spill", "low").await.unwrap(), 2);
+    assert_eq!(database.load_spilled_jobs(1).await.unwrap()[0].priority, "critical");
+}
+
+#[tokio::test]
+async fn test_processing_timings() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut file = create_test_file_record();
+    file.extension = Some("PDF".to_string());
+    file.size = 100 * 1024;
+    database.insert_file(&file).await.expect("Failed to insert file");
+    database.record_processing_time(&file.id, 300).await.expect("Failed to record time");
+    database.record_processing_time(&file.id, 500).await.unwrap();
+    // A file removed meanwhile is not counted
+    database.record_processing_time("missing", 1000).await.unwrap();
+
+    let timings = database.get_processing_timings().await.expect("Failed to get timings");
+    assert_eq!(timings, vec![jobs::ProcessingTiming {
+        extension: "pdf".to_string(),
+        size_bucket: jobs::size_bucket(100 * 1024),
+        jobs: 2,
+        total_ms: 800,
+    }]);
+
+    // Past the window each job replaces an average one, so old timings fade
+    for _ in 0..200 {
+        database.record_processing_time(&file.id, 1000).await.unwrap();
     }
-
-    #[tokio::test]
-    async fn test_location_stats() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        // Create files in different locations with different statuses
-        let locations_and_statuses = vec![
-            ("/test/dir/file1.txt", "completed"),
-            ("/test/dir/file2.txt", "pending"),
-            ("/test/dir/subdir/file3.txt", "error"),
-            ("/other/file4.txt", "completed"),
-        ];
-
-        for (path, status) in locations_and_statuses {
-            let mut file = create_test_file_record();
-            file.path = path.to_string();
-            file.processing_status = status.to_string();
-            database.insert_file(&file).await.expect("Failed to insert file");
-        }
-
-        // Get stats for /test/dir (should include subdirectories)
-        let stats = database.get_location_stats("/test/dir").await
-            .expect("Failed to get location stats");
-
-        let stats_obj = stats.as_object().expect("Stats should be an object");
-        assert_eq!(stats_obj["total_files"].as_i64().unwrap(), 3);
-        assert_eq!(stats_obj["processed_files"].as_i64().unwrap(), 1);
-        assert_eq!(stats_obj["pending_files"].as_i64().unwrap(), 1); // pending + processing
-        assert_eq!(stats_obj["error_files"].as_i64().unwrap(), 1);
+    let timing = &database.get_processing_timings().await.unwrap()[0];
+    assert_eq!(timing.jobs, 100);
+    assert!(timing.total_ms / timing.jobs > 850, "average {} still held by old timings", timing.total_ms / timing.jobs);
+    assert_eq!(jobs::size_bucket(0), 0);
+    assert_eq!(jobs::size_bucket(16 * 1024), 1);
+    assert_eq!(jobs::size_bucket(100 * 1024), 2);
+    assert_eq!(jobs::size_bucket(i64::MAX), 10);
+
+    database.save_jobs(&[jobs::StoredJob {
+        id: "job".to_string(),
+        file_id: file.id.clone(),
+        file_path: file.path.clone(),
+        priority: "normal".to_string(),
+        state: "queued".to_string(),
+        retry_count: 0,
+        created_at: Utc::now(),
+        file_size: 100 * 1024,
+    }]).await.unwrap();
+    let queued = database.get_queued_files().await.expect("Failed to get queued files");
+    assert_eq!(queued, vec![jobs::QueuedFile { file_path: file.path.clone(), extension: "pdf".to_string(), size: 100 * 1024 }]);
+}
+
+#[tokio::test]
+async fn test_dead_jobs() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let dead_job = |id: &str, age_seconds: i64| jobs::DeadJob {
+        id: id.to_string(),
+        file_id: format!("file-{}", id),
+        file_path: format!("/dead/{}.pdf", id),
+        priority: "normal".to_string(),
+        stage: "extracting".to_string(),
+        kind: "failed".to_string(),
+        error: "Failed to read PDF: unexpected end of file".to_string(),
+        retry_count: 3,
+        created_at: Utc::now() - chrono::Duration::seconds(60),
+        failed_at: Utc::now() - chrono::Duration::seconds(age_seconds),
+    };
+    for id in ["old", "new"] {
+        let mut file = create_test_file_record();
+        file.id = format!("file-{}", id);
+        file.path = format!("/dead/{}.pdf", id);
+        database.insert_file(&file).await.expect("Failed to insert file");
+    }
+    database.add_dead_job(&dead_job("old", 20)).await.expect("Failed to add dead job");
+    database.add_dead_job(&dead_job("new", 10)).await.expect("Failed to add dead job");
+
+    let stored = database.get_dead_jobs().await.expect("Failed to get dead jobs");
+    let ids: Vec<&str> = stored.iter().map(|job| job.id.as_str()).collect();
+    assert_eq!(ids, vec!["new", "old"]);
+    assert_eq!(stored[1].stage, "extracting");
+    assert_eq!(stored[1].kind, "failed");
+    assert_eq!(stored[1].error, "Failed to read PDF: unexpected end of file");
+
+    // A file keeps only its latest failure
+    database.add_dead_job(&jobs::DeadJob { id: "newer".to_string(), ..dead_job("new", 5) }).await.unwrap();
+    let ids: Vec<String> = database.get_dead_jobs().await.unwrap().into_iter().map(|job| job.id).collect();
+    assert_eq!(ids, vec!["newer", "old"]);
+
+    assert_eq!(database.remove_dead_jobs(&["old".to_string(), "missing".to_string()]).await.unwrap(), 1);
+    assert_eq!(database.get_dead_jobs().await.unwrap().len(), 1);
+
+    // Deleting the file forgets its failure
+    database.mark_file_deleted("file-new").await.unwrap();
+    assert!(database.get_dead_jobs().await.unwrap().is_empty());
+    assert_eq!(database.remove_dead_jobs(&["newer".to_string()]).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_processing_history() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let attempt = |attempt: i64, result: &str, error: Option<&str>| jobs::ProcessingAttempt {
+        job_id: "job-1".to_string(),
+        file_id: "file-1".to_string(),
+        file_path: "/history/report.pdf".to_string(),
+        attempt,
+        started_at: Utc::now() - chrono::Duration::seconds(60 - attempt * 10),
+        finished_at: Utc::now(),
+        extraction_ms: Some(120),
+        analysis_ms: error.is_none().then_some(2400),
+        embedding_ms: None,
+        model: error.is_none().then(|| "llama3.1:8b".to_string()),
+        result: result.to_string(),
+        error: error.map(str::to_string),
+    };
+    database.record_processing_attempt(&attempt(0, "timeout", Some("Timed out while analyzing after 300s")))
+        .await
+        .expect("Failed to record attempt");
+    database.record_processing_attempt(&attempt(1, "completed", None)).await.expect("Failed to record attempt");
+
+    let history = database.get_processing_history("file-1").await.expect("Failed to get history");
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].attempt, 1);
+    assert_eq!(history[0].model.as_deref(), Some("llama3.1:8b"));
+    assert_eq!(history[0].analysis_ms, Some(2400));
+    assert_eq!(history[1].result, "timeout");
+    assert_eq!(history[1].analysis_ms, None);
+    assert!(database.get_processing_history("file-2").await.unwrap().is_empty());
+
+    // Only the attempt started before the cutoff goes
+    let cutoff = Utc::now() - chrono::Duration::seconds(55);
+    assert_eq!(database.prune_processing_history(cutoff).await.expect("Failed to prune history"), 1);
+    let history = database.get_processing_history("file-1").await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].attempt, 1);
+}
+
+#[tokio::test]
+async fn test_bulk_update_status() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    for (path, extension, status) in [
+        ("/bulk/docs/a.pdf", "pdf", "error"),
+        ("/bulk/docs/b.txt", "txt", "error"),
+        ("/bulk/other/c.pdf", "pdf", "error"),
+        ("/bulk/docs/d.pdf", "pdf", "completed"),
+    ] {
+        let mut file = create_test_file_record();
+        file.path = path.to_string();
+        file.extension = Some(extension.to_string());
+        file.processing_status = status.to_string();
+        file.error_message = (status == "error").then(|| "failed".to_string());
+        database.insert_file(&file).await.expect("Failed to insert file");
     }
 
-    #[tokio::test]
-    async fn test_insights_data() {
-        let (database, _temp_dir) = create_test_database().await;
-        
-        // Create files with different extensions
-        let files_data = vec![
-            ("file1.pdf", "completed"),
-            ("file2.docx", "completed"),
-            ("image1.jpg", "completed"),
-            ("image2.png", "completed"),
-            ("script.js", "completed"),
-            ("style.css", "error"),
-        ];
-
-        for (name, status) in files_data {
-            let mut file = create_test_file_record();
-            file.path = format!("/test/{}", name);
-            file.name = name.to_string();
-            file.extension = Some(name.split('.').last().unwrap().to_string());
-            file.processing_status = status.to_string();
-            database.insert_file(&file).await.expect("Failed to insert file");
-        }
+    let filters = SearchFilters {
+        extensions: vec!["PDF".to_string()],
+        processing_status: vec!["error".to_string()],
+        root_path: Some("/bulk/docs".to_string()),
+        ..Default::default()
+    };
+    let updated = database.bulk_update_status(&filters, "pending", None).await
+        .expect("Failed to update status");
+    assert_eq!(updated.len(), 1);
+    assert_eq!(updated[0].path, "/bulk/docs/a.pdf");
+    assert_eq!(updated[0].processing_status, "pending");
+    assert_eq!(updated[0].error_message, None);
+    // Everything but the status is kept
+    assert_eq!(updated[0].ai_analysis.as_deref(), Some("This is a test document."));
+
+    let errors = database.get_files_by_status("error").await.unwrap();
+    assert_eq!(errors.len(), 2);
+
+    // Soft-deleted files stay deleted whatever the filters match
+    let deleted = database.get_file_by_path("/bulk/other/c.pdf").await.unwrap().unwrap();
+    database.mark_file_deleted(&deleted.id).await.unwrap();
+    let filters = SearchFilters { root_path: Some("/bulk".to_string()), ..Default::default() };
+    let updated = database.bulk_update_status(&filters, "pending", None).await.unwrap();
+    assert!(updated.iter().all(|file| file.id != deleted.id));
+    assert!(database.bulk_update_status(&filters, "deleted", None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_last_attempt_filter() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut attempted = create_test_file_record();
+    attempted.path = "/attempts/attempted.pdf".to_string();
+    database.insert_file(&attempted).await.expect("Failed to insert file");
+    let mut untried = create_test_file_record();
+    untried.path = "/attempts/untried.pdf".to_string();
+    database.insert_file(&untried).await.expect("Failed to insert file");
+
+    database.update_file_status(&attempted.id, "processing", None).await.unwrap();
+    database.update_file_status(&attempted.id, "error", Some("failed")).await.unwrap();
+
+    let filters = |end: chrono::DateTime<Utc>| SearchFilters {
+        root_path: Some("/attempts".to_string()),
+        date_range: Some(DateRange { start: None, end: Some(end), field: DateField::LastAttempt }),
+        ..Default::default()
+    };
+    let updated = database.bulk_update_status(&filters(Utc::now()), "pending", None).await
+        .expect("Failed to update status");
+    let paths: Vec<&str> = updated.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, vec!["/attempts/attempted.pdf"]);
+
+    let updated = database.bulk_update_status(&filters(Utc::now() - chrono::Duration::hours(1)), "pending", None).await.unwrap();
+    assert!(updated.is_empty());
+}
+
+// Folder renames join paths with the platform separator
+#[cfg(unix)]
+#[tokio::test]
+async fn test_renames_keep_the_record() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut file = create_test_file_record();
+    file.path = "/renamed/docs/report.txt".to_string();
+    database.insert_file(&file).await.expect("Failed to insert file");
+    let mut other = create_test_file_record();
+    other.path = "/renamed/docs/deeper/notes.txt".to_string();
+    database.insert_file(&other).await.expect("Failed to insert file");
+
+    database.rename_file(&file.id, "/renamed/docs/final report.txt").await
+        .expect("Failed to rename file");
+    let renamed = database.get_file_by_id(&file.id).await.unwrap().unwrap();
+    assert_eq!(renamed.name, "final report.txt");
+    assert_eq!(renamed.ai_analysis, file.ai_analysis);
+    assert_eq!(renamed.processing_status, "completed");
+
+    let moved = database.rename_directory("/renamed/docs", "/renamed/archive").await
+        .expect("Failed to rename directory");
+    assert_eq!(moved, 2);
+    let moved_file = database.get_file_by_id(&other.id).await.unwrap().unwrap();
+    assert_eq!(moved_file.path, "/renamed/archive/deeper/notes.txt");
+    assert!(database.get_file_by_path("/renamed/docs/deeper/notes.txt").await.unwrap().is_none());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_deleted_directory() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut inside = create_test_file_record();
+    inside.path = "/trashed/docs/deeper/notes.txt".to_string();
+    database.insert_file(&inside).await.expect("Failed to insert file");
+    let mut sibling = create_test_file_record();
+    sibling.path = "/trashed/docs-old/notes.txt".to_string();
+    database.insert_file(&sibling).await.expect("Failed to insert file");
+
+    let deleted = database.mark_directory_deleted("/trashed/docs/").await
+        .expect("Failed to delete directory");
+    assert_eq!(deleted, 1);
+    let record = database.get_file_by_id(&inside.id).await.unwrap().unwrap();
+    assert_eq!(record.processing_status, "deleted");
+    assert_eq!(record.ai_analysis, None);
+    let untouched = database.get_file_by_id(&sibling.id).await.unwrap().unwrap();
+    assert_eq!(untouched.processing_status, "completed");
+}
+
+#[tokio::test]
+async fn test_insights_data() {
+    let (database, _temp_dir) = create_test_database().await;
+    
+    // Create files with different extensions
+    let files_data = vec![
+        ("file1.pdf", "completed"),
+        ("file2.docx", "completed"),
+        ("image1.jpg", "completed"),
+        ("image2.png", "completed"),
+        ("script.js", "completed"),
+        ("style.css", "error"),
+    ];
+
+    for (name, status) in files_data {
+        let mut file = create_test_file_record();
+        file.path = format!("/test/{}", name);
+        file.name = name.to_string();
+        file.extension = Some(name.rsplit('.').next().unwrap().to_string());
+        file.processing_status = status.to_string();
+        database.insert_file(&file).await.expect("Failed to insert file");
+    }
 
-        let insights = database.get_insights_data().await
-            .expect("Failed to get insights data");
-
-        let insights_obj = insights.as_object().expect("Insights should be an object");
-        
-        // Check file types
-        let file_types = insights_obj["file_types"].as_object().unwrap();
-        assert_eq!(file_types["documents"].as_i64().unwrap(), 2); // pdf, docx
-        assert_eq!(file_types["images"].as_i64().unwrap(), 2); // jpg, png
-        assert_eq!(file_types["code"].as_i64().unwrap(), 1); // js (css is error status)
-        
-        // Check processing summary
-        let processing_summary = insights_obj["processing_summary"].as_object().unwrap();
-        assert_eq!(processing_summary["total_files"].as_i64().unwrap(), 6);
-        assert_eq!(processing_summary["completed_files"].as_i64().unwrap(), 5);
-        assert_eq!(processing_summary["error_files"].as_i64().unwrap(), 1);
+    let insights = database.get_insights_data().await
+        .expect("Failed to get insights data");
+
+    let insights_obj = insights.as_object().expect("Insights should be an object");
+    
+    // Check file types
+    let file_types = insights_obj["file_types"].as_object().unwrap();
+    assert_eq!(file_types["documents"].as_i64().unwrap(), 2); // pdf, docx
+    assert_eq!(file_types["images"].as_i64().unwrap(), 2); // jpg, png
+    assert_eq!(file_types["code"].as_i64().unwrap(), 1); // js (css is error status)
+    
+    // Check processing summary
+    let processing_summary = insights_obj["processing_summary"].as_object().unwrap();
+    assert_eq!(processing_summary["total_files"].as_i64().unwrap(), 6);
+    assert_eq!(processing_summary["completed_files"].as_i64().unwrap(), 5);
+    assert_eq!(processing_summary["error_files"].as_i64().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_soft_delete_undo_and_purge() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file = create_test_file_record();
+    database.insert_file(&file).await.expect("Failed to insert file");
+    let collection = database.create_collection("Kept", None).await.expect("Failed to create collection");
+    database.add_file_to_collection(&file.id, &collection.id).await.expect("Failed to add file to collection");
+
+    database.mark_file_deleted(&file.id).await.expect("Failed to delete file");
+    assert_eq!(database.get_deleted_files().await.unwrap().len(), 1);
+    assert!(database.search_files("Test", 10, 0).await.unwrap().is_empty());
+
+    // Derived data goes with the deletion
+    let deleted = database.get_file_by_path(&file.path).await.unwrap().unwrap();
+    assert!(deleted.content.is_none() && deleted.ai_analysis.is_none() && deleted.indexed_at.is_none());
+    let search_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files_fts WHERE id = ?")
+        .bind(&file.id)
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+    assert_eq!(search_rows, 0);
+    let collection_after_delete = database.get_collection_by_id(&collection.id).await.unwrap().unwrap();
+    assert_eq!(collection_after_delete.file_count, 0);
+
+    assert!(database.restore_deleted_file(&file.id).await.unwrap());
+    assert!(!database.restore_deleted_file(&file.id).await.unwrap());
+    let restored = database.get_file_by_path(&file.path).await.unwrap().unwrap();
+    assert_eq!(restored.processing_status, "pending");
+    assert_eq!(database.search_files("file", 10, 0).await.unwrap().len(), 1);
+
+    // Inside the grace period nothing is purged
+    database.mark_file_deleted(&file.id).await.expect("Failed to delete file");
+    let purged = database.purge_deleted_files(Utc::now() - chrono::Duration::days(30)).await.unwrap();
+    assert_eq!(purged, 0);
+
+    let purged = database.purge_deleted_files(Utc::now() + chrono::Duration::seconds(1)).await.unwrap();
+    assert_eq!(purged, 1);
+    assert!(database.get_file_by_path(&file.path).await.unwrap().is_none());
+    assert!(database.get_deleted_files().await.unwrap().is_empty());
+
+    let collection = database.get_collection_by_id(&collection.id).await.unwrap().unwrap();
+    assert_eq!(collection.file_count, 0);
+}
+
+#[tokio::test]
+async fn test_retention_strips_content_and_keeps_metadata() {
+    let (database, _temp_dir) = create_test_database().await;
+    let mut old_file = create_test_file_record();
+    old_file.indexed_at = Some(Utc::now() - chrono::Duration::days(400));
+    database.insert_file(&old_file).await.expect("Failed to insert file");
+
+    let mut recent_file = create_test_file_record();
+    recent_file.path = "/test/path/recent.txt".to_string();
+    recent_file.name = "recent.txt".to_string();
+    database.insert_file(&recent_file).await.expect("Failed to insert file");
+
+    let cutoff = Utc::now() - chrono::Duration::days(365);
+    let expired = database.get_files_past_retention(cutoff, 100).await.unwrap();
+    assert_eq!(expired, vec![old_file.id.clone()]);
+    assert_eq!(database.strip_file_content(&expired).await.unwrap(), 1);
+    assert!(database.get_files_past_retention(cutoff, 100).await.unwrap().is_empty());
+
+    let stripped = database.get_file_by_path(&old_file.path).await.unwrap().unwrap();
+    assert!(stripped.content.is_none() && stripped.ai_analysis.is_none() && stripped.embedding.is_none());
+    assert_eq!(stripped.tags, old_file.tags);
+    assert_eq!(stripped.metadata, old_file.metadata);
+
+    // Content words no longer match, the name still does
+    let results = database.search_files("content", 10, 0).await.unwrap();
+    assert_eq!(results.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(), vec![recent_file.id.as_str()]);
+    assert_eq!(database.search_files("file.txt", 10, 0).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_insert_files_in_batches_skips_existing_paths() {
+    let (database, _temp_dir) = create_test_database().await;
+    let existing = create_test_file_record();
+    database.insert_file(&existing).await.expect("Failed to insert file");
+
+    let mut files = vec![create_test_file_record()];
+    for i in 0..INSERT_BATCH_SIZE + 10 {
+        let mut file = create_test_file_record();
+        file.path = format!("/test/scan/file{}.txt", i);
+        file.name = format!("file{}.txt", i);
+        files.push(file);
     }
-}
\ No newline at end of file
+
+    let inserted = database.insert_files(&files).await.expect("Failed to insert files");
+    assert_eq!(inserted.len(), INSERT_BATCH_SIZE + 10);
+    assert!(!inserted.contains(&files[0].id));
+
+    let kept = database.get_file_by_path(&existing.path).await.unwrap().unwrap();
+    assert_eq!(kept.id, existing.id);
+    assert_eq!(database.search_files("file42.txt", 10, 0).await.unwrap().len(), 1);
+    assert!(database.insert_files(&files).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_file_fingerprints_and_stat_updates() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file = create_test_file_record();
+    database.insert_file(&file).await.expect("Failed to insert file");
+
+    let paths = vec![file.path.clone(), "/test/unknown.txt".to_string()];
+    let fingerprints = database.get_file_fingerprints(&paths).await.unwrap();
+    assert_eq!(fingerprints.len(), 1);
+    let fingerprint = &fingerprints[&file.path];
+    assert_eq!(fingerprint.size, file.size);
+    assert_eq!(fingerprint.modified_at, file.modified_at);
+    assert_eq!(fingerprint.hash.as_deref(), Some("test-hash"));
+
+    // Same content under a new mtime keeps the analysis
+    let touched = file.modified_at + chrono::Duration::seconds(5);
+    database.update_file_stat(&file.id, file.size, touched, false).await.unwrap();
+    let stored = database.get_file_by_path(&file.path).await.unwrap().unwrap();
+    assert_eq!(stored.modified_at, touched);
+    assert_eq!(stored.processing_status, "completed");
+
+    database.update_file_stat(&file.id, 2048, touched, true).await.unwrap();
+    let stored = database.get_file_by_path(&file.path).await.unwrap().unwrap();
+    assert_eq!(stored.size, 2048);
+    assert_eq!(stored.processing_status, "pending");
+}
+
+#[tokio::test]
+async fn test_reconcile_marks_missing_files_deleted() {
+    let (database, temp_dir) = create_test_database().await;
+    let missing = create_test_file_record();
+    database.insert_file(&missing).await.expect("Failed to insert file");
+
+    let present_path = temp_dir.path().join("present.txt");
+    std::fs::write(&present_path, "still here").unwrap();
+    let mut present = create_test_file_record();
+    present.id = Uuid::new_v4().to_string();
+    present.path = present_path.to_string_lossy().to_string();
+    database.insert_file(&present).await.expect("Failed to insert file");
+
+    let monitor = crate::file_monitor::FileMonitor::new(database.clone());
+    let report = monitor.reconcile_missing_files(true).await.unwrap();
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.missing_paths, vec![missing.path.clone()]);
+    assert_eq!(report.marked_deleted, 0);
+    assert!(database.get_deleted_files().await.unwrap().is_empty());
+
+    let report = monitor.reconcile_missing_files(false).await.unwrap();
+    assert_eq!(report.marked_deleted, 1);
+    let deleted = database.get_deleted_files().await.unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].id, missing.id);
+}
+
+#[tokio::test]
+async fn test_changed_files_keep_previous_versions() {
+    let (database, _temp_dir) = create_test_database().await;
+    let file = create_test_file_record();
+    database.insert_file(&file).await.expect("Failed to insert file");
+
+    let touched = file.modified_at + chrono::Duration::seconds(5);
+    database.update_file_stat(&file.id, file.size, touched, false).await.unwrap();
+    assert!(database.get_file_versions(&file.id).await.unwrap().is_empty());
+
+    database.update_file_stat(&file.id, 2048, touched, true).await.unwrap();
+    database.update_file_analysis(&file.id, "New content", "This is a revised document.", None, None)
+        .await
+        .unwrap();
+
+    let versions = database.get_file_versions(&file.id).await.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].version, 1);
+    assert_eq!(versions[0].size, file.size);
+    assert_eq!(versions[0].summary, file.ai_analysis);
+    assert_eq!(versions[0].hash.as_deref(), Some("test-hash"));
+
+    let diff = database.diff_file_versions(&file.id, 1, None).await.unwrap();
+    let removed: Vec<&str> = diff.segments.iter()
+        .filter(|s| s.kind == versions::DiffKind::Removed)
+        .map(|s| s.text.as_str())
+        .collect();
+    assert_eq!(removed, vec!["test"]);
+    assert!(database.diff_file_versions(&file.id, 2, None).await.is_err());
+
+    database.mark_file_deleted(&file.id).await.unwrap();
+    database.purge_deleted_files(Utc::now() + chrono::Duration::seconds(1)).await.unwrap();
+    assert!(database.get_file_versions(&file.id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_connection_settings_defaults_and_synchronous_mode() {
+    // Settings saved before a field existed take its default
+    let settings: ConnectionSettings = serde_json::from_str(r#"{"max_connections": 4}"#)
+        .expect("Failed to read partial settings");
+    assert_eq!(settings.max_connections, 4);
+    assert_eq!(settings.synchronous, ConnectionSettings::default().synchronous);
+    assert!(settings.synchronous_mode().is_ok());
+
+    let invalid = ConnectionSettings { synchronous: "sometimes".to_string(), ..Default::default() };
+    assert!(invalid.synchronous_mode().is_err());
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    assert!(Database::open(temp_dir.path().join("test.db"), None, &invalid).await.is_err());
+}
+
+#[tokio::test]
+async fn test_search_matches_like_wildcards_literally() {
+    let (database, _temp_dir) = create_test_database().await;
+
+    let mut similar = create_test_file_record();
+    similar.path = "/wildcards/axb.txt".to_string();
+    similar.name = "axb.txt".to_string();
+    similar.content = Some("axb".to_string());
+    database.insert_file(&similar).await.expect("Failed to insert file");
+
+    let mut exact = create_test_file_record();
+    exact.path = "/wildcards/a_b.txt".to_string();
+    exact.name = "a_b.txt".to_string();
+    exact.content = Some("a_b".to_string());
+    database.insert_file(&exact).await.expect("Failed to insert file");
+
+    let results = database
+        .search_files_filtered(&QueryNode::parse("a_b").unwrap(), &SearchFilters::default(), 10, 0)
+        .await
+        .expect("Failed to search");
+    let paths: Vec<&str> = results.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, vec![exact.path.as_str()]);
+}
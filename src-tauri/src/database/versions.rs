@@ -0,0 +1,227 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::{Row, SqliteConnection};
+
+use super::Database;
+
+/// Older versions beyond this are dropped when a new one is recorded
+const MAX_VERSIONS_PER_FILE: i64 = 20;
+
+/// Word diffs above this many comparisons fall back to replacing the whole summary
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// What a file looked like when it was last indexed, before it changed on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileVersion {
+    pub version: i64,
+    pub hash: Option<String>,
+    pub size: i64,
+    pub modified_at: DateTime<Utc>,
+    pub summary: Option<String>,
+    /// JSON array, as stored on the file
+    pub tags: Option<String>,
+    pub indexed_at: Option<DateTime<Utc>>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Equal,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryDiff {
+    pub from_version: i64,
+    /// `None` compares against the current summary
+    pub to_version: Option<i64>,
+    pub segments: Vec<DiffSegment>,
+}
+
+/// Copy the indexed state of a file into its history before it is reprocessed.
+/// Files that were never indexed have nothing worth keeping.
+pub(super) async fn record_version(connection: &mut SqliteConnection, file_id: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO file_versions (file_id, version, hash, size, modified_at, summary, tags, indexed_at, recorded_at)
+        SELECT id,
+               COALESCE((SELECT MAX(version) FROM file_versions WHERE file_id = files.id), 0) + 1,
+               hash, size, modified_at, ai_analysis, tags, indexed_at, ?
+        FROM files
+        WHERE id = ? AND indexed_at IS NOT NULL
+        "#
+    )
+    .bind(Utc::now().to_rfc3339())
+    .bind(file_id)
+    .execute(&mut *connection)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM file_versions
+        WHERE file_id = ?
+          AND version <= (SELECT MAX(version) FROM file_versions WHERE file_id = ?) - ?
+        "#
+    )
+    .bind(file_id)
+    .bind(file_id)
+    .bind(MAX_VERSIONS_PER_FILE)
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+}
+
+fn version_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<FileVersion> {
+    let indexed_at: Option<String> = row.get("indexed_at");
+    Ok(FileVersion {
+        version: row.get("version"),
+        hash: row.get("hash"),
+        size: row.get("size"),
+        modified_at: parse_timestamp(&row.get::<String, _>("modified_at"))?,
+        summary: row.get("summary"),
+        tags: row.get("tags"),
+        indexed_at: indexed_at.as_deref().map(parse_timestamp).transpose()?,
+        recorded_at: parse_timestamp(&row.get::<String, _>("recorded_at"))?,
+    })
+}
+
+impl Database {
+    /// Earlier versions of a file, newest first
+    pub async fn get_file_versions(&self, file_id: &str) -> Result<Vec<FileVersion>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT version, hash, size, modified_at, summary, tags, indexed_at, recorded_at
+            FROM file_versions
+            WHERE file_id = ?
+            ORDER BY version DESC
+            "#
+        )
+        .bind(file_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(version_from_row).collect()
+    }
+
+    async fn version_summary(&self, file_id: &str, version: i64) -> Result<Option<String>> {
+        let summary: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT summary FROM file_versions WHERE file_id = ? AND version = ?"
+        )
+        .bind(file_id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        summary.ok_or_else(|| anyhow!("Version {} of file {} not found", version, file_id))
+    }
+
+    /// Word diff between the summaries of two versions, or of one version and the current file
+    pub async fn diff_file_versions(&self, file_id: &str, from_version: i64, to_version: Option<i64>) -> Result<SummaryDiff> {
+        let old = self.version_summary(file_id, from_version).await?;
+        let new = match to_version {
+            Some(version) => self.version_summary(file_id, version).await?,
+            None => {
+                let row = sqlx::query("SELECT ai_analysis FROM files WHERE id = ?")
+                    .bind(file_id)
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .ok_or_else(|| anyhow!("File not found: {}", file_id))?;
+                row.get("ai_analysis")
+            }
+        };
+
+        Ok(SummaryDiff {
+            from_version,
+            to_version,
+            segments: diff_words(old.as_deref().unwrap_or(""), new.as_deref().unwrap_or("")),
+        })
+    }
+}
+
+/// Longest-common-subsequence diff over whitespace separated words
+pub fn diff_words(old: &str, new: &str) -> Vec<DiffSegment> {
+    let old: Vec<&str> = old.split_whitespace().collect();
+    let new: Vec<&str> = new.split_whitespace().collect();
+
+    let mut segments = Vec::new();
+    if (old.len() + 1) * (new.len() + 1) > MAX_DIFF_CELLS {
+        push_word(&mut segments, DiffKind::Removed, &old.join(" "));
+        push_word(&mut segments, DiffKind::Added, &new.join(" "));
+        return segments;
+    }
+
+    // lengths[i][j]: common subsequence length of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push_word(&mut segments, DiffKind::Equal, old[i]);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[(i + 1) * width + j] >= lengths[i * width + j + 1]) {
+            push_word(&mut segments, DiffKind::Removed, old[i]);
+            i += 1;
+        } else {
+            push_word(&mut segments, DiffKind::Added, new[j]);
+            j += 1;
+        }
+    }
+
+    segments
+}
+
+fn push_word(segments: &mut Vec<DiffSegment>, kind: DiffKind, word: &str) {
+    if word.is_empty() {
+        return;
+    }
+    match segments.last_mut() {
+        Some(last) if last.kind == kind => {
+            last.text.push(' ');
+            last.text.push_str(word);
+        }
+        _ => segments.push(DiffSegment { kind, text: word.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_words_groups_changes() {
+        let segments = diff_words("the quick brown fox", "the slow brown fox jumps");
+        assert_eq!(segments, vec![
+            DiffSegment { kind: DiffKind::Equal, text: "the".to_string() },
+            DiffSegment { kind: DiffKind::Removed, text: "quick".to_string() },
+            DiffSegment { kind: DiffKind::Added, text: "slow".to_string() },
+            DiffSegment { kind: DiffKind::Equal, text: "brown fox".to_string() },
+            DiffSegment { kind: DiffKind::Added, text: "jumps".to_string() },
+        ]);
+        assert!(diff_words("", "").is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+
+use super::Database;
+
+/// A watched folder and the policy its files are indexed with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedPathRecord {
+    pub path: String,
+    pub respect_ignore_files: bool,
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    /// Bytes; the monitor's limit applies when unset
+    pub max_file_size: Option<i64>,
+    /// `low`, `normal`, `high` or `critical`
+    pub priority: String,
+    /// `local`, `network` or `removable`
+    pub volume_kind: String,
+    pub added_at: DateTime<Utc>,
+    /// Set through `set_last_rescan`; saving the folder keeps the stored time
+    pub last_rescan_at: Option<DateTime<Utc>>,
+}
+
+impl Database {
+    /// Insert the folder or replace its policy; it keeps its original `added_at`
+    pub async fn save_watched_path(&self, record: &WatchedPathRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO watched_paths
+                (path, respect_ignore_files, include_patterns, exclude_patterns, max_file_size, priority, volume_kind, added_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(path) DO UPDATE SET
+                respect_ignore_files = excluded.respect_ignore_files,
+                include_patterns = excluded.include_patterns,
+                exclude_patterns = excluded.exclude_patterns,
+                max_file_size = excluded.max_file_size,
+                priority = excluded.priority,
+                volume_kind = excluded.volume_kind
+            "#
+        )
+        .bind(&record.path)
+        .bind(record.respect_ignore_files)
+        .bind(serde_json::to_string(&record.include_patterns)?)
+        .bind(serde_json::to_string(&record.exclude_patterns)?)
+        .bind(record.max_file_size)
+        .bind(&record.priority)
+        .bind(&record.volume_kind)
+        .bind(record.added_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Every watched folder, in the order they were added
+    pub async fn get_watched_paths(&self) -> Result<Vec<WatchedPathRecord>> {
+        let rows = sqlx::query("SELECT * FROM watched_paths ORDER BY added_at, path")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(WatchedPathRecord {
+                    path: row.get("path"),
+                    respect_ignore_files: row.get("respect_ignore_files"),
+                    include_patterns: serde_json::from_str(&row.get::<String, _>("include_patterns"))?,
+                    exclude_patterns: serde_json::from_str(&row.get::<String, _>("exclude_patterns"))?,
+                    max_file_size: row.get("max_file_size"),
+                    priority: row.get("priority"),
+                    volume_kind: row.get("volume_kind"),
+                    added_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("added_at"))?.with_timezone(&Utc),
+                    last_rescan_at: row.get::<Option<String>, _>("last_rescan_at")
+                        .map(|at| DateTime::parse_from_rfc3339(&at))
+                        .transpose()?
+                        .map(|at| at.with_timezone(&Utc)),
+                })
+            })
+            .collect()
+    }
+
+    /// Record when the folder was last rescanned
+    pub async fn set_last_rescan(&self, path: &str, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE watched_paths SET last_rescan_at = ? WHERE path = ?")
+            .bind(at.to_rfc3339())
+            .bind(path)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns whether the folder was stored
+    pub async fn remove_watched_path(&self, path: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM watched_paths WHERE path = ?")
+            .bind(path)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
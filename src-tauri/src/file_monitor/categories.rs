@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "mkv", "avi", "mov", "wmv", "flv", "webm", "mpg", "mpeg", "3gp", "ogv", "vob", "m2ts",
+];
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "wav", "flac", "aac", "ogg", "oga", "m4a", "wma", "opus", "aiff", "aif", "mid", "midi",
+];
+const BINARY_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "so", "dylib", "bin", "o", "obj", "a", "lib", "class", "pyc", "pyo", "wasm", "msi", "dmg",
+    "iso", "img", "apk", "deb", "rpm", "sys",
+];
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    "zip", "tar", "gz", "tgz", "bz2", "tbz2", "xz", "txz", "7z", "rar", "zst", "lz4", "cab", "jar",
+];
+const BINARY_MIME_TYPES: &[&str] = &[
+    "application/octet-stream", "application/x-executable", "application/x-msdownload", "application/x-sharedlib",
+];
+const ARCHIVE_MIME_TYPES: &[&str] = &[
+    "application/zip", "application/x-tar", "application/gzip", "application/x-bzip2", "application/x-7z-compressed",
+    "application/vnd.rar", "application/x-rar-compressed",
+];
+/// Source files whose extension is registered for a media type too, e.g. `.ts` for MPEG streams
+const SOURCE_EXTENSIONS: &[&str] = &["ts"];
+
+/// Kinds of file that can be left out of the index as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileCategory {
+    Video,
+    Audio,
+    Binary,
+    Archive,
+}
+
+impl FileCategory {
+    /// Category of `path` by its extension, or by the MIME type registered for it
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        let extension = extension.as_str();
+        if VIDEO_EXTENSIONS.contains(&extension) {
+            return Some(FileCategory::Video);
+        }
+        if AUDIO_EXTENSIONS.contains(&extension) {
+            return Some(FileCategory::Audio);
+        }
+        if BINARY_EXTENSIONS.contains(&extension) {
+            return Some(FileCategory::Binary);
+        }
+        if ARCHIVE_EXTENSIONS.contains(&extension) {
+            return Some(FileCategory::Archive);
+        }
+        if SOURCE_EXTENSIONS.contains(&extension) {
+            return None;
+        }
+
+        let mime = mime_guess::from_path(path).first()?;
+        match mime.type_().as_str() {
+            "video" => Some(FileCategory::Video),
+            "audio" => Some(FileCategory::Audio),
+            _ if BINARY_MIME_TYPES.contains(&mime.essence_str()) => Some(FileCategory::Binary),
+            _ if ARCHIVE_MIME_TYPES.contains(&mime.essence_str()) => Some(FileCategory::Archive),
+            _ => None,
+        }
+    }
+}
+
+/// Categories of file that are never indexed, for users who only care about documents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CategoryExclusions {
+    pub skip_video: bool,
+    pub skip_audio: bool,
+    /// Executables, libraries, object files and disk images
+    pub skip_binaries: bool,
+    /// Archives larger than this are skipped; all are indexed without it
+    pub max_archive_mb: Option<u64>,
+}
+
+impl CategoryExclusions {
+    /// Whether a file of `size` bytes at `path` is left out
+    pub fn excludes(&self, path: &Path, size: u64) -> bool {
+        match FileCategory::of(path) {
+            Some(FileCategory::Video) => self.skip_video,
+            Some(FileCategory::Audio) => self.skip_audio,
+            Some(FileCategory::Binary) => self.skip_binaries,
+            Some(FileCategory::Archive) => match self.max_archive_mb {
+                Some(max_mb) => size > max_mb.saturating_mul(1024 * 1024),
+                None => false,
+            },
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categories() {
+        assert_eq!(FileCategory::of(Path::new("/movies/Clip.MKV")), Some(FileCategory::Video));
+        assert_eq!(FileCategory::of(Path::new("/music/song.flac")), Some(FileCategory::Audio));
+        assert_eq!(FileCategory::of(Path::new("/build/libfoo.so")), Some(FileCategory::Binary));
+        assert_eq!(FileCategory::of(Path::new("/backup/site.tar.gz")), Some(FileCategory::Archive));
+        // Known by MIME type only
+        assert_eq!(FileCategory::of(Path::new("/music/track.weba")), Some(FileCategory::Audio));
+        assert_eq!(FileCategory::of(Path::new("/src/app.ts")), None);
+        assert_eq!(FileCategory::of(Path::new("/docs/report.pdf")), None);
+        assert_eq!(FileCategory::of(Path::new("/docs/README")), None);
+    }
+
+    #[test]
+    fn test_exclusions() {
+        let exclusions = CategoryExclusions { skip_video: true, max_archive_mb: Some(10), ..Default::default() };
+        assert!(exclusions.excludes(Path::new("/movies/clip.mp4"), 1));
+        assert!(!exclusions.excludes(Path::new("/music/song.mp3"), 1));
+        assert!(!exclusions.excludes(Path::new("/backup/small.zip"), 10 * 1024 * 1024));
+        assert!(exclusions.excludes(Path::new("/backup/large.zip"), 10 * 1024 * 1024 + 1));
+        assert!(!CategoryExclusions::default().excludes(Path::new("/movies/clip.mp4"), u64::MAX));
+    }
+}
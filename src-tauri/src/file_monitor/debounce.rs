@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::time::{Duration, Instant};
+
+use super::{FileEvent, FileEventType};
+
+/// A path written to without pause is still dispatched after this many windows
+const MAX_DELAY_WINDOWS: u32 = 10;
+
+/// How many times longer the window is while the processing queue is full
+const BACKPRESSURE_WINDOWS: u32 = 4;
+
+struct PendingEvent {
+    event: FileEvent,
+    first_seen: Instant,
+    last_seen: Instant,
+}
+
+/// Collects file events per path and releases one event per path once the path has been
+/// quiet for the window, so a save that emits a burst of events is handled once.
+pub struct EventDebouncer {
+    window: Duration,
+    backpressure: bool,
+    pending: HashMap<PathBuf, PendingEvent>,
+}
+
+impl EventDebouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            backpressure: false,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, mut event: FileEvent, now: Instant) {
+        // Whatever was pending for the old name of a rename is taken over by the rename
+        if let FileEventType::Renamed { from, .. } = &mut event.event_type {
+            if let Some(previous) = self.pending.remove(from.as_path()) {
+                if let FileEventType::Renamed { from: original, .. } = previous.event.event_type {
+                    *from = original;
+                }
+            }
+        }
+
+        let (event, first_seen) = match self.pending.remove(&event.path) {
+            Some(previous) => {
+                // A file renamed and then deleted leaves its record under the old name, so that
+                // name is deleted too, unless something new happened there since
+                if let (FileEventType::Renamed { from, .. }, FileEventType::Deleted) = (&previous.event.event_type, &event.event_type) {
+                    if !self.pending.contains_key(from) {
+                        let deleted = FileEvent { path: from.clone(), event_type: FileEventType::Deleted, timestamp: event.timestamp };
+                        self.pending.insert(from.clone(), PendingEvent { event: deleted, first_seen: previous.first_seen, last_seen: now });
+                    }
+                }
+                let event_type = coalesce(previous.event.event_type, event.event_type);
+                (FileEvent { event_type, ..event }, previous.first_seen)
+            }
+            None => (event, now),
+        };
+        self.pending.insert(event.path.clone(), PendingEvent { event, first_seen, last_seen: now });
+    }
+
+    /// Wait longer before releasing events while the queue cannot take more, so paths that keep
+    /// changing are handled fewer times
+    pub fn set_backpressure(&mut self, backpressure: bool) {
+        self.backpressure = backpressure;
+    }
+
+    fn window(&self) -> Duration {
+        match self.backpressure {
+            true => self.window * BACKPRESSURE_WINDOWS,
+            false => self.window,
+        }
+    }
+
+    fn ready_at(&self, pending: &PendingEvent) -> Instant {
+        let window = self.window();
+        (pending.last_seen + window).min(pending.first_seen + window * MAX_DELAY_WINDOWS)
+    }
+
+    /// When the next pending event becomes ready, if any
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().map(|pending| self.ready_at(pending)).min()
+    }
+
+    /// Remove and return the events that are ready, oldest first
+    pub fn take_ready(&mut self, now: Instant) -> Vec<FileEvent> {
+        let ready_paths: Vec<PathBuf> = self.pending.iter()
+            .filter(|(_, pending)| self.ready_at(pending) <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let mut ready: Vec<PendingEvent> = ready_paths.iter()
+            .filter_map(|path| self.pending.remove(path))
+            .collect();
+        ready.sort_by_key(|pending| pending.first_seen);
+        ready.into_iter().map(|pending| pending.event).collect()
+    }
+
+    /// Everything still waiting, e.g. when the event stream ends
+    pub fn drain(&mut self) -> Vec<FileEvent> {
+        let mut pending: Vec<PendingEvent> = self.pending.drain().map(|(_, pending)| pending).collect();
+        pending.sort_by_key(|pending| pending.first_seen);
+        pending.into_iter().map(|pending| pending.event).collect()
+    }
+}
+
+/// The single event that has the same effect as `earlier` followed by `later`
+fn coalesce(earlier: FileEventType, later: FileEventType) -> FileEventType {
+    match (earlier, later) {
+        (_, FileEventType::Deleted) => FileEventType::Deleted,
+        // A file renamed over this path replaces whatever happened to it before
+        (_, renamed @ FileEventType::Renamed { .. }) => renamed,
+        // A file created in the window is new however often it was written afterwards
+        (FileEventType::Created, _) => FileEventType::Created,
+        (renamed @ FileEventType::Renamed { .. }, _) => renamed,
+        // Covers delete-then-create, which is how many editors save
+        _ => FileEventType::Modified,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn event(path: &str, event_type: FileEventType) -> FileEvent {
+        FileEvent {
+            path: PathBuf::from(path),
+            event_type,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_bursts_collapse_into_one_event() {
+        let window = Duration::from_millis(500);
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(window);
+
+        debouncer.push(event("/a.txt", FileEventType::Created), start);
+        for i in 1..5 {
+            debouncer.push(event("/a.txt", FileEventType::Modified), start + Duration::from_millis(i * 100));
+        }
+        debouncer.push(event("/b.txt", FileEventType::Deleted), start);
+        debouncer.push(event("/b.txt", FileEventType::Created), start);
+
+        assert_eq!(debouncer.next_deadline(), Some(start + window));
+        let ready = debouncer.take_ready(start + window);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].path, PathBuf::from("/b.txt"));
+        assert!(matches!(ready[0].event_type, FileEventType::Modified));
+
+        let ready = debouncer.take_ready(start + Duration::from_millis(900));
+        assert_eq!(ready.len(), 1);
+        assert!(matches!(ready[0].event_type, FileEventType::Created));
+        assert!(debouncer.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_chained_renames_keep_the_original_name() {
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(Duration::from_millis(100));
+
+        debouncer.push(event("/a.txt", FileEventType::Modified), start);
+        let rename = |from: &str, to: &str| event(to, FileEventType::Renamed { from: PathBuf::from(from), to: PathBuf::from(to) });
+        debouncer.push(rename("/a.txt", "/b.txt"), start);
+        debouncer.push(rename("/b.txt", "/c.txt"), start);
+
+        let ready = debouncer.drain();
+        assert_eq!(ready.len(), 1);
+        assert!(matches!(
+            &ready[0].event_type,
+            FileEventType::Renamed { from, to } if from == &PathBuf::from("/a.txt") && to == &PathBuf::from("/c.txt")
+        ));
+    }
+
+    #[test]
+    fn test_deleting_a_renamed_file_deletes_its_old_name() {
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(Duration::from_millis(100));
+
+        let rename = FileEventType::Renamed { from: PathBuf::from("/a.txt"), to: PathBuf::from("/b.txt") };
+        debouncer.push(event("/b.txt", rename), start);
+        debouncer.push(event("/b.txt", FileEventType::Deleted), start);
+
+        let mut ready = debouncer.drain();
+        ready.sort_by(|left, right| left.path.cmp(&right.path));
+        assert_eq!(ready.len(), 2);
+        assert_eq!(ready[0].path, PathBuf::from("/a.txt"));
+        assert!(ready.iter().all(|event| matches!(event.event_type, FileEventType::Deleted)));
+    }
+
+    #[test]
+    fn test_continuous_writes_are_not_held_forever() {
+        let window = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(window);
+
+        for i in 0..30 {
+            debouncer.push(event("/log.txt", FileEventType::Modified), start + Duration::from_millis(i * 50));
+        }
+        assert_eq!(debouncer.next_deadline(), Some(start + window * MAX_DELAY_WINDOWS));
+    }
+
+    #[test]
+    fn test_backpressure_widens_the_window() {
+        let window = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(window);
+
+        debouncer.push(event("/a.txt", FileEventType::Modified), start);
+        debouncer.set_backpressure(true);
+        assert!(debouncer.take_ready(start + window).is_empty());
+        assert_eq!(debouncer.next_deadline(), Some(start + window * BACKPRESSURE_WINDOWS));
+
+        debouncer.set_backpressure(false);
+        assert_eq!(debouncer.take_ready(start + window).len(), 1);
+    }
+}
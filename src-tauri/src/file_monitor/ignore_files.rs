@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+
+/// Files whose patterns keep paths out of the index, in the folder they apply to and below
+pub const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore", ".metamindignore"];
+
+/// Walker over `root` that honours the ignore files when `respect_ignore_files` is set.
+/// Only files inside the root count, the same ones `IgnoreRules` reads for watcher events.
+/// Symlinked folders are descended into when `follow_links` is set; loops are skipped.
+pub fn walker(root: &Path, respect_ignore_files: bool, follow_links: bool) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .follow_links(follow_links)
+        .hidden(false)
+        .parents(false)
+        .git_global(false)
+        .git_exclude(false)
+        .require_git(false)
+        .git_ignore(respect_ignore_files)
+        .ignore(respect_ignore_files);
+    if respect_ignore_files {
+        builder.add_custom_ignore_filename(".metamindignore");
+    }
+    builder
+}
+
+/// Parsed ignore files per folder, for deciding on watcher events one path at a time
+#[derive(Default)]
+pub struct IgnoreRules {
+    folders: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the ignore files between `root` and `path` exclude it. The closest folder with
+    /// a matching pattern decides, so a nested `!pattern` can bring a path back.
+    pub fn is_ignored(&mut self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let mut folder = path.parent();
+        while let Some(current) = folder {
+            if !current.starts_with(root) {
+                break;
+            }
+            let matched = self.rules_for(current).matched_path_or_any_parents(path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+            folder = current.parent();
+        }
+        false
+    }
+
+    /// Drop cached rules when an ignore file itself changed
+    pub fn invalidate(&mut self, path: &Path) {
+        let is_ignore_file = path.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| IGNORE_FILE_NAMES.contains(&name))
+            .unwrap_or(false);
+        if let (true, Some(folder)) = (is_ignore_file, path.parent()) {
+            self.folders.remove(folder);
+        }
+    }
+
+    fn rules_for(&mut self, folder: &Path) -> &Gitignore {
+        self.folders.entry(folder.to_path_buf()).or_insert_with(|| {
+            let mut builder = GitignoreBuilder::new(folder);
+            for name in IGNORE_FILE_NAMES {
+                let file = folder.join(name);
+                if file.is_file() {
+                    if let Some(e) = builder.add(&file) {
+                        tracing::warn!("Failed to read {}: {}", file.display(), e);
+                    }
+                }
+            }
+            builder.build().unwrap_or_else(|e| {
+                tracing::warn!("Invalid ignore rules in {}: {}", folder.display(), e);
+                Gitignore::empty()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_walker_and_watcher_rules_agree() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join("src/generated")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(root.join("src/.metamindignore"), "generated/\n!keep.log\n").unwrap();
+        for file in ["target/debug/app.o", "src/main.rs", "src/generated/api.rs", "build.log", "src/keep.log"] {
+            std::fs::write(root.join(file), "x").unwrap();
+        }
+
+        let mut walked: Vec<String> = walker(root, true, false).build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file() && !IGNORE_FILE_NAMES.iter().any(|name| entry.file_name() == *name))
+            .map(|entry| entry.path().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        walked.sort();
+        assert_eq!(walked, vec!["src/keep.log", "src/main.rs"]);
+
+        let mut rules = IgnoreRules::new();
+        assert!(rules.is_ignored(root, &root.join("target/debug/app.o"), false));
+        assert!(rules.is_ignored(root, &root.join("src/generated/api.rs"), false));
+        assert!(rules.is_ignored(root, &root.join("build.log"), false));
+        assert!(!rules.is_ignored(root, &root.join("src/keep.log"), false));
+        assert!(!rules.is_ignored(root, &root.join("src/main.rs"), false));
+
+        assert_eq!(walker(root, false, false).build().filter_map(|e| e.ok()).filter(|e| e.path().is_file()).count(), 7);
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::database::Database;
+use crate::database::file_links::FileLink;
+use crate::paths;
+
+/// What happens to links found while scanning or watching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkPolicy {
+    /// Symlinks are skipped, and so are the folders they lead to
+    #[default]
+    Ignore,
+    /// Symlinks are followed and indexed under their own name, as copies of their target
+    Follow,
+    /// Symlinks are followed and their target indexed once, under its own path. A hard link
+    /// to a file indexed under another path is recorded against it instead of indexed again.
+    DedupeByTarget,
+}
+
+impl LinkPolicy {
+    pub fn follows_links(self) -> bool {
+        self != LinkPolicy::Ignore
+    }
+}
+
+/// The path a file found at `path` is indexed under, or None when it is left out: a symlink
+/// while links are ignored, or a hard link to a file indexed under another path. Links are
+/// recorded along the way when deduplicating.
+pub async fn resolve(database: &Database, path: &Path, policy: LinkPolicy) -> Result<Option<PathBuf>> {
+    let is_symlink = tokio::fs::symlink_metadata(path).await?.file_type().is_symlink();
+    let resolved = match (policy, is_symlink) {
+        (LinkPolicy::Ignore, true) => return Ok(None),
+        (LinkPolicy::Follow, _) => paths::canonicalize_link(path),
+        _ => paths::canonicalize(path),
+    };
+    if policy != LinkPolicy::DedupeByTarget {
+        return Ok(Some(resolved));
+    }
+
+    let resolved_path = resolved.to_string_lossy().to_string();
+    if is_symlink {
+        let link_path = paths::canonicalize_link(path).to_string_lossy().to_string();
+        database.save_file_link(&FileLink::symlink(link_path, resolved_path.clone())).await?;
+    }
+
+    if let Some((device, inode)) = hardlink_identity(&resolved).await? {
+        // The first path seen is the one indexed, as long as it is still there
+        let mut target = resolved_path.clone();
+        for candidate in database.get_hardlink_targets(device, inode).await? {
+            if candidate == resolved_path || Path::new(&candidate).exists() {
+                target = candidate;
+                break;
+            }
+        }
+        let skipped = target != resolved_path;
+        if skipped {
+            tracing::debug!("Skipping hard link {} to indexed file {}", resolved_path, target);
+        }
+        database.save_file_link(&FileLink::hardlink(resolved_path, target, device, inode)).await?;
+        if skipped {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(resolved))
+}
+
+/// Device and inode of a file with more than one hard link
+#[cfg(unix)]
+async fn hardlink_identity(path: &Path) -> Result<Option<(i64, i64)>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = tokio::fs::metadata(path).await?;
+    Ok((metadata.nlink() > 1).then(|| (metadata.dev() as i64, metadata.ino() as i64)))
+}
+
+/// File ids that identify hard links are not exposed by the standard library here
+#[cfg(not(unix))]
+async fn hardlink_identity(_path: &Path) -> Result<Option<(i64, i64)>> {
+    Ok(None)
+}
@@ -1,28 +1,146 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 // use std::time::SystemTime; // Unused import
 
 use anyhow::{Result, anyhow};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher, Config};
-use tokio::sync::{mpsc, RwLock};
-use tokio::time::{interval, Duration};
-use walkdir::WalkDir;
+use notify::event::{ModifyKind, RenameMode};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::time::{Duration, Instant};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
 
-use crate::database::{Database, FileRecord};
-use crate::processing_queue::{ProcessingQueue, JobPriority};
+use crate::content_extractor::ContentExtractor;
+use crate::database::{Database, FileFingerprint, FileRecord, INSERT_BATCH_SIZE};
+use crate::paths;
+use crate::processing_queue::{Backpressure, ProcessingQueue, JobPriority};
+
+mod categories;
+mod debounce;
+mod ignore_files;
+mod links;
+mod patterns;
+mod rename;
+mod scan_limits;
+mod scan_progress;
+mod schedule;
+mod throttle;
+mod transient;
+mod volumes;
+mod watch_rules;
+
+use debounce::EventDebouncer;
+pub use categories::CategoryExclusions;
+pub use links::LinkPolicy;
+pub use patterns::{PathPatterns, DEFAULT_EXCLUDED_PATTERNS};
+pub use scan_limits::{ScanEstimate, ScanLimits};
+pub use scan_progress::ScanProgress;
+pub use schedule::{parse_scan_schedules, ScanSchedule, ScheduledScan};
+pub use throttle::{others_cpu_percent, LoadThrottle, ThrottleLevel};
+pub use volumes::{VolumeKind, VolumeStatus};
+pub use watch_rules::{WatchOptions, WatchRules};
+use ignore_files::IgnoreRules;
+use rename::RenameMatcher;
+use scan_limits::EstimateCounter;
+use scan_progress::ScanTracker;
+
+/// How long a path must be quiet before its events are handled, unless configured
+pub const DEFAULT_DEBOUNCE_WINDOW: Duration = Duration::from_millis(500);
+
+/// Time between rescans of watched folders without a schedule of their own, unless configured
+pub const DEFAULT_RESCAN_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the scheduler looks for watched folders that are due for a rescan
+const SCHEDULER_TICK: Duration = Duration::from_secs(30);
+
+/// How often watched folders are checked for drives and shares that went away or came back
+const VOLUME_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often the watcher is checked for errors and lost events, and so how soon it is
+/// re-created after an error. The wait doubles while it keeps failing, up to the maximum.
+const WATCHER_RECOVERY_INTERVAL: Duration = Duration::from_secs(5);
+const WATCHER_MAX_RECOVERY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long the watcher has to go without losing events before the watched folders are
+/// rescanned for what it lost, so a burst of overflows costs one rescan
+const LOST_EVENTS_RESCAN_DELAY: Duration = Duration::from_secs(30);
+
+/// Cancellation flags of running scans by scan id, with the folder each walks
+type ActiveScans = Arc<RwLock<HashMap<String, (PathBuf, Arc<AtomicBool>)>>>;
 
 #[derive(Debug, Clone)]
 pub struct FileMonitor {
     database: Database,
     processing_queue: Option<Arc<tokio::sync::Mutex<ProcessingQueue>>>,
-    watched_paths: Arc<RwLock<HashSet<PathBuf>>>,
-    excluded_patterns: Arc<RwLock<Vec<String>>>,
+    /// Set by the queue while it is full; events are then coalesced over longer windows
+    backpressure: Backpressure,
+    watched_paths: Arc<RwLock<HashMap<PathBuf, WatchRules>>>,
+    /// The volume each watched folder is on and whether it is connected
+    volumes: Arc<RwLock<HashMap<PathBuf, VolumeStatus>>>,
+    excluded_patterns: Arc<RwLock<PathPatterns>>,
+    link_policy: Arc<RwLock<LinkPolicy>>,
+    /// Kinds of file, such as video or large archives, that are never indexed
+    category_exclusions: Arc<RwLock<CategoryExclusions>>,
     max_file_size: u64,
+    debounce_window: Duration,
+    rescan_interval: Arc<RwLock<Duration>>,
+    /// Rescan schedules of the watched folders that do not use the rescan interval
+    scan_schedules: Arc<RwLock<HashMap<PathBuf, ScanSchedule>>>,
+    /// When the scheduler last rescanned each watched folder, or first saw it; saved with the folder
+    last_rescans: Arc<RwLock<HashMap<PathBuf, DateTime<Local>>>>,
+    scan_progress: broadcast::Sender<ScanProgress>,
+    /// Cancellation flags of the scans running now, by scan id, with the folder each walks
+    active_scans: ActiveScans,
+    /// Set once the watcher and event loop run, so starting again does not add a second pair
+    monitoring: Arc<AtomicBool>,
+    /// The OS watcher while monitoring runs; notifications stop when it is dropped
+    watcher: Arc<std::sync::Mutex<Option<RecommendedWatcher>>>,
+    /// Set when the watcher reported an error, until it has been re-created
+    watcher_failed: Arc<AtomicBool>,
+    /// When the watcher last reported lost events, until the rescan for them starts
+    events_lost_at: Arc<std::sync::Mutex<Option<Instant>>>,
+    /// Watcher events are dropped and scheduled rescans skipped while set
+    paused: Arc<AtomicBool>,
+    /// Slows scans and event handling down while the machine is busy
+    throttle: LoadThrottle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MonitoringState {
+    Stopped,
+    Running,
+    Paused,
 }
 
+/// What a watched folder's policy means for one of its files
+#[derive(Debug, Clone)]
+struct IndexingPolicy {
+    max_file_size: u64,
+    priority: JobPriority,
+    link_policy: LinkPolicy,
+    category_exclusions: CategoryExclusions,
+}
+
+/// Outcome of comparing the index with what is on disk
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReconcileReport {
+    pub checked: usize,
+    pub missing: usize,
+    /// Soft deleted in this pass; always zero on a dry run
+    pub marked_deleted: usize,
+    /// Files under a watched folder that is unavailable, e.g. on an unmounted drive
+    pub skipped_unavailable: usize,
+    /// The first missing paths, for display
+    pub missing_paths: Vec<String>,
+}
+
+const RECONCILE_PAGE_SIZE: i64 = 500;
+const MISSING_PATHS_SHOWN: usize = 100;
+
 #[derive(Debug)]
 pub struct FileEvent {
     pub path: PathBuf,
@@ -38,21 +156,38 @@ pub enum FileEventType {
     Renamed { from: PathBuf, to: PathBuf },
 }
 
+/// What the watcher hands to the event loop, where the two halves of a rename are paired
+#[derive(Debug)]
+enum WatchEvent {
+    File(FileEvent),
+    RenameFrom { path: PathBuf, tracker: Option<usize> },
+    RenameTo { path: PathBuf, tracker: Option<usize> },
+}
+
 impl FileMonitor {
     pub fn new(database: Database) -> Self {
         Self {
             database,
             processing_queue: None,
-            watched_paths: Arc::new(RwLock::new(HashSet::new())),
-            excluded_patterns: Arc::new(RwLock::new(vec![
-                ".git".to_string(),
-                "node_modules".to_string(),
-                ".DS_Store".to_string(),
-                "Thumbs.db".to_string(),
-                ".tmp".to_string(),
-                ".temp".to_string(),
-            ])),
+            backpressure: Backpressure::default(),
+            watched_paths: Arc::new(RwLock::new(HashMap::new())),
+            volumes: Arc::new(RwLock::new(HashMap::new())),
+            excluded_patterns: Arc::new(RwLock::new(PathPatterns::default())),
+            link_policy: Arc::new(RwLock::new(LinkPolicy::default())),
+            category_exclusions: Arc::new(RwLock::new(CategoryExclusions::default())),
             max_file_size: 100 * 1024 * 1024, // 100MB default
+            debounce_window: DEFAULT_DEBOUNCE_WINDOW,
+            rescan_interval: Arc::new(RwLock::new(DEFAULT_RESCAN_INTERVAL)),
+            scan_schedules: Arc::new(RwLock::new(HashMap::new())),
+            last_rescans: Arc::new(RwLock::new(HashMap::new())),
+            scan_progress: broadcast::channel(64).0,
+            active_scans: Arc::new(RwLock::new(HashMap::new())),
+            monitoring: Arc::new(AtomicBool::new(false)),
+            watcher: Arc::new(std::sync::Mutex::new(None)),
+            watcher_failed: Arc::new(AtomicBool::new(false)),
+            events_lost_at: Arc::new(std::sync::Mutex::new(None)),
+            paused: Arc::new(AtomicBool::new(false)),
+            throttle: LoadThrottle::new(false),
         }
     }
     
@@ -61,171 +196,789 @@ impl FileMonitor {
         self
     }
 
-    pub async fn add_watch_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let path = path.as_ref().to_path_buf();
-        
-        if !path.exists() {
-            return Err(anyhow!("Path does not exist: {}", path.display()));
+    pub fn with_backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    pub fn with_debounce_window(mut self, window: Duration) -> Self {
+        self.debounce_window = window;
+        self
+    }
+
+    pub fn with_rescan_interval(mut self, rescan_interval: Duration) -> Self {
+        self.rescan_interval = Arc::new(RwLock::new(rescan_interval));
+        self
+    }
+
+    /// Applies from the scheduler's next check, counted from each folder's last rescan
+    pub async fn set_rescan_interval(&self, rescan_interval: Duration) {
+        *self.rescan_interval.write().await = rescan_interval;
+    }
+
+    pub fn with_scan_schedules(mut self, schedules: HashMap<PathBuf, ScanSchedule>) -> Self {
+        self.scan_schedules = Arc::new(RwLock::new(schedules));
+        self
+    }
+
+    /// Replace the rescan schedules; watched folders left out go back to the rescan interval
+    pub async fn set_scan_schedules(&self, schedules: &[ScheduledScan]) -> Result<()> {
+        let schedules = schedule::parse_scan_schedules(schedules)?;
+        *self.scan_schedules.write().await = schedules;
+        Ok(())
+    }
+
+    /// Each watched folder with its schedule, if it has one, and when it is next rescanned.
+    /// Folders the scheduler has not seen yet and folders whose schedule never fires have no
+    /// next rescan.
+    pub async fn scheduled_scans(&self) -> Vec<(PathBuf, Option<String>, Option<DateTime<Local>>)> {
+        let roots: Vec<PathBuf> = self.watched_paths.read().await.keys().cloned().collect();
+        let mut scheduled = Vec::with_capacity(roots.len());
+        for root in roots {
+            let schedule = self.scan_schedules.read().await.get(&root).map(|schedule| schedule.expression().to_string());
+            let last_rescan = self.last_rescans.read().await.get(&root).copied();
+            let next_rescan = match last_rescan {
+                Some(last_rescan) => self.next_rescan(&root, last_rescan).await,
+                None => None,
+            };
+            scheduled.push((root, schedule, next_rescan));
         }
+        scheduled
+    }
 
-        let mut watched_paths = self.watched_paths.write().await;
-        watched_paths.insert(path.clone());
+    /// Hold scans and event handling back while CPU or disk are under pressure
+    pub fn with_adaptive_throttling(self, enabled: bool) -> Self {
+        self.throttle.set_enabled(enabled);
+        self
+    }
+
+    pub fn set_adaptive_throttling(&self, enabled: bool) {
+        self.throttle.set_enabled(enabled);
+    }
+
+    /// How much indexing is held back right now; always none with adaptive throttling off
+    pub fn throttle_level(&self) -> ThrottleLevel {
+        self.throttle.level()
+    }
+
+    pub fn with_excluded_patterns(mut self, patterns: PathPatterns) -> Self {
+        self.excluded_patterns = Arc::new(RwLock::new(patterns));
+        self
+    }
+
+    pub async fn excluded_patterns(&self) -> Vec<String> {
+        self.excluded_patterns.read().await.patterns().to_vec()
+    }
+
+    /// Replace the exclusion globs; the watcher and later scans pick them up right away
+    pub async fn set_excluded_patterns(&self, patterns: &[String]) -> Result<()> {
+        let patterns = PathPatterns::new(patterns)?;
+        *self.excluded_patterns.write().await = patterns;
+        Ok(())
+    }
+
+    pub fn with_link_policy(mut self, link_policy: LinkPolicy) -> Self {
+        self.link_policy = Arc::new(RwLock::new(link_policy));
+        self
+    }
+
+    /// Applies to files found from now on; what is indexed already stays until rescanned
+    pub async fn set_link_policy(&self, link_policy: LinkPolicy) {
+        *self.link_policy.write().await = link_policy;
+    }
+
+    pub fn with_category_exclusions(mut self, exclusions: CategoryExclusions) -> Self {
+        self.category_exclusions = Arc::new(RwLock::new(exclusions));
+        self
+    }
+
+    /// Applies to files found from now on; what is indexed already stays until removed
+    pub async fn set_category_exclusions(&self, exclusions: CategoryExclusions) {
+        *self.category_exclusions.write().await = exclusions;
+    }
+
+    /// Progress updates of every directory scan while it runs
+    pub fn subscribe_scan_progress(&self) -> broadcast::Receiver<ScanProgress> {
+        self.scan_progress.subscribe()
+    }
+
+    pub async fn add_watch_path<P: AsRef<Path>>(&self, path: P, options: WatchOptions) -> Result<()> {
+        if !path.as_ref().exists() {
+            return Err(anyhow!("Path does not exist: {}", path.as_ref().display()));
+        }
+        // Files found under the root inherit its spelling, so it is resolved once here
+        let path = paths::canonicalize(path.as_ref());
+        let rules = WatchRules::new(options)?;
+        let kind = volumes::detect_kind(&path).await;
+
+        self.database.save_watched_path(&rules.options().to_record(&path, kind)).await?;
+        self.watched_paths.write().await.insert(path.clone(), rules);
+        self.volumes.write().await.insert(path.clone(), VolumeStatus { kind, available: true });
+        self.watch_folder(&path);
         
-        // Perform initial scan of the path
-        self.scan_directory(&path).await?;
+        // Perform initial scan of the path; cancelling it takes back adding the folder
+        if self.scan_directory(&path).await?.cancelled {
+            self.remove_watch_path(&path).await?;
+            tracing::info!("Initial scan cancelled, not watching {}", path.display());
+            return Ok(());
+        }
         
         tracing::info!("Added watch path: {}", path.display());
         Ok(())
     }
 
     pub async fn remove_watch_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let path = path.as_ref().to_path_buf();
+        let path = paths::canonicalize(path.as_ref());
         let mut watched_paths = self.watched_paths.write().await;
         watched_paths.remove(&path);
+        self.volumes.write().await.remove(&path);
+        self.last_rescans.write().await.remove(&path);
+        self.unwatch_folder(&path);
+        self.database.remove_watched_path(&path.to_string_lossy()).await?;
+
+        // A scan of the folder still running is no longer wanted
+        self.cancel_scans_under(&path).await;
         
         tracing::info!("Removed watch path: {}", path.display());
         Ok(())
     }
 
+    /// Change how a watched folder is indexed; takes effect for new events and the next scan
+    pub async fn set_watch_options<P: AsRef<Path>>(&self, path: P, options: WatchOptions) -> Result<()> {
+        let path = paths::canonicalize(path.as_ref());
+        let rules = WatchRules::new(options)?;
+        let mut watched_paths = self.watched_paths.write().await;
+        let current = watched_paths.get_mut(&path)
+            .ok_or_else(|| anyhow!("Path is not watched: {}", path.display()))?;
+        let kind = self.volume_status(&path).await.kind;
+        self.database.save_watched_path(&rules.options().to_record(&path, kind)).await?;
+        *current = rules;
+        Ok(())
+    }
+
+    /// Change only the queue priority of a watched folder's files
+    pub async fn set_watch_priority<P: AsRef<Path>>(&self, path: P, priority: JobPriority) -> Result<()> {
+        let path = paths::canonicalize(path.as_ref());
+        let mut options = self.watched_paths.read().await.get(&path)
+            .map(|rules| rules.options().clone())
+            .ok_or_else(|| anyhow!("Path is not watched: {}", path.display()))?;
+        options.priority = priority;
+        self.set_watch_options(&path, options).await
+    }
+
+    /// Queue priority of files by the watched folder they are in, as the folders are set now
+    pub async fn folder_priorities(&self) -> impl Fn(&str) -> JobPriority {
+        let watched_paths = self.watched_paths.read().await.clone();
+        move |path: &str| {
+            Self::watch_root(&watched_paths, Path::new(path))
+                .map(|(_, rules)| rules.options().priority.clone())
+                .unwrap_or_default()
+        }
+    }
+
+    /// Watch the folders saved by earlier sessions again. Folders that are unavailable, e.g. on
+    /// an unmounted drive, are suspended until they come back. Returns the folders available now.
+    pub async fn restore_watch_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut restored = Vec::new();
+        for record in self.database.get_watched_paths().await? {
+            let path = PathBuf::from(&record.path);
+            let kind = record.volume_kind.parse().unwrap_or_default();
+            let rules = match WatchOptions::from_record(&record).and_then(WatchRules::new) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    tracing::warn!("Invalid saved options for watched path {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            self.watched_paths.write().await.insert(path.clone(), rules);
+            if let Some(last_rescan) = record.last_rescan_at {
+                self.last_rescans.write().await.insert(path.clone(), last_rescan.with_timezone(&Local));
+            }
+
+            let available = volumes::is_available(&path, kind).await;
+            self.volumes.write().await.insert(path.clone(), VolumeStatus { kind, available });
+            if available {
+                restored.push(path);
+            } else {
+                tracing::warn!("Watched path is unavailable, suspended until it is reconnected: {}", path.display());
+            }
+        }
+        Ok(restored)
+    }
+
+    /// The watched folders, how each is indexed and the volume it is on
+    pub async fn watch_paths(&self) -> Vec<(PathBuf, WatchOptions, VolumeStatus)> {
+        let watched_paths = self.watched_paths.read().await;
+        let mut watch_paths = Vec::with_capacity(watched_paths.len());
+        for (root, rules) in watched_paths.iter() {
+            watch_paths.push((root.clone(), rules.options().clone(), self.volume_status(root).await));
+        }
+        watch_paths
+    }
+
+    async fn volume_status(&self, root: &Path) -> VolumeStatus {
+        self.volumes.read().await.get(root).copied()
+            .unwrap_or(VolumeStatus { kind: VolumeKind::Local, available: true })
+    }
+
+    /// Watched folders that are connected now, for rescans
+    async fn available_watch_paths(&self) -> Vec<PathBuf> {
+        // Same lock order as `remove_watch_path`
+        let watched_paths = self.watched_paths.read().await;
+        let volumes = self.volumes.read().await;
+        watched_paths.keys()
+            .filter(|root| volumes.get(*root).map(|status| status.available).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+
+    /// The watched folder containing `path` and its rules, the innermost if folders nest
+    fn watch_root<'a>(watched_paths: &'a HashMap<PathBuf, WatchRules>, path: &Path) -> Option<(&'a PathBuf, &'a WatchRules)> {
+        watched_paths.iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+    }
+
+    /// Size limit and queue priority for `path` from the watched folder it is in, with the
+    /// link policy and category exclusions that apply everywhere
+    async fn indexing_policy(&self, path: &Path) -> IndexingPolicy {
+        let (max_file_size, priority) = match Self::watch_root(&*self.watched_paths.read().await, path) {
+            Some((_, rules)) => (rules.max_file_size(self.max_file_size), rules.options().priority.clone()),
+            None => (self.max_file_size, JobPriority::Normal),
+        };
+        IndexingPolicy {
+            max_file_size,
+            priority,
+            link_policy: *self.link_policy.read().await,
+            category_exclusions: *self.category_exclusions.read().await,
+        }
+    }
+
+    pub fn monitoring_state(&self) -> MonitoringState {
+        if !self.monitoring.load(Ordering::SeqCst) {
+            MonitoringState::Stopped
+        } else if self.paused.load(Ordering::SeqCst) {
+            MonitoringState::Paused
+        } else {
+            MonitoringState::Running
+        }
+    }
+
+    /// Stop reacting to file changes, stop running scans after their current entry and skip
+    /// scheduled rescans until `resume_monitoring`. Returns false if monitoring was paused already.
+    pub fn pause_monitoring(&self) -> bool {
+        let paused = !self.paused.swap(true, Ordering::SeqCst);
+        if paused {
+            tracing::info!("File monitoring paused");
+        }
+        paused
+    }
+
+    /// React to file changes again, then rescan the watched folders in the background and mark
+    /// what was deleted meanwhile. Returns false if monitoring was not paused.
+    pub fn resume_monitoring(&self) -> bool {
+        if !self.paused.swap(false, Ordering::SeqCst) {
+            return false;
+        }
+        tracing::info!("File monitoring resumed");
+
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            for path in monitor.available_watch_paths().await {
+                if let Err(e) = monitor.scan_directory(&path).await {
+                    tracing::error!("Rescan after resuming failed for {}: {}", path.display(), e);
+                }
+            }
+            // Paused again before the rescans finished; the next resume catches up
+            if monitor.paused.load(Ordering::SeqCst) {
+                return;
+            }
+            if let Err(e) = monitor.reconcile_missing_files(false).await {
+                tracing::error!("Failed to reconcile missing files after resuming: {}", e);
+            }
+        });
+        true
+    }
+
     pub async fn start_monitoring(&self) -> Result<()> {
-        let (tx, mut rx) = mpsc::channel::<FileEvent>(1000);
+        if self.monitoring.swap(true, Ordering::SeqCst) {
+            tracing::debug!("File monitoring is already running");
+            return Ok(());
+        }
+        let (tx, mut rx) = mpsc::channel::<WatchEvent>(1000);
         
         // Start file watcher
-        let _watcher_handle = self.start_file_watcher(tx.clone()).await?;
+        match self.start_file_watcher(tx.clone()).await {
+            Ok(watcher) => *self.watcher.lock().unwrap() = Some(watcher),
+            Err(e) => {
+                self.monitoring.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        }
+        self.start_watcher_recovery(tx.clone());
         
-        // Start processing events
-        let database = self.database.clone();
-        let processing_queue = self.processing_queue.clone();
+        // Start processing events, each path once it has been quiet for the debounce window
+        let monitor = self.clone();
+        let mut debouncer = EventDebouncer::new(self.debounce_window);
+        let mut renames = RenameMatcher::new();
         tokio::spawn(async move {
-            while let Some(event) = rx.recv().await {
-                if let Err(e) = Self::process_file_event(&database, &processing_queue, event).await {
-                    tracing::error!("Failed to process file event: {}", e);
+            loop {
+                debouncer.set_backpressure(monitor.backpressure.is_active());
+                let deadline = debouncer.next_deadline().into_iter().chain(renames.next_deadline()).min();
+                tokio::select! {
+                    event = rx.recv() => match event {
+                        Some(WatchEvent::File(event)) => debouncer.push(event, Instant::now()),
+                        Some(WatchEvent::RenameFrom { path, tracker }) => renames.rename_from(path, tracker, Instant::now()),
+                        Some(WatchEvent::RenameTo { path, tracker }) => debouncer.push(renames.rename_to(path, tracker), Instant::now()),
+                        None => {
+                            for event in renames.drain() {
+                                debouncer.push(event, Instant::now());
+                            }
+                            monitor.dispatch_events(debouncer.drain()).await;
+                            break;
+                        }
+                    },
+                    _ = Self::sleep_until(deadline) => {
+                        let now = Instant::now();
+                        for event in renames.take_expired(now) {
+                            debouncer.push(event, now);
+                        }
+                        monitor.dispatch_events(debouncer.take_ready(now)).await;
+                    }
                 }
             }
         });
 
-        // Start periodic rescan
-        self.start_periodic_rescan().await;
+        // Start scheduled rescans
+        self.start_scan_scheduler();
+        self.start_volume_checks();
+        self.throttle.start_sampling();
 
         tracing::info!("File monitoring started");
         Ok(())
     }
 
-    async fn start_file_watcher(&self, tx: mpsc::Sender<FileEvent>) -> Result<RecommendedWatcher> {
+    async fn dispatch_events(&self, events: Vec<FileEvent>) {
+        // Deletions are checked against the volume as it is now: unmounting a drive reports its
+        // files as removed before the periodic check notices it is gone
+        let mut live_availability: HashMap<PathBuf, bool> = HashMap::new();
+
+        for event in events {
+            let root = Self::watch_root(&*self.watched_paths.read().await, &event.path).map(|(root, _)| root.clone());
+            if let Some(root) = root {
+                let status = self.volume_status(&root).await;
+                let available = match event.event_type {
+                    FileEventType::Deleted => match live_availability.get(&root) {
+                        Some(available) => *available,
+                        None => {
+                            let available = volumes::is_available(&root, status.kind).await;
+                            live_availability.insert(root.clone(), available);
+                            available
+                        }
+                    },
+                    _ => status.available,
+                };
+                if !available {
+                    tracing::debug!("Dropping event under unavailable watch path: {}", event.path.display());
+                    continue;
+                }
+            }
+
+            let policy = self.indexing_policy(&event.path).await;
+            if let Err(e) = Self::process_file_event(&self.database, &self.processing_queue, &policy, event).await {
+                tracing::error!("Failed to process file event: {}", e);
+            }
+            self.throttle.pause().await;
+        }
+    }
+
+    /// Sleep until `deadline`, or forever when there is nothing to wait for
+    async fn sleep_until(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn start_file_watcher(&self, tx: mpsc::Sender<WatchEvent>) -> Result<RecommendedWatcher> {
         let watched_paths = self.watched_paths.clone();
         let excluded_patterns = self.excluded_patterns.clone();
+        let paused = self.paused.clone();
+        let watcher_failed = self.watcher_failed.clone();
+        let events_lost_at = self.events_lost_at.clone();
 
+        // Handled on the watcher's thread in arrival order, which pairing renames depends on
+        let mut ignore_rules = IgnoreRules::new();
         let mut watcher = RecommendedWatcher::new(
-            move |res: Result<Event, notify::Error>| {
-                let tx = tx.clone();
-                let watched_paths = watched_paths.clone();
-                let excluded_patterns = excluded_patterns.clone();
-                
-                tokio::spawn(async move {
-                    match res {
-                        Ok(event) => {
-                            if let Err(e) = Self::handle_notify_event(event, tx, watched_paths, excluded_patterns).await {
-                                tracing::error!("Failed to handle file event: {}", e);
-                            }
-                        }
-                        Err(e) => tracing::error!("File watcher error: {}", e),
+            move |res: Result<Event, notify::Error>| match res {
+                // The OS dropped events, e.g. when its queue overflowed
+                Ok(event) if event.need_rescan() => {
+                    tracing::warn!("File watcher lost events, rescanning once they stop");
+                    *events_lost_at.lock().unwrap() = Some(Instant::now());
+                }
+                // Changes made while paused are picked up by the rescan on resume
+                Ok(_) if paused.load(Ordering::SeqCst) => {}
+                Ok(event) => {
+                    if let Err(e) = Self::handle_notify_event(event, &tx, &watched_paths, &excluded_patterns, &mut ignore_rules) {
+                        tracing::error!("Failed to handle file event: {}", e);
                     }
-                });
+                }
+                Err(e) => {
+                    tracing::error!("File watcher error: {}", e);
+                    watcher_failed.store(true, Ordering::SeqCst);
+                }
             },
             Config::default(),
         )?;
 
-        // Watch all configured paths
-        let paths = self.watched_paths.read().await;
-        for path in paths.iter() {
-            watcher.watch(path, RecursiveMode::Recursive)?;
-            tracing::info!("Watching path: {}", path.display());
+        // Folders on a drive or share that is gone are watched once it is back
+        for path in self.available_watch_paths().await {
+            match watcher.watch(&path, RecursiveMode::Recursive) {
+                Ok(()) => tracing::info!("Watching path: {}", path.display()),
+                Err(e) => tracing::warn!("Failed to watch {}: {}", path.display(), e),
+            }
         }
 
         Ok(watcher)
     }
 
-    async fn handle_notify_event(
+    /// Add a watched folder to the running watcher, if there is one
+    fn watch_folder(&self, path: &Path) {
+        if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
+            match watcher.watch(path, RecursiveMode::Recursive) {
+                Ok(()) => tracing::info!("Watching path: {}", path.display()),
+                Err(e) => tracing::warn!("Failed to watch {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn unwatch_folder(&self, path: &Path) {
+        if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
+            // Fails when the OS dropped the watch already, e.g. for an unmounted drive
+            if let Err(e) = watcher.unwatch(path) {
+                tracing::debug!("Failed to unwatch {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Re-create the watcher after it reported an error, and rescan the watched folders and
+    /// reconcile the index once it has stopped losing events for a while
+    fn start_watcher_recovery(&self, tx: mpsc::Sender<WatchEvent>) {
+        let monitor = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = WATCHER_RECOVERY_INTERVAL;
+            loop {
+                tokio::time::sleep(interval).await;
+                if monitor.watcher_failed.swap(false, Ordering::SeqCst) {
+                    interval = (interval * 2).min(WATCHER_MAX_RECOVERY_INTERVAL);
+
+                    // The old watcher goes first, so the two do not report the same changes
+                    monitor.watcher.lock().unwrap().take();
+                    match monitor.start_file_watcher(tx.clone()).await {
+                        Ok(watcher) => {
+                            *monitor.watcher.lock().unwrap() = Some(watcher);
+                            tracing::info!("File watcher re-created");
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to re-create file watcher: {}", e);
+                            monitor.watcher_failed.store(true, Ordering::SeqCst);
+                        }
+                    }
+                } else {
+                    interval = WATCHER_RECOVERY_INTERVAL;
+                }
+
+                let rescan_due = {
+                    let mut events_lost_at = monitor.events_lost_at.lock().unwrap();
+                    let due = events_lost_at.is_some_and(|at| at.elapsed() >= LOST_EVENTS_RESCAN_DELAY);
+                    if due {
+                        events_lost_at.take();
+                    }
+                    due
+                };
+                // Resuming rescans everything
+                if !rescan_due || monitor.paused.load(Ordering::SeqCst) {
+                    continue;
+                }
+                for root in monitor.available_watch_paths().await {
+                    if let Err(e) = monitor.scan_directory(&root).await {
+                        tracing::error!("Rescan after lost events failed for {}: {}", root.display(), e);
+                    }
+                }
+                if let Err(e) = monitor.reconcile_missing_files(false).await {
+                    tracing::error!("Failed to reconcile missing files: {}", e);
+                }
+            }
+        });
+    }
+
+    fn handle_notify_event(
         event: Event,
-        tx: mpsc::Sender<FileEvent>,
-        _watched_paths: Arc<RwLock<HashSet<PathBuf>>>,
-        excluded_patterns: Arc<RwLock<Vec<String>>>,
+        tx: &mpsc::Sender<WatchEvent>,
+        watched_paths: &Arc<RwLock<HashMap<PathBuf, WatchRules>>>,
+        excluded_patterns: &Arc<RwLock<PathPatterns>>,
+        ignore_rules: &mut IgnoreRules,
     ) -> Result<()> {
-        let patterns = excluded_patterns.read().await;
-        
+        // The watcher thread is outside the runtime, so it may block on the locks and the channel,
+        // but never on both at once: a full channel waits on the event loop, which may itself be
+        // queued behind a writer that is waiting for these read guards
+        let outgoing = {
+            let patterns = excluded_patterns.blocking_read();
+            let roots = watched_paths.blocking_read();
+            Self::filter_notify_event(event, &patterns, &roots, ignore_rules)
+        };
+
+        for watch_event in outgoing {
+            if let Err(e) = tx.blocking_send(watch_event) {
+                tracing::error!("Failed to send file event: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turns a notify event into the watch events that survive the exclusion rules.
+    fn filter_notify_event(
+        event: Event,
+        patterns: &PathPatterns,
+        roots: &HashMap<PathBuf, WatchRules>,
+        ignore_rules: &mut IgnoreRules,
+    ) -> Vec<WatchEvent> {
+        let tracker = event.attrs.tracker();
+        let mut outgoing = Vec::new();
+
         for path in event.paths {
+            ignore_rules.invalidate(&path);
+
+            // The new half of a move to the trash still goes out, so the old half pairs with it
+            // and the file is deleted rather than left waiting for a partner
+            if transient::is_trash(&path) {
+                let moved_in = match event.kind {
+                    EventKind::Modify(ModifyKind::Name(RenameMode::To)) => true,
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Any | RenameMode::Other)) => path.exists(),
+                    _ => false,
+                };
+                if moved_in {
+                    outgoing.push(WatchEvent::RenameTo { path: path.clone(), tracker });
+                }
+                continue;
+            }
+
             // Check if path should be excluded
-            if Self::should_exclude_path(&path, &patterns) {
+            if Self::should_exclude_path(&path, patterns) {
                 continue;
             }
+            if let Some((root, rules)) = Self::watch_root(roots, &path) {
+                let is_dir = path.is_dir();
+                if rules.is_excluded(root, &path, is_dir) {
+                    continue;
+                }
+                if rules.options().respect_ignore_files && ignore_rules.is_ignored(root, &path, is_dir) {
+                    continue;
+                }
+            }
 
-            let file_event = match event.kind {
-                EventKind::Create(_) => FileEvent {
-                    path: path.clone(),
-                    event_type: FileEventType::Created,
-                    timestamp: Utc::now(),
-                },
-                EventKind::Modify(_) => FileEvent {
-                    path: path.clone(),
-                    event_type: FileEventType::Modified,
-                    timestamp: Utc::now(),
-                },
-                EventKind::Remove(_) => FileEvent {
-                    path: path.clone(),
-                    event_type: FileEventType::Deleted,
-                    timestamp: Utc::now(),
-                },
+            let file_event = |event_type| WatchEvent::File(FileEvent {
+                path: path.clone(),
+                event_type,
+                timestamp: Utc::now(),
+            });
+            let watch_event = match event.kind {
+                EventKind::Create(_) => file_event(FileEventType::Created),
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)) => WatchEvent::RenameFrom { path: path.clone(), tracker },
+                EventKind::Modify(ModifyKind::Name(RenameMode::To)) => WatchEvent::RenameTo { path: path.clone(), tracker },
+                // Follows the From and To halves, which are paired already
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => continue,
+                // macOS does not say which side of a rename a path is; only the new name exists
+                EventKind::Modify(ModifyKind::Name(_)) => {
+                    if path.exists() {
+                        WatchEvent::RenameTo { path: path.clone(), tracker }
+                    } else {
+                        WatchEvent::RenameFrom { path: path.clone(), tracker }
+                    }
+                }
+                EventKind::Modify(_) => file_event(FileEventType::Modified),
+                EventKind::Remove(_) => file_event(FileEventType::Deleted),
                 _ => continue,
             };
-
-            if let Err(e) = tx.send(file_event).await {
-                tracing::error!("Failed to send file event: {}", e);
-            }
+            outgoing.push(watch_event);
         }
 
-        Ok(())
+        outgoing
     }
 
     async fn process_file_event(
         database: &Database,
         processing_queue: &Option<Arc<tokio::sync::Mutex<ProcessingQueue>>>,
+        policy: &IndexingPolicy,
         event: FileEvent,
     ) -> Result<()> {
         match event.event_type {
             FileEventType::Created | FileEventType::Modified => {
                 if event.path.is_file() {
-                    Self::process_file_with_queue(database, processing_queue, &event.path).await?;
+                    Self::process_file_with_queue(database, processing_queue, &event.path, policy).await?;
                 }
             }
             FileEventType::Deleted => {
-                // Remove from database if it exists
-                if let Some(file) = database.get_file_by_path(&event.path.to_string_lossy()).await? {
-                    // Mark as deleted or remove entirely
-                    database.update_file_status(&file.id, "deleted", None).await?;
+                // Soft delete; the record can be restored until the grace period ends
+                if let Some(file) = database.get_file_by_path(&paths::canonical_string(&event.path)).await? {
+                    database.mark_file_deleted(&file.id).await?;
                 }
+                let link_path = paths::canonicalize_link(&event.path).to_string_lossy().to_string();
+                database.remove_file_link(&link_path).await?;
+                database.remove_file_links_below(&link_path).await?;
+                Self::enqueue_subtitled_video(database, processing_queue, &event.path, &policy.priority).await;
             }
-            FileEventType::Renamed { from: _, to } => {
-                if to.is_file() {
-                    Self::process_file_with_queue(database, processing_queue, &to).await?;
-                }
+            FileEventType::Renamed { from, to } => {
+                Self::process_rename(database, processing_queue, policy, &from, &to).await?;
             }
         }
 
         Ok(())
     }
 
+    /// Move the records of a renamed file or folder to the new path instead of indexing the
+    /// file again. A file whose type changed with its extension is indexed afresh.
+    async fn process_rename(
+        database: &Database,
+        processing_queue: &Option<Arc<tokio::sync::Mutex<ProcessingQueue>>>,
+        policy: &IndexingPolicy,
+        from: &Path,
+        to: &Path,
+    ) -> Result<()> {
+        let from_path = paths::canonical_string(from);
+        let to_path = paths::canonical_string(to);
+        database.remove_file_link(&paths::canonicalize_link(from).to_string_lossy()).await?;
+
+        // Moved to the trash, which is a deletion that can be undone from the trash
+        if transient::is_trash(to) {
+            if let Some(file) = database.get_file_by_path(&from_path).await? {
+                database.mark_file_deleted(&file.id).await?;
+            }
+            let deleted = database.mark_directory_deleted(&from_path).await?;
+            database.remove_file_links_below(&paths::canonicalize_link(from).to_string_lossy()).await?;
+            tracing::debug!("Moved to the trash: {} ({} files below it)", from_path, deleted);
+            return Ok(());
+        }
+
+        if to.is_dir() {
+            let moved = database.rename_directory(&from_path, &to_path).await?;
+            tracing::info!("Moved {} indexed files from {} to {}", moved, from_path, to_path);
+            return Ok(());
+        }
+
+        if let Some(file) = database.get_file_by_path(&from_path).await? {
+            let same_type = from.extension() == to.extension();
+            // A record already at the new path belongs to the file this one replaced and stays
+            if to.is_file() && same_type && database.get_file_by_path(&to_path).await?.is_none() {
+                database.rename_file(&file.id, &to_path).await?;
+                tracing::debug!("Renamed indexed file {} to {}", from_path, to_path);
+            } else {
+                database.mark_file_deleted(&file.id).await?;
+            }
+        }
+        // The video the subtitle went with under its old name loses its dialogue
+        Self::enqueue_subtitled_video(database, processing_queue, from, &policy.priority).await;
+
+        // Catches content changed along with the rename and files that were never indexed
+        if to.is_file() {
+            Self::process_file_with_queue(database, processing_queue, to, policy).await?;
+        }
+
+        Ok(())
+    }
+
     async fn process_file_with_queue(
         database: &Database,
         processing_queue: &Option<Arc<tokio::sync::Mutex<ProcessingQueue>>>,
         path: &Path,
+        policy: &IndexingPolicy,
     ) -> Result<()> {
+        let Some(file_record) = Self::build_file_record(database, path, policy).await? else {
+            return Ok(());
+        };
+
+        // Known files are only processed again when their content changed
+        match database.get_file_fingerprints(std::slice::from_ref(&file_record.path)).await {
+            Ok(mut fingerprints) => {
+                if let Some(existing) = fingerprints.remove(&file_record.path) {
+                    match Self::detect_change(database, &existing, file_record).await? {
+                        Some(changed) => {
+                            Self::enqueue_files(processing_queue, &[changed], &policy.priority).await;
+                            Self::enqueue_subtitled_video(database, processing_queue, path, &policy.priority).await;
+                        }
+                        None => tracing::debug!("File unchanged, skipping: {}", path.display()),
+                    }
+                    return Ok(());
+                }
+                // File doesn't exist, continue with insertion
+            }
+            Err(e) => {
+                tracing::warn!("Could not check if file exists (database might be corrupted), continuing: {}", e);
+                // Continue anyway, INSERT OR REPLACE will handle duplicates
+            }
+        }
+        
+        // Insert or update file record
+        tracing::debug!("Inserting file record into database: {}", path.display());
+        match database.insert_file(&file_record).await {
+            Ok(()) => {
+                tracing::debug!("Successfully inserted file record: {}", path.display());
+            }
+            Err(e) => {
+                tracing::error!("Failed to insert file record for {}: {:?}", path.display(), e);
+                return Err(e);
+            }
+        }
+        
+        Self::enqueue_files(processing_queue, std::slice::from_ref(&file_record), &policy.priority).await;
+        Self::enqueue_subtitled_video(database, processing_queue, path, &policy.priority).await;
+        
+        tracing::debug!("Successfully processed file: {}", path.display());
+        Ok(())
+    }
+
+    /// Process the video a new, changed or removed subtitle file goes with again, since a
+    /// video's text takes in the dialogue of its subtitles
+    async fn enqueue_subtitled_video(
+        database: &Database,
+        processing_queue: &Option<Arc<tokio::sync::Mutex<ProcessingQueue>>>,
+        path: &Path,
+        priority: &JobPriority,
+    ) {
+        let subtitle = path.to_path_buf();
+        let Ok(Some(video)) = tokio::task::spawn_blocking(move || ContentExtractor::subtitled_video(&subtitle)).await else {
+            return;
+        };
+        match database.get_file_by_path(&video.to_string_lossy()).await {
+            Ok(Some(video)) if video.processing_status != "deleted" => Self::enqueue_files(processing_queue, &[video], priority).await,
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to look up the video of {}: {}", path.display(), e),
+        }
+    }
+
+    /// Record for a file on disk, or None when it is over the size limit or left out by the
+    /// link policy or category exclusions
+    async fn build_file_record(database: &Database, path: &Path, policy: &IndexingPolicy) -> Result<Option<FileRecord>> {
+        // Dotted spellings of a path, and symlinked ones unless links are followed as copies, map to one record
+        let Some(path) = links::resolve(database, path, policy.link_policy).await? else {
+            return Ok(None);
+        };
+        let path = path.as_path();
+        let max_file_size = policy.max_file_size;
+
         // Get file metadata
         let metadata = tokio::fs::metadata(path).await?;
         
         // Skip if file is too large
-        if metadata.len() > 100 * 1024 * 1024 {
+        if metadata.len() > max_file_size {
             tracing::debug!("Skipping large file: {} ({} bytes)", path.display(), metadata.len());
-            return Ok(());
+            return Ok(None);
+        }
+        if policy.category_exclusions.excludes(path, metadata.len()) {
+            tracing::debug!("Skipping file of an excluded category: {}", path.display());
+            return Ok(None);
         }
 
         // Create file record
@@ -271,127 +1024,472 @@ impl FileMonitor {
             error_message: None,
         };
 
-        // Check if file already exists in database
-        match database.file_exists(&file_record.path).await {
-            Ok(true) => {
-                tracing::info!("File already exists in database, skipping: {}", path.display());
-                return Ok(());
-            }
-            Ok(false) => {
-                // File doesn't exist, continue with insertion
-            }
-            Err(e) => {
-                tracing::warn!("Could not check if file exists (database might be corrupted), continuing: {}", e);
-                // Continue anyway, INSERT OR REPLACE will handle duplicates
+        Ok(Some(file_record))
+    }
+
+    async fn enqueue_files(
+        processing_queue: &Option<Arc<tokio::sync::Mutex<ProcessingQueue>>>,
+        files: &[FileRecord],
+        priority: &JobPriority,
+    ) {
+        if files.is_empty() {
+            return;
+        }
+
+        // Add to processing queue if available
+        let Some(queue) = processing_queue else {
+            tracing::warn!("No processing queue available for {} files", files.len());
+            return;
+        };
+
+        let queue_guard = queue.lock().await;
+        if let Err(e) = queue_guard.add_jobs(files, priority.clone()).await {
+            // Don't fail the entire operation if queue addition fails
+            tracing::error!("Failed to add {} files to processing queue: {}", files.len(), e);
+        }
+    }
+
+    /// Compare a file on disk with its indexed fingerprint and return the record to queue when
+    /// its content changed. Size and mtime settle most files without reading them; when only the
+    /// mtime moved, the content hash decides, so touched or copied-back files are not re-extracted.
+    async fn detect_change(database: &Database, existing: &FileFingerprint, mut current: FileRecord) -> Result<Option<FileRecord>> {
+        // A file that comes back before it is purged keeps its record, tags and history
+        if existing.processing_status == "deleted" && database.restore_deleted_file(&existing.id).await? {
+            tracing::info!("Restored deleted file: {}", current.path);
+            // Its content went with the deletion, so it is indexed again
+            database.update_file_stat(&existing.id, current.size, current.modified_at, true).await?;
+            current.id = existing.id.clone();
+            return Ok(Some(current));
+        }
+
+        if existing.size == current.size && existing.modified_at == current.modified_at {
+            return Ok(None);
+        }
+
+        if let (Some(stored_hash), true) = (&existing.hash, existing.size == current.size) {
+            let hash = ContentExtractor::compute_file_hash(&current.path).await?;
+            if &hash == stored_hash {
+                database.update_file_stat(&existing.id, current.size, current.modified_at, false).await?;
+                return Ok(None);
             }
         }
-        
-        // Insert or update file record
-        tracing::debug!("Inserting file record into database: {}", path.display());
-        match database.insert_file(&file_record).await {
-            Ok(()) => {
-                tracing::debug!("Successfully inserted file record: {}", path.display());
+
+        database.update_file_stat(&existing.id, current.size, current.modified_at, true).await?;
+        current.id = existing.id.clone();
+        Ok(Some(current))
+    }
+
+    /// Insert buffered scan records in one batch and queue the ones that are new or changed
+    async fn flush_scan_batch(&self, batch: &mut Vec<FileRecord>, priority: &JobPriority) -> Result<usize> {
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        // Known files are compared with their fingerprint; only new ones are written
+        let paths: Vec<String> = batch.iter().map(|file| file.path.clone()).collect();
+        let mut fingerprints = self.database.get_file_fingerprints(&paths).await?;
+        let (known, new): (Vec<FileRecord>, Vec<FileRecord>) = batch.drain(..)
+            .partition(|file| fingerprints.contains_key(&file.path));
+
+        let inserted: HashSet<String> = self.database.insert_files(&new).await?.into_iter().collect();
+        let mut queued: Vec<FileRecord> = new.into_iter()
+            .filter(|file| inserted.contains(&file.id))
+            .collect();
+
+        for file in known {
+            let Some(existing) = fingerprints.remove(&file.path) else {
+                continue;
+            };
+            let path = file.path.clone();
+            match Self::detect_change(&self.database, &existing, file).await {
+                Ok(Some(changed)) => queued.push(changed),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to check {} for changes: {}", path, e),
             }
-            Err(e) => {
-                tracing::error!("Failed to insert file record for {}: {:?}", path.display(), e);
-                return Err(e);
+        }
+
+        Self::enqueue_files(&self.processing_queue, &queued, priority).await;
+        Ok(queued.len())
+    }
+
+    /// Stop a running scan after the entry it is on; returns false if no such scan runs
+    pub async fn cancel_scan(&self, scan_id: &str) -> bool {
+        match self.active_scans.read().await.get(scan_id) {
+            Some((_, cancelled)) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
             }
+            None => false,
         }
-        
-        // Add to processing queue if available
-        if let Some(queue) = processing_queue {
-            tracing::debug!("Adding file to processing queue: {}", path.display());
-            let queue_guard = queue.lock().await;
-            if let Err(e) = queue_guard.add_job(&file_record, JobPriority::Normal).await {
-                tracing::error!("Failed to add file to processing queue: {}", e);
-                // Don't fail the entire operation if queue addition fails
-            } else {
-                tracing::debug!("Successfully added file to processing queue: {}", path.display());
+    }
+
+    async fn cancel_scans_under(&self, path: &Path) {
+        for (root, cancelled) in self.active_scans.read().await.values() {
+            if root.starts_with(path) {
+                cancelled.store(true, Ordering::Relaxed);
             }
-        } else {
-            tracing::warn!("No processing queue available for file: {}", path.display());
         }
-        
-        tracing::debug!("Successfully processed file: {}", path.display());
-        Ok(())
     }
 
-    pub async fn scan_directory<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    /// Walk `path` and index what is new or changed. The scan can be stopped with `cancel_scan`
+    /// using the id in its progress updates.
+    pub async fn scan_directory<P: AsRef<Path>>(&self, path: P) -> Result<ScanProgress> {
+        self.scan_directory_with_limits(path, ScanLimits::default()).await
+    }
+
+    /// `scan_directory` that stops at the first of `limits` it reaches
+    pub async fn scan_directory_with_limits<P: AsRef<Path>>(&self, path: P, limits: ScanLimits) -> Result<ScanProgress> {
         let path = path.as_ref();
+        // What was indexed below the folder before stands in for its size
+        let expected_files = match self.database.get_location_stats(&path.to_string_lossy()).await {
+            Ok(stats) => stats["total_files"].as_u64().unwrap_or(0) as usize,
+            Err(_) => 0,
+        };
+        let mut tracker = ScanTracker::new(self.scan_progress.clone(), path, expected_files);
+        let scan_id = tracker.scan_id().to_string();
+        self.active_scans.write().await.insert(scan_id.clone(), (path.to_path_buf(), tracker.cancellation()));
+
+        let result = self.walk_directory(path, limits, &mut tracker).await;
+        self.active_scans.write().await.remove(&scan_id);
+        if let Err(e) = result {
+            // The last update tells whoever shows the scan that it is over
+            tracker.fail(e.to_string());
+            return Err(e);
+        }
+
+        let summary = tracker.finish();
+        if summary.cancelled {
+            tracing::info!("Directory scan cancelled after {} files: {}", summary.files_discovered, path.display());
+        } else if summary.paused {
+            tracing::info!("Directory scan stopped by pausing after {} files: {}", summary.files_discovered, path.display());
+        } else if summary.limit_reached {
+            tracing::info!("Directory scan stopped at its limit after {} files ({} new or changed) from {}",
+                          summary.files_discovered, summary.files_queued, path.display());
+        } else {
+            tracing::info!("Directory scan completed. Processed {} files ({} new or changed) from {}", 
+                          summary.files_discovered, summary.files_queued, path.display());
+        }
+        Ok(summary)
+    }
+
+    /// The watched folder `path` is in and its rules, or `path` itself with default rules
+    async fn scan_root(&self, path: &Path) -> Result<(PathBuf, WatchRules)> {
+        match Self::watch_root(&*self.watched_paths.read().await, path) {
+            Some((root, rules)) => Ok((root.clone(), rules.clone())),
+            None => Ok((path.to_path_buf(), WatchRules::new(WatchOptions::default())?)),
+        }
+    }
+
+    /// Whether a walked entry is left out by the exclusions or its folder's rules
+    fn is_walk_excluded(root: &Path, rules: &WatchRules, excluded_patterns: &PathPatterns, path: &Path, is_dir: bool) -> bool {
+        Self::should_exclude_path(path, excluded_patterns) || rules.is_excluded(root, path, is_dir)
+    }
+
+    async fn walk_directory(&self, path: &Path, limits: ScanLimits, tracker: &mut ScanTracker) -> Result<()> {
+        let (root, rules) = self.scan_root(path).await?;
+        let policy = IndexingPolicy {
+            max_file_size: rules.max_file_size(self.max_file_size),
+            priority: rules.options().priority.clone(),
+            link_policy: *self.link_policy.read().await,
+            category_exclusions: *self.category_exclusions.read().await,
+        };
         let excluded_patterns = self.excluded_patterns.read().await;
-        let mut processed_count = 0;
+        let mut batch = Vec::with_capacity(INSERT_BATCH_SIZE);
+        let (mut files_taken, mut bytes_taken) = (0, 0);
 
-        tracing::info!("Starting directory scan: {}", path.display());
+        tracing::info!("Starting directory scan {}: {}", tracker.scan_id(), path.display());
 
-        for entry in WalkDir::new(path)
-            .follow_links(false)
-            .into_iter()
+        for entry in ignore_files::walker(path, rules.options().respect_ignore_files, policy.link_policy.follows_links())
+            .max_depth(limits.max_depth)
+            .build()
             .filter_map(|e| e.ok())
         {
+            if tracker.is_cancelled() {
+                break;
+            }
+            if self.paused.load(Ordering::SeqCst) {
+                tracker.paused();
+                break;
+            }
             let entry_path = entry.path();
             
             // Skip if should be excluded
-            if Self::should_exclude_path(entry_path, &excluded_patterns) {
+            let is_dir = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false);
+            if Self::is_walk_excluded(&root, &rules, &excluded_patterns, entry_path, is_dir) {
                 continue;
             }
+            if is_dir {
+                tracker.enter_directory(entry_path);
+            }
 
             // Only process files
             if entry_path.is_file() {
-                if let Err(e) = Self::process_file_with_queue(&self.database, &self.processing_queue, entry_path).await {
-                    tracing::error!("Failed to process file {}: {}", entry_path.display(), e);
-                } else {
-                    processed_count += 1;
-                    
-                    // Log progress every 100 files
-                    if processed_count % 100 == 0 {
-                        tracing::info!("Scanned {} files...", processed_count);
+                // Checked before the file is looked at, so nothing is recorded for one left out
+                let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                if policy.category_exclusions.excludes(entry_path, size) {
+                    continue;
+                }
+                if size <= policy.max_file_size && !limits.admits(files_taken, bytes_taken, size) {
+                    tracker.limit_reached();
+                    break;
+                }
+                match Self::build_file_record(&self.database, entry_path, &policy).await {
+                    Ok(Some(file_record)) => {
+                        files_taken += 1;
+                        bytes_taken += file_record.size as u64;
+                        batch.push(file_record);
+                    }
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::error!("Failed to process file {}: {}", entry_path.display(), e);
+                        continue;
                     }
                 }
+                tracker.file_discovered();
+                self.throttle.pause().await;
+                
+                if batch.len() >= INSERT_BATCH_SIZE {
+                    tracker.files_queued(self.flush_scan_batch(&mut batch, &policy.priority).await?);
+                }
             }
         }
-
-        tracing::info!("Directory scan completed. Processed {} files from {}", 
-                      processed_count, path.display());
+        // Files found before a cancellation are kept
+        tracker.files_queued(self.flush_scan_batch(&mut batch, &policy.priority).await?);
         Ok(())
     }
 
-    async fn start_periodic_rescan(&self) {
-        let watched_paths = self.watched_paths.clone();
-        let database = self.database.clone();
-        let excluded_patterns = self.excluded_patterns.clone();
+    /// Count what scanning `path` within `limits` would index, by walking it with the same
+    /// exclusions and size limit but without reading or recording any file
+    pub async fn estimate_scan<P: AsRef<Path>>(&self, path: P, limits: ScanLimits) -> Result<ScanEstimate> {
+        let path = path.as_ref().to_path_buf();
+        if !path.is_dir() {
+            return Err(anyhow!("Not a directory: {}", path.display()));
+        }
+        let (root, rules) = self.scan_root(&path).await?;
+        let max_file_size = rules.max_file_size(self.max_file_size);
+        let link_policy = *self.link_policy.read().await;
+        let category_exclusions = *self.category_exclusions.read().await;
+        let excluded_patterns = self.excluded_patterns.read().await.clone();
+
+        // Nothing in the walk waits on the runtime
+        tokio::task::spawn_blocking(move || {
+            let mut counter = EstimateCounter::new(&path, limits);
+            for entry in ignore_files::walker(&path, rules.options().respect_ignore_files, link_policy.follows_links())
+                .max_depth(limits.max_depth)
+                .build()
+                .filter_map(|e| e.ok())
+            {
+                let entry_path = entry.path();
+                let is_dir = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false);
+                if entry.depth() == 0 || Self::is_walk_excluded(&root, &rules, &excluded_patterns, entry_path, is_dir) {
+                    continue;
+                }
+                if is_dir {
+                    counter.add_directory();
+                    continue;
+                }
+                if !entry_path.is_file() || (link_policy == LinkPolicy::Ignore && entry.path_is_symlink()) {
+                    continue;
+                }
+                let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                if category_exclusions.excludes(entry_path, size) {
+                    continue;
+                }
+                if !counter.add_file(entry_path, size, max_file_size) {
+                    break;
+                }
+            }
+            counter.finish()
+        })
+        .await
+        .map_err(|e| anyhow!("Scan estimate failed: {}", e))
+    }
+
+    /// Rescan each watched folder when it is due, on its own schedule or once the rescan
+    /// interval has passed since its last rescan. Scans only queue new and changed files, and
+    /// files removed while events were missed are marked deleted afterwards. Rescans that fall
+    /// due while paused are skipped rather than caught up on.
+    fn start_scan_scheduler(&self) {
+        let monitor = self.clone();
 
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(3600)); // Rescan every hour
-            
             loop {
-                interval.tick().await;
-                
-                let paths = watched_paths.read().await.clone();
-                for path in paths {
-                    tracing::info!("Starting periodic rescan of: {}", path.display());
-                    
-                    // Create a temporary FileMonitor for the rescan
-                    let monitor = FileMonitor {
-                        database: database.clone(),
-                        processing_queue: None, // No queue for periodic rescans
-                        watched_paths: watched_paths.clone(),
-                        excluded_patterns: excluded_patterns.clone(),
-                        max_file_size: 100 * 1024 * 1024,
+                tokio::time::sleep(SCHEDULER_TICK).await;
+
+                let now = Local::now();
+                let mut due = Vec::new();
+                for root in monitor.available_watch_paths().await {
+                    let last_rescan = match monitor.last_rescans.read().await.get(&root).copied() {
+                        Some(last_rescan) => last_rescan,
+                        // A folder never rescanned counts from when the scheduler first sees it
+                        None => {
+                            monitor.record_rescan(&root, now).await;
+                            now
+                        }
                     };
-                    
-                    if let Err(e) = monitor.scan_directory(&path).await {
-                        tracing::error!("Periodic rescan failed for {}: {}", path.display(), e);
+                    if matches!(monitor.next_rescan(&root, last_rescan).await, Some(next) if next <= now) {
+                        due.push(root);
+                    }
+                }
+                if due.is_empty() {
+                    continue;
+                }
+
+                let paused = monitor.paused.load(Ordering::SeqCst);
+                for root in due {
+                    if !paused {
+                        tracing::info!("Starting scheduled rescan of: {}", root.display());
+                        if let Err(e) = monitor.scan_directory(&root).await {
+                            tracing::error!("Scheduled rescan failed for {}: {}", root.display(), e);
+                        }
+                    }
+                    // Counted from the end of the scan, so a long one is not followed by another at once
+                    monitor.record_rescan(&root, Local::now()).await;
+                }
+                if paused {
+                    continue;
+                }
+
+                match monitor.reconcile_missing_files(false).await {
+                    Ok(report) if report.marked_deleted > 0 => {
+                        tracing::info!("Marked {} files missing from disk as deleted", report.marked_deleted);
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Failed to reconcile missing files: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Remember when `root` was last rescanned, in the database too so restarts keep the schedule
+    async fn record_rescan(&self, root: &Path, at: DateTime<Local>) {
+        self.last_rescans.write().await.insert(root.to_path_buf(), at);
+        if let Err(e) = self.database.set_last_rescan(&root.to_string_lossy(), at.with_timezone(&Utc)).await {
+            tracing::warn!("Failed to save the last rescan of {}: {}", root.display(), e);
+        }
+    }
+
+    /// When `root` is due for a rescan after the one at `last_rescan`
+    async fn next_rescan(&self, root: &Path, last_rescan: DateTime<Local>) -> Option<DateTime<Local>> {
+        if let Some(schedule) = self.scan_schedules.read().await.get(root) {
+            return schedule.next_after(last_rescan);
+        }
+        let interval = chrono::Duration::from_std(*self.rescan_interval.read().await).ok()?;
+        Some(last_rescan + interval)
+    }
+
+    /// Check the watched folders for drives and shares that went away or came back. A folder
+    /// that went away is suspended, so its files are not marked deleted; when it is back it is
+    /// rescanned and reconciled.
+    fn start_volume_checks(&self) {
+        let monitor = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(VOLUME_CHECK_INTERVAL).await;
+
+                let watched: Vec<(PathBuf, VolumeStatus)> = monitor.volumes.read().await.iter()
+                    .map(|(root, status)| (root.clone(), *status))
+                    .collect();
+                for (root, status) in watched {
+                    let available = volumes::is_available(&root, status.kind).await;
+                    if available == status.available {
+                        continue;
+                    }
+                    // Removed while it was checked
+                    match monitor.volumes.write().await.get_mut(&root) {
+                        Some(current) => current.available = available,
+                        None => continue,
+                    }
+
+                    if !available {
+                        tracing::warn!("Watched path became unavailable, suspending it: {}", root.display());
+                        monitor.cancel_scans_under(&root).await;
+                        monitor.unwatch_folder(&root);
+                        continue;
                     }
+                    tracing::info!("Watched path is available again: {}", root.display());
+                    // The watch is lost when a drive is unmounted, so it is set up anew
+                    monitor.unwatch_folder(&root);
+                    monitor.watch_folder(&root);
+                    if monitor.paused.load(Ordering::SeqCst) {
+                        // Resuming rescans it
+                        continue;
+                    }
+                    let catch_up = monitor.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = catch_up.scan_directory(&root).await {
+                            tracing::error!("Rescan after reconnecting failed for {}: {}", root.display(), e);
+                        }
+                        if let Err(e) = catch_up.reconcile_missing_files(false).await {
+                            tracing::error!("Failed to reconcile missing files: {}", e);
+                        }
+                    });
                 }
             }
         });
     }
 
+    /// Find indexed files that vanished from disk, e.g. while the app was closed, and soft delete
+    /// them unless `dry_run` is set. Folders that are unavailable as a whole are left alone.
+    pub async fn reconcile_missing_files(&self, dry_run: bool) -> Result<ReconcileReport> {
+        let roots: Vec<PathBuf> = self.watched_paths.read().await.keys().cloned().collect();
+        let mut unavailable_roots = Vec::new();
+        for root in roots {
+            if !volumes::is_available(&root, self.volume_status(&root).await.kind).await {
+                unavailable_roots.push(root);
+            }
+        }
+
+        let mut report = ReconcileReport::default();
+        let mut missing_ids = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = self.database.get_active_files(RECONCILE_PAGE_SIZE, offset).await?;
+            if page.is_empty() {
+                break;
+            }
+            offset += page.len() as i64;
+
+            for file in page {
+                report.checked += 1;
+                let path = Path::new(&file.path);
+                if unavailable_roots.iter().any(|root| path.starts_with(root)) {
+                    report.skipped_unavailable += 1;
+                    continue;
+                }
+
+                // Only a definite "not found" counts; permission errors leave the file be
+                match tokio::fs::metadata(path).await {
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        report.missing += 1;
+                        if report.missing_paths.len() < MISSING_PATHS_SHOWN {
+                            report.missing_paths.push(file.path.clone());
+                        }
+                        missing_ids.push(file.id);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Marked after the walk so the pages stay stable
+        if !dry_run {
+            for file_id in &missing_ids {
+                self.database.mark_file_deleted(file_id).await?;
+                report.marked_deleted += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
     pub async fn process_single_file_public(&self, path: &str) -> Result<()> {
         tracing::debug!("Starting single file processing for: {}", path);
         let path = std::path::Path::new(path);
         
-        match Self::process_file_with_queue(&self.database, &self.processing_queue, path).await {
+        let policy = self.indexing_policy(path).await;
+        match Self::process_file_with_queue(&self.database, &self.processing_queue, path, &policy).await {
             Ok(()) => {
                 tracing::debug!("Successfully processed single file: {}", path.display());
                 Ok(())
@@ -403,13 +1501,14 @@ impl FileMonitor {
         }
     }
 
-    fn should_exclude_path(path: &Path, excluded_patterns: &[String]) -> bool {
-        let path_str = path.to_string_lossy();
-        
-        for pattern in excluded_patterns {
-            if path_str.contains(pattern) {
-                return true;
-            }
+    fn should_exclude_path(path: &Path, excluded_patterns: &PathPatterns) -> bool {
+        if excluded_patterns.matches(path) {
+            return true;
+        }
+
+        // Trash, editor scratch files and unfinished downloads, whatever the patterns say
+        if transient::classify(path).is_some() {
+            return true;
         }
         
         // Skip hidden files and directories
@@ -0,0 +1,101 @@
+use std::path::{Component, Path};
+
+use anyhow::{Result, anyhow};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Excluded until the user configures otherwise
+pub const DEFAULT_EXCLUDED_PATTERNS: &[&str] = &[
+    ".git",
+    "node_modules",
+    ".DS_Store",
+    "Thumbs.db",
+    "*.tmp",
+    "*.temp",
+];
+
+/// Whether the platform's default file systems ignore letter case
+const CASE_INSENSITIVE: bool = cfg!(any(target_os = "windows", target_os = "macos"));
+
+/// Glob patterns over paths. A pattern without a separator matches any file or folder name
+/// along the path, e.g. `node_modules` or `*.log`; one with a separator matches the whole path,
+/// e.g. `**/cache/**` or `/Users/me/Downloads/*`.
+#[derive(Debug, Clone)]
+pub struct PathPatterns {
+    patterns: Vec<String>,
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl PathPatterns {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                return Err(anyhow!("Patterns cannot be empty"));
+            }
+            let glob = GlobBuilder::new(pattern)
+                .case_insensitive(CASE_INSENSITIVE)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| anyhow!("Invalid pattern '{}': {}", pattern, e))?;
+            if pattern.contains(['/', '\\']) {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+
+        Ok(Self {
+            patterns: patterns.iter().map(|pattern| pattern.trim().to_string()).collect(),
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.paths.is_match(path) {
+            return true;
+        }
+        path.components().any(|component| match component {
+            Component::Normal(name) => self.names.is_match(Path::new(name)),
+            _ => false,
+        })
+    }
+}
+
+impl Default for PathPatterns {
+    fn default() -> Self {
+        let patterns: Vec<String> = DEFAULT_EXCLUDED_PATTERNS.iter().map(|pattern| pattern.to_string()).collect();
+        Self::new(&patterns).expect("default exclusion patterns are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_and_path_patterns() {
+        let patterns = PathPatterns::new(&[
+            "*.log".to_string(),
+            "node_modules".to_string(),
+            "**/cache/**".to_string(),
+        ]).unwrap();
+
+        assert!(patterns.matches(Path::new("/home/me/app/server.log")));
+        assert!(patterns.matches(Path::new("/home/me/app/node_modules/react/index.js")));
+        assert!(patterns.matches(Path::new("/home/me/.local/cache/thumbs/a.png")));
+        assert!(!patterns.matches(Path::new("/home/me/app/logbook.txt")));
+        assert!(!patterns.matches(Path::new("/home/me/app/cache.txt")));
+
+        assert!(PathPatterns::new(&["a[".to_string()]).is_err());
+        assert!(PathPatterns::new(&[" ".to_string()]).is_err());
+        assert!(PathPatterns::default().matches(Path::new("/tmp/download.tmp")));
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use chrono::Utc;
+use tokio::time::{Duration, Instant};
+
+use super::{FileEvent, FileEventType};
+
+/// The old name of a rename that has not seen its new name within this time moved out of view
+const RENAME_PAIR_TIMEOUT: Duration = Duration::from_millis(500);
+
+struct PendingFrom {
+    path: PathBuf,
+    tracker: Option<usize>,
+    seen: Instant,
+}
+
+/// Pairs the two halves of a rename into one event. Linux tags both halves with a cookie,
+/// Windows reports them back to back, and macOS gives no link at all, so a half without a
+/// tracker is paired with the latest unmatched old name.
+#[derive(Default)]
+pub struct RenameMatcher {
+    pending: Vec<PendingFrom>,
+}
+
+impl RenameMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rename_from(&mut self, path: PathBuf, tracker: Option<usize>, now: Instant) {
+        self.pending.push(PendingFrom { path, tracker, seen: now });
+    }
+
+    /// The rename that ends at `path`, or a creation when the file moved in from elsewhere
+    pub fn rename_to(&mut self, path: PathBuf, tracker: Option<usize>) -> FileEvent {
+        let matched = match tracker {
+            Some(_) => self.pending.iter().position(|from| from.tracker == tracker),
+            None => self.pending.iter().rposition(|from| from.tracker.is_none()),
+        };
+
+        let event_type = match matched {
+            Some(index) => FileEventType::Renamed {
+                from: self.pending.remove(index).path,
+                to: path.clone(),
+            },
+            None => FileEventType::Created,
+        };
+        FileEvent { path, event_type, timestamp: Utc::now() }
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.iter().map(|from| from.seen + RENAME_PAIR_TIMEOUT).min()
+    }
+
+    /// Old names that were never matched, as deletions
+    pub fn take_expired(&mut self, now: Instant) -> Vec<FileEvent> {
+        let (expired, pending) = self.pending.drain(..)
+            .partition(|from| from.seen + RENAME_PAIR_TIMEOUT <= now);
+        self.pending = pending;
+        expired.into_iter().map(Self::deletion).collect()
+    }
+
+    pub fn drain(&mut self) -> Vec<FileEvent> {
+        self.pending.drain(..).map(Self::deletion).collect()
+    }
+
+    fn deletion(from: PendingFrom) -> FileEvent {
+        FileEvent {
+            path: from.path,
+            event_type: FileEventType::Deleted,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halves_pair_by_tracker_or_order() {
+        let now = Instant::now();
+        let mut matcher = RenameMatcher::new();
+
+        matcher.rename_from(PathBuf::from("/a.txt"), Some(7), now);
+        matcher.rename_from(PathBuf::from("/b.txt"), Some(8), now);
+        let event = matcher.rename_to(PathBuf::from("/b2.txt"), Some(8));
+        assert!(matches!(event.event_type, FileEventType::Renamed { ref from, .. } if from == &PathBuf::from("/b.txt")));
+
+        matcher.rename_from(PathBuf::from("/c.txt"), None, now);
+        let event = matcher.rename_to(PathBuf::from("/c2.txt"), None);
+        assert!(matches!(event.event_type, FileEventType::Renamed { ref from, .. } if from == &PathBuf::from("/c.txt")));
+
+        let event = matcher.rename_to(PathBuf::from("/moved-in.txt"), None);
+        assert!(matches!(event.event_type, FileEventType::Created));
+
+        // /a.txt moved somewhere that is not watched
+        assert!(matcher.take_expired(now).is_empty());
+        let expired = matcher.take_expired(now + RENAME_PAIR_TIMEOUT);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].path, PathBuf::from("/a.txt"));
+        assert!(matches!(expired[0].event_type, FileEventType::Deleted));
+    }
+}
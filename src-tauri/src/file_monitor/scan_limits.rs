@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+/// Bounds on one directory scan; the walk stops at the first one reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanLimits {
+    /// Levels below the root that are visited; 1 covers only the files directly in it
+    pub max_depth: Option<usize>,
+    pub max_files: Option<usize>,
+    /// Combined size of the files taken, in bytes
+    pub max_total_bytes: Option<u64>,
+}
+
+impl ScanLimits {
+    /// Whether one more file of `size` bytes still fits after `files` files of `total_bytes`
+    pub fn admits(&self, files: usize, total_bytes: u64, size: u64) -> bool {
+        let files_fit = match self.max_files {
+            Some(max) => files < max,
+            None => true,
+        };
+        let bytes_fit = match self.max_total_bytes {
+            Some(max) => total_bytes.saturating_add(size) <= max,
+            None => true,
+        };
+        files_fit && bytes_fit
+    }
+}
+
+/// Files of one extension in a scan estimate
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExtensionEstimate {
+    /// Lowercased, without the dot; empty for files without one
+    pub extension: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// What scanning a folder would take on, counted without indexing anything. Exclusions, ignore
+/// files and size limits apply as they would in the scan; hard links are counted once per path.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanEstimate {
+    pub root: String,
+    pub files: usize,
+    pub directories: usize,
+    pub total_bytes: u64,
+    /// Over the size limit, so they would be skipped
+    pub oversized_files: usize,
+    /// Largest share of the bytes first
+    pub by_extension: Vec<ExtensionEstimate>,
+    /// A scan limit was reached, so the counts cover only part of the folder
+    pub limit_reached: bool,
+}
+
+/// Running counts of a scan estimate
+pub struct EstimateCounter {
+    estimate: ScanEstimate,
+    limits: ScanLimits,
+    by_extension: HashMap<String, ExtensionEstimate>,
+}
+
+impl EstimateCounter {
+    pub fn new(root: &Path, limits: ScanLimits) -> Self {
+        Self {
+            estimate: ScanEstimate {
+                root: root.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            limits,
+            by_extension: HashMap::new(),
+        }
+    }
+
+    pub fn add_directory(&mut self) {
+        self.estimate.directories += 1;
+    }
+
+    /// Returns false once a limit is reached and the walk should stop
+    pub fn add_file(&mut self, path: &Path, size: u64, max_file_size: u64) -> bool {
+        if size > max_file_size {
+            self.estimate.oversized_files += 1;
+            return true;
+        }
+        if !self.limits.admits(self.estimate.files, self.estimate.total_bytes, size) {
+            self.estimate.limit_reached = true;
+            return false;
+        }
+
+        self.estimate.files += 1;
+        self.estimate.total_bytes += size;
+        let extension = path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let entry = self.by_extension.entry(extension.clone()).or_insert_with(|| ExtensionEstimate {
+            extension,
+            ..Default::default()
+        });
+        entry.files += 1;
+        entry.bytes += size;
+        true
+    }
+
+    pub fn finish(mut self) -> ScanEstimate {
+        let mut by_extension: Vec<ExtensionEstimate> = self.by_extension.into_values().collect();
+        by_extension.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.extension.cmp(&b.extension)));
+        self.estimate.by_extension = by_extension;
+        self.estimate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_stops_at_limits() {
+        let limits = ScanLimits { max_files: Some(3), max_total_bytes: Some(1_000), ..Default::default() };
+        let mut counter = EstimateCounter::new(Path::new("/docs"), limits);
+
+        assert!(counter.add_file(Path::new("/docs/a.PDF"), 400, 10_000));
+        assert!(counter.add_file(Path::new("/docs/huge.iso"), 50_000, 10_000));
+        assert!(counter.add_file(Path::new("/docs/b.txt"), 100, 10_000));
+        assert!(counter.add_file(Path::new("/docs/README"), 10, 10_000));
+        assert!(!counter.add_file(Path::new("/docs/c.txt"), 1, 10_000));
+
+        let estimate = counter.finish();
+        assert_eq!((estimate.files, estimate.total_bytes, estimate.oversized_files), (3, 510, 1));
+        assert!(estimate.limit_reached);
+        let extensions: Vec<&str> = estimate.by_extension.iter().map(|e| e.extension.as_str()).collect();
+        assert_eq!(extensions, vec!["pdf", "txt", ""]);
+
+        let bytes_only = ScanLimits { max_total_bytes: Some(100), ..Default::default() };
+        assert!(bytes_only.admits(1_000, 60, 40));
+        assert!(!bytes_only.admits(0, 60, 41));
+        assert!(ScanLimits::default().admits(usize::MAX - 1, u64::MAX / 2, 1));
+    }
+}
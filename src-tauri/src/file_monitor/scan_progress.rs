@@ -0,0 +1,187 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// A running scan publishes at most one update per interval, plus its final state
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Where a directory scan stands, as shown to the user while it runs
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanProgress {
+    pub scan_id: String,
+    pub root: String,
+    pub current_directory: String,
+    pub files_discovered: usize,
+    /// New or changed files handed to the processing queue
+    pub files_queued: usize,
+    /// Estimated from the files indexed below the root before; unknown for a new folder
+    pub percent: Option<f32>,
+    pub finished: bool,
+    /// The scan was stopped before the walk ended; what was found so far is indexed
+    pub cancelled: bool,
+    /// The walk stopped at one of the scan's limits; what was found so far is indexed
+    pub limit_reached: bool,
+    /// Monitoring was paused during the walk; resuming scans the folder again
+    pub paused: bool,
+    /// Why the scan failed, if it did; what was found before is indexed
+    pub error: Option<String>,
+}
+
+/// Counts of one scan, published on the monitor's progress channel as they change
+pub struct ScanTracker {
+    progress: ScanProgress,
+    expected_files: usize,
+    sender: broadcast::Sender<ScanProgress>,
+    last_sent: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ScanTracker {
+    pub fn new(sender: broadcast::Sender<ScanProgress>, root: &Path, expected_files: usize) -> Self {
+        let root = root.to_string_lossy().to_string();
+        Self {
+            progress: ScanProgress {
+                scan_id: Uuid::new_v4().to_string(),
+                current_directory: root.clone(),
+                root,
+                files_discovered: 0,
+                files_queued: 0,
+                percent: None,
+                finished: false,
+                cancelled: false,
+                limit_reached: false,
+                paused: false,
+                error: None,
+            },
+            expected_files,
+            sender,
+            last_sent: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn scan_id(&self) -> &str {
+        &self.progress.scan_id
+    }
+
+    /// Set to stop the scan at the next entry
+    pub fn cancellation(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn enter_directory(&mut self, directory: &Path) {
+        self.progress.current_directory = directory.to_string_lossy().to_string();
+        self.publish(Instant::now());
+    }
+
+    pub fn file_discovered(&mut self) {
+        self.progress.files_discovered += 1;
+        self.publish(Instant::now());
+    }
+
+    pub fn limit_reached(&mut self) {
+        self.progress.limit_reached = true;
+    }
+
+    pub fn paused(&mut self) {
+        self.progress.paused = true;
+    }
+
+    pub fn files_queued(&mut self, count: usize) {
+        self.progress.files_queued += count;
+        self.publish(Instant::now());
+    }
+
+    pub fn finish(mut self) -> ScanProgress {
+        self.progress.finished = true;
+        self.progress.cancelled = self.is_cancelled();
+        if !self.progress.cancelled && !self.progress.paused {
+            self.progress.percent = Some(100.0);
+        }
+        // Nobody listening is fine
+        let _ = self.sender.send(self.progress.clone());
+        self.progress
+    }
+
+    /// Publish the final state of a scan that stopped on `error`
+    pub fn fail(mut self, error: String) -> ScanProgress {
+        self.progress.finished = true;
+        self.progress.error = Some(error);
+        let _ = self.sender.send(self.progress.clone());
+        self.progress
+    }
+
+    fn publish(&mut self, now: Instant) {
+        if self.last_sent.is_some_and(|sent| now < sent + PROGRESS_INTERVAL) {
+            return;
+        }
+        self.last_sent = Some(now);
+
+        // A folder that grew past what was indexed stays just short of done until the walk ends
+        self.progress.percent = (self.expected_files > 0).then(|| {
+            (self.progress.files_discovered as f32 / self.expected_files as f32 * 100.0).min(99.0)
+        });
+        let _ = self.sender.send(self.progress.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_are_throttled_and_estimated() {
+        let (sender, mut receiver) = broadcast::channel(16);
+        let mut tracker = ScanTracker::new(sender, Path::new("/photos"), 4);
+        let start = Instant::now();
+
+        tracker.progress.files_discovered = 1;
+        tracker.publish(start);
+        tracker.progress.files_discovered = 2;
+        tracker.publish(start + Duration::from_millis(10));
+        tracker.progress.files_discovered = 6;
+        tracker.publish(start + PROGRESS_INTERVAL);
+
+        let first = receiver.try_recv().unwrap();
+        assert_eq!((first.files_discovered, first.percent), (1, Some(25.0)));
+        let second = receiver.try_recv().unwrap();
+        assert_eq!((second.files_discovered, second.percent), (6, Some(99.0)));
+        assert!(receiver.try_recv().is_err());
+
+        let done = tracker.finish();
+        assert!(done.finished);
+        assert_eq!(receiver.try_recv().unwrap().percent, Some(100.0));
+
+        let (sender, _receiver) = broadcast::channel(16);
+        let mut tracker = ScanTracker::new(sender, Path::new("/new"), 0);
+        tracker.file_discovered();
+        assert_eq!(tracker.progress.percent, None);
+
+        tracker.cancellation().store(true, Ordering::Relaxed);
+        assert!(tracker.is_cancelled());
+        let stopped = tracker.finish();
+        assert!(stopped.cancelled && stopped.percent.is_none());
+
+        let (sender, _receiver) = broadcast::channel(16);
+        let mut tracker = ScanTracker::new(sender, Path::new("/docs"), 2);
+        tracker.paused();
+        let paused = tracker.finish();
+        assert!(paused.paused && !paused.cancelled && paused.percent.is_none());
+
+        let (sender, mut receiver) = broadcast::channel(16);
+        let tracker = ScanTracker::new(sender, Path::new("/gone"), 2);
+        tracker.fail("Permission denied".to_string());
+        let failed = receiver.try_recv().unwrap();
+        assert!(failed.finished && failed.percent.is_none());
+        assert_eq!(failed.error.as_deref(), Some("Permission denied"));
+    }
+}
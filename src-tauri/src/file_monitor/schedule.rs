@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use serde::{Serialize, Deserialize};
+
+use crate::paths;
+
+/// Days looked ahead for the next match; covers every leap-day schedule
+const MAX_DAYS_AHEAD: u32 = 366 * 8;
+
+/// A rescan schedule for one watched folder, as stored in the configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledScan {
+    pub path: String,
+    /// Cron expression, see `ScanSchedule`
+    pub schedule: String,
+}
+
+/// When a watched folder is rescanned, as a cron expression of five fields in local time:
+/// minute, hour, day of month, month and day of week (0 or 7 is Sunday). Fields take `*`,
+/// values, ranges `a-b`, steps `*/n` or `a-b/n` and lists of these, e.g. `0 2 * * *` for every
+/// night at 2am or `*/15 * * * *` for every 15 minutes. As in cron, a day matches when either
+/// day field does if both are restricted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl ScanSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(anyhow!("Schedule '{}' must have 5 fields: minute hour day-of-month month day-of-week", expression));
+        };
+
+        let field = |value: &str, name: &str, min: u32, max: u32| {
+            parse_field(value, min, max).map_err(|e| anyhow!("Invalid {} in schedule '{}': {}", name, expression, e))
+        };
+        let mut days_of_week = field(day_of_week, "day of week", 0, 7)?;
+        // Sunday may be written as 7
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: field(minute, "minute", 0, 59)?,
+            hours: field(hour, "hour", 0, 23)?,
+            days_of_month: field(day_of_month, "day of month", 1, 31)?,
+            months: field(month, "month", 1, 12)?,
+            days_of_week,
+            any_day_of_month: day_of_month == "*",
+            any_day_of_week: day_of_week == "*",
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The first time after `after` the schedule fires. Times skipped by a daylight saving
+    /// change are left out; None if no date matches, e.g. for February 30th.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut from = after.naive_local();
+        loop {
+            let next = self.next_naive_after(from)?;
+            if let Some(local) = Local.from_local_datetime(&next).earliest() {
+                return Some(local);
+            }
+            from = next;
+        }
+    }
+
+    fn next_naive_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        // Whole minutes only, starting with the one after `after`
+        let start = after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..MAX_DAYS_AHEAD {
+            if self.matches_day(date) {
+                let same_day = date == start.date();
+                let first_hour = if same_day { start.hour() } else { 0 };
+                for hour in (first_hour..24).filter(|hour| self.hours & (1 << hour) != 0) {
+                    let first_minute = if same_day && hour == start.hour() { start.minute() } else { 0 };
+                    if let Some(minute) = (first_minute..60).find(|minute| self.minutes & (1 << minute) != 0) {
+                        return date.and_hms_opt(hour, minute, 0);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day_of_month = self.days_of_month & (1 << date.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+}
+
+/// Schedules by watched folder, resolved the way watched folders are
+pub fn parse_scan_schedules(schedules: &[ScheduledScan]) -> Result<HashMap<PathBuf, ScanSchedule>> {
+    schedules.iter()
+        .map(|scheduled| Ok((paths::canonicalize(Path::new(&scheduled.path)), ScanSchedule::parse(&scheduled.schedule)?)))
+        .collect()
+}
+
+/// Bit set of the values a cron field allows
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut allowed = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| anyhow!("bad step '{}'", step))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(anyhow!("step must be at least 1"));
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => {
+                let value = |text: &str| text.parse::<u32>().map_err(|_| anyhow!("bad value '{}'", text));
+                match range.split_once('-') {
+                    Some((start, end)) => (value(start)?, value(end)?),
+                    // `5/10` runs from 5 to the end of the range
+                    None if part.contains('/') => (value(range)?, max),
+                    None => (value(range)?, value(range)?),
+                }
+            }
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!("'{}' is outside {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed |= 1 << value;
+        }
+    }
+    Ok(allowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> Option<NaiveDateTime> {
+        ScanSchedule::parse(expression).unwrap().next_naive_after(at(after))
+    }
+
+    #[test]
+    fn test_next_run() {
+        assert_eq!(next("0 2 * * *", "2024-03-10 01:59:30"), Some(at("2024-03-10 02:00:00")));
+        assert_eq!(next("0 2 * * *", "2024-03-10 02:00:00"), Some(at("2024-03-11 02:00:00")));
+        assert_eq!(next("*/15 * * * *", "2024-03-10 10:07:00"), Some(at("2024-03-10 10:15:00")));
+        assert_eq!(next("*/15 * * * *", "2024-12-31 23:50:00"), Some(at("2025-01-01 00:00:00")));
+        // Weekdays at 9 and 17; 2024-03-09 is a Saturday
+        assert_eq!(next("0 9,17 * * 1-5", "2024-03-09 12:00:00"), Some(at("2024-03-11 09:00:00")));
+        // Sunday written as 7, and either day field matching
+        assert_eq!(next("30 3 * * 7", "2024-03-10 04:00:00"), Some(at("2024-03-17 03:30:00")));
+        assert_eq!(next("0 0 13 * 5", "2024-03-10 00:00:00"), Some(at("2024-03-13 00:00:00")));
+        assert_eq!(next("0 0 29 2 *", "2024-03-01 00:00:00"), Some(at("2028-02-29 00:00:00")));
+        assert_eq!(next("0 0 30 2 *", "2024-03-01 00:00:00"), None);
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        for expression in ["", "0 2 * *", "60 * * * *", "* 24 * * *", "*/0 * * * *", "5-1 * * * *", "a * * * *", "0 0 0 * *"] {
+            assert!(ScanSchedule::parse(expression).is_err(), "{} should be rejected", expression);
+        }
+        assert_eq!(ScanSchedule::parse("  0   2 * * * ").unwrap().expression(), "0 2 * * *");
+    }
+}
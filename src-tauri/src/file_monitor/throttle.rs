@@ -0,0 +1,195 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessExt, ProcessRefreshKind, System, SystemExt, CpuExt};
+
+/// How often CPU and disk load are measured
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Average CPU use, in percent, from which indexing slows down a little and a lot
+const LIGHT_CPU_PERCENT: f32 = 70.0;
+const HEAVY_CPU_PERCENT: f32 = 90.0;
+
+/// Bytes read and written per second by all processes, from which indexing slows down
+const LIGHT_DISK_BYTES_PER_SEC: u64 = 40 * 1024 * 1024;
+const HEAVY_DISK_BYTES_PER_SEC: u64 = 120 * 1024 * 1024;
+
+/// How much scans and file events hold back so a large index does not make the machine unusable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThrottleLevel {
+    None,
+    Light,
+    Heavy,
+}
+
+impl ThrottleLevel {
+    /// The higher of what CPU and disk load each call for
+    pub fn from_load(cpu_percent: f32, disk_bytes_per_sec: u64) -> Self {
+        let cpu = if cpu_percent >= HEAVY_CPU_PERCENT {
+            ThrottleLevel::Heavy
+        } else if cpu_percent >= LIGHT_CPU_PERCENT {
+            ThrottleLevel::Light
+        } else {
+            ThrottleLevel::None
+        };
+        let disk = if disk_bytes_per_sec >= HEAVY_DISK_BYTES_PER_SEC {
+            ThrottleLevel::Heavy
+        } else if disk_bytes_per_sec >= LIGHT_DISK_BYTES_PER_SEC {
+            ThrottleLevel::Light
+        } else {
+            ThrottleLevel::None
+        };
+        cpu.max(disk)
+    }
+
+    /// Pause after each file
+    pub fn delay(self) -> Duration {
+        match self {
+            ThrottleLevel::None => Duration::ZERO,
+            ThrottleLevel::Light => Duration::from_millis(5),
+            ThrottleLevel::Heavy => Duration::from_millis(50),
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ThrottleLevel::Light,
+            2 => ThrottleLevel::Heavy,
+            _ => ThrottleLevel::None,
+        }
+    }
+}
+
+/// Average CPU use of every other process, in percent of the whole machine, as of the last
+/// refresh of the CPUs and of this process. Indexing should not hold itself back for its own work.
+pub fn others_cpu_percent(sys: &System, own_pid: Option<Pid>) -> f32 {
+    let own = own_pid.and_then(|pid| sys.process(pid)).map_or(0.0, |process| process.cpu_usage());
+    without_own_cpu(sys.global_cpu_info().cpu_usage(), own, sys.cpus().len())
+}
+
+/// A process's CPU use counts each core up to 100%, the machine's average all of them together
+fn without_own_cpu(machine_percent: f32, own_percent: f32, cpus: usize) -> f32 {
+    (machine_percent - own_percent / cpus.max(1) as f32).max(0.0)
+}
+
+/// Throttle level shared by the monitor's clones, kept current by a sampling thread while enabled
+#[derive(Debug, Clone)]
+pub struct LoadThrottle {
+    enabled: Arc<AtomicBool>,
+    level: Arc<AtomicU8>,
+    sampling: Arc<AtomicBool>,
+}
+
+impl LoadThrottle {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+            level: Arc::new(AtomicU8::new(ThrottleLevel::None as u8)),
+            sampling: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Turning it off lets indexing run at full speed right away
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.level.store(ThrottleLevel::None as u8, Ordering::Relaxed);
+        }
+    }
+
+    pub fn level(&self) -> ThrottleLevel {
+        ThrottleLevel::from_u8(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Wait as long as the current level asks for
+    pub async fn pause(&self) {
+        let delay = self.level().delay();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Measure load in the background from now on; calling it again does nothing
+    pub fn start_sampling(&self) {
+        if self.sampling.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let throttle = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name("load-throttle".to_string())
+            .spawn(move || throttle.sample_loop());
+        if let Err(e) = spawned {
+            self.sampling.store(false, Ordering::SeqCst);
+            tracing::error!("Failed to start load sampling: {}", e);
+        }
+    }
+
+    fn sample_loop(&self) {
+        let mut sys = System::new();
+        let processes = ProcessRefreshKind::new().with_cpu().with_disk_usage();
+        let own_pid = sysinfo::get_current_pid().ok();
+        let mut last_refresh = Instant::now();
+        loop {
+            std::thread::sleep(SAMPLE_INTERVAL);
+            if !self.is_enabled() {
+                continue;
+            }
+
+            // Both are measured since the previous refresh
+            sys.refresh_cpu();
+            sys.refresh_processes_specifics(processes);
+            let elapsed = last_refresh.elapsed().as_secs_f64().max(SAMPLE_INTERVAL.as_secs_f64());
+            last_refresh = Instant::now();
+            let cpu_percent = others_cpu_percent(&sys, own_pid);
+            let disk_bytes: u64 = sys.processes().iter()
+                .filter(|(pid, _)| Some(**pid) != own_pid)
+                .map(|(_, process)| {
+                    let usage = process.disk_usage();
+                    usage.read_bytes + usage.written_bytes
+                })
+                .sum();
+            let disk_bytes_per_sec = (disk_bytes as f64 / elapsed) as u64;
+
+            // Disabled while measuring leaves the level at none
+            let level = match self.is_enabled() {
+                true => ThrottleLevel::from_load(cpu_percent, disk_bytes_per_sec),
+                false => ThrottleLevel::None,
+            };
+            let previous = ThrottleLevel::from_u8(self.level.swap(level as u8, Ordering::Relaxed));
+            if level != previous {
+                tracing::info!("Indexing throttle changed from {:?} to {:?} (CPU {:.0}%, disk {} B/s)",
+                              previous, level, cpu_percent, disk_bytes_per_sec);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_follows_the_busier_resource() {
+        assert_eq!(ThrottleLevel::from_load(20.0, 0), ThrottleLevel::None);
+        assert_eq!(ThrottleLevel::from_load(75.0, 0), ThrottleLevel::Light);
+        assert_eq!(ThrottleLevel::from_load(95.0, LIGHT_DISK_BYTES_PER_SEC), ThrottleLevel::Heavy);
+        assert_eq!(ThrottleLevel::from_load(10.0, HEAVY_DISK_BYTES_PER_SEC), ThrottleLevel::Heavy);
+        assert!(ThrottleLevel::None.delay().is_zero());
+        assert_eq!(without_own_cpu(80.0, 200.0, 4), 30.0);
+        assert_eq!(without_own_cpu(10.0, 100.0, 2), 0.0);
+        assert!(ThrottleLevel::Light.delay() < ThrottleLevel::Heavy.delay());
+
+        let throttle = LoadThrottle::new(true);
+        throttle.level.store(ThrottleLevel::Heavy as u8, Ordering::Relaxed);
+        assert_eq!(throttle.level(), ThrottleLevel::Heavy);
+        throttle.set_enabled(false);
+        assert_eq!(throttle.level(), ThrottleLevel::None);
+    }
+}
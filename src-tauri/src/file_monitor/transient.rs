@@ -0,0 +1,93 @@
+use std::path::{Component, Path};
+
+/// Folders the platforms move deleted files into, lowercased: macOS in the home folder and on
+/// other volumes, the XDG trash on other volumes, and Windows old and new recycle bins
+const TRASH_FOLDERS: &[&str] = &[".trash", ".trashes", "$recycle.bin", "recycler"];
+
+/// Extensions browsers and download managers give a file until it is complete
+const PARTIAL_DOWNLOAD_EXTENSIONS: &[&str] = &["crdownload", "part", "partial", "opdownload", "!ut", "aria2"];
+
+/// Extensions of editor swap files and other scratch copies
+const TEMPORARY_EXTENSIONS: &[&str] = &["swp", "swo", "swx", "tmp", "temp", "crswap"];
+
+/// Why a file is not worth indexing however the exclusions are set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransientKind {
+    /// In the trash, so deleted as far as the user is concerned
+    Trash,
+    /// An editor's swap, backup or lock file
+    Temporary,
+    /// A download still in progress, renamed once it completes
+    PartialDownload,
+}
+
+pub fn classify(path: &Path) -> Option<TransientKind> {
+    if is_trash(path) {
+        return Some(TransientKind::Trash);
+    }
+
+    let names = lowercase_names(path);
+    let file_name = names.last()?;
+    // Safari keeps a download in a `.download` bundle, so the folder is checked as well
+    if names.iter().any(|name| extension(name) == Some("download"))
+        || extension(file_name).is_some_and(|extension| PARTIAL_DOWNLOAD_EXTENSIONS.contains(&extension))
+    {
+        return Some(TransientKind::PartialDownload);
+    }
+
+    let is_temporary = extension(file_name).is_some_and(|extension| TEMPORARY_EXTENSIONS.contains(&extension))
+        // Backups of vim, emacs and others
+        || file_name.ends_with('~')
+        // Office and LibreOffice lock files
+        || file_name.starts_with("~$")
+        || file_name.starts_with(".~lock.")
+        // Emacs auto-saves and locks
+        || (file_name.len() > 1 && file_name.starts_with('#') && file_name.ends_with('#'))
+        || file_name.starts_with(".#")
+        // Written by vim to check that a folder is writable
+        || file_name == "4913";
+    is_temporary.then_some(TransientKind::Temporary)
+}
+
+/// Whether `path` is in a trash folder, including the XDG one in the user's data folder
+pub fn is_trash(path: &Path) -> bool {
+    let names = lowercase_names(path);
+    let in_trash_folder = names.iter().any(|name| TRASH_FOLDERS.contains(&name.as_str()) || name.starts_with(".trash-"));
+    let in_xdg_trash = names.windows(3).any(|window| window == [".local", "share", "trash"])
+        || dirs::data_dir().is_some_and(|data_dir| cfg!(target_os = "linux") && path.starts_with(data_dir.join("Trash")));
+    in_trash_folder || in_xdg_trash
+}
+
+fn lowercase_names(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn extension(name: &str) -> Option<&str> {
+    Path::new(name).extension().and_then(|extension| extension.to_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        for path in ["/home/me/.local/share/Trash/files/report.pdf", "/Users/me/.Trash/report.pdf", "/media/usb/.Trash-1000/files/a.txt", "D:/$RECYCLE.BIN/S-1-5-21/$R1.docx"] {
+            assert_eq!(classify(Path::new(path)), Some(TransientKind::Trash), "{}", path);
+        }
+        for path in ["/dl/movie.mkv.crdownload", "/dl/setup.exe.part", "/dl/paper.pdf.download/paper.pdf", "/dl/a.zip.!ut"] {
+            assert_eq!(classify(Path::new(path)), Some(TransientKind::PartialDownload), "{}", path);
+        }
+        for path in ["/src/.main.rs.swp", "/docs/notes.txt~", "/docs/~$report.docx", "/docs/#draft.org#", "/docs/4913", "/docs/a.TMP"] {
+            assert_eq!(classify(Path::new(path)), Some(TransientKind::Temporary), "{}", path);
+        }
+        for path in ["/docs/report.pdf", "/docs/trash/old.txt", "/docs/parts/a.txt", "/docs/#", "/docs/c#"] {
+            assert_eq!(classify(Path::new(path)), None, "{}", path);
+        }
+    }
+}
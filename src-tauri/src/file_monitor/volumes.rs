@@ -0,0 +1,115 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use sysinfo::{DiskExt, System, SystemExt};
+use tokio::time::Duration;
+
+/// File systems that live on another machine
+const NETWORK_FILE_SYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smbfs", "smb2", "smb3", "afpfs", "webdav", "davfs", "fuse.sshfs", "sshfs", "9p", "ncpfs",
+];
+
+/// A network share that stops answering counts as disconnected after this long
+const AVAILABILITY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a watched folder's files are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeKind {
+    #[default]
+    Local,
+    Network,
+    Removable,
+}
+
+impl VolumeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VolumeKind::Local => "local",
+            VolumeKind::Network => "network",
+            VolumeKind::Removable => "removable",
+        }
+    }
+
+    fn from_disk(file_system: &str, is_removable: bool) -> Self {
+        if NETWORK_FILE_SYSTEMS.contains(&file_system.to_lowercase().as_str()) {
+            VolumeKind::Network
+        } else if is_removable {
+            VolumeKind::Removable
+        } else {
+            VolumeKind::Local
+        }
+    }
+}
+
+impl FromStr for VolumeKind {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "local" => Ok(VolumeKind::Local),
+            "network" => Ok(VolumeKind::Network),
+            "removable" => Ok(VolumeKind::Removable),
+            other => Err(anyhow!("Unknown volume kind: {}", other)),
+        }
+    }
+}
+
+/// The kind and current availability of the volume a watched folder is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VolumeStatus {
+    pub kind: VolumeKind,
+    pub available: bool,
+}
+
+/// Kind of the volume `path` is mounted from, judged by the innermost mount point containing it
+pub async fn detect_kind(path: &Path) -> VolumeKind {
+    // UNC paths name a server whether or not a drive letter is mapped to it
+    if cfg!(windows) && path.to_string_lossy().starts_with(r"\\") {
+        return VolumeKind::Network;
+    }
+
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut sys = System::new();
+        sys.refresh_disks_list();
+        sys.disks().iter()
+            .filter(|disk| path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().components().count())
+            .map(|disk| VolumeKind::from_disk(&String::from_utf8_lossy(disk.file_system()), disk.is_removable()))
+            .unwrap_or_default()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Whether the folder can be read now. A share that does not answer in time counts as gone,
+/// and so does a folder on a drive or share that is no longer mounted, where an empty mount
+/// point may be left behind on the disk below.
+pub async fn is_available(path: &Path, kind: VolumeKind) -> bool {
+    let readable = matches!(
+        tokio::time::timeout(AVAILABILITY_TIMEOUT, tokio::fs::metadata(path)).await,
+        Ok(Ok(metadata)) if metadata.is_dir()
+    );
+    readable && (kind == VolumeKind::Local || detect_kind(path).await == kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_disk() {
+        assert_eq!(VolumeKind::from_disk("CIFS", false), VolumeKind::Network);
+        assert_eq!(VolumeKind::from_disk("nfs4", true), VolumeKind::Network);
+        assert_eq!(VolumeKind::from_disk("exfat", true), VolumeKind::Removable);
+        assert_eq!(VolumeKind::from_disk("apfs", false), VolumeKind::Local);
+
+        for kind in [VolumeKind::Local, VolumeKind::Network, VolumeKind::Removable] {
+            assert_eq!(kind.as_str().parse::<VolumeKind>().unwrap(), kind);
+        }
+        assert!("floppy".parse::<VolumeKind>().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+
+use super::PathPatterns;
+use super::volumes::VolumeKind;
+use crate::database::watched_paths::WatchedPathRecord;
+use crate::processing_queue::JobPriority;
+
+/// How the files below one watched folder are indexed. Patterns are matched against paths
+/// relative to the folder, so `src/**` or `*.psd` work the same wherever it lives.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchOptions {
+    /// Skip what `.gitignore`, `.ignore` and `.metamindignore` files inside the folder exclude
+    pub respect_ignore_files: bool,
+    /// Only files matching one of these are indexed; empty indexes every file
+    pub include_patterns: Vec<String>,
+    /// Skipped on top of the global exclusion patterns
+    pub exclude_patterns: Vec<String>,
+    /// Bytes; larger files are not indexed. Unset uses the monitor's limit.
+    pub max_file_size: Option<u64>,
+    /// Queue priority of the folder's files
+    pub priority: JobPriority,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            respect_ignore_files: true,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            max_file_size: None,
+            priority: JobPriority::Normal,
+        }
+    }
+}
+
+impl WatchOptions {
+    pub fn to_record(&self, path: &Path, volume_kind: VolumeKind) -> WatchedPathRecord {
+        WatchedPathRecord {
+            path: path.to_string_lossy().to_string(),
+            respect_ignore_files: self.respect_ignore_files,
+            include_patterns: self.include_patterns.clone(),
+            exclude_patterns: self.exclude_patterns.clone(),
+            max_file_size: self.max_file_size.map(|size| size as i64),
+            priority: self.priority.as_str().to_string(),
+            volume_kind: volume_kind.as_str().to_string(),
+            added_at: Utc::now(),
+            last_rescan_at: None,
+        }
+    }
+
+    pub fn from_record(record: &WatchedPathRecord) -> Result<Self> {
+        Ok(Self {
+            respect_ignore_files: record.respect_ignore_files,
+            include_patterns: record.include_patterns.clone(),
+            exclude_patterns: record.exclude_patterns.clone(),
+            max_file_size: record.max_file_size.map(|size| size as u64),
+            priority: record.priority.parse()?,
+        })
+    }
+}
+
+/// Compiled `WatchOptions` of one watched folder
+#[derive(Debug, Clone)]
+pub struct WatchRules {
+    options: WatchOptions,
+    include: Option<PathPatterns>,
+    exclude: PathPatterns,
+}
+
+impl WatchRules {
+    pub fn new(options: WatchOptions) -> Result<Self> {
+        let include = match options.include_patterns.is_empty() {
+            true => None,
+            false => Some(PathPatterns::new(&options.include_patterns)?),
+        };
+        let exclude = PathPatterns::new(&options.exclude_patterns)?;
+        Ok(Self { options, include, exclude })
+    }
+
+    pub fn options(&self) -> &WatchOptions {
+        &self.options
+    }
+
+    /// Whether `path` below `root` is left out. Include patterns only filter files, so folders
+    /// are still descended into to find the files that match.
+    pub fn is_excluded(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        if self.exclude.matches(relative) {
+            return true;
+        }
+        match &self.include {
+            Some(include) if !is_dir => !include.matches(relative),
+            _ => false,
+        }
+    }
+
+    pub fn max_file_size(&self, default: u64) -> u64 {
+        self.options.max_file_size.unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_are_relative_to_the_root() {
+        let rules = WatchRules::new(WatchOptions {
+            include_patterns: vec!["*.rs".to_string(), "docs/**".to_string()],
+            exclude_patterns: vec!["target".to_string()],
+            ..Default::default()
+        }).unwrap();
+        let root = Path::new("/home/me/docs/project");
+
+        assert!(!rules.is_excluded(root, &root.join("src/main.rs"), false));
+        assert!(!rules.is_excluded(root, &root.join("docs/guide.md"), false));
+        assert!(!rules.is_excluded(root, &root.join("src"), true));
+        // `docs` in the root's own path does not count
+        assert!(rules.is_excluded(root, &root.join("README.md"), false));
+        assert!(rules.is_excluded(root, &root.join("target/debug/build.rs"), false));
+        assert!(rules.is_excluded(root, &root.join("target"), true));
+
+        assert!(WatchRules::new(WatchOptions {
+            exclude_patterns: vec!["[".to_string()],
+            ..Default::default()
+        }).is_err());
+    }
+}
@@ -145,7 +145,7 @@ impl FolderVectorizer {
             analysis.theme_vector.clone(),
             analysis.file_count,
             analysis.total_size,
-            "nomic-embed-text", // TODO: Make configurable
+            self.ai_processor.embedding_model(),
         ).await?;
 
         Ok(analysis)
@@ -0,0 +1,466 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::database::{Database, FileRecord};
+use crate::paths;
+use crate::vector_storage::VectorStorageManager;
+
+/// Bumped when the archive layout changes incompatibly
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+/// A portable copy of the index that can be merged into another installation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexArchive {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub files: Vec<ArchivedFile>,
+    pub collections: Vec<ArchivedCollection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub path: String,
+    pub name: String,
+    pub extension: Option<String>,
+    pub size: i64,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+    pub mime_type: Option<String>,
+    pub hash: Option<String>,
+    pub content: Option<String>,
+    /// JSON array, as stored in the index
+    pub tags: Option<String>,
+    pub metadata: Option<String>,
+    pub ai_analysis: Option<String>,
+    pub indexed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vectors: Option<ArchivedVectors>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedVectors {
+    pub embedding: Option<Vec<f32>>,
+    pub content: Option<Vec<f32>>,
+    pub metadata: Option<Vec<f32>>,
+    pub summary: Option<Vec<f32>>,
+    pub model_name: Option<String>,
+}
+
+/// Collections refer to their files by path since ids differ between installations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedCollection {
+    pub name: String,
+    pub description: Option<String>,
+    pub file_paths: Vec<String>,
+}
+
+/// What to do when a file exists at the same path with different content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    KeepLocal,
+    PreferArchive,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Archived files that were not indexed here yet and exist on disk
+    pub inserted: usize,
+    /// Local files that took over an archived analysis, matched by path or by hash
+    pub merged: usize,
+    /// Local files already analysed at least as recently as the archive
+    pub unchanged: usize,
+    /// Paths whose local content differs from the archive and were left alone
+    pub conflicts: Vec<String>,
+    /// Conflicting files replaced by the archive under `PreferArchive`
+    pub overwritten: usize,
+    /// Archived files neither indexed nor present on this machine
+    pub skipped_missing: usize,
+    pub collections_created: usize,
+    pub collection_links: usize,
+}
+
+/// Collect the index into an archive; vectors are large and only included when asked for
+pub async fn export_index(
+    database: &Database,
+    vector_storage: &VectorStorageManager,
+    include_embeddings: bool,
+) -> Result<IndexArchive> {
+    let mut files = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = database.get_active_files(EXPORT_PAGE_SIZE, offset).await?;
+        if page.is_empty() {
+            break;
+        }
+        offset += page.len() as i64;
+
+        for file in page {
+            let vectors = if include_embeddings {
+                let stored = vector_storage.get_file_vectors(&file.id).await?;
+                Some(ArchivedVectors {
+                    embedding: file.embedding.clone(),
+                    content: stored.as_ref().and_then(|v| v.content.clone()),
+                    metadata: stored.as_ref().and_then(|v| v.metadata.clone()),
+                    summary: stored.as_ref().and_then(|v| v.summary.clone()),
+                    model_name: stored.map(|v| v.model_name),
+                })
+            } else {
+                None
+            };
+            files.push(ArchivedFile::from_record(file, vectors));
+        }
+    }
+
+    let mut collections = Vec::new();
+    for collection in database.get_collections().await? {
+        let file_paths = database.get_files_in_collection(&collection.id).await?
+            .into_iter()
+            .map(|file| file.path)
+            .collect();
+        collections.push(ArchivedCollection {
+            name: collection.name,
+            description: collection.description,
+            file_paths,
+        });
+    }
+
+    Ok(IndexArchive {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        files,
+        collections,
+    })
+}
+
+/// Merge an archive into the index. Files match by path first, then by content hash;
+/// a path match with a different hash is a conflict settled by `policy`.
+pub async fn import_index(
+    database: &Database,
+    vector_storage: &VectorStorageManager,
+    archive: &IndexArchive,
+    policy: ConflictPolicy,
+) -> Result<ImportReport> {
+    if archive.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Archive format {} is newer than this version supports ({})",
+            archive.format_version, ARCHIVE_FORMAT_VERSION
+        ));
+    }
+
+    let mut report = ImportReport::default();
+    // Archived path to local file id, used to rebuild collections
+    let mut local_ids: HashMap<&str, String> = HashMap::new();
+
+    for archived in &archive.files {
+        let local_path = paths::canonical_string(Path::new(&archived.path));
+        if let Some(mut local) = database.get_file_by_path(&local_path).await? {
+            // A soft-deleted record still holds the path; it comes back only if the file did
+            if local.processing_status == "deleted" {
+                if !Path::new(&local_path).is_file() {
+                    report.skipped_missing += 1;
+                    continue;
+                }
+                database.restore_deleted_file(&local.id).await?;
+                local = database.get_file_by_id(&local.id).await?
+                    .ok_or_else(|| anyhow!("Restored file {} is missing from the index", local_path))?;
+            }
+            local_ids.insert(&archived.path, local.id.clone());
+
+            let same_content = match (&local.hash, &archived.hash) {
+                (Some(local_hash), Some(archived_hash)) => local_hash == archived_hash,
+                // Not hashed yet on one side; the path is the best evidence available
+                _ => true,
+            };
+            if !same_content {
+                if policy == ConflictPolicy::PreferArchive && archived.indexed_at.is_some() {
+                    apply_archived(database, vector_storage, &local.id, archived).await?;
+                    report.overwritten += 1;
+                } else {
+                    report.conflicts.push(archived.path.clone());
+                }
+                continue;
+            }
+
+            if is_newer(archived, &local) {
+                apply_archived(database, vector_storage, &local.id, archived).await?;
+                report.merged += 1;
+            } else {
+                report.unchanged += 1;
+            }
+            continue;
+        }
+
+        // Same bytes indexed under another path, e.g. a different home directory
+        let copies = match &archived.hash {
+            Some(hash) => database.get_files_by_hash(hash).await?,
+            None => Vec::new(),
+        };
+        if let Some(first) = copies.first() {
+            local_ids.insert(&archived.path, first.id.clone());
+            for copy in &copies {
+                if is_newer(archived, copy) {
+                    apply_archived(database, vector_storage, &copy.id, archived).await?;
+                    report.merged += 1;
+                } else {
+                    report.unchanged += 1;
+                }
+            }
+            continue;
+        }
+
+        if Path::new(&local_path).is_file() {
+            let mut record = archived.to_record(Uuid::new_v4().to_string());
+            record.path = local_path;
+            database.insert_file(&record).await?;
+            store_archived_vectors(vector_storage, &record.id, archived).await?;
+            local_ids.insert(&archived.path, record.id);
+            report.inserted += 1;
+        } else {
+            report.skipped_missing += 1;
+        }
+    }
+
+    let mut collections: HashMap<String, String> = database.get_collections().await?
+        .into_iter()
+        .map(|collection| (collection.name.to_lowercase(), collection.id))
+        .collect();
+    for archived in &archive.collections {
+        let collection_id = match collections.get(&archived.name.to_lowercase()) {
+            Some(id) => id.clone(),
+            None => {
+                let created = database.create_collection(&archived.name, archived.description.as_deref()).await?;
+                collections.insert(archived.name.to_lowercase(), created.id.clone());
+                report.collections_created += 1;
+                created.id
+            }
+        };
+
+        for path in &archived.file_paths {
+            if let Some(file_id) = local_ids.get(path.as_str()) {
+                database.add_file_to_collection(file_id, &collection_id).await?;
+                report.collection_links += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Whether the archive holds an analysis the local record lacks or that is more recent
+fn is_newer(archived: &ArchivedFile, local: &FileRecord) -> bool {
+    match (archived.indexed_at, local.indexed_at) {
+        (Some(archived_at), Some(local_at)) => archived_at > local_at,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+async fn apply_archived(
+    database: &Database,
+    vector_storage: &VectorStorageManager,
+    file_id: &str,
+    archived: &ArchivedFile,
+) -> Result<()> {
+    database.apply_imported_analysis(file_id, &archived.to_record(file_id.to_string())).await?;
+    store_archived_vectors(vector_storage, file_id, archived).await
+}
+
+async fn store_archived_vectors(vector_storage: &VectorStorageManager, file_id: &str, archived: &ArchivedFile) -> Result<()> {
+    let Some(vectors) = &archived.vectors else {
+        return Ok(());
+    };
+    if vectors.content.is_none() && vectors.metadata.is_none() && vectors.summary.is_none() {
+        return Ok(());
+    }
+    vector_storage.store_file_vectors(
+        file_id,
+        vectors.content.clone(),
+        vectors.metadata.clone(),
+        vectors.summary.clone(),
+        vectors.model_name.as_deref().unwrap_or("unknown"),
+    ).await
+}
+
+impl ArchivedFile {
+    fn from_record(file: FileRecord, vectors: Option<ArchivedVectors>) -> Self {
+        Self {
+            path: file.path,
+            name: file.name,
+            extension: file.extension,
+            size: file.size,
+            created_at: file.created_at,
+            modified_at: file.modified_at,
+            mime_type: file.mime_type,
+            hash: file.hash,
+            content: file.content,
+            tags: file.tags,
+            metadata: file.metadata,
+            ai_analysis: file.ai_analysis,
+            indexed_at: file.indexed_at,
+            vectors,
+        }
+    }
+
+    fn to_record(&self, id: String) -> FileRecord {
+        FileRecord {
+            id,
+            path: self.path.clone(),
+            name: self.name.clone(),
+            extension: self.extension.clone(),
+            size: self.size,
+            created_at: self.created_at,
+            modified_at: self.modified_at,
+            last_accessed: None,
+            mime_type: self.mime_type.clone(),
+            hash: self.hash.clone(),
+            content: self.content.clone(),
+            tags: self.tags.clone(),
+            metadata: self.metadata.clone(),
+            ai_analysis: self.ai_analysis.clone(),
+            embedding: self.vectors.as_ref().and_then(|v| v.embedding.clone()),
+            indexed_at: self.indexed_at,
+            processing_status: if self.indexed_at.is_some() { "completed" } else { "pending" }.to_string(),
+            error_message: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn archived(path: &str, hash: &str, indexed_days_ago: Option<i64>) -> ArchivedFile {
+        let now = Utc::now();
+        ArchivedFile {
+            path: path.to_string(),
+            name: Path::new(path).file_name().unwrap().to_string_lossy().to_string(),
+            extension: Some("txt".to_string()),
+            size: 5,
+            created_at: now,
+            modified_at: now,
+            mime_type: Some("text/plain".to_string()),
+            hash: Some(hash.to_string()),
+            content: Some("archived content".to_string()),
+            tags: Some(r#"["imported"]"#.to_string()),
+            metadata: None,
+            ai_analysis: Some("Archived summary".to_string()),
+            indexed_at: indexed_days_ago.map(|days| now - chrono::Duration::days(days)),
+            vectors: None,
+        }
+    }
+
+    async fn insert_local(database: &Database, path: &str, hash: &str) -> FileRecord {
+        let mut record = archived(path, hash, None).to_record(Uuid::new_v4().to_string());
+        record.content = None;
+        record.ai_analysis = None;
+        database.insert_file(&record).await.expect("Failed to insert file");
+        record
+    }
+
+    #[tokio::test]
+    async fn test_import_merges_by_path_and_hash() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await.expect("Failed to create database");
+        let vector_storage = VectorStorageManager::new(database.pool.clone());
+
+        insert_local(&database, "/home/a/notes.txt", "h1").await;
+        let moved = insert_local(&database, "/home/b/moved.txt", "h2").await;
+        insert_local(&database, "/home/a/changed.txt", "local").await;
+
+        let on_disk = temp_dir.path().join("new.txt");
+        std::fs::write(&on_disk, "hello").unwrap();
+
+        let archive = IndexArchive {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            files: vec![
+                archived("/home/a/notes.txt", "h1", Some(1)),
+                archived("/other/home/moved.txt", "h2", Some(1)),
+                archived("/home/a/changed.txt", "remote", Some(1)),
+                archived(&on_disk.to_string_lossy(), "h3", Some(1)),
+                archived("/nowhere/gone.txt", "h4", Some(1)),
+            ],
+            collections: vec![ArchivedCollection {
+                name: "Imported".to_string(),
+                description: None,
+                file_paths: vec!["/home/a/notes.txt".to_string(), "/other/home/moved.txt".to_string()],
+            }],
+        };
+
+        let report = import_index(&database, &vector_storage, &archive, ConflictPolicy::KeepLocal).await.unwrap();
+        assert_eq!(report.merged, 2);
+        assert_eq!(report.inserted, 1);
+        assert_eq!(report.conflicts, vec!["/home/a/changed.txt".to_string()]);
+        assert_eq!(report.skipped_missing, 1);
+        assert_eq!((report.collections_created, report.collection_links), (1, 2));
+
+        let merged = database.get_file_by_id(&moved.id).await.unwrap().unwrap();
+        assert_eq!(merged.ai_analysis.as_deref(), Some("Archived summary"));
+        assert_eq!(merged.processing_status, "completed");
+
+        // Importing the same archive again changes nothing
+        let report = import_index(&database, &vector_storage, &archive, ConflictPolicy::KeepLocal).await.unwrap();
+        assert_eq!((report.merged, report.inserted, report.unchanged), (0, 0, 3));
+
+        let report = import_index(&database, &vector_storage, &archive, ConflictPolicy::PreferArchive).await.unwrap();
+        assert_eq!(report.overwritten, 1);
+        assert!(report.conflicts.is_empty());
+        let overwritten = database.get_file_by_path("/home/a/changed.txt").await.unwrap().unwrap();
+        assert_eq!(overwritten.hash.as_deref(), Some("remote"));
+    }
+
+    #[tokio::test]
+    async fn test_import_restores_soft_deleted_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database = Database::new(temp_dir.path().join("test.db")).await.expect("Failed to create database");
+        let vector_storage = VectorStorageManager::new(database.pool.clone());
+
+        let on_disk = temp_dir.path().join("back.txt");
+        std::fs::write(&on_disk, "hello").unwrap();
+        let on_disk = paths::canonical_string(&on_disk);
+        let returned = insert_local(&database, &on_disk, "h1").await;
+        let gone = insert_local(&database, "/nowhere/gone.txt", "h2").await;
+        database.mark_file_deleted(&returned.id).await.unwrap();
+        database.mark_file_deleted(&gone.id).await.unwrap();
+
+        let archive = IndexArchive {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            files: vec![archived(&on_disk, "h1", Some(1)), archived("/nowhere/gone.txt", "h2", Some(1))],
+            collections: Vec::new(),
+        };
+
+        let report = import_index(&database, &vector_storage, &archive, ConflictPolicy::KeepLocal).await.unwrap();
+        assert_eq!((report.merged, report.skipped_missing), (1, 1));
+
+        let restored = database.get_file_by_id(&returned.id).await.unwrap().unwrap();
+        assert_eq!(restored.processing_status, "completed");
+        assert_eq!(restored.ai_analysis.as_deref(), Some("Archived summary"));
+        let still_gone = database.get_file_by_id(&gone.id).await.unwrap().unwrap();
+        assert_eq!(still_gone.processing_status, "deleted");
+        assert!(still_gone.ai_analysis.is_none());
+    }
+
+    #[test]
+    fn test_archive_round_trips_without_vectors() {
+        let archive = IndexArchive {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            files: vec![archived("/a.txt", "h", Some(0))],
+            collections: Vec::new(),
+        };
+        let json = serde_json::to_string(&archive).unwrap();
+        assert!(!json.contains("\"vectors\""));
+        let parsed: IndexArchive = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.files[0].path, "/a.txt");
+    }
+}
@@ -0,0 +1,335 @@
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::database::Database;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    File,
+    Entity,
+    Topic,
+    Folder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// File to an entity named in it
+    Mentions,
+    /// File to one of its tags
+    TaggedWith,
+    /// File to the folder it lives in
+    ContainedIn,
+    /// File to file, weighted by the entities, topics and folders they share
+    Related,
+    /// Entity to entity, weighted by the files naming both
+    CoOccurs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+    /// File id, entity name, tag name or folder path the node stands for
+    pub reference: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KnowledgeGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    #[serde(skip)]
+    node_index: HashMap<String, usize>,
+    #[serde(skip)]
+    edge_index: HashMap<(String, String, EdgeKind), usize>,
+}
+
+impl KnowledgeGraph {
+    /// Add a node once and return its id; entities are matched case-insensitively
+    pub fn add_node(&mut self, kind: NodeKind, reference: &str, label: &str) -> String {
+        let id = node_id(kind, reference);
+        if !self.node_index.contains_key(&id) {
+            self.node_index.insert(id.clone(), self.nodes.len());
+            self.nodes.push(GraphNode {
+                id: id.clone(),
+                kind,
+                label: label.to_string(),
+                reference: reference.to_string(),
+            });
+        }
+        id
+    }
+
+    /// Add an edge, or add `weight` to an existing edge of the same kind between the same nodes
+    pub fn add_edge(&mut self, source: &str, target: &str, kind: EdgeKind, weight: f32) {
+        let key = (source.to_string(), target.to_string(), kind);
+        if let Some(&index) = self.edge_index.get(&key) {
+            self.edges[index].weight += weight;
+            return;
+        }
+        self.edge_index.insert(key, self.edges.len());
+        self.edges.push(GraphEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            kind,
+            weight,
+        });
+    }
+}
+
+fn node_id(kind: NodeKind, reference: &str) -> String {
+    match kind {
+        NodeKind::File => format!("file:{}", reference),
+        NodeKind::Entity => format!("entity:{}", reference.to_lowercase()),
+        NodeKind::Topic => format!("topic:{}", reference),
+        NodeKind::Folder => format!("folder:{}", reference),
+    }
+}
+
+fn parent_folder(path: &str) -> Option<String> {
+    Path::new(path).parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .filter(|parent| !parent.is_empty())
+}
+
+fn folder_label(folder: &str) -> String {
+    Path::new(folder).file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| folder.to_string())
+}
+
+/// A file with its topics, entities and folder, and the files sharing them
+pub async fn file_neighborhood(database: &Database, file_id: &str, limit: usize) -> Result<KnowledgeGraph> {
+    let file = database.get_file_by_id(file_id).await?
+        .ok_or_else(|| anyhow!("File not found: {}", file_id))?;
+
+    let mut graph = KnowledgeGraph::default();
+    let center = graph.add_node(NodeKind::File, &file.id, &file.name);
+    let mut shared: HashMap<String, f32> = HashMap::new();
+
+    for tag in file_tags(database, &file.id).await? {
+        let topic = graph.add_node(NodeKind::Topic, &tag, &tag);
+        graph.add_edge(&center, &topic, EdgeKind::TaggedWith, 1.0);
+        for (id, name) in files_with_tag(database, &tag, &file.id, limit).await? {
+            let neighbor = graph.add_node(NodeKind::File, &id, &name);
+            graph.add_edge(&neighbor, &topic, EdgeKind::TaggedWith, 1.0);
+            *shared.entry(neighbor).or_default() += 1.0;
+        }
+    }
+
+    for entity in file_entities(database, &file.id).await? {
+        let entity_node = graph.add_node(NodeKind::Entity, &entity, &entity);
+        graph.add_edge(&center, &entity_node, EdgeKind::Mentions, 1.0);
+        for (id, name) in files_with_entity(database, &entity, Some(&file.id), limit).await? {
+            let neighbor = graph.add_node(NodeKind::File, &id, &name);
+            graph.add_edge(&neighbor, &entity_node, EdgeKind::Mentions, 1.0);
+            *shared.entry(neighbor).or_default() += 1.0;
+        }
+    }
+
+    if let Some(folder) = parent_folder(&file.path) {
+        let folder_node = graph.add_node(NodeKind::Folder, &folder, &folder_label(&folder));
+        graph.add_edge(&center, &folder_node, EdgeKind::ContainedIn, 1.0);
+        for (id, name) in files_in_folder(database, &folder, &file.id, limit).await? {
+            let neighbor = graph.add_node(NodeKind::File, &id, &name);
+            graph.add_edge(&neighbor, &folder_node, EdgeKind::ContainedIn, 1.0);
+            // A shared folder is weaker evidence than a shared topic or entity
+            *shared.entry(neighbor).or_default() += 0.5;
+        }
+    }
+
+    let mut related: Vec<(String, f32)> = shared.into_iter().collect();
+    related.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+    for (neighbor, weight) in related {
+        graph.add_edge(&center, &neighbor, EdgeKind::Related, weight);
+    }
+
+    Ok(graph)
+}
+
+/// An entity with the files naming it and the entities and topics appearing alongside it
+pub async fn entity_neighborhood(database: &Database, entity: &str, limit: usize) -> Result<KnowledgeGraph> {
+    let entity = entity.trim();
+    if entity.is_empty() {
+        return Err(anyhow!("Entity name is empty"));
+    }
+
+    let mut graph = KnowledgeGraph::default();
+    let center = graph.add_node(NodeKind::Entity, entity, entity);
+    let mut co_occurring: HashMap<String, (String, usize)> = HashMap::new();
+
+    for (id, name) in files_with_entity(database, entity, None, limit).await? {
+        let file_node = graph.add_node(NodeKind::File, &id, &name);
+        graph.add_edge(&file_node, &center, EdgeKind::Mentions, 1.0);
+
+        for tag in file_tags(database, &id).await? {
+            let topic = graph.add_node(NodeKind::Topic, &tag, &tag);
+            graph.add_edge(&file_node, &topic, EdgeKind::TaggedWith, 1.0);
+        }
+        for other in file_entities(database, &id).await? {
+            if other.to_lowercase() == entity.to_lowercase() {
+                continue;
+            }
+            co_occurring.entry(other.to_lowercase()).or_insert((other, 0)).1 += 1;
+        }
+    }
+
+    // Only the most frequent companions are kept so popular entities stay readable
+    let mut companions: Vec<(String, usize)> = co_occurring.into_values().collect();
+    companions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    for (other, count) in companions.into_iter().take(limit) {
+        let other_node = graph.add_node(NodeKind::Entity, &other, &other);
+        graph.add_edge(&center, &other_node, EdgeKind::CoOccurs, count as f32);
+    }
+
+    Ok(graph)
+}
+
+async fn file_tags(database: &Database, file_id: &str) -> Result<Vec<String>> {
+    let tags = sqlx::query_scalar(
+        r#"
+        SELECT t.name FROM tags t
+        INNER JOIN file_tags ft ON ft.tag_id = t.id
+        WHERE ft.file_id = ?
+        ORDER BY t.name
+        "#
+    )
+    .bind(file_id)
+    .fetch_all(&database.pool)
+    .await?;
+    Ok(tags)
+}
+
+async fn file_entities(database: &Database, file_id: &str) -> Result<Vec<String>> {
+    let entities = sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT entity.value FROM files, json_each(
+            CASE WHEN json_valid(files.metadata) THEN files.metadata ELSE '{}' END,
+            '$.entities'
+        ) entity
+        WHERE files.id = ? AND entity.type = 'text'
+        "#
+    )
+    .bind(file_id)
+    .fetch_all(&database.pool)
+    .await?;
+    Ok(entities)
+}
+
+async fn files_with_tag(database: &Database, tag: &str, exclude_id: &str, limit: usize) -> Result<Vec<(String, String)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT f.id, f.name FROM files f
+        INNER JOIN file_tags ft ON ft.file_id = f.id
+        INNER JOIN tags t ON t.id = ft.tag_id
+        WHERE t.name = ? AND f.id != ? AND f.deleted_at IS NULL
+        ORDER BY f.modified_at DESC
+        LIMIT ?
+        "#
+    )
+    .bind(tag)
+    .bind(exclude_id)
+    .bind(limit as i64)
+    .fetch_all(&database.pool)
+    .await?;
+    Ok(rows.iter().map(|row| (row.get("id"), row.get("name"))).collect())
+}
+
+async fn files_with_entity(database: &Database, entity: &str, exclude_id: Option<&str>, limit: usize) -> Result<Vec<(String, String)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT DISTINCT files.id, files.name, files.modified_at FROM files, json_each(
+            CASE WHEN json_valid(files.metadata) THEN files.metadata ELSE '{}' END,
+            '$.entities'
+        ) entity
+        WHERE entity.type = 'text' AND entity.value = ? COLLATE NOCASE
+          AND files.id != COALESCE(?, '') AND files.deleted_at IS NULL
+        ORDER BY files.modified_at DESC
+        LIMIT ?
+        "#
+    )
+    .bind(entity)
+    .bind(exclude_id)
+    .bind(limit as i64)
+    .fetch_all(&database.pool)
+    .await?;
+    Ok(rows.iter().map(|row| (row.get("id"), row.get("name"))).collect())
+}
+
+async fn files_in_folder(database: &Database, folder: &str, exclude_id: &str, limit: usize) -> Result<Vec<(String, String)>> {
+    // The prefix also matches subfolders, so direct children are picked out here
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, path FROM files
+        WHERE substr(path, 1, length(?)) = ? AND id != ? AND deleted_at IS NULL
+        ORDER BY modified_at DESC
+        LIMIT 1000
+        "#
+    )
+    .bind(folder)
+    .bind(folder)
+    .bind(exclude_id)
+    .fetch_all(&database.pool)
+    .await?;
+
+    Ok(rows.iter()
+        .filter(|row| parent_folder(&row.get::<String, _>("path")).as_deref() == Some(folder))
+        .take(limit)
+        .map(|row| (row.get("id"), row.get("name")))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nodes_are_deduplicated() {
+        let mut graph = KnowledgeGraph::default();
+        let first = graph.add_node(NodeKind::Entity, "Acme Corp", "Acme Corp");
+        let second = graph.add_node(NodeKind::Entity, "ACME CORP", "ACME CORP");
+        let topic = graph.add_node(NodeKind::Topic, "Acme Corp", "Acme Corp");
+
+        assert_eq!(first, second);
+        assert_ne!(first, topic);
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes[0].label, "Acme Corp");
+    }
+
+    #[test]
+    fn test_repeated_edges_accumulate_weight() {
+        let mut graph = KnowledgeGraph::default();
+        let a = graph.add_node(NodeKind::File, "a", "a.txt");
+        let b = graph.add_node(NodeKind::File, "b", "b.txt");
+        graph.add_edge(&a, &b, EdgeKind::Related, 1.0);
+        graph.add_edge(&a, &b, EdgeKind::Related, 0.5);
+        graph.add_edge(&a, &b, EdgeKind::CoOccurs, 1.0);
+
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].weight, 1.5);
+
+        let json = serde_json::to_value(&graph).unwrap();
+        assert_eq!(json["edges"][0]["kind"], "related");
+        assert!(json.get("node_index").is_none());
+    }
+
+    #[test]
+    fn test_folder_helpers() {
+        assert_eq!(parent_folder("/docs/reports/q3.pdf").as_deref(), Some("/docs/reports"));
+        assert_eq!(folder_label("/docs/reports"), "reports");
+        assert_eq!(parent_folder("q3.pdf"), None);
+    }
+}
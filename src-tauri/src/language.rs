@@ -0,0 +1,317 @@
+use std::collections::HashSet;
+
+use whatlang::Lang;
+
+/// Number of characters looked at when guessing a document's language
+const DETECTION_SAMPLE_CHARS: usize = 2000;
+
+/// ISO 639-1 codes of the languages whatlang tells apart
+const ISO_CODES: &[(Lang, &str)] = &[
+    (Lang::Epo, "eo"), (Lang::Eng, "en"), (Lang::Rus, "ru"), (Lang::Cmn, "zh"), (Lang::Spa, "es"),
+    (Lang::Por, "pt"), (Lang::Ita, "it"), (Lang::Ben, "bn"), (Lang::Fra, "fr"), (Lang::Deu, "de"),
+    (Lang::Ukr, "uk"), (Lang::Kat, "ka"), (Lang::Ara, "ar"), (Lang::Hin, "hi"), (Lang::Jpn, "ja"),
+    (Lang::Heb, "he"), (Lang::Yid, "yi"), (Lang::Pol, "pl"), (Lang::Amh, "am"), (Lang::Jav, "jv"),
+    (Lang::Kor, "ko"), (Lang::Nob, "nb"), (Lang::Dan, "da"), (Lang::Swe, "sv"), (Lang::Fin, "fi"),
+    (Lang::Tur, "tr"), (Lang::Nld, "nl"), (Lang::Hun, "hu"), (Lang::Ces, "cs"), (Lang::Ell, "el"),
+    (Lang::Bul, "bg"), (Lang::Bel, "be"), (Lang::Mar, "mr"), (Lang::Kan, "kn"), (Lang::Ron, "ro"),
+    (Lang::Slv, "sl"), (Lang::Hrv, "hr"), (Lang::Srp, "sr"), (Lang::Mkd, "mk"), (Lang::Lit, "lt"),
+    (Lang::Lav, "lv"), (Lang::Est, "et"), (Lang::Tam, "ta"), (Lang::Vie, "vi"), (Lang::Urd, "ur"),
+    (Lang::Tha, "th"), (Lang::Guj, "gu"), (Lang::Uzb, "uz"), (Lang::Pan, "pa"), (Lang::Aze, "az"),
+    (Lang::Ind, "id"), (Lang::Tel, "te"), (Lang::Pes, "fa"), (Lang::Mal, "ml"), (Lang::Ori, "or"),
+    (Lang::Mya, "my"), (Lang::Nep, "ne"), (Lang::Sin, "si"), (Lang::Khm, "km"), (Lang::Tuk, "tk"),
+    (Lang::Aka, "ak"), (Lang::Zul, "zu"), (Lang::Sna, "sn"), (Lang::Afr, "af"), (Lang::Lat, "la"),
+    (Lang::Slk, "sk"), (Lang::Cat, "ca"), (Lang::Tgl, "tl"), (Lang::Hye, "hy"),
+];
+
+/// Spellings of umlauts and other letters that writers of a language swap in for them,
+/// indexed alongside the plain folding so "Mueller" finds "Müller" in a German document
+const TRANSLITERATIONS: &[(&str, &[(char, &str)])] = &[
+    ("de", &[('ä', "ae"), ('ö', "oe"), ('ü', "ue")]),
+    ("da", &[('æ', "ae"), ('ø', "oe"), ('å', "aa")]),
+    ("nb", &[('æ', "ae"), ('ø', "oe"), ('å', "aa")]),
+    ("sv", &[('ä', "ae"), ('ö', "oe"), ('å', "aa")]),
+];
+
+const STOP_WORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "to", "is", "in", "that", "it", "with", "for", "this", "are"]),
+    ("de", &["der", "die", "das", "und", "ist", "nicht", "ein", "eine", "mit", "den", "auf", "sich"]),
+    ("fr", &["le", "la", "les", "et", "est", "des", "une", "dans", "pour", "que", "pas", "avec"]),
+    ("es", &["el", "los", "las", "y", "es", "una", "por", "con", "para", "que", "del", "como"]),
+    ("it", &["il", "di", "che", "e", "non", "una", "per", "con", "sono", "della", "gli", "come"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "niet", "met", "voor", "op", "zijn", "dat"]),
+    ("pt", &["o", "os", "as", "e", "do", "da", "uma", "para", "com", "não", "que", "em"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+    Latin,
+    Han,
+    Kana,
+    Hangul,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+}
+
+fn script_of(c: char) -> Option<Script> {
+    match c as u32 {
+        0x0041..=0x024F if c.is_alphabetic() => Some(Script::Latin),
+        0x0370..=0x03FF => Some(Script::Greek),
+        0x0400..=0x04FF => Some(Script::Cyrillic),
+        0x0590..=0x05FF => Some(Script::Hebrew),
+        0x0600..=0x06FF => Some(Script::Arabic),
+        0x0900..=0x097F => Some(Script::Devanagari),
+        0x0E00..=0x0E7F => Some(Script::Thai),
+        0x3040..=0x30FF => Some(Script::Kana),
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Some(Script::Han),
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => Some(Script::Hangul),
+        _ => None,
+    }
+}
+
+/// Scripts written without spaces between words, indexed as overlapping character bigrams
+fn is_unspaced(c: char) -> bool {
+    matches!(script_of(c), Some(Script::Han | Script::Kana | Script::Thai))
+}
+
+/// Guess the ISO 639-1 code of the dominant language in `text` from its trigrams, falling
+/// back to script and function words when the text is too short for a reliable answer.
+pub fn detect_language(text: &str) -> Option<String> {
+    let sample: String = text.chars().take(DETECTION_SAMPLE_CHARS).collect();
+
+    whatlang::detect(&sample)
+        .filter(|info| info.is_reliable())
+        .and_then(|info| iso_code(info.lang()))
+        .map(str::to_string)
+        .or_else(|| detect_by_script(&sample))
+}
+
+fn iso_code(lang: Lang) -> Option<&'static str> {
+    ISO_CODES.iter().find(|(candidate, _)| *candidate == lang).map(|(_, code)| *code)
+}
+
+/// English name of the language with the ISO 639-1 `code`, for prompts and display
+pub fn language_name(code: &str) -> Option<&'static str> {
+    ISO_CODES.iter().find(|(_, candidate)| *candidate == code).map(|(lang, _)| lang.eng_name())
+}
+
+/// Non-Latin scripts are identified by their characters, Latin ones by common function words
+fn detect_by_script(sample: &str) -> Option<String> {
+    let mut counts = [0usize; 10];
+    for script in sample.chars().filter_map(script_of) {
+        counts[script as usize] += 1;
+    }
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    // Japanese text mixes kana with kanji, so any noticeable kana decides it
+    if counts[Script::Kana as usize] * 10 >= total {
+        return Some("ja".to_string());
+    }
+
+    let dominant = [
+        Script::Latin, Script::Han, Script::Hangul, Script::Cyrillic, Script::Greek,
+        Script::Arabic, Script::Hebrew, Script::Devanagari, Script::Thai,
+    ]
+    .into_iter()
+    .max_by_key(|script| counts[*script as usize])?;
+
+    let code = match dominant {
+        Script::Latin => return Some(detect_latin_language(sample)),
+        Script::Han => "zh",
+        Script::Kana => "ja",
+        Script::Hangul => "ko",
+        Script::Cyrillic => "ru",
+        Script::Greek => "el",
+        Script::Arabic => "ar",
+        Script::Hebrew => "he",
+        Script::Devanagari => "hi",
+        Script::Thai => "th",
+    };
+    Some(code.to_string())
+}
+
+fn detect_latin_language(sample: &str) -> String {
+    let words: Vec<String> = sample
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+
+    STOP_WORDS.iter()
+        .map(|(code, stop_words)| {
+            let hits = words.iter().filter(|w| stop_words.contains(&w.as_str())).count();
+            (*code, hits)
+        })
+        // Earlier entries win ties, so English is the fallback
+        .fold(("en", 0), |best, candidate| if candidate.1 > best.1 { candidate } else { best })
+        .0
+        .to_string()
+}
+
+/// Fold case and Latin diacritics so "Straße", "STRASSE" and "strasse" compare equal
+fn fold_char(c: char, out: &mut String) {
+    match c {
+        'ß' => out.push_str("ss"),
+        'æ' | 'Æ' => out.push_str("ae"),
+        'œ' | 'Œ' => out.push_str("oe"),
+        'ø' | 'Ø' => out.push('o'),
+        'đ' | 'Đ' => out.push('d'),
+        'ł' | 'Ł' => out.push('l'),
+        _ => {
+            for lower in c.to_lowercase() {
+                out.push(strip_accent(lower));
+            }
+        }
+    }
+}
+
+fn strip_accent(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
+
+/// Split text into search tokens: folded words for spaced scripts and overlapping
+/// character bigrams for Chinese, Japanese and Thai, which do not separate words.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut run: Vec<char> = Vec::new();
+
+    let flush_word = |word: &mut String, tokens: &mut Vec<String>| {
+        if !word.is_empty() {
+            tokens.push(std::mem::take(word));
+        }
+    };
+    let flush_run = |run: &mut Vec<char>, tokens: &mut Vec<String>| {
+        match run.len() {
+            0 => {}
+            1 => tokens.push(run[0].to_string()),
+            _ => tokens.extend(run.windows(2).map(|pair| pair.iter().collect::<String>())),
+        }
+        run.clear();
+    };
+
+    for c in text.chars() {
+        if is_unspaced(c) {
+            flush_word(&mut word, &mut tokens);
+            run.push(c);
+        } else if c.is_alphanumeric() {
+            flush_run(&mut run, &mut tokens);
+            fold_char(c, &mut word);
+        } else {
+            flush_word(&mut word, &mut tokens);
+            flush_run(&mut run, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    flush_run(&mut run, &mut tokens);
+
+    tokens
+}
+
+/// Token string stored in the search index and matched against with LIKE
+pub fn search_text(text: &str) -> String {
+    tokenize(text).join(" ")
+}
+
+/// Token string for a document in `language`: the plain folding, followed by the words
+/// spelled differently with the language's transliterations, so either spelling matches
+/// without storing the text twice
+pub fn search_text_for(text: &str, language: Option<&str>) -> String {
+    let Some((_, letters)) = TRANSLITERATIONS.iter().find(|(code, _)| Some(*code) == language) else {
+        return search_text(text);
+    };
+    let tokens = tokenize(text);
+
+    let mut spelled = String::with_capacity(text.len());
+    for c in text.chars() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        match letters.iter().find(|(letter, _)| *letter == lower) {
+            Some((_, replacement)) => spelled.push_str(replacement),
+            None => spelled.push(c),
+        }
+    }
+    let mut seen: HashSet<&str> = tokens.iter().map(String::as_str).collect();
+    let transliterated = tokenize(&spelled);
+    let extra: Vec<&str> = transliterated.iter()
+        .map(String::as_str)
+        .filter(|token| seen.insert(*token))
+        .collect();
+    let mut search_text = tokens.join(" ");
+    for token in extra {
+        search_text.push(' ');
+        search_text.push_str(token);
+    }
+    search_text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("The report is in the folder with the others").as_deref(), Some("en"));
+        assert_eq!(detect_language("Das ist nicht die Rechnung, die wir mit der Post bekommen haben").as_deref(), Some("de"));
+        assert_eq!(detect_language("Le rapport est dans le dossier avec les autres").as_deref(), Some("fr"));
+        assert_eq!(detect_language("東京の会議の議事録です").as_deref(), Some("ja"));
+        assert_eq!(detect_language("会议纪要").as_deref(), Some("zh"));
+        assert_eq!(detect_language("Отчёт за квартал").as_deref(), Some("ru"));
+        assert_eq!(detect_language("1234 --- ..."), None);
+    }
+
+    #[test]
+    fn test_detect_language_from_longer_text() {
+        let swedish = "Det här är en rapport om hur vi har arbetat med projektet under året och vad vi vill göra härnäst.";
+        assert_eq!(detect_language(swedish).as_deref(), Some("sv"));
+        let polish = "To jest sprawozdanie z tego, jak pracowaliśmy nad projektem w tym roku i co chcemy zrobić dalej.";
+        assert_eq!(detect_language(polish).as_deref(), Some("pl"));
+        assert_eq!(language_name("pl"), Some("Polish"));
+        assert_eq!(language_name("xx"), None);
+    }
+
+    #[test]
+    fn test_search_text_for_adds_transliterations() {
+        assert_eq!(search_text_for("Herr Müller", Some("de")), "herr muller mueller");
+        assert_eq!(search_text_for("Müller und Müller", Some("de")), "muller und muller mueller");
+        assert_eq!(search_text_for("Herr Müller", Some("en")), "herr muller");
+        assert_eq!(search_text_for("Herr Schmidt", Some("de")), "herr schmidt");
+    }
+
+    #[test]
+    fn test_tokenize_folds_latin_words() {
+        assert_eq!(tokenize("Straße, Café & Ünïcode-2024"), vec!["strasse", "cafe", "unicode", "2024"]);
+        assert_eq!(search_text("STRASSE"), search_text("straße"));
+    }
+
+    #[test]
+    fn test_tokenize_bigrams_unspaced_scripts() {
+        assert_eq!(tokenize("東京都 report"), vec!["東京", "京都", "report"]);
+        assert_eq!(tokenize("都"), vec!["都"]);
+
+        // A query inside a longer passage yields a contiguous run of the passage's tokens
+        let document = search_text("私は東京都に住んでいます");
+        assert!(document.contains(&search_text("東京都")));
+    }
+}
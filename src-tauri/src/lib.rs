@@ -18,9 +18,22 @@ pub mod semantic_search;
 pub mod folder_vectorizer;
 pub mod vector_cache;
 pub mod vector_benchmarks;
+pub mod search_query;
+pub mod regex_search;
+pub mod temporal_query;
+pub mod search_export;
+pub mod snippets;
+pub mod rag;
+pub mod knowledge_graph;
+pub mod topic_clusters;
+pub mod query_filters;
+pub mod language;
+pub mod chunking;
+pub mod index_archive;
+pub mod paths;
 
 pub use database::Database;
-pub use file_monitor::FileMonitor;
+pub use file_monitor::{FileMonitor, WatchOptions};
 pub use ai_processor::AIProcessor;
 pub use processing_queue::ProcessingQueue;
 pub use updater::Updater;
@@ -21,11 +21,26 @@ mod semantic_search;
 mod folder_vectorizer;
 mod vector_cache;
 mod vector_benchmarks;
-
-use database::Database;
-use file_monitor::FileMonitor;
+mod search_query;
+mod regex_search;
+mod temporal_query;
+mod search_export;
+mod snippets;
+mod rag;
+mod knowledge_graph;
+mod topic_clusters;
+mod query_filters;
+mod language;
+mod chunking;
+mod index_archive;
+mod paths;
+
+use database::{ConnectionSettings, Database, SearchFilters};
+use database::jobs::{DeadJob, ProcessingAttempt};
+use file_monitor::{CategoryExclusions, LinkPolicy, PathPatterns, FileMonitor, ScanLimits, ScheduledScan, WatchOptions};
 use ai_processor::AIProcessor;
-use processing_queue::ProcessingQueue;
+use content_extractor::{ContentExtractor, ExtractionSettings};
+use processing_queue::{JobPriority, PowerPolicy, ProcessingQueue, ProcessingWindow, SchedulingPolicy, StageTimeouts, DEFAULT_QUEUE_CAPACITY};
 use updater::Updater;
 use error_reporting::ErrorReporter;
 use vector_storage::VectorStorageManager;
@@ -33,6 +48,8 @@ use semantic_search::SemanticSearchEngine;
 use folder_vectorizer::FolderVectorizer;
 use vector_cache::{VectorCache, VectorCacheConfig, CacheManager};
 use vector_benchmarks::{VectorBenchmarks, BenchmarkConfig};
+use search_query::{QueryNode, QuerySyntaxError, QUERY_GRAMMAR};
+use regex_search::{RegexScanPosition, RegexSearcher, RegexSearchConfig};
 
 #[derive(Debug)]
 pub struct AppState {
@@ -48,6 +65,10 @@ pub struct AppState {
     pub folder_vectorizer: FolderVectorizer,
     pub vector_cache: Arc<VectorCache>,
     pub benchmarks: VectorBenchmarks,
+    /// Result of the last near-duplicate clustering run, shown in insights
+    pub near_duplicates: Arc<RwLock<Option<serde_json::Value>>>,
+    /// Result of the last topic clustering run, refreshed in the background
+    pub topic_clusters: Arc<RwLock<Option<serde_json::Value>>>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -57,6 +78,13 @@ pub struct AppConfig {
     pub performance: PerformanceConfig,
     pub privacy: PrivacyConfig,
     pub ui: UIConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub monitoring: MonitoringConfig,
+    /// How files are turned into text, e.g. whether images go through OCR
+    #[serde(default)]
+    pub extraction: ExtractionSettings,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -66,6 +94,24 @@ pub struct AIConfig {
     pub enabled: bool,
     pub max_content_length: usize,
     pub timeout_seconds: u64,
+    /// Cosine similarity at which two files count as near duplicates
+    #[serde(default = "default_near_duplicate_threshold")]
+    pub near_duplicate_threshold: f32,
+    /// Rerank the top semantic results with the LLM, applied at startup
+    #[serde(default)]
+    pub enable_reranking: bool,
+    /// Ollama model used for embeddings. A multilingual model lets queries match
+    /// documents written in other languages; changing it requires re-vectorizing.
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+}
+
+fn default_embedding_model() -> String {
+    "nomic-embed-text".to_string()
+}
+
+fn default_near_duplicate_threshold() -> f32 {
+    0.97
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -74,13 +120,111 @@ pub struct PerformanceConfig {
     pub max_file_size_mb: u64,
     pub enable_background_processing: bool,
     pub adaptive_performance: bool,
+    /// Fewer workers, no AI analysis or a pause on battery power
+    #[serde(default)]
+    pub power: PowerPolicy,
+    /// Within a priority, which kinds of file are processed first
+    #[serde(default)]
+    pub scheduling: SchedulingPolicy,
+    /// How long extraction, analysis and embedding of a file may take before it is given up
+    #[serde(default)]
+    pub stage_timeouts: StageTimeouts,
+    /// Hours or idle time in which processing runs at full speed; it slows down or pauses outside
+    #[serde(default)]
+    pub processing_window: ProcessingWindow,
+    /// Jobs kept in memory at most; more wait in the database and file events slow down
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+    /// SQLite tuning, applied when the database is opened at startup
+    #[serde(default)]
+    pub database: ConnectionSettings,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PrivacyConfig {
     pub local_processing_only: bool,
+    /// Extracted content, analysis and vectors are dropped from files indexed longer ago
     pub data_retention_days: u32,
     pub anonymous_analytics: bool,
+    #[serde(default = "default_true")]
+    pub record_search_history: bool,
+    /// Days a file removed from disk stays restorable before it is purged
+    #[serde(default = "default_deleted_file_grace_days")]
+    pub deleted_file_grace_days: u32,
+}
+
+fn default_queue_capacity() -> usize {
+    DEFAULT_QUEUE_CAPACITY
+}
+
+fn default_deleted_file_grace_days() -> u32 {
+    30
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BackupConfig {
+    /// Take snapshots of the database in the background
+    pub enabled: bool,
+    pub interval_hours: u32,
+    /// Number of snapshots kept, older ones are deleted
+    pub retention_count: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            retention_count: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MonitoringConfig {
+    /// A path must see no new file events for this long before they are handled, applied at startup
+    pub event_debounce_ms: u64,
+    /// Globs for files and folders that are never indexed, e.g. `*.log` or `**/cache/**`
+    #[serde(default = "default_excluded_patterns")]
+    pub excluded_patterns: Vec<String>,
+    /// Watched folders are rescanned this often to pick up changes the watcher missed, unless
+    /// they have a schedule of their own
+    #[serde(default = "default_rescan_interval_minutes")]
+    pub rescan_interval_minutes: u32,
+    /// Cron schedules for rescanning particular watched folders, e.g. `0 2 * * *` for nightly
+    #[serde(default)]
+    pub scan_schedules: Vec<ScheduledScan>,
+    /// Whether symlinks are skipped, as by default, indexed as copies, or indexed once under their target
+    #[serde(default)]
+    pub link_policy: LinkPolicy,
+    /// Kinds of file left out of the index, e.g. video and audio or archives over a size
+    #[serde(default)]
+    pub excluded_categories: CategoryExclusions,
+}
+
+fn default_rescan_interval_minutes() -> u32 {
+    (file_monitor::DEFAULT_RESCAN_INTERVAL.as_secs() / 60) as u32
+}
+
+fn default_excluded_patterns() -> Vec<String> {
+    file_monitor::DEFAULT_EXCLUDED_PATTERNS.iter().map(|pattern| pattern.to_string()).collect()
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            event_debounce_ms: file_monitor::DEFAULT_DEBOUNCE_WINDOW.as_millis() as u64,
+            excluded_patterns: default_excluded_patterns(),
+            rescan_interval_minutes: default_rescan_interval_minutes(),
+            scan_schedules: Vec::new(),
+            link_policy: LinkPolicy::default(),
+            excluded_categories: CategoryExclusions::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -101,17 +245,28 @@ impl Default for AppConfig {
                 enabled: true,
                 max_content_length: 1_000_000, // 1MB
                 timeout_seconds: 60,
+                near_duplicate_threshold: default_near_duplicate_threshold(),
+                enable_reranking: false,
+                embedding_model: default_embedding_model(),
             },
             performance: PerformanceConfig {
                 max_concurrent_jobs: 4,
                 max_file_size_mb: 100,
                 enable_background_processing: true,
                 adaptive_performance: true,
+                power: PowerPolicy::default(),
+                scheduling: SchedulingPolicy::default(),
+                stage_timeouts: StageTimeouts::default(),
+                processing_window: ProcessingWindow::default(),
+                queue_capacity: DEFAULT_QUEUE_CAPACITY,
+                database: ConnectionSettings::default(),
             },
             privacy: PrivacyConfig {
                 local_processing_only: true,
                 data_retention_days: 365,
                 anonymous_analytics: false,
+                record_search_history: true,
+                deleted_file_grace_days: default_deleted_file_grace_days(),
             },
             ui: UIConfig {
                 theme: "auto".to_string(),
@@ -119,6 +274,9 @@ impl Default for AppConfig {
                 compact_mode: false,
                 show_file_previews: true,
             },
+            backup: BackupConfig::default(),
+            monitoring: MonitoringConfig::default(),
+            extraction: ExtractionSettings::default(),
         }
     }
 }
@@ -154,12 +312,86 @@ fn validate_config(config: &AppConfig) -> Result<(), String> {
     if config.performance.max_file_size_mb == 0 || config.performance.max_file_size_mb > 1000 {
         return Err("Max file size must be between 1MB and 1GB".to_string());
     }
+
+    let power = &config.performance.power;
+    if power.battery_workers == 0 || power.battery_workers > config.performance.max_concurrent_jobs {
+        return Err("Workers on battery must be between 1 and the max concurrent jobs".to_string());
+    }
+    if power.pause_below_percent.is_some_and(|percent| percent > 100) {
+        return Err("Battery level to pause at must be a percentage".to_string());
+    }
+    if config.performance.scheduling.large_file_mb == 0 {
+        return Err("The size from which files count as large must be at least 1MB".to_string());
+    }
+    let stage_timeouts = &config.performance.stage_timeouts;
+    if [stage_timeouts.extraction_secs, stage_timeouts.analysis_secs, stage_timeouts.embedding_secs].contains(&0)
+        || stage_timeouts.extraction_secs_by_extension.values().any(|secs| *secs == 0)
+    {
+        return Err("Stage timeouts must be at least one second".to_string());
+    }
+    if let Err(e) = config.performance.processing_window.validate() {
+        return Err(format!("Invalid processing window: {}", e));
+    }
+    if config.performance.queue_capacity < 100 || config.performance.queue_capacity > 1_000_000 {
+        return Err("Queue capacity must be between 100 and 1,000,000 jobs".to_string());
+    }
+    
+    let database = &config.performance.database;
+    if database.max_connections == 0 || database.max_connections > 64 {
+        return Err("Database pool size must be between 1 and 64 connections".to_string());
+    }
+    
+    if database.cache_size == 0 || database.cache_size.abs() > 4_000_000 {
+        return Err("Database cache size must be non-zero and at most 4,000,000 pages or KiB".to_string());
+    }
+    
+    if database.mmap_size > 64 * 1024 * 1024 * 1024 {
+        return Err("Database mmap size must be at most 64GB".to_string());
+    }
+    
+    // Checked the way the database reads it when opening
+    if database.synchronous_mode().is_err() {
+        return Err("Database synchronous mode must be 'off', 'normal', 'full' or 'extra'".to_string());
+    }
+    
+    if config.monitoring.event_debounce_ms > 60_000 {
+        return Err("File event debounce must be at most 60 seconds".to_string());
+    }
+    
+    if let Err(e) = PathPatterns::new(&config.monitoring.excluded_patterns) {
+        return Err(e.to_string());
+    }
+    
+    if config.monitoring.rescan_interval_minutes < 5 || config.monitoring.rescan_interval_minutes > 7 * 24 * 60 {
+        return Err("Rescan interval must be between 5 minutes and 7 days".to_string());
+    }
+    
+    if let Err(e) = file_monitor::parse_scan_schedules(&config.monitoring.scan_schedules) {
+        return Err(e.to_string());
+    }
     
     // Validate privacy configuration
     if config.privacy.data_retention_days == 0 || config.privacy.data_retention_days > 3650 {
         return Err("Data retention must be between 1 day and 10 years".to_string());
     }
     
+    if config.privacy.deleted_file_grace_days > 365 {
+        return Err("Deleted file grace period must be at most 365 days".to_string());
+    }
+    
+    // Validate backup configuration
+    if config.backup.interval_hours == 0 || config.backup.interval_hours > 24 * 30 {
+        return Err("Backup interval must be between 1 hour and 30 days".to_string());
+    }
+    
+    if config.backup.retention_count == 0 || config.backup.retention_count > 100 {
+        return Err("Backup retention must be between 1 and 100 snapshots".to_string());
+    }
+    
+    if let Err(e) = config.extraction.validate() {
+        return Err(format!("Invalid extraction settings: {}", e));
+    }
+    
     // Validate UI configuration
     if !["light", "dark", "auto"].contains(&config.ui.theme.as_str()) {
         return Err("Theme must be 'light', 'dark', or 'auto'".to_string());
@@ -210,7 +442,7 @@ async fn load_config_from_disk() -> Result<AppConfig, anyhow::Error> {
 }
 
 #[tauri::command]
-async fn get_system_info() -> Result<serde_json::Value, String> {
+async fn get_system_info(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     let mut sys = System::new_all();
     sys.refresh_all();
     
@@ -251,17 +483,23 @@ async fn get_system_info() -> Result<serde_json::Value, String> {
         "memory_used": memory_used,
         "disk_usage": disk_usage,
         "thermal_state": "Normal", // Could be enhanced with thermal sensors
-        "performance_profile": "Balanced"
+        "performance_profile": "Balanced",
+        "throttle_level": state.file_monitor.throttle_level()
     });
     Ok(info)
 }
 
 #[tauri::command]
-async fn start_file_monitoring(paths: Vec<String>, state: State<'_, AppState>) -> Result<(), String> {
+async fn start_file_monitoring(
+    paths: Vec<String>,
+    options: Option<WatchOptions>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
     tracing::info!("Starting file monitoring for paths: {:?}", paths);
     
+    let options = options.unwrap_or_default();
     for path in paths {
-        if let Err(e) = state.file_monitor.add_watch_path(&path).await {
+        if let Err(e) = state.file_monitor.add_watch_path(&path, options.clone()).await {
             tracing::error!("Failed to add watch path {}: {}", path, e);
             return Err(format!("Failed to add watch path {}: {}", path, e));
         }
@@ -273,234 +511,1226 @@ async fn start_file_monitoring(paths: Vec<String>, state: State<'_, AppState>) -
     }
     
     // Requeue any pending files for processing
-    if let Err(e) = state.processing_queue.lock().await.requeue_pending_files().await {
+    let priority_for = state.file_monitor.folder_priorities().await;
+    if let Err(e) = state.processing_queue.lock().await.requeue_pending_files(priority_for).await {
         tracing::error!("Failed to requeue pending files: {}", e);
     }
     
     Ok(())
 }
 
+/// Change the include and exclude patterns, size limit, priority or ignore file handling of a
+/// watched folder
 #[tauri::command]
-async fn search_files(query: String, _filters: Option<serde_json::Value>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    tracing::info!("Searching for: {}", query);
-    
-    let start_time = std::time::Instant::now();
-    
-    // Perform search in database
-    let search_results = match state.database.search_files(&query, 50, 0).await {
-        Ok(files) => files,
+async fn set_watch_path_options(
+    path: String,
+    options: WatchOptions,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let priority = options.priority.clone();
+    match state.file_monitor.set_watch_options(&path, options).await {
+        Ok(()) => {
+            reprioritize_watch_path(&path, priority, &state).await;
+            Ok(())
+        }
         Err(e) => {
-            tracing::error!("Search failed: {}", e);
-            return Err(format!("Search failed: {}", e));
+            tracing::error!("Failed to update watch path options for {}: {}", path, e);
+            Err(format!("Failed to update watch path options: {}", e))
         }
-    };
-    
-    // Convert to frontend format
-    let results: Vec<serde_json::Value> = search_results
-        .iter()
-        .map(|file| {
-            serde_json::json!({
-                "file": {
-                    "id": file.id,
-                    "path": file.path,
-                    "name": file.name,
-                    "extension": file.extension,
-                    "size": file.size,
-                    "created_at": file.created_at,
-                    "modified_at": file.modified_at,
-                    "mime_type": file.mime_type,
-                    "processing_status": file.processing_status
-                },
-                "score": 0.85, // TODO: Implement proper relevance scoring
-                "snippet": file.ai_analysis.as_ref()
-                    .map(|analysis| {
-                        if analysis.len() > 200 {
-                            format!("{}...", &analysis[..200])
-                        } else {
-                            analysis.clone()
-                        }
-                    })
-                    .unwrap_or_else(|| "No analysis available".to_string()),
-                "highlights": file.tags.as_ref()
-                    .and_then(|tags| serde_json::from_str::<Vec<String>>(tags).ok())
-                    .unwrap_or_default()
-            })
-        })
-        .collect();
-    
-    let execution_time = start_time.elapsed().as_millis();
-    
-    let response = serde_json::json!({
-        "results": results,
-        "total": results.len(),
-        "query": query,
-        "execution_time_ms": execution_time
-    });
-    
-    Ok(response)
+    }
 }
 
+/// Mark a watched folder, e.g. Desktop or Documents, as more or less urgent than the rest. Its
+/// files already waiting in the processing queue move up or down right away.
 #[tauri::command]
-async fn get_processing_status(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    match state.processing_queue.lock().await.get_statistics().await {
-        Ok(stats) => Ok(stats),
+async fn set_watch_path_priority(
+    path: String,
+    priority: JobPriority,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    match state.file_monitor.set_watch_priority(&path, priority.clone()).await {
+        Ok(()) => {
+            reprioritize_watch_path(&path, priority, &state).await;
+            Ok(())
+        }
         Err(e) => {
-            tracing::error!("Failed to get processing status: {}", e);
-            Err(format!("Failed to get processing status: {}", e))
+            tracing::error!("Failed to set priority of watch path {}: {}", path, e);
+            Err(format!("Failed to set watch path priority: {}", e))
         }
     }
 }
 
+async fn reprioritize_watch_path(path: &str, priority: JobPriority, state: &State<'_, AppState>) {
+    let folder = paths::canonicalize(std::path::Path::new(path));
+    let moved = state.processing_queue.lock().await.reprioritize_folder(&folder, priority).await;
+    if moved > 0 {
+        tracing::info!("Moved {} queued jobs of {} to the new priority", moved, folder.display());
+    }
+}
+
+/// The watched folders with their options and volume, which are restored on the next launch
 #[tauri::command]
-async fn get_processing_insights(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    match state.processing_queue.lock().await.get_processing_insights().await {
-        Ok(insights) => Ok(insights),
+async fn get_watch_paths(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let watch_paths: Vec<serde_json::Value> = state.file_monitor.watch_paths().await
+        .into_iter()
+        .map(|(path, options, volume)| serde_json::json!({
+            "path": path.to_string_lossy(),
+            "options": options,
+            "volume": volume,
+        }))
+        .collect();
+    Ok(serde_json::json!(watch_paths))
+}
+
+/// Stop watching a folder and forget it; its indexed files stay until reconciled or removed
+#[tauri::command]
+async fn remove_watch_path(path: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.file_monitor.remove_watch_path(&path).await {
+        Ok(()) => Ok(()),
         Err(e) => {
-            tracing::error!("Failed to get processing insights: {}", e);
-            Err(format!("Failed to get processing insights: {}", e))
+            tracing::error!("Failed to remove watch path {}: {}", path, e);
+            Err(format!("Failed to remove watch path: {}", e))
         }
     }
 }
 
 #[tauri::command]
-async fn get_config(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    let config = state.config.read().await;
-    Ok(serde_json::to_value(&*config).map_err(|e| e.to_string())?)
+async fn get_exclusion_patterns(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    Ok(state.config.read().await.monitoring.excluded_patterns.clone())
 }
 
+/// Replace the globs for paths that are never indexed. Files already indexed stay until removed.
 #[tauri::command]
-async fn update_config(
+async fn set_exclusion_patterns(
+    patterns: Vec<String>,
     state: State<'_, AppState>,
-    config_update: serde_json::Value,
 ) -> Result<(), String> {
-    let mut config = state.config.write().await;
-    if let Ok(new_config) = serde_json::from_value::<AppConfig>(config_update) {
-        // Validate configuration before applying
-        if let Err(e) = validate_config(&new_config) {
-            return Err(format!("Invalid configuration: {}", e));
-        }
-        
-        *config = new_config.clone();
-        
-        // Save configuration to disk
-        if let Err(e) = save_config_to_disk(&new_config).await {
-            tracing::error!("Failed to save configuration: {}", e);
-            return Err(format!("Failed to save configuration: {}", e));
-        }
-        
-        tracing::info!("Configuration updated successfully");
+    if let Err(e) = state.file_monitor.set_excluded_patterns(&patterns).await {
+        tracing::error!("Failed to set exclusion patterns: {}", e);
+        return Err(format!("Failed to set exclusion patterns: {}", e));
     }
-    Ok(())
-}
 
-#[tauri::command]
-async fn reset_config_to_defaults(state: State<'_, AppState>) -> Result<(), String> {
-    let default_config = AppConfig::default();
-    
     let mut config = state.config.write().await;
-    *config = default_config.clone();
-    
-    // Save to disk
-    if let Err(e) = save_config_to_disk(&default_config).await {
-        tracing::error!("Failed to save default configuration: {}", e);
-        return Err(format!("Failed to save default configuration: {}", e));
+    config.monitoring.excluded_patterns = state.file_monitor.excluded_patterns().await;
+    if let Err(e) = save_config_to_disk(&config).await {
+        tracing::error!("Failed to save configuration: {}", e);
+        return Err(format!("Failed to save configuration: {}", e));
     }
-    
-    tracing::info!("Configuration reset to defaults");
     Ok(())
 }
 
+/// Each watched folder with its rescan schedule, null when it uses the rescan interval, and
+/// when it is next rescanned
 #[tauri::command]
-async fn export_config(state: State<'_, AppState>) -> Result<String, String> {
-    let config = state.config.read().await;
-    match serde_json::to_string_pretty(&*config) {
-        Ok(json) => Ok(json),
-        Err(e) => Err(format!("Failed to export configuration: {}", e))
-    }
+async fn get_scan_schedules(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let schedules: Vec<serde_json::Value> = state.file_monitor.scheduled_scans().await
+        .into_iter()
+        .map(|(path, schedule, next_rescan)| serde_json::json!({
+            "path": path.to_string_lossy(),
+            "schedule": schedule,
+            "next_rescan": next_rescan.map(|time| time.to_rfc3339()),
+        }))
+        .collect();
+    Ok(serde_json::json!(schedules))
 }
 
+/// Give a watched folder its own cron rescan schedule, or with None put it back on the rescan
+/// interval
 #[tauri::command]
-async fn import_config(
+async fn set_scan_schedule(
+    path: String,
+    schedule: Option<String>,
     state: State<'_, AppState>,
-    config_json: String,
 ) -> Result<(), String> {
-    let new_config: AppConfig = serde_json::from_str(&config_json)
-        .map_err(|e| format!("Invalid configuration format: {}", e))?;
-    
-    // Validate configuration
-    if let Err(e) = validate_config(&new_config) {
-        return Err(format!("Invalid configuration: {}", e));
-    }
-    
     let mut config = state.config.write().await;
-    *config = new_config.clone();
-    
-    // Save to disk
-    if let Err(e) = save_config_to_disk(&new_config).await {
-        tracing::error!("Failed to save imported configuration: {}", e);
-        return Err(format!("Failed to save imported configuration: {}", e));
+    let folder = paths::canonicalize(std::path::Path::new(&path));
+    let mut schedules: Vec<ScheduledScan> = config.monitoring.scan_schedules.iter()
+        .filter(|scheduled| paths::canonicalize(std::path::Path::new(&scheduled.path)) != folder)
+        .cloned()
+        .collect();
+    if let Some(schedule) = schedule {
+        schedules.push(ScheduledScan { path: folder.to_string_lossy().to_string(), schedule });
     }
-    
-    tracing::info!("Configuration imported successfully");
-    Ok(())
-}
-
-#[tauri::command]
-async fn get_system_capabilities() -> Result<serde_json::Value, String> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    
-    let total_memory_gb = (sys.total_memory() as f64 / 1024.0 / 1024.0 / 1024.0).round() as u64;
-    
-    let capabilities = serde_json::json!({
-        "cpu_cores": num_cpus::get(),
-        "total_memory_gb": total_memory_gb,
-        "architecture": std::env::consts::ARCH,
-        "os": std::env::consts::OS,
-        "gpu_acceleration": false,
-        "recommended_max_threads": num_cpus::get(),
-        "supports_background_processing": true
-    });
-    Ok(capabilities)
-}
 
-#[tauri::command]
-async fn start_system_monitoring() -> Result<(), String> {
-    println!("Starting system monitoring");
-    Ok(())
-}
+    if let Err(e) = state.file_monitor.set_scan_schedules(&schedules).await {
+        tracing::error!("Failed to set scan schedule: {}", e);
+        return Err(format!("Failed to set scan schedule: {}", e));
+    }
 
-#[tauri::command]
-async fn stop_system_monitoring() -> Result<(), String> {
-    println!("Stopping system monitoring");
+    config.monitoring.scan_schedules = schedules;
+    if let Err(e) = save_config_to_disk(&config).await {
+        tracing::error!("Failed to save configuration: {}", e);
+        return Err(format!("Failed to save configuration: {}", e));
+    }
     Ok(())
 }
 
-#[tauri::command]
-async fn get_search_suggestions(partial_query: String) -> Result<Vec<String>, String> {
-    let suggestions = vec![
-        format!("{} documents", partial_query),
-        format!("{} images", partial_query),
-        format!("{} code", partial_query),
-    ];
-    Ok(suggestions)
+/// Remember an executed query for suggestions unless the user opted out. Callers record only
+/// the first page, as each recording ranks the query higher in the suggestions.
+async fn record_search_history(state: &State<'_, AppState>, query: &str) {
+    if !state.config.read().await.privacy.record_search_history {
+        return;
+    }
+    if let Err(e) = state.database.record_search_query(query).await {
+        tracing::warn!("Failed to record search history: {}", e);
+    }
 }
 
 #[tauri::command]
-async fn get_available_models(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    // Check if AI processor is available and get models
-    match state.ai_processor.get_available_models().await {
-        Ok(models) => Ok(serde_json::json!({
-            "models": models,
-            "ai_available": true
-        })),
-        Err(e) => {
-            tracing::warn!("Failed to get available models: {}", e);
-            Ok(serde_json::json!({
-                "models": [],
-                "ai_available": false,
-                "error": e.to_string()
-            }))
+async fn search_files(
+    query: String,
+    filters: Option<serde_json::Value>,
+    search_type: Option<semantic_search::SearchType>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    collection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let first_page = cursor.is_none();
+    let page = PageRequest::new(limit, cursor);
+    let mut filters = parse_search_filters(filters)?;
+    if collection_id.is_some() {
+        filters.collection_id = collection_scope(&state, collection_id).await?;
+    }
+    let started = std::time::Instant::now();
+    let stats_query = query.clone();
+    let stats_state = state.clone();
+    let (label, response) = match search_type {
+        Some(semantic_search::SearchType::Auto) => {
+            let response = routed_search(query, filters, page, state).await?;
+            // Counted under the engine the query went to
+            let route = response["route"]["route"].as_str().unwrap_or("keyword").to_string();
+            (route, response)
+        }
+        Some(semantic_search::SearchType::Regex) => {
+            if first_page {
+                record_search_history(&state, &query).await;
+            }
+            ("regex".to_string(), regex_file_search(query, filters, page, state).await?)
+        }
+        _ => {
+            if first_page {
+                record_search_history(&state, &query).await;
+            }
+            ("keyword".to_string(), keyword_search(query, filters, page, state).await?)
+        }
+    };
+    if first_page {
+        record_search_stats(&stats_state, &stats_query, &label, started, &response).await;
+    }
+    Ok(response)
+}
+
+/// Add a search to the local analytics; the query text is withheld when history is off. Only
+/// the first page of a search is added, so paging through results does not count it again.
+async fn record_search_stats(
+    state: &State<'_, AppState>,
+    query: &str,
+    search_type: &str,
+    started: std::time::Instant,
+    response: &serde_json::Value,
+) {
+    // Rejected queries never ran
+    if response.get("syntax_error").is_some() {
+        return;
+    }
+    let (record_query, retention_days) = {
+        let config = state.config.read().await;
+        (config.privacy.record_search_history, config.privacy.data_retention_days)
+    };
+    let result_count = response["total"].as_i64()
+        .or_else(|| response["results"].as_array().map(|results| results.len() as i64))
+        .unwrap_or(0);
+    let latency_ms = started.elapsed().as_millis() as i64;
+
+    if let Err(e) = state.database
+        .record_search_stat(record_query.then_some(query), search_type, result_count, latency_ms, retention_days)
+        .await
+    {
+        tracing::warn!("Failed to record search statistics: {}", e);
+    }
+}
+
+/// Send the query to the engine that suits it and report the chosen route
+async fn routed_search(query: String, filters: SearchFilters, page: PageRequest, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    // Inline filters travel with the query and are honoured by every route
+    let intent = match query_filters::extract(&query) {
+        Ok(inline) if filters.is_scope_only() => semantic_search::classify_query(&inline.remaining_query),
+        Ok(_) => semantic_search::QueryIntent {
+            route: semantic_search::SearchRoute::Keyword,
+            reason: "structured filters are only supported by keyword search".to_string(),
+        },
+        Err(_) => semantic_search::QueryIntent {
+            route: semantic_search::SearchRoute::Keyword,
+            reason: "the query has invalid filter syntax".to_string(),
+        },
+    };
+    tracing::debug!("Routing query {:?} to {:?}: {}", query, intent.route, intent.reason);
+    
+    // The semantic and hybrid commands record history themselves
+    let mut response = match intent.route {
+        semantic_search::SearchRoute::Keyword => {
+            if page.cursor.is_none() {
+                record_search_history(&state, &query).await;
+            }
+            keyword_search(query, filters, page, state).await?
+        }
+        semantic_search::SearchRoute::Semantic => {
+            semantic_search_response(query, Some(page.limit), page.cursor, filters.collection_id, state).await?
+        }
+        semantic_search::SearchRoute::Hybrid => {
+            hybrid_search_response(query, Some(page.limit), page.cursor, filters.collection_id, None, None, state).await?
+        }
+    };
+    
+    if let Some(object) = response.as_object_mut() {
+        object.insert("route".to_string(), serde_json::json!(intent));
+    }
+    Ok(response)
+}
+
+/// Page size and position requested by the frontend
+struct PageRequest {
+    limit: i64,
+    cursor: Option<String>,
+}
+
+impl PageRequest {
+    const DEFAULT_LIMIT: i64 = 50;
+    const MAX_LIMIT: i64 = 500;
+
+    fn new(limit: Option<i64>, cursor: Option<String>) -> Self {
+        Self {
+            limit: limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT),
+            cursor: cursor.filter(|c| !c.is_empty()),
+        }
+    }
+
+    fn page_cursor(&self) -> Result<Option<PageCursor>, String> {
+        self.cursor.as_deref().map(PageCursor::decode).transpose()
+    }
+}
+
+/// Where the next page of a search starts. Keyword, regex and vector searches page differently, so
+/// the cursor is tagged with the engine that produced the page, and a search that fell back
+/// from one to the other keeps paging through the engine that served its first page.
+#[derive(Debug, Clone, PartialEq)]
+enum PageCursor {
+    /// Keyset position in the keyword search ordering
+    Keyword(String),
+    /// Offset into a vector ranking kept in the search cache under `session`
+    Ranked { session: String, offset: usize },
+    /// Scan position of a regex search
+    Regex(RegexScanPosition),
+}
+
+impl PageCursor {
+    fn encode(&self) -> String {
+        match self {
+            PageCursor::Keyword(position) => format!("keyword:{}", position),
+            PageCursor::Ranked { session, offset } => format!("ranked:{}:{}", session, offset),
+            PageCursor::Regex(position) => format!("regex:{}:{}", position.offset, position.matched),
+        }
+    }
+
+    fn decode(cursor: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid search cursor: {}", cursor);
+        match cursor.split_once(':') {
+            Some(("keyword", position)) => Ok(PageCursor::Keyword(position.to_string())),
+            Some(("ranked", ranking)) => {
+                let (session, offset) = ranking.rsplit_once(':').ok_or_else(invalid)?;
+                Ok(PageCursor::Ranked {
+                    session: session.to_string(),
+                    offset: offset.parse().map_err(|_| invalid())?,
+                })
+            }
+            Some(("regex", position)) => {
+                let (offset, matched) = position.split_once(':').ok_or_else(invalid)?;
+                Ok(PageCursor::Regex(RegexScanPosition {
+                    offset: offset.parse().map_err(|_| invalid())?,
+                    matched: matched.parse().map_err(|_| invalid())?,
+                }))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Error for a cursor produced by a different search engine than the one continuing it
+const FOREIGN_CURSOR_ERROR: &str = "The cursor belongs to a different search; run the search again";
+
+/// Number of ranked candidates a vector search scores; the first page ranks them once and
+/// later pages are sliced from the ranking kept in the search cache
+const VECTOR_SEARCH_WINDOW: usize = 1000;
+
+/// Largest window a scoped vector search widens to while filling the requested page
+const MAX_VECTOR_SEARCH_WINDOW: usize = 16 * VECTOR_SEARCH_WINDOW;
+
+/// Run a vector search and drop hits outside `scope`, searching a wider window while the
+/// scope leaves fewer than `needed` hits and the engine filled the last one. The outer error
+/// is a failure to scope, the inner one a failure of the engine.
+async fn scoped_vector_search(
+    state: &State<'_, AppState>,
+    mut request: semantic_search::SearchRequest,
+    scope: &SearchFilters,
+    needed: usize,
+) -> Result<anyhow::Result<semantic_search::SearchResponse>, String> {
+    let mut window = VECTOR_SEARCH_WINDOW;
+    loop {
+        request.limit = Some(window);
+        let mut response = match state.semantic_search.search(request.clone()).await {
+            Ok(response) => response,
+            Err(e) => return Ok(Err(e)),
+        };
+        retain_in_scope(state, scope, &mut response.results).await?;
+
+        if response.truncated && response.results.len() < needed && window < MAX_VECTOR_SEARCH_WINDOW {
+            window *= 4;
+            continue;
+        }
+        // Short of a full window, what the scope kept is the whole answer
+        if !response.truncated {
+            response.total_results = response.results.len();
+        }
+        return Ok(Ok(response));
+    }
+}
+
+/// Respond with one page of a vector ranking, the whole ranking having been scoped already
+async fn ranked_search_page(
+    state: &State<'_, AppState>,
+    page: &PageRequest,
+    session: String,
+    offset: usize,
+    ranking: &semantic_search::SearchResponse,
+    search_type: &str,
+    interpreted_dates: Option<serde_json::Value>,
+) -> serde_json::Value {
+    // Facets describe the whole ranking, not just the current page
+    let file_ids: Vec<String> = ranking.results.iter().map(|r| r.file_id.clone()).collect();
+    let total = ranking.results.len();
+    let total_is_lower_bound = ranking.truncated;
+    let start = offset.min(total);
+    let end = (start + page.limit as usize).min(total);
+    let next_cursor = (end < total).then(|| PageCursor::Ranked { session, offset: end }.encode());
+
+    let results: Vec<serde_json::Value> = ranking.results[start..end]
+        .iter()
+        .map(|result| {
+            serde_json::json!({
+                "file": {
+                    "id": result.file_id,
+                    "path": result.file_path,
+                    "name": result.file_name,
+                    "extension": std::path::Path::new(&result.file_path)
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .unwrap_or(""),
+                    "size": 0, // TODO: Get from metadata
+                    "created_at": result.last_modified,
+                    "modified_at": result.last_modified,
+                    "mime_type": "", // TODO: Get from metadata
+                    "processing_status": "completed"
+                },
+                "score": result.similarity_score,
+                "snippet": result.snippet.as_ref().unwrap_or(&format!("Match in {}", result.file_name)),
+                "snippet_range": result.metadata.get("best_chunk")
+                    .map(|chunk| serde_json::json!({ "start": chunk["start"], "end": chunk["end"] })),
+                "highlights": result.highlights,
+                "search_type": search_type
+            })
+        })
+        .collect();
+
+    let facets = state.database.get_facets_for_files(&file_ids).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to compute search facets: {}", e);
+        Default::default()
+    });
+
+    serde_json::json!({
+        "results": results,
+        "total": total,
+        "total_is_lower_bound": total_is_lower_bound,
+        "total_label": if total_is_lower_bound { format!("{}+", total) } else { total.to_string() },
+        "next_cursor": next_cursor,
+        "query": ranking.query,
+        "execution_time_ms": ranking.search_time_ms,
+        "search_type": search_type,
+        "expanded_query": ranking.expanded_query,
+        "suggestions": ranking.suggestions,
+        "facets": facets,
+        "interpreted_date_range": interpreted_dates
+    })
+}
+
+/// Keep a scoped vector ranking for the following pages and return its session
+async fn store_ranking(state: &State<'_, AppState>, ranking: &semantic_search::SearchResponse) -> String {
+    let session = uuid::Uuid::new_v4().simple().to_string();
+    state.vector_cache.store_search_result(&format!("ranked:{}", session), ranking.clone()).await;
+    session
+}
+
+/// A ranking kept by `store_ranking`, gone once the search cache expires it
+async fn stored_ranking(state: &State<'_, AppState>, session: &str) -> Option<semantic_search::SearchResponse> {
+    state.vector_cache.get_search_result(&format!("ranked:{}", session)).await
+}
+
+/// Validate a collection id passed to a search command
+async fn collection_scope(state: &State<'_, AppState>, collection_id: Option<String>) -> Result<Option<String>, String> {
+    let Some(collection_id) = collection_id else {
+        return Ok(None);
+    };
+
+    match state.database.get_collection_by_id(&collection_id).await {
+        Ok(Some(_)) => Ok(Some(collection_id)),
+        Ok(None) => Err(format!("Collection not found: {}", collection_id)),
+        Err(e) => Err(format!("Failed to get collection: {}", e)),
+    }
+}
+
+/// Drop vector search hits outside the requested collection, date range or inline filters
+async fn retain_in_scope(
+    state: &State<'_, AppState>,
+    scope: &SearchFilters,
+    results: &mut Vec<semantic_search::SearchResult>,
+) -> Result<(), String> {
+    if scope.collection_id.is_none() && scope.is_scope_only() {
+        return Ok(());
+    }
+
+    let candidate_ids: Vec<String> = results.iter().map(|r| r.file_id.clone()).collect();
+    let members: std::collections::HashSet<String> = state.database
+        .file_ids_matching(&candidate_ids, &QueryNode::And(Vec::new()), scope)
+        .await
+        .map_err(|e| format!("Failed to scope search results: {}", e))?
+        .into_iter()
+        .collect();
+
+    results.retain(|r| members.contains(&r.file_id));
+    Ok(())
+}
+
+fn local_midnight(date: chrono::NaiveDate) -> Option<chrono::DateTime<chrono::Utc>> {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|dt| dt.and_local_timezone(chrono::Local).earliest())
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
+
+/// No extension holds a path separator, so this stands in for an empty extension filter
+const NO_EXTENSION: &str = "/";
+
+/// Move inline filters and a date phrase from the query into `filters`, narrowing whatever
+/// the UI already set. Returns the remaining query and a description of the interpreted range.
+fn apply_query_filters(query: &str, filters: &mut SearchFilters) -> Result<(String, Option<serde_json::Value>), QuerySyntaxError> {
+    let ui_dates = filters.date_range.is_some();
+    let search_text = apply_inline_filters(query, filters)?;
+    Ok(apply_temporal_phrase(&search_text, filters, ui_dates))
+}
+
+/// Move inline filters such as `ext:pdf` or `size:>10mb` from the query into `filters`,
+/// narrowing whatever the UI already set.
+fn apply_inline_filters(query: &str, filters: &mut SearchFilters) -> Result<String, QuerySyntaxError> {
+    let inline = query_filters::extract(query)?;
+    if inline.is_empty() {
+        return Ok(query.to_string());
+    }
+
+    // Extensions match any of the list, so narrowing keeps only those both sides allow
+    if !inline.extensions.is_empty() {
+        if filters.extensions.is_empty() {
+            filters.extensions = inline.extensions;
+        } else {
+            filters.extensions.retain(|extension| {
+                inline.extensions.contains(&extension.trim_start_matches('.').to_lowercase())
+            });
+            if filters.extensions.is_empty() {
+                filters.extensions.push(NO_EXTENSION.to_string());
+            }
+        }
+    }
+    // Tags must all match already
+    for tag in inline.tags {
+        if !filters.tags.contains(&tag) {
+            filters.tags.push(tag);
+        }
+    }
+
+    if inline.min_size.is_some() || inline.max_size.is_some() {
+        let size_range = filters.size_range.get_or_insert_with(Default::default);
+        size_range.min = size_range.min.max(inline.min_size);
+        size_range.max = match (size_range.max, inline.max_size) {
+            (Some(ui), Some(typed)) => Some(ui.min(typed)),
+            (ui, typed) => ui.or(typed),
+        };
+    }
+
+    if inline.after.is_some() || inline.before.is_some() {
+        // The filter's end is inclusive, `before` names the first day excluded
+        let end = inline.before
+            .and_then(local_midnight)
+            .map(|dt| dt - chrono::Duration::nanoseconds(1));
+        narrow_date_range(filters, inline.after.and_then(local_midnight), end, None);
+    }
+
+    Ok(inline.remaining_query)
+}
+
+/// Narrow `filters.date_range` to the given bounds, keeping the later start and earlier end
+fn narrow_date_range(
+    filters: &mut SearchFilters,
+    start: Option<chrono::DateTime<chrono::Utc>>,
+    end: Option<chrono::DateTime<chrono::Utc>>,
+    field: Option<database::DateField>,
+) {
+    let date_range = filters.date_range.get_or_insert_with(Default::default);
+    date_range.start = date_range.start.max(start);
+    date_range.end = match (date_range.end, end) {
+        (Some(current), Some(end)) => Some(current.min(end)),
+        (current, end) => current.or(end),
+    };
+    if let Some(field) = field {
+        date_range.field = field;
+    }
+}
+
+/// Move a date phrase such as "last March" from the query into `filters.date_range`.
+/// Returns the remaining query and a description of the interpreted range.
+fn apply_temporal_phrase(query: &str, filters: &mut SearchFilters, ui_dates: bool) -> (String, Option<serde_json::Value>) {
+    // An explicit date filter from the UI wins over anything typed
+    if ui_dates {
+        return (query.to_string(), None);
+    }
+
+    let Some(phrase) = temporal_query::interpret(query, chrono::Local::now().date_naive()) else {
+        return (query.to_string(), None);
+    };
+
+    let start = phrase.start.and_then(local_midnight);
+    // Range ends are exclusive days, the filter is inclusive
+    let end = phrase.end.and_then(local_midnight).map(|dt| dt - chrono::Duration::nanoseconds(1));
+
+    // Inline `after:`/`before:` filters and the phrase both apply
+    narrow_date_range(filters, start, end, Some(phrase.field));
+
+    let interpreted = serde_json::json!({
+        "phrase": phrase.phrase,
+        "field": phrase.field,
+        "start": start,
+        "end": end
+    });
+    (phrase.remaining_query, Some(interpreted))
+}
+
+fn parse_search_filters(filters: Option<serde_json::Value>) -> Result<SearchFilters, String> {
+    match filters {
+        Some(value) if !value.is_null() => serde_json::from_value::<SearchFilters>(value)
+            .map_err(|e| format!("Invalid search filters: {}", e)),
+        _ => Ok(SearchFilters::default()),
+    }
+}
+
+async fn regex_file_search(pattern: String, filters: SearchFilters, page: PageRequest, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    tracing::info!("Regex search for: {}", pattern);
+    
+    let start_time = std::time::Instant::now();
+    
+    let searcher = RegexSearcher::new(&pattern, RegexSearchConfig::default())
+        .map_err(|e| e.to_string())?;
+    let start = match page.page_cursor()? {
+        Some(PageCursor::Regex(position)) => position,
+        Some(_) => return Err(FOREIGN_CURSOR_ERROR.to_string()),
+        None => RegexScanPosition::default(),
+    };
+    let outcome = searcher.search(&state.database, &filters, start, page.limit as usize).await
+        .map_err(|e| {
+            tracing::error!("Regex search failed: {}", e);
+            format!("Regex search failed: {}", e)
+        })?;
+    
+    let results: Vec<serde_json::Value> = outcome.hits
+        .iter()
+        .map(|hit| {
+            let file = &hit.file;
+            serde_json::json!({
+                "file": {
+                    "id": file.id,
+                    "path": file.path,
+                    "name": file.name,
+                    "extension": file.extension,
+                    "size": file.size,
+                    "created_at": file.created_at,
+                    "modified_at": file.modified_at,
+                    "mime_type": file.mime_type,
+                    "processing_status": file.processing_status
+                },
+                "score": 1.0,
+                "snippet": hit.matches.iter()
+                    .map(|m| m.text.clone())
+                    .collect::<Vec<_>>()
+                    .join(" … "),
+                "highlights": hit.matches.iter().map(|m| m.text.clone()).collect::<Vec<_>>(),
+                "matches": hit.matches,
+                "search_type": "regex"
+            })
+        })
+        .collect();
+    
+    // Counting every match would mean scanning every file, so totals short of the end are lower bounds
+    let total = outcome.total(start);
+    let total_is_lower_bound = outcome.next.is_some();
+    
+    Ok(serde_json::json!({
+        "results": results,
+        "total": total,
+        "total_is_lower_bound": total_is_lower_bound,
+        "total_label": if total_is_lower_bound { format!("{}+", total) } else { total.to_string() },
+        "next_cursor": outcome.next.map(|position| PageCursor::Regex(position).encode()),
+        "query": pattern,
+        "execution_time_ms": start_time.elapsed().as_millis(),
+        "search_type": "regex",
+        "files_scanned": outcome.files_scanned,
+        "timed_out": outcome.timed_out
+    }))
+}
+
+/// Empty result set carrying the parse error and grammar so the UI can explain it
+fn syntax_error_response(query: &str, error: &QuerySyntaxError) -> serde_json::Value {
+    serde_json::json!({
+        "results": [],
+        "total": 0,
+        "next_cursor": null,
+        "query": query,
+        "execution_time_ms": 0,
+        "syntax_error": error,
+        "grammar": QUERY_GRAMMAR,
+        "filter_syntax": query_filters::FILTER_SYNTAX
+    })
+}
+
+/// Maximum snippet length in characters, excluding ellipses
+const SNIPPET_LENGTH: usize = 200;
+
+async fn keyword_search(query: String, mut filters: SearchFilters, page: PageRequest, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    tracing::info!("Searching for: {}", query);
+    
+    let start_time = std::time::Instant::now();
+    
+    let (search_text, interpreted_dates) = match apply_query_filters(&query, &mut filters) {
+        Ok(applied) => applied,
+        Err(e) => return Ok(syntax_error_response(&query, &e)),
+    };
+    let parsed_query = match QueryNode::parse(&search_text) {
+        Ok(node) => node,
+        Err(e) => return Ok(syntax_error_response(&query, &e)),
+    };
+    
+    let position = match page.page_cursor()? {
+        Some(PageCursor::Keyword(position)) => Some(position),
+        // A vector search's ranking expired and the engine is no longer available to rank again
+        Some(PageCursor::Ranked { .. }) => return Err("The search results have expired; run the search again".to_string()),
+        Some(PageCursor::Regex(_)) => return Err(FOREIGN_CURSOR_ERROR.to_string()),
+        None => None,
+    };
+    
+    // Perform search in database
+    let search_page = match state.database.search_files_page(&parsed_query, &filters, page.limit, position.as_deref()).await {
+        Ok(search_page) => search_page,
+        Err(e) => {
+            tracing::error!("Search failed: {}", e);
+            return Err(format!("Search failed: {}", e));
+        }
+    };
+    
+    let terms = parsed_query.positive_terms();
+    
+    // Convert to frontend format
+    let results: Vec<serde_json::Value> = search_page.files
+        .iter()
+        .map(|file| {
+            let snippet = file.content.as_deref()
+                .filter(|content| !content.trim().is_empty())
+                .or(file.ai_analysis.as_deref())
+                .map(|text| snippets::build_snippet(text, &terms, SNIPPET_LENGTH));
+            let tags = file.tags.as_ref()
+                .and_then(|tags| serde_json::from_str::<Vec<String>>(tags).ok())
+                .unwrap_or_default();
+            let mut highlights = snippet.as_ref()
+                .map(|snippet| snippet.matched_terms.clone())
+                .unwrap_or_default();
+            highlights.extend(tags);
+            
+            serde_json::json!({
+                "file": {
+                    "id": file.id,
+                    "path": file.path,
+                    "name": file.name,
+                    "extension": file.extension,
+                    "size": file.size,
+                    "created_at": file.created_at,
+                    "modified_at": file.modified_at,
+                    "mime_type": file.mime_type,
+                    "processing_status": file.processing_status
+                },
+                "score": 0.85, // TODO: Implement proper relevance scoring
+                "snippet": snippet.as_ref()
+                    .map(|snippet| snippet.text.clone())
+                    .unwrap_or_else(|| "No analysis available".to_string()),
+                "snippet_highlights": snippet.map(|snippet| snippet.highlights).unwrap_or_default(),
+                "highlights": highlights
+            })
+        })
+        .collect();
+    
+    let facets = match state.database.get_search_facets(&parsed_query, &filters).await {
+        Ok(facets) => facets,
+        Err(e) => {
+            tracing::warn!("Failed to compute search facets: {}", e);
+            Default::default()
+        }
+    };
+    
+    let execution_time = start_time.elapsed().as_millis();
+    
+    let response = serde_json::json!({
+        "results": results,
+        "total": search_page.total,
+        "next_cursor": search_page.next_cursor.map(|position| PageCursor::Keyword(position).encode()),
+        "query": query,
+        "execution_time_ms": execution_time,
+        "facets": facets,
+        "interpreted_date_range": interpreted_dates
+    });
+    
+    Ok(response)
+}
+
+/// Re-run a keyword search without the page cap and write every hit to a CSV or JSON file
+#[tauri::command]
+async fn export_search_results(
+    query: String,
+    filters: Option<serde_json::Value>,
+    output_path: String,
+    format: Option<search_export::ExportFormat>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let output = std::path::PathBuf::from(&output_path);
+    let format = format
+        .or_else(|| search_export::ExportFormat::from_path(&output))
+        .ok_or_else(|| "Export format must be csv or json".to_string())?;
+
+    let mut filters = parse_search_filters(filters)?;
+    let (search_text, _) = apply_query_filters(&query, &mut filters).map_err(|e| format!("Invalid search query: {}", e))?;
+    let parsed_query = QueryNode::parse(&search_text).map_err(|e| format!("Invalid search query: {}", e))?;
+
+    let rows = search_export::export_search_results(&state.database, &parsed_query, &filters, format, &output)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to export search results: {}", e);
+            format!("Failed to export search results: {}", e)
+        })?;
+
+    tracing::info!("Exported {} search results to {}", rows, output_path);
+    Ok(serde_json::json!({
+        "path": output_path,
+        "format": format,
+        "rows": rows
+    }))
+}
+
+#[tauri::command]
+async fn get_processing_status(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.processing_queue.lock().await.get_statistics().await {
+        Ok(mut stats) => {
+            stats["monitoring_state"] = serde_json::json!(state.file_monitor.monitoring_state());
+            Ok(stats)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get processing status: {}", e);
+            Err(format!("Failed to get processing status: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_processing_insights(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let watch_paths: Vec<std::path::PathBuf> = state.file_monitor.watch_paths().await
+        .into_iter()
+        .map(|(path, _, _)| path)
+        .collect();
+    match state.processing_queue.lock().await.get_processing_insights(&watch_paths).await {
+        Ok(insights) => Ok(insights),
+        Err(e) => {
+            tracing::error!("Failed to get processing insights: {}", e);
+            Err(format!("Failed to get processing insights: {}", e))
+        }
+    }
+}
+
+/// Process the given files, e.g. those shown in the UI, before the background backlog.
+/// Returns how many queued jobs moved up.
+#[tauri::command]
+async fn prioritize_files(file_ids: Vec<String>, state: State<'_, AppState>) -> Result<usize, String> {
+    let moved = state.processing_queue.lock().await.prioritize_files(&file_ids).await;
+    if moved > 0 {
+        tracing::debug!("Moved {} queued jobs to critical priority", moved);
+    }
+    Ok(moved)
+}
+
+/// The OCR language packs installed, to pick from for the extraction settings
+#[tauri::command]
+async fn get_ocr_languages(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let settings = state.config.read().await.extraction.clone();
+    ContentExtractor::installed_ocr_languages(&settings).await.map_err(|e| {
+        tracing::error!("Failed to list OCR languages: {}", e);
+        format!("Failed to list OCR languages: {}", e)
+    })
+}
+
+/// Jobs that failed on their last retry, with the stage they failed in and the full error
+#[tauri::command]
+async fn get_dead_jobs(state: State<'_, AppState>) -> Result<Vec<DeadJob>, String> {
+    state.processing_queue.lock().await.get_dead_jobs().await.map_err(|e| {
+        tracing::error!("Failed to get failed jobs: {}", e);
+        format!("Failed to get failed jobs: {}", e)
+    })
+}
+
+/// Process failed jobs again from the start; all of them when `ids` is empty
+#[tauri::command]
+async fn retry_dead_jobs(ids: Vec<String>, state: State<'_, AppState>) -> Result<usize, String> {
+    state.processing_queue.lock().await.retry_dead_jobs(&ids).await.map_err(|e| {
+        tracing::error!("Failed to retry failed jobs: {}", e);
+        format!("Failed to retry failed jobs: {}", e)
+    })
+}
+
+/// Forget failed jobs, leaving their files in error; every one of them only when `all` is set
+#[tauri::command]
+async fn dismiss_dead_jobs(ids: Vec<String>, all: Option<bool>, state: State<'_, AppState>) -> Result<u64, String> {
+    state.processing_queue.lock().await.dismiss_dead_jobs(&ids, all.unwrap_or(false)).await.map_err(|e| {
+        tracing::error!("Failed to dismiss failed jobs: {}", e);
+        format!("Failed to dismiss failed jobs: {}", e)
+    })
+}
+
+/// Every processing attempt for a file, most recent first, with stage timings, model and result
+#[tauri::command]
+async fn get_processing_history(file_id: String, state: State<'_, AppState>) -> Result<Vec<ProcessingAttempt>, String> {
+    state.database.get_processing_history(&file_id).await.map_err(|e| {
+        tracing::error!("Failed to get processing history: {}", e);
+        format!("Failed to get processing history: {}", e)
+    })
+}
+
+#[tauri::command]
+async fn get_config(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let config = state.config.read().await;
+    Ok(serde_json::to_value(&*config).map_err(|e| e.to_string())?)
+}
+
+/// Bring the running monitor, queue and history in line with a configuration replacing `previous`
+async fn apply_config(state: &State<'_, AppState>, previous: &AppConfig, new_config: &AppConfig) {
+    // Turning history off also forgets what was already recorded
+    if previous.privacy.record_search_history && !new_config.privacy.record_search_history {
+        if let Err(e) = state.database.clear_search_history().await {
+            tracing::warn!("Failed to clear search history: {}", e);
+        }
+    }
+    
+    if let Err(e) = state.file_monitor.set_excluded_patterns(&new_config.monitoring.excluded_patterns).await {
+        tracing::warn!("Failed to apply exclusion patterns: {}", e);
+    }
+    state.file_monitor.set_rescan_interval(rescan_interval(&new_config.monitoring)).await;
+    if let Err(e) = state.file_monitor.set_scan_schedules(&new_config.monitoring.scan_schedules).await {
+        tracing::warn!("Failed to apply scan schedules: {}", e);
+    }
+    state.file_monitor.set_link_policy(new_config.monitoring.link_policy).await;
+    state.file_monitor.set_category_exclusions(new_config.monitoring.excluded_categories).await;
+    state.file_monitor.set_adaptive_throttling(new_config.performance.adaptive_performance);
+    
+    let processing_queue = state.processing_queue.lock().await;
+    processing_queue.set_max_concurrent_jobs(new_config.performance.max_concurrent_jobs);
+    processing_queue.set_adaptive_scaling(new_config.performance.adaptive_performance);
+    processing_queue.set_power_policy(new_config.performance.power.clone());
+    processing_queue.set_scheduling_policy(new_config.performance.scheduling.clone()).await;
+    processing_queue.set_stage_timeouts(new_config.performance.stage_timeouts.clone());
+    processing_queue.set_queue_capacity(new_config.performance.queue_capacity);
+    processing_queue.set_processing_window(new_config.performance.processing_window.clone());
+    processing_queue.set_extraction_settings(new_config.extraction.clone());
+}
+
+#[tauri::command]
+async fn update_config(
+    state: State<'_, AppState>,
+    config_update: serde_json::Value,
+) -> Result<(), String> {
+    let mut config = state.config.write().await;
+    if let Ok(new_config) = serde_json::from_value::<AppConfig>(config_update) {
+        // Validate configuration before applying
+        if let Err(e) = validate_config(&new_config) {
+            return Err(format!("Invalid configuration: {}", e));
+        }
+        
+        apply_config(&state, &config, &new_config).await;
+        
+        *config = new_config.clone();
+        
+        // Save configuration to disk
+        if let Err(e) = save_config_to_disk(&new_config).await {
+            tracing::error!("Failed to save configuration: {}", e);
+            return Err(format!("Failed to save configuration: {}", e));
+        }
+        
+        tracing::info!("Configuration updated successfully");
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn reset_config_to_defaults(state: State<'_, AppState>) -> Result<(), String> {
+    let default_config = AppConfig::default();
+    
+    let mut config = state.config.write().await;
+    apply_config(&state, &config, &default_config).await;
+    *config = default_config.clone();
+    
+    // Save to disk
+    if let Err(e) = save_config_to_disk(&default_config).await {
+        tracing::error!("Failed to save default configuration: {}", e);
+        return Err(format!("Failed to save default configuration: {}", e));
+    }
+    
+    tracing::info!("Configuration reset to defaults");
+    Ok(())
+}
+
+#[tauri::command]
+async fn export_config(state: State<'_, AppState>) -> Result<String, String> {
+    let config = state.config.read().await;
+    match serde_json::to_string_pretty(&*config) {
+        Ok(json) => Ok(json),
+        Err(e) => Err(format!("Failed to export configuration: {}", e))
+    }
+}
+
+#[tauri::command]
+async fn import_config(
+    state: State<'_, AppState>,
+    config_json: String,
+) -> Result<(), String> {
+    let new_config: AppConfig = serde_json::from_str(&config_json)
+        .map_err(|e| format!("Invalid configuration format: {}", e))?;
+    
+    // Validate configuration
+    if let Err(e) = validate_config(&new_config) {
+        return Err(format!("Invalid configuration: {}", e));
+    }
+    
+    let mut config = state.config.write().await;
+    apply_config(&state, &config, &new_config).await;
+    *config = new_config.clone();
+    
+    // Save to disk
+    if let Err(e) = save_config_to_disk(&new_config).await {
+        tracing::error!("Failed to save imported configuration: {}", e);
+        return Err(format!("Failed to save imported configuration: {}", e));
+    }
+    
+    tracing::info!("Configuration imported successfully");
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_system_capabilities() -> Result<serde_json::Value, String> {
+    let mut sys = System::new_all();
+    sys.refresh_all();
+    
+    let total_memory_gb = (sys.total_memory() as f64 / 1024.0 / 1024.0 / 1024.0).round() as u64;
+    
+    let capabilities = serde_json::json!({
+        "cpu_cores": num_cpus::get(),
+        "total_memory_gb": total_memory_gb,
+        "architecture": std::env::consts::ARCH,
+        "os": std::env::consts::OS,
+        "gpu_acceleration": false,
+        "recommended_max_threads": num_cpus::get(),
+        "supports_background_processing": true
+    });
+    Ok(capabilities)
+}
+
+#[tauri::command]
+async fn start_system_monitoring() -> Result<(), String> {
+    println!("Starting system monitoring");
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_system_monitoring() -> Result<(), String> {
+    println!("Stopping system monitoring");
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_search_suggestions(partial_query: String, state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    const MAX_SUGGESTIONS: usize = 10;
+    const MAX_HISTORY_SUGGESTIONS: i64 = 4;
+
+    // Past queries the user completed come first, the index fills the rest
+    let mut suggestions = if state.config.read().await.privacy.record_search_history {
+        state.database.get_search_history_suggestions(&partial_query, MAX_HISTORY_SUGGESTIONS).await
+            .map_err(|e| format!("Failed to get search suggestions: {}", e))?
+    } else {
+        Vec::new()
+    };
+
+    let from_index = state.database.get_index_suggestions(&partial_query, MAX_SUGGESTIONS).await
+        .map_err(|e| {
+            tracing::error!("Failed to get index suggestions: {}", e);
+            format!("Failed to get search suggestions: {}", e)
+        })?;
+
+    for suggestion in from_index {
+        if suggestions.len() >= MAX_SUGGESTIONS {
+            break;
+        }
+        if !suggestions.iter().any(|s| s.eq_ignore_ascii_case(&suggestion.text)) {
+            suggestions.push(suggestion.text);
+        }
+    }
+
+    Ok(suggestions)
+}
+
+#[tauri::command]
+async fn get_search_analytics(limit: Option<i64>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let limit = limit.unwrap_or(20).clamp(1, 200);
+    
+    let privacy = state.config.read().await.privacy.clone();
+    
+    match state.database.get_search_analytics(limit).await {
+        Ok(analytics) => Ok(serde_json::json!({
+            "total_searches": analytics.total_searches,
+            "top_queries": analytics.top_queries,
+            "latency_by_type": analytics.latency_by_type,
+            "zero_result_queries": analytics.zero_result_queries,
+            "query_text_recorded": privacy.record_search_history,
+            "retention_days": privacy.data_retention_days
+        })),
+        Err(e) => {
+            tracing::error!("Failed to get search analytics: {}", e);
+            Err(format!("Failed to get search analytics: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn clear_search_history(state: State<'_, AppState>) -> Result<u64, String> {
+    match state.database.clear_search_history().await {
+        Ok(removed) => {
+            tracing::info!("Cleared {} search history entries", removed);
+            Ok(removed)
+        }
+        Err(e) => {
+            tracing::error!("Failed to clear search history: {}", e);
+            Err(format!("Failed to clear search history: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn ask_files(
+    question: String,
+    top_k: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    tracing::info!("Answering question over indexed files: {}", question);
+    
+    if !state.ai_processor.is_available().await {
+        return Err("AI is not available, start Ollama to ask questions about your files".to_string());
+    }
+    
+    let mut config = rag::RagConfig::default();
+    if let Some(top_k) = top_k {
+        config.top_k = top_k.clamp(1, 20);
+    }
+    
+    match rag::ask(&state.semantic_search, &state.database, &state.ai_processor, &question, &config).await {
+        Ok(answer) => {
+            tracing::info!("Answered question with {} source passages", answer.citations.len());
+            Ok(serde_json::json!({
+                "question": answer.question,
+                "answer": answer.answer,
+                "citations": answer.citations,
+                "model": state.config.read().await.ai.model
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to answer question: {}", e);
+            Err(format!("Failed to answer question: {}", e))
+        }
+    }
+}
+
+/// Default number of neighbours fetched per topic, entity or folder in graph views
+const GRAPH_NEIGHBOR_LIMIT: usize = 10;
+
+#[tauri::command]
+async fn get_file_graph(
+    file_id: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let limit = limit.unwrap_or(GRAPH_NEIGHBOR_LIMIT).clamp(1, 100);
+    
+    match knowledge_graph::file_neighborhood(&state.database, &file_id, limit).await {
+        Ok(graph) => Ok(serde_json::json!({
+            "nodes": graph.nodes,
+            "edges": graph.edges
+        })),
+        Err(e) => {
+            tracing::error!("Failed to build file graph: {}", e);
+            Err(format!("Failed to build file graph: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_entity_graph(
+    entity: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let limit = limit.unwrap_or(GRAPH_NEIGHBOR_LIMIT).clamp(1, 100);
+    
+    match knowledge_graph::entity_neighborhood(&state.database, &entity, limit).await {
+        Ok(graph) => Ok(serde_json::json!({
+            "nodes": graph.nodes,
+            "edges": graph.edges
+        })),
+        Err(e) => {
+            tracing::error!("Failed to build entity graph: {}", e);
+            Err(format!("Failed to build entity graph: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_available_models(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    // Check if AI processor is available and get models
+    match state.ai_processor.get_available_models().await {
+        Ok(models) => Ok(serde_json::json!({
+            "models": models,
+            "ai_available": true
+        })),
+        Err(e) => {
+            tracing::warn!("Failed to get available models: {}", e);
+            Ok(serde_json::json!({
+                "models": [],
+                "ai_available": false,
+                "error": e.to_string()
+            }))
         }
     }
 }
@@ -516,77 +1746,97 @@ async fn check_ai_availability(state: State<'_, AppState>) -> Result<serde_json:
 }
 
 #[tauri::command]
-async fn semantic_search(query: String, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+async fn semantic_search(
+    query: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    collection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let started = std::time::Instant::now();
+    let first_page = cursor.is_none();
+    let response = semantic_search_response(query.clone(), limit, cursor, collection_id, state.clone()).await?;
+    if first_page {
+        record_search_stats(&state, &query, "semantic", started, &response).await;
+    }
+    Ok(response)
+}
+
+async fn semantic_search_response(
+    query: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    collection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
     tracing::info!("Performing semantic search for: {}", query);
+    if cursor.is_none() {
+        record_search_history(&state, &query).await;
+    }
+    let page = PageRequest::new(limit, cursor);
+    let collection_id = collection_scope(&state, collection_id).await?;
+    let fallback_filters = SearchFilters { collection_id: collection_id.clone(), ..Default::default() };
+    let mut scope = fallback_filters.clone();
+    let (search_text, interpreted_dates) = match apply_query_filters(&query, &mut scope) {
+        Ok(applied) => applied,
+        Err(e) => return Ok(syntax_error_response(&query, &e)),
+    };
+    
+    // Later pages continue with the engine that served the first
+    let offset = match page.page_cursor()? {
+        Some(PageCursor::Keyword(_)) => return keyword_search(query, fallback_filters, page, state).await,
+        Some(PageCursor::Ranked { session, offset }) => {
+            if let Some(ranking) = stored_ranking(&state, &session).await {
+                return Ok(ranked_search_page(&state, &page, session, offset, &ranking, "semantic", interpreted_dates).await);
+            }
+            offset
+        }
+        Some(PageCursor::Regex(_)) => return Err(FOREIGN_CURSOR_ERROR.to_string()),
+        None => 0,
+    };
+    
+    // A query that was only a date phrase has nothing to embed
+    if search_text.trim().is_empty() {
+        return keyword_search(query, fallback_filters, page, state).await;
+    }
     
     if !state.ai_processor.is_available().await {
         tracing::warn!("AI not available, falling back to regular search");
-        return search_files(query, None, state).await;
+        return keyword_search(query, fallback_filters, page, state).await;
     }
 
     // Use the new semantic search engine
-    let search_request = semantic_search::SearchRequest {
-        query: query.clone(),
-        search_type: semantic_search::SearchType::Semantic,
-        filters: None,
-        limit: Some(50),
-        threshold: Some(0.7),
-    };
-
-    match state.semantic_search.search(search_request).await {
-        Ok(search_response) => {
-            // Convert our search response to the expected frontend format
-            let results: Vec<serde_json::Value> = search_response.results
-                .iter()
-                .map(|result| {
-                    serde_json::json!({
-                        "file": {
-                            "id": result.file_id,
-                            "path": result.file_path,
-                            "name": result.file_name,
-                            "extension": std::path::Path::new(&result.file_path)
-                                .extension()
-                                .and_then(|ext| ext.to_str())
-                                .unwrap_or(""),
-                            "size": 0, // TODO: Get from metadata
-                            "created_at": result.last_modified,
-                            "modified_at": result.last_modified,
-                            "mime_type": "", // TODO: Get from metadata
-                            "processing_status": "completed"
-                        },
-                        "score": result.similarity_score,
-                        "snippet": result.snippet.as_ref().unwrap_or(&format!("Match in {}", result.file_name)),
-                        "highlights": result.highlights,
-                        "search_type": "semantic"
-                    })
-                })
-                .collect();
+    let search_request = semantic_search::SearchRequest {
+        query: search_text,
+        search_type: semantic_search::SearchType::Semantic,
+        filters: None,
+        limit: Some(VECTOR_SEARCH_WINDOW),
+        threshold: Some(0.7),
+        fusion: None,
+    };
 
-            let response = serde_json::json!({
-                "results": results,
-                "total": search_response.total_results,
-                "query": search_response.query,
-                "execution_time_ms": search_response.search_time_ms,
-                "search_type": "semantic",
-                "expanded_query": search_response.expanded_query,
-                "suggestions": search_response.suggestions
-            });
+    match scoped_vector_search(&state, search_request, &scope, offset + page.limit as usize).await? {
+        Ok(search_response) => {
+            let session = store_ranking(&state, &search_response).await;
+            let response = ranked_search_page(&state, &page, session, offset, &search_response, "semantic", interpreted_dates).await;
 
             tracing::info!("Semantic search completed: {} results in {}ms", 
-                search_response.total_results, search_response.search_time_ms);
+                search_response.results.len(), search_response.search_time_ms);
             Ok(response)
         }
         Err(e) => {
             tracing::error!("Semantic search failed: {}", e);
             // Fallback to regular search
             tracing::info!("Falling back to regular search due to semantic search failure");
-            search_files(query, None, state).await
+            keyword_search(query, fallback_filters, page, state).await
         }
     }
 }
 
+/// Index a file, or what is new or changed in a folder. A folder scan stops at the first of
+/// `limits` it reaches.
 #[tauri::command]
-async fn scan_directory(path: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn scan_directory(path: String, limits: Option<ScanLimits>, state: State<'_, AppState>) -> Result<(), String> {
     tracing::info!("Starting directory scan: {}", path);
     
     // Check if path is a file or directory
@@ -596,106 +1846,666 @@ async fn scan_directory(path: String, state: State<'_, AppState>) -> Result<(),
         return process_single_file(path, state).await;
     }
     
-    match state.file_monitor.scan_directory(&path).await {
-        Ok(()) => {
+    match state.file_monitor.scan_directory_with_limits(&path, limits.unwrap_or_default()).await {
+        Ok(summary) if summary.cancelled => {
+            tracing::info!("Directory scan was cancelled");
+            Ok(())
+        }
+        Ok(summary) if summary.limit_reached => {
+            tracing::info!("Directory scan stopped at its limit");
+            Ok(())
+        }
+        Ok(_) => {
             tracing::info!("Directory scan completed successfully");
             Ok(())
         }
         Err(e) => {
-            tracing::error!("Directory scan failed: {}", e);
-            Err(format!("Directory scan failed: {}", e))
+            tracing::error!("Directory scan failed: {}", e);
+            Err(format!("Directory scan failed: {}", e))
+        }
+    }
+}
+
+/// Count the files, types and bytes a scan of `path` would index, without indexing anything
+#[tauri::command]
+async fn estimate_scan(path: String, limits: Option<ScanLimits>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.file_monitor.estimate_scan(&path, limits.unwrap_or_default()).await {
+        Ok(estimate) => Ok(serde_json::json!(estimate)),
+        Err(e) => {
+            tracing::error!("Failed to estimate scan of {}: {}", path, e);
+            Err(format!("Failed to estimate scan: {}", e))
+        }
+    }
+}
+
+/// Suspend reacting to file changes and scheduled rescans; queued files are still processed
+#[tauri::command]
+async fn pause_monitoring(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.file_monitor.pause_monitoring())
+}
+
+/// Undo `pause_monitoring`, rescanning the watched folders for changes made meanwhile
+#[tauri::command]
+async fn resume_monitoring(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.file_monitor.resume_monitoring())
+}
+
+/// Start no more processing jobs; running ones finish and queued files wait for
+/// `resume_processing`
+#[tauri::command]
+async fn pause_processing(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.processing_queue.lock().await.pause_processing())
+}
+
+/// Undo `pause_processing`
+#[tauri::command]
+async fn resume_processing(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.processing_queue.lock().await.resume_processing())
+}
+
+/// Stop a running scan, identified by the `scan_id` of its progress events. Files found so
+/// far stay indexed.
+#[tauri::command]
+async fn cancel_scan(scan_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let cancelled = state.file_monitor.cancel_scan(&scan_id).await;
+    if cancelled {
+        tracing::info!("Cancelling directory scan {}", scan_id);
+    }
+    Ok(cancelled)
+}
+
+#[tauri::command]
+async fn process_single_file(path: String, state: State<'_, AppState>) -> Result<(), String> {
+    tracing::info!("Processing single file: {}", path);
+    
+    use crate::file_monitor::FileMonitor;
+    
+    // Validate file exists and is accessible
+    if !std::path::Path::new(&path).exists() {
+        let error_msg = format!("File does not exist: {}", path);
+        tracing::error!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    if !std::path::Path::new(&path).is_file() {
+        let error_msg = format!("Path is not a file: {}", path);
+        tracing::error!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    // Check file permissions
+    match tokio::fs::metadata(&path).await {
+        Ok(metadata) => {
+            tracing::info!("File metadata - size: {} bytes, readable: true", metadata.len());
+        }
+        Err(e) => {
+            let error_msg = format!("Cannot read file metadata for {}: {}", path, e);
+            tracing::error!("{}", error_msg);
+            return Err(error_msg);
+        }
+    }
+    
+    // Create a temporary file monitor to process the single file
+    let temp_monitor = FileMonitor::new(state.database.clone())
+        .with_processing_queue(state.processing_queue.clone());
+    
+    // Process the file using the private method (we'll need to make it public)
+    match temp_monitor.process_single_file_public(&path).await {
+        Ok(()) => {
+            tracing::info!("Single file processing completed successfully: {}", path);
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Single file processing failed for {}: {:?}", path, e);
+            // Provide more specific error message
+            let error_msg = match e.to_string().as_str() {
+                s if s.contains("UNIQUE constraint failed") => format!("File already exists in database: {}", path),
+                s if s.contains("permission denied") => format!("Permission denied accessing file: {}", path),
+                s if s.contains("no such file") => format!("File not found: {}", path),
+                _ => format!("Failed to process file {}: {}", path, e)
+            };
+            Err(error_msg)
+        }
+    }
+}
+
+// Database maintenance commands
+#[tauri::command]
+async fn reprocess_error_files(state: State<'_, AppState>) -> Result<(), String> {
+    tracing::info!("Reprocessing all error files with updated logic");
+    requeue_files(RequeueFilter::default(), state).await.map(|_| ())
+}
+
+/// Which files `requeue_files` processes again
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RequeueFilter {
+    /// Processing statuses to requeue, failed files by default
+    pub statuses: Vec<String>,
+    /// Only files below this folder
+    pub path_prefix: Option<String>,
+    /// Extensions without the leading dot, e.g. "pdf"
+    pub extensions: Vec<String>,
+    /// Only files whose last processing attempt started at least this many minutes ago
+    pub min_attempt_age_minutes: Option<u64>,
+}
+
+impl Default for RequeueFilter {
+    fn default() -> Self {
+        Self {
+            statuses: vec!["error".to_string()],
+            path_prefix: None,
+            extensions: Vec::new(),
+            min_attempt_age_minutes: None,
+        }
+    }
+}
+
+impl RequeueFilter {
+    fn to_search_filters(&self) -> SearchFilters {
+        SearchFilters {
+            processing_status: self.statuses.clone(),
+            root_path: self.path_prefix.clone(),
+            extensions: self.extensions.clone(),
+            date_range: self.min_attempt_age_minutes.map(|minutes| database::DateRange {
+                start: None,
+                end: Some(chrono::Utc::now() - chrono::Duration::minutes(minutes as i64)),
+                field: database::DateField::LastAttempt,
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Set the files matching `filter` back to pending and queue them, e.g. the PDFs of one folder
+/// after an extractor fix. Returns how many were queued.
+#[tauri::command]
+async fn requeue_files(filter: RequeueFilter, state: State<'_, AppState>) -> Result<usize, String> {
+    if filter.statuses.is_empty() {
+        return Err("Choose the processing statuses of the files to requeue".to_string());
+    }
+    if let Some(status) = filter.statuses.iter().find(|status| !BULK_STATUSES.contains(&status.as_str())) {
+        return Err(format!("Unsupported status '{}', expected one of: {}", status, BULK_STATUSES.join(", ")));
+    }
+
+    let files = match state.database.bulk_update_status(&filter.to_search_filters(), "pending", None).await {
+        Ok(files) => files,
+        Err(e) => {
+            tracing::error!("Failed to reset files for requeueing: {}", e);
+            return Err(format!("Failed to reset files for requeueing: {}", e));
+        }
+    };
+    tracing::info!("Found {} files to requeue", files.len());
+
+    let queued = queue_for_reprocessing(&state, &files).await;
+    tracing::info!("Requeued {} files", queued);
+    Ok(queued)
+}
+
+/// Statuses that can be set on many files at once; deletion has its own commands
+const BULK_STATUSES: &[&str] = &["pending", "completed", "error"];
+
+/// Set the processing status of every file matching `filters`. Files set to pending are queued again.
+#[tauri::command]
+async fn bulk_update_status(
+    filters: SearchFilters,
+    status: String,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    if !BULK_STATUSES.contains(&status.as_str()) {
+        return Err(format!("Unsupported status '{}', expected one of: {}", status, BULK_STATUSES.join(", ")));
+    }
+    if filters.is_empty() {
+        return Err("Refusing to update the status of every file; narrow the filters".to_string());
+    }
+
+    let files = match state.database.bulk_update_status(&filters, &status, None).await {
+        Ok(files) => files,
+        Err(e) => {
+            tracing::error!("Failed to update file status: {}", e);
+            return Err(format!("Failed to update file status: {}", e));
+        }
+    };
+
+    let queued = if status == "pending" {
+        queue_for_reprocessing(&state, &files).await
+    } else {
+        0
+    };
+
+    tracing::info!("Set {} files to {}", files.len(), status);
+    Ok(serde_json::json!({
+        "updated": files.len(),
+        "queued": queued,
+    }))
+}
+
+/// Put files back on the processing queue ahead of new ones, returning how many were added
+async fn queue_for_reprocessing(state: &AppState, files: &[database::FileRecord]) -> usize {
+    let queue = state.processing_queue.lock().await;
+    let mut queued = 0;
+    for file in files {
+        match queue.add_job(file, crate::processing_queue::JobPriority::High).await {
+            Ok(_) => queued += 1,
+            Err(e) => tracing::error!("Failed to add file to queue {}: {}", file.path, e),
+        }
+    }
+    queued
+}
+
+fn data_directory() -> Option<std::path::PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("MetaMind"))
+}
+
+fn backup_directory() -> Result<std::path::PathBuf, String> {
+    data_directory()
+        .map(|dir| dir.join("backups"))
+        .ok_or_else(|| "Failed to get data directory".to_string())
+}
+
+/// Snapshot the database now and drop snapshots beyond the configured retention
+async fn create_backup(database: &Database, retention_count: usize) -> anyhow::Result<database::backup::BackupInfo> {
+    let backup_dir = backup_directory().map_err(anyhow::Error::msg)?;
+    let backup = database.backup_to(&backup_dir).await?;
+    let removed = database::backup::prune_backups(&backup_dir, retention_count).await?;
+    if removed > 0 {
+        tracing::info!("Removed {} old database backups", removed);
+    }
+    Ok(backup)
+}
+
+#[tauri::command]
+async fn backup_now(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let retention_count = state.config.read().await.backup.retention_count;
+    
+    match create_backup(&state.database, retention_count).await {
+        Ok(backup) => {
+            tracing::info!("Database backed up to {}", backup.path);
+            Ok(serde_json::json!(backup))
+        }
+        Err(e) => {
+            tracing::error!("Failed to back up database: {}", e);
+            Err(format!("Failed to back up database: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn list_backups() -> Result<serde_json::Value, String> {
+    let backup_dir = backup_directory()?;
+    
+    match database::backup::list_backups(&backup_dir).await {
+        Ok(backups) => Ok(serde_json::json!({
+            "directory": backup_dir,
+            "backups": backups
+        })),
+        Err(e) => {
+            tracing::error!("Failed to list backups: {}", e);
+            Err(format!("Failed to list backups: {}", e))
+        }
+    }
+}
+
+/// Stage a backup to replace the database on the next start; the current state is backed up first
+#[tauri::command]
+async fn restore_backup(name: String, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    tracing::warn!("Restoring database from backup {}", name);
+    let backup_dir = backup_directory()?;
+    let database_path = data_directory()
+        .ok_or("Failed to get data directory")?
+        .join("metamind.db");
+    
+    let safety_backup = state.database.backup_to(&backup_dir).await
+        .map_err(|e| format!("Failed to back up current database before restoring: {}", e))?;
+    
+    let database_key = database::encryption::load_key().map_err(|e| e.to_string())?;
+    database::backup::stage_restore(&database_path, &backup_dir, &name, database_key.as_deref()).await
+        .map_err(|e| {
+            tracing::error!("Failed to restore backup: {}", e);
+            format!("Failed to restore backup: {}", e)
+        })?;
+    
+    // Pruned only now so the snapshot being restored cannot be removed before it is staged
+    let retention_count = state.config.read().await.backup.retention_count;
+    if let Err(e) = database::backup::prune_backups(&backup_dir, retention_count).await {
+        tracing::warn!("Failed to prune old database backups: {}", e);
+    }
+    
+    tracing::info!("Backup {} staged for restore - application restart required", name);
+    Ok(serde_json::json!({
+        "restored": name,
+        "safety_backup": safety_backup,
+        "restart_required": true
+    }))
+}
+
+/// Opt in to encrypting the database with a key derived from `passphrase`.
+/// The key goes to the keychain and the existing database is encrypted on the next start.
+#[tauri::command]
+async fn enable_database_encryption(passphrase: String, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match database::encryption::load_key() {
+        Ok(Some(_)) => return Err("Database encryption is already enabled".to_string()),
+        Ok(None) => {}
+        Err(e) => return Err(e.to_string()),
+    }
+    
+    match database::encryption::sqlcipher_available(&state.database.pool).await {
+        Ok(true) => {}
+        Ok(false) => return Err("Database encryption requires a build with SQLCipher support".to_string()),
+        Err(e) => return Err(format!("Failed to check for SQLCipher support: {}", e)),
+    }
+    
+    let database_path = data_directory()
+        .ok_or("Failed to get data directory")?
+        .join("metamind.db");
+    let salt = database::encryption::load_or_create_salt(&database_path).await
+        .map_err(|e| format!("Failed to store the database salt: {}", e))?;
+    let key = database::encryption::derive_key(&passphrase, &salt).map_err(|e| e.to_string())?;
+    if let Err(e) = database::encryption::store_key(&key) {
+        tracing::error!("Failed to store database key: {}", e);
+        return Err(format!("Failed to store database key: {}", e));
+    }
+    
+    tracing::info!("Database encryption enabled - application restart required");
+    Ok(serde_json::json!({
+        "enabled": true,
+        "restart_required": true
+    }))
+}
+
+/// Put the database key back in the keychain, derived again from the passphrase it was
+/// enabled with, after the keychain entry was lost
+#[tauri::command]
+async fn recover_database_key(passphrase: String) -> Result<serde_json::Value, String> {
+    let database_path = data_directory()
+        .ok_or("Failed to get data directory")?
+        .join("metamind.db");
+    database::encryption::recover_key(&database_path, &passphrase).await
+        .map_err(|e| {
+            tracing::error!("Failed to recover database key: {}", e);
+            format!("Failed to recover database key: {}", e)
+        })?;
+    
+    tracing::info!("Database key recovered - application restart required");
+    Ok(serde_json::json!({
+        "recovered": true,
+        "restart_required": true
+    }))
+}
+
+/// Write the whole index to a portable JSON archive at `path`
+#[tauri::command]
+async fn export_index(
+    path: String,
+    include_embeddings: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let archive = index_archive::export_index(&state.database, &state.vector_storage, include_embeddings.unwrap_or(false))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to export index: {}", e);
+            format!("Failed to export index: {}", e)
+        })?;
+    
+    let json = serde_json::to_vec(&archive).map_err(|e| format!("Failed to serialize index: {}", e))?;
+    tokio::fs::write(&path, &json).await.map_err(|e| {
+        tracing::error!("Failed to write index archive: {}", e);
+        format!("Failed to write index archive: {}", e)
+    })?;
+    
+    tracing::info!("Exported {} files to {}", archive.files.len(), path);
+    Ok(serde_json::json!({
+        "path": path,
+        "file_count": archive.files.len(),
+        "collection_count": archive.collections.len(),
+        "size": json.len()
+    }))
+}
+
+/// Merge an archive written by `export_index` into this index
+#[tauri::command]
+async fn import_index(
+    path: String,
+    conflict_policy: Option<index_archive::ConflictPolicy>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let json = tokio::fs::read(&path).await
+        .map_err(|e| format!("Failed to read index archive: {}", e))?;
+    let archive: index_archive::IndexArchive = serde_json::from_slice(&json)
+        .map_err(|e| format!("Invalid index archive: {}", e))?;
+    
+    match index_archive::import_index(&state.database, &state.vector_storage, &archive, conflict_policy.unwrap_or_default()).await {
+        Ok(report) => {
+            tracing::info!(
+                "Imported index from {}: {} inserted, {} merged, {} conflicts",
+                path, report.inserted, report.merged, report.conflicts.len()
+            );
+            Ok(serde_json::json!(report))
+        }
+        Err(e) => {
+            tracing::error!("Failed to import index: {}", e);
+            Err(format!("Failed to import index: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn optimize_database(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.optimize().await {
+        Ok(report) => {
+            tracing::info!("Database optimized, reclaimed {} bytes", report.bytes_reclaimed);
+            Ok(serde_json::json!(report))
+        }
+        Err(e) => {
+            tracing::error!("Failed to optimize database: {}", e);
+            Err(format!("Failed to optimize database: {}", e))
+        }
+    }
+}
+
+/// Check the database for corruption and drift, repairing what can be rebuilt.
+/// `reset_recommended` in the result means only `reset_database` is left.
+#[tauri::command]
+async fn check_database_integrity(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let mut report = match state.database.check_integrity().await {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::error!("Failed to check database integrity: {}", e);
+            return Err(format!("Failed to check database integrity: {}", e));
+        }
+    };
+
+    // Vector tables are only worth checking in a database that is otherwise sound
+    if !report.reset_recommended {
+        match state.vector_storage.check_consistency().await {
+            Ok(issues) => report.issues.extend(issues),
+            Err(e) => {
+                tracing::error!("Failed to check vector storage: {}", e);
+                return Err(format!("Failed to check vector storage: {}", e));
+            }
+        }
+    }
+
+    if report.reset_recommended {
+        tracing::error!("Database corruption could not be repaired: {}", report.corruption.join("; "));
+    } else if !report.issues.is_empty() {
+        tracing::warn!("Repaired {} database integrity issues", report.issues.len());
+    }
+    Ok(serde_json::json!(report))
+}
+
+/// Find indexed files missing from disk; they are soft deleted unless this is a dry run
+#[tauri::command]
+async fn reconcile_missing_files(dry_run: Option<bool>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.file_monitor.reconcile_missing_files(dry_run.unwrap_or(true)).await {
+        Ok(report) => Ok(serde_json::json!(report)),
+        Err(e) => {
+            tracing::error!("Failed to reconcile missing files: {}", e);
+            Err(format!("Failed to reconcile missing files: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_file_versions(file_id: String, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.get_file_versions(&file_id).await {
+        Ok(versions) => Ok(serde_json::json!(versions)),
+        Err(e) => {
+            tracing::error!("Failed to get file versions: {}", e);
+            Err(format!("Failed to get file versions: {}", e))
+        }
+    }
+}
+
+/// Symlinks and hard links found leading to the file indexed at `path`
+#[tauri::command]
+async fn get_file_links(path: String, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.get_file_links(&path).await {
+        Ok(links) => Ok(serde_json::json!(links)),
+        Err(e) => {
+            tracing::error!("Failed to get file links: {}", e);
+            Err(format!("Failed to get file links: {}", e))
+        }
+    }
+}
+
+/// Compare the summary of `from_version` with `to_version`, or with the current summary when omitted
+#[tauri::command]
+async fn diff_file_versions(
+    file_id: String,
+    from_version: i64,
+    to_version: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    match state.database.diff_file_versions(&file_id, from_version, to_version).await {
+        Ok(diff) => Ok(serde_json::json!(diff)),
+        Err(e) => {
+            tracing::error!("Failed to diff file versions: {}", e);
+            Err(format!("Failed to diff file versions: {}", e))
         }
     }
 }
 
 #[tauri::command]
-async fn process_single_file(path: String, state: State<'_, AppState>) -> Result<(), String> {
-    tracing::info!("Processing single file: {}", path);
-    
-    use crate::file_monitor::FileMonitor;
-    
-    // Validate file exists and is accessible
-    if !std::path::Path::new(&path).exists() {
-        let error_msg = format!("File does not exist: {}", path);
-        tracing::error!("{}", error_msg);
-        return Err(error_msg);
-    }
-    
-    if !std::path::Path::new(&path).is_file() {
-        let error_msg = format!("Path is not a file: {}", path);
-        tracing::error!("{}", error_msg);
-        return Err(error_msg);
-    }
-    
-    // Check file permissions
-    match tokio::fs::metadata(&path).await {
-        Ok(metadata) => {
-            tracing::info!("File metadata - size: {} bytes, readable: true", metadata.len());
-        }
+async fn list_deleted_files(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.get_deleted_files().await {
+        Ok(files) => Ok(serde_json::json!(files)),
         Err(e) => {
-            let error_msg = format!("Cannot read file metadata for {}: {}", path, e);
-            tracing::error!("{}", error_msg);
-            return Err(error_msg);
+            tracing::error!("Failed to list deleted files: {}", e);
+            Err(format!("Failed to list deleted files: {}", e))
         }
     }
-    
-    // Create a temporary file monitor to process the single file
-    let temp_monitor = FileMonitor::new(state.database.clone())
-        .with_processing_queue(state.processing_queue.clone());
-    
-    // Process the file using the private method (we'll need to make it public)
-    match temp_monitor.process_single_file_public(&path).await {
-        Ok(()) => {
-            tracing::info!("Single file processing completed successfully: {}", path);
-            Ok(())
+}
+
+#[tauri::command]
+async fn undo_file_deletion(file_id: String, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.restore_deleted_file(&file_id).await {
+        Ok(restored) => {
+            // The file's content was dropped with the deletion, so it is processed again
+            if restored {
+                if let Ok(Some(file)) = state.database.get_file_by_id(&file_id).await {
+                    queue_for_reprocessing(&state, std::slice::from_ref(&file)).await;
+                }
+            }
+            Ok(serde_json::json!({ "restored": restored }))
         }
         Err(e) => {
-            tracing::error!("Single file processing failed for {}: {:?}", path, e);
-            // Provide more specific error message
-            let error_msg = match e.to_string().as_str() {
-                s if s.contains("UNIQUE constraint failed") => format!("File already exists in database: {}", path),
-                s if s.contains("permission denied") => format!("Permission denied accessing file: {}", path),
-                s if s.contains("no such file") => format!("File not found: {}", path),
-                _ => format!("Failed to process file {}: {}", path, e)
-            };
-            Err(error_msg)
+            tracing::error!("Failed to restore deleted file: {}", e);
+            Err(format!("Failed to restore deleted file: {}", e))
         }
     }
 }
 
-// Database maintenance commands
+/// Purge files whose grace period has ended, or every deleted file when `all` is set
 #[tauri::command]
-async fn reprocess_error_files(state: State<'_, AppState>) -> Result<(), String> {
-    tracing::info!("Reprocessing all error files with updated logic");
+async fn purge_deleted_files(all: Option<bool>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let cutoff = if all.unwrap_or(false) {
+        chrono::Utc::now()
+    } else {
+        deleted_file_cutoff(state.config.read().await.privacy.deleted_file_grace_days)
+    };
     
-    // Get all files with error status
-    let error_files = match state.database.get_files_by_status("error").await {
-        Ok(files) => files,
+    match state.database.purge_deleted_files(cutoff).await {
+        Ok(purged) => {
+            tracing::info!("Purged {} deleted files", purged);
+            Ok(serde_json::json!({ "purged": purged }))
+        }
         Err(e) => {
-            tracing::error!("Failed to get error files: {}", e);
-            return Err(format!("Failed to get error files: {}", e));
+            tracing::error!("Failed to purge deleted files: {}", e);
+            Err(format!("Failed to purge deleted files: {}", e))
         }
-    };
-    
-    let error_files_count = error_files.len();
-    tracing::info!("Found {} error files to reprocess", error_files_count);
-    
-    // Reset their status to pending and add them back to the queue
-    for file in error_files {
-        // Reset status to pending
-        if let Err(e) = state.database.update_file_status(&file.id, "pending", None).await {
-            tracing::error!("Failed to reset status for file {}: {}", file.path, e);
-            continue;
+    }
+}
+
+/// Strip content, analysis and vectors from files indexed longer ago than `retention_days`,
+/// returning how many files were stripped
+async fn apply_data_retention(
+    database: &Database,
+    vector_storage: &VectorStorageManager,
+    retention_days: u32,
+) -> anyhow::Result<u64> {
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(retention_days as i64);
+    let mut stripped = 0;
+    loop {
+        let file_ids = database.get_files_past_retention(cutoff, 200).await?;
+        if file_ids.is_empty() {
+            return Ok(stripped);
         }
-        
-        // Add back to processing queue
-        if let Err(e) = state.processing_queue.lock().await.add_job(&file, crate::processing_queue::JobPriority::High).await {
-            tracing::error!("Failed to add file to queue {}: {}", file.path, e);
+        // Vectors go first so a failure leaves the file selected for the next run
+        vector_storage.delete_file_vectors(&file_ids).await?;
+        stripped += database.strip_file_content(&file_ids).await?;
+    }
+}
+
+/// Relay directory scan progress to the frontend as `scan-progress` events
+async fn forward_scan_progress(
+    mut progress: tokio::sync::broadcast::Receiver<file_monitor::ScanProgress>,
+    app_handle: tauri::AppHandle,
+) {
+    use tauri::Manager;
+    loop {
+        match progress.recv().await {
+            Ok(update) => {
+                if let Err(e) = app_handle.emit_all("scan-progress", &update) {
+                    tracing::warn!("Failed to emit scan progress: {}", e);
+                }
+            }
+            // Skipped updates are superseded by the next one
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
         }
     }
-    
-    tracing::info!("Reprocessing initiated for {} files", error_files_count);
-    Ok(())
+}
+
+/// Relay the stages of processing jobs to the frontend as `job-progress` events
+async fn forward_job_events(
+    mut events: tokio::sync::broadcast::Receiver<processing_queue::JobEvent>,
+    app_handle: tauri::AppHandle,
+) {
+    use tauri::Manager;
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                if let Err(e) = app_handle.emit_all("job-progress", &event) {
+                    tracing::warn!("Failed to emit job progress: {}", e);
+                }
+            }
+            // The panel catches up with the job's next stage
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+fn rescan_interval(monitoring: &MonitoringConfig) -> std::time::Duration {
+    std::time::Duration::from_secs(monitoring.rescan_interval_minutes as u64 * 60)
+}
+
+/// Days a processing attempt stays in a file's history
+const PROCESSING_HISTORY_RETENTION_DAYS: i64 = 90;
+
+fn deleted_file_cutoff(grace_days: u32) -> chrono::DateTime<chrono::Utc> {
+    chrono::Utc::now() - chrono::Duration::days(grace_days as i64)
 }
 
 #[tauri::command]
@@ -942,6 +2752,18 @@ async fn get_location_stats(
     }
 }
 
+/// Daily indexing counters for the last `days` days (30 by default), oldest first
+#[tauri::command]
+async fn get_daily_stats(days: Option<u32>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.get_daily_stats(days.unwrap_or(30)).await {
+        Ok(stats) => Ok(serde_json::json!(stats)),
+        Err(e) => {
+            tracing::error!("Failed to get daily stats: {}", e);
+            Err(format!("Failed to get daily stats: {}", e))
+        }
+    }
+}
+
 #[tauri::command]
 async fn get_insights_data(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     tracing::info!("Getting insights data - START");
@@ -962,7 +2784,13 @@ async fn get_insights_data(state: State<'_, AppState>) -> Result<serde_json::Val
     }
     
     match state.database.get_insights_data().await {
-        Ok(insights) => {
+        Ok(mut insights) => {
+            insights["near_duplicates"] = state.near_duplicates.read().await
+                .clone()
+                .unwrap_or(serde_json::Value::Null);
+            insights["topic_clusters"] = state.topic_clusters.read().await
+                .clone()
+                .unwrap_or(serde_json::Value::Null);
             tracing::info!("Retrieved insights data successfully");
             tracing::debug!("Insights data: {:?}", insights);
             Ok(insights)
@@ -974,6 +2802,151 @@ async fn get_insights_data(state: State<'_, AppState>) -> Result<serde_json::Val
     }
 }
 
+#[tauri::command]
+async fn find_duplicates(limit: Option<i64>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+
+    match state.database.find_duplicates(limit).await {
+        Ok(report) => Ok(serde_json::to_value(report).map_err(|e| e.to_string())?),
+        Err(e) => {
+            tracing::error!("Failed to find duplicates: {}", e);
+            Err(format!("Failed to find duplicates: {}", e))
+        }
+    }
+}
+
+/// Cluster near-identical files by content vector and cache the report for insights
+#[tauri::command]
+async fn find_near_duplicates(threshold: Option<f32>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let threshold = match threshold {
+        Some(threshold) => threshold,
+        None => state.config.read().await.ai.near_duplicate_threshold,
+    };
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(format!("Similarity threshold must be between 0 and 1, got {}", threshold));
+    }
+
+    let groups = state.vector_storage.find_near_duplicates(threshold).await
+        .map_err(|e| {
+            tracing::error!("Near-duplicate detection failed: {}", e);
+            format!("Near-duplicate detection failed: {}", e)
+        })?;
+
+    let mut report_groups = Vec::with_capacity(groups.len());
+    for group in groups {
+        let mut files = Vec::with_capacity(group.file_ids.len());
+        for file_id in &group.file_ids {
+            if let Ok(Some(file)) = state.database.get_file_by_id(file_id).await {
+                files.push(serde_json::json!({
+                    "id": file.id,
+                    "path": file.path,
+                    "name": file.name,
+                    "size": file.size,
+                    "modified_at": file.modified_at
+                }));
+            }
+        }
+        // Vectors of deleted files can leave a group with a single survivor
+        if files.len() > 1 {
+            report_groups.push(serde_json::json!({
+                "similarity": group.similarity,
+                "files": files
+            }));
+        }
+    }
+
+    let report = serde_json::json!({
+        "threshold": threshold,
+        "generated_at": chrono::Utc::now(),
+        "group_count": report_groups.len(),
+        "groups": report_groups
+    });
+    *state.near_duplicates.write().await = Some(report.clone());
+
+    Ok(report)
+}
+
+/// How often topic clusters are recomputed in the background
+const TOPIC_CLUSTER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 60 * 60);
+
+async fn refresh_topic_clusters(
+    vector_storage: &VectorStorageManager,
+    ai_processor: &AIProcessor,
+    cache: &RwLock<Option<serde_json::Value>>,
+) -> anyhow::Result<serde_json::Value> {
+    let clusters = topic_clusters::cluster_topics(
+        vector_storage,
+        ai_processor,
+        &topic_clusters::TopicClusterConfig::default(),
+    ).await?;
+
+    let report = serde_json::json!({
+        "generated_at": chrono::Utc::now(),
+        "cluster_count": clusters.len(),
+        "clustered_files": clusters.iter().map(|c| c.file_count).sum::<usize>(),
+        "clusters": clusters
+    });
+    *cache.write().await = Some(report.clone());
+    Ok(report)
+}
+
+#[tauri::command]
+async fn get_topic_clusters(refresh: Option<bool>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    if !refresh.unwrap_or(false) {
+        if let Some(report) = state.topic_clusters.read().await.clone() {
+            return Ok(report);
+        }
+    }
+
+    refresh_topic_clusters(&state.vector_storage, &state.ai_processor, &state.topic_clusters).await
+        .map_err(|e| {
+            tracing::error!("Topic clustering failed: {}", e);
+            format!("Topic clustering failed: {}", e)
+        })
+}
+
+#[tauri::command]
+async fn list_tags(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.list_tags().await {
+        Ok(tags) => Ok(serde_json::to_value(tags).map_err(|e| e.to_string())?),
+        Err(e) => {
+            tracing::error!("Failed to list tags: {}", e);
+            Err(format!("Failed to list tags: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn rename_tag(old_name: String, new_name: String, state: State<'_, AppState>) -> Result<u64, String> {
+    state.database.rename_tag(&old_name, &new_name).await.map_err(|e| {
+        tracing::error!("Failed to rename tag: {}", e);
+        format!("Failed to rename tag: {}", e)
+    })
+}
+
+/// Functions, types and modules defined in indexed source files, by name
+#[tauri::command]
+async fn search_code_symbols(query: String, limit: Option<i64>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    match state.database.search_code_symbols(&query, limit.unwrap_or(50)).await {
+        Ok(symbols) => Ok(serde_json::json!(symbols)),
+        Err(e) => {
+            tracing::error!("Failed to search code symbols: {}", e);
+            Err(format!("Failed to search code symbols: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn search_by_tag(
+    tag: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let filters = SearchFilters { tags: vec![tag], ..Default::default() };
+    keyword_search(String::new(), filters, PageRequest::new(limit, cursor), state).await
+}
+
 #[tauri::command]
 async fn get_file_errors(
     path: String,
@@ -984,7 +2957,7 @@ async fn get_file_errors(
     // Check if it's a single file or a directory
     if std::path::Path::new(&path).is_file() {
         // Single file - get specific error
-        match state.database.get_file_by_path(&path).await {
+        match state.database.get_file_by_path(&paths::canonical_string(std::path::Path::new(&path))).await {
             Ok(Some(file)) => {
                 Ok(serde_json::json!({
                     "type": "single_file",
@@ -1093,7 +3066,8 @@ async fn generate_file_vectors(
     let file_path = file_id.clone(); // Assuming file_id is actually a path for now
 
     // Extract content for vector generation
-    let content = crate::content_extractor::ContentExtractor::extract_content(&file_path).await
+    let settings = state.config.read().await.extraction.clone();
+    let content = ContentExtractor::extract_content(&file_path, &settings).await
         .map_err(|e| format!("Content extraction failed: {}", e))?;
 
     // Generate vectors
@@ -1107,9 +3081,14 @@ async fn generate_file_vectors(
         content_vector,
         metadata_vector,
         summary_vector,
-        "nomic-embed-text", // TODO: Make configurable
+        state.ai_processor.embedding_model(),
     ).await.map_err(|e| format!("Vector storage failed: {}", e))?;
 
+    let chunks = crate::chunking::embed_chunks(&state.ai_processor, &content.text, &crate::chunking::ChunkingConfig::default()).await
+        .map_err(|e| format!("Chunk vector generation failed: {}", e))?;
+    state.vector_storage.store_chunk_vectors(&file_id, &chunks, state.ai_processor.embedding_model()).await
+        .map_err(|e| format!("Chunk vector storage failed: {}", e))?;
+
     tracing::info!("Vectors generated and stored for file: {}", file_id);
     Ok(())
 }
@@ -1137,65 +3116,168 @@ async fn get_vector_statistics(state: State<'_, AppState>) -> Result<serde_json:
 }
 
 #[tauri::command]
-async fn hybrid_search(query: String, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+async fn hybrid_search(
+    query: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    collection_id: Option<String>,
+    keyword_weight: Option<f32>,
+    semantic_weight: Option<f32>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let started = std::time::Instant::now();
+    let first_page = cursor.is_none();
+    let response = hybrid_search_response(query.clone(), limit, cursor, collection_id, keyword_weight, semantic_weight, state.clone()).await?;
+    if first_page {
+        record_search_stats(&state, &query, "hybrid", started, &response).await;
+    }
+    Ok(response)
+}
+
+async fn hybrid_search_response(
+    query: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    collection_id: Option<String>,
+    keyword_weight: Option<f32>,
+    semantic_weight: Option<f32>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
     tracing::info!("Performing hybrid search for: {}", query);
+    let mut fusion = semantic_search::FusionWeights::default();
+    if let Some(weight) = keyword_weight {
+        fusion.keyword = weight.max(0.0);
+    }
+    if let Some(weight) = semantic_weight {
+        fusion.semantic = weight.max(0.0);
+    }
+    if cursor.is_none() {
+        record_search_history(&state, &query).await;
+    }
+    let page = PageRequest::new(limit, cursor);
+    let collection_id = collection_scope(&state, collection_id).await?;
+    let fallback_filters = SearchFilters { collection_id: collection_id.clone(), ..Default::default() };
+    let mut scope = fallback_filters.clone();
+    let (search_text, interpreted_dates) = match apply_query_filters(&query, &mut scope) {
+        Ok(applied) => applied,
+        Err(e) => return Ok(syntax_error_response(&query, &e)),
+    };
+    
+    // Later pages continue with the engine that served the first
+    let offset = match page.page_cursor()? {
+        Some(PageCursor::Keyword(_)) => return keyword_search(query, fallback_filters, page, state).await,
+        Some(PageCursor::Ranked { session, offset }) => {
+            if let Some(ranking) = stored_ranking(&state, &session).await {
+                return Ok(ranked_search_page(&state, &page, session, offset, &ranking, "hybrid", interpreted_dates).await);
+            }
+            offset
+        }
+        Some(PageCursor::Regex(_)) => return Err(FOREIGN_CURSOR_ERROR.to_string()),
+        None => 0,
+    };
+    
+    let parsed_query = match QueryNode::parse(&search_text) {
+        Ok(node) => node,
+        Err(e) => return Ok(syntax_error_response(&query, &e)),
+    };
+    
+    // Embeddings only understand the positive terms; negations are applied afterwards
+    let semantic_text = parsed_query.positive_text();
+    if semantic_text.is_empty() {
+        return keyword_search(query, fallback_filters, page, state).await;
+    }
     
     let search_request = semantic_search::SearchRequest {
-        query: query.clone(),
+        query: semantic_text,
         search_type: semantic_search::SearchType::Hybrid,
         filters: None,
-        limit: Some(50),
+        limit: Some(VECTOR_SEARCH_WINDOW),
         threshold: Some(0.6),
+        fusion: Some(fusion),
     };
 
-    match state.semantic_search.search(search_request).await {
-        Ok(search_response) => {
-            let results: Vec<serde_json::Value> = search_response.results
-                .iter()
-                .map(|result| {
-                    serde_json::json!({
-                        "file": {
-                            "id": result.file_id,
-                            "path": result.file_path,
-                            "name": result.file_name,
-                            "extension": std::path::Path::new(&result.file_path)
-                                .extension()
-                                .and_then(|ext| ext.to_str())
-                                .unwrap_or(""),
-                            "size": 0, // TODO: Get from metadata
-                            "created_at": result.last_modified,
-                            "modified_at": result.last_modified,
-                            "mime_type": "", // TODO: Get from metadata
-                            "processing_status": "completed"
-                        },
-                        "score": result.similarity_score,
-                        "snippet": result.snippet.as_ref().unwrap_or(&format!("Match in {}", result.file_name)),
-                        "highlights": result.highlights,
-                        "search_type": "hybrid"
-                    })
-                })
-                .collect();
+    match scoped_vector_search(&state, search_request, &scope, offset + page.limit as usize).await? {
+        Ok(mut search_response) => {
+            let required_paths: Vec<QueryNode> = parsed_query.required_paths().into_iter().cloned().collect();
+            if !required_paths.is_empty() {
+                let candidate_ids: Vec<String> = search_response.results.iter().map(|r| r.file_id.clone()).collect();
+                match state.database.file_ids_matching(&candidate_ids, &QueryNode::And(required_paths), &SearchFilters::default()).await {
+                    Ok(allowed) => {
+                        let allowed: std::collections::HashSet<String> = allowed.into_iter().collect();
+                        search_response.results.retain(|r| allowed.contains(&r.file_id));
+                    }
+                    Err(e) => tracing::warn!("Failed to apply path constraints: {}", e),
+                }
+            }
 
-            let response = serde_json::json!({
-                "results": results,
-                "total": search_response.total_results,
-                "query": search_response.query,
-                "execution_time_ms": search_response.search_time_ms,
-                "search_type": "hybrid",
-                "expanded_query": search_response.expanded_query,
-                "suggestions": search_response.suggestions
-            });
+            let negated: Vec<QueryNode> = parsed_query.negated_nodes().into_iter().cloned().collect();
+            if !negated.is_empty() {
+                let candidate_ids: Vec<String> = search_response.results.iter().map(|r| r.file_id.clone()).collect();
+                match state.database.file_ids_matching(&candidate_ids, &QueryNode::Or(negated), &SearchFilters::default()).await {
+                    Ok(excluded) => {
+                        let excluded: std::collections::HashSet<String> = excluded.into_iter().collect();
+                        search_response.results.retain(|r| !excluded.contains(&r.file_id));
+                    }
+                    Err(e) => tracing::warn!("Failed to apply negated query terms: {}", e),
+                }
+            }
 
-            Ok(response)
+            // Hybrid results are shown against the query as typed, not its embedded terms
+            search_response.query = query;
+            let session = store_ranking(&state, &search_response).await;
+            Ok(ranked_search_page(&state, &page, session, offset, &search_response, "hybrid", interpreted_dates).await)
         }
         Err(e) => {
             tracing::error!("Hybrid search failed: {}", e);
             // Fallback to regular search
-            search_files(query, None, state).await
+            keyword_search(query, fallback_filters, page, state).await
         }
     }
 }
 
+#[tauri::command]
+async fn find_similar_files(file_id: String, limit: Option<usize>, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let limit = limit.unwrap_or(10).clamp(1, 100);
+
+    let neighbours = state.vector_storage.find_similar_files(&file_id, limit, 0.5).await
+        .map_err(|e| {
+            tracing::error!("Failed to find similar files: {}", e);
+            format!("Failed to find similar files: {}", e)
+        })?;
+
+    let mut results = Vec::with_capacity(neighbours.len());
+    for (neighbour_id, score) in neighbours {
+        // Vectors can outlive their file row until the next cleanup
+        let file = match state.database.get_file_by_id(&neighbour_id).await {
+            Ok(Some(file)) => file,
+            Ok(None) => continue,
+            Err(e) => return Err(format!("Failed to load similar file: {}", e)),
+        };
+
+        results.push(serde_json::json!({
+            "file": {
+                "id": file.id,
+                "path": file.path,
+                "name": file.name,
+                "extension": file.extension,
+                "size": file.size,
+                "created_at": file.created_at,
+                "modified_at": file.modified_at,
+                "mime_type": file.mime_type,
+                "processing_status": file.processing_status
+            },
+            "score": score,
+            "search_type": "similar"
+        }));
+    }
+
+    Ok(serde_json::json!({
+        "file_id": file_id,
+        "results": results,
+        "total": results.len()
+    }))
+}
+
 #[tauri::command]
 async fn get_cache_statistics(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     let stats = state.vector_cache.get_statistics().await;
@@ -1269,11 +3351,7 @@ async fn main() {
         tracing::error!("Failed to create data directory: {}", e);
     }
     
-    let database = Database::new(data_dir.join("metamind.db"))
-        .await
-        .expect("Failed to initialize database");
-
-    // Load configuration from disk
+    // Load configuration from disk; it also tunes the database connections
     let config = match load_config_from_disk().await {
         Ok(config) => {
             tracing::info!("Loaded configuration from disk");
@@ -1285,11 +3363,41 @@ async fn main() {
         }
     };
 
+    // A key is only in the keychain once database encryption has been enabled. A keychain that
+    // cannot be read is not the same as no key: an encrypted database is never opened without it.
+    let database_path = data_dir.join("metamind.db");
+    let database_key = match database::encryption::load_key_retrying().await {
+        Ok(key) => key,
+        Err(e) if !database_path.exists() || matches!(database::encryption::is_plaintext(&database_path).await, Ok(true)) => {
+            tracing::warn!("{}; the database is not encrypted yet, so it is opened without a key", e);
+            None
+        }
+        Err(e) => {
+            tracing::error!("{}", e);
+            eprintln!("MetaMind cannot open its encrypted database: {}. Unlock the system keychain and start MetaMind again.", e);
+            std::process::exit(1);
+        }
+    };
+    let database = Database::open(&database_path, database_key.as_deref(), &config.performance.database)
+        .await
+        .expect("Failed to initialize database");
+
+    // Files indexed by older versions have no search tokens yet
+    let backfill_database = database.clone();
+    tokio::spawn(async move {
+        match backfill_database.backfill_search_tokens(200).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Indexed search tokens for {} existing files", count),
+            Err(e) => tracing::warn!("Failed to backfill search tokens: {}", e),
+        }
+    });
+
     // Initialize AI processor with loaded configuration
     let ai_processor = AIProcessor::new(
         config.ai.ollama_url.clone(),
         config.ai.model.clone(),
-    );
+    )
+    .with_embedding_model(config.ai.embedding_model.clone());
 
     // Initialize vector search components
     let vector_storage = VectorStorageManager::new(database.pool.clone());
@@ -1304,7 +3412,12 @@ async fn main() {
     let semantic_search_engine = SemanticSearchEngine::new(
         vector_storage.clone(),
         ai_processor.clone(),
-    );
+    )
+    .with_keyword_index(database.clone())
+    .with_config(semantic_search::SearchConfig {
+        enable_reranking: config.ai.enable_reranking,
+        ..Default::default()
+    });
 
     let folder_vectorizer = FolderVectorizer::new(
         vector_storage.clone(),
@@ -1330,13 +3443,35 @@ async fn main() {
     let processing_queue = ProcessingQueue::new(
         database.clone(),
         ai_processor.clone(),
-        4, // max concurrent jobs
+        config.performance.max_concurrent_jobs,
     );
+    processing_queue.set_adaptive_scaling(config.performance.adaptive_performance);
+    processing_queue.set_power_policy(config.performance.power.clone());
+    processing_queue.set_scheduling_policy(config.performance.scheduling.clone()).await;
+    processing_queue.set_stage_timeouts(config.performance.stage_timeouts.clone());
+    processing_queue.set_queue_capacity(config.performance.queue_capacity);
+    processing_queue.set_processing_window(config.performance.processing_window.clone());
+    processing_queue.set_extraction_settings(config.extraction.clone());
+    let backpressure = processing_queue.backpressure();
     let processing_queue = Arc::new(tokio::sync::Mutex::new(processing_queue));
 
     // Initialize file monitor with processing queue
     let file_monitor = FileMonitor::new(database.clone())
-        .with_processing_queue(processing_queue.clone());
+        .with_processing_queue(processing_queue.clone())
+        .with_backpressure(backpressure)
+        .with_debounce_window(std::time::Duration::from_millis(config.monitoring.event_debounce_ms))
+        .with_rescan_interval(rescan_interval(&config.monitoring))
+        .with_scan_schedules(file_monitor::parse_scan_schedules(&config.monitoring.scan_schedules).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring configured scan schedules: {}", e);
+            Default::default()
+        }))
+        .with_adaptive_throttling(config.performance.adaptive_performance)
+        .with_link_policy(config.monitoring.link_policy)
+        .with_category_exclusions(config.monitoring.excluded_categories)
+        .with_excluded_patterns(PathPatterns::new(&config.monitoring.excluded_patterns).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring configured exclusion patterns: {}", e);
+            PathPatterns::default()
+        }));
 
     // Start the processing queue
     {
@@ -1369,16 +3504,177 @@ async fn main() {
         folder_vectorizer,
         vector_cache,
         benchmarks,
+        near_duplicates: Arc::new(RwLock::new(None)),
+        topic_clusters: Arc::new(RwLock::new(None)),
     };
 
+    // Resume watching the folders of the last session; the scans catch up on changes made meanwhile
+    let resume_monitor = app_state.file_monitor.clone();
+    let resume_queue = app_state.processing_queue.clone();
+    tokio::spawn(async move {
+        let restored = match resume_monitor.restore_watch_paths().await {
+            Ok(restored) => restored,
+            Err(e) => {
+                tracing::error!("Failed to restore watch paths: {}", e);
+                return;
+            }
+        };
+        if restored.is_empty() {
+            return;
+        }
+
+        if let Err(e) = resume_monitor.start_monitoring().await {
+            tracing::error!("Failed to resume file monitoring: {}", e);
+            return;
+        }
+        tracing::info!("Resumed monitoring {} watched paths", restored.len());
+
+        for path in &restored {
+            if let Err(e) = resume_monitor.scan_directory(path).await {
+                tracing::error!("Failed to rescan {}: {}", path.display(), e);
+            }
+        }
+        let priority_for = resume_monitor.folder_priorities().await;
+        if let Err(e) = resume_queue.lock().await.requeue_pending_files(priority_for).await {
+            tracing::error!("Failed to requeue pending files: {}", e);
+        }
+    });
+
+    // Periodic database snapshots; settings are re-read each round so changes apply without a restart
+    let backup_database = app_state.database.clone();
+    let backup_config = Arc::clone(&app_state.config);
+    tokio::spawn(async move {
+        loop {
+            let interval_hours = backup_config.read().await.backup.interval_hours;
+            tokio::time::sleep(std::time::Duration::from_secs(interval_hours as u64 * 60 * 60)).await;
+            if !backup_config.read().await.backup.enabled {
+                continue;
+            }
+            let retention_count = backup_config.read().await.backup.retention_count;
+            match create_backup(&backup_database, retention_count).await {
+                Ok(backup) => tracing::info!("Scheduled database backup written to {}", backup.path),
+                Err(e) => tracing::warn!("Scheduled database backup failed: {}", e),
+            }
+        }
+    });
+
+    // Purge files deleted longer ago than the grace period, and old processing attempts, once a day
+    let purge_database = app_state.database.clone();
+    let purge_config = Arc::clone(&app_state.config);
+    tokio::spawn(async move {
+        loop {
+            let grace_days = purge_config.read().await.privacy.deleted_file_grace_days;
+            match purge_database.purge_deleted_files(deleted_file_cutoff(grace_days)).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Purged {} deleted files past the grace period", purged),
+                Err(e) => tracing::warn!("Failed to purge deleted files: {}", e),
+            }
+            let history_cutoff = chrono::Utc::now() - chrono::Duration::days(PROCESSING_HISTORY_RETENTION_DAYS);
+            match purge_database.prune_processing_history(history_cutoff).await {
+                Ok(0) => {}
+                Ok(pruned) => tracing::info!("Pruned {} processing attempts past the retention period", pruned),
+                Err(e) => tracing::warn!("Failed to prune processing history: {}", e),
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(24 * 60 * 60)).await;
+        }
+    });
+
+    // Weekly database maintenance, after startup work has settled. Timed from the last run,
+    // manual ones included, so restarts neither postpone nor repeat it.
+    let maintenance_database = app_state.database.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(10 * 60)).await;
+        loop {
+            let last_run = maintenance_database.last_optimized_at().await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to read the last database maintenance: {}", e);
+                None
+            });
+            let wait = last_run
+                .map(|at| at + chrono::Duration::days(7) - chrono::Utc::now())
+                .and_then(|wait| wait.to_std().ok());
+            if let Some(wait) = wait {
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+
+            match maintenance_database.optimize().await {
+                Ok(report) => tracing::info!(
+                    "Scheduled database maintenance reclaimed {} bytes in {} ms",
+                    report.bytes_reclaimed,
+                    report.duration_ms
+                ),
+                Err(e) => {
+                    tracing::warn!("Scheduled database maintenance failed: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(24 * 60 * 60)).await;
+                }
+            }
+        }
+    });
+
+    // Enforce the privacy retention window once a day
+    let retention_database = app_state.database.clone();
+    let retention_storage = app_state.vector_storage.clone();
+    let retention_config = Arc::clone(&app_state.config);
+    tokio::spawn(async move {
+        loop {
+            let retention_days = retention_config.read().await.privacy.data_retention_days;
+            match apply_data_retention(&retention_database, &retention_storage, retention_days).await {
+                Ok(0) => {}
+                Ok(stripped) => tracing::info!("Removed stored content of {} files past the retention window", stripped),
+                Err(e) => tracing::warn!("Failed to apply data retention: {}", e),
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(24 * 60 * 60)).await;
+        }
+    });
+
+    // Keep topic clusters current as files are indexed; the first run waits for startup work
+    let cluster_storage = app_state.vector_storage.clone();
+    let cluster_ai = app_state.ai_processor.clone();
+    let cluster_cache = Arc::clone(&app_state.topic_clusters);
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(120)).await;
+        loop {
+            match refresh_topic_clusters(&cluster_storage, &cluster_ai, &cluster_cache).await {
+                Ok(report) => tracing::info!("Refreshed {} topic clusters", report["cluster_count"]),
+                Err(e) => tracing::warn!("Background topic clustering failed: {}", e),
+            }
+            tokio::time::sleep(TOPIC_CLUSTER_INTERVAL).await;
+        }
+    });
+
+    let scan_progress = app_state.file_monitor.subscribe_scan_progress();
+    let job_events = app_state.processing_queue.lock().await.subscribe_job_events();
+    let adaptive_scaling = app_state.processing_queue.lock().await.adaptive_scaling();
+
     tauri::Builder::default()
         .manage(app_state)
+        // Fewer workers run while the user is busy in other apps
+        .on_window_event(move |event| {
+            if let tauri::WindowEvent::Focused(focused) = event.event() {
+                adaptive_scaling.set_window_focused(*focused);
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             get_system_info,
             start_file_monitoring,
+            set_watch_path_options,
+            set_watch_path_priority,
+            get_watch_paths,
+            remove_watch_path,
+            get_exclusion_patterns,
+            set_exclusion_patterns,
+            get_scan_schedules,
+            set_scan_schedule,
             search_files,
+            export_search_results,
             get_processing_status,
             get_processing_insights,
+            prioritize_files,
+            get_dead_jobs,
+            retry_dead_jobs,
+            dismiss_dead_jobs,
+            get_processing_history,
+            get_ocr_languages,
             get_config,
             update_config,
             reset_config_to_defaults,
@@ -1388,12 +3684,39 @@ async fn main() {
             start_system_monitoring,
             stop_system_monitoring,
             get_search_suggestions,
+            clear_search_history,
+            get_search_analytics,
+            ask_files,
+            get_file_graph,
+            get_entity_graph,
             get_available_models,
             check_ai_availability,
             semantic_search,
             scan_directory,
+            estimate_scan,
+            cancel_scan,
+            pause_monitoring,
+            resume_monitoring,
+            pause_processing,
+            resume_processing,
             process_single_file,
             reset_database,
+            backup_now,
+            list_backups,
+            restore_backup,
+            enable_database_encryption,
+            recover_database_key,
+            optimize_database,
+            check_database_integrity,
+            export_index,
+            import_index,
+            reconcile_missing_files,
+            get_file_versions,
+            get_file_links,
+            diff_file_versions,
+            list_deleted_files,
+            undo_file_deletion,
+            purge_deleted_files,
             create_collection,
             get_collections,
             get_collection_by_id,
@@ -1405,7 +3728,17 @@ async fn main() {
             get_location_stats,
             get_file_errors,
             get_insights_data,
+            get_daily_stats,
+            find_duplicates,
+            find_near_duplicates,
+            get_topic_clusters,
+            list_tags,
+            rename_tag,
+            search_by_tag,
+            search_code_symbols,
             reprocess_error_files,
+            requeue_files,
+            bulk_update_status,
             check_for_updates,
             install_update,
             get_error_reports,
@@ -1414,13 +3747,16 @@ async fn main() {
             process_folder_vectors,
             get_vector_statistics,
             hybrid_search,
+            find_similar_files,
             get_cache_statistics,
             clear_cache,
             run_vector_benchmarks,
             run_quick_benchmark
         ])
-        .setup(|_app| {
+        .setup(move |app| {
             tracing::info!("MetaMind is starting up!");
+            tokio::spawn(forward_scan_progress(scan_progress, app.handle()));
+            tokio::spawn(forward_job_events(job_events, app.handle()));
             Ok(())
         })
         .run(tauri::generate_context!())
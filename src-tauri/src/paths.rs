@@ -0,0 +1,126 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Whether the platform's default file systems treat `Docs` and `docs` as the same name
+const CASE_INSENSITIVE: bool = cfg!(any(target_os = "windows", target_os = "macos"));
+
+/// The one spelling under which a file is indexed: `.` and `..` removed, symlinks resolved and
+/// letter case as stored on disk. Paths that no longer exist are resolved up to their nearest
+/// existing ancestor, so a deleted file still maps to the row it was indexed under.
+pub fn canonicalize(path: &Path) -> PathBuf {
+    let path = normalize_lexically(path);
+
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    loop {
+        match std::fs::canonicalize(existing) {
+            Ok(resolved) => {
+                let mut resolved = strip_verbatim_prefix(resolved);
+                for name in missing.iter().rev() {
+                    resolved.push(name);
+                }
+                return resolved;
+            }
+            Err(_) => match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name.to_os_string());
+                    existing = parent;
+                }
+                _ => return path,
+            },
+        }
+    }
+}
+
+/// Like `canonicalize`, but a symlink at `path` itself keeps its own name instead of resolving
+/// to its target; the folders above it are still resolved.
+pub fn canonicalize_link(path: &Path) -> PathBuf {
+    let path = normalize_lexically(path);
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => canonicalize(parent).join(name),
+        _ => canonicalize(&path),
+    }
+}
+
+pub fn canonical_string(path: &Path) -> String {
+    canonicalize(path).to_string_lossy().to_string()
+}
+
+/// Key under which two stored paths refer to the same file
+pub fn path_key(path: &str) -> String {
+    if CASE_INSENSITIVE {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Drop `.` components, fold `..` into their parent and remove repeated or trailing separators
+pub fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(normalized.components().next_back(), Some(Component::Normal(_))) {
+                    normalized.pop();
+                } else if !normalized.has_root() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Windows canonical paths come back as `\\?\C:\...`, which nothing else in the index uses
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    if !cfg!(windows) {
+        return path;
+    }
+    let text = path.to_string_lossy();
+    if let Some(rest) = text.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", rest))
+    } else if let Some(rest) = text.strip_prefix(r"\\?\") {
+        PathBuf::from(rest)
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lexical_normalization() {
+        assert_eq!(normalize_lexically(Path::new("/Users/me/./docs//a/../b.txt")), PathBuf::from("/Users/me/docs/b.txt"));
+        assert_eq!(normalize_lexically(Path::new("/../a/")), PathBuf::from("/a"));
+        assert_eq!(normalize_lexically(Path::new("../a")), PathBuf::from("../a"));
+    }
+
+    #[test]
+    fn test_missing_files_resolve_through_existing_parent() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = std::fs::canonicalize(temp_dir.path()).unwrap();
+        let dotted = temp_dir.path().join(".").join("gone.txt");
+        assert_eq!(canonicalize(&dotted), root.join("gone.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_resolve_to_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = std::fs::canonicalize(temp_dir.path()).unwrap();
+        std::fs::create_dir(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(root.join("docs"), root.join("link")).unwrap();
+
+        assert_eq!(canonicalize(&root.join("link/a.txt")), root.join("docs/a.txt"));
+
+        std::os::unix::fs::symlink(root.join("docs/a.txt"), root.join("docs/b.txt")).unwrap();
+        assert_eq!(canonicalize(&root.join("link/b.txt")), root.join("docs/a.txt"));
+        assert_eq!(canonicalize_link(&root.join("link/b.txt")), root.join("docs/b.txt"));
+    }
+}
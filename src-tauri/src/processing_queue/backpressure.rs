@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Jobs kept in memory unless configured otherwise; more wait in the jobs table
+pub const DEFAULT_QUEUE_CAPACITY: usize = 10_000;
+
+/// Set while the backlog is at the queue's capacity, so the file monitor coalesces events for
+/// longer and hands fewer of them over until the workers catch up
+#[derive(Debug, Clone, Default)]
+pub struct Backpressure {
+    active: Arc<AtomicBool>,
+}
+
+impl Backpressure {
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Follow the backlog of queued and spilled jobs, logging when the signal changes
+    pub(super) fn update(&self, backlog: usize, capacity: usize) {
+        let was_active = self.is_active();
+        let active = is_saturated(was_active, backlog, capacity);
+        if active != was_active {
+            self.active.store(active, Ordering::SeqCst);
+            match active {
+                true => tracing::info!("Processing queue is full with {} jobs, slowing file events down", backlog),
+                false => tracing::info!("Processing queue has room again, file events back to normal"),
+            }
+        }
+    }
+}
+
+/// On from the capacity, and off only once the backlog is down to three quarters of it, so
+/// the signal does not flap while the backlog hovers at the limit
+fn is_saturated(was_active: bool, backlog: usize, capacity: usize) -> bool {
+    match was_active {
+        true => backlog > capacity / 4 * 3,
+        false => backlog >= capacity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backpressure_hysteresis() {
+        let backpressure = Backpressure::default();
+        backpressure.update(99, 100);
+        assert!(!backpressure.is_active());
+        backpressure.update(100, 100);
+        assert!(backpressure.is_active());
+        backpressure.update(80, 100);
+        assert!(backpressure.is_active());
+        backpressure.update(75, 100);
+        assert!(!backpressure.is_active());
+        backpressure.update(80, 100);
+        assert!(!backpressure.is_active());
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::database::jobs::{size_bucket, ProcessingTiming, QueuedFile};
+
+/// Assumed per file until a job has been timed
+const DEFAULT_JOB_MS: f64 = 2000.0;
+
+/// Average processing times learnt from finished jobs, from the most to the least specific
+#[derive(Debug, Default)]
+pub struct TimingModel {
+    by_extension_and_size: HashMap<(String, i64), f64>,
+    by_extension: HashMap<String, f64>,
+    by_size: HashMap<i64, f64>,
+    overall: Option<f64>,
+}
+
+impl TimingModel {
+    pub fn new(timings: &[ProcessingTiming]) -> Self {
+        let mut by_extension: HashMap<String, (i64, i64)> = HashMap::new();
+        let mut by_size: HashMap<i64, (i64, i64)> = HashMap::new();
+        let mut overall = (0, 0);
+        let mut model = Self::default();
+        for timing in timings.iter().filter(|timing| timing.jobs > 0) {
+            model.by_extension_and_size.insert(
+                (timing.extension.clone(), timing.size_bucket),
+                timing.total_ms as f64 / timing.jobs as f64,
+            );
+            for totals in [
+                by_extension.entry(timing.extension.clone()).or_default(),
+                by_size.entry(timing.size_bucket).or_default(),
+                &mut overall,
+            ] {
+                totals.0 += timing.jobs;
+                totals.1 += timing.total_ms;
+            }
+        }
+
+        let average = |(jobs, total_ms): (i64, i64)| total_ms as f64 / jobs as f64;
+        model.by_extension = by_extension.into_iter().map(|(extension, totals)| (extension, average(totals))).collect();
+        model.by_size = by_size.into_iter().map(|(bucket, totals)| (bucket, average(totals))).collect();
+        model.overall = (overall.0 > 0).then(|| average(overall));
+        model
+    }
+
+    /// Expected milliseconds for a file, from the closest match that has been timed
+    pub fn estimate_ms(&self, extension: &str, size: i64) -> f64 {
+        let bucket = size_bucket(size);
+        self.by_extension_and_size.get(&(extension.to_string(), bucket))
+            .or_else(|| self.by_extension.get(extension))
+            .or_else(|| self.by_size.get(&bucket))
+            .copied()
+            .or(self.overall)
+            .unwrap_or(DEFAULT_JOB_MS)
+    }
+}
+
+/// The queued work of one watched folder
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEstimate {
+    pub path: String,
+    pub queued_jobs: usize,
+    /// With all workers on the folder's files
+    pub estimated_seconds: f64,
+}
+
+/// Seconds to process all queued files with `workers` at once, and the share of each watched
+/// folder. Files outside every folder count towards the total only.
+pub fn estimate_completion(
+    model: &TimingModel,
+    queued: &[QueuedFile],
+    watch_paths: &[PathBuf],
+    workers: usize,
+) -> (f64, Vec<PathEstimate>) {
+    let workers = workers.max(1) as f64;
+    let mut total_ms = 0.0;
+    let mut per_path: Vec<(usize, f64)> = vec![(0, 0.0); watch_paths.len()];
+    for file in queued {
+        let ms = model.estimate_ms(&file.extension, file.size);
+        total_ms += ms;
+        // Nested folders count the file for the innermost one
+        let folder = watch_paths.iter()
+            .enumerate()
+            .filter(|(_, root)| Path::new(&file.file_path).starts_with(root))
+            .max_by_key(|(_, root)| root.components().count());
+        if let Some((index, _)) = folder {
+            per_path[index].0 += 1;
+            per_path[index].1 += ms;
+        }
+    }
+
+    let estimates = watch_paths.iter()
+        .zip(per_path)
+        .map(|(path, (queued_jobs, ms))| PathEstimate {
+            path: path.to_string_lossy().to_string(),
+            queued_jobs,
+            estimated_seconds: ms / 1000.0 / workers,
+        })
+        .collect();
+    (total_ms / 1000.0 / workers, estimates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(extension: &str, size_bucket: i64, jobs: i64, total_ms: i64) -> ProcessingTiming {
+        ProcessingTiming { extension: extension.to_string(), size_bucket, jobs, total_ms }
+    }
+
+    #[test]
+    fn test_estimate_falls_back_to_less_specific_timings() {
+        let model = TimingModel::new(&[timing("pdf", 2, 2, 8000), timing("pdf", 0, 2, 2000), timing("txt", 0, 4, 400)]);
+        assert_eq!(model.estimate_ms("pdf", 100 * 1024), 4000.0);
+        // Other sizes of a known extension
+        assert_eq!(model.estimate_ms("pdf", 1024 * 1024 * 1024), 2500.0);
+        // Other extensions of a known size
+        assert_eq!(model.estimate_ms("docx", 1024), 400.0);
+        assert_eq!(model.estimate_ms("docx", 100 * 1024), 4000.0);
+        assert_eq!(model.estimate_ms("docx", 1024 * 1024 * 1024), 1300.0);
+        assert_eq!(TimingModel::new(&[]).estimate_ms("pdf", 0), DEFAULT_JOB_MS);
+    }
+
+    #[test]
+    fn test_estimate_completion_per_watch_path() {
+        let model = TimingModel::new(&[timing("txt", 0, 1, 1000)]);
+        let queued: Vec<QueuedFile> = ["/docs/a.txt", "/docs/notes/b.txt", "/docs/notes/c.txt", "/tmp/d.txt"]
+            .into_iter()
+            .map(|path| QueuedFile { file_path: path.to_string(), extension: "txt".to_string(), size: 10 })
+            .collect();
+        let watch_paths = vec![PathBuf::from("/docs"), PathBuf::from("/docs/notes")];
+
+        let (total_seconds, estimates) = estimate_completion(&model, &queued, &watch_paths, 2);
+        assert_eq!(total_seconds, 2.0);
+        assert_eq!(estimates[0].queued_jobs, 1);
+        assert_eq!(estimates[0].estimated_seconds, 0.5);
+        assert_eq!(estimates[1].queued_jobs, 2);
+        assert_eq!(estimates[1].estimated_seconds, 1.0);
+    }
+}
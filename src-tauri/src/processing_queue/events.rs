@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use super::ProcessingJob;
+
+/// Where a processing job stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStage {
+    Queued,
+    Extracting,
+    Analyzing,
+    Embedding,
+    Done,
+    Failed,
+}
+
+impl JobStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStage::Queued => "queued",
+            JobStage::Extracting => "extracting",
+            JobStage::Analyzing => "analyzing",
+            JobStage::Embedding => "embedding",
+            JobStage::Done => "done",
+            JobStage::Failed => "failed",
+        }
+    }
+}
+
+/// A processing job entering a stage, as shown to the user while files are processed
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub job_id: String,
+    pub file_id: String,
+    pub file_path: String,
+    pub stage: JobStage,
+    /// Attempts before this one
+    pub retry_count: u32,
+    /// Since the job left the queue; zero while queued
+    pub elapsed_ms: u64,
+    /// Spent in the stage before this one
+    pub previous_stage_ms: u64,
+    /// Why the job failed
+    pub error: Option<String>,
+    /// A failed job is queued again
+    pub will_retry: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Publishes the stages of one job run on the queue's event channel
+pub struct JobReporter {
+    sender: broadcast::Sender<JobEvent>,
+    job_id: String,
+    file_id: String,
+    file_path: String,
+    retry_count: u32,
+    current_stage: JobStage,
+    /// Time spent in each working stage left so far, in milliseconds
+    stage_durations: Vec<(JobStage, u64)>,
+    started: Instant,
+    stage_started: Instant,
+}
+
+impl JobReporter {
+    pub fn new(sender: broadcast::Sender<JobEvent>, job: &ProcessingJob) -> Self {
+        let now = Instant::now();
+        Self {
+            sender,
+            job_id: job.id.clone(),
+            file_id: job.file_id.clone(),
+            file_path: job.file_path.clone(),
+            retry_count: job.retry_count,
+            current_stage: JobStage::Queued,
+            stage_durations: Vec::new(),
+            started: now,
+            stage_started: now,
+        }
+    }
+
+    /// A job put on the queue, which has no timing yet
+    pub fn queued(sender: &broadcast::Sender<JobEvent>, job: &ProcessingJob) {
+        // Nobody may be listening, which is fine
+        let _ = sender.send(JobEvent {
+            job_id: job.id.clone(),
+            file_id: job.file_id.clone(),
+            file_path: job.file_path.clone(),
+            stage: JobStage::Queued,
+            retry_count: job.retry_count,
+            elapsed_ms: 0,
+            previous_stage_ms: 0,
+            error: None,
+            will_retry: false,
+            timestamp: Utc::now(),
+        });
+    }
+
+    /// The stage the job reached last, which is where it failed once it fails
+    pub fn current_stage(&self) -> JobStage {
+        self.current_stage
+    }
+
+    /// Milliseconds spent in `stage`, once the job has moved past it
+    pub fn stage_ms(&self, stage: JobStage) -> Option<u64> {
+        self.stage_durations.iter().find(|(done, _)| *done == stage).map(|(_, ms)| *ms)
+    }
+
+    pub fn stage(&mut self, stage: JobStage) {
+        self.send(stage, None, false);
+    }
+
+    pub fn failed(&mut self, error: &anyhow::Error, will_retry: bool) {
+        // The failing stage stays current, for the dead-letter record
+        self.send(JobStage::Failed, Some(format!("{:#}", error)), will_retry);
+    }
+
+    fn send(&mut self, stage: JobStage, error: Option<String>, will_retry: bool) {
+        let now = Instant::now();
+        let previous_stage_ms = now.duration_since(self.stage_started).as_millis() as u64;
+        if matches!(self.current_stage, JobStage::Extracting | JobStage::Analyzing | JobStage::Embedding) {
+            self.stage_durations.push((self.current_stage, previous_stage_ms));
+        }
+        let _ = self.sender.send(JobEvent {
+            job_id: self.job_id.clone(),
+            file_id: self.file_id.clone(),
+            file_path: self.file_path.clone(),
+            stage,
+            retry_count: self.retry_count,
+            elapsed_ms: now.duration_since(self.started).as_millis() as u64,
+            previous_stage_ms,
+            error,
+            will_retry,
+            timestamp: Utc::now(),
+        });
+        self.stage_started = now;
+        if stage != JobStage::Failed {
+            self.current_stage = stage;
+        }
+    }
+}
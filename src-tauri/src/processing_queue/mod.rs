@@ -1,13 +1,48 @@
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
-use std::collections::VecDeque;
-use tokio::sync::{RwLock, Semaphore};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use sysinfo::{ProcessRefreshKind, System, SystemExt};
+use tokio::sync::{broadcast, RwLock, Semaphore};
 use tokio::time::{interval, Duration, Instant};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
 use crate::database::{Database, FileRecord};
-use crate::content_extractor::ContentExtractor;
+use crate::database::jobs::{DeadJob, ProcessingAttempt, StoredJob};
+use crate::content_extractor::{ContentExtractor, ExtractionSettings};
 use crate::ai_processor::AIProcessor;
+use crate::chunking::{self, ChunkingConfig};
+use crate::vector_storage::VectorStorageManager;
+
+mod backpressure;
+mod estimate;
+mod events;
+mod power;
+mod scaling;
+mod scheduling;
+mod timeouts;
+mod window;
+
+pub use backpressure::{Backpressure, DEFAULT_QUEUE_CAPACITY};
+pub use events::{JobEvent, JobStage};
+use events::JobReporter;
+pub use power::PowerPolicy;
+use power::{PowerMode, PowerMonitor};
+pub use scaling::AdaptiveScaling;
+pub use scheduling::{JobCategory, SchedulingPolicy};
+pub use timeouts::StageTimeouts;
+use timeouts::{StagePanic, StageTimeout};
+pub use window::ProcessingWindow;
+use window::{WindowMonitor, WindowState};
+
+/// Pending files queued again per batch at startup
+const REQUEUE_BATCH: usize = 500;
 
 #[derive(Debug, Clone)]
 pub struct ProcessingJob {
@@ -17,24 +52,170 @@ pub struct ProcessingJob {
     pub priority: JobPriority,
     pub created_at: Instant,
     pub retry_count: u32,
+    pub file_size: u64,
+    pub category: JobCategory,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+impl ProcessingJob {
+    fn new(file_id: String, file_path: String, file_size: u64, priority: JobPriority, policy: &SchedulingPolicy) -> Self {
+        let category = JobCategory::of(Path::new(&file_path), file_size, policy.large_file_bytes());
+        Self {
+            id: Uuid::new_v4().to_string(),
+            file_id,
+            file_path,
+            priority,
+            created_at: Instant::now(),
+            retry_count: 0,
+            file_size,
+            category,
+        }
+    }
+
+    fn to_stored(&self, state: &str) -> StoredJob {
+        StoredJob {
+            id: self.id.clone(),
+            file_id: self.file_id.clone(),
+            file_path: self.file_path.clone(),
+            priority: self.priority.as_str().to_string(),
+            state: state.to_string(),
+            retry_count: self.retry_count as i64,
+            created_at: Utc::now() - chrono::Duration::from_std(self.created_at.elapsed()).unwrap_or_default(),
+            file_size: self.file_size as i64,
+        }
+    }
+
+    fn to_dead(&self, stage: JobStage, error: &anyhow::Error) -> DeadJob {
+        let stored = self.to_stored("failed");
+        DeadJob {
+            id: stored.id,
+            file_id: stored.file_id,
+            file_path: stored.file_path,
+            priority: stored.priority,
+            stage: stage.as_str().to_string(),
+            kind: failure_kind(error).to_string(),
+            error: format!("{:#}", error),
+            retry_count: stored.retry_count,
+            created_at: stored.created_at,
+            failed_at: Utc::now(),
+        }
+    }
+
+    /// This run of the job for the history, with the stage durations the reporter saw
+    fn to_attempt(
+        &self,
+        reporter: &JobReporter,
+        started_at: DateTime<Utc>,
+        model: Option<String>,
+        error: Option<&anyhow::Error>,
+    ) -> ProcessingAttempt {
+        let stage_ms = |stage| reporter.stage_ms(stage).map(|ms| ms as i64);
+        let result = error.map_or("completed", failure_kind);
+        ProcessingAttempt {
+            job_id: self.id.clone(),
+            file_id: self.file_id.clone(),
+            file_path: self.file_path.clone(),
+            attempt: self.retry_count as i64,
+            started_at,
+            finished_at: Utc::now(),
+            extraction_ms: stage_ms(JobStage::Extracting),
+            analysis_ms: stage_ms(JobStage::Analyzing),
+            embedding_ms: stage_ms(JobStage::Embedding),
+            model,
+            result: result.to_string(),
+            error: error.map(|e| format!("{:#}", e)),
+        }
+    }
+
+    fn from_stored(stored: StoredJob, policy: &SchedulingPolicy) -> Result<Self> {
+        let age = (Utc::now() - stored.created_at).to_std().unwrap_or_default();
+        let file_size = stored.file_size.max(0) as u64;
+        let mut job = Self::new(stored.file_id, stored.file_path, file_size, stored.priority.parse()?, policy);
+        job.id = stored.id;
+        job.created_at = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+        job.retry_count = stored.retry_count as u32;
+        Ok(job)
+    }
+
+    /// Higher priorities first, then the policy's order of kinds of file, which a job moves up
+    /// in the longer it waits, then older jobs; `Less` runs first
+    fn schedule_order(&self, other: &Self, policy: &SchedulingPolicy) -> std::cmp::Ordering {
+        let rank = |job: &Self| policy.aged_rank(job.category, job.created_at.elapsed());
+        other.priority.cmp(&self.priority)
+            .then_with(|| rank(self).cmp(&rank(other)))
+            .then_with(|| self.created_at.cmp(&other.created_at))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum JobPriority {
     Low = 1,
+    #[default]
     Normal = 2,
     High = 3,
     Critical = 4,
 }
 
+impl JobPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobPriority::Low => "low",
+            JobPriority::Normal => "normal",
+            JobPriority::High => "high",
+            JobPriority::Critical => "critical",
+        }
+    }
+}
+
+impl std::str::FromStr for JobPriority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "low" => Ok(JobPriority::Low),
+            "normal" => Ok(JobPriority::Normal),
+            "high" => Ok(JobPriority::High),
+            "critical" => Ok(JobPriority::Critical),
+            _ => Err(anyhow!("Invalid job priority: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ProcessingQueue {
     database: Database,
     ai_processor: AIProcessor,
     queue: Arc<RwLock<VecDeque<ProcessingJob>>>,
+    /// Jobs kept in memory at most; the lowest ranked of any more are spilled to the jobs table
+    capacity: Arc<AtomicUsize>,
+    /// Jobs waiting in the jobs table only, loaded back as the queue drains
+    spilled: Arc<AtomicUsize>,
+    backpressure: Backpressure,
+    /// Files with a job taken off the queue and when, until it finishes or is queued again to retry
+    in_flight: Arc<std::sync::Mutex<HashMap<String, Instant>>>,
+    /// Files changed again while their job was in flight, with the job to queue once it is done
+    changed_in_flight: Arc<std::sync::Mutex<HashMap<String, ProcessingJob>>>,
+    /// Jobs not queued because their file had one queued or in flight already
+    duplicates_suppressed: Arc<AtomicU64>,
+    timeouts: Arc<std::sync::RwLock<StageTimeouts>>,
+    extraction: Arc<std::sync::RwLock<ExtractionSettings>>,
+    /// Job runs given up because a stage ran past its limit
+    timed_out_jobs: Arc<AtomicU64>,
+    /// One permit per worker; a job only starts with one in hand
     processing_semaphore: Arc<Semaphore>,
-    max_concurrent_jobs: usize,
+    max_concurrent_jobs: Arc<AtomicUsize>,
+    /// Permits in circulation: the maximum, or fewer while on battery or adaptive scaling holds
+    /// some back
+    worker_limit: Arc<AtomicUsize>,
+    scaling: AdaptiveScaling,
+    power: PowerMonitor,
+    /// Hours or idle time in which the queue runs at full speed
+    window: WindowMonitor,
+    scheduling: Arc<std::sync::RwLock<SchedulingPolicy>>,
+    /// No new job starts while set; running ones finish
+    paused: Arc<AtomicBool>,
     max_retries: u32,
+    job_events: broadcast::Sender<JobEvent>,
 }
 
 impl ProcessingQueue {
@@ -47,86 +228,260 @@ impl ProcessingQueue {
             database,
             ai_processor,
             queue: Arc::new(RwLock::new(VecDeque::new())),
+            capacity: Arc::new(AtomicUsize::new(DEFAULT_QUEUE_CAPACITY)),
+            spilled: Arc::new(AtomicUsize::new(0)),
+            backpressure: Backpressure::default(),
+            in_flight: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            changed_in_flight: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            timeouts: Arc::new(std::sync::RwLock::new(StageTimeouts::default())),
+            extraction: Arc::new(std::sync::RwLock::new(ExtractionSettings::default())),
+            timed_out_jobs: Arc::new(AtomicU64::new(0)),
+            duplicates_suppressed: Arc::new(AtomicU64::new(0)),
             processing_semaphore: Arc::new(Semaphore::new(max_concurrent_jobs)),
-            max_concurrent_jobs,
+            max_concurrent_jobs: Arc::new(AtomicUsize::new(max_concurrent_jobs)),
+            worker_limit: Arc::new(AtomicUsize::new(max_concurrent_jobs)),
+            scaling: AdaptiveScaling::new(false),
+            power: PowerMonitor::default(),
+            window: WindowMonitor::default(),
+            scheduling: Arc::new(std::sync::RwLock::new(SchedulingPolicy::default())),
+            paused: Arc::new(AtomicBool::new(false)),
             max_retries: 3,
+            job_events: broadcast::channel(256).0,
         }
     }
 
     pub async fn start_processing(&self) -> Result<()> {
+        // No worker runs yet, so any file still `processing` was left by a crash
+        let reset = self.database.reset_processing_files().await?;
+        if reset > 0 {
+            tracing::warn!("Reset {} files left processing by the last session to pending", reset);
+        }
+        self.restore_jobs().await?;
+
         // Start the main processing loop
         let queue = self.queue.clone();
+        let in_flight = self.in_flight.clone();
+        let changed_in_flight = self.changed_in_flight.clone();
         let database = self.database.clone();
         let ai_processor = self.ai_processor.clone();
-        let _semaphore = self.processing_semaphore.clone();
+        let semaphore = self.processing_semaphore.clone();
         let max_retries = self.max_retries;
+        let job_events = self.job_events.clone();
+        let paused = self.paused.clone();
+        let power = self.power.clone();
+        let window = self.window.clone();
+        let timeouts = self.timeouts.clone();
+        let extraction = self.extraction.clone();
+        let timed_out_jobs = self.timed_out_jobs.clone();
+        let capacity = self.capacity.clone();
+        let spilled = self.spilled.clone();
+        let backpressure = self.backpressure.clone();
+        let scheduling = self.scheduling.clone();
 
         tokio::spawn(async move {
             let mut interval = interval(Duration::from_millis(100));
             
             loop {
-                interval.tick().await;
+                if paused.load(Ordering::SeqCst) || power.mode() == PowerMode::Paused || window.state() == WindowState::Paused {
+                    interval.tick().await;
+                    continue;
+                }
+
+                // Spilled jobs come back once the queue in memory is down to half its capacity
+                let queue_capacity = capacity.load(Ordering::SeqCst);
+                if spilled.load(Ordering::SeqCst) > 0 && queue.read().await.len() <= queue_capacity / 2 {
+                    let policy = scheduling.read().unwrap().clone();
+                    Self::load_spilled_jobs(&database, &queue, &spilled, queue_capacity, &policy).await;
+                }
+
+                // Wait for a free worker before taking a job off the queue
+                let Ok(permit) = semaphore.clone().acquire_owned().await else {
+                    break;
+                };
                 
                 // Get next job from queue
                 let job = {
                     let mut queue_guard = queue.write().await;
-                    queue_guard.pop_front()
+                    let job = queue_guard.pop_front();
+                    backpressure.update(queue_guard.len() + spilled.load(Ordering::SeqCst), queue_capacity);
+                    if let Some(job) = &job {
+                        in_flight.lock().unwrap().insert(job.file_id.clone(), Instant::now());
+                    }
+                    job
                 };
                 
                 if let Some(job) = job {
-                    // Simplified processing without semaphore for now
                     let db = database.clone();
                     let ai = ai_processor.clone();
                     let queue_for_retry = queue.clone();
+                    let in_flight = in_flight.clone();
+                    let changed_in_flight = changed_in_flight.clone();
+                    let policy = scheduling.read().unwrap().clone();
+                    let use_ai = power.allows_ai();
+                    let timeouts = timeouts.read().unwrap().clone();
+                    let extraction = extraction.read().unwrap().clone();
+                    let timed_out_jobs = timed_out_jobs.clone();
+                    let job_events = job_events.clone();
                     
                     tokio::spawn(async move {
-                        if let Err(e) = Self::process_job(&db, &ai, &job).await {
-                            tracing::error!("Job {} failed: {}", job.id, e);
-                            
-                            // Retry logic
-                            if job.retry_count < max_retries {
-                                let mut retry_job = job.clone();
-                                retry_job.retry_count += 1;
-                                retry_job.created_at = Instant::now();
-                                
-                                // Add delay before retry
-                                tokio::time::sleep(Duration::from_secs(2u64.pow(retry_job.retry_count))).await;
-                                
-                                let mut queue_guard = queue_for_retry.write().await;
-                                queue_guard.push_back(retry_job);
-                            } else {
-                                // Mark as failed in database
-                                if let Err(e) = db.update_file_status(&job.file_id, "error", Some(&e.to_string())).await {
-                                    tracing::error!("Failed to update file status: {}", e);
+                        if let Err(e) = db.set_job_state(&job.id, "running").await {
+                            tracing::warn!("Failed to store state of job {}: {}", job.id, e);
+                        }
+
+                        let mut reporter = JobReporter::new(job_events.clone(), &job);
+                        let started = Instant::now();
+                        let started_at = Utc::now();
+                        // A panic fails this job like any error, so it is retried and the file does
+                        // not stay `processing`
+                        let result = AssertUnwindSafe(Self::process_job(&db, &ai, &job, use_ai, &timeouts, &extraction, &mut reporter))
+                            .catch_unwind()
+                            .await
+                            .unwrap_or_else(|panic| Err(anyhow!("Processing panicked: {}", panic_message(panic.as_ref()))));
+                        // The worker is free for the next job while this one waits to retry
+                        drop(permit);
+                        let e = match result {
+                            Ok(model) => {
+                                reporter.stage(JobStage::Done);
+                                Self::record_attempt(&db, &job.to_attempt(&reporter, started_at, model, None)).await;
+                                if let Err(e) = db.record_processing_time(&job.file_id, started.elapsed().as_millis() as u64).await {
+                                    tracing::warn!("Failed to record processing time of job {}: {}", job.id, e);
                                 }
+                                if let Err(e) = db.remove_jobs(std::slice::from_ref(&job.id)).await {
+                                    tracing::warn!("Failed to remove finished job {}: {}", job.id, e);
+                                }
+                                Self::leave_in_flight(&db, &queue_for_retry, &in_flight, &changed_in_flight, &job.file_id, &policy, &job_events).await;
+                                return;
                             }
+                            Err(e) => e,
+                        };
+                        tracing::error!("Job {} failed: {}", job.id, e);
+                        if e.is::<StageTimeout>() {
+                            timed_out_jobs.fetch_add(1, Ordering::Relaxed);
+                        }
+                        reporter.failed(&e, job.retry_count < max_retries && is_retryable(&e));
+                        Self::record_attempt(&db, &job.to_attempt(&reporter, started_at, None, Some(&e))).await;
+                        
+                        // Retry logic; a file that panicked the parser would only panic it again, and
+                        // one that wedged it would leave another blocked thread behind each time
+                        if job.retry_count < max_retries && is_retryable(&e) {
+                            let mut retry_job = job.clone();
+                            retry_job.retry_count += 1;
+                            // The retry reads the file afresh, so it covers a change made meanwhile
+                            if let Some(changed) = changed_in_flight.lock().unwrap().remove(&job.file_id) {
+                                retry_job.priority = retry_job.priority.clone().max(changed.priority);
+                            }
+                            retry_job.created_at = Instant::now();
+                            // Stored before the delay, so a restart meanwhile still retries it
+                            if let Err(e) = db.save_jobs(&[retry_job.to_stored("queued")]).await {
+                                tracing::warn!("Failed to store retry of job {}: {}", job.id, e);
+                            }
+                            
+                            // Add delay before retry
+                            tokio::time::sleep(Duration::from_secs(2u64.pow(retry_job.retry_count))).await;
+                            
+                            JobReporter::queued(&job_events, &retry_job);
+                            let mut queue_guard = queue_for_retry.write().await;
+                            in_flight.lock().unwrap().remove(&retry_job.file_id);
+                            queue_guard.push_back(retry_job);
+                        } else {
+                            // Mark as failed in database
+                            if let Err(e) = db.update_file_status(&job.file_id, "error", Some(&e.to_string())).await {
+                                tracing::error!("Failed to update file status: {}", e);
+                            }
+                            let dead_job = job.to_dead(reporter.current_stage(), &e);
+                            if let Err(e) = db.add_dead_job(&dead_job).await {
+                                tracing::warn!("Failed to store failed job {}: {}", job.id, e);
+                            }
+                            if let Err(e) = db.remove_jobs(std::slice::from_ref(&job.id)).await {
+                                tracing::warn!("Failed to remove failed job {}: {}", job.id, e);
+                            }
+                            Self::leave_in_flight(&db, &queue_for_retry, &in_flight, &changed_in_flight, &job.file_id, &policy, &job_events).await;
                         }
                     });
+                } else {
+                    drop(permit);
+                    interval.tick().await;
                 }
             }
         });
 
         // Start periodic queue maintenance
         self.start_queue_maintenance().await;
+        self.start_power_monitoring();
+        self.start_window_monitoring();
+        self.start_adaptive_scaling();
         
-        tracing::info!("Processing queue started with {} workers", self.max_concurrent_jobs);
+        tracing::info!("Processing queue started with {} workers", self.max_concurrent_jobs());
         Ok(())
     }
 
+    /// Take a finished job's file out of flight, and queue the file again if it changed while
+    /// the job ran. The queue lock is held throughout so `enqueue` sees one or the other.
+    async fn leave_in_flight(
+        database: &Database,
+        queue: &RwLock<VecDeque<ProcessingJob>>,
+        in_flight: &std::sync::Mutex<HashMap<String, Instant>>,
+        changed_in_flight: &std::sync::Mutex<HashMap<String, ProcessingJob>>,
+        file_id: &str,
+        policy: &SchedulingPolicy,
+        job_events: &broadcast::Sender<JobEvent>,
+    ) {
+        let mut queue = queue.write().await;
+        in_flight.lock().unwrap().remove(file_id);
+        let Some(job) = changed_in_flight.lock().unwrap().remove(file_id) else {
+            return;
+        };
+        if let Err(e) = database.save_jobs(&[job.to_stored("queued")]).await {
+            tracing::warn!("Failed to store processing job {}: {}", job.id, e);
+        }
+        tracing::debug!("Queued {} again, it changed while being processed", job.file_path);
+        JobReporter::queued(job_events, &job);
+        Self::insert_stored(&mut queue, vec![job], policy);
+    }
+
+    /// History is for explaining results, so failing to keep it does not fail the job
+    async fn record_attempt(database: &Database, attempt: &ProcessingAttempt) {
+        if let Err(e) = database.record_processing_attempt(attempt).await {
+            tracing::warn!("Failed to record processing attempt of job {}: {}", attempt.job_id, e);
+        }
+    }
+
+    /// Returns the AI model the content was analyzed with, or none for the basic analysis
     async fn process_job(
         database: &Database,
         ai_processor: &AIProcessor,
         job: &ProcessingJob,
-    ) -> Result<()> {
+        use_ai: bool,
+        timeouts: &StageTimeouts,
+        extraction: &ExtractionSettings,
+        reporter: &mut JobReporter,
+    ) -> Result<Option<String>> {
         tracing::debug!("Processing job {} for file {}", job.id, job.file_path);
         
         // Update status to processing
         database.update_file_status(&job.file_id, "processing", None).await?;
+        reporter.stage(JobStage::Extracting);
         
         let start_time = Instant::now();
         
-        // Extract content from file
-        let extracted_content = ContentExtractor::extract_content(&job.file_path).await?;
+        // Hash the raw bytes so exact duplicates can be grouped later, then extract content
+        // within the time limit for what the file really is
+        let sniffed = ContentExtractor::sniff(Path::new(&job.file_path)).await;
+        let limit_extension = sniffed.extension.clone();
+        let file_path = job.file_path.clone();
+        let extraction = extraction.clone();
+        let (hash, extracted_content) = timeouts::extracting(timeouts, &limit_extension, async move {
+            let hash = ContentExtractor::compute_file_hash(&file_path).await;
+            Ok((hash, ContentExtractor::extract_sniffed(Path::new(&file_path), sniffed, &extraction).await))
+        }).await?;
+        match hash {
+            Ok(hash) => database.update_file_hash(&job.file_id, &hash).await?,
+            Err(e) => tracing::warn!("Failed to hash {}: {}", job.file_path, e),
+        }
+        let extracted_content = extracted_content?;
+        if let Some(mime_type) = &extracted_content.metadata.mime_type {
+            database.update_file_mime_type(&job.file_id, mime_type).await?;
+        }
         
         tracing::debug!("Extracted content length: {} characters", extracted_content.text.len());
         
@@ -142,14 +497,19 @@ impl ProcessingQueue {
         };
         
         // Perform AI analysis if available
-        let (summary, tags_json, embedding) = if ai_processor.is_available().await {
+        reporter.stage(JobStage::Analyzing);
+        let mut model = None;
+        let (summary, tags_json, embedding, entities) = if use_ai && ai_processor.is_available().await {
             tracing::debug!("Performing AI analysis for file {}", job.file_path);
             
-            match ai_processor.analyze_content(&extracted_content).await {
+            match timeouts::within(timeouts, JobStage::Analyzing, ai_processor.analyze_content(&extracted_content)).await {
                 Ok(analysis) => {
+                    model = Some(ai_processor.model().to_string());
                     let tags_json = serde_json::to_string(&analysis.tags)?;
-                    (analysis.summary, Some(tags_json), analysis.embedding)
+                    (analysis.summary, Some(tags_json), analysis.embedding, analysis.key_entities)
                 }
+                // A model that hangs would hang the next file too, so this is not worked around
+                Err(e) if e.is::<StageTimeout>() => return Err(e),
                 Err(e) => {
                     tracing::warn!("AI analysis failed for {}: {}, falling back to basic analysis", job.file_path, e);
                     
@@ -161,7 +521,7 @@ impl ProcessingQueue {
                     };
                     let basic_tags = vec![extracted_content.file_type.clone()];
                     let tags_json = serde_json::to_string(&basic_tags)?;
-                    (simple_summary, Some(tags_json), None)
+                    (simple_summary, Some(tags_json), None, Vec::new())
                 }
             }
         } else {
@@ -175,7 +535,7 @@ impl ProcessingQueue {
             };
             let basic_tags = vec![extracted_content.file_type.clone()];
             let tags_json = serde_json::to_string(&basic_tags)?;
-            (simple_summary, Some(tags_json), None)
+            (simple_summary, Some(tags_json), None, Vec::new())
         };
         
         tracing::debug!("Updating database with content length: {}", truncated_content.len());
@@ -189,6 +549,44 @@ impl ProcessingQueue {
             embedding.as_deref(),
         ).await?;
         
+        if !entities.is_empty() {
+            database.update_file_entities(&job.file_id, &entities).await?;
+        }
+        
+        // Also clears location data kept from before it was turned off
+        match extracted_content.file_type.as_str() {
+            "image" => database.update_file_exif(&job.file_id, extracted_content.metadata.exif_data.as_ref()).await?,
+            "email" => database.update_file_email(&job.file_id, extracted_content.metadata.email.as_ref()).await?,
+            "code" => database.update_file_code(&job.file_id, extracted_content.metadata.code.as_ref()).await?,
+            "markup" => database.update_file_html(&job.file_id, extracted_content.metadata.html.as_ref()).await?,
+            "csv" => database.update_file_table(&job.file_id, extracted_content.metadata.table.as_ref()).await?,
+            "dataset" => database.update_file_dataset(&job.file_id, extracted_content.metadata.dataset.as_ref()).await?,
+            "database" => database.update_file_database(&job.file_id, extracted_content.metadata.database.as_ref()).await?,
+            "audio" => database.update_file_audio(&job.file_id, extracted_content.metadata.audio.as_ref()).await?,
+            "video" | "subtitles" => database.update_file_subtitles(&job.file_id, extracted_content.metadata.subtitles.as_ref()).await?,
+            _ => {}
+        }
+        
+        // Chunk vectors let semantic search reach past the start of long documents
+        if embedding.is_some() {
+            reporter.stage(JobStage::Embedding);
+            let embedded = timeouts::within(
+                timeouts,
+                JobStage::Embedding,
+                chunking::embed_chunks(ai_processor, &truncated_content, &ChunkingConfig::default()),
+            ).await;
+            // Chunk vectors are extra, so the job succeeds without them
+            let stored = match embedded {
+                Ok(chunks) => VectorStorageManager::new(database.pool.clone())
+                    .store_chunk_vectors(&job.file_id, &chunks, ai_processor.embedding_model())
+                    .await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = stored {
+                tracing::warn!("Failed to store chunk vectors for {}: {}", job.file_path, e);
+            }
+        }
+        
         let processing_time = start_time.elapsed();
         tracing::info!(
             "Successfully processed file {} in {:?}",
@@ -196,37 +594,500 @@ impl ProcessingQueue {
             processing_time
         );
         
-        Ok(())
+        Ok(model)
     }
 
     pub async fn add_job(&self, file_record: &FileRecord, priority: JobPriority) -> Result<()> {
-        let job = ProcessingJob {
-            id: Uuid::new_v4().to_string(),
-            file_id: file_record.id.clone(),
-            file_path: file_record.path.clone(),
-            priority,
-            created_at: Instant::now(),
-            retry_count: 0,
-        };
-        
+        self.add_jobs(std::slice::from_ref(file_record), priority).await
+    }
+
+    /// Queue a job for each file, storing them together first
+    pub async fn add_jobs(&self, file_records: &[FileRecord], priority: JobPriority) -> Result<()> {
+        let policy = self.scheduling_policy();
+        let jobs: Vec<ProcessingJob> = file_records.iter()
+            .map(|file_record| ProcessingJob::new(
+                file_record.id.clone(),
+                file_record.path.clone(),
+                file_record.size.max(0) as u64,
+                priority.clone(),
+                &policy,
+            ))
+            .collect();
+        self.enqueue(jobs).await
+    }
+
+    /// Queue the jobs, except for files with a job queued already, which takes the priority of
+    /// a more urgent duplicate instead. A file with a job in flight may have changed since that
+    /// job read it, so its job is held back and queued once the running one is done.
+    async fn enqueue(&self, jobs: Vec<ProcessingJob>) -> Result<()> {
+        let policy = self.scheduling_policy();
         let mut queue = self.queue.write().await;
+
+        let jobs: Vec<ProcessingJob> = {
+            let in_flight = self.in_flight.lock().unwrap();
+            let mut changed_in_flight = self.changed_in_flight.lock().unwrap();
+            jobs.into_iter()
+                .filter_map(|job| {
+                    if !in_flight.contains_key(&job.file_id) {
+                        return Some(job);
+                    }
+                    match changed_in_flight.get_mut(&job.file_id) {
+                        Some(held) => held.priority = held.priority.clone().max(job.priority),
+                        None => {
+                            changed_in_flight.insert(job.file_id.clone(), job);
+                        }
+                    }
+                    None
+                })
+                .collect()
+        };
+        let mut taken: HashSet<String> = queue.iter().map(|job| job.file_id.clone()).collect();
+        if self.spilled.load(Ordering::SeqCst) > 0 {
+            let file_ids: Vec<String> = jobs.iter().map(|job| job.file_id.clone()).collect();
+            taken.extend(self.database.spilled_file_ids_among(&file_ids).await?);
+        }
+        let mut new_jobs = Vec::with_capacity(jobs.len());
+        let mut raised = Vec::new();
+        for job in jobs {
+            if taken.insert(job.file_id.clone()) {
+                new_jobs.push(job);
+                continue;
+            }
+            self.duplicates_suppressed.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!("Skipped duplicate processing job for file: {}", job.file_path);
+            if let Some(earlier) = new_jobs.iter_mut().find(|earlier| earlier.file_id == job.file_id) {
+                earlier.priority = earlier.priority.clone().max(job.priority);
+            } else if let Some(queued) = queue.iter_mut().find(|queued| queued.file_id == job.file_id && queued.priority < job.priority) {
+                queued.priority = job.priority;
+                raised.push(queued.to_stored("queued"));
+            }
+        }
+        if !raised.is_empty() {
+            queue.make_contiguous().sort_by(|a, b| a.schedule_order(b, &policy));
+        }
+
+        let stored: Vec<StoredJob> = new_jobs.iter().map(|job| job.to_stored("queued")).chain(raised).collect();
+        self.database.save_jobs(&stored).await?;
         
-        // Insert job based on priority
-        let insert_pos = queue
-            .iter()
-            .position(|existing_job| existing_job.priority < job.priority)
-            .unwrap_or(queue.len());
-        
-        queue.insert(insert_pos, job);
+        // Insert job based on priority and kind of file
+        for job in new_jobs {
+            let insert_pos = queue
+                .iter()
+                .position(|existing_job| existing_job.schedule_order(&job, &policy).is_gt())
+                .unwrap_or(queue.len());
+            tracing::debug!("Added processing job for file: {}", job.file_path);
+            JobReporter::queued(&self.job_events, &job);
+            queue.insert(insert_pos, job);
+        }
+        self.spill_excess(&mut queue).await;
         
-        tracing::debug!("Added processing job for file: {}", file_record.path);
         Ok(())
     }
 
+    /// Move the lowest ranked jobs past the capacity to the jobs table, and signal backpressure
+    /// while the backlog stays at the capacity
+    async fn spill_excess(&self, queue: &mut VecDeque<ProcessingJob>) {
+        let capacity = self.queue_capacity();
+        if queue.len() > capacity {
+            let excess: Vec<ProcessingJob> = queue.split_off(capacity).into();
+            let ids: Vec<String> = excess.iter().map(|job| job.id.clone()).collect();
+            match self.database.set_jobs_state(&ids, "spilled").await {
+                Ok(()) => {
+                    self.spilled.fetch_add(ids.len(), Ordering::SeqCst);
+                    tracing::debug!("Spilled {} processing jobs to the store", ids.len());
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to spill processing jobs, keeping them in memory: {}", e);
+                    queue.extend(excess);
+                }
+            }
+        }
+        self.backpressure.update(queue.len() + self.spilled_jobs(), capacity);
+    }
+
+    /// Bring back as many spilled jobs as the queue has room for, highest priority first
+    async fn load_spilled_jobs(
+        database: &Database,
+        queue: &RwLock<VecDeque<ProcessingJob>>,
+        spilled: &AtomicUsize,
+        capacity: usize,
+        policy: &SchedulingPolicy,
+    ) {
+        let mut queue = queue.write().await;
+        let room = capacity.saturating_sub(queue.len());
+        let stored = match database.load_spilled_jobs(room as i64).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                tracing::warn!("Failed to load spilled processing jobs: {}", e);
+                return;
+            }
+        };
+        if stored.is_empty() {
+            // Spilled jobs were removed some other way, e.g. by clearing the queue
+            spilled.store(0, Ordering::SeqCst);
+            return;
+        }
+
+        let ids: Vec<String> = stored.iter().map(|job| job.id.clone()).collect();
+        if let Err(e) = database.set_jobs_state(&ids, "queued").await {
+            tracing::warn!("Failed to load spilled processing jobs: {}", e);
+            return;
+        }
+        let _ = spilled.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| Some(count.saturating_sub(ids.len())));
+        let jobs: Vec<ProcessingJob> = stored.into_iter()
+            .filter_map(|stored| {
+                let id = stored.id.clone();
+                ProcessingJob::from_stored(stored, policy)
+                    .map_err(|e| tracing::warn!("Skipping stored job {}: {}", id, e))
+                    .ok()
+            })
+            .collect();
+        tracing::debug!("Loaded {} spilled processing jobs back into the queue", jobs.len());
+        Self::insert_stored(&mut queue, jobs, policy);
+    }
+
+    /// Put jobs from the store in their place, ahead of jobs of the same place queued after them
+    fn insert_stored(queue: &mut VecDeque<ProcessingJob>, mut jobs: Vec<ProcessingJob>, policy: &SchedulingPolicy) {
+        jobs.sort_by(|a, b| a.schedule_order(b, policy));
+        for job in jobs.into_iter().rev() {
+            let insert_pos = queue
+                .iter()
+                .position(|existing_job| existing_job.schedule_order(&job, policy).is_ge())
+                .unwrap_or(queue.len());
+            queue.insert(insert_pos, job);
+        }
+    }
+
+    /// Jobs that failed on their last retry, most recent first
+    pub async fn get_dead_jobs(&self) -> Result<Vec<DeadJob>> {
+        self.database.get_dead_jobs().await
+    }
+
+    /// Queue the failed jobs `ids` again from a fresh start, or all of them when `ids` is empty.
+    /// Jobs of files no longer on disk are dismissed instead. Returns how many were queued.
+    pub async fn retry_dead_jobs(&self, ids: &[String]) -> Result<usize> {
+        let dead_jobs: Vec<DeadJob> = self.database.get_dead_jobs().await?
+            .into_iter()
+            .filter(|job| ids.is_empty() || ids.contains(&job.id))
+            .collect();
+        let policy = self.scheduling_policy();
+        let mut jobs = Vec::with_capacity(dead_jobs.len());
+        let mut gone = 0;
+        for dead_job in &dead_jobs {
+            let Ok(metadata) = tokio::fs::metadata(&dead_job.file_path).await else {
+                gone += 1;
+                continue;
+            };
+            self.database.update_file_status(&dead_job.file_id, "pending", None).await?;
+            jobs.push(ProcessingJob::new(
+                dead_job.file_id.clone(),
+                dead_job.file_path.clone(),
+                metadata.len(),
+                dead_job.priority.parse().unwrap_or_default(),
+                &policy,
+            ));
+        }
+        let queued = jobs.len();
+        self.enqueue(jobs).await?;
+
+        let retried: Vec<String> = dead_jobs.into_iter().map(|job| job.id).collect();
+        self.database.remove_dead_jobs(&retried).await?;
+        tracing::info!("Queued {} failed jobs again, dismissed {} of missing files", queued, gone);
+        Ok(queued)
+    }
+
+    /// Forget the failed jobs `ids`, or every one of them when `all` is set; their files stay in error
+    pub async fn dismiss_dead_jobs(&self, ids: &[String], all: bool) -> Result<u64> {
+        let ids = if all {
+            self.database.get_dead_jobs().await?.into_iter().map(|job| job.id).collect()
+        } else {
+            ids.to_vec()
+        };
+        self.database.remove_dead_jobs(&ids).await
+    }
+
+    /// Put the jobs stored by the last session back on the queue, by priority, kind of file
+    /// and then age
+    async fn restore_jobs(&self) -> Result<()> {
+        let policy = self.scheduling_policy();
+        self.spilled.store(self.database.count_spilled_jobs().await? as usize, Ordering::SeqCst);
+        let mut jobs = Vec::new();
+        for stored in self.database.load_jobs().await? {
+            let id = stored.id.clone();
+            match ProcessingJob::from_stored(stored, &policy) {
+                Ok(job) => jobs.push(job),
+                Err(e) => tracing::warn!("Skipping stored job {}: {}", id, e),
+            }
+        }
+        if jobs.is_empty() && self.spilled_jobs() == 0 {
+            return Ok(());
+        }
+
+        let mut queue = self.queue.write().await;
+        let restored = jobs.len() + self.spilled_jobs();
+        // Jobs added before the restore are newer, so they go behind restored ones of their place
+        Self::insert_stored(&mut queue, jobs, &policy);
+        self.spill_excess(&mut queue).await;
+        tracing::info!("Restored {} processing jobs from the last session", restored);
+        Ok(())
+    }
+
+    /// Give the queued jobs of files below `folder` a new priority. They move ahead of or behind
+    /// the other jobs accordingly and keep their order among jobs of the same priority. Critical
+    /// jobs are for files the user is looking at, and stay critical.
+    pub async fn reprioritize_folder(&self, folder: &Path, priority: JobPriority) -> usize {
+        let queued = self.reprioritize_where(priority.clone(), |job| {
+            job.priority != JobPriority::Critical && Path::new(&job.file_path).starts_with(folder)
+        }).await;
+        let spilled = match self.spilled_jobs() {
+            0 => 0,
+            _ => self.database.set_spilled_priority_below(&folder.to_string_lossy(), priority.as_str()).await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to store priorities of spilled jobs: {}", e);
+                    0
+                }),
+        };
+        queued + spilled as usize
+    }
+
+    /// Move the queued jobs of `file_ids`, e.g. files the user is looking at, to critical so
+    /// they are processed before the backlog. Returns how many jobs moved.
+    pub async fn prioritize_files(&self, file_ids: &[String]) -> usize {
+        let wanted: HashSet<&String> = file_ids.iter().collect();
+        let queued = self.reprioritize_where(JobPriority::Critical, |job| wanted.contains(&job.file_id)).await;
+        // Spilled jobs come back highest priority first, so these are loaded next
+        let spilled = match self.spilled_jobs() {
+            0 => 0,
+            _ => self.database.set_spilled_priority_for_files(file_ids, JobPriority::Critical.as_str()).await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to store priorities of spilled jobs: {}", e);
+                    0
+                }),
+        };
+        queued + spilled as usize
+    }
+
+    async fn reprioritize_where(&self, priority: JobPriority, matches: impl Fn(&ProcessingJob) -> bool) -> usize {
+        let mut queue = self.queue.write().await;
+        let mut changed = Vec::new();
+        for job in queue.iter_mut() {
+            if job.priority != priority && matches(job) {
+                job.priority = priority.clone();
+                changed.push(job.id.clone());
+            }
+        }
+        if changed.is_empty() {
+            return 0;
+        }
+        let policy = self.scheduling_policy();
+        queue.make_contiguous().sort_by(|a, b| a.schedule_order(b, &policy));
+        if let Err(e) = self.database.set_jobs_priority(&changed, priority.as_str()).await {
+            tracing::warn!("Failed to store job priorities: {}", e);
+        }
+        changed.len()
+    }
+
+    pub fn queue_capacity(&self) -> usize {
+        self.capacity.load(Ordering::SeqCst)
+    }
+
+    /// Jobs to keep in memory at most. A lower capacity takes effect as jobs are next added.
+    pub fn set_queue_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity.max(1), Ordering::SeqCst);
+    }
+
+    /// Jobs waiting in the jobs table because the queue in memory was full
+    pub fn spilled_jobs(&self) -> usize {
+        self.spilled.load(Ordering::SeqCst)
+    }
+
+    /// The signal the file monitor slows event handling down on while the queue is full
+    pub fn backpressure(&self) -> Backpressure {
+        self.backpressure.clone()
+    }
+
+    pub fn stage_timeouts(&self) -> StageTimeouts {
+        self.timeouts.read().unwrap().clone()
+    }
+
+    /// Limits for the stages of jobs that start from now on
+    pub fn set_stage_timeouts(&self, timeouts: StageTimeouts) {
+        *self.timeouts.write().unwrap() = timeouts;
+    }
+
+    /// How jobs that start from now on extract their files
+    pub fn set_extraction_settings(&self, settings: ExtractionSettings) {
+        *self.extraction.write().unwrap() = settings;
+    }
+
+    pub fn scheduling_policy(&self) -> SchedulingPolicy {
+        self.scheduling.read().unwrap().clone()
+    }
+
+    /// Change the order of kinds of file within a priority, reordering the queued jobs
+    pub async fn set_scheduling_policy(&self, policy: SchedulingPolicy) {
+        let mut queue = self.queue.write().await;
+        for job in queue.iter_mut() {
+            job.category = JobCategory::of(Path::new(&job.file_path), job.file_size, policy.large_file_bytes());
+        }
+        queue.make_contiguous().sort_by(|a, b| a.schedule_order(b, &policy));
+        *self.scheduling.write().unwrap() = policy;
+    }
+
+    /// Each job's stages as it goes through them, from queued to done or failed
+    pub fn subscribe_job_events(&self) -> broadcast::Receiver<JobEvent> {
+        self.job_events.subscribe()
+    }
+
+    pub fn max_concurrent_jobs(&self) -> usize {
+        self.max_concurrent_jobs.load(Ordering::SeqCst)
+    }
+
+    /// Change how many jobs run at most at once. Extra workers start right away, unless adaptive
+    /// scaling adds them as load allows; when there are fewer, running jobs finish first.
+    pub fn set_max_concurrent_jobs(&self, max_concurrent_jobs: usize) {
+        let max_concurrent_jobs = max_concurrent_jobs.max(1);
+        self.max_concurrent_jobs.store(max_concurrent_jobs, Ordering::SeqCst);
+        let cap = Self::worker_cap(&self.power, &self.window, max_concurrent_jobs);
+        let workers = match self.scaling.is_enabled() {
+            true => self.worker_limit.load(Ordering::SeqCst).min(cap),
+            false => cap,
+        };
+        Self::resize_workers(&self.processing_semaphore, &self.worker_limit, workers);
+    }
+
+    /// Workers currently allowed to run, at most `max_concurrent_jobs`
+    pub fn worker_limit(&self) -> usize {
+        self.worker_limit.load(Ordering::SeqCst)
+    }
+
+    /// The handle to turn adaptive scaling on and off and to report window focus with
+    pub fn adaptive_scaling(&self) -> AdaptiveScaling {
+        self.scaling.clone()
+    }
+
+    /// Scale workers between one and the maximum with CPU load, memory pressure and window
+    /// focus, or run the maximum when turned off
+    pub fn set_adaptive_scaling(&self, enabled: bool) {
+        self.scaling.set_enabled(enabled);
+        if !enabled {
+            let cap = Self::worker_cap(&self.power, &self.window, self.max_concurrent_jobs());
+            Self::resize_workers(&self.processing_semaphore, &self.worker_limit, cap);
+        }
+    }
+
+    /// How processing holds back on battery power; takes effect with the next load check
+    pub fn set_power_policy(&self, policy: PowerPolicy) {
+        self.power.set_policy(policy);
+    }
+
+    /// When the queue runs at full speed; outside the window it slows down or pauses within
+    /// a few seconds
+    pub fn set_processing_window(&self, window: ProcessingWindow) {
+        self.window.set_policy(window);
+    }
+
+    /// Read the user's idle time now and every half minute after, for windows that use it
+    fn start_window_monitoring(&self) {
+        let window = self.window.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(window::IDLE_CHECK_INTERVAL);
+            let mut state = window.state();
+            loop {
+                interval.tick().await;
+                state = window.refresh(state).await;
+            }
+        });
+    }
+
+    /// The most workers the power source and processing window allow out of `max`
+    fn worker_cap(power: &PowerMonitor, window: &WindowMonitor, max: usize) -> usize {
+        window.worker_cap(power.worker_cap(max))
+    }
+
+    /// Check the power source now and every half minute after
+    fn start_power_monitoring(&self) {
+        let power = self.power.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(power::POWER_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                power.refresh().await;
+            }
+        });
+    }
+
+    fn resize_workers(semaphore: &Arc<Semaphore>, worker_limit: &AtomicUsize, workers: usize) {
+        let previous = worker_limit.swap(workers, Ordering::SeqCst);
+        match workers.cmp(&previous) {
+            std::cmp::Ordering::Equal => return,
+            std::cmp::Ordering::Greater => semaphore.add_permits(workers - previous),
+            std::cmp::Ordering::Less => {
+                // Taken out of circulation as they are released
+                let semaphore = semaphore.clone();
+                let excess = (previous - workers) as u32;
+                tokio::spawn(async move {
+                    if let Ok(permits) = semaphore.acquire_many_owned(excess).await {
+                        permits.forget();
+                    }
+                });
+            }
+        }
+        tracing::info!("Processing queue now runs {} workers", workers);
+    }
+
+    /// Resize the workers every few seconds: to the cap of the power source and processing
+    /// window, and with adaptive scaling on, below it as measured load calls for
+    fn start_adaptive_scaling(&self) {
+        let semaphore = self.processing_semaphore.clone();
+        let max_concurrent_jobs = self.max_concurrent_jobs.clone();
+        let worker_limit = self.worker_limit.clone();
+        let adaptive = self.scaling.clone();
+        let power = self.power.clone();
+        let window = self.window.clone();
+
+        tokio::spawn(async move {
+            let mut sys = System::new();
+            let own_pid = sysinfo::get_current_pid().ok();
+            let mut interval = interval(scaling::SCALING_INTERVAL);
+            loop {
+                interval.tick().await;
+                let max = Self::worker_cap(&power, &window, max_concurrent_jobs.load(Ordering::SeqCst));
+                if !adaptive.is_enabled() {
+                    if worker_limit.load(Ordering::SeqCst) != max {
+                        Self::resize_workers(&semaphore, &worker_limit, max);
+                    }
+                    continue;
+                }
+
+                // CPU use is measured since the previous refresh; the workers' own does not
+                // count, or adding one would make the next measurement shed it again
+                sys.refresh_cpu();
+                sys.refresh_memory();
+                if let Some(pid) = own_pid {
+                    sys.refresh_process_specifics(pid, ProcessRefreshKind::new().with_cpu());
+                }
+                let cpu_percent = crate::file_monitor::others_cpu_percent(&sys, own_pid);
+                let available_memory_ratio = match sys.total_memory() {
+                    0 => 1.0,
+                    total => sys.available_memory() as f64 / total as f64,
+                };
+                let target = scaling::target_workers(max, cpu_percent, available_memory_ratio, adaptive.is_window_focused());
+                let current = worker_limit.load(Ordering::SeqCst);
+                let next = scaling::next_workers(current, target);
+                if next != current {
+                    tracing::debug!("Scaling workers for CPU {:.0}%, {:.0}% memory available", cpu_percent, available_memory_ratio * 100.0);
+                    Self::resize_workers(&semaphore, &worker_limit, next);
+                }
+            }
+        });
+    }
+
     pub async fn get_queue_status(&self) -> serde_json::Value {
         let queue = self.queue.read().await;
-        let available_workers = self.processing_semaphore.available_permits();
-        let active_workers = self.max_concurrent_jobs - available_workers;
+        let worker_limit = self.worker_limit();
+        let available_workers = self.processing_semaphore.available_permits().min(worker_limit);
+        let active_workers = worker_limit - available_workers;
         
         let priority_counts = queue.iter().fold(
             std::collections::HashMap::new(),
@@ -236,6 +1097,14 @@ impl ProcessingQueue {
             }
         );
         
+        let category_counts = queue.iter().fold(
+            std::collections::HashMap::new(),
+            |mut acc, job| {
+                *acc.entry(job.category).or_insert(0) += 1;
+                acc
+            }
+        );
+        
         let avg_retry_count = if queue.is_empty() {
             0.0
         } else {
@@ -243,10 +1112,27 @@ impl ProcessingQueue {
         };
         
         serde_json::json!({
-            "total_queued": queue.len(),
+            "total_queued": queue.len() + self.spilled_jobs(),
+            "spilled_jobs": self.spilled_jobs(),
+            "queue_capacity": self.queue_capacity(),
+            "backpressure": self.backpressure.is_active(),
+            "paused": self.is_paused(),
+            "power": {
+                "mode": self.power.mode(),
+                "status": self.power.status(),
+            },
+            "processing_window": {
+                "state": self.window.state(),
+                "idle_seconds": self.window.idle().map(|idle| idle.as_secs()),
+                "policy": self.window.policy(),
+            },
+            "duplicates_suppressed": self.duplicates_suppressed.load(Ordering::Relaxed),
             "active_workers": active_workers,
             "available_workers": available_workers,
+            "worker_limit": worker_limit,
             "priority_breakdown": priority_counts,
+            "category_breakdown": category_counts,
+            "scheduling": self.scheduling_policy(),
             "oldest_job_age_seconds": queue.front()
                 .map(|job| job.created_at.elapsed().as_secs())
                 .unwrap_or(0),
@@ -260,25 +1146,41 @@ impl ProcessingQueue {
     pub async fn clear_queue(&self) {
         let mut queue = self.queue.write().await;
         queue.clear();
+        self.changed_in_flight.lock().unwrap().clear();
+        if let Err(e) = self.database.clear_jobs().await {
+            tracing::warn!("Failed to clear stored jobs: {}", e);
+        }
+        self.spilled.store(0, Ordering::SeqCst);
+        self.backpressure.update(0, self.queue_capacity());
         tracing::info!("Processing queue cleared");
     }
 
-    pub async fn pause_processing(&self) {
-        // Acquire all permits to effectively pause processing
-        for _ in 0..self.max_concurrent_jobs {
-            let _ = self.processing_semaphore.acquire().await;
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Start no more jobs until `resume_processing`; running jobs finish and queued ones stay
+    /// queued. Returns false if processing was paused already.
+    pub fn pause_processing(&self) -> bool {
+        let paused = !self.paused.swap(true, Ordering::SeqCst);
+        if paused {
+            tracing::info!("Processing paused");
         }
-        tracing::info!("Processing paused");
+        paused
     }
 
-    pub fn resume_processing(&self) {
-        // Release all permits to resume processing
-        self.processing_semaphore.add_permits(self.max_concurrent_jobs);
-        tracing::info!("Processing resumed");
+    /// Returns false if processing was not paused
+    pub fn resume_processing(&self) -> bool {
+        let resumed = self.paused.swap(false, Ordering::SeqCst);
+        if resumed {
+            tracing::info!("Processing resumed");
+        }
+        resumed
     }
 
     async fn start_queue_maintenance(&self) {
         let queue = self.queue.clone();
+        let database = self.database.clone();
         
         tokio::spawn(async move {
             let mut interval = interval(Duration::from_secs(300)); // Every 5 minutes
@@ -288,27 +1190,47 @@ impl ProcessingQueue {
                 
                 // Remove old failed jobs
                 let mut queue_guard = queue.write().await;
-                let original_len = queue_guard.len();
+                let mut removed = Vec::new();
                 
                 queue_guard.retain(|job| {
                     // Remove jobs older than 1 hour that have max retries
-                    !(job.retry_count >= 3 && job.created_at.elapsed() > Duration::from_secs(3600))
+                    let stale = job.retry_count >= 3 && job.created_at.elapsed() > Duration::from_secs(3600);
+                    if stale {
+                        removed.push(job.id.clone());
+                    }
+                    !stale
                 });
                 
-                let removed = original_len - queue_guard.len();
-                if removed > 0 {
-                    tracing::info!("Queue maintenance: removed {} stale jobs", removed);
+                if !removed.is_empty() {
+                    if let Err(e) = database.remove_jobs(&removed).await {
+                        tracing::warn!("Failed to remove stale stored jobs: {}", e);
+                    }
+                    tracing::info!("Queue maintenance: removed {} stale jobs", removed.len());
                 }
             }
         });
     }
 
-    pub async fn requeue_pending_files(&self) -> Result<()> {
-        let pending_files = self.database.get_files_by_status("pending").await?;
+    /// Queue every pending file that has no job yet, each with the priority `priority_for`
+    /// gives its path. Files with a stored job are queued already, by `start_processing`.
+    pub async fn requeue_pending_files(&self, priority_for: impl Fn(&str) -> JobPriority) -> Result<()> {
+        let queued = self.database.get_job_file_ids().await?;
+        let pending_files: Vec<FileRecord> = self.database.get_files_by_status("pending").await?
+            .into_iter()
+            .filter(|file| !queued.contains(&file.id))
+            .collect();
         let count = pending_files.len();
-        
-        for file in pending_files {
-            self.add_job(&file, JobPriority::Normal).await?;
+
+        // Stored and queued a batch at a time, one transaction and queue lock per batch
+        let mut pending_files = pending_files.into_iter().peekable();
+        while pending_files.peek().is_some() {
+            let mut by_priority: BTreeMap<JobPriority, Vec<FileRecord>> = BTreeMap::new();
+            for file in pending_files.by_ref().take(REQUEUE_BATCH) {
+                by_priority.entry(priority_for(&file.path)).or_default().push(file);
+            }
+            for (priority, files) in by_priority {
+                self.add_jobs(&files, priority).await?;
+            }
         }
         
         tracing::info!("Requeued {} pending files", count);
@@ -325,19 +1247,27 @@ impl ProcessingQueue {
             "database": db_stats,
             "ai_available": ai_available,
             "performance": {
-                "max_workers": self.max_concurrent_jobs,
+                "max_workers": self.max_concurrent_jobs(),
+                "adaptive_scaling": self.scaling.is_enabled(),
                 "max_retries": self.max_retries,
                 "ai_analysis_enabled": ai_available
             }
         }))
     }
 
-    pub async fn get_processing_insights(&self) -> Result<serde_json::Value> {
+    /// Queue insights, with a completion estimate overall and for each of `watch_paths` from
+    /// how long files of the same type and size took before
+    pub async fn get_processing_insights(&self, watch_paths: &[PathBuf]) -> Result<serde_json::Value> {
+        let model = estimate::TimingModel::new(&self.database.get_processing_timings().await?);
+        let queued_files = self.database.get_queued_files().await?;
+        let (estimated_seconds, path_estimates) =
+            estimate::estimate_completion(&model, &queued_files, watch_paths, self.worker_limit());
+
         let queue = self.queue.read().await;
         let ai_available = self.ai_processor.is_available().await;
         
         // Calculate processing insights
-        let total_jobs = queue.len();
+        let total_jobs = queue.len() + self.spilled_jobs();
         let high_priority_jobs = queue.iter().filter(|job| matches!(job.priority, JobPriority::High | JobPriority::Critical)).count();
         let retry_jobs = queue.iter().filter(|job| job.retry_count > 0).count();
         
@@ -345,21 +1275,26 @@ impl ProcessingQueue {
             .map(|job| job.created_at.elapsed().as_secs() as f64 / 3600.0)
             .unwrap_or(0.0);
         
+        // Running longer than all stage limits together means stuck outside of them
+        let hang_limit = self.stage_timeouts().total();
+        let hung_jobs = self.in_flight.lock().unwrap().values().filter(|started| started.elapsed() > hang_limit).count();
+        let timed_out_jobs = self.timed_out_jobs.load(Ordering::Relaxed);
+        
         Ok(serde_json::json!({
             "total_jobs_queued": total_jobs,
             "high_priority_jobs": high_priority_jobs,
             "retry_jobs": retry_jobs,
             "oldest_job_hours": oldest_job_hours,
             "ai_processing_enabled": ai_available,
-            "estimated_completion_hours": if total_jobs == 0 { 0.0 } else {
-                // Rough estimate: 2 seconds per file average
-                (total_jobs as f64 * 2.0) / 3600.0
-            },
-            "recommendations": self.generate_recommendations(total_jobs, high_priority_jobs, retry_jobs, oldest_job_hours, ai_available)
+            "estimated_completion_hours": estimated_seconds / 3600.0,
+            "watch_path_estimates": path_estimates,
+            "timed_out_jobs": timed_out_jobs,
+            "hung_jobs": hung_jobs,
+            "recommendations": self.generate_recommendations(total_jobs, high_priority_jobs, retry_jobs, oldest_job_hours, ai_available, timed_out_jobs + hung_jobs as u64)
         }))
     }
 
-    fn generate_recommendations(&self, total_jobs: usize, high_priority_jobs: usize, retry_jobs: usize, oldest_job_hours: f64, ai_available: bool) -> Vec<String> {
+    fn generate_recommendations(&self, total_jobs: usize, high_priority_jobs: usize, retry_jobs: usize, oldest_job_hours: f64, ai_available: bool, stuck_jobs: u64) -> Vec<String> {
         let mut recommendations = Vec::new();
         
         if total_jobs > 100 {
@@ -370,6 +1305,10 @@ impl ProcessingQueue {
             recommendations.push("High retry rate detected - check file permissions and corruption".to_string());
         }
         
+        if stuck_jobs > 0 {
+            recommendations.push("Jobs timed out or hung - check Ollama and the files that failed, or raise the stage timeouts".to_string());
+        }
+        
         if oldest_job_hours > 24.0 {
             recommendations.push("Jobs over 24 hours old detected - consider queue maintenance".to_string());
         }
@@ -388,4 +1327,29 @@ impl ProcessingQueue {
         
         recommendations
     }
-}
\ No newline at end of file
+}
+
+/// How a job failed, for its history and the failed jobs list: `timeout`, `panic` or `failed`
+fn failure_kind(error: &anyhow::Error) -> &'static str {
+    if error.is::<StageTimeout>() {
+        "timeout"
+    } else if error.is::<StagePanic>() {
+        "panic"
+    } else {
+        "failed"
+    }
+}
+
+/// Panics and timed-out extraction would happen again on the same file
+fn is_retryable(error: &anyhow::Error) -> bool {
+    let wedged = error.downcast_ref::<StageTimeout>().is_some_and(|timeout| timeout.stage == JobStage::Extracting);
+    !wedged && !error.is::<StagePanic>()
+}
+
+/// The message a panic was raised with, if it was a string
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload.downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause")
+}
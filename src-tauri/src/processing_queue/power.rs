@@ -0,0 +1,232 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How often the power source is checked
+pub const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How processing holds back on battery power, so background indexing does not drain a laptop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerPolicy {
+    /// Follow the power source at all; when off, processing runs the same on battery
+    pub enabled: bool,
+    /// Workers to run at most on battery power
+    pub battery_workers: usize,
+    /// Keep analyzing with the AI model on battery; otherwise files get the basic analysis
+    pub ai_on_battery: bool,
+    /// Pause processing on battery power below this charge, in percent
+    pub pause_below_percent: Option<u8>,
+    /// Pause processing while the system's low power mode is on
+    pub pause_in_low_power_mode: bool,
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            battery_workers: 1,
+            ai_on_battery: false,
+            pause_below_percent: Some(20),
+            pause_in_low_power_mode: true,
+        }
+    }
+}
+
+/// What the system reports about its power source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub battery_percent: Option<u8>,
+    pub low_power_mode: bool,
+}
+
+/// How processing runs under the current policy and power source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerMode {
+    Full,
+    /// Fewer workers and possibly no AI analysis
+    Saving,
+    Paused,
+}
+
+impl PowerPolicy {
+    pub fn mode(&self, status: &PowerStatus) -> PowerMode {
+        if !self.enabled {
+            return PowerMode::Full;
+        }
+        let low_charge = status.on_battery
+            && matches!((status.battery_percent, self.pause_below_percent), (Some(percent), Some(limit)) if percent < limit);
+        if low_charge || (status.low_power_mode && self.pause_in_low_power_mode) {
+            PowerMode::Paused
+        } else if status.on_battery || status.low_power_mode {
+            PowerMode::Saving
+        } else {
+            PowerMode::Full
+        }
+    }
+}
+
+/// The power policy and the last status read, shared by the queue's tasks
+#[derive(Debug, Clone, Default)]
+pub struct PowerMonitor {
+    policy: Arc<RwLock<PowerPolicy>>,
+    status: Arc<RwLock<PowerStatus>>,
+}
+
+impl PowerMonitor {
+    pub fn policy(&self) -> PowerPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    pub fn set_policy(&self, policy: PowerPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    pub fn status(&self) -> PowerStatus {
+        *self.status.read().unwrap()
+    }
+
+    pub fn mode(&self) -> PowerMode {
+        self.policy().mode(&self.status())
+    }
+
+    /// At most `max` workers, fewer while saving power
+    pub fn worker_cap(&self, max: usize) -> usize {
+        match self.mode() {
+            PowerMode::Saving => max.min(self.policy().battery_workers.max(1)),
+            PowerMode::Full | PowerMode::Paused => max,
+        }
+    }
+
+    pub fn allows_ai(&self) -> bool {
+        self.mode() == PowerMode::Full || self.policy().ai_on_battery
+    }
+
+    /// Read the power source again, logging when the mode changes
+    pub async fn refresh(&self) {
+        let previous = self.mode();
+        let status = tokio::task::spawn_blocking(read_power_status).await.unwrap_or_default();
+        *self.status.write().unwrap() = status;
+        let mode = self.mode();
+        if mode != previous {
+            tracing::info!("Processing power mode changed from {:?} to {:?} ({:?})", previous, mode, status);
+        }
+    }
+}
+
+/// The power source of a machine without a battery, or where it cannot be read
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_power_status() -> PowerStatus {
+    PowerStatus::default()
+}
+
+#[cfg(target_os = "linux")]
+fn read_power_status() -> PowerStatus {
+    let mut status = PowerStatus::default();
+    let mut on_mains = false;
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return status;
+    };
+    for supply in supplies.flatten() {
+        let read = |name: &str| std::fs::read_to_string(supply.path().join(name)).map(|value| value.trim().to_string()).ok();
+        match read("type").as_deref() {
+            Some("Mains") | Some("USB") => on_mains |= read("online").as_deref() == Some("1"),
+            Some("Battery") => {
+                status.on_battery |= read("status").as_deref() == Some("Discharging");
+                if let Some(percent) = read("capacity").and_then(|capacity| capacity.parse().ok()) {
+                    status.battery_percent = Some(percent);
+                }
+            }
+            _ => {}
+        }
+    }
+    status.on_battery &= !on_mains;
+    status.low_power_mode = std::fs::read_to_string("/sys/firmware/acpi/platform_profile")
+        .is_ok_and(|profile| profile.trim() == "low-power");
+    status
+}
+
+#[cfg(target_os = "macos")]
+fn read_power_status() -> PowerStatus {
+    use std::process::Command;
+
+    let run = |args: &[&str]| {
+        Command::new("pmset")
+            .args(args)
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+            .unwrap_or_default()
+    };
+    parse_pmset(&run(&["-g", "batt"]), &run(&["-g"]))
+}
+
+/// Parse `pmset -g batt`, e.g. "Now drawing from 'Battery Power'" and "85%;", and the
+/// `lowpowermode` setting of `pmset -g`
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset(battery: &str, settings: &str) -> PowerStatus {
+    let battery_percent = battery.split_whitespace()
+        .find_map(|word| word.trim_end_matches(';').strip_suffix('%'))
+        .and_then(|percent| percent.parse().ok());
+    let low_power_mode = settings.lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        fields.next() == Some("lowpowermode") && fields.next() == Some("1")
+    });
+    PowerStatus {
+        on_battery: battery.contains("'Battery Power'"),
+        battery_percent,
+        low_power_mode,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn read_power_status() -> PowerStatus {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    // Safety: the call only writes the struct it is given
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return PowerStatus::default();
+    }
+    // ACLineStatus 0 means on battery, a life percent of 255 unknown, and bit 0 of the
+    // status flag that battery saver is on
+    PowerStatus {
+        on_battery: status.ACLineStatus == 0,
+        battery_percent: (status.BatteryLifePercent != 255).then_some(status.BatteryLifePercent.min(100)),
+        low_power_mode: status.SystemStatusFlag & 1 != 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_mode() {
+        let policy = PowerPolicy::default();
+        let status = |on_battery, battery_percent, low_power_mode| PowerStatus { on_battery, battery_percent, low_power_mode };
+        assert_eq!(policy.mode(&status(false, Some(10), false)), PowerMode::Full);
+        assert_eq!(policy.mode(&status(true, Some(80), false)), PowerMode::Saving);
+        assert_eq!(policy.mode(&status(true, None, false)), PowerMode::Saving);
+        assert_eq!(policy.mode(&status(true, Some(15), false)), PowerMode::Paused);
+        assert_eq!(policy.mode(&status(false, Some(90), true)), PowerMode::Paused);
+
+        let relaxed = PowerPolicy { pause_below_percent: None, pause_in_low_power_mode: false, ..Default::default() };
+        assert_eq!(relaxed.mode(&status(true, Some(5), true)), PowerMode::Saving);
+        let off = PowerPolicy { enabled: false, ..Default::default() };
+        assert_eq!(off.mode(&status(true, Some(5), true)), PowerMode::Full);
+    }
+
+    #[test]
+    fn test_parse_pmset() {
+        let battery = "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1234)\t85%; discharging; 4:10 remaining present: true";
+        let settings = "System-wide power settings:\nCurrently in use:\n lowpowermode         1\n sleep                1";
+        assert_eq!(parse_pmset(battery, settings), PowerStatus { on_battery: true, battery_percent: Some(85), low_power_mode: true });
+
+        let battery = "Now drawing from 'AC Power'\n -InternalBattery-0 (id=1234)\t100%; charged; 0:00 remaining";
+        assert_eq!(parse_pmset(battery, ""), PowerStatus { on_battery: false, battery_percent: Some(100), low_power_mode: false });
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often load is measured and the worker count adjusted
+pub const SCALING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Average CPU use, in percent, from which fewer and only one worker run
+const BUSY_CPU_PERCENT: f32 = 70.0;
+const SATURATED_CPU_PERCENT: f32 = 90.0;
+
+/// Share of memory still available below which fewer and only one worker run
+const LOW_MEMORY_RATIO: f64 = 0.2;
+const CRITICAL_MEMORY_RATIO: f64 = 0.1;
+
+/// Whether workers follow the machine's load, and what the scaling is told about the window.
+/// Shared with the window event handler, which reports focus changes.
+#[derive(Debug, Clone)]
+pub struct AdaptiveScaling {
+    enabled: Arc<AtomicBool>,
+    window_focused: Arc<AtomicBool>,
+}
+
+impl AdaptiveScaling {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+            window_focused: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_window_focused(&self) -> bool {
+        self.window_focused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_window_focused(&self, focused: bool) {
+        self.window_focused.store(focused, Ordering::Relaxed);
+    }
+}
+
+/// Workers to run out of `max` under the measured load. While the window is in the background
+/// the user is busy with other apps, so half of them leave room; CPU and memory pressure each
+/// halve the count or bring it down to one.
+pub fn target_workers(max: usize, cpu_percent: f32, available_memory_ratio: f64, window_focused: bool) -> usize {
+    let max = max.max(1);
+    let half = max.div_ceil(2);
+    let for_window = if window_focused { max } else { half };
+    let for_cpu = if cpu_percent >= SATURATED_CPU_PERCENT {
+        1
+    } else if cpu_percent >= BUSY_CPU_PERCENT {
+        half
+    } else {
+        max
+    };
+    let for_memory = if available_memory_ratio < CRITICAL_MEMORY_RATIO {
+        1
+    } else if available_memory_ratio < LOW_MEMORY_RATIO {
+        half
+    } else {
+        max
+    };
+    for_window.min(for_cpu).min(for_memory)
+}
+
+/// The next worker count on the way from `current` to `target`. Workers are shed at once but
+/// added one at a time, since the queue's own work raises the load it is measured by.
+pub fn next_workers(current: usize, target: usize) -> usize {
+    if target > current {
+        current + 1
+    } else {
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_workers() {
+        assert_eq!(target_workers(4, 20.0, 0.5, true), 4);
+        assert_eq!(target_workers(4, 20.0, 0.5, false), 2);
+        assert_eq!(target_workers(4, 75.0, 0.5, true), 2);
+        assert_eq!(target_workers(4, 95.0, 0.5, true), 1);
+        assert_eq!(target_workers(4, 20.0, 0.15, true), 2);
+        assert_eq!(target_workers(4, 20.0, 0.05, false), 1);
+        assert_eq!(target_workers(1, 20.0, 0.5, false), 1);
+        assert_eq!(target_workers(3, 20.0, 0.5, false), 2);
+
+        assert_eq!(next_workers(1, 4), 2);
+        assert_eq!(next_workers(4, 1), 1);
+        assert_eq!(next_workers(2, 2), 2);
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How long a job waits to move one kind of file up the order, so large files and media are
+/// reached while smaller files keep arriving
+const AGING_STEP: Duration = Duration::from_secs(5 * 60);
+
+/// Source code and markup, which is plain text whatever MIME type is registered for it
+const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "jsx", "ts", "tsx", "go", "java", "kt", "swift", "c", "h", "cpp", "hpp", "cs", "rb", "php",
+    "sh", "sql", "toml", "yaml", "yml", "json", "xml", "html", "css", "md", "rst", "tex",
+];
+const DOCUMENT_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "odt", "rtf", "xls", "xlsx", "ods", "ppt", "pptx", "odp", "epub", "pages", "key", "numbers",
+];
+const EMAIL_EXTENSIONS: &[&str] = &["eml", "msg", "mbox"];
+
+/// Kinds of file the queue orders its jobs by, within a priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobCategory {
+    /// Text and source files, quick to process
+    Text,
+    /// PDFs and office documents
+    Document,
+    /// Single messages and mailboxes
+    Email,
+    /// Anything above the policy's size limit that is not media
+    Large,
+    /// Images, audio and video
+    Media,
+    Other,
+}
+
+impl JobCategory {
+    pub fn of(path: &Path, size: u64, large_file_bytes: u64) -> Self {
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let mime = mime_guess::from_path(path).first();
+        let top_level = mime.as_ref().map(|mime| mime.type_().as_str().to_string()).unwrap_or_default();
+
+        let is_code = CODE_EXTENSIONS.contains(&extension.as_str());
+        if !is_code && matches!(top_level.as_str(), "image" | "audio" | "video") {
+            return JobCategory::Media;
+        }
+        if size > large_file_bytes {
+            return JobCategory::Large;
+        }
+        if is_code || top_level == "text" {
+            JobCategory::Text
+        } else if DOCUMENT_EXTENSIONS.contains(&extension.as_str()) {
+            JobCategory::Document
+        } else if EMAIL_EXTENSIONS.contains(&extension.as_str()) {
+            JobCategory::Email
+        } else {
+            JobCategory::Other
+        }
+    }
+}
+
+/// The order in which jobs of the same priority are processed, by kind of file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulingPolicy {
+    /// Order by kind of file at all; when off, jobs of a priority go first in, first out
+    pub enabled: bool,
+    /// Earlier kinds go first; kinds left out go last
+    pub order: Vec<JobCategory>,
+    /// Files above this are large, whatever their type
+    pub large_file_mb: u64,
+}
+
+impl Default for SchedulingPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            order: vec![
+                JobCategory::Text,
+                JobCategory::Document,
+                JobCategory::Email,
+                JobCategory::Other,
+                JobCategory::Large,
+                JobCategory::Media,
+            ],
+            large_file_mb: 20,
+        }
+    }
+}
+
+impl SchedulingPolicy {
+    pub fn large_file_bytes(&self) -> u64 {
+        self.large_file_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// Place of a kind of file in the order; all kinds rank the same when disabled
+    pub fn rank(&self, category: JobCategory) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+        self.order.iter().position(|ordered| *ordered == category).unwrap_or(self.order.len())
+    }
+
+    /// `rank` of a job that has waited for `waited`, one place earlier per `AGING_STEP`
+    pub fn aged_rank(&self, category: JobCategory, waited: Duration) -> usize {
+        let steps = (waited.as_secs() / AGING_STEP.as_secs()) as usize;
+        self.rank(category).saturating_sub(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_category() {
+        let large = 20 * 1024 * 1024;
+        assert_eq!(JobCategory::of(Path::new("/src/main.rs"), 1024, large), JobCategory::Text);
+        assert_eq!(JobCategory::of(Path::new("/src/app.ts"), 1024, large), JobCategory::Text);
+        assert_eq!(JobCategory::of(Path::new("/notes/todo.txt"), 1024, large), JobCategory::Text);
+        assert_eq!(JobCategory::of(Path::new("/docs/report.pdf"), 1024, large), JobCategory::Document);
+        assert_eq!(JobCategory::of(Path::new("/docs/manual.pdf"), large + 1, large), JobCategory::Large);
+        assert_eq!(JobCategory::of(Path::new("/photos/cat.jpg"), large + 1, large), JobCategory::Media);
+        assert_eq!(JobCategory::of(Path::new("/music/song.mp3"), 1024, large), JobCategory::Media);
+        assert_eq!(JobCategory::of(Path::new("/mail/inbox.mbox"), 1024, large), JobCategory::Email);
+        assert_eq!(JobCategory::of(Path::new("/mail/archive.mbox"), large + 1, large), JobCategory::Large);
+        assert_eq!(JobCategory::of(Path::new("/data/blob"), 1024, large), JobCategory::Other);
+    }
+
+    #[test]
+    fn test_rank() {
+        let policy = SchedulingPolicy { order: vec![JobCategory::Text, JobCategory::Media], ..Default::default() };
+        assert!(policy.rank(JobCategory::Text) < policy.rank(JobCategory::Media));
+        assert_eq!(policy.rank(JobCategory::Document), policy.rank(JobCategory::Other));
+        assert!(policy.rank(JobCategory::Media) < policy.rank(JobCategory::Document));
+
+        let waited = |steps: u32| AGING_STEP * steps;
+        let policy = SchedulingPolicy::default();
+        assert_eq!(policy.aged_rank(JobCategory::Media, Duration::ZERO), policy.rank(JobCategory::Media));
+        assert!(policy.aged_rank(JobCategory::Media, waited(2)) < policy.rank(JobCategory::Large));
+        assert_eq!(policy.aged_rank(JobCategory::Media, waited(10)), policy.rank(JobCategory::Text));
+
+        let disabled = SchedulingPolicy { enabled: false, ..Default::default() };
+        assert_eq!(disabled.rank(JobCategory::Text), disabled.rank(JobCategory::Media));
+    }
+}
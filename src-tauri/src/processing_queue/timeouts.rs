@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::JobStage;
+
+/// How long each stage of a job may take before the job is given up as hung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageTimeouts {
+    pub extraction_secs: u64,
+    /// Extraction limits for particular extensions, such as PDFs that may need OCR
+    pub extraction_secs_by_extension: BTreeMap<String, u64>,
+    /// The AI model's analysis of the content
+    pub analysis_secs: u64,
+    /// Embedding the content's chunks
+    pub embedding_secs: u64,
+}
+
+impl Default for StageTimeouts {
+    fn default() -> Self {
+        Self {
+            extraction_secs: 120,
+            extraction_secs_by_extension: [("pdf", 300), ("mbox", 600)]
+                .into_iter()
+                .map(|(extension, secs)| (extension.to_string(), secs))
+                .collect(),
+            analysis_secs: 300,
+            embedding_secs: 600,
+        }
+    }
+}
+
+impl StageTimeouts {
+    pub fn limit(&self, stage: JobStage) -> Duration {
+        let secs = match stage {
+            JobStage::Extracting => self.extraction_secs,
+            JobStage::Analyzing => self.analysis_secs,
+            JobStage::Embedding => self.embedding_secs,
+            JobStage::Queued | JobStage::Done | JobStage::Failed => return Duration::MAX,
+        };
+        Duration::from_secs(secs.max(1))
+    }
+
+    /// How long extracting a file may take, by the extension its contents were sniffed as
+    pub fn extraction_limit(&self, extension: &str) -> Duration {
+        match self.extraction_secs_by_extension.get(&extension.to_lowercase()) {
+            Some(secs) => Duration::from_secs((*secs).max(1)),
+            None => self.limit(JobStage::Extracting),
+        }
+    }
+
+    /// The longest a job can run with every stage at its limit
+    pub fn total(&self) -> Duration {
+        let extraction = self.extraction_secs_by_extension.values()
+            .map(|secs| Duration::from_secs((*secs).max(1)))
+            .fold(self.limit(JobStage::Extracting), Duration::max);
+        extraction + self.limit(JobStage::Analyzing) + self.limit(JobStage::Embedding)
+    }
+}
+
+/// A stage that ran past its limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTimeout {
+    pub stage: JobStage,
+    pub limit: Duration,
+}
+
+impl fmt::Display for StageTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timed out while {} after {}s", self.stage.as_str(), self.limit.as_secs())
+    }
+}
+
+impl std::error::Error for StageTimeout {}
+
+/// A stage that panicked, which the same file would do again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagePanic {
+    pub stage: JobStage,
+    pub message: String,
+}
+
+impl fmt::Display for StagePanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Panicked while {}: {}", self.stage.as_str(), self.message)
+    }
+}
+
+impl std::error::Error for StagePanic {}
+
+/// Run `future` as `stage`, failing with a `StageTimeout` once it takes longer than its limit
+pub async fn within<T>(timeouts: &StageTimeouts, stage: JobStage, future: impl Future<Output = Result<T>>) -> Result<T> {
+    within_limit(timeouts.limit(stage), stage, future).await
+}
+
+async fn within_limit<T>(limit: Duration, stage: JobStage, future: impl Future<Output = Result<T>>) -> Result<T> {
+    match tokio::time::timeout(limit, future).await {
+        Ok(result) => result,
+        Err(_) => Err(StageTimeout { stage, limit }.into()),
+    }
+}
+
+/// Extract a file on a blocking thread, for parsers that may not yield to the runtime or may
+/// panic on a malformed file, within the limit for the extension it was sniffed as. A wedged
+/// thread is left behind but no longer holds a worker, so a timeout here is not retried; a
+/// panic becomes a `StagePanic`.
+pub async fn extracting<T, F>(timeouts: &StageTimeouts, extension: &str, future: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T>> + Send + 'static,
+{
+    let stage = JobStage::Extracting;
+    let runtime = tokio::runtime::Handle::current();
+    let task = tokio::task::spawn_blocking(move || runtime.block_on(future));
+    within_limit(timeouts.extraction_limit(extension), stage, async {
+        match task.await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => {
+                let message = super::panic_message(e.into_panic().as_ref()).to_string();
+                Err(StagePanic { stage, message }.into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stage_timeout() {
+        let timeouts = StageTimeouts { extraction_secs: 1, ..Default::default() };
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        };
+        let error = within(&timeouts, JobStage::Extracting, slow).await.unwrap_err();
+        let timeout = error.downcast_ref::<StageTimeout>().expect("Expected a stage timeout");
+        assert_eq!(timeout.stage, JobStage::Extracting);
+        assert_eq!(error.to_string(), "Timed out while extracting after 1s");
+
+        assert_eq!(extracting(&timeouts, "txt", async { Ok(7) }).await.unwrap(), 7);
+        assert_eq!(timeouts.total(), Duration::from_secs(600 + 300 + 600));
+    }
+
+    #[test]
+    fn test_extraction_limit_by_extension() {
+        let timeouts = StageTimeouts::default();
+        assert_eq!(timeouts.extraction_limit("PDF"), Duration::from_secs(300));
+        assert_eq!(timeouts.extraction_limit("txt"), Duration::from_secs(120));
+        assert_eq!(timeouts.extraction_limit(""), Duration::from_secs(120));
+    }
+
+    #[tokio::test]
+    async fn test_extraction_panic_is_caught() {
+        let timeouts = StageTimeouts::default();
+        let error = extracting(&timeouts, "pdf", async {
+            if true {
+                panic!("invalid xref table");
+            }
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+        let panic = error.downcast_ref::<StagePanic>().expect("Expected a stage panic");
+        assert_eq!(panic.message, "invalid xref table");
+        assert_eq!(error.to_string(), "Panicked while extracting: invalid xref table");
+    }
+}
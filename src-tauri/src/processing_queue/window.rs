@@ -0,0 +1,259 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// How often the user's idle time is read
+pub const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What the queue does outside its processing window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutsideWindow {
+    /// Keep draining with a single worker
+    Slow,
+    Pause,
+}
+
+/// When the queue runs at full speed, e.g. only overnight or only while the user is away
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessingWindow {
+    /// Follow the window at all; when off, the queue always runs at full speed
+    pub enabled: bool,
+    /// Local hours to run in as `HH:MM-HH:MM`; `22:00-07:00` runs overnight
+    pub hours: Option<String>,
+    /// Also run once the user has been idle this many minutes. Where the idle time cannot be
+    /// read, e.g. on Linux without `xprintidle`, only the hours apply, or none at all.
+    pub idle_minutes: Option<u32>,
+    pub outside: OutsideWindow,
+}
+
+impl Default for ProcessingWindow {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hours: None,
+            idle_minutes: None,
+            outside: OutsideWindow::Slow,
+        }
+    }
+}
+
+/// How the queue runs at the moment under its processing window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowState {
+    Open,
+    Slow,
+    Paused,
+}
+
+impl ProcessingWindow {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(hours) = &self.hours {
+            parse_hours(hours)?;
+        }
+        if self.idle_minutes == Some(0) {
+            return Err(anyhow!("Idle time must be at least one minute"));
+        }
+        Ok(())
+    }
+
+    /// Within the hours or idle long enough, whichever are set; always when neither is. An
+    /// idle time that cannot be read leaves the idle condition out rather than never meeting it.
+    pub fn is_open(&self, now: NaiveTime, idle: Option<Duration>) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let in_hours = self.hours.as_deref()
+            .and_then(|hours| parse_hours(hours).ok())
+            .map(|(start, end)| within(now, start, end));
+        let idle_enough = self.idle_minutes
+            .zip(idle)
+            .map(|(minutes, idle)| idle >= Duration::from_secs(minutes as u64 * 60));
+        match (in_hours, idle_enough) {
+            (None, None) => true,
+            (in_hours, idle_enough) => in_hours.unwrap_or(false) || idle_enough.unwrap_or(false),
+        }
+    }
+
+    pub fn state(&self, now: NaiveTime, idle: Option<Duration>) -> WindowState {
+        match (self.is_open(now, idle), self.outside) {
+            (true, _) => WindowState::Open,
+            (false, OutsideWindow::Slow) => WindowState::Slow,
+            (false, OutsideWindow::Pause) => WindowState::Paused,
+        }
+    }
+}
+
+fn parse_hours(hours: &str) -> Result<(NaiveTime, NaiveTime)> {
+    let (start, end) = hours.split_once('-')
+        .ok_or_else(|| anyhow!("Hours '{}' must be a range such as 22:00-07:00", hours))?;
+    let time = |value: &str| {
+        NaiveTime::parse_from_str(value.trim(), "%H:%M")
+            .map_err(|e| anyhow!("Invalid time '{}' in hours '{}': {}", value.trim(), hours, e))
+    };
+    Ok((time(start)?, time(end)?))
+}
+
+/// From the start up to the end, past midnight when the end comes first; all day when they match
+fn within(now: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
+    match start.cmp(&end) {
+        std::cmp::Ordering::Less => start <= now && now < end,
+        std::cmp::Ordering::Greater => now >= start || now < end,
+        std::cmp::Ordering::Equal => true,
+    }
+}
+
+/// The processing window and the last idle time read, shared by the queue's tasks
+#[derive(Debug, Clone, Default)]
+pub struct WindowMonitor {
+    policy: Arc<RwLock<ProcessingWindow>>,
+    idle: Arc<RwLock<Option<Duration>>>,
+}
+
+impl WindowMonitor {
+    pub fn policy(&self) -> ProcessingWindow {
+        self.policy.read().unwrap().clone()
+    }
+
+    pub fn set_policy(&self, policy: ProcessingWindow) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    /// How long the user has been idle, if it could be read
+    pub fn idle(&self) -> Option<Duration> {
+        *self.idle.read().unwrap()
+    }
+
+    pub fn state(&self) -> WindowState {
+        self.policy().state(Local::now().time(), self.idle())
+    }
+
+    /// At most `max` workers, one while outside the window
+    pub fn worker_cap(&self, max: usize) -> usize {
+        match self.state() {
+            WindowState::Slow => max.min(1),
+            WindowState::Open | WindowState::Paused => max,
+        }
+    }
+
+    /// Read the idle time again when the window depends on it, logging when the state changes
+    pub async fn refresh(&self, previous: WindowState) -> WindowState {
+        let policy = self.policy();
+        let idle = match policy.enabled && policy.idle_minutes.is_some() {
+            true => tokio::task::spawn_blocking(read_idle_time).await.unwrap_or_default(),
+            false => None,
+        };
+        *self.idle.write().unwrap() = idle;
+        let state = self.state();
+        if state != previous {
+            tracing::info!("Processing window changed from {:?} to {:?}", previous, state);
+        }
+        state
+    }
+}
+
+/// The idle time where it cannot be read, which never counts as idle
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_idle_time() -> Option<Duration> {
+    None
+}
+
+/// Needs `xprintidle`, which reports the idle time of the X session in milliseconds
+#[cfg(target_os = "linux")]
+fn read_idle_time() -> Option<Duration> {
+    let output = std::process::Command::new("xprintidle").output().ok()?;
+    let ms: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(Duration::from_millis(ms))
+}
+
+#[cfg(target_os = "macos")]
+fn read_idle_time() -> Option<Duration> {
+    let output = std::process::Command::new("ioreg").args(["-c", "IOHIDSystem", "-d", "4"]).output().ok()?;
+    parse_ioreg_idle(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the `"HIDIdleTime" = 1234567890` line of `ioreg -c IOHIDSystem`, in nanoseconds
+#[cfg(any(target_os = "macos", test))]
+fn parse_ioreg_idle(output: &str) -> Option<Duration> {
+    output.lines()
+        .find_map(|line| line.split_once("\"HIDIdleTime\" = "))
+        .and_then(|(_, nanos)| nanos.trim().parse().ok())
+        .map(Duration::from_nanos)
+}
+
+#[cfg(target_os = "windows")]
+fn read_idle_time() -> Option<Duration> {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+    // Safety: the call only writes the struct it is given, sized as it expects
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return None;
+    }
+    // Both are millisecond tick counts that wrap after 49.7 days of uptime
+    let ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Some(Duration::from_millis(ms as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(value, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_overnight_hours() {
+        let window = ProcessingWindow { enabled: true, hours: Some("22:00-07:00".to_string()), ..Default::default() };
+        assert!(window.is_open(time("23:30"), None));
+        assert!(window.is_open(time("06:59"), None));
+        assert!(!window.is_open(time("07:00"), None));
+        assert_eq!(window.state(time("12:00"), None), WindowState::Slow);
+
+        let paused = ProcessingWindow { outside: OutsideWindow::Pause, ..window.clone() };
+        assert_eq!(paused.state(time("12:00"), None), WindowState::Paused);
+        let disabled = ProcessingWindow { enabled: false, ..window };
+        assert!(disabled.is_open(time("12:00"), None));
+    }
+
+    #[test]
+    fn test_idle_opens_the_window() {
+        let window = ProcessingWindow {
+            enabled: true,
+            hours: Some("01:00-05:00".to_string()),
+            idle_minutes: Some(10),
+            ..Default::default()
+        };
+        assert!(!window.is_open(time("12:00"), Some(Duration::from_secs(9 * 60))));
+        assert!(window.is_open(time("12:00"), Some(Duration::from_secs(10 * 60))));
+        // An idle time that cannot be read leaves only the hours
+        assert!(!window.is_open(time("12:00"), None));
+        assert!(window.is_open(time("03:00"), None));
+        let idle_only = ProcessingWindow { hours: None, ..window };
+        assert!(!idle_only.is_open(time("03:00"), Some(Duration::from_secs(60))));
+        assert!(idle_only.is_open(time("03:00"), None));
+    }
+
+    #[test]
+    fn test_validate() {
+        let window = |hours: &str| ProcessingWindow { hours: Some(hours.to_string()), ..Default::default() };
+        assert!(window("09:00-17:30").validate().is_ok());
+        assert!(window("9-17").validate().is_err());
+        assert!(window("22:00").validate().is_err());
+        assert!(ProcessingWindow { idle_minutes: Some(0), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_ioreg_idle() {
+        let output = "    | |   \"HIDIdleTime\" = 125000000000\n    | |   \"HIDParameters\" = {}";
+        assert_eq!(parse_ioreg_idle(output), Some(Duration::from_secs(125)));
+        assert_eq!(parse_ioreg_idle(""), None);
+    }
+}
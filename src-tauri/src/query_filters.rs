@@ -0,0 +1,360 @@
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+
+use crate::search_query::QuerySyntaxError;
+
+/// Filter syntax accepted inline in a search query, shown with syntax errors
+pub const FILTER_SYNTAX: &str = r#"ext:pdf | ext:jpg,png      file extension, any of a comma-separated list
+size:>10mb | size:<=500kb | size:1mb..5mb   size in b, kb, mb or gb
+before:2023-01 | after:2022       modified strictly before or after a year, month or day
+tag:invoice | tag:"tax return"    carries the tag, repeat for several
+Filters apply to the whole query and may appear anywhere in it."#;
+
+/// Structured filters written inline in a query, e.g. `ext:pdf size:>10mb before:2023-01 tag:invoice budget`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InlineFilters {
+    /// Lowercase, without the leading dot
+    pub extensions: Vec<String>,
+    pub tags: Vec<String>,
+    /// Inclusive size bounds in bytes
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    /// First day included
+    pub after: Option<NaiveDate>,
+    /// First day no longer included
+    pub before: Option<NaiveDate>,
+    /// The query with the filters removed
+    pub remaining_query: String,
+}
+
+impl InlineFilters {
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+            && self.tags.is_empty()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.after.is_none()
+            && self.before.is_none()
+    }
+}
+
+const FILTER_KEYS: [&str; 5] = ["ext", "size", "before", "after", "tag"];
+
+/// A piece of the query left after pulling out filters
+#[derive(Debug, PartialEq)]
+enum Piece {
+    /// Where a filter was removed
+    Hole,
+    Text(String),
+    LParen,
+    RParen,
+}
+
+impl Piece {
+    fn is_word(&self, word: &str) -> bool {
+        matches!(self, Piece::Text(text) if text == word)
+    }
+
+    fn is_binary_operator(&self) -> bool {
+        self.is_word("AND") || self.is_word("OR")
+    }
+}
+
+/// Pull `key:value` filters out of `query`; quoted phrases are left alone
+pub fn extract(query: &str) -> Result<InlineFilters, QuerySyntaxError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut filters = InlineFilters::default();
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut in_quotes = false;
+    let mut i = 0;
+
+    let flush = |text: &mut String, pieces: &mut Vec<Piece>| {
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(text)));
+        }
+    };
+
+    while i < chars.len() {
+        let at_word_start = i == 0 || chars[i - 1].is_whitespace() || chars[i - 1] == '(';
+        if !in_quotes && at_word_start {
+            if let Some((key, value_start)) = filter_key(&chars, i) {
+                let (value, next) = read_value(&chars, value_start)?;
+                apply_filter(&mut filters, key, &value, i)?;
+                flush(&mut text, &mut pieces);
+                pieces.push(Piece::Hole);
+                i = next;
+                continue;
+            }
+        }
+
+        match chars[i] {
+            '"' => {
+                in_quotes = !in_quotes;
+                text.push('"');
+            }
+            c if in_quotes => text.push(c),
+            c if c.is_whitespace() => flush(&mut text, &mut pieces),
+            c @ ('(' | ')') => {
+                flush(&mut text, &mut pieces);
+                pieces.push(if c == '(' { Piece::LParen } else { Piece::RParen });
+            }
+            c => text.push(c),
+        }
+        i += 1;
+    }
+    flush(&mut text, &mut pieces);
+
+    filters.remaining_query = render(fill_holes(pieces));
+    Ok(filters)
+}
+
+/// Drop the operators and groups that only applied to removed filters, so
+/// `budget OR ext:pdf` leaves `budget` rather than a dangling `OR`
+fn fill_holes(mut pieces: Vec<Piece>) -> Vec<Piece> {
+    while let Some(mut at) = pieces.iter().position(|piece| *piece == Piece::Hole) {
+        pieces.remove(at);
+        if at > 0 && pieces[at - 1].is_word("NOT") {
+            pieces.remove(at - 1);
+            at -= 1;
+        }
+        if at > 0 && pieces[at - 1].is_binary_operator() {
+            pieces.remove(at - 1);
+            at -= 1;
+        } else if pieces.get(at).is_some_and(Piece::is_binary_operator) {
+            pieces.remove(at);
+        }
+        // A group that held only filters is gone as well
+        if at > 0 && pieces[at - 1] == Piece::LParen && pieces.get(at) == Some(&Piece::RParen) {
+            pieces.splice(at - 1..=at, [Piece::Hole]);
+        }
+    }
+    pieces
+}
+
+fn render(pieces: Vec<Piece>) -> String {
+    let mut query = String::new();
+    let mut after_paren = true;
+    for piece in pieces {
+        if !after_paren && piece != Piece::RParen {
+            query.push(' ');
+        }
+        after_paren = piece == Piece::LParen;
+        match piece {
+            Piece::Text(text) => query.push_str(&text),
+            Piece::LParen => query.push('('),
+            Piece::RParen => query.push(')'),
+            Piece::Hole => {}
+        }
+    }
+    query
+}
+
+/// The filter key starting at `start` and the index of its value
+fn filter_key(chars: &[char], start: usize) -> Option<(&'static str, usize)> {
+    FILTER_KEYS.iter().find_map(|key| {
+        let end = start + key.len();
+        let matches = chars.get(end) == Some(&':')
+            && chars[start..end].iter().collect::<String>().eq_ignore_ascii_case(key);
+        matches.then_some((*key, end + 1))
+    })
+}
+
+/// A bare value runs to the next space or closing parenthesis, a quoted one to the closing quote
+fn read_value(chars: &[char], start: usize) -> Result<(String, usize), QuerySyntaxError> {
+    if chars.get(start) == Some(&'"') {
+        return match chars[start + 1..].iter().position(|&c| c == '"') {
+            Some(offset) => {
+                let end = start + 1 + offset;
+                Ok((chars[start + 1..end].iter().collect(), end + 1))
+            }
+            None => Err(QuerySyntaxError {
+                message: "Unterminated quoted phrase".to_string(),
+                position: start,
+            }),
+        };
+    }
+
+    let mut end = start;
+    while end < chars.len() && !chars[end].is_whitespace() && chars[end] != ')' {
+        end += 1;
+    }
+    Ok((chars[start..end].iter().collect(), end))
+}
+
+fn apply_filter(filters: &mut InlineFilters, key: &str, value: &str, position: usize) -> Result<(), QuerySyntaxError> {
+    let value = value.trim();
+    let error = |message: &str| QuerySyntaxError { message: message.to_string(), position };
+    if value.is_empty() {
+        return Err(error(&format!("{}: needs a value", key)));
+    }
+
+    match key {
+        "ext" => {
+            for extension in value.split(',') {
+                let extension = extension.trim().trim_start_matches('.').to_lowercase();
+                if !extension.is_empty() && !filters.extensions.contains(&extension) {
+                    filters.extensions.push(extension);
+                }
+            }
+        }
+        "tag" => {
+            if !filters.tags.iter().any(|tag| tag.eq_ignore_ascii_case(value)) {
+                filters.tags.push(value.to_string());
+            }
+        }
+        "size" => {
+            let (min, max) = parse_size_filter(value)
+                .ok_or_else(|| error("size: expects >N, >=N, <N, <=N or N..M with an optional b, kb, mb or gb unit"))?;
+            if let Some(min) = min {
+                filters.min_size = Some(filters.min_size.map_or(min, |current| current.max(min)));
+            }
+            if let Some(max) = max {
+                filters.max_size = Some(filters.max_size.map_or(max, |current| current.min(max)));
+            }
+        }
+        "before" | "after" => {
+            let (start, end) = parse_date_period(value)
+                .ok_or_else(|| error(&format!("{}: expects a date as YYYY, YYYY-MM or YYYY-MM-DD", key)))?;
+            if key == "before" {
+                filters.before = Some(filters.before.map_or(start, |current| current.min(start)));
+            } else {
+                filters.after = Some(filters.after.map_or(end, |current| current.max(end)));
+            }
+        }
+        _ => unreachable!("unknown filter key {}", key),
+    }
+    Ok(())
+}
+
+/// Inclusive byte bounds of a size filter
+fn parse_size_filter(value: &str) -> Option<(Option<i64>, Option<i64>)> {
+    if let Some((low, high)) = value.split_once("..") {
+        let (low, high) = (parse_size(low)?, parse_size(high)?);
+        return (low <= high).then_some((Some(low), Some(high)));
+    }
+    if let Some(rest) = value.strip_prefix(">=") {
+        return Some((Some(parse_size(rest)?), None));
+    }
+    if let Some(rest) = value.strip_prefix("<=") {
+        return Some((None, Some(parse_size(rest)?)));
+    }
+    if let Some(rest) = value.strip_prefix('>') {
+        return Some((Some(parse_size(rest)?.saturating_add(1)), None));
+    }
+    if let Some(rest) = value.strip_prefix('<') {
+        return Some((None, Some((parse_size(rest)? - 1).max(0))));
+    }
+    None
+}
+
+/// A size such as "10mb" or "1.5 gb" in bytes, units being powers of 1024
+fn parse_size(value: &str) -> Option<i64> {
+    let value = value.trim().to_lowercase();
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit.trim() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1024.0,
+        "m" | "mb" => 1024.0 * 1024.0,
+        "g" | "gb" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * multiplier).round() as i64)
+}
+
+/// The [start, end) days of a year, month or single day
+fn parse_date_period(value: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let parts: Vec<&str> = value.split('-').collect();
+    let numbers: Vec<u32> = parts.iter().map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    if parts[0].len() != 4 {
+        return None;
+    }
+    let year = numbers[0] as i32;
+
+    match numbers[..] {
+        [_] => Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?)),
+        [_, month] => {
+            let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+            let end = if month == 12 {
+                NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+            } else {
+                NaiveDate::from_ymd_opt(year, month + 1, 1)?
+            };
+            Some((start, end))
+        }
+        [_, month, day] => {
+            let start = NaiveDate::from_ymd_opt(year, month, day)?;
+            Some((start, start.succ_opt()?))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_extracts_filters_and_keeps_text() {
+        let filters = extract("ext:pdf size:>10mb before:2023-01 tag:invoice budget").unwrap();
+        assert_eq!(filters.extensions, vec!["pdf"]);
+        assert_eq!(filters.tags, vec!["invoice"]);
+        assert_eq!(filters.min_size, Some(10 * 1024 * 1024 + 1));
+        assert_eq!(filters.before, Some(day(2023, 1, 1)));
+        assert_eq!(filters.remaining_query, "budget");
+    }
+
+    #[test]
+    fn test_filter_values() {
+        let filters = extract("EXT:.JPG,png size:1kb..2kb after:2022 tag:\"tax return\" notes OR drafts").unwrap();
+        assert_eq!(filters.extensions, vec!["jpg", "png"]);
+        assert_eq!((filters.min_size, filters.max_size), (Some(1024), Some(2048)));
+        assert_eq!(filters.after, Some(day(2023, 1, 1)));
+        assert_eq!(filters.tags, vec!["tax return"]);
+        assert_eq!(filters.remaining_query, "notes OR drafts");
+
+        let filters = extract("size:<=1.5mb before:2024-02-29").unwrap();
+        assert_eq!(filters.max_size, Some(1572864));
+        assert_eq!(filters.before, Some(day(2024, 2, 29)));
+
+        // Sizes past what an i64 holds saturate rather than overflow
+        let filters = extract("size:>99999999999gb").unwrap();
+        assert_eq!(filters.min_size, Some(i64::MAX));
+    }
+
+    #[test]
+    fn test_removed_filters_take_their_operators() {
+        let remaining = |query: &str| extract(query).unwrap().remaining_query;
+        assert_eq!(remaining("budget OR ext:pdf"), "budget");
+        assert_eq!(remaining("ext:pdf OR budget"), "budget");
+        assert_eq!(remaining("budget AND NOT tag:draft"), "budget");
+        assert_eq!(remaining("(ext:pdf OR ext:doc) AND budget"), "budget");
+        assert_eq!(remaining("(notes OR size:>1mb) drafts"), "(notes) drafts");
+        assert_eq!(remaining("(a OR b) c"), "(a OR b) c");
+        assert_eq!(remaining("ext:pdf"), "");
+    }
+
+    #[test]
+    fn test_plain_text_is_left_alone() {
+        let filters = extract("\"tag:literal\" path:/docs mytag:x").unwrap();
+        assert!(filters.is_empty());
+        assert_eq!(filters.remaining_query, "\"tag:literal\" path:/docs mytag:x");
+    }
+
+    #[test]
+    fn test_invalid_filters_report_position() {
+        let err = extract("budget size:huge").unwrap_err();
+        assert_eq!(err.position, 7);
+        assert!(err.message.starts_with("size:"));
+
+        assert_eq!(extract("before:2023-13").unwrap_err().position, 0);
+        assert_eq!(extract("a ext:").unwrap_err().message, "ext: needs a value");
+        assert!(extract("size:5mb..1mb").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+
+use crate::ai_processor::AIProcessor;
+use crate::database::Database;
+use crate::semantic_search::{SearchRequest, SearchType, SemanticSearchEngine};
+use crate::snippets;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagConfig {
+    /// Number of files passages are drawn from
+    pub top_k: usize,
+    pub min_similarity: f32,
+    /// Length of the passage taken from each file, in characters
+    pub passage_chars: usize,
+    /// Upper bound for all passages together so the prompt fits the model context
+    pub max_context_chars: usize,
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            top_k: 5,
+            min_similarity: 0.5,
+            passage_chars: 800,
+            max_context_chars: 6000,
+        }
+    }
+}
+
+/// A passage given to the model, numbered as it appears in the prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub index: usize,
+    pub file_id: String,
+    pub file_path: String,
+    pub file_name: String,
+    pub score: f32,
+    pub excerpt: String,
+    /// The answer refers to this passage by its number
+    pub cited: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagAnswer {
+    pub question: String,
+    pub answer: String,
+    pub citations: Vec<Citation>,
+}
+
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "are", "was", "were", "what", "when", "where", "which", "who",
+    "why", "how", "does", "did", "has", "have", "had", "with", "from", "about", "this",
+    "that", "there", "their", "they", "you", "your", "can", "could", "should", "would",
+    "any", "all", "into", "than", "then", "them", "its", "our", "not",
+];
+
+/// Answer `question` from the most relevant indexed files, citing the passages used
+pub async fn ask(
+    engine: &SemanticSearchEngine,
+    database: &Database,
+    ai_processor: &AIProcessor,
+    question: &str,
+    config: &RagConfig,
+) -> Result<RagAnswer> {
+    let question = question.trim();
+    if question.is_empty() {
+        return Err(anyhow!("Question is empty"));
+    }
+
+    let response = engine.search(SearchRequest {
+        query: question.to_string(),
+        search_type: SearchType::Semantic,
+        filters: None,
+        limit: Some(config.top_k),
+        threshold: Some(config.min_similarity),
+        fusion: None,
+    }).await?;
+
+    let terms = question_terms(question);
+    let mut citations = Vec::new();
+    let mut context_chars = 0;
+
+    for result in response.results {
+        let Some(file) = database.get_file_by_id(&result.file_id).await? else {
+            continue;
+        };
+        let Some(text) = file.content.as_deref()
+            .filter(|content| !content.trim().is_empty())
+            .or(file.ai_analysis.as_deref())
+        else {
+            continue;
+        };
+
+        let excerpt = snippets::build_snippet(text, &terms, config.passage_chars).text;
+        let excerpt_chars = excerpt.chars().count();
+        if context_chars + excerpt_chars > config.max_context_chars {
+            break;
+        }
+        context_chars += excerpt_chars;
+
+        citations.push(Citation {
+            index: citations.len() + 1,
+            file_id: result.file_id,
+            // Vector matches carry only the id, the record has the location
+            file_path: file.path.clone(),
+            file_name: file.name.clone(),
+            score: result.similarity_score,
+            excerpt,
+            cited: false,
+        });
+    }
+
+    if citations.is_empty() {
+        return Ok(RagAnswer {
+            question: question.to_string(),
+            answer: "No indexed files appear relevant to this question.".to_string(),
+            citations,
+        });
+    }
+
+    let answer = ai_processor.generate(&build_prompt(question, &citations)).await?;
+    let answer = answer.trim().to_string();
+    for citation in &mut citations {
+        citation.cited = answer.contains(&format!("[{}]", citation.index));
+    }
+
+    Ok(RagAnswer {
+        question: question.to_string(),
+        answer,
+        citations,
+    })
+}
+
+/// Content words of the question, used to pick the passage of each file
+fn question_terms(question: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in question.split(|c: char| !c.is_alphanumeric()) {
+        let word = word.to_lowercase();
+        if word.chars().count() >= 3 && !STOP_WORDS.contains(&word.as_str()) && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+fn build_prompt(question: &str, citations: &[Citation]) -> String {
+    let sources = citations.iter()
+        .map(|c| format!("[{}] {} ({})\n{}", c.index, c.file_name, c.file_path, c.excerpt))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        r#"Answer the question using only the numbered excerpts from the user's files below.
+Cite the excerpts you rely on by their number in square brackets, e.g. [1].
+If the excerpts do not contain the answer, say that you could not find it in the files.
+
+{}
+
+Question: {}
+Answer:"#,
+        sources, question
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn citation(index: usize, name: &str, excerpt: &str) -> Citation {
+        Citation {
+            index,
+            file_id: format!("file-{}", index),
+            file_path: format!("/docs/{}", name),
+            file_name: name.to_string(),
+            score: 0.8,
+            excerpt: excerpt.to_string(),
+            cited: false,
+        }
+    }
+
+    #[test]
+    fn test_question_terms_drop_stop_words() {
+        assert_eq!(
+            question_terms("What was the Q3 budget for the Berlin office?"),
+            vec!["budget".to_string(), "berlin".to_string(), "office".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_prompt_numbers_sources() {
+        let prompt = build_prompt(
+            "When is the lease up?",
+            &[citation(1, "lease.pdf", "The lease ends in May."), citation(2, "notes.md", "Call landlord.")],
+        );
+
+        assert!(prompt.contains("[1] lease.pdf (/docs/lease.pdf)\nThe lease ends in May."));
+        assert!(prompt.contains("[2] notes.md (/docs/notes.md)"));
+        assert!(prompt.trim_end().ends_with("Question: When is the lease up?\nAnswer:"));
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::{Result, anyhow};
+use regex::{Regex, RegexBuilder};
+use serde::{Serialize, Deserialize};
+use tokio::time::{Duration, Instant};
+
+use crate::database::{Database, FileRecord, SearchFilters};
+use crate::search_query::QueryNode;
+
+/// Guards applied to a user-supplied regular expression search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegexSearchConfig {
+    pub max_pattern_length: usize,
+    /// Upper bound for the compiled program, keeps pathological patterns out
+    pub compiled_size_limit: usize,
+    /// Only this many bytes of each file's extracted content are scanned
+    pub max_content_bytes: usize,
+    pub max_matches_per_file: usize,
+    pub timeout: Duration,
+    pub batch_size: i64,
+}
+
+impl Default for RegexSearchConfig {
+    fn default() -> Self {
+        Self {
+            max_pattern_length: 512,
+            compiled_size_limit: 1 << 20,
+            max_content_bytes: 1 << 20,
+            max_matches_per_file: 50,
+            timeout: Duration::from_secs(5),
+            batch_size: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchField {
+    Name,
+    Content,
+}
+
+/// Character offsets of a single match, suitable for highlighting
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegexMatch {
+    pub field: MatchField,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub struct RegexSearchHit {
+    pub file: FileRecord,
+    pub matches: Vec<RegexMatch>,
+}
+
+/// Where a search resumes in the candidate ordering, with the hits earlier pages found
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RegexScanPosition {
+    /// Candidates already scanned
+    pub offset: i64,
+    /// Hits returned before this position
+    pub matched: usize,
+}
+
+#[derive(Debug)]
+pub struct RegexSearchOutcome {
+    pub hits: Vec<RegexSearchHit>,
+    pub files_scanned: usize,
+    /// The deadline elapsed before every candidate was scanned
+    pub timed_out: bool,
+    /// Where the following page starts, `None` once every candidate has been scanned
+    pub next: Option<RegexScanPosition>,
+}
+
+impl RegexSearchOutcome {
+    /// Hits on this page and the earlier ones; only a lower bound while `next` is set
+    pub fn total(&self, start: RegexScanPosition) -> usize {
+        start.matched + self.hits.len()
+    }
+}
+
+pub struct RegexSearcher {
+    regex: Regex,
+    config: RegexSearchConfig,
+}
+
+impl RegexSearcher {
+    pub fn new(pattern: &str, config: RegexSearchConfig) -> Result<Self> {
+        if pattern.is_empty() {
+            return Err(anyhow!("Regular expression is empty"));
+        }
+        if pattern.len() > config.max_pattern_length {
+            return Err(anyhow!(
+                "Regular expression is longer than {} characters",
+                config.max_pattern_length
+            ));
+        }
+
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .multi_line(true)
+            .size_limit(config.compiled_size_limit)
+            .dfa_size_limit(config.compiled_size_limit)
+            .build()
+            .map_err(|e| anyhow!("Invalid regular expression: {}", e))?;
+
+        Ok(Self { regex, config })
+    }
+
+    /// Scan names and extracted content of files passing `filters` from `start`, stopping at
+    /// `limit` hits or the deadline. At least one file is scanned so a resumed search always
+    /// makes progress.
+    pub async fn search(
+        &self,
+        database: &Database,
+        filters: &SearchFilters,
+        start: RegexScanPosition,
+        limit: usize,
+    ) -> Result<RegexSearchOutcome> {
+        let deadline = Instant::now() + self.config.timeout;
+        let match_all = QueryNode::And(Vec::new());
+
+        let mut hits = Vec::new();
+        let mut files_scanned = 0;
+        let mut offset = start.offset;
+        let mut timed_out = false;
+        let mut exhausted = false;
+
+        'batches: loop {
+            let batch = database
+                .search_files_filtered(&match_all, filters, self.config.batch_size, offset)
+                .await?;
+            let last_batch = (batch.len() as i64) < self.config.batch_size;
+
+            for file in batch {
+                if files_scanned > 0 && Instant::now() >= deadline {
+                    timed_out = true;
+                    break 'batches;
+                }
+                files_scanned += 1;
+                offset += 1;
+
+                let matches = self.find_matches(&file);
+                if !matches.is_empty() {
+                    hits.push(RegexSearchHit { file, matches });
+                    if hits.len() >= limit {
+                        break 'batches;
+                    }
+                }
+            }
+
+            if last_batch {
+                exhausted = true;
+                break;
+            }
+        }
+
+        let next = (!exhausted).then(|| RegexScanPosition {
+            offset,
+            matched: start.matched + hits.len(),
+        });
+        Ok(RegexSearchOutcome { hits, files_scanned, timed_out, next })
+    }
+
+    pub fn find_matches(&self, file: &FileRecord) -> Vec<RegexMatch> {
+        let mut matches = self.matches_in(&file.name, MatchField::Name, self.config.max_matches_per_file);
+
+        if let Some(content) = &file.content {
+            let remaining = self.config.max_matches_per_file.saturating_sub(matches.len());
+            let scanned = truncate_to_boundary(content, self.config.max_content_bytes);
+            matches.extend(self.matches_in(scanned, MatchField::Content, remaining));
+        }
+
+        matches
+    }
+
+    fn matches_in(&self, text: &str, field: MatchField, max: usize) -> Vec<RegexMatch> {
+        let mut matches = Vec::new();
+        // Convert byte offsets to character offsets incrementally instead of rescanning from 0
+        let mut last_byte = 0;
+        let mut last_char = 0;
+
+        for found in self.regex.find_iter(text).filter(|m| !m.is_empty()).take(max) {
+            let start = last_char + text[last_byte..found.start()].chars().count();
+            let end = start + found.as_str().chars().count();
+            last_byte = found.end();
+            last_char = end;
+
+            matches.push(RegexMatch {
+                field: field.clone(),
+                start,
+                end,
+                text: found.as_str().to_string(),
+            });
+        }
+
+        matches
+    }
+}
+
+fn truncate_to_boundary(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn file_with(name: &str, content: &str) -> FileRecord {
+        FileRecord {
+            id: "file-1".to_string(),
+            path: format!("/tmp/{}", name),
+            name: name.to_string(),
+            extension: None,
+            size: content.len() as i64,
+            created_at: Utc::now(),
+            modified_at: Utc::now(),
+            last_accessed: None,
+            mime_type: None,
+            hash: None,
+            content: Some(content.to_string()),
+            tags: None,
+            metadata: None,
+            ai_analysis: None,
+            embedding: None,
+            indexed_at: None,
+            processing_status: "completed".to_string(),
+            error_message: None,
+        }
+    }
+
+    #[test]
+    fn test_match_offsets_are_character_based() {
+        let searcher = RegexSearcher::new(r"inv-\d+", RegexSearchConfig::default()).unwrap();
+        let matches = searcher.find_matches(&file_with("INV-7.txt", "café inv-42 and inv-43"));
+
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0], RegexMatch { field: MatchField::Name, start: 0, end: 5, text: "INV-7".to_string() });
+        assert_eq!(matches[1].field, MatchField::Content);
+        assert_eq!((matches[1].start, matches[1].end), (5, 11));
+        assert_eq!((matches[2].start, matches[2].end), (16, 22));
+    }
+
+    async fn database_with(names: &[&str]) -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let database = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        for (i, name) in names.iter().enumerate() {
+            let mut file = file_with(name, "");
+            file.id = format!("file-{}", i);
+            database.insert_file(&file).await.unwrap();
+        }
+        (database, temp_dir)
+    }
+
+    fn hit_ids(outcome: &RegexSearchOutcome) -> Vec<String> {
+        outcome.hits.iter().map(|hit| hit.file.id.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_resume_without_overlap() {
+        let (database, _temp_dir) = database_with(&["report-1.txt", "notes.txt", "report-2.txt", "report-3.txt"]).await;
+        let config = RegexSearchConfig { batch_size: 2, ..Default::default() };
+        let searcher = RegexSearcher::new(r"report-\d", config).unwrap();
+        let filters = SearchFilters::default();
+
+        let first = searcher.search(&database, &filters, RegexScanPosition::default(), 2).await.unwrap();
+        assert_eq!(first.hits.len(), 2);
+        let next = first.next.expect("More candidates remain after the first page");
+        assert_eq!(next.matched, 2);
+
+        let second = searcher.search(&database, &filters, next, 2).await.unwrap();
+        assert_eq!(second.hits.len(), 1);
+        assert!(second.next.is_none());
+        assert_eq!(second.total(next), 3);
+
+        let first_ids = hit_ids(&first);
+        assert!(hit_ids(&second).iter().all(|id| !first_ids.contains(id)));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_leaves_a_resumable_position() {
+        let (database, _temp_dir) = database_with(&["report-1.txt", "report-2.txt", "report-3.txt"]).await;
+        let filters = SearchFilters::default();
+
+        let config = RegexSearchConfig { timeout: Duration::ZERO, ..Default::default() };
+        let hurried = RegexSearcher::new(r"report-\d", config).unwrap();
+        let partial = hurried.search(&database, &filters, RegexScanPosition::default(), 10).await.unwrap();
+        assert!(partial.timed_out);
+        assert_eq!(partial.files_scanned, 1);
+        let next = partial.next.expect("A timed out search can be continued");
+        assert_eq!(next, RegexScanPosition { offset: 1, matched: 1 });
+
+        let searcher = RegexSearcher::new(r"report-\d", RegexSearchConfig::default()).unwrap();
+        let rest = searcher.search(&database, &filters, next, 10).await.unwrap();
+        assert!(!rest.timed_out);
+        assert!(rest.next.is_none());
+        assert_eq!(rest.hits.len(), 2);
+        assert!(!hit_ids(&rest).contains(&partial.hits[0].file.id));
+        assert_eq!(rest.total(next), 3);
+    }
+
+    #[test]
+    fn test_guards() {
+        let config = RegexSearchConfig { max_pattern_length: 4, ..Default::default() };
+        assert!(RegexSearcher::new("abcde", config).is_err());
+        assert!(RegexSearcher::new("(unclosed", RegexSearchConfig::default()).is_err());
+
+        let config = RegexSearchConfig { max_content_bytes: 6, ..Default::default() };
+        let searcher = RegexSearcher::new("needle", config).unwrap();
+        assert!(searcher.find_matches(&file_with("a.txt", "hay needle")).is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::database::{Database, FileRecord, SearchFilters};
+use crate::search_query::QueryNode;
+
+/// Rows fetched from the database per round trip while exporting
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Guess the format from the target file's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportRow {
+    pub path: String,
+    pub size: i64,
+    pub tags: Vec<String>,
+    pub summary: Option<String>,
+    pub score: f32,
+}
+
+impl ExportRow {
+    fn from_file(file: FileRecord, score: f32) -> Self {
+        let tags = file.tags.as_deref()
+            .and_then(|tags| serde_json::from_str::<Vec<String>>(tags).ok())
+            .unwrap_or_default();
+
+        Self {
+            path: file.path,
+            size: file.size,
+            tags,
+            summary: file.ai_analysis,
+            score,
+        }
+    }
+}
+
+/// Write every file matching the query to `output`, paging through the results
+/// so the whole set is never held in memory. Returns the number of rows written.
+pub async fn export_search_results(
+    database: &Database,
+    query: &QueryNode,
+    filters: &SearchFilters,
+    format: ExportFormat,
+    output: &Path,
+) -> Result<usize> {
+    let mut writer = BufWriter::new(File::create(output).await?);
+    let mut written = 0;
+    let mut cursor: Option<String> = None;
+
+    match format {
+        ExportFormat::Csv => writer.write_all(b"path,size,tags,summary,score\n").await?,
+        ExportFormat::Json => writer.write_all(b"[").await?,
+    }
+
+    loop {
+        let page = database.search_files_page(query, filters, EXPORT_BATCH_SIZE, cursor.as_deref()).await?;
+
+        for file in page.files {
+            // Keyword matches are unranked, matching the score shown in the UI
+            let row = ExportRow::from_file(file, 0.85);
+            match format {
+                ExportFormat::Csv => {
+                    let line = format!(
+                        "{},{},{},{},{}\n",
+                        csv_field(&row.path),
+                        row.size,
+                        csv_field(&row.tags.join("; ")),
+                        csv_field(row.summary.as_deref().unwrap_or("")),
+                        row.score,
+                    );
+                    writer.write_all(line.as_bytes()).await?;
+                }
+                ExportFormat::Json => {
+                    if written > 0 {
+                        writer.write_all(b",").await?;
+                    }
+                    writer.write_all(b"\n  ").await?;
+                    writer.write_all(serde_json::to_string(&row)?.as_bytes()).await?;
+                }
+            }
+            written += 1;
+        }
+
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    if format == ExportFormat::Json {
+        writer.write_all(b"\n]\n").await?;
+    }
+    writer.flush().await?;
+
+    Ok(written)
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ExportFormat::from_path(Path::new("/tmp/out.CSV")), Some(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_path(Path::new("/tmp/out.json")), Some(ExportFormat::Json));
+        assert_eq!(ExportFormat::from_path(Path::new("/tmp/out.txt")), None);
+    }
+}
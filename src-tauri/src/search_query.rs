@@ -0,0 +1,359 @@
+use serde::{Serialize, Deserialize};
+
+/// Grammar accepted by `QueryNode::parse`, returned to the frontend alongside syntax errors
+pub const QUERY_GRAMMAR: &str = r#"query   := or_expr
+or_expr := and_expr ("OR" and_expr)*
+and_expr:= unary (["AND"] unary)*      adjacent terms are implicitly ANDed
+unary   := ("NOT" | "-") unary | primary
+primary := "(" or_expr ")" | "\"phrase\"" | path | term
+path    := "path:" (term | "\"phrase\"")  absolute paths match the subtree, others any path containing it
+Operators are case-sensitive: "and", "or" and "not" in lowercase are plain terms."#;
+
+/// Parsed boolean search query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QueryNode {
+    Term(String),
+    Phrase(String),
+    /// Constraint on the file path rather than its text
+    Path(String),
+    And(Vec<QueryNode>),
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuerySyntaxError {
+    pub message: String,
+    /// Character offset into the original query
+    pub position: usize,
+}
+
+impl std::fmt::Display for QuerySyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QuerySyntaxError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Path(String),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl QueryNode {
+    /// Parse a user query into an AST; an empty query matches everything
+    pub fn parse(input: &str) -> Result<QueryNode, QuerySyntaxError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(QueryNode::And(Vec::new()));
+        }
+
+        let mut parser = Parser { tokens, pos: 0, end: input.chars().count() };
+        let node = parser.parse_or()?;
+
+        if let Some((token, position)) = parser.tokens.get(parser.pos) {
+            let message = match token {
+                Token::RParen => "Unmatched closing parenthesis".to_string(),
+                _ => "Unexpected token".to_string(),
+            };
+            return Err(QuerySyntaxError { message, position: *position });
+        }
+
+        Ok(node)
+    }
+
+    /// Plain text of the terms and phrases that are not negated, for embedding-based search
+    pub fn positive_text(&self) -> String {
+        let mut parts = Vec::new();
+        self.collect_positive(&mut parts);
+        parts.join(" ")
+    }
+
+    /// Terms and phrases a match should contain, one entry each, for highlighting
+    pub fn positive_terms(&self) -> Vec<String> {
+        let mut parts = Vec::new();
+        self.collect_positive(&mut parts);
+        parts
+    }
+
+    fn collect_positive(&self, parts: &mut Vec<String>) {
+        match self {
+            QueryNode::Term(term) | QueryNode::Phrase(term) => parts.push(term.clone()),
+            QueryNode::And(children) | QueryNode::Or(children) => {
+                for child in children {
+                    child.collect_positive(parts);
+                }
+            }
+            QueryNode::Path(_) | QueryNode::Not(_) => {}
+        }
+    }
+
+    /// Path constraints every match must satisfy, i.e. those ANDed at the top level
+    pub fn required_paths(&self) -> Vec<&QueryNode> {
+        match self {
+            QueryNode::Path(_) => vec![self],
+            QueryNode::And(children) => children.iter()
+                .filter(|child| matches!(child, QueryNode::Path(_)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Sub-expressions that appear under a NOT at any level
+    pub fn negated_nodes(&self) -> Vec<&QueryNode> {
+        match self {
+            QueryNode::Not(inner) => vec![inner.as_ref()],
+            QueryNode::And(children) | QueryNode::Or(children) => {
+                children.iter().flat_map(|child| child.negated_nodes()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos)
+            .map(|(_, position)| *position)
+            .unwrap_or(self.end)
+    }
+
+    fn parse_or(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let mut children = vec![self.parse_and()?];
+
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            children.push(self.parse_and()?);
+        }
+
+        Ok(if children.len() == 1 { children.remove(0) } else { QueryNode::Or(children) })
+    }
+
+    fn parse_and(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let mut children = vec![self.parse_unary()?];
+
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.pos += 1;
+                    children.push(self.parse_unary()?);
+                }
+                Some(Token::Word(_)) | Some(Token::Phrase(_)) | Some(Token::Path(_)) | Some(Token::Not) | Some(Token::LParen) => {
+                    children.push(self.parse_unary()?);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(if children.len() == 1 { children.remove(0) } else { QueryNode::And(children) })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(QueryNode::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let position = self.position();
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+
+        match token {
+            Some(Token::Word(word)) => {
+                self.pos += 1;
+                Ok(QueryNode::Term(word))
+            }
+            Some(Token::Phrase(phrase)) => {
+                self.pos += 1;
+                Ok(QueryNode::Phrase(phrase))
+            }
+            Some(Token::Path(path)) => {
+                self.pos += 1;
+                Ok(QueryNode::Path(path))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let node = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(QuerySyntaxError {
+                        message: "Missing closing parenthesis".to_string(),
+                        position: self.position(),
+                    });
+                }
+                self.pos += 1;
+                Ok(node)
+            }
+            Some(Token::RParen) => Err(QuerySyntaxError {
+                message: "Unmatched closing parenthesis".to_string(),
+                position,
+            }),
+            Some(Token::And) | Some(Token::Or) | Some(Token::Not) => Err(QuerySyntaxError {
+                message: "Operator is missing an operand".to_string(),
+                position,
+            }),
+            None => Err(QuerySyntaxError {
+                message: "Query ends where a term was expected".to_string(),
+                position,
+            }),
+        }
+    }
+}
+
+const PATH_PREFIX: [char; 5] = ['p', 'a', 't', 'h', ':'];
+
+/// Read a quoted string starting at the opening quote, returning it and the index after the closing quote
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QuerySyntaxError> {
+    match chars[start + 1..].iter().position(|&ch| ch == '"') {
+        Some(offset) => {
+            let end = start + 1 + offset;
+            Ok((chars[start + 1..end].iter().collect(), end + 1))
+        }
+        None => Err(QuerySyntaxError {
+            message: "Unterminated quoted phrase".to_string(),
+            position: start,
+        }),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, QuerySyntaxError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push((Token::LParen, i));
+            i += 1;
+        } else if c == ')' {
+            tokens.push((Token::RParen, i));
+            i += 1;
+        } else if c == '-' && i + 1 < chars.len() && !chars[i + 1].is_whitespace() {
+            tokens.push((Token::Not, i));
+            i += 1;
+        } else if c == '"' {
+            let (phrase, next) = read_quoted(&chars, i)?;
+            if !phrase.trim().is_empty() {
+                tokens.push((Token::Phrase(phrase.trim().to_string()), i));
+            }
+            i = next;
+        } else if chars[i..].starts_with(&PATH_PREFIX) {
+            let start = i;
+            i += PATH_PREFIX.len();
+            let path = if i < chars.len() && chars[i] == '"' {
+                let (path, next) = read_quoted(&chars, i)?;
+                i = next;
+                path
+            } else {
+                let value_start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')') {
+                    i += 1;
+                }
+                chars[value_start..i].iter().collect()
+            };
+            if path.trim().is_empty() {
+                return Err(QuerySyntaxError {
+                    message: "path: needs a value".to_string(),
+                    position: start,
+                });
+            }
+            tokens.push((Token::Path(path.trim().to_string()), start));
+        } else {
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '"') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let token = match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Word(word),
+            };
+            tokens.push((token, start));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(s: &str) -> QueryNode {
+        QueryNode::Term(s.to_string())
+    }
+
+    #[test]
+    fn test_implicit_and_and_precedence() {
+        let node = QueryNode::parse("rust async OR tokio").unwrap();
+        assert_eq!(node, QueryNode::Or(vec![
+            QueryNode::And(vec![term("rust"), term("async")]),
+            term("tokio"),
+        ]));
+    }
+
+    #[test]
+    fn test_phrases_negation_and_groups() {
+        let node = QueryNode::parse("\"annual report\" -draft NOT (old OR archived)").unwrap();
+        assert_eq!(node, QueryNode::And(vec![
+            QueryNode::Phrase("annual report".to_string()),
+            QueryNode::Not(Box::new(term("draft"))),
+            QueryNode::Not(Box::new(QueryNode::Or(vec![term("old"), term("archived")]))),
+        ]));
+        assert_eq!(node.positive_text(), "annual report");
+        assert_eq!(node.negated_nodes().len(), 2);
+    }
+
+    #[test]
+    fn test_path_constraints() {
+        let node = QueryNode::parse("invoice path:\"/Users/me/Work Docs\" -path:node_modules").unwrap();
+        assert_eq!(node, QueryNode::And(vec![
+            term("invoice"),
+            QueryNode::Path("/Users/me/Work Docs".to_string()),
+            QueryNode::Not(Box::new(QueryNode::Path("node_modules".to_string()))),
+        ]));
+        assert_eq!(node.positive_text(), "invoice");
+        assert_eq!(node.required_paths(), vec![&QueryNode::Path("/Users/me/Work Docs".to_string())]);
+
+        assert_eq!(QueryNode::parse("path:").unwrap_err().message, "path: needs a value");
+    }
+
+    #[test]
+    fn test_syntax_errors_report_position() {
+        let err = QueryNode::parse("budget AND").unwrap_err();
+        assert_eq!(err.position, 10);
+
+        let err = QueryNode::parse("(a OR b").unwrap_err();
+        assert_eq!(err.message, "Missing closing parenthesis");
+
+        let err = QueryNode::parse("say \"hello").unwrap_err();
+        assert_eq!(err.position, 4);
+
+        assert_eq!(QueryNode::parse("  ").unwrap(), QueryNode::And(Vec::new()));
+    }
+}
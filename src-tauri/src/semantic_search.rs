@@ -1,5 +1,5 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 
@@ -7,6 +7,8 @@ use crate::vector_math::VectorMath;
 use crate::vector_storage::{VectorStorageManager, VectorType};
 use crate::ai_processor::AIProcessor;
 use crate::content_extractor::ExtractedContent;
+use crate::database::{Database, FileRecord, SearchFilters as KeywordFilters};
+use crate::search_query::QueryNode;
 
 /// Advanced semantic search engine with vector capabilities
 #[derive(Debug, Clone)]
@@ -14,6 +16,8 @@ pub struct SemanticSearchEngine {
     vector_storage: VectorStorageManager,
     ai_processor: AIProcessor,
     config: SearchConfig,
+    /// Source of the keyword ranking fused into hybrid results
+    keyword_index: Option<Database>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,8 +30,25 @@ pub struct SearchConfig {
     pub content_weight: f32,
     pub metadata_weight: f32,
     pub summary_weight: f32,
+    /// Ask the LLM to reorder the top candidates by relevance; costs one model round trip per search
+    #[serde(default)]
+    pub enable_reranking: bool,
+    /// How many of the top results are sent to the reranker
+    #[serde(default = "default_rerank_candidates")]
+    pub rerank_candidates: usize,
 }
 
+fn default_rerank_candidates() -> usize {
+    50
+}
+
+/// Characters of each candidate's description included in the rerank prompt
+const RERANK_DESCRIPTION_CHARS: usize = 300;
+
+/// Keyword matches fetched per hybrid result, so relevance ordering picks from more than the
+/// files the database lists first
+const KEYWORD_CANDIDATE_FACTOR: usize = 4;
+
 impl Default for SearchConfig {
     fn default() -> Self {
         Self {
@@ -39,6 +60,8 @@ impl Default for SearchConfig {
             content_weight: 0.6,
             metadata_weight: 0.2,
             summary_weight: 0.2,
+            enable_reranking: false,
+            rerank_candidates: default_rerank_candidates(),
         }
     }
 }
@@ -73,6 +96,8 @@ pub enum MatchType {
     SemanticSummary,
     HybridMatch,
     FolderTheme,
+    /// Found by the keyword ranking of a hybrid search only
+    Keyword,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +107,28 @@ pub struct SearchRequest {
     pub filters: Option<SearchFilters>,
     pub limit: Option<usize>,
     pub threshold: Option<f32>,
+    /// Per-source weights for hybrid searches, defaults to equal weighting
+    #[serde(default)]
+    pub fusion: Option<FusionWeights>,
+}
+
+/// Weights of the ranked lists merged by reciprocal rank fusion
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FusionWeights {
+    pub keyword: f32,
+    pub semantic: f32,
+    /// Damping constant, larger values flatten the advantage of the top ranks
+    pub k: f32,
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        Self {
+            keyword: 1.0,
+            semantic: 1.0,
+            k: 60.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -91,6 +138,26 @@ pub enum SearchType {
     FolderOnly,
     ContentOnly,
     MetadataOnly,
+    /// Pattern match over names and extracted content, served by `regex_search`
+    Regex,
+    /// Pick keyword, semantic or hybrid from the shape of the query, see `classify_query`
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchRoute {
+    Keyword,
+    Semantic,
+    Hybrid,
+}
+
+/// The route chosen for an automatically routed query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryIntent {
+    pub route: SearchRoute,
+    /// Short explanation shown alongside the results
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,11 +175,17 @@ pub struct SearchResponse {
     pub query: String,
     pub expanded_query: Option<String>,
     pub total_results: usize,
+    /// Set when the engine stopped at the limit, so more than `total_results` may match
+    #[serde(default)]
+    pub truncated: bool,
     pub search_time_ms: u128,
     pub results: Vec<SearchResult>,
     pub folder_results: Vec<FolderSearchResult>,
     pub suggestions: Vec<String>,
     pub facets: SearchFacets,
+    /// Set when the request used `SearchType::Auto`
+    #[serde(default)]
+    pub intent: Option<QueryIntent>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -129,9 +202,15 @@ impl SemanticSearchEngine {
             vector_storage,
             ai_processor,
             config: SearchConfig::default(),
+            keyword_index: None,
         }
     }
 
+    pub fn with_keyword_index(mut self, database: Database) -> Self {
+        self.keyword_index = Some(database);
+        self
+    }
+
     pub fn with_config(mut self, config: SearchConfig) -> Self {
         self.config = config;
         self
@@ -140,6 +219,28 @@ impl SemanticSearchEngine {
     /// Perform comprehensive semantic search
     pub async fn search(&self, request: SearchRequest) -> Result<SearchResponse> {
         let start_time = std::time::Instant::now();
+
+        if matches!(request.search_type, SearchType::Regex) {
+            return Err(anyhow!("Regex searches are not vector based; use RegexSearcher"));
+        }
+
+        let mut request = request;
+        let intent = if matches!(request.search_type, SearchType::Auto) {
+            let intent = classify_query(&request.query);
+            request.search_type = match intent.route {
+                SearchRoute::Semantic => SearchType::Semantic,
+                SearchRoute::Hybrid => SearchType::Hybrid,
+                SearchRoute::Keyword => {
+                    // Keyword-only ranking is a hybrid search with the vector list weighted out
+                    let fusion = request.fusion.unwrap_or_default();
+                    request.fusion = Some(FusionWeights { semantic: 0.0, ..fusion });
+                    SearchType::Hybrid
+                }
+            };
+            Some(intent)
+        } else {
+            None
+        };
         
         // Expand query if enabled
         let expanded_query = if self.config.enable_query_expansion {
@@ -153,7 +254,7 @@ impl SemanticSearchEngine {
             &request.query,
             expanded_query.clone(),
             self.generate_query_vector(&request.query),
-            "nomic-embed-text", // TODO: Make configurable
+            self.ai_processor.embedding_model(),
         ).await?;
 
         // Perform search based on type
@@ -182,6 +283,8 @@ impl SemanticSearchEngine {
                 let files = self.metadata_only_search(&query_vector, &request).await?;
                 (files, Vec::new())
             },
+            SearchType::Regex => unreachable!("regex requests return early"),
+            SearchType::Auto => unreachable!("auto requests are resolved above"),
         };
 
         // Apply filters
@@ -189,9 +292,18 @@ impl SemanticSearchEngine {
             results = self.apply_filters(results, filters).await?;
         }
 
+        // A failed rerank keeps the vector ranking rather than failing the search
+        if self.config.enable_reranking && results.len() > 1 {
+            if let Err(e) = self.rerank(&request.query, &mut results).await {
+                tracing::warn!("Reranking failed, keeping vector order: {}", e);
+            }
+        }
+
         // Limit results
         let limit = request.limit.unwrap_or(self.config.max_results);
         results.truncate(limit);
+        // A full window means the engine stopped counting, not that nothing else matched
+        let truncated = results.len() >= limit;
 
         // Generate suggestions and facets
         let suggestions = self.generate_suggestions(&request.query, &results).await?;
@@ -203,11 +315,13 @@ impl SemanticSearchEngine {
             query: request.query,
             expanded_query,
             total_results: results.len(),
+            truncated,
             search_time_ms: search_time,
             results,
             folder_results,
             suggestions,
             facets,
+            intent,
         })
     }
 
@@ -271,6 +385,36 @@ impl SemanticSearchEngine {
                     last_modified: Utc::now(),
                 });
             }
+
+            // Long documents are matched chunk by chunk too; the best chunk stands in
+            // for the whole-document vector when it scores higher
+            let chunk_matches = self.vector_storage.find_best_chunks(query_vector, limit, threshold).await?;
+            for chunk in chunk_matches {
+                let score = chunk.score * self.config.content_weight;
+                let best_chunk = serde_json::json!({
+                    "chunk_index": chunk.chunk_index,
+                    "start": chunk.start,
+                    "end": chunk.end,
+                    "score": chunk.score,
+                });
+
+                if let Some(existing) = all_results.iter_mut().find(|r| r.file_id == chunk.file_id) {
+                    existing.similarity_score = existing.similarity_score.max(score);
+                    existing.metadata.insert("best_chunk".to_string(), best_chunk);
+                } else {
+                    all_results.push(SearchResult {
+                        file_id: chunk.file_id,
+                        file_path: String::new(),
+                        file_name: String::new(),
+                        similarity_score: score,
+                        match_type: MatchType::SemanticContent,
+                        snippet: None,
+                        highlights: Vec::new(),
+                        metadata: HashMap::from([("best_chunk".to_string(), best_chunk)]),
+                        last_modified: Utc::now(),
+                    });
+                }
+            }
         }
 
         // Search metadata vectors
@@ -338,11 +482,46 @@ impl SemanticSearchEngine {
         // Sort by combined similarity score
         all_results.sort_by(|a, b| b.similarity_score.partial_cmp(&a.similarity_score).unwrap_or(std::cmp::Ordering::Equal));
         all_results.truncate(limit);
+        self.attach_chunk_snippets(&mut all_results).await?;
 
         // TODO: Enrich results with file metadata
         Ok(all_results)
     }
 
+    /// Use the text of the best-matching chunk as the snippet of chunk-matched results
+    async fn attach_chunk_snippets(&self, results: &mut [SearchResult]) -> Result<()> {
+        for result in results.iter_mut().filter(|r| r.snippet.is_none()) {
+            let Some(chunk) = result.metadata.get("best_chunk") else {
+                continue;
+            };
+            let (Some(start), Some(end)) = (chunk["start"].as_u64(), chunk["end"].as_u64()) else {
+                continue;
+            };
+            result.snippet = self.vector_storage
+                .get_chunk_text(&result.file_id, start as usize, end as usize)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Second stage: let the LLM judge the top candidates against the query and reorder them
+    async fn rerank(&self, query: &str, results: &mut Vec<SearchResult>) -> Result<()> {
+        let count = results.len().min(self.config.rerank_candidates);
+        let ids: Vec<String> = results[..count].iter().map(|r| r.file_id.clone()).collect();
+        let descriptions = self.vector_storage.get_file_descriptions(&ids, RERANK_DESCRIPTION_CHARS).await?;
+
+        let candidates: Vec<(String, String)> = ids.iter()
+            .map(|id| descriptions.get(id).cloned().unwrap_or_else(|| (id.clone(), String::new())))
+            .collect();
+        let response = self.ai_processor.generate(&build_rerank_prompt(query, &candidates)).await?;
+        let order = parse_rerank_order(&response, count)?;
+
+        let mut head: Vec<Option<SearchResult>> = results.drain(..count).map(Some).collect();
+        let reordered: Vec<SearchResult> = order.into_iter().filter_map(|i| head[i].take()).collect();
+        results.splice(0..0, reordered);
+        Ok(())
+    }
+
     /// Search folder-level vectors
     async fn folder_search(&self, query_vector: &[f32], request: &SearchRequest) -> Result<Vec<FolderSearchResult>> {
         let threshold = request.threshold.unwrap_or(self.config.similarity_threshold);
@@ -384,9 +563,85 @@ impl SemanticSearchEngine {
             Vec::new()
         };
 
-        // TODO: Implement traditional keyword search and merge results
-        // For now, return semantic results
-        Ok((semantic_results, folder_results))
+        let Some(database) = self.keyword_index.as_ref() else {
+            return Ok((semantic_results, folder_results));
+        };
+        let (terms, exclusions) = hybrid_keyword_terms(&request.query);
+
+        // Files matching an excluded word leave the vector ranking too
+        let semantic_results = if exclusions.is_empty() {
+            semantic_results
+        } else {
+            let ids: Vec<String> = semantic_results.iter().map(|r| r.file_id.clone()).collect();
+            let excluded: HashSet<String> = database
+                .file_ids_matching(&ids, &QueryNode::Or(exclusions.clone()), &KeywordFilters::default())
+                .await?
+                .into_iter()
+                .collect();
+            semantic_results.into_iter().filter(|r| !excluded.contains(&r.file_id)).collect()
+        };
+        if terms.is_empty() {
+            return Ok((semantic_results, folder_results));
+        }
+
+        // Any query word may match, the fused ranking rewards files matching many of them
+        let mut keyword_query = vec![QueryNode::Or(terms.iter().map(|term| QueryNode::Term(term.clone())).collect())];
+        keyword_query.extend(exclusions.into_iter().map(|node| QueryNode::Not(Box::new(node))));
+        let keyword_query = QueryNode::And(keyword_query);
+
+        let limit = request.limit.unwrap_or(self.config.max_results);
+        let mut keyword_files = database
+            .search_files_filtered(&keyword_query, &KeywordFilters::default(), (limit * KEYWORD_CANDIDATE_FACTOR) as i64, 0)
+            .await?;
+        // The database orders by analysis state and age; rank positions must follow relevance
+        let lowered_terms: Vec<String> = terms.iter().map(|term| term.to_lowercase()).collect();
+        keyword_files.sort_by_cached_key(|file| std::cmp::Reverse(keyword_relevance(file, &lowered_terms)));
+        keyword_files.truncate(limit);
+
+        let weights = request.fusion.unwrap_or_default();
+        let fused = reciprocal_rank_fusion(
+            &[
+                (keyword_files.iter().map(|f| f.id.clone()).collect(), weights.keyword),
+                (semantic_results.iter().map(|r| r.file_id.clone()).collect(), weights.semantic),
+            ],
+            weights.k,
+        );
+
+        let mut semantic_by_id: HashMap<String, SearchResult> = semantic_results.into_iter()
+            .map(|r| (r.file_id.clone(), r))
+            .collect();
+        let keyword_by_id: HashMap<&str, _> = keyword_files.iter().map(|f| (f.id.as_str(), f)).collect();
+
+        let mut results = Vec::with_capacity(fused.len());
+        for (file_id, score) in fused.into_iter().take(limit) {
+            let keyword_file = keyword_by_id.get(file_id.as_str());
+            let result = match (semantic_by_id.remove(&file_id), keyword_file) {
+                (Some(mut result), keyword_file) => {
+                    if let Some(file) = keyword_file {
+                        result.match_type = MatchType::HybridMatch;
+                        result.file_path = file.path.clone();
+                        result.file_name = file.name.clone();
+                    }
+                    result.similarity_score = score;
+                    result
+                }
+                (None, Some(file)) => SearchResult {
+                    file_id: file.id.clone(),
+                    file_path: file.path.clone(),
+                    file_name: file.name.clone(),
+                    similarity_score: score,
+                    match_type: MatchType::Keyword,
+                    snippet: None,
+                    highlights: Vec::new(),
+                    metadata: HashMap::new(),
+                    last_modified: file.modified_at,
+                },
+                (None, None) => continue,
+            };
+            results.push(result);
+        }
+
+        Ok((results, folder_results))
     }
 
     /// Content-only semantic search
@@ -540,6 +795,190 @@ impl SemanticSearchEngine {
     }
 }
 
+/// The words a hybrid search matches in the keyword index and the parts of the query that
+/// exclude files, e.g. `-draft`. A query that does not parse searches its words as written.
+fn hybrid_keyword_terms(query: &str) -> (Vec<String>, Vec<QueryNode>) {
+    match QueryNode::parse(query) {
+        Ok(parsed) => (parsed.positive_terms(), parsed.negated_nodes().into_iter().cloned().collect()),
+        Err(_) => (
+            query.split_whitespace().filter(|word| !word.starts_with('-')).map(str::to_string).collect(),
+            Vec::new(),
+        ),
+    }
+}
+
+/// How well a keyword match fits: a point for each of `terms` (lowercased) found in its text,
+/// and two for each found in its name. Ties keep the database order.
+fn keyword_relevance(file: &FileRecord, terms: &[String]) -> usize {
+    let name = file.name.to_lowercase();
+    let text = [&file.content, &file.tags, &file.ai_analysis]
+        .into_iter()
+        .flatten()
+        .map(|field| field.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("\n");
+    terms.iter()
+        .map(|term| usize::from(name.contains(term.as_str())) * 2 + usize::from(text.contains(term.as_str())))
+        .sum()
+}
+
+/// Merge ranked id lists, scoring each id by the weighted sum of 1 / (k + rank).
+/// Scores are scaled so an id ranked first in every list gets 1.0; ties keep first-seen order.
+/// Lists weighted zero or below are left out entirely.
+fn reciprocal_rank_fusion(lists: &[(Vec<String>, f32)], k: f32) -> Vec<(String, f32)> {
+    let mut order: Vec<String> = Vec::new();
+    let mut scores: HashMap<String, f32> = HashMap::new();
+    let lists: Vec<&(Vec<String>, f32)> = lists.iter().filter(|(_, weight)| *weight > 0.0).collect();
+
+    for (ids, weight) in &lists {
+        for (rank, id) in ids.iter().enumerate() {
+            let contribution = weight / (k + rank as f32 + 1.0);
+            match scores.get_mut(id) {
+                Some(score) => *score += contribution,
+                None => {
+                    order.push(id.clone());
+                    scores.insert(id.clone(), contribution);
+                }
+            }
+        }
+    }
+
+    let best_possible: f32 = lists.iter().map(|(_, weight)| weight / (k + 1.0)).sum();
+    let mut fused: Vec<(String, f32)> = order.into_iter()
+        .map(|id| {
+            let score = scores[&id];
+            let scaled = if best_possible > 0.0 { score / best_possible } else { 0.0 };
+            (id, scaled)
+        })
+        .collect();
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused
+}
+
+const QUESTION_WORDS: &[&str] = &[
+    "what", "why", "how", "who", "where", "when", "which", "about", "similar", "like", "related",
+];
+
+const COMMON_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "csv", "txt", "md", "json", "xml",
+    "png", "jpg", "jpeg", "gif", "svg", "mp3", "mp4", "mov", "zip", "rs", "py", "js", "ts",
+];
+
+/// Decide how a query should be searched. Exact file names, extensions, paths and identifiers
+/// want keyword search, descriptive queries want semantic search and mixtures get both.
+pub fn classify_query(query: &str) -> QueryIntent {
+    let mut exact = 0;
+    let mut descriptive = 0;
+    let mut asks_question = false;
+    let mut in_quotes = false;
+
+    for token in query.split_whitespace() {
+        let quoted = in_quotes || token.starts_with('"');
+        if token.matches('"').count() % 2 == 1 {
+            in_quotes = !in_quotes;
+        }
+
+        if quoted || is_exact_token(token) {
+            exact += 1;
+        } else {
+            descriptive += 1;
+            let word = token.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+            asks_question |= QUESTION_WORDS.contains(&word.as_str());
+        }
+    }
+
+    let (route, reason) = if exact > 0 && descriptive <= 1 {
+        (SearchRoute::Keyword, "query names files, extensions or paths")
+    } else if exact > 0 {
+        (SearchRoute::Hybrid, "query mixes exact names with descriptive words")
+    } else if asks_question || descriptive >= 3 {
+        (SearchRoute::Semantic, "query describes a concept")
+    } else if descriptive > 0 {
+        (SearchRoute::Hybrid, "short query may be a name or a topic")
+    } else {
+        (SearchRoute::Keyword, "empty query")
+    };
+
+    QueryIntent { route, reason: reason.to_string() }
+}
+
+fn is_exact_token(token: &str) -> bool {
+    let token = token.trim_matches(|c: char| matches!(c, ',' | ';' | '(' | ')'));
+
+    // Query syntax: paths, operators and negation
+    if token.starts_with("path:") || token.contains('/') || token.contains('\\')
+        || matches!(token, "AND" | "OR" | "NOT") || (token.starts_with('-') && token.len() > 1)
+    {
+        return true;
+    }
+
+    let extension_like = |ext: &str| {
+        (1..=5).contains(&ext.len())
+            && ext.chars().all(|c| c.is_ascii_alphanumeric())
+            && ext.chars().any(|c| c.is_ascii_alphabetic())
+    };
+    if let Some(ext) = token.strip_prefix("*.").or_else(|| token.strip_prefix('.')) {
+        return extension_like(ext);
+    }
+    if let Some((stem, ext)) = token.rsplit_once('.') {
+        if !stem.is_empty() && extension_like(ext) {
+            return true;
+        }
+    }
+    if COMMON_EXTENSIONS.contains(&token.to_lowercase().as_str()) {
+        return true;
+    }
+
+    // Identifiers: snake_case, camelCase and codes such as INV-2024
+    let has_digit = token.chars().any(|c| c.is_ascii_digit());
+    let camel_case = token.chars().zip(token.chars().skip(1)).any(|(a, b)| a.is_lowercase() && b.is_uppercase());
+    token.contains('_') || camel_case || (has_digit && token.contains('-'))
+}
+
+fn build_rerank_prompt(query: &str, candidates: &[(String, String)]) -> String {
+    let listing = candidates.iter()
+        .enumerate()
+        .map(|(i, (name, description))| format!("[{}] {}: {}", i + 1, name, description.replace('\n', " ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"Rank these files by how relevant they are to the search query "{}".
+
+{}
+
+Return only a JSON array of the file numbers, most relevant first, e.g. [3, 1, 2]."#,
+        query, listing
+    )
+}
+
+/// Turn the model's answer into a permutation of `0..count`. Numbers are 1-based;
+/// unknown and repeated ones are ignored and unmentioned candidates keep their order at the end.
+fn parse_rerank_order(response: &str, count: usize) -> Result<Vec<usize>> {
+    let start = response.find('[').ok_or_else(|| anyhow!("Reranker response has no ranking"))?;
+    let end = response[start..].find(']').map(|i| start + i)
+        .ok_or_else(|| anyhow!("Reranker response has no ranking"))?;
+    let ranked: Vec<serde_json::Value> = serde_json::from_str(&response[start..=end])?;
+
+    let mut order = Vec::with_capacity(count);
+    for position in ranked.iter().filter_map(|v| v.as_u64()) {
+        let index = position as usize;
+        if (1..=count).contains(&index) && !order.contains(&(index - 1)) {
+            order.push(index - 1);
+        }
+    }
+    if order.is_empty() {
+        return Err(anyhow!("Reranker returned no usable ranking"));
+    }
+
+    for index in 0..count {
+        if !order.contains(&index) {
+            order.push(index);
+        }
+    }
+    Ok(order)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -584,4 +1023,103 @@ mod tests {
         assert!(serialized.contains("Title: Test Document"));
         assert!(serialized.contains("Author: Test Author"));
     }
+
+    #[test]
+    fn test_classify_query_routes() {
+        let route = |query: &str| classify_query(query).route;
+
+        assert_eq!(route("budget_2024.xlsx"), SearchRoute::Keyword);
+        assert_eq!(route("*.pdf"), SearchRoute::Keyword);
+        assert_eq!(route("path:/home/me/notes"), SearchRoute::Keyword);
+        assert_eq!(route("\"exact phrase here\""), SearchRoute::Keyword);
+        assert_eq!(route("parseConfig"), SearchRoute::Keyword);
+
+        assert_eq!(route("photos of the beach at sunset"), SearchRoute::Semantic);
+        assert_eq!(route("how to cook rice"), SearchRoute::Semantic);
+
+        assert_eq!(route("pdf about quarterly revenue"), SearchRoute::Hybrid);
+        assert_eq!(route("invoices"), SearchRoute::Hybrid);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let ids = |list: &[&str]| list.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let fused = reciprocal_rank_fusion(
+            &[(ids(&["a", "b", "c"]), 1.0), (ids(&["b", "d"]), 1.0)],
+            60.0,
+        );
+
+        let order: Vec<&str> = fused.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["b", "a", "d", "c"]);
+        assert!(fused.iter().all(|(_, score)| *score > 0.0 && *score <= 1.0));
+
+        // Weighting the second list up lets its exclusive hit overtake the first list's top result
+        let fused = reciprocal_rank_fusion(
+            &[(ids(&["a", "b"]), 0.2), (ids(&["c"]), 1.0)],
+            60.0,
+        );
+        assert_eq!(fused[0].0, "c");
+
+        let fused = reciprocal_rank_fusion(&[(ids(&["x"]), 1.0), (ids(&["x"]), 1.0)], 60.0);
+        assert!((fused[0].1 - 1.0).abs() < 1e-6);
+
+        let fused = reciprocal_rank_fusion(&[(ids(&["a"]), 1.0), (ids(&["b"]), 0.0)], 60.0);
+        assert_eq!(fused, vec![("a".to_string(), 1.0)]);
+    }
+
+    #[test]
+    fn test_hybrid_keyword_terms_and_relevance() {
+        let (terms, exclusions) = hybrid_keyword_terms("budget report -draft");
+        assert_eq!(terms, vec!["budget".to_string(), "report".to_string()]);
+        assert_eq!(exclusions, vec![QueryNode::Term("draft".to_string())]);
+
+        let (terms, exclusions) = hybrid_keyword_terms("\"unclosed budget -draft");
+        assert_eq!(terms, vec!["\"unclosed".to_string(), "budget".to_string()]);
+        assert!(exclusions.is_empty());
+
+        let file = |name: &str, content: &str| FileRecord {
+            id: name.to_string(),
+            path: format!("/docs/{}", name),
+            name: name.to_string(),
+            extension: None,
+            size: 0,
+            created_at: Utc::now(),
+            modified_at: Utc::now(),
+            last_accessed: None,
+            mime_type: None,
+            hash: None,
+            content: Some(content.to_string()),
+            tags: None,
+            metadata: None,
+            ai_analysis: None,
+            embedding: None,
+            indexed_at: None,
+            processing_status: "completed".to_string(),
+            error_message: None,
+        };
+        let terms = vec!["budget".to_string(), "report".to_string()];
+        assert_eq!(keyword_relevance(&file("notes.txt", "The Budget"), &terms), 1);
+        assert_eq!(keyword_relevance(&file("notes.txt", "budget report"), &terms), 2);
+        assert_eq!(keyword_relevance(&file("Budget.xlsx", "quarterly report"), &terms), 3);
+    }
+
+    #[test]
+    fn test_parse_rerank_order() {
+        let order = parse_rerank_order("Sure! Here is the ranking: [3, 1, 3, 9]", 4).unwrap();
+        assert_eq!(order, vec![2, 0, 1, 3]);
+
+        assert!(parse_rerank_order("I cannot rank these.", 4).is_err());
+        assert!(parse_rerank_order("[0, 12]", 4).is_err());
+    }
+
+    #[test]
+    fn test_rerank_prompt_lists_candidates() {
+        let prompt = build_rerank_prompt("tax return", &[
+            ("2023-taxes.pdf".to_string(), "Federal return\nfiled April".to_string()),
+            ("recipes.md".to_string(), String::new()),
+        ]);
+        assert!(prompt.contains("[1] 2023-taxes.pdf: Federal return filed April"));
+        assert!(prompt.contains("[2] recipes.md: "));
+        assert!(prompt.contains("\"tax return\""));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,181 @@
+use serde::{Serialize, Deserialize};
+
+/// Character range inside a snippet that matched a query term
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HighlightRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub text: String,
+    /// Offsets are in characters relative to `text`, sorted and non-overlapping
+    pub highlights: Vec<HighlightRange>,
+    /// The distinct query terms found in the snippet, as they appear in the text
+    pub matched_terms: Vec<String>,
+}
+
+const ELLIPSIS: &str = "…";
+
+/// Only the head of very large documents is scanned for matches
+const MAX_SCAN_CHARS: usize = 200_000;
+
+/// Build a snippet of at most `max_chars` characters centred on the region of `text`
+/// that contains the most distinct `terms`, with every term occurrence marked.
+pub fn build_snippet(text: &str, terms: &[String], max_chars: usize) -> Snippet {
+    let chars: Vec<char> = text.chars().take(MAX_SCAN_CHARS).collect();
+    let truncated = text.chars().nth(MAX_SCAN_CHARS).is_some();
+    // One lowercase char per source char keeps offsets aligned
+    let lowered: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+
+    let needles: Vec<Vec<char>> = terms.iter()
+        .map(|t| t.trim().chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect::<Vec<char>>())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let occurrences = find_occurrences(&lowered, &needles);
+    let (window_start, window_end) = best_window(chars.len(), &occurrences, max_chars);
+    let (window_start, window_end) = snap_to_words(&chars, window_start, window_end);
+
+    let prefix = if window_start > 0 { ELLIPSIS } else { "" };
+    let suffix = if window_end < chars.len() || truncated { ELLIPSIS } else { "" };
+    let offset = prefix.chars().count();
+
+    let body: String = chars[window_start..window_end].iter().collect();
+    let text = format!("{}{}{}", prefix, body.trim_end(), suffix);
+    let body_len = body.trim_end().chars().count();
+
+    let mut highlights: Vec<HighlightRange> = Vec::new();
+    let mut matched_terms: Vec<String> = Vec::new();
+    let mut covered_until = window_start;
+    for &(start, end, _) in &occurrences {
+        // Skip matches outside the window and ones overlapping an earlier, longer match
+        if start < covered_until || end > window_start + body_len {
+            continue;
+        }
+        covered_until = end;
+        highlights.push(HighlightRange {
+            start: start - window_start + offset,
+            end: end - window_start + offset,
+        });
+
+        let term: String = chars[start..end].iter().collect();
+        if !matched_terms.iter().any(|t| t.to_lowercase() == term.to_lowercase()) {
+            matched_terms.push(term);
+        }
+    }
+
+    Snippet { text, highlights, matched_terms }
+}
+
+/// All (start, end, needle index) matches, sorted by position, longest first on ties
+fn find_occurrences(haystack: &[char], needles: &[Vec<char>]) -> Vec<(usize, usize, usize)> {
+    let mut found = Vec::new();
+    for (index, needle) in needles.iter().enumerate() {
+        if needle.len() > haystack.len() {
+            continue;
+        }
+        for start in 0..=(haystack.len() - needle.len()) {
+            if haystack[start..start + needle.len()] == needle[..] {
+                found.push((start, start + needle.len(), index));
+            }
+        }
+    }
+    found.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    found
+}
+
+/// Window covering the most distinct terms (then the most matches), centred on them
+fn best_window(len: usize, occurrences: &[(usize, usize, usize)], max_chars: usize) -> (usize, usize) {
+    if len <= max_chars {
+        return (0, len);
+    }
+    if occurrences.is_empty() {
+        return (0, max_chars);
+    }
+
+    let mut best = (0usize, 0usize, 0usize, 0usize);
+    for (i, &(start, _, _)) in occurrences.iter().enumerate() {
+        let in_window: Vec<&(usize, usize, usize)> = occurrences[i..].iter()
+            .take_while(|(_, end, _)| *end <= start + max_chars)
+            .collect();
+        let mut distinct: Vec<usize> = in_window.iter().map(|(_, _, n)| *n).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+
+        let score = (distinct.len(), in_window.len());
+        if score > (best.0, best.1) {
+            let last_end = in_window.last().map(|(_, end, _)| *end).unwrap_or(start);
+            best = (score.0, score.1, start, last_end);
+        }
+    }
+
+    // Centre the matched span inside the window
+    let (_, _, span_start, span_end) = best;
+    let slack = max_chars.saturating_sub(span_end - span_start);
+    let start = span_start.saturating_sub(slack / 2).min(len - max_chars);
+    (start, start + max_chars)
+}
+
+/// Move window edges off the middle of words so the snippet starts and ends cleanly
+fn snap_to_words(chars: &[char], start: usize, end: usize) -> (usize, usize) {
+    let mut start = start;
+    if start > 0 {
+        let limit = (start + 15).min(end);
+        if let Some(space) = (start..limit).find(|&i| chars[i].is_whitespace()) {
+            start = space + 1;
+        }
+    }
+
+    let mut end = end;
+    if end < chars.len() {
+        let limit = end.saturating_sub(15).max(start);
+        if let Some(space) = (limit..end).rev().find(|&i| chars[i].is_whitespace()) {
+            end = space;
+        }
+    }
+
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_short_text_is_highlighted_in_place() {
+        let snippet = build_snippet("Quarterly Budget for 2024", &terms(&["budget"]), 200);
+        assert_eq!(snippet.text, "Quarterly Budget for 2024");
+        assert_eq!(snippet.highlights, vec![HighlightRange { start: 10, end: 16 }]);
+        assert_eq!(snippet.matched_terms, vec!["Budget".to_string()]);
+    }
+
+    #[test]
+    fn test_window_centres_on_densest_match() {
+        let filler = "lorem ipsum dolor sit amet ".repeat(20);
+        let text = format!("budget {}the annual budget report is here {}", filler, filler);
+        let snippet = build_snippet(&text, &terms(&["budget", "report"]), 60);
+
+        assert!(snippet.text.starts_with(ELLIPSIS));
+        assert!(snippet.text.ends_with(ELLIPSIS));
+        assert!(snippet.text.chars().count() <= 62);
+        assert_eq!(snippet.matched_terms.len(), 2);
+        for range in &snippet.highlights {
+            let marked: String = snippet.text.chars().skip(range.start).take(range.end - range.start).collect();
+            assert!(marked == "budget" || marked == "report");
+        }
+    }
+
+    #[test]
+    fn test_no_match_falls_back_to_leading_text() {
+        let snippet = build_snippet(&"word ".repeat(100), &terms(&["absent"]), 40);
+        assert!(snippet.highlights.is_empty());
+        assert!(snippet.text.starts_with("word"));
+        assert!(snippet.text.ends_with(ELLIPSIS));
+    }
+}
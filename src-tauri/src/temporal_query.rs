@@ -0,0 +1,288 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Serialize, Deserialize};
+
+use crate::database::DateField;
+
+/// A date phrase recognised in a search query, e.g. "from last March"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemporalPhrase {
+    /// The words that were interpreted, as typed
+    pub phrase: String,
+    /// First day included, `None` for ranges open towards the past
+    pub start: Option<NaiveDate>,
+    /// First day no longer included, `None` for ranges open towards the future
+    pub end: Option<NaiveDate>,
+    pub field: DateField,
+    /// The query with the date phrase removed
+    pub remaining_query: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Within,
+    Since,
+    Before,
+}
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+
+/// Find the first date phrase in `query`, relative to `today`
+pub fn interpret(query: &str, today: NaiveDate) -> Option<TemporalPhrase> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let normalized: Vec<String> = words.iter()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .collect();
+
+    for i in 0..normalized.len() {
+        // Leave dates inside quoted phrases alone
+        if words[..i].iter().map(|w| w.matches('"').count()).sum::<usize>() % 2 == 1 {
+            continue;
+        }
+
+        let mut cursor = i;
+        let mut field = DateField::Modified;
+        let mut bound = Bound::Within;
+        let mut has_connector = false;
+
+        if let Some(f) = field_word(&normalized[cursor]) {
+            field = f;
+            cursor += 1;
+        }
+        if let Some(b) = normalized.get(cursor).and_then(|w| connector(w)) {
+            bound = b;
+            has_connector = true;
+            cursor += 1;
+        }
+
+        let Some((consumed, start, end)) = match_range(&normalized[cursor.min(normalized.len())..], today, has_connector) else {
+            continue;
+        };
+
+        let last = cursor + consumed;
+        let (start, end) = match bound {
+            Bound::Within => (Some(start), Some(end)),
+            Bound::Since => (Some(start), None),
+            Bound::Before => (None, Some(start)),
+        };
+
+        let remaining_query = words[..i].iter()
+            .chain(words[last..].iter())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        return Some(TemporalPhrase {
+            phrase: words[i..last].join(" "),
+            start,
+            end,
+            field,
+            remaining_query,
+        });
+    }
+
+    None
+}
+
+fn field_word(word: &str) -> Option<DateField> {
+    match word {
+        "created" | "made" => Some(DateField::Created),
+        "modified" | "edited" | "changed" | "updated" => Some(DateField::Modified),
+        _ => None,
+    }
+}
+
+fn connector(word: &str) -> Option<Bound> {
+    match word {
+        "from" | "in" | "during" | "on" => Some(Bound::Within),
+        "since" | "after" => Some(Bound::Since),
+        "before" => Some(Bound::Before),
+        _ => None,
+    }
+}
+
+fn month_number(word: &str) -> Option<u32> {
+    MONTHS.iter()
+        .position(|m| word.len() >= 3 && m.starts_with(word))
+        .map(|i| i as u32 + 1)
+}
+
+fn year_number(word: &str) -> Option<i32> {
+    if word.len() == 4 && word.chars().all(|c| c.is_ascii_digit()) {
+        word.parse().ok().filter(|y| (1970..=2100).contains(y))
+    } else {
+        None
+    }
+}
+
+fn month_range(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let end = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    Some((start, end))
+}
+
+fn year_range(year: i32) -> Option<(NaiveDate, NaiveDate)> {
+    Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?))
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(day.weekday().num_days_from_monday() as i64)
+}
+
+fn shift_months(day: NaiveDate, months: i32) -> NaiveDate {
+    let total = day.year() * 12 + day.month0() as i32 + months;
+    let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+    let mut candidate = day.day();
+    loop {
+        if let Some(date) = NaiveDate::from_ymd_opt(year, month, candidate) {
+            return date;
+        }
+        candidate -= 1;
+    }
+}
+
+/// Match a range expression at the start of `words`, returning words consumed and [start, end)
+fn match_range(words: &[String], today: NaiveDate, has_connector: bool) -> Option<(usize, NaiveDate, NaiveDate)> {
+    let first = words.first()?.as_str();
+    let second = words.get(1).map(String::as_str);
+    let third = words.get(2).map(String::as_str);
+    let tomorrow = today + Duration::days(1);
+
+    match (first, second) {
+        ("today", _) => return Some((1, today, tomorrow)),
+        ("yesterday", _) => return Some((1, today - Duration::days(1), today)),
+        ("this", Some("week")) => return Some((2, week_start(today), tomorrow)),
+        ("this", Some("month")) => {
+            let (start, _) = month_range(today.year(), today.month())?;
+            return Some((2, start, tomorrow));
+        }
+        ("this", Some("year")) => {
+            let (start, _) = year_range(today.year())?;
+            return Some((2, start, tomorrow));
+        }
+        ("last", Some("week")) => {
+            let end = week_start(today);
+            return Some((2, end - Duration::days(7), end));
+        }
+        ("last", Some("month")) => {
+            let previous = shift_months(today, -1);
+            let (start, end) = month_range(previous.year(), previous.month())?;
+            return Some((2, start, end));
+        }
+        ("last", Some("year")) => {
+            let (start, end) = year_range(today.year() - 1)?;
+            return Some((2, start, end));
+        }
+        ("last" | "past", Some(count)) => {
+            // Bounded so date arithmetic cannot overflow
+            let count = count.parse::<i64>().ok().filter(|n| (1..=1000).contains(n));
+            if let (Some(n), Some(unit)) = (count, third) {
+                let start = match unit.trim_end_matches('s') {
+                    "day" => today - Duration::days(n - 1),
+                    "week" => today - Duration::days(n * 7 - 1),
+                    "month" => shift_months(today, -(n as i32)) + Duration::days(1),
+                    "year" => shift_months(today, -(n as i32) * 12) + Duration::days(1),
+                    _ => return None,
+                };
+                return Some((3, start, tomorrow));
+            }
+            if let Some(month) = second.and_then(month_number) {
+                // The most recent completed occurrence of that month
+                let year = if month < today.month() { today.year() } else { today.year() - 1 };
+                let (start, end) = month_range(year, month)?;
+                return Some((2, start, end));
+            }
+        }
+        ("this", Some(month)) => {
+            if let Some(month) = month_number(month) {
+                let (start, end) = month_range(today.year(), month)?;
+                return Some((2, start, end));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(month) = month_number(first) {
+        // Bare month names are common words ("may"), so require a connector or a year
+        if let Some(year) = second.and_then(year_number) {
+            let (start, end) = month_range(year, month)?;
+            return Some((2, start, end));
+        }
+        if has_connector {
+            let year = if month <= today.month() { today.year() } else { today.year() - 1 };
+            let (start, end) = month_range(year, month)?;
+            return Some((1, start, end));
+        }
+    }
+
+    if has_connector {
+        if let Some(year) = year_number(first) {
+            let (start, end) = year_range(year)?;
+            return Some((1, start, end));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_relative_phrases() {
+        // A Wednesday
+        let today = day(2024, 5, 15);
+
+        let parsed = interpret("invoices from last March", today).unwrap();
+        assert_eq!(parsed.phrase, "from last March");
+        assert_eq!(parsed.remaining_query, "invoices");
+        assert_eq!((parsed.start, parsed.end), (Some(day(2024, 3, 1)), Some(day(2024, 4, 1))));
+
+        let parsed = interpret("screenshots this week", today).unwrap();
+        assert_eq!((parsed.start, parsed.end), (Some(day(2024, 5, 13)), Some(day(2024, 5, 16))));
+
+        let parsed = interpret("notes last month", today).unwrap();
+        assert_eq!((parsed.start, parsed.end), (Some(day(2024, 4, 1)), Some(day(2024, 5, 1))));
+
+        let parsed = interpret("logs past 7 days", today).unwrap();
+        assert_eq!((parsed.start, parsed.end), (Some(day(2024, 5, 9)), Some(day(2024, 5, 16))));
+
+        let parsed = interpret("last june", today).unwrap();
+        assert_eq!(parsed.start, Some(day(2023, 6, 1)));
+    }
+
+    #[test]
+    fn test_absolute_and_open_ranges() {
+        let today = day(2024, 5, 15);
+
+        let parsed = interpret("tax documents created in 2022", today).unwrap();
+        assert_eq!(parsed.field, DateField::Created);
+        assert_eq!(parsed.remaining_query, "tax documents");
+        assert_eq!((parsed.start, parsed.end), (Some(day(2022, 1, 1)), Some(day(2023, 1, 1))));
+
+        let parsed = interpret("photos since January 2023", today).unwrap();
+        assert_eq!((parsed.start, parsed.end), (Some(day(2023, 1, 1)), None));
+
+        let parsed = interpret("drafts before 2020", today).unwrap();
+        assert_eq!((parsed.start, parsed.end), (None, Some(day(2020, 1, 1))));
+    }
+
+    #[test]
+    fn test_plain_queries_are_left_alone() {
+        let today = day(2024, 5, 15);
+        assert!(interpret("may the force", today).is_none());
+        assert!(interpret("report 2023 budget", today).is_none());
+        assert!(interpret("", today).is_none());
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::ai_processor::AIProcessor;
+use crate::vector_math::VectorMath;
+use crate::vector_storage::{VectorStorageManager, VectorType};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicClusterConfig {
+    pub max_clusters: usize,
+    /// Smaller groups are treated as noise and left out
+    pub min_cluster_size: usize,
+    /// Files closest to the cluster centre, shown with the cluster and used to label it
+    pub representatives: usize,
+    pub max_iterations: usize,
+}
+
+impl Default for TopicClusterConfig {
+    fn default() -> Self {
+        Self {
+            max_clusters: 12,
+            min_cluster_size: 3,
+            representatives: 5,
+            max_iterations: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterFile {
+    pub id: String,
+    pub name: String,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicCluster {
+    pub id: usize,
+    pub label: String,
+    pub file_count: usize,
+    pub file_ids: Vec<String>,
+    pub representative_files: Vec<ClusterFile>,
+}
+
+/// Length of the description of each representative file given to the labelling prompt
+const LABEL_DESCRIPTION_CHARS: usize = 300;
+
+/// Group indexed files by the topic of their content embeddings and name each group
+pub async fn cluster_topics(
+    vector_storage: &VectorStorageManager,
+    ai_processor: &AIProcessor,
+    config: &TopicClusterConfig,
+) -> Result<Vec<TopicCluster>> {
+    let vectors = vector_storage.get_vectors_by_type(VectorType::Content).await?;
+
+    // Files embedded by an earlier model cannot be compared with the rest
+    let mut dimension_counts: HashMap<usize, usize> = HashMap::new();
+    for (_, vector) in &vectors {
+        *dimension_counts.entry(vector.len()).or_default() += 1;
+    }
+    let Some(dimensions) = dimension_counts.into_iter().max_by_key(|(_, count)| *count).map(|(d, _)| d) else {
+        return Ok(Vec::new());
+    };
+    let (file_ids, vectors): (Vec<String>, Vec<Vec<f32>>) = vectors.into_iter()
+        .filter(|(_, vector)| vector.len() == dimensions)
+        .unzip();
+
+    let k = cluster_count(vectors.len(), config);
+    if k == 0 {
+        return Ok(Vec::new());
+    }
+    let iterations = config.max_iterations;
+    let (assignments, vectors) = tokio::task::spawn_blocking(move || {
+        VectorMath::kmeans(&vectors, k, iterations).map(|assignments| (assignments, vectors))
+    }).await??;
+
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); k];
+    for (i, &group) in assignments.iter().enumerate() {
+        groups[group].push(i);
+    }
+    groups.retain(|members| members.len() >= config.min_cluster_size);
+    groups.sort_by_key(|members| std::cmp::Reverse(members.len()));
+
+    let ai_available = ai_processor.is_available().await;
+    let mut clusters = Vec::with_capacity(groups.len());
+    for (id, members) in groups.into_iter().enumerate() {
+        let member_vectors: Vec<Vec<f32>> = members.iter().map(|&i| vectors[i].clone()).collect();
+        let centroid = VectorMath::average_vectors(&member_vectors)?;
+
+        let mut ranked: Vec<(usize, f32)> = members.iter()
+            .map(|&i| (i, VectorMath::cosine_similarity(&vectors[i], &centroid).unwrap_or(0.0)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.truncate(config.representatives);
+
+        let representative_ids: Vec<String> = ranked.iter().map(|(i, _)| file_ids[*i].clone()).collect();
+        let descriptions = vector_storage.get_file_descriptions(&representative_ids, LABEL_DESCRIPTION_CHARS).await?;
+        let representative_files: Vec<ClusterFile> = ranked.iter()
+            .filter_map(|(i, similarity)| {
+                let (name, _) = descriptions.get(&file_ids[*i])?;
+                Some(ClusterFile { id: file_ids[*i].clone(), name: name.clone(), similarity: *similarity })
+            })
+            .collect();
+
+        let mut label = None;
+        if ai_available {
+            let samples: Vec<(String, String)> = representative_ids.iter()
+                .filter_map(|file_id| descriptions.get(file_id).cloned())
+                .collect();
+            match ai_processor.generate(&build_label_prompt(&samples)).await {
+                Ok(response) => label = clean_label(&response),
+                Err(e) => tracing::warn!("Failed to label topic cluster: {}", e),
+            }
+        }
+
+        clusters.push(TopicCluster {
+            id,
+            label: label.unwrap_or_else(|| format!("Topic {}", id + 1)),
+            file_count: members.len(),
+            file_ids: members.iter().map(|&i| file_ids[i].clone()).collect(),
+            representative_files,
+        });
+    }
+
+    Ok(clusters)
+}
+
+/// Roughly sqrt(n / 2) clusters, the usual rule of thumb, within the configured maximum
+fn cluster_count(file_count: usize, config: &TopicClusterConfig) -> usize {
+    if file_count < config.min_cluster_size.max(1) * 2 {
+        return 0;
+    }
+    let k = ((file_count as f64 / 2.0).sqrt().round() as usize).max(2);
+    k.min(config.max_clusters).min(file_count / config.min_cluster_size.max(1))
+}
+
+fn build_label_prompt(samples: &[(String, String)]) -> String {
+    let files = samples.iter()
+        .map(|(name, description)| format!("- {}: {}", name, description.replace('\n', " ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"The following files belong to the same group in a personal file collection.
+Name the topic they share in two to four words, e.g. "Tax Returns" or "Machine Learning Papers".
+Respond with the topic name only.
+
+{}
+
+Topic:"#,
+        files
+    )
+}
+
+/// First non-empty line of the model's reply without quotes, list markers or a "Topic:" prefix
+fn clean_label(response: &str) -> Option<String> {
+    let line = response.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.strip_prefix("Topic:").unwrap_or(line);
+    let label = line
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '*' | '-' | '.' | '`'))
+        .to_string();
+
+    if label.is_empty() || label.chars().count() > 60 {
+        None
+    } else {
+        Some(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_count() {
+        let config = TopicClusterConfig::default();
+        assert_eq!(cluster_count(4, &config), 0);
+        assert_eq!(cluster_count(6, &config), 2);
+        assert_eq!(cluster_count(200, &config), 10);
+        assert_eq!(cluster_count(10_000, &config), 12);
+    }
+
+    #[test]
+    fn test_clean_label() {
+        assert_eq!(clean_label("\n\"Tax Returns\"\n").as_deref(), Some("Tax Returns"));
+        assert_eq!(clean_label("Topic: **Travel Plans**.").as_deref(), Some("Travel Plans"));
+        assert_eq!(clean_label("   "), None);
+        assert_eq!(clean_label(&"word ".repeat(30)), None);
+    }
+
+    #[test]
+    fn test_label_prompt_lists_files() {
+        let prompt = build_label_prompt(&[
+            ("1040.pdf".to_string(), "Federal income tax\nreturn".to_string()),
+            ("w2.pdf".to_string(), "Wage statement".to_string()),
+        ]);
+        assert!(prompt.contains("- 1040.pdf: Federal income tax return"));
+        assert!(prompt.trim_end().ends_with("Topic:"));
+    }
+}
@@ -267,6 +267,7 @@ impl VectorBenchmarks {
                 filters: None,
                 limit: Some(50),
                 threshold: Some(0.7),
+                fusion: None,
             };
 
             // Warmup
@@ -362,6 +363,7 @@ impl VectorBenchmarks {
                 filters: None,
                 limit: Some(50),
                 threshold: Some(0.7),
+                fusion: None,
             };
 
             let start = Instant::now();
@@ -444,6 +446,7 @@ impl VectorBenchmarks {
                     filters: None,
                     limit: Some(10),
                     threshold: Some(0.7),
+                    fusion: None,
                 };
 
                 let semantic_search = semantic_search_clone.clone();
@@ -97,6 +97,10 @@ impl VectorMath {
         let mut top_k = BinaryHeap::with_capacity(k + 1);
 
         for (id, candidate_vector) in candidates {
+            // Vectors from another embedding model are not comparable until re-vectorized
+            if candidate_vector.len() != query.len() {
+                continue;
+            }
             let similarity = Self::cosine_similarity(query, candidate_vector)?;
             
             // Only consider vectors above threshold
@@ -143,6 +147,135 @@ impl VectorMath {
         Ok(results)
     }
 
+    /// Group vectors whose pairwise cosine similarity reaches `threshold`, transitively.
+    /// Returns index groups of two or more members with the weakest link that joined them.
+    pub fn cluster_by_similarity(vectors: &[Vec<f32>], threshold: f32) -> Result<Vec<(Vec<usize>, f32)>> {
+        // Zero vectors cannot be normalized; an empty vector never clears the threshold
+        let normalized: Vec<Vec<f32>> = vectors.iter()
+            .map(|v| Self::normalize_copy(v).unwrap_or_default())
+            .collect();
+
+        let mut parent: Vec<usize> = (0..vectors.len()).collect();
+        let mut weakest = vec![1.0f32; vectors.len()];
+
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        for i in 0..normalized.len() {
+            for j in (i + 1)..normalized.len() {
+                if normalized[i].len() != normalized[j].len() {
+                    continue;
+                }
+                let similarity = Self::dot_product(&normalized[i], &normalized[j]);
+                if similarity < threshold {
+                    continue;
+                }
+
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                if a != b {
+                    weakest[a] = similarity.min(weakest[a]).min(weakest[b]);
+                    parent[b] = a;
+                }
+            }
+        }
+
+        let mut groups: std::collections::HashMap<usize, Vec<usize>> = std::collections::HashMap::new();
+        for i in 0..vectors.len() {
+            let r = root(&mut parent, i);
+            groups.entry(r).or_default().push(i);
+        }
+
+        let mut clusters: Vec<(Vec<usize>, f32)> = groups.into_iter()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(r, members)| (members, weakest[r]))
+            .collect();
+        clusters.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0[0].cmp(&b.0[0])));
+
+        Ok(clusters)
+    }
+
+    /// Spherical k-means: partition vectors into at most `k` groups by cosine similarity.
+    /// Seeds are picked farthest-first from the first vector, so results are deterministic.
+    /// Returns the group index of every vector; some groups may end up empty.
+    pub fn kmeans(vectors: &[Vec<f32>], k: usize, max_iterations: usize) -> Result<Vec<usize>> {
+        if vectors.is_empty() {
+            return Ok(Vec::new());
+        }
+        let dimensions = vectors[0].len();
+        if vectors.iter().any(|v| v.len() != dimensions) {
+            return Err(anyhow!("All vectors must have {} dimensions to be clustered", dimensions));
+        }
+
+        let normalized: Vec<Vec<f32>> = vectors.iter()
+            .map(|v| Self::normalize_copy(v).unwrap_or_else(|_| vec![0.0; dimensions]))
+            .collect();
+        let k = k.clamp(1, vectors.len());
+
+        fn nearest(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+            let mut best = (0, f32::NEG_INFINITY);
+            for (i, centroid) in centroids.iter().enumerate() {
+                let similarity = VectorMath::dot_product(vector, centroid);
+                if similarity > best.1 {
+                    best = (i, similarity);
+                }
+            }
+            best.0
+        }
+
+        let mut centroids = vec![normalized[0].clone()];
+        let mut closest: Vec<f32> = normalized.iter()
+            .map(|v| Self::dot_product(v, &centroids[0]))
+            .collect();
+        while centroids.len() < k {
+            let mut farthest = 0;
+            for i in 1..closest.len() {
+                if closest[i] < closest[farthest] {
+                    farthest = i;
+                }
+            }
+            let seed = normalized[farthest].clone();
+            for (similarity, v) in closest.iter_mut().zip(&normalized) {
+                *similarity = similarity.max(Self::dot_product(v, &seed));
+            }
+            centroids.push(seed);
+        }
+
+        let mut assignments = vec![usize::MAX; vectors.len()];
+        for _ in 0..max_iterations.max(1) {
+            let mut changed = false;
+            for (assignment, v) in assignments.iter_mut().zip(&normalized) {
+                let best = nearest(v, &centroids);
+                if *assignment != best {
+                    *assignment = best;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+
+            // An empty group keeps its previous centroid
+            let mut sums = vec![vec![0.0f32; dimensions]; k];
+            for (v, &group) in normalized.iter().zip(&assignments) {
+                for (sum, component) in sums[group].iter_mut().zip(v) {
+                    *sum += component;
+                }
+            }
+            for (centroid, sum) in centroids.iter_mut().zip(sums) {
+                if let Ok(mean) = Self::normalize_copy(&sum) {
+                    *centroid = mean;
+                }
+            }
+        }
+
+        Ok(assignments)
+    }
+
     /// Calculate average vector from a collection of vectors
     /// Useful for folder-level aggregation
     pub fn average_vectors(vectors: &[Vec<f32>]) -> Result<Vec<f32>> {
@@ -310,6 +443,45 @@ mod tests {
         assert!((similarity - 0.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_cluster_by_similarity() {
+        let vectors = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.99, 0.01, 0.0],
+            vec![0.0, 0.0, 1.0],
+            vec![0.0, 0.02, 1.0],
+        ];
+        let clusters = VectorMath::cluster_by_similarity(&vectors, 0.97).unwrap();
+
+        assert_eq!(clusters.len(), 2);
+        let members: Vec<Vec<usize>> = clusters.iter().map(|(m, _)| m.clone()).collect();
+        assert!(members.contains(&vec![0, 2]));
+        assert!(members.contains(&vec![3, 4]));
+        assert!(clusters.iter().all(|(_, link)| *link >= 0.97 && *link <= 1.0));
+    }
+
+    #[test]
+    fn test_kmeans() {
+        let vectors = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.95, 0.05, 0.0],
+            vec![0.05, 0.95, 0.0],
+            vec![0.0, 0.1, 1.0],
+        ];
+        let assignments = VectorMath::kmeans(&vectors, 3, 20).unwrap();
+
+        assert_eq!(assignments[0], assignments[2]);
+        assert_eq!(assignments[1], assignments[3]);
+        assert_ne!(assignments[0], assignments[1]);
+        assert_ne!(assignments[4], assignments[0]);
+        assert_ne!(assignments[4], assignments[1]);
+
+        assert!(VectorMath::kmeans(&[vec![1.0, 0.0], vec![1.0]], 2, 10).is_err());
+        assert_eq!(VectorMath::kmeans(&vectors, 10, 10).unwrap().len(), 5);
+    }
+
     #[test]
     fn test_euclidean_distance() {
         let a = vec![0.0, 0.0];
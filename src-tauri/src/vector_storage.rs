@@ -1,10 +1,17 @@
 use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use futures_util::TryStreamExt;
 use sqlx::{SqlitePool, Row};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use sha2::{Sha256, Digest};
 
+use crate::chunking::TextChunk;
+use crate::database::integrity::IntegrityIssue;
+use crate::database::locking::ImmediateTransaction;
+use crate::vector_math::VectorMath;
+
 /// Manager for vector storage and retrieval operations
 #[derive(Debug, Clone)]
 pub struct VectorStorageManager {
@@ -58,6 +65,25 @@ pub struct FileVectors {
     pub created_at: DateTime<Utc>,
 }
 
+/// Files whose content vectors are nearly identical
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearDuplicateGroup {
+    pub file_ids: Vec<String>,
+    /// Weakest pairwise similarity that linked the group
+    pub similarity: f32,
+}
+
+/// The best matching chunk of a file for a query vector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkMatch {
+    pub file_id: String,
+    pub chunk_index: usize,
+    /// Byte range of the chunk in the file's extracted content
+    pub start: usize,
+    pub end: usize,
+    pub score: f32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FolderVector {
     pub folder_path: String,
@@ -99,10 +125,134 @@ impl VectorStorageManager {
             }
         }
 
+        self.create_chunk_table().await?;
+
+        // Purging a file drops its vectors with it
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_vectors_delete AFTER DELETE ON files BEGIN
+                DELETE FROM file_vectors WHERE file_id = old.id;
+            END
+            "#
+        ).execute(&self.db).await?;
+
+        // A file deleted from disk loses its vectors right away, though its record waits for purge
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_vectors_soft_delete AFTER UPDATE OF deleted_at ON files
+            WHEN old.deleted_at IS NULL AND new.deleted_at IS NOT NULL
+            BEGIN
+                DELETE FROM file_vectors WHERE file_id = old.id;
+                DELETE FROM file_chunk_vectors WHERE file_id = old.id;
+            END
+            "#
+        ).execute(&self.db).await?;
+
         tracing::info!("Vector storage schema initialized");
         Ok(())
     }
 
+    async fn create_chunk_table(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS file_chunk_vectors (
+                file_id TEXT NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                start_offset INTEGER NOT NULL,
+                end_offset INTEGER NOT NULL,
+                embedding BLOB NOT NULL,
+                dimensions INTEGER NOT NULL,
+                model_name TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (file_id, chunk_index)
+            )
+            "#
+        ).execute(&self.db).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS files_chunk_vectors_delete AFTER DELETE ON files BEGIN
+                DELETE FROM file_chunk_vectors WHERE file_id = old.id;
+            END
+            "#
+        ).execute(&self.db).await?;
+
+        Ok(())
+    }
+
+    /// Replace the chunk vectors of a file
+    pub async fn store_chunk_vectors(&self, file_id: &str, chunks: &[(TextChunk, Vec<f32>)], model_name: &str) -> Result<()> {
+        let mut tx = ImmediateTransaction::begin(&self.db).await?;
+
+        sqlx::query("DELETE FROM file_chunk_vectors WHERE file_id = ?")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let created_at = Utc::now().to_rfc3339();
+        for (chunk, vector) in chunks {
+            sqlx::query(
+                "INSERT INTO file_chunk_vectors
+                 (file_id, chunk_index, start_offset, end_offset, embedding, dimensions, model_name, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(file_id)
+            .bind(chunk.index as i64)
+            .bind(chunk.start as i64)
+            .bind(chunk.end as i64)
+            .bind(self.serialize_vector(vector))
+            .bind(vector.len() as i64)
+            .bind(model_name)
+            .bind(&created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Score every stored chunk against `query` and keep the best chunk per file.
+    /// Rows are streamed so large chunk tables are never loaded at once.
+    pub async fn find_best_chunks(&self, query: &[f32], limit: usize, threshold: f32) -> Result<Vec<ChunkMatch>> {
+        let mut rows = sqlx::query(
+            "SELECT file_id, chunk_index, start_offset, end_offset, embedding
+             FROM file_chunk_vectors WHERE dimensions = ?"
+        )
+        .bind(query.len() as i64)
+        .fetch(&self.db);
+
+        let mut best: HashMap<String, ChunkMatch> = HashMap::new();
+        while let Some(row) = rows.try_next().await? {
+            let embedding_bytes: Vec<u8> = row.get("embedding");
+            let Ok(vector) = self.deserialize_vector(&embedding_bytes) else {
+                continue;
+            };
+            let Ok(score) = VectorMath::cosine_similarity(query, &vector) else {
+                continue;
+            };
+            if score < threshold {
+                continue;
+            }
+
+            let file_id: String = row.get("file_id");
+            if !best.get(&file_id).is_some_and(|current| current.score >= score) {
+                best.insert(file_id.clone(), ChunkMatch {
+                    file_id,
+                    chunk_index: row.get::<i64, _>("chunk_index") as usize,
+                    start: row.get::<i64, _>("start_offset") as usize,
+                    end: row.get::<i64, _>("end_offset") as usize,
+                    score,
+                });
+            }
+        }
+
+        let mut matches: Vec<ChunkMatch> = best.into_values().collect();
+        matches.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
     /// Store multiple vector types for a file
     pub async fn store_file_vectors(
         &self,
@@ -112,7 +262,7 @@ impl VectorStorageManager {
         summary_vector: Option<Vec<f32>>,
         model_name: &str,
     ) -> Result<()> {
-        let mut tx = self.db.begin().await?;
+        let mut tx = ImmediateTransaction::begin(&self.db).await?;
 
         // Update main files table
         sqlx::query(
@@ -191,6 +341,43 @@ impl VectorStorageManager {
         Ok(vectors)
     }
 
+    /// Name and a short description of each file, used to judge candidates by their text.
+    /// The AI summary is preferred, falling back to the start of the extracted content.
+    pub async fn get_file_descriptions(&self, file_ids: &[String], max_chars: usize) -> Result<HashMap<String, (String, String)>> {
+        let mut descriptions = HashMap::with_capacity(file_ids.len());
+
+        for file_id in file_ids {
+            let row = sqlx::query(
+                "SELECT name, COALESCE(NULLIF(ai_analysis, ''), substr(content, 1, ?), '') as description
+                 FROM files WHERE id = ?"
+            )
+            .bind(max_chars as i64)
+            .bind(file_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+            if let Some(row) = row {
+                let description: String = row.get("description");
+                let description: String = description.chars().take(max_chars).collect();
+                descriptions.insert(file_id.clone(), (row.get("name"), description));
+            }
+        }
+
+        Ok(descriptions)
+    }
+
+    /// Text of a chunk, read back from the file's current content.
+    /// None when the content changed since the chunk was embedded.
+    pub async fn get_chunk_text(&self, file_id: &str, start: usize, end: usize) -> Result<Option<String>> {
+        let content: Option<String> = sqlx::query_scalar("SELECT content FROM files WHERE id = ?")
+            .bind(file_id)
+            .fetch_optional(&self.db)
+            .await?
+            .flatten();
+
+        Ok(content.and_then(|content| content.get(start..end).map(str::to_string)))
+    }
+
     /// Retrieve vectors of specific type
     pub async fn get_vectors_by_type(&self, vector_type: VectorType) -> Result<Vec<(String, Vec<f32>)>> {
         let rows = sqlx::query(
@@ -215,6 +402,67 @@ impl VectorStorageManager {
         Ok(vectors)
     }
 
+    /// Stored vector of one type for a single file
+    pub async fn get_file_vector(&self, file_id: &str, vector_type: VectorType) -> Result<Option<Vec<f32>>> {
+        let row = sqlx::query(
+            "SELECT embedding FROM file_vectors 
+             WHERE file_id = ? AND vector_type = ? AND embedding IS NOT NULL"
+        )
+        .bind(file_id)
+        .bind(vector_type.as_str())
+        .fetch_optional(&self.db)
+        .await?;
+
+        match row {
+            Some(row) => {
+                let embedding_bytes: Vec<u8> = row.get("embedding");
+                Ok(Some(self.deserialize_vector(&embedding_bytes)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Nearest neighbours of a file by its content vector, falling back to its metadata vector
+    pub async fn find_similar_files(&self, file_id: &str, limit: usize, threshold: f32) -> Result<Vec<(String, f32)>> {
+        let mut source = None;
+        for vector_type in [VectorType::Content, VectorType::Metadata] {
+            if let Some(vector) = self.get_file_vector(file_id, vector_type.clone()).await? {
+                source = Some((vector_type, vector));
+                break;
+            }
+        }
+
+        let (vector_type, query_vector) = source
+            .ok_or_else(|| anyhow!("No vectors stored for file: {}", file_id))?;
+
+        let candidates: Vec<(String, Vec<f32>)> = self.get_vectors_by_type(vector_type).await?
+            .into_iter()
+            .filter(|(id, _)| id != file_id)
+            .collect();
+
+        VectorMath::find_similar_vectors(&query_vector, &candidates, limit, threshold)
+    }
+
+    /// Cluster files whose content vectors are at least `threshold` similar
+    pub async fn find_near_duplicates(&self, threshold: f32) -> Result<Vec<NearDuplicateGroup>> {
+        let (file_ids, vectors): (Vec<String>, Vec<Vec<f32>>) = self
+            .get_vectors_by_type(VectorType::Content).await?
+            .into_iter()
+            .unzip();
+
+        // Pairwise comparison is quadratic, keep it off the async workers
+        let clusters = tokio::task::spawn_blocking(move || {
+            VectorMath::cluster_by_similarity(&vectors, threshold)
+        }).await??;
+
+        Ok(clusters.into_iter()
+            .map(|(members, similarity)| NearDuplicateGroup {
+                file_ids: members.into_iter().map(|i| file_ids[i].clone()).collect(),
+                similarity,
+            })
+            .collect())
+    }
+
     /// Get comprehensive vectors for a specific file
     pub async fn get_file_vectors(&self, file_id: &str) -> Result<Option<FileVectors>> {
         let row = sqlx::query(
@@ -312,7 +560,7 @@ impl VectorStorageManager {
         let query_hash = self.hash_query(query);
 
         // Try to get from cache first
-        if let Some(cached_vector) = self.get_cached_query_vector(&query_hash).await? {
+        if let Some(cached_vector) = self.get_cached_query_vector(&query_hash, model_name).await? {
             // Update hit count
             self.update_query_cache_usage(&query_hash).await?;
             return Ok(cached_vector);
@@ -327,12 +575,13 @@ impl VectorStorageManager {
         Ok(vector)
     }
 
-    /// Get cached query vector
-    async fn get_cached_query_vector(&self, query_hash: &str) -> Result<Option<Vec<f32>>> {
+    /// Get cached query vector, ignoring vectors produced by a different embedding model
+    async fn get_cached_query_vector(&self, query_hash: &str, model_name: &str) -> Result<Option<Vec<f32>>> {
         let row = sqlx::query(
-            "SELECT query_vector FROM query_vector_cache WHERE query_hash = ?"
+            "SELECT query_vector FROM query_vector_cache WHERE query_hash = ? AND vector_model = ?"
         )
         .bind(query_hash)
+        .bind(model_name)
         .fetch_optional(&self.db)
         .await?;
 
@@ -388,6 +637,66 @@ impl VectorStorageManager {
         Ok(())
     }
 
+    /// Remove every content and chunk vector of the given files
+    pub async fn delete_file_vectors(&self, file_ids: &[String]) -> Result<()> {
+        let mut tx = ImmediateTransaction::begin(&self.db).await?;
+        for file_id in file_ids {
+            sqlx::query("DELETE FROM file_vectors WHERE file_id = ?")
+                .bind(file_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM file_chunk_vectors WHERE file_id = ?")
+                .bind(file_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Drop vectors of files that are deleted or gone or whose blob does not match its dimensions,
+    /// and recreate the cleanup triggers if they are missing
+    pub async fn check_consistency(&self) -> Result<Vec<IntegrityIssue>> {
+        let mut issues = Vec::new();
+
+        let triggers: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'trigger' AND tbl_name = 'files'"
+        )
+        .fetch_all(&self.db)
+        .await?;
+        let missing: Vec<&str> = ["files_vectors_delete", "files_chunk_vectors_delete", "files_vectors_soft_delete"].into_iter()
+            .filter(|name| !triggers.iter().any(|t| t == name))
+            .collect();
+        if !missing.is_empty() {
+            self.initialize().await?;
+            issues.push(IntegrityIssue::repaired("vector_triggers", format!("Reattached missing triggers: {}", missing.join(", "))));
+        }
+
+        for table in ["file_vectors", "file_chunk_vectors"] {
+            let orphaned = sqlx::query(&format!(
+                "DELETE FROM {} WHERE NOT EXISTS (SELECT 1 FROM files WHERE files.id = {}.file_id AND files.deleted_at IS NULL)",
+                table, table
+            ))
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+            if orphaned > 0 {
+                issues.push(IntegrityIssue::repaired(table, format!("Removed {} vectors of deleted files", orphaned)));
+            }
+
+            // Vectors are little-endian f32s, four bytes per dimension
+            let malformed = sqlx::query(&format!("DELETE FROM {} WHERE length(embedding) != dimensions * 4", table))
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+            if malformed > 0 {
+                issues.push(IntegrityIssue::repaired(table, format!("Removed {} malformed vectors", malformed)));
+            }
+        }
+
+        Ok(issues)
+    }
+
     /// Clean up old cache entries
     pub async fn cleanup_cache(&self, max_entries: usize, max_age_days: u32) -> Result<usize> {
         // Delete entries older than max_age_days
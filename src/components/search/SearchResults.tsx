@@ -1,6 +1,27 @@
+import type { ReactNode } from "react";
 import { motion } from "framer-motion";
 import { useSearchStore } from "../../stores/useSearchStore";
 import { formatBytes, formatDate, getFileIcon } from "../../utils/fileUtils";
+import type { HighlightRange } from "../../types";
+
+// Offsets count Unicode code points, so slice the code point array rather than the string
+function renderSnippet(snippet: string, ranges: HighlightRange[] = []) {
+  const chars = Array.from(snippet);
+  const parts: ReactNode[] = [];
+  let cursor = 0;
+  ranges.forEach((range, idx) => {
+    if (range.start < cursor || range.end > chars.length) return;
+    parts.push(chars.slice(cursor, range.start).join(""));
+    parts.push(
+      <mark key={idx} className="bg-yellow-200 dark:bg-yellow-700 text-inherit rounded-sm">
+        {chars.slice(range.start, range.end).join("")}
+      </mark>
+    );
+    cursor = range.end;
+  });
+  parts.push(chars.slice(cursor).join(""));
+  return parts;
+}
 
 export function SearchResults() {
   const {
@@ -190,7 +211,7 @@ export function SearchResults() {
                   {/* Snippet */}
                   {result.snippet && (
                     <p className="text-xs text-gray-600 dark:text-gray-400 mt-2 line-clamp-2">
-                      {result.snippet}
+                      {renderSnippet(result.snippet, result.snippet_highlights)}
                     </p>
                   )}
 
@@ -74,9 +74,18 @@ export interface SearchResult {
   file: FileRecord;
   score: number;
   snippet?: string;
+  /** Character offsets into `snippet` of matched query terms */
+  snippet_highlights?: HighlightRange[];
+  /** Byte range of the snippet within the file's extracted text, for chunk matches */
+  snippet_range?: { start: number; end: number };
   highlights: string[];
 }
 
+export interface HighlightRange {
+  start: number;
+  end: number;
+}
+
 export interface Collection {
   id: string;
   name: string;